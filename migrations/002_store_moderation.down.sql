@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS update_store_appeals_updated_at ON store_appeals;
+
+DROP TABLE IF EXISTS store_appeals;
+DROP TABLE IF EXISTS store_moderation_actions;
+
+DROP TYPE IF EXISTS appeal_status;
+
+ALTER TABLE users DROP COLUMN IF EXISTS is_platform_admin;
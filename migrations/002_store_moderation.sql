@@ -0,0 +1,42 @@
+-- Platform administrators
+ALTER TABLE users ADD COLUMN is_platform_admin BOOLEAN NOT NULL DEFAULT false;
+
+-- Store moderation history (suspensions, closures, reinstatements)
+CREATE TABLE store_moderation_actions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    actor_id UUID NOT NULL REFERENCES users(id),
+    previous_status store_status NOT NULL,
+    new_status store_status NOT NULL,
+    reason TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_store_moderation_actions_store_id ON store_moderation_actions(store_id);
+
+-- Owner appeals against moderation decisions
+CREATE TYPE appeal_status AS ENUM ('Pending', 'Approved', 'Rejected');
+
+CREATE TABLE store_appeals (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    submitted_by UUID NOT NULL REFERENCES users(id),
+    message TEXT NOT NULL,
+    status appeal_status NOT NULL DEFAULT 'Pending',
+    reviewed_by UUID REFERENCES users(id),
+    review_note TEXT,
+    reviewed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_store_appeals_store_id ON store_appeals(store_id);
+CREATE INDEX idx_store_appeals_status ON store_appeals(status);
+
+-- Only one open appeal per store at a time
+CREATE UNIQUE INDEX idx_unique_pending_appeal
+ON store_appeals(store_id)
+WHERE status = 'Pending';
+
+CREATE TRIGGER update_store_appeals_updated_at BEFORE UPDATE ON store_appeals
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
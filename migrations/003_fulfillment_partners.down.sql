@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS update_fulfillment_partners_updated_at ON fulfillment_partners;
+
+ALTER TABLE stores DROP COLUMN IF EXISTS fulfillment_partner_id;
+
+DROP TABLE IF EXISTS fulfillment_partners;
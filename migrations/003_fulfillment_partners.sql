@@ -0,0 +1,17 @@
+-- Fulfillment partners shared by stores for consolidated shipping
+CREATE TABLE fulfillment_partners (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR(255) NOT NULL UNIQUE,
+    rate_table JSONB NOT NULL DEFAULT '[]',
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE stores
+    ADD COLUMN fulfillment_partner_id UUID REFERENCES fulfillment_partners(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_stores_fulfillment_partner_id ON stores(fulfillment_partner_id);
+
+CREATE TRIGGER update_fulfillment_partners_updated_at BEFORE UPDATE ON fulfillment_partners
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
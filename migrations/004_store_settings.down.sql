@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS update_store_settings_updated_at ON store_settings;
+
+DROP TABLE IF EXISTS store_settings;
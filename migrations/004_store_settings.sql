@@ -0,0 +1,13 @@
+-- Per-store commercial settings (currency, tax, shipping policy)
+CREATE TABLE store_settings (
+    store_id UUID PRIMARY KEY REFERENCES stores(id) ON DELETE CASCADE,
+    currency_code CHAR(3) NOT NULL DEFAULT 'USD',
+    tax_rate DECIMAL(6, 4) NOT NULL DEFAULT 0 CHECK (tax_rate >= 0 AND tax_rate <= 1),
+    flat_shipping_fee DECIMAL(10, 2) NOT NULL DEFAULT 0 CHECK (flat_shipping_fee >= 0),
+    free_shipping_threshold DECIMAL(10, 2) CHECK (free_shipping_threshold >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TRIGGER update_store_settings_updated_at BEFORE UPDATE ON store_settings
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
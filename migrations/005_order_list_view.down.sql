@@ -0,0 +1,17 @@
+DROP TRIGGER IF EXISTS order_list_view_product_update ON products;
+DROP TRIGGER IF EXISTS order_list_view_store_update ON stores;
+DROP TRIGGER IF EXISTS order_list_view_user_update ON users;
+DROP TRIGGER IF EXISTS order_list_view_item_insert ON order_items;
+DROP TRIGGER IF EXISTS order_list_view_order_update ON orders;
+DROP TRIGGER IF EXISTS order_list_view_order_insert ON orders;
+
+DROP FUNCTION IF EXISTS order_list_view_on_product_update();
+DROP FUNCTION IF EXISTS order_list_view_on_store_update();
+DROP FUNCTION IF EXISTS order_list_view_on_user_update();
+DROP FUNCTION IF EXISTS order_list_view_on_item_insert();
+DROP FUNCTION IF EXISTS order_list_view_on_order_update();
+DROP FUNCTION IF EXISTS order_list_view_on_order_insert();
+
+DROP TABLE IF EXISTS order_list_view;
+
+ALTER TABLE products DROP COLUMN IF EXISTS thumbnail_url;
@@ -0,0 +1,137 @@
+-- Product thumbnails shown in listings
+ALTER TABLE products ADD COLUMN thumbnail_url TEXT;
+
+-- Denormalized read model backing buyer and store order listings
+CREATE TABLE order_list_view (
+    order_id UUID PRIMARY KEY REFERENCES orders(id) ON DELETE CASCADE,
+    order_group_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    store_id UUID NOT NULL,
+    order_number VARCHAR(50) NOT NULL,
+    status order_status NOT NULL,
+    total_amount DECIMAL(10, 2) NOT NULL,
+    buyer_name VARCHAR(255) NOT NULL,
+    store_name VARCHAR(255) NOT NULL,
+    item_count INTEGER NOT NULL DEFAULT 0,
+    first_item_product_id UUID,
+    first_item_name VARCHAR(255),
+    first_item_thumbnail_url TEXT,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_order_list_view_user ON order_list_view(user_id, created_at DESC);
+CREATE INDEX idx_order_list_view_store ON order_list_view(store_id, created_at DESC);
+
+CREATE OR REPLACE FUNCTION order_list_view_on_order_insert()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO order_list_view (
+        order_id, order_group_id, user_id, store_id, order_number, status,
+        total_amount, buyer_name, store_name, created_at, updated_at
+    )
+    SELECT NEW.id, NEW.order_group_id, NEW.user_id, NEW.store_id, NEW.order_number,
+           NEW.status, NEW.total_amount, u.full_name, s.name, NEW.created_at, NEW.updated_at
+    FROM users u, stores s
+    WHERE u.id = NEW.user_id AND s.id = NEW.store_id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE OR REPLACE FUNCTION order_list_view_on_order_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE order_list_view
+    SET status = NEW.status,
+        total_amount = NEW.total_amount,
+        updated_at = NEW.updated_at
+    WHERE order_id = NEW.id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE OR REPLACE FUNCTION order_list_view_on_item_insert()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE order_list_view v
+    SET item_count = v.item_count + NEW.quantity,
+        first_item_product_id = COALESCE(v.first_item_product_id, p.id),
+        first_item_name = COALESCE(v.first_item_name, p.name),
+        first_item_thumbnail_url = CASE
+            WHEN v.first_item_product_id IS NULL THEN p.thumbnail_url
+            ELSE v.first_item_thumbnail_url
+        END
+    FROM products p
+    WHERE v.order_id = NEW.order_id AND p.id = NEW.product_id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE OR REPLACE FUNCTION order_list_view_on_user_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE order_list_view SET buyer_name = NEW.full_name WHERE user_id = NEW.id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE OR REPLACE FUNCTION order_list_view_on_store_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE order_list_view SET store_name = NEW.name WHERE store_id = NEW.id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE OR REPLACE FUNCTION order_list_view_on_product_update()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE order_list_view
+    SET first_item_name = NEW.name,
+        first_item_thumbnail_url = NEW.thumbnail_url
+    WHERE first_item_product_id = NEW.id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER order_list_view_order_insert AFTER INSERT ON orders
+    FOR EACH ROW EXECUTE FUNCTION order_list_view_on_order_insert();
+
+CREATE TRIGGER order_list_view_order_update AFTER UPDATE ON orders
+    FOR EACH ROW EXECUTE FUNCTION order_list_view_on_order_update();
+
+CREATE TRIGGER order_list_view_item_insert AFTER INSERT ON order_items
+    FOR EACH ROW EXECUTE FUNCTION order_list_view_on_item_insert();
+
+CREATE TRIGGER order_list_view_user_update AFTER UPDATE OF full_name ON users
+    FOR EACH ROW EXECUTE FUNCTION order_list_view_on_user_update();
+
+CREATE TRIGGER order_list_view_store_update AFTER UPDATE OF name ON stores
+    FOR EACH ROW EXECUTE FUNCTION order_list_view_on_store_update();
+
+CREATE TRIGGER order_list_view_product_update AFTER UPDATE OF name, thumbnail_url ON products
+    FOR EACH ROW EXECUTE FUNCTION order_list_view_on_product_update();
+
+-- Backfill orders placed before the projection existed
+INSERT INTO order_list_view (
+    order_id, order_group_id, user_id, store_id, order_number, status,
+    total_amount, buyer_name, store_name, item_count,
+    first_item_product_id, first_item_name, first_item_thumbnail_url,
+    created_at, updated_at
+)
+SELECT o.id, o.order_group_id, o.user_id, o.store_id, o.order_number, o.status,
+       o.total_amount, u.full_name, s.name,
+       COALESCE((SELECT SUM(oi.quantity) FROM order_items oi WHERE oi.order_id = o.id), 0),
+       first_item.id, first_item.name, first_item.thumbnail_url,
+       o.created_at, o.updated_at
+FROM orders o
+JOIN users u ON u.id = o.user_id
+JOIN stores s ON s.id = o.store_id
+LEFT JOIN LATERAL (
+    SELECT p.id, p.name, p.thumbnail_url
+    FROM order_items oi
+    JOIN products p ON p.id = oi.product_id
+    WHERE oi.order_id = o.id
+    ORDER BY oi.created_at ASC, oi.id ASC
+    LIMIT 1
+) first_item ON true;
@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS store_cleanups;
+DROP TABLE IF EXISTS user_notifications;
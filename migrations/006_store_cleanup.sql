@@ -0,0 +1,21 @@
+-- In-app notifications delivered to users
+CREATE TABLE user_notifications (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    store_id UUID REFERENCES stores(id) ON DELETE SET NULL,
+    kind VARCHAR(50) NOT NULL,
+    message TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_user_notifications_user ON user_notifications(user_id, created_at DESC);
+
+-- Outcome of the cleanup pipeline run after a store is closed
+CREATE TABLE store_cleanups (
+    store_id UUID PRIMARY KEY REFERENCES stores(id) ON DELETE CASCADE,
+    products_deactivated INTEGER NOT NULL,
+    cart_items_removed INTEGER NOT NULL,
+    customers_notified INTEGER NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,8 @@
+DROP TRIGGER IF EXISTS update_store_credit_balances_updated_at ON store_credit_balances;
+
+ALTER TABLE orders DROP COLUMN IF EXISTS credit_applied;
+
+DROP TABLE IF EXISTS store_credit_entries;
+DROP TABLE IF EXISTS store_credit_balances;
+
+DROP TYPE IF EXISTS credit_entry_kind;
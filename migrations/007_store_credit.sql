@@ -0,0 +1,31 @@
+-- Per-store customer credit
+CREATE TYPE credit_entry_kind AS ENUM ('Goodwill', 'Refund', 'Redemption');
+
+CREATE TABLE store_credit_balances (
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    balance DECIMAL(10, 2) NOT NULL DEFAULT 0 CHECK (balance >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (store_id, user_id)
+);
+
+CREATE INDEX idx_store_credit_balances_user ON store_credit_balances(user_id);
+
+CREATE TABLE store_credit_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    kind credit_entry_kind NOT NULL,
+    amount DECIMAL(10, 2) NOT NULL CHECK (amount <> 0),
+    order_id UUID REFERENCES orders(id),
+    issued_by UUID REFERENCES users(id),
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_store_credit_entries_account ON store_credit_entries(store_id, user_id, created_at DESC);
+
+ALTER TABLE orders ADD COLUMN credit_applied DECIMAL(10, 2) NOT NULL DEFAULT 0;
+
+CREATE TRIGGER update_store_credit_balances_updated_at BEFORE UPDATE ON store_credit_balances
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,9 @@
+-- Products each user viewed most recently, trimmed to a fixed size on write
+CREATE TABLE recently_viewed_products (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    viewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, product_id)
+);
+
+CREATE INDEX idx_recently_viewed_user ON recently_viewed_products(user_id, viewed_at DESC);
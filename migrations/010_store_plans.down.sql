@@ -0,0 +1,2 @@
+ALTER TABLE stores DROP COLUMN IF EXISTS plan;
+DROP TYPE IF EXISTS store_plan;
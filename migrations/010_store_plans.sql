@@ -0,0 +1,4 @@
+-- Plan tiers controlling per-store quotas
+CREATE TYPE store_plan AS ENUM ('Free', 'Pro');
+
+ALTER TABLE stores ADD COLUMN plan store_plan NOT NULL DEFAULT 'Free';
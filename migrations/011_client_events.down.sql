@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS client_events;
+DROP TYPE IF EXISTS client_event_type;
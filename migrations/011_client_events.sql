@@ -0,0 +1,15 @@
+-- Storefront telemetry feeding funnel analytics
+CREATE TYPE client_event_type AS ENUM ('ProductView', 'AddToCart', 'BeginCheckout');
+
+CREATE TABLE client_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    event_type client_event_type NOT NULL,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    product_id UUID REFERENCES products(id) ON DELETE SET NULL,
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    session_id VARCHAR(64) NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_client_events_store_time ON client_events(store_id, occurred_at);
@@ -0,0 +1,16 @@
+-- Digital products and their downloadable files
+CREATE TYPE product_type AS ENUM ('Physical', 'Digital');
+
+ALTER TABLE products ADD COLUMN product_type product_type NOT NULL DEFAULT 'Physical';
+
+CREATE TABLE product_files (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    storage_key TEXT NOT NULL UNIQUE,
+    file_name VARCHAR(255) NOT NULL,
+    content_type VARCHAR(100) NOT NULL,
+    size_bytes BIGINT NOT NULL CHECK (size_bytes > 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_product_files_product ON product_files(product_id);
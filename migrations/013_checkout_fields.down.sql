@@ -0,0 +1,3 @@
+ALTER TABLE orders DROP COLUMN IF EXISTS custom_fields;
+DROP TABLE IF EXISTS store_checkout_fields;
+DROP TYPE IF EXISTS checkout_field_type;
@@ -0,0 +1,19 @@
+-- Store-defined extra fields collected at checkout
+CREATE TYPE checkout_field_type AS ENUM ('Text', 'Number', 'Boolean');
+
+CREATE TABLE store_checkout_fields (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    key VARCHAR(50) NOT NULL,
+    label VARCHAR(100) NOT NULL,
+    field_type checkout_field_type NOT NULL,
+    required BOOLEAN NOT NULL DEFAULT false,
+    max_length INTEGER CHECK (max_length > 0),
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, key)
+);
+
+CREATE INDEX idx_store_checkout_fields_store ON store_checkout_fields(store_id, position);
+
+ALTER TABLE orders ADD COLUMN custom_fields JSONB NOT NULL DEFAULT '{}'::jsonb;
@@ -0,0 +1,4 @@
+-- Soft delete: archived products leave listings but stay resolvable for order history
+ALTER TABLE products ADD COLUMN archived_at TIMESTAMPTZ;
+
+CREATE INDEX idx_products_store_live ON products(store_id, created_at DESC) WHERE archived_at IS NULL;
@@ -0,0 +1,9 @@
+-- Indexes backing search-as-you-type suggestions
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX idx_products_name_trgm ON products
+    USING gin (lower(name) gin_trgm_ops)
+    WHERE is_active = true AND archived_at IS NULL;
+
+CREATE INDEX idx_products_category_prefix ON products (lower(category) text_pattern_ops)
+    WHERE category IS NOT NULL AND is_active = true AND archived_at IS NULL;
@@ -0,0 +1,33 @@
+-- Tax charged per order, recorded at checkout for compliance reporting
+CREATE TABLE order_tax_lines (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    jurisdiction TEXT NOT NULL,
+    rate DECIMAL(6, 4) NOT NULL,
+    taxable_amount DECIMAL(10, 2) NOT NULL,
+    tax_amount DECIMAL(10, 2) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_order_tax_lines_order ON order_tax_lines(order_id);
+CREATE INDEX idx_order_tax_lines_store ON order_tax_lines(store_id, created_at);
+
+-- Orders placed before tax lines existed: derive the rate from the amounts.
+INSERT INTO order_tax_lines (order_id, store_id, jurisdiction, rate, taxable_amount, tax_amount, created_at)
+SELECT
+    o.id,
+    o.store_id,
+    COALESCE(
+        UPPER(NULLIF(TRIM(o.shipping_address->>'country'), ''))
+            || COALESCE('-' || UPPER(COALESCE(
+                NULLIF(TRIM(o.shipping_address->>'state'), ''),
+                NULLIF(TRIM(o.shipping_address->>'region'), '')
+            )), ''),
+        'UNKNOWN'
+    ),
+    CASE WHEN o.subtotal > 0 THEN ROUND(o.tax / o.subtotal, 4) ELSE 0 END,
+    o.subtotal,
+    o.tax,
+    o.created_at
+FROM orders o;
@@ -0,0 +1,2 @@
+-- Countries a store ships physical goods to; empty means anywhere
+ALTER TABLE store_settings ADD COLUMN ships_to TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,24 @@
+-- Ledger of every stock change
+CREATE TYPE inventory_movement_kind AS ENUM ('Sale', 'Restock', 'Adjustment', 'CancellationRestore');
+
+CREATE TABLE inventory_movements (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    kind inventory_movement_kind NOT NULL,
+    delta INTEGER NOT NULL CHECK (delta <> 0),
+    stock_after INTEGER NOT NULL,
+    actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    order_id UUID REFERENCES orders(id) ON DELETE SET NULL,
+    reason TEXT,
+    -- clock_timestamp keeps movements within one transaction in order
+    created_at TIMESTAMPTZ NOT NULL DEFAULT clock_timestamp()
+);
+
+CREATE INDEX idx_inventory_movements_product ON inventory_movements(product_id, created_at DESC);
+
+-- Opening balance so each product's deltas add up to its current stock.
+INSERT INTO inventory_movements (product_id, store_id, kind, delta, stock_after, reason)
+SELECT id, store_id, 'Adjustment', stock_quantity, stock_quantity, 'Opening balance'
+FROM products
+WHERE stock_quantity <> 0;
@@ -0,0 +1,3 @@
+-- Bumped whenever a user's credentials or store roles change; session
+-- tokens carry the version they were issued with.
+ALTER TABLE users ADD COLUMN token_version INTEGER NOT NULL DEFAULT 0;
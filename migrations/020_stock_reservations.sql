@@ -0,0 +1,13 @@
+-- Stock held for a buyer's cart line until it expires or is checked out
+CREATE TABLE stock_reservations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (user_id, product_id)
+);
+
+CREATE INDEX idx_stock_reservations_product ON stock_reservations(product_id, expires_at);
+CREATE INDEX idx_stock_reservations_expires_at ON stock_reservations(expires_at);
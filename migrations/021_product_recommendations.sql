@@ -0,0 +1,11 @@
+-- "Frequently bought together" pairs, rebuilt periodically from order history
+CREATE TABLE product_co_purchases (
+    product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    related_product_id UUID NOT NULL REFERENCES products(id) ON DELETE CASCADE,
+    order_count INTEGER NOT NULL CHECK (order_count > 0),
+    refreshed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (product_id, related_product_id)
+);
+
+CREATE INDEX idx_product_co_purchases_rank
+    ON product_co_purchases (product_id, order_count DESC);
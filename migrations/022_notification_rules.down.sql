@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS notification_deliveries;
+DROP TYPE IF EXISTS delivery_status;
+DROP TABLE IF EXISTS store_notification_rules;
+DROP TYPE IF EXISTS notification_channel;
@@ -0,0 +1,40 @@
+-- Per-store routing of store notifications to channels and recipients
+CREATE TYPE notification_channel AS ENUM ('InApp', 'Email', 'Webhook');
+
+CREATE TABLE store_notification_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    channel notification_channel NOT NULL,
+    recipient_user_id UUID REFERENCES users(id) ON DELETE CASCADE,
+    target TEXT,
+    created_by UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- In-app rules address a user, the others an email address or URL
+    CHECK ((channel = 'InApp') = (recipient_user_id IS NOT NULL)),
+    CHECK ((channel = 'InApp') = (target IS NULL))
+);
+
+CREATE INDEX idx_store_notification_rules_store ON store_notification_rules(store_id, kind);
+
+-- Outbox of email and webhook notifications awaiting delivery
+CREATE TYPE delivery_status AS ENUM ('Pending', 'Sent', 'Failed');
+
+CREATE TABLE notification_deliveries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    rule_id UUID REFERENCES store_notification_rules(id) ON DELETE SET NULL,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    kind VARCHAR(50) NOT NULL,
+    channel notification_channel NOT NULL,
+    target TEXT NOT NULL,
+    message TEXT NOT NULL,
+    status delivery_status NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_notification_deliveries_due ON notification_deliveries(next_attempt_at)
+    WHERE status = 'Pending';
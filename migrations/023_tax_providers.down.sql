@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS tax_transactions;
+DROP TYPE IF EXISTS tax_transaction_status;
+
+ALTER TABLE store_settings DROP COLUMN IF EXISTS tax_provider;
+DROP TYPE IF EXISTS tax_provider_kind;
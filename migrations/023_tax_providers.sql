@@ -0,0 +1,24 @@
+-- Per-store choice of where tax rates come from
+CREATE TYPE tax_provider_kind AS ENUM ('Builtin', 'External');
+
+ALTER TABLE store_settings
+    ADD COLUMN tax_provider tax_provider_kind NOT NULL DEFAULT 'Builtin';
+
+-- Sales of externally taxed stores, reported to the provider for filing
+CREATE TYPE tax_transaction_status AS ENUM ('Pending', 'Committed', 'Failed');
+
+CREATE TABLE tax_transactions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL UNIQUE REFERENCES orders(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    status tax_transaction_status NOT NULL DEFAULT 'Pending',
+    external_id TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    committed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_tax_transactions_due ON tax_transactions(next_attempt_at)
+    WHERE status = 'Pending';
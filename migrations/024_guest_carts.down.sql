@@ -0,0 +1,9 @@
+DELETE FROM cart_items WHERE guest_session_id IS NOT NULL;
+
+DROP INDEX IF EXISTS idx_cart_items_guest_updated;
+DROP INDEX IF EXISTS idx_cart_items_guest_product;
+
+ALTER TABLE cart_items
+    DROP CONSTRAINT IF EXISTS cart_items_single_owner,
+    DROP COLUMN IF EXISTS guest_session_id,
+    ALTER COLUMN user_id SET NOT NULL;
@@ -0,0 +1,11 @@
+-- Carts of shoppers who have not signed in, keyed by the session id inside
+-- their signed guest token. Each line belongs to a user or a guest session.
+ALTER TABLE cart_items
+    ALTER COLUMN user_id DROP NOT NULL,
+    ADD COLUMN guest_session_id UUID,
+    ADD CONSTRAINT cart_items_single_owner CHECK ((user_id IS NULL) <> (guest_session_id IS NULL));
+
+CREATE UNIQUE INDEX idx_cart_items_guest_product ON cart_items(guest_session_id, product_id)
+    WHERE guest_session_id IS NOT NULL;
+CREATE INDEX idx_cart_items_guest_updated ON cart_items(updated_at)
+    WHERE guest_session_id IS NOT NULL;
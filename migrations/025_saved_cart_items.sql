@@ -0,0 +1,4 @@
+-- Cart lines the shopper set aside: kept in the cart but left out of
+-- checkout, totals and stock reservations until restored.
+ALTER TABLE cart_items
+    ADD COLUMN saved_for_later BOOLEAN NOT NULL DEFAULT false;
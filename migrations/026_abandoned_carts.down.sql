@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_cart_items_user_updated;
+DROP TABLE IF EXISTS abandoned_carts;
@@ -0,0 +1,16 @@
+-- One row per user and store each time an idle cart is expired, kept for
+-- marketing follow-ups and analytics after the cart lines are gone.
+CREATE TABLE abandoned_carts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    item_count INTEGER NOT NULL,
+    cart_value NUMERIC(12, 2) NOT NULL,
+    last_activity_at TIMESTAMPTZ NOT NULL,
+    expired_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_abandoned_carts_store_time ON abandoned_carts(store_id, expired_at);
+CREATE INDEX idx_abandoned_carts_user ON abandoned_carts(user_id);
+CREATE INDEX idx_cart_items_user_updated ON cart_items(user_id, updated_at)
+    WHERE user_id IS NOT NULL;
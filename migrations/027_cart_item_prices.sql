@@ -0,0 +1,9 @@
+-- Price the shopper saw when adding each cart line, so the cart can point
+-- out changes before checkout charges the current price.
+ALTER TABLE cart_items ADD COLUMN added_price NUMERIC(12, 2);
+
+UPDATE cart_items c SET added_price = p.price
+FROM products p
+WHERE p.id = c.product_id;
+
+ALTER TABLE cart_items ALTER COLUMN added_price SET NOT NULL;
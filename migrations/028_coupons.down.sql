@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS coupon_redemptions;
+DROP TABLE IF EXISTS cart_coupons;
+DROP TABLE IF EXISTS coupons;
+DROP TYPE IF EXISTS coupon_kind;
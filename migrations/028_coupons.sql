@@ -0,0 +1,41 @@
+-- Per-store discount codes
+CREATE TYPE coupon_kind AS ENUM ('Percent', 'Fixed');
+
+CREATE TABLE coupons (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    -- Stored upper-case; shoppers may type codes in any case.
+    code VARCHAR(40) NOT NULL,
+    kind coupon_kind NOT NULL,
+    value NUMERIC(12, 2) NOT NULL CHECK (value > 0),
+    min_order_value NUMERIC(12, 2),
+    usage_limit INTEGER CHECK (usage_limit > 0),
+    times_used INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, code),
+    CHECK (kind <> 'Percent' OR value <= 100)
+);
+
+-- The code a signed-in shopper applied to their cart, at most one per store.
+CREATE TABLE cart_coupons (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    coupon_id UUID NOT NULL REFERENCES coupons(id) ON DELETE CASCADE,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, store_id)
+);
+
+CREATE INDEX idx_cart_coupons_coupon ON cart_coupons(coupon_id);
+
+CREATE TABLE coupon_redemptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    coupon_id UUID NOT NULL REFERENCES coupons(id),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    discount_amount NUMERIC(12, 2) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_coupon_redemptions_coupon ON coupon_redemptions(coupon_id, created_at DESC);
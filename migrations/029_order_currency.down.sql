@@ -0,0 +1,21 @@
+CREATE OR REPLACE FUNCTION order_list_view_on_order_insert()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO order_list_view (
+        order_id, order_group_id, user_id, store_id, order_number, status,
+        total_amount, buyer_name, store_name, created_at, updated_at
+    )
+    SELECT NEW.id, NEW.order_group_id, NEW.user_id, NEW.store_id, NEW.order_number,
+           NEW.status, NEW.total_amount, u.full_name, s.name, NEW.created_at, NEW.updated_at
+    FROM users u, stores s
+    WHERE u.id = NEW.user_id AND s.id = NEW.store_id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+ALTER TABLE order_list_view DROP COLUMN IF EXISTS currency;
+
+ALTER TABLE orders
+    DROP COLUMN IF EXISTS exchange_rate,
+    DROP COLUMN IF EXISTS presentment_currency,
+    DROP COLUMN IF EXISTS currency;
@@ -0,0 +1,34 @@
+-- The currency an order is charged in (the store's at checkout) and, when the
+-- shopper browsed in another currency, the rate their totals were shown at.
+ALTER TABLE orders
+    ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD',
+    ADD COLUMN presentment_currency CHAR(3),
+    ADD COLUMN exchange_rate NUMERIC(18, 8);
+
+UPDATE orders o
+SET currency = s.currency_code
+FROM store_settings s
+WHERE s.store_id = o.store_id;
+
+ALTER TABLE order_list_view ADD COLUMN currency CHAR(3) NOT NULL DEFAULT 'USD';
+
+UPDATE order_list_view v
+SET currency = o.currency
+FROM orders o
+WHERE o.id = v.order_id;
+
+CREATE OR REPLACE FUNCTION order_list_view_on_order_insert()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO order_list_view (
+        order_id, order_group_id, user_id, store_id, order_number, status,
+        total_amount, currency, buyer_name, store_name, created_at, updated_at
+    )
+    SELECT NEW.id, NEW.order_group_id, NEW.user_id, NEW.store_id, NEW.order_number,
+           NEW.status, NEW.total_amount, NEW.currency, u.full_name, s.name,
+           NEW.created_at, NEW.updated_at
+    FROM users u, stores s
+    WHERE u.id = NEW.user_id AND s.id = NEW.store_id;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
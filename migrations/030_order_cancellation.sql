@@ -0,0 +1,6 @@
+-- Who cancelled an order, when and why. Buyers may give a reason when they
+-- cancel their own order.
+ALTER TABLE orders
+    ADD COLUMN cancelled_at TIMESTAMPTZ,
+    ADD COLUMN cancelled_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN cancellation_reason TEXT;
@@ -0,0 +1,2 @@
+DROP TRIGGER IF EXISTS update_order_shipments_updated_at ON order_shipments;
+DROP TABLE IF EXISTS order_shipments;
@@ -0,0 +1,18 @@
+-- Parcels a store sent for an order, with the carrier's tracking details.
+CREATE TABLE order_shipments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    carrier VARCHAR(100) NOT NULL,
+    tracking_number VARCHAR(100) NOT NULL,
+    tracking_url TEXT,
+    shipped_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_order_shipments_order ON order_shipments(order_id, shipped_at);
+
+CREATE TRIGGER update_order_shipments_updated_at BEFORE UPDATE ON order_shipments
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,16 @@
+-- One invoice per order, numbered without gaps per store in the order they
+-- were issued.
+CREATE TABLE invoice_counters (
+    store_id UUID PRIMARY KEY REFERENCES stores(id) ON DELETE CASCADE,
+    last_number INTEGER NOT NULL
+);
+
+CREATE TABLE invoices (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL UNIQUE REFERENCES orders(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    sequence_number INTEGER NOT NULL,
+    invoice_number VARCHAR(32) NOT NULL,
+    issued_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, sequence_number)
+);
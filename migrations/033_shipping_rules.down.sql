@@ -0,0 +1,4 @@
+ALTER TABLE orders DROP COLUMN IF EXISTS shipping_method;
+DROP TABLE IF EXISTS store_shipping_rules;
+DROP TYPE IF EXISTS shipping_rule_kind;
+ALTER TABLE products DROP COLUMN IF EXISTS weight_grams;
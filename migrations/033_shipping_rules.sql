@@ -0,0 +1,23 @@
+-- Parcel weight used by weight-tiered shipping rules.
+ALTER TABLE products ADD COLUMN weight_grams INTEGER NOT NULL DEFAULT 0 CHECK (weight_grams >= 0);
+
+-- Shipping methods a store offers at checkout. Without any rules the store's
+-- flat fee and free-shipping threshold apply.
+CREATE TYPE shipping_rule_kind AS ENUM ('Flat', 'WeightTiers', 'PriceTiers', 'Carrier');
+
+CREATE TABLE store_shipping_rules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    kind shipping_rule_kind NOT NULL,
+    fee NUMERIC(12, 2) NOT NULL DEFAULT 0 CHECK (fee >= 0),
+    tiers JSONB NOT NULL DEFAULT '[]',
+    free_shipping_threshold NUMERIC(12, 2) CHECK (free_shipping_threshold >= 0),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (store_id, name)
+);
+
+CREATE INDEX idx_store_shipping_rules_store ON store_shipping_rules(store_id, created_at);
+
+-- The shipping option the buyer picked for each sub-order.
+ALTER TABLE orders ADD COLUMN shipping_method VARCHAR(200);
@@ -0,0 +1,5 @@
+DROP TRIGGER IF EXISTS update_payments_updated_at ON payments;
+DROP TABLE IF EXISTS payments;
+DROP TYPE IF EXISTS payment_attempt_status;
+ALTER TABLE store_settings DROP COLUMN IF EXISTS payment_provider;
+DROP TYPE IF EXISTS payment_provider_kind;
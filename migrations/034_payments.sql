@@ -0,0 +1,35 @@
+-- Payment backend each store takes payments through.
+CREATE TYPE payment_provider_kind AS ENUM ('Manual', 'PayPal');
+
+ALTER TABLE store_settings
+    ADD COLUMN payment_provider payment_provider_kind NOT NULL DEFAULT 'Manual';
+
+CREATE TYPE payment_attempt_status AS ENUM ('Pending', 'Authorized', 'Captured', 'Failed', 'Refunded');
+
+-- Attempts to collect a sub-order's total through its store's provider. A
+-- failed attempt stays on record when the buyer retries.
+CREATE TABLE payments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_group_id UUID NOT NULL REFERENCES order_groups(id) ON DELETE CASCADE,
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    provider payment_provider_kind NOT NULL,
+    -- The provider's id for the payment, once it created one.
+    reference VARCHAR(255),
+    status payment_attempt_status NOT NULL DEFAULT 'Pending',
+    amount NUMERIC(12, 2) NOT NULL CHECK (amount > 0),
+    currency CHAR(3) NOT NULL,
+    -- Where the buyer approves the payment, for redirect-based providers.
+    approval_url TEXT,
+    failure_reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_payments_group ON payments(order_group_id, created_at);
+CREATE INDEX idx_payments_order ON payments(order_id, created_at);
+CREATE UNIQUE INDEX idx_payments_provider_reference ON payments(provider, reference)
+    WHERE reference IS NOT NULL;
+
+CREATE TRIGGER update_payments_updated_at BEFORE UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
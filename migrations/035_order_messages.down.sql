@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS order_message_reads;
+DROP TABLE IF EXISTS order_messages;
+DROP TYPE IF EXISTS message_sender;
@@ -0,0 +1,23 @@
+CREATE TYPE message_sender AS ENUM ('Buyer', 'Store');
+
+-- Conversation between an order's buyer and the store's staff.
+CREATE TABLE order_messages (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    sender_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    -- Which side of the conversation wrote the message.
+    sender message_sender NOT NULL,
+    body TEXT NOT NULL CHECK (char_length(body) BETWEEN 1 AND 4000),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_order_messages_order ON order_messages(order_id, created_at);
+
+-- How far each participant has read an order's thread; messages from the
+-- other side after `last_read_at` are unread.
+CREATE TABLE order_message_reads (
+    order_id UUID NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    last_read_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (order_id, user_id)
+);
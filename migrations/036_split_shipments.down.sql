@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS order_shipment_items;
+ALTER TABLE order_shipments DROP COLUMN IF EXISTS delivered_at;
+ALTER TABLE order_shipments DROP COLUMN IF EXISTS status;
+DROP TYPE IF EXISTS shipment_status;
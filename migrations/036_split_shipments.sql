@@ -0,0 +1,29 @@
+-- Shipments name the order lines they carry and track their own delivery,
+-- so an order can go out in several parcels.
+CREATE TYPE shipment_status AS ENUM ('Shipped', 'Delivered');
+
+ALTER TABLE order_shipments
+    ADD COLUMN status shipment_status NOT NULL DEFAULT 'Shipped',
+    ADD COLUMN delivered_at TIMESTAMPTZ;
+
+CREATE TABLE order_shipment_items (
+    shipment_id UUID NOT NULL REFERENCES order_shipments(id) ON DELETE CASCADE,
+    order_item_id UUID NOT NULL REFERENCES order_items(id) ON DELETE CASCADE,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    PRIMARY KEY (shipment_id, order_item_id)
+);
+
+CREATE INDEX idx_order_shipment_items_item ON order_shipment_items(order_item_id);
+
+-- Orders shipped before they could be split went out whole in their first
+-- parcel.
+INSERT INTO order_shipment_items (shipment_id, order_item_id, quantity)
+SELECT DISTINCT ON (oi.id) s.id, oi.id, oi.quantity
+FROM order_items oi
+JOIN order_shipments s ON s.order_id = oi.order_id
+ORDER BY oi.id, s.shipped_at, s.id;
+
+UPDATE order_shipments s
+SET status = 'Delivered', delivered_at = o.updated_at
+FROM orders o
+WHERE o.id = s.order_id AND o.status = 'Delivered';
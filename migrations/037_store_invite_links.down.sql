@@ -0,0 +1,2 @@
+DROP TABLE IF EXISTS store_invite_redemptions;
+DROP TABLE IF EXISTS store_invite_links;
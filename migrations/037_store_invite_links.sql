@@ -0,0 +1,23 @@
+-- Shareable links that give whoever opens them access to a private store.
+CREATE TABLE store_invite_links (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    created_by UUID NOT NULL REFERENCES users(id),
+    access_level access_level NOT NULL DEFAULT 'ViewAndBuy',
+    max_uses INTEGER NOT NULL CHECK (max_uses > 0),
+    use_count INTEGER NOT NULL DEFAULT 0 CHECK (use_count <= max_uses),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_store_invite_links_store ON store_invite_links(store_id, created_at);
+
+-- Who redeemed each link, so opening it twice does not use it up.
+CREATE TABLE store_invite_redemptions (
+    link_id UUID NOT NULL REFERENCES store_invite_links(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    grant_id UUID NOT NULL REFERENCES store_access_grants(id) ON DELETE CASCADE,
+    redeemed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (link_id, user_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE store_members DROP COLUMN IF EXISTS custom_role_id;
+DROP TABLE IF EXISTS store_roles;
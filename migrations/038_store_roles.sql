@@ -0,0 +1,19 @@
+-- Named roles a store defines for its Custom members, each with its own
+-- permission set.
+CREATE TABLE store_roles (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    permissions JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_store_roles_store_name ON store_roles(store_id, lower(name));
+
+CREATE TRIGGER update_store_roles_updated_at BEFORE UPDATE ON store_roles
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Deleting a role leaves its members with only their own permissions.
+ALTER TABLE store_members
+    ADD COLUMN custom_role_id UUID REFERENCES store_roles(id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS permission_audit_log;
+DROP FUNCTION IF EXISTS reject_permission_audit_changes();
+DROP TYPE IF EXISTS permission_audit_action;
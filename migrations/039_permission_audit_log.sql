@@ -0,0 +1,33 @@
+CREATE TYPE permission_audit_action AS ENUM (
+    'MemberAdded', 'MemberUpdated', 'MemberRemoved',
+    'AccessGranted', 'AccessUpdated', 'AccessRevoked',
+    'RoleCreated', 'RoleUpdated', 'RoleDeleted'
+);
+
+-- Every change to who may do what in a store. Rows are never changed or
+-- removed once written.
+CREATE TABLE permission_audit_log (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    store_id UUID NOT NULL REFERENCES stores(id),
+    actor_id UUID REFERENCES users(id),
+    action permission_audit_action NOT NULL,
+    -- The member or grant holder affected; NULL for role changes.
+    target_user_id UUID REFERENCES users(id),
+    -- The membership, grant or role row that changed.
+    subject_id UUID NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_permission_audit_log_store ON permission_audit_log(store_id, created_at DESC);
+
+CREATE OR REPLACE FUNCTION reject_permission_audit_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'permission_audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER permission_audit_log_append_only BEFORE UPDATE OR DELETE ON permission_audit_log
+    FOR EACH ROW EXECUTE FUNCTION reject_permission_audit_changes();
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS analytics_rollup_state;
+DROP TABLE IF EXISTS store_daily_product_sales;
+DROP TABLE IF EXISTS store_daily_customers;
+DROP TABLE IF EXISTS store_daily_sales;
@@ -0,0 +1,39 @@
+-- Daily per-store sales totals, rebuilt by the analytics rollup job so
+-- reports don't aggregate the whole order history on every request. Each
+-- day is split by the payment status of the orders' groups.
+CREATE TABLE store_daily_sales (
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    payment_status payment_status NOT NULL,
+    order_count BIGINT NOT NULL,
+    revenue DECIMAL(14, 2) NOT NULL,
+    PRIMARY KEY (store_id, day, payment_status)
+);
+
+-- Customers who ordered on each day, for distinct-customer counts over a
+-- range of days.
+CREATE TABLE store_daily_customers (
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    payment_status payment_status NOT NULL,
+    user_id UUID NOT NULL,
+    PRIMARY KEY (store_id, day, payment_status, user_id)
+);
+
+CREATE TABLE store_daily_product_sales (
+    store_id UUID NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    payment_status payment_status NOT NULL,
+    product_id UUID NOT NULL,
+    units_sold BIGINT NOT NULL,
+    revenue DECIMAL(14, 2) NOT NULL,
+    PRIMARY KEY (store_id, day, payment_status, product_id)
+);
+
+-- The last day the rollups cover. Later orders are read live.
+CREATE TABLE analytics_rollup_state (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    rolled_up_through DATE
+);
+
+INSERT INTO analytics_rollup_state (id, rolled_up_through) VALUES (TRUE, NULL);
@@ -0,0 +1,20 @@
+-- Responses to POST/PATCH requests sent with an Idempotency-Key, replayed
+-- when the client retries the same request.
+CREATE TABLE idempotency_keys (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    idempotency_key VARCHAR(255) NOT NULL,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    -- SHA-256 of the method, path and body, so a key can't be reused for a
+    -- different request.
+    request_hash BYTEA NOT NULL,
+    -- Unset while the first request is still being handled.
+    status_code SMALLINT,
+    content_type TEXT,
+    response_body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_expires_at ON idempotency_keys(expires_at);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS audit_events;
+DROP FUNCTION IF EXISTS reject_audit_event_changes();
+DROP TYPE IF EXISTS audit_action;
@@ -0,0 +1,35 @@
+CREATE TYPE audit_action AS ENUM (
+    'Login', 'LoginFailed', 'PermissionChange', 'PriceChange', 'OrderStatusChange', 'Refund'
+);
+
+-- Sensitive actions across the platform, with who did them, from where and
+-- what changed. Rows are never changed or removed once written.
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- The store the action happened in; NULL for account actions.
+    store_id UUID REFERENCES stores(id),
+    -- NULL when the actor is unknown, e.g. a failed login.
+    actor_id UUID REFERENCES users(id),
+    action audit_action NOT NULL,
+    entity_type VARCHAR(50) NOT NULL,
+    entity_id UUID,
+    diff JSONB NOT NULL DEFAULT '{}'::jsonb,
+    ip_address INET,
+    request_id VARCHAR(128),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_events_store ON audit_events(store_id, created_at DESC)
+    WHERE store_id IS NOT NULL;
+CREATE INDEX idx_audit_events_actor ON audit_events(actor_id, created_at DESC);
+CREATE INDEX idx_audit_events_created ON audit_events(created_at DESC);
+
+CREATE OR REPLACE FUNCTION reject_audit_event_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_events is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only BEFORE UPDATE OR DELETE ON audit_events
+    FOR EACH ROW EXECUTE FUNCTION reject_audit_event_changes();
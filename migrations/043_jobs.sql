@@ -0,0 +1,20 @@
+CREATE TYPE job_status AS ENUM ('Pending', 'Succeeded', 'Failed');
+
+-- Background work queued for the job worker. A claimed job stays Pending
+-- with `run_at` pushed past its lease, so a worker that dies mid-run leaves
+-- it to be retried.
+CREATE TABLE jobs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Selects the registered handler that runs the job.
+    kind VARCHAR(100) NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}'::jsonb,
+    status job_status NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    last_error TEXT,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_jobs_due ON jobs(kind, run_at) WHERE status = 'Pending';
@@ -0,0 +1,15 @@
+-- Domain events written by the transaction that made the change they
+-- describe, so an event exists exactly when its change committed. The relay
+-- queues each one for every subscriber and marks it published.
+CREATE TABLE outbox_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    event_type VARCHAR(100) NOT NULL,
+    -- The event as subscribers receive it, tagged with its type.
+    payload JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    published_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_outbox_events_unpublished ON outbox_events(created_at)
+    WHERE published_at IS NULL;
+CREATE INDEX idx_outbox_events_published_at ON outbox_events(published_at);
@@ -0,0 +1,20 @@
+-- Every email the marketplace sends, rendered when queued and sent by the
+-- `email` job. Failed sends stay Pending until the job runs out of attempts.
+CREATE TABLE emails (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    template VARCHAR(50) NOT NULL,
+    recipient VARCHAR(255) NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status delivery_status NOT NULL DEFAULT 'Pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    -- Id the provider gave the message, for tracing it in their logs.
+    provider_message_id TEXT,
+    last_error TEXT,
+    -- Set for emails caused by an event that may repeat, so it sends once.
+    dedupe_key VARCHAR(255) UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_emails_status ON emails(status, created_at DESC);
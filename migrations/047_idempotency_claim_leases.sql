@@ -0,0 +1,4 @@
+-- In-flight claims hold a key only until their lease runs out, so a request
+-- whose handler never finished (crash, dropped connection) can be retried
+-- long before the key itself expires.
+ALTER TABLE idempotency_keys ADD COLUMN locked_until TIMESTAMPTZ;
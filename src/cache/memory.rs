@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+
+use super::Cache;
+
+/// A cache local to this process, for single-instance deployments and
+/// tests. When full, expired entries are dropped first, then the entry
+/// closest to expiring.
+pub struct MemoryCache {
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, Vec<u8>)>>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let entries = self.entries.lock().expect("memory cache poisoned");
+        Ok(entries
+            .get(key)
+            .filter(|(expires_at, _)| *expires_at > Instant::now())
+            .map(|(_, value)| value.clone()))
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> anyhow::Result<()> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut entries = self.entries.lock().expect("memory cache poisoned");
+        if entries.len() >= self.capacity && !entries.contains_key(key) {
+            entries.retain(|_, (expires_at, _)| *expires_at > now);
+            if entries.len() >= self.capacity {
+                let soonest = entries
+                    .iter()
+                    .min_by_key(|(_, (expires_at, _))| *expires_at)
+                    .map(|(key, _)| key.clone());
+                if let Some(soonest) = soonest {
+                    entries.remove(&soonest);
+                }
+            }
+        }
+        entries.insert(key.to_string(), (now + ttl, value));
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        self.entries
+            .lock()
+            .expect("memory cache poisoned")
+            .remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn entries_expire_and_the_soonest_to_expire_is_evicted() {
+        let cache = MemoryCache::new(2);
+        cache
+            .set("short", b"1".to_vec(), Duration::from_millis(20))
+            .await
+            .unwrap();
+        cache
+            .set("long", b"2".to_vec(), Duration::from_secs(60))
+            .await
+            .unwrap();
+        cache
+            .set("longer", b"3".to_vec(), Duration::from_secs(120))
+            .await
+            .unwrap();
+
+        assert_eq!(cache.get("short").await.unwrap(), None);
+        assert_eq!(cache.get("long").await.unwrap(), Some(b"2".to_vec()));
+        assert_eq!(cache.get("longer").await.unwrap(), Some(b"3".to_vec()));
+
+        cache.delete("long").await.unwrap();
+        assert_eq!(cache.get("long").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,205 @@
+use std::{str::FromStr, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use uuid::Uuid;
+
+mod memory;
+mod redis;
+
+pub use memory::MemoryCache;
+pub use redis::RedisCache;
+
+/// How long a scope's generation is kept. It only needs to outlive the
+/// entries built from it.
+const GENERATION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A key-value store for values that can be rebuilt from the database.
+/// Backends report failures; [`CacheHandle`] decides what they mean.
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>>;
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> anyhow::Result<()>;
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Checks the backend can be reached, for readiness probes.
+    async fn ping(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+pub type SharedCache = Arc<dyn Cache>;
+
+/// A cache backend plus how long entries live in it, handed to the
+/// repositories whose reads it speeds up. Every value can be rebuilt from the
+/// database, so a failing backend is logged and treated as empty.
+#[derive(Clone)]
+pub struct CacheHandle {
+    backend: SharedCache,
+    ttl: Duration,
+}
+
+impl CacheHandle {
+    pub fn new(backend: SharedCache, ttl: Duration) -> Self {
+        Self { backend, ttl }
+    }
+
+    pub fn backend(&self) -> &SharedCache {
+        &self.backend
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.backend.get(key).await {
+            Ok(Some(bytes)) => match serde_json::from_slice(&bytes) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    tracing::warn!(key, "Ignoring undecodable cache entry: {}", err);
+                    None
+                }
+            },
+            Ok(None) => None,
+            Err(err) => {
+                tracing::warn!(key, "Cache read failed: {:#}", err);
+                None
+            }
+        }
+    }
+
+    /// Stores `value` for the handle's TTL.
+    pub async fn set_json<T: Serialize>(&self, key: &str, value: &T) {
+        self.set_json_for(key, value, self.ttl).await;
+    }
+
+    /// Stores `value` for `ttl`, for entries that must expire sooner, such
+    /// as permissions from a grant that ends.
+    pub async fn set_json_for<T: Serialize>(&self, key: &str, value: &T, ttl: Duration) {
+        if ttl.is_zero() {
+            return;
+        }
+        let bytes = match serde_json::to_vec(value) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                tracing::warn!(key, "Not caching unserializable value: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = self.backend.set(key, bytes, ttl).await {
+            tracing::warn!(key, "Cache write failed: {:#}", err);
+        }
+    }
+
+    pub async fn invalidate(&self, key: &str) {
+        if let Err(err) = self.backend.delete(key).await {
+            tracing::warn!(key, "Cache invalidation failed: {:#}", err);
+        }
+    }
+
+    /// The current generation of a scope such as one store's product
+    /// listings. Keys built with it are all dropped at once by
+    /// [`invalidate_scope`](Self::invalidate_scope), however many pages or
+    /// users they cover.
+    pub async fn generation(&self, scope: &str) -> String {
+        let key = format!("generation:{scope}");
+        if let Some(generation) = self.get_json::<String>(&key).await {
+            return generation;
+        }
+        let generation = Uuid::new_v4().simple().to_string();
+        self.set_json_for(&key, &generation, GENERATION_TTL).await;
+        generation
+    }
+
+    pub async fn invalidate_scope(&self, scope: &str) {
+        self.invalidate(&format!("generation:{scope}")).await;
+    }
+
+    /// Drops everyone's cached permissions in the store, after a change to
+    /// its members, roles or access grants.
+    pub async fn invalidate_permissions(&self, store_id: Uuid) {
+        self.invalidate_scope(&keys::store_permissions_scope(store_id))
+            .await;
+    }
+}
+
+/// Cache keys and scopes, kept together so readers and the repositories that
+/// invalidate them agree.
+pub mod keys {
+    use uuid::Uuid;
+
+    /// Every page of a store's product listing.
+    pub fn store_products_scope(store_id: Uuid) -> String {
+        format!("products:{store_id}")
+    }
+
+    pub fn store_products_page(
+        generation: &str,
+        store_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> String {
+        format!("products:{store_id}:{generation}:{limit}:{offset}")
+    }
+
+    /// Everyone's permissions in a store.
+    pub fn store_permissions_scope(store_id: Uuid) -> String {
+        format!("permissions:{store_id}")
+    }
+
+    pub fn store_permissions(generation: &str, store_id: Uuid, user_id: Uuid) -> String {
+        format!("permissions:{store_id}:{generation}:{user_id}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CacheBackend {
+    #[default]
+    Memory,
+    Redis,
+}
+
+impl FromStr for CacheBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "memory" => Ok(CacheBackend::Memory),
+            "redis" => Ok(CacheBackend::Redis),
+            other => Err(format!("unknown cache backend '{}'", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn invalidating_a_scope_drops_every_key_built_from_it() {
+        let cache = CacheHandle::new(Arc::new(MemoryCache::new(100)), Duration::from_secs(60));
+        let store_id = Uuid::new_v4();
+        let scope = keys::store_products_scope(store_id);
+
+        let generation = cache.generation(&scope).await;
+        assert_eq!(cache.generation(&scope).await, generation);
+        let first = keys::store_products_page(&generation, store_id, 20, 0);
+        let second = keys::store_products_page(&generation, store_id, 20, 20);
+        cache.set_json(&first, &vec!["a"]).await;
+        cache.set_json(&second, &vec!["b"]).await;
+        assert_eq!(
+            cache.get_json::<Vec<String>>(&first).await,
+            Some(vec!["a".into()])
+        );
+
+        cache.invalidate_scope(&scope).await;
+        let generation = cache.generation(&scope).await;
+        assert!(cache
+            .get_json::<Vec<String>>(&keys::store_products_page(&generation, store_id, 20, 0))
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use reqwest::Url;
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufStream},
+    net::TcpStream,
+    sync::Mutex,
+};
+
+use super::Cache;
+
+/// How long a Redis command may take before the cache gives up on it, so
+/// a slow Redis degrades to cache misses instead of slow requests.
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Every key is prefixed so instances sharing a Redis stay apart from other
+/// applications' data.
+const KEY_PREFIX: &str = "markethub:";
+
+/// A cache shared by every instance, kept in Redis. Commands go over one
+/// connection, which is reopened after any failure.
+pub struct RedisCache {
+    address: String,
+    password: Option<String>,
+    database: Option<u32>,
+    connection: Mutex<Option<BufStream<TcpStream>>>,
+}
+
+enum Reply {
+    Ok,
+    Integer,
+    Bulk(Option<Vec<u8>>),
+}
+
+impl RedisCache {
+    /// Takes a `redis://[:password@]host[:port][/db]` URL.
+    pub fn from_url(url: &str) -> anyhow::Result<Self> {
+        let parsed = Url::parse(url).context("Invalid REDIS_URL")?;
+        if parsed.scheme() != "redis" {
+            bail!("REDIS_URL must start with redis://");
+        }
+        let host = parsed.host_str().context("REDIS_URL has no host")?;
+        let database = match parsed.path().trim_start_matches('/') {
+            "" => None,
+            database => Some(database.parse().context("Invalid database in REDIS_URL")?),
+        };
+        Ok(Self {
+            address: format!("{}:{}", host, parsed.port().unwrap_or(6379)),
+            password: parsed.password().map(str::to_string),
+            database,
+            connection: Mutex::new(None),
+        })
+    }
+
+    async fn connect(&self) -> anyhow::Result<BufStream<TcpStream>> {
+        let stream = TcpStream::connect(&self.address)
+            .await
+            .with_context(|| format!("Failed to connect to Redis at {}", self.address))?;
+        let mut connection = BufStream::new(stream);
+        if let Some(password) = &self.password {
+            execute(&mut connection, &[b"AUTH", password.as_bytes()]).await?;
+        }
+        if let Some(database) = self.database {
+            execute(
+                &mut connection,
+                &[b"SELECT", database.to_string().as_bytes()],
+            )
+            .await?;
+        }
+        Ok(connection)
+    }
+
+    async fn command(&self, args: &[&[u8]]) -> anyhow::Result<Reply> {
+        let mut connection = self.connection.lock().await;
+        let result = tokio::time::timeout(COMMAND_TIMEOUT, async {
+            if connection.is_none() {
+                *connection = Some(self.connect().await?);
+            }
+            let stream = connection.as_mut().expect("connection was just opened");
+            execute(stream, args).await
+        })
+        .await
+        .unwrap_or_else(|_| Err(anyhow::anyhow!("Redis did not answer in time")));
+        if result.is_err() {
+            // The connection may be mid-reply; start over on the next command
+            *connection = None;
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl Cache for RedisCache {
+    async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let key = format!("{KEY_PREFIX}{key}");
+        match self.command(&[b"GET", key.as_bytes()]).await? {
+            Reply::Bulk(value) => Ok(value),
+            _ => bail!("Unexpected reply to GET"),
+        }
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Duration) -> anyhow::Result<()> {
+        let key = format!("{KEY_PREFIX}{key}");
+        let millis = ttl.as_millis().max(1).to_string();
+        self.command(&[b"SET", key.as_bytes(), &value, b"PX", millis.as_bytes()])
+            .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let key = format!("{KEY_PREFIX}{key}");
+        self.command(&[b"DEL", key.as_bytes()]).await?;
+        Ok(())
+    }
+
+    async fn ping(&self) -> anyhow::Result<()> {
+        self.command(&[b"PING"]).await?;
+        Ok(())
+    }
+}
+
+/// Sends one command and reads its reply.
+async fn execute<S>(stream: &mut S, args: &[&[u8]]) -> anyhow::Result<Reply>
+where
+    S: AsyncBufRead + AsyncWrite + Unpin,
+{
+    stream.write_all(&encode(args)).await?;
+    stream.flush().await?;
+    read_reply(stream).await
+}
+
+/// A command as a RESP array of bulk strings.
+fn encode(args: &[&[u8]]) -> Vec<u8> {
+    let mut command = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        command.extend_from_slice(arg);
+        command.extend_from_slice(b"\r\n");
+    }
+    command
+}
+
+/// Reads the replies the cache's commands get: simple strings, errors,
+/// integers and bulk strings.
+async fn read_reply<S>(stream: &mut S) -> anyhow::Result<Reply>
+where
+    S: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    if stream.read_line(&mut line).await? == 0 {
+        bail!("Redis closed the connection");
+    }
+    let line = line.trim_end_matches("\r\n");
+    let (kind, rest) = line.split_at(line.len().min(1));
+    match kind {
+        "+" => Ok(Reply::Ok),
+        "-" => bail!("Redis error: {rest}"),
+        ":" => Ok(Reply::Integer),
+        "$" => {
+            let len: i64 = rest.parse().context("Malformed Redis bulk length")?;
+            if len < 0 {
+                return Ok(Reply::Bulk(None));
+            }
+            let mut value = vec![0; len as usize + 2];
+            stream.read_exact(&mut value).await?;
+            value.truncate(len as usize);
+            Ok(Reply::Bulk(Some(value)))
+        }
+        _ => bail!("Unexpected Redis reply: {line}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{duplex, BufReader};
+
+    #[test]
+    fn commands_are_encoded_as_resp_arrays() {
+        assert_eq!(
+            encode(&[b"SET", b"key", b"value", b"PX", b"1000"]),
+            b"*5\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nvalue\r\n$2\r\nPX\r\n$4\r\n1000\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn replies_are_decoded() {
+        let (client, mut server) = duplex(1024);
+        server
+            .write_all(b"$5\r\nhello\r\n$-1\r\n+OK\r\n:1\r\n-ERR wrong type\r\n")
+            .await
+            .unwrap();
+        let mut client = BufReader::new(client);
+
+        assert!(matches!(
+            read_reply(&mut client).await.unwrap(),
+            Reply::Bulk(Some(value)) if value == b"hello"
+        ));
+        assert!(matches!(
+            read_reply(&mut client).await.unwrap(),
+            Reply::Bulk(None)
+        ));
+        assert!(matches!(read_reply(&mut client).await.unwrap(), Reply::Ok));
+        assert!(matches!(
+            read_reply(&mut client).await.unwrap(),
+            Reply::Integer
+        ));
+        let err = read_reply(&mut client).await.err().unwrap();
+        assert_eq!(err.to_string(), "Redis error: ERR wrong type");
+    }
+
+    #[test]
+    fn urls_carry_the_password_and_database() {
+        let cache = RedisCache::from_url("redis://:s3cret@cache.internal:6380/2").unwrap();
+        assert_eq!(cache.address, "cache.internal:6380");
+        assert_eq!(cache.password.as_deref(), Some("s3cret"));
+        assert_eq!(cache.database, Some(2));
+
+        assert!(RedisCache::from_url("http://cache.internal").is_err());
+    }
+}
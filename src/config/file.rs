@@ -0,0 +1,244 @@
+use std::{env, fs, path::Path};
+
+use anyhow::{bail, Context};
+use toml_edit::{DocumentMut, Item, Value};
+
+/// Every setting a config file may hold, named like its environment
+/// variable.
+pub const SETTINGS: [&str; 89] = [
+    "HOST",
+    "PORT",
+    "INTERNAL_PORT",
+    "SHUTDOWN_GRACE_PERIOD_SECS",
+    "METRICS_BEARER_TOKEN",
+    "METRICS_BASIC_AUTH",
+    "METRICS_ALLOWED_IPS",
+    "ADMIN_ALLOWED_IPS",
+    "DATABASE_URL",
+    "DB_MAX_CONNECTIONS",
+    "DB_MIN_CONNECTIONS",
+    "DB_ACQUIRE_TIMEOUT_SECS",
+    "DB_STATEMENT_TIMEOUT_MS",
+    "DB_SLOW_QUERY_MS",
+    "JWT_SECRET",
+    "JWT_EXPIRATION_HOURS",
+    "PUBLIC_ID_SECRET",
+    "SHIPPING_CONSOLIDATION",
+    "TAX_PROVIDER_URL",
+    "TAX_PROVIDER_API_KEY",
+    "TAX_RATE_CACHE_SECS",
+    "CARRIER_RATES_URL",
+    "CARRIER_RATES_API_KEY",
+    "PAYPAL_API_URL",
+    "PAYPAL_CLIENT_ID",
+    "PAYPAL_CLIENT_SECRET",
+    "PAYPAL_WEBHOOK_ID",
+    "EXCHANGE_RATES_URL",
+    "EXCHANGE_RATES_API_KEY",
+    "EXCHANGE_RATES",
+    "EXCHANGE_RATES_BASE",
+    "EXCHANGE_RATE_CACHE_SECS",
+    "ANALYTICS_CACHE_SECS",
+    "CART_MAX_STORES",
+    "STOCK_RESERVATION_TTL_SECS",
+    "GUEST_CART_TTL_SECS",
+    "CART_EXPIRY_AGE_SECS",
+    "UNPAID_ORDER_TTL_SECS",
+    "IDEMPOTENCY_KEY_TTL_SECS",
+    "READINESS_TIMEOUT_MS",
+    "STORE_CLEANUP_INTERVAL_SECS",
+    "RESERVATION_EXPIRY_INTERVAL_SECS",
+    "GUEST_CART_CLEANUP_INTERVAL_SECS",
+    "IDEMPOTENCY_CLEANUP_INTERVAL_SECS",
+    "CART_EXPIRY_INTERVAL_SECS",
+    "UNPAID_ORDER_EXPIRY_INTERVAL_SECS",
+    "RECOMMENDATION_REFRESH_INTERVAL_SECS",
+    "ANALYTICS_ROLLUP_INTERVAL_SECS",
+    "ANALYTICS_ROLLUP_LOOKBACK_DAYS",
+    "NOTIFICATION_DELIVERY_INTERVAL_SECS",
+    "JOB_POLL_INTERVAL_SECS",
+    "OUTBOX_RELAY_INTERVAL_SECS",
+    "WEBHOOK_DELIVERY_INTERVAL_SECS",
+    "TAX_COMMIT_INTERVAL_SECS",
+    "STORAGE_LOCAL_DIR",
+    "STORAGE_PUBLIC_URL",
+    "STORAGE_PRIVATE_DIR",
+    "EMAIL_BACKEND",
+    "EMAIL_FROM",
+    "SMTP_URL",
+    "SES_REGION",
+    "SES_ACCESS_KEY_ID",
+    "SES_SECRET_ACCESS_KEY",
+    "SES_ENDPOINT",
+    "EVENT_SAMPLE_RATE",
+    "COMPRESSION_MIN_BYTES",
+    "HSTS_MAX_AGE_SECS",
+    "HSTS_INCLUDE_SUBDOMAINS",
+    "FRAME_OPTIONS",
+    "LOG_FORMAT",
+    "RUST_LOG",
+    "SENTRY_DSN",
+    "SENTRY_ENVIRONMENT",
+    "SECRETS_BACKEND",
+    "SECRETS_DIR",
+    "SECRETS_REFRESH_INTERVAL_SECS",
+    "VAULT_ADDR",
+    "VAULT_TOKEN",
+    "VAULT_SECRET_PATH",
+    "AWS_REGION",
+    "AWS_SECRET_ID",
+    "AWS_ACCESS_KEY_ID",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "AWS_SECRETS_ENDPOINT",
+    "CACHE_BACKEND",
+    "REDIS_URL",
+    "CACHE_TTL_SECS",
+    "CACHE_CAPACITY",
+];
+
+/// Reads a TOML config file and exports its settings as environment
+/// variables that are unset or empty, so the environment overrides the file
+/// and the file overrides the built-in defaults. Returns the keys that are
+/// not settings, such as misspelled ones, which are left out.
+pub fn apply(path: &Path) -> anyhow::Result<Vec<String>> {
+    let mut unknown = Vec::new();
+    for (name, value) in read(path)? {
+        if !SETTINGS.contains(&name.as_str()) {
+            unknown.push(name.to_ascii_lowercase());
+        } else if env::var_os(&name).is_none_or(|current| current.is_empty()) {
+            env::set_var(name, value);
+        }
+    }
+    Ok(unknown)
+}
+
+/// The settings in a config file, named like the environment variables they
+/// stand in for.
+pub fn read(path: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => {}
+        Some("yaml" | "yml") => bail!(
+            "{}: YAML config files are not supported, use TOML",
+            path.display()
+        ),
+        _ => bail!("{}: config files must end in .toml", path.display()),
+    }
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    parse(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+/// Flattens a TOML document into variable names: keys are upper-cased and
+/// tables prefix their keys, so `port = 8000` is `PORT` and `bearer_token`
+/// under `[metrics]` is `METRICS_BEARER_TOKEN`. Arrays become comma-separated
+/// lists, as in `admin_allowed_ips = ["10.0.0.0/8"]`.
+pub fn parse(contents: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let document: DocumentMut = contents.parse()?;
+    let mut settings = Vec::new();
+    flatten("", document.as_item(), &mut settings)?;
+    Ok(settings)
+}
+
+fn flatten(prefix: &str, item: &Item, settings: &mut Vec<(String, String)>) -> anyhow::Result<()> {
+    match item {
+        Item::None => {}
+        Item::Value(Value::InlineTable(table)) => {
+            for (key, value) in table.iter() {
+                flatten(&join(prefix, key), &Item::Value(value.clone()), settings)?;
+            }
+        }
+        Item::Value(value) => settings.push((prefix.to_string(), scalar(prefix, value)?)),
+        Item::Table(table) => {
+            for (key, item) in table.iter() {
+                flatten(&join(prefix, key), item, settings)?;
+            }
+        }
+        Item::ArrayOfTables(_) => bail!("{prefix}: arrays of tables are not supported"),
+    }
+    Ok(())
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    let key = key.to_ascii_uppercase();
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{prefix}_{key}")
+    }
+}
+
+fn scalar(name: &str, value: &Value) -> anyhow::Result<String> {
+    Ok(match value {
+        Value::String(value) => value.value().clone(),
+        Value::Integer(value) => value.value().to_string(),
+        Value::Float(value) => value.value().to_string(),
+        Value::Boolean(value) => value.value().to_string(),
+        Value::Datetime(value) => value.value().to_string(),
+        Value::Array(values) => values
+            .iter()
+            .map(|value| scalar(name, value))
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .join(","),
+        Value::InlineTable(_) => bail!("{name}: nested tables are not allowed in arrays"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_prefix_keys_and_arrays_become_lists() {
+        let settings = parse(
+            r#"
+            port = 8080
+            log_format = "json"
+            hsts_include_subdomains = true
+            event_sample_rate = 0.25
+            admin_allowed_ips = ["10.0.0.0/8", "192.168.1.1"]
+
+            [metrics]
+            bearer_token = "scrape-token"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            settings,
+            [
+                ("PORT", "8080"),
+                ("LOG_FORMAT", "json"),
+                ("HSTS_INCLUDE_SUBDOMAINS", "true"),
+                ("EVENT_SAMPLE_RATE", "0.25"),
+                ("ADMIN_ALLOWED_IPS", "10.0.0.0/8,192.168.1.1"),
+                ("METRICS_BEARER_TOKEN", "scrape-token"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn misspelled_keys_are_returned_instead_of_applied() {
+        let path = env::temp_dir().join(format!("markethub-config-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "job_worker_intervl_secs = 5\n[metrics]\nbearer_tokn = \"x\"\n",
+        )
+        .unwrap();
+        let unknown = apply(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(unknown, ["job_worker_intervl_secs", "metrics_bearer_tokn"]);
+        assert!(env::var_os("JOB_WORKER_INTERVL_SECS").is_none());
+    }
+
+    #[test]
+    fn malformed_and_yaml_files_are_rejected() {
+        assert!(parse("port = ").is_err());
+        assert!(parse("[[stores]]\nname = \"a\"").is_err());
+
+        let error = read(Path::new("markethub.yaml")).unwrap_err();
+        assert!(error.to_string().contains("use TOML"));
+    }
+}
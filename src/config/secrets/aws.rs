@@ -0,0 +1,150 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::SecretsProvider;
+use crate::utils::sigv4::{host_header, RequestSigner};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const SERVICE: &str = "secretsmanager";
+const CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+const TARGET: &str = "secretsmanager.GetSecretValue";
+
+/// AWS Secrets Manager. The secret named by `secret_id` must hold a JSON
+/// object whose keys are the secret names, which is how the console stores
+/// key/value secrets. Requests are signed with Signature Version 4.
+pub struct AwsSecretsManager {
+    client: reqwest::Client,
+    endpoint: String,
+    secret_id: String,
+    signer: RequestSigner,
+}
+
+impl AwsSecretsManager {
+    pub fn new(
+        region: impl Into<String>,
+        secret_id: impl Into<String>,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> anyhow::Result<Self> {
+        let region = region.into();
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            endpoint: format!("https://{}.{}.amazonaws.com", SERVICE, region),
+            secret_id: secret_id.into(),
+            signer: RequestSigner::new(SERVICE, region, access_key_id, secret_access_key),
+        })
+    }
+
+    /// Temporary credentials (instance roles, SSO) come with a session token.
+    pub fn with_session_token(mut self, token: impl Into<String>) -> Self {
+        self.signer = self.signer.with_session_token(token);
+        self
+    }
+
+    /// Overrides the regional endpoint, e.g. for VPC endpoints or LocalStack.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        let endpoint = endpoint.into();
+        if !endpoint.is_empty() {
+            self.endpoint = endpoint.trim_end_matches('/').to_string();
+        }
+        self
+    }
+
+    /// Headers for a signed `POST /` carrying `body`, `Authorization` included.
+    fn signed_headers(
+        &self,
+        host: &str,
+        body: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<(&'static str, String)> {
+        let headers = vec![
+            ("content-type", CONTENT_TYPE.to_string()),
+            ("x-amz-target", TARGET.to_string()),
+        ];
+        self.signer
+            .post_headers(host, "/", headers, body.as_bytes(), now)
+    }
+}
+
+#[derive(Deserialize)]
+struct GetSecretValueResponse {
+    #[serde(rename = "SecretString")]
+    secret_string: Option<String>,
+}
+
+#[async_trait]
+impl SecretsProvider for AwsSecretsManager {
+    async fn fetch(&self, names: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        let url = reqwest::Url::parse(&self.endpoint).context("Invalid AWS_SECRETS_ENDPOINT")?;
+        let host = host_header(&url).context("Invalid AWS_SECRETS_ENDPOINT")?;
+        let body = json!({ "SecretId": self.secret_id }).to_string();
+
+        let mut request = self.client.post(url);
+        for (name, value) in self.signed_headers(&host, &body, Utc::now()) {
+            request = request.header(name, value);
+        }
+        let response: GetSecretValueResponse = request
+            .body(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("Secrets Manager request failed")?
+            .json()
+            .await
+            .context("Unexpected Secrets Manager response")?;
+
+        let fields: HashMap<String, Value> = response
+            .secret_string
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .context("Secret value is not a JSON object")?
+            .unwrap_or_default();
+        Ok(names
+            .iter()
+            .filter_map(|name| {
+                fields
+                    .get(*name)
+                    .and_then(Value::as_str)
+                    .map(|value| (name.to_string(), value.to_string()))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn signs_every_amz_header() {
+        let provider = AwsSecretsManager::new("eu-west-1", "markethub/prod", "AKID", "secret")
+            .unwrap()
+            .with_session_token("session");
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let headers = provider.signed_headers("secretsmanager.eu-west-1.amazonaws.com", "{}", now);
+
+        let authorization = &headers
+            .iter()
+            .find(|(name, _)| *name == "authorization")
+            .unwrap()
+            .1;
+        assert!(authorization.starts_with(
+            "AWS4-HMAC-SHA256 Credential=AKID/20240501/eu-west-1/secretsmanager/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-security-token;x-amz-target, \
+             Signature="
+        ));
+        assert!(headers
+            .iter()
+            .any(|(name, value)| *name == "x-amz-security-token" && value == "session"));
+    }
+}
@@ -0,0 +1,66 @@
+use std::{collections::HashMap, io::ErrorKind, path::PathBuf};
+
+use anyhow::Context;
+use async_trait::async_trait;
+
+use super::SecretsProvider;
+
+/// One file per secret, named after it, as Docker and Kubernetes mount them
+/// (`/run/secrets/JWT_SECRET`). Trailing newlines are ignored.
+pub struct FileSecrets {
+    dir: PathBuf,
+}
+
+impl FileSecrets {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl SecretsProvider for FileSecrets {
+    async fn fetch(&self, names: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        let mut secrets = HashMap::new();
+        for name in names {
+            let path = self.dir.join(name);
+            match tokio::fs::read_to_string(&path).await {
+                Ok(contents) => {
+                    let value = contents.trim_end_matches(['\r', '\n']);
+                    if !value.is_empty() {
+                        secrets.insert(name.to_string(), value.to_string());
+                    }
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => {
+                    return Err(err).with_context(|| format!("Cannot read {}", path.display()))
+                }
+            }
+        }
+        Ok(secrets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn reads_present_files_and_skips_missing_ones() {
+        let dir = std::env::temp_dir().join(format!("markethub-secrets-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        tokio::fs::write(dir.join("JWT_SECRET"), "from-file\n")
+            .await
+            .unwrap();
+
+        let secrets = FileSecrets::new(&dir)
+            .fetch(&["JWT_SECRET", "DATABASE_URL"])
+            .await
+            .unwrap();
+        assert_eq!(
+            secrets,
+            HashMap::from([("JWT_SECRET".to_string(), "from-file".to_string())])
+        );
+
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    collections::HashMap,
+    env, fmt,
+    str::FromStr,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+
+mod aws;
+mod file;
+mod vault;
+
+pub use aws::AwsSecretsManager;
+pub use file::FileSecrets;
+pub use vault::VaultSecrets;
+
+/// Secrets the application reads through the configured backend. Everything
+/// else in `Config` is plain environment configuration.
+pub const MANAGED_SECRETS: [&str; 9] = [
+    "DATABASE_URL",
+    "JWT_SECRET",
+    "PUBLIC_ID_SECRET",
+    "TAX_PROVIDER_API_KEY",
+    "CARRIER_RATES_API_KEY",
+    "PAYPAL_CLIENT_SECRET",
+    "EXCHANGE_RATES_API_KEY",
+    "SMTP_URL",
+    "SES_SECRET_ACCESS_KEY",
+];
+
+/// A place secrets are read from. Backends return only the names they hold;
+/// anything missing falls back to the process environment.
+#[async_trait]
+pub trait SecretsProvider: Send + Sync {
+    async fn fetch(&self, names: &[&str]) -> anyhow::Result<HashMap<String, String>>;
+}
+
+/// Reads secrets straight from environment variables.
+pub struct EnvSecrets;
+
+#[async_trait]
+impl SecretsProvider for EnvSecrets {
+    async fn fetch(&self, names: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        Ok(names
+            .iter()
+            .filter_map(|name| {
+                env::var(name)
+                    .ok()
+                    .filter(|value| !value.is_empty())
+                    .map(|value| (name.to_string(), value))
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SecretsBackend {
+    #[default]
+    Env,
+    File,
+    Vault,
+    Aws,
+}
+
+impl FromStr for SecretsBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "env" => Ok(SecretsBackend::Env),
+            "file" => Ok(SecretsBackend::File),
+            "vault" => Ok(SecretsBackend::Vault),
+            "aws" => Ok(SecretsBackend::Aws),
+            other => Err(format!("unknown secrets backend '{}'", other)),
+        }
+    }
+}
+
+impl SecretsBackend {
+    /// Builds the provider, reading its own connection settings (Vault
+    /// address and token, AWS region and credentials, ...) from the environment.
+    pub fn provider_from_env(self) -> anyhow::Result<Arc<dyn SecretsProvider>> {
+        let required = |name: &str| {
+            env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .with_context(|| format!("{} must be set for the {:?} secrets backend", name, self))
+        };
+        let provider: Arc<dyn SecretsProvider> = match self {
+            SecretsBackend::Env => Arc::new(EnvSecrets),
+            SecretsBackend::File => Arc::new(FileSecrets::new(
+                env::var("SECRETS_DIR").unwrap_or_else(|_| "/run/secrets".to_string()),
+            )),
+            SecretsBackend::Vault => Arc::new(VaultSecrets::new(
+                required("VAULT_ADDR")?,
+                required("VAULT_TOKEN")?,
+                &required("VAULT_SECRET_PATH")?,
+            )?),
+            SecretsBackend::Aws => {
+                let mut provider = AwsSecretsManager::new(
+                    required("AWS_REGION")?,
+                    required("AWS_SECRET_ID")?,
+                    required("AWS_ACCESS_KEY_ID")?,
+                    required("AWS_SECRET_ACCESS_KEY")?,
+                )?;
+                if let Ok(token) = env::var("AWS_SESSION_TOKEN") {
+                    provider = provider.with_session_token(token);
+                }
+                if let Ok(endpoint) = env::var("AWS_SECRETS_ENDPOINT") {
+                    provider = provider.with_endpoint(endpoint);
+                }
+                Arc::new(provider)
+            }
+        };
+        Ok(provider)
+    }
+}
+
+type RotationHook = Box<dyn Fn(&str) + Send + Sync>;
+
+/// Current values of the managed secrets. `refresh` re-reads the backend and
+/// hands changed values to the hooks registered for them, so a rotated
+/// credential reaches the JWT keys, the connection pool or an API client
+/// without a restart.
+pub struct SecretStore {
+    provider: Arc<dyn SecretsProvider>,
+    values: RwLock<HashMap<String, String>>,
+    hooks: Mutex<Vec<(String, RotationHook)>>,
+}
+
+impl SecretStore {
+    pub fn new(provider: Arc<dyn SecretsProvider>) -> Self {
+        Self {
+            provider,
+            values: RwLock::new(HashMap::new()),
+            hooks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn load(&self) -> anyhow::Result<()> {
+        let fetched = self.fetch().await?;
+        *self.values.write().unwrap() = fetched;
+        Ok(())
+    }
+
+    /// The backend's value, or the environment variable of the same name.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.values
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .or_else(|| env::var(name).ok().filter(|value| !value.is_empty()))
+    }
+
+    pub fn on_rotate(&self, name: &str, hook: impl Fn(&str) + Send + Sync + 'static) {
+        self.hooks
+            .lock()
+            .unwrap()
+            .push((name.to_string(), Box::new(hook)));
+    }
+
+    /// Re-reads every managed secret and returns the names whose value
+    /// changed. A secret the backend stops returning keeps its last value.
+    pub async fn refresh(&self) -> anyhow::Result<Vec<String>> {
+        let fetched = self.fetch().await?;
+        let mut rotated = Vec::new();
+        {
+            let mut values = self.values.write().unwrap();
+            for (name, value) in fetched {
+                if values.get(&name) != Some(&value) {
+                    values.insert(name.clone(), value.clone());
+                    rotated.push((name, value));
+                }
+            }
+        }
+
+        let hooks = self.hooks.lock().unwrap();
+        for (name, value) in &rotated {
+            for (_, hook) in hooks.iter().filter(|(hooked, _)| hooked == name) {
+                hook(value);
+            }
+        }
+        Ok(rotated.into_iter().map(|(name, _)| name).collect())
+    }
+
+    async fn fetch(&self) -> anyhow::Result<HashMap<String, String>> {
+        self.provider
+            .fetch(&MANAGED_SECRETS)
+            .await
+            .context("Failed to read secrets")
+    }
+}
+
+impl fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<String> = self.values.read().unwrap().keys().cloned().collect();
+        names.sort();
+        f.debug_struct("SecretStore")
+            .field("loaded", &names)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct StaticSecrets(Mutex<HashMap<String, String>>);
+
+    #[async_trait]
+    impl SecretsProvider for StaticSecrets {
+        async fn fetch(&self, _names: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn refresh_runs_hooks_only_for_rotated_secrets() {
+        let backend = Arc::new(StaticSecrets(Mutex::new(HashMap::from([
+            ("JWT_SECRET".to_string(), "first".to_string()),
+            ("TAX_PROVIDER_API_KEY".to_string(), "key".to_string()),
+        ]))));
+        let store = SecretStore::new(backend.clone());
+        store.load().await.unwrap();
+        assert_eq!(store.get("JWT_SECRET").as_deref(), Some("first"));
+
+        let rotations = Arc::new(AtomicUsize::new(0));
+        let seen = rotations.clone();
+        store.on_rotate("JWT_SECRET", move |value| {
+            assert_eq!(value, "second");
+            seen.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert!(store.refresh().await.unwrap().is_empty());
+        backend
+            .0
+            .lock()
+            .unwrap()
+            .insert("JWT_SECRET".to_string(), "second".to_string());
+        assert_eq!(store.refresh().await.unwrap(), vec!["JWT_SECRET"]);
+        assert_eq!(rotations.load(Ordering::SeqCst), 1);
+        assert_eq!(store.get("JWT_SECRET").as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn parses_backend_names() {
+        assert_eq!("Vault".parse::<SecretsBackend>(), Ok(SecretsBackend::Vault));
+        assert_eq!("aws".parse::<SecretsBackend>(), Ok(SecretsBackend::Aws));
+        assert!("consul".parse::<SecretsBackend>().is_err());
+    }
+}
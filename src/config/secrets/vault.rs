@@ -0,0 +1,105 @@
+use std::{collections::HashMap, time::Duration};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::SecretsProvider;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HashiCorp Vault KV secrets engine. `path` is the API path below `/v1`,
+/// e.g. `secret/data/markethub` for a KV v2 mount; the secret's keys are the
+/// secret names. Both KV versions are understood.
+pub struct VaultSecrets {
+    client: reqwest::Client,
+    url: String,
+    token: String,
+}
+
+impl VaultSecrets {
+    pub fn new(
+        addr: impl Into<String>,
+        token: impl Into<String>,
+        path: &str,
+    ) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            url: format!(
+                "{}/v1/{}",
+                addr.into().trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ),
+            token: token.into(),
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct VaultResponse {
+    data: Value,
+}
+
+/// KV v2 nests the secret under `data.data` next to its metadata; KV v1
+/// returns it as `data` directly.
+fn secret_fields(response: VaultResponse) -> HashMap<String, Value> {
+    let data = match response.data.get("data") {
+        Some(inner @ Value::Object(_)) if response.data.get("metadata").is_some() => inner.clone(),
+        _ => response.data,
+    };
+    serde_json::from_value(data).unwrap_or_default()
+}
+
+#[async_trait]
+impl SecretsProvider for VaultSecrets {
+    async fn fetch(&self, names: &[&str]) -> anyhow::Result<HashMap<String, String>> {
+        let response: VaultResponse = self
+            .client
+            .get(&self.url)
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .context("Vault request failed")?
+            .json()
+            .await
+            .context("Unexpected Vault response")?;
+
+        let fields = secret_fields(response);
+        Ok(names
+            .iter()
+            .filter_map(|name| {
+                fields
+                    .get(*name)
+                    .and_then(Value::as_str)
+                    .map(|value| (name.to_string(), value.to_string()))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_kv_v1_and_v2_payloads() {
+        let v2 = VaultResponse {
+            data: json!({
+                "data": {"JWT_SECRET": "v2-secret"},
+                "metadata": {"version": 3},
+            }),
+        };
+        assert_eq!(secret_fields(v2)["JWT_SECRET"], json!("v2-secret"));
+
+        let v1 = VaultResponse {
+            data: json!({"JWT_SECRET": "v1-secret"}),
+        };
+        assert_eq!(secret_fields(v1)["JWT_SECRET"], json!("v1-secret"));
+    }
+}
@@ -0,0 +1,68 @@
+use std::{collections::HashMap, sync::RwLock, time::Duration};
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use super::ExchangeRateProvider;
+use crate::error::AppError;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client for a hosted rates API in the style of Frankfurter or Open
+/// Exchange Rates: `GET {url}?base=USD` answers `{"rates": {"EUR": 0.92}}`.
+/// The API key, when set, is sent as a bearer token.
+pub struct HttpExchangeRates {
+    client: reqwest::Client,
+    url: String,
+    api_key: RwLock<String>,
+}
+
+impl HttpExchangeRates {
+    pub fn new(url: impl Into<String>, api_key: impl Into<String>) -> crate::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| AppError::Internal(err.into()))?;
+        Ok(Self {
+            client,
+            url: url.into(),
+            api_key: RwLock::new(api_key.into()),
+        })
+    }
+
+    /// Swaps the API key for subsequent requests, e.g. after a rotation.
+    pub fn set_api_key(&self, api_key: &str) {
+        *self.api_key.write().unwrap() = api_key.to_string();
+    }
+}
+
+#[derive(Deserialize)]
+struct RatesResponse {
+    rates: HashMap<String, Decimal>,
+}
+
+#[async_trait]
+impl ExchangeRateProvider for HttpExchangeRates {
+    async fn rates(&self, base: &str) -> crate::Result<HashMap<String, Decimal>> {
+        let api_key = self.api_key.read().unwrap().clone();
+        let mut request = self.client.get(&self.url).query(&[("base", base)]);
+        if !api_key.is_empty() {
+            request = request.bearer_auth(api_key);
+        }
+        let response: RatesResponse = request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AppError::Internal(err.into()))?
+            .json()
+            .await
+            .map_err(|err| AppError::Internal(err.into()))?;
+
+        Ok(response
+            .rates
+            .into_iter()
+            .map(|(code, rate)| (code.to_uppercase(), rate))
+            .collect())
+    }
+}
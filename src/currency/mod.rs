@@ -0,0 +1,217 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use crate::error::AppError;
+
+pub mod http;
+
+pub use http::HttpExchangeRates;
+
+/// Source of exchange rates for showing prices in the shopper's currency.
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync {
+    /// Units of each quoted currency per unit of `base`, keyed by ISO 4217
+    /// code, e.g. `{"EUR": 0.92}` for a `USD` base.
+    async fn rates(&self, base: &str) -> crate::Result<HashMap<String, Decimal>>;
+}
+
+/// Fixed rates against one reference currency, such as rates set in
+/// configuration. Rates between two other currencies go through the
+/// reference.
+#[derive(Debug, Clone)]
+pub struct StaticRates {
+    reference: String,
+    rates: HashMap<String, Decimal>,
+}
+
+impl StaticRates {
+    pub fn new(reference: impl Into<String>, rates: HashMap<String, Decimal>) -> Self {
+        Self {
+            reference: reference.into().to_uppercase(),
+            rates,
+        }
+    }
+
+    /// Parses `EUR=0.92,GBP=0.79`, the units of each currency per unit of
+    /// `reference`.
+    pub fn parse(reference: &str, spec: &str) -> Result<Self, String> {
+        let mut rates = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (code, rate) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected CODE=RATE, got '{}'", entry))?;
+            let rate: Decimal = rate
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid rate for {}", code.trim()))?;
+            if rate <= Decimal::ZERO {
+                return Err(format!("rate for {} must be positive", code.trim()));
+            }
+            rates.insert(code.trim().to_uppercase(), rate);
+        }
+        Ok(Self::new(reference, rates))
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for StaticRates {
+    async fn rates(&self, base: &str) -> crate::Result<HashMap<String, Decimal>> {
+        let per_base = if base == self.reference {
+            Decimal::ONE
+        } else {
+            match self.rates.get(base) {
+                Some(rate) => *rate,
+                None => return Ok(HashMap::new()),
+            }
+        };
+
+        let mut rates: HashMap<String, Decimal> = self
+            .rates
+            .iter()
+            .map(|(code, rate)| (code.clone(), rate / per_base))
+            .collect();
+        rates.insert(self.reference.clone(), Decimal::ONE / per_base);
+        Ok(rates)
+    }
+}
+
+/// Units of each currency per unit of one base currency.
+type RateTable = Arc<HashMap<String, Decimal>>;
+
+/// Converts amounts between currencies with the configured provider. Rate
+/// tables are cached per base currency, so a listing of many products costs
+/// at most one provider request.
+pub struct ExchangeRates {
+    provider: Arc<dyn ExchangeRateProvider>,
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (Instant, RateTable)>>,
+}
+
+impl ExchangeRates {
+    pub fn new(provider: Arc<dyn ExchangeRateProvider>) -> Self {
+        Self {
+            provider,
+            ttl: Duration::ZERO,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// No rates at all: prices can only be shown in their own currency.
+    pub fn none() -> Self {
+        Self::new(Arc::new(StaticRates::new("USD", HashMap::new())))
+    }
+
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Units of `to` per unit of `from`.
+    pub async fn rate(&self, from: &str, to: &str) -> crate::Result<Decimal> {
+        if from == to {
+            return Ok(Decimal::ONE);
+        }
+        self.table(from).await?.get(to).copied().ok_or_else(|| {
+            AppError::BadRequest(format!("Prices in {} cannot be shown in {}", from, to))
+        })
+    }
+
+    async fn table(&self, base: &str) -> crate::Result<RateTable> {
+        if let Some((fetched_at, rates)) = self
+            .cache
+            .lock()
+            .expect("exchange rate cache poisoned")
+            .get(base)
+        {
+            if fetched_at.elapsed() < self.ttl {
+                return Ok(rates.clone());
+            }
+        }
+
+        let rates = Arc::new(self.provider.rates(base).await?);
+        if !self.ttl.is_zero() {
+            self.cache
+                .lock()
+                .expect("exchange rate cache poisoned")
+                .insert(base.to_string(), (Instant::now(), rates.clone()));
+        }
+        Ok(rates)
+    }
+}
+
+impl Default for ExchangeRates {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+/// `amount` in the quoted currency, rounded to cents.
+pub fn convert(amount: Decimal, rate: Decimal) -> Decimal {
+    (amount * rate).round_dp(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn rates() -> StaticRates {
+        StaticRates::parse("USD", "EUR=0.5, GBP=0.25").unwrap()
+    }
+
+    #[tokio::test]
+    async fn static_rates_derive_cross_rates_through_the_reference() {
+        let rates = ExchangeRates::new(Arc::new(rates()));
+        assert_eq!(rates.rate("USD", "EUR").await.unwrap(), Decimal::new(5, 1));
+        assert_eq!(rates.rate("EUR", "USD").await.unwrap(), Decimal::TWO);
+        assert_eq!(rates.rate("EUR", "GBP").await.unwrap(), Decimal::new(5, 1));
+        assert_eq!(rates.rate("JPY", "JPY").await.unwrap(), Decimal::ONE);
+        assert!(matches!(
+            rates.rate("USD", "JPY").await,
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_rate_lists() {
+        assert!(StaticRates::parse("USD", "EUR").is_err());
+        assert!(StaticRates::parse("USD", "EUR=abc").is_err());
+        assert!(StaticRates::parse("USD", "EUR=0").is_err());
+        assert!(StaticRates::parse("USD", "").is_ok());
+    }
+
+    struct CountingProvider(AtomicUsize);
+
+    #[async_trait]
+    impl ExchangeRateProvider for CountingProvider {
+        async fn rates(&self, _base: &str) -> crate::Result<HashMap<String, Decimal>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(HashMap::from([("EUR".to_string(), Decimal::ONE)]))
+        }
+    }
+
+    #[tokio::test]
+    async fn caches_rate_tables_per_base() {
+        let provider = Arc::new(CountingProvider(AtomicUsize::new(0)));
+        let rates = ExchangeRates::new(provider.clone()).with_cache_ttl(Duration::from_secs(60));
+        rates.rate("USD", "EUR").await.unwrap();
+        rates.rate("USD", "EUR").await.unwrap();
+        assert_eq!(provider.0.load(Ordering::SeqCst), 1);
+        rates.rate("GBP", "EUR").await.unwrap();
+        assert_eq!(provider.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn converts_to_cents() {
+        assert_eq!(
+            convert(Decimal::new(1999, 2), Decimal::new(92, 2)),
+            Decimal::new(1839, 2)
+        );
+    }
+}
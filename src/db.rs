@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use log::LevelFilter;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    ConnectOptions, PgPool,
+};
+
+/// How the database pool is sized and how its connections behave.
+#[derive(Debug, Clone)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    /// How long a query waits for a free connection before failing.
+    pub acquire_timeout: Duration,
+    /// Postgres `statement_timeout` set on every connection; `None` leaves
+    /// the server's default.
+    pub statement_timeout: Option<Duration>,
+    /// Statements slower than this are logged as warnings.
+    pub slow_query_threshold: Duration,
+}
+
+impl Default for PoolSettings {
+    fn default() -> Self {
+        Self {
+            max_connections: 5,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            statement_timeout: None,
+            slow_query_threshold: Duration::from_secs(1),
+        }
+    }
+}
+
+impl PoolSettings {
+    pub async fn connect(&self, database_url: &str) -> anyhow::Result<PgPool> {
+        let options = self.connect_options(database_url.parse()?);
+        Ok(self.pool_options().connect_with(options).await?)
+    }
+
+    /// Per-connection options, also applied to a rotated `DATABASE_URL`.
+    pub fn connect_options(&self, options: PgConnectOptions) -> PgConnectOptions {
+        options.log_slow_statements(LevelFilter::Warn, self.slow_query_threshold)
+    }
+
+    pub fn pool_options(&self) -> PgPoolOptions {
+        let statement_timeout = self.statement_timeout;
+        PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .acquire_timeout(self.acquire_timeout)
+            .after_connect(move |conn, _meta| {
+                Box::pin(async move {
+                    if let Some(timeout) = statement_timeout {
+                        sqlx::query(&format!("SET statement_timeout = {}", timeout.as_millis()))
+                            .execute(conn)
+                            .await?;
+                    }
+                    Ok(())
+                })
+            })
+    }
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use super::{EmailMessage, EmailService};
+
+/// Writes emails to the log instead of sending them. The default until SMTP
+/// or SES is configured; suitable for development.
+pub struct LogEmailService;
+
+#[async_trait]
+impl EmailService for LogEmailService {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<Option<String>> {
+        tracing::info!(
+            to = %message.to,
+            subject = %message.subject,
+            "{}",
+            message.text
+        );
+        Ok(None)
+    }
+}
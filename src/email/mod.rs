@@ -0,0 +1,262 @@
+//! Transactional email. Emails are rendered from a template and recorded
+//! when queued, then sent by the `email` background job with the configured
+//! backend, so a slow or failing mail provider never holds up a request and
+//! failed sends are retried with the job worker's backoff.
+
+use std::{str::FromStr, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    jobs::{JobHandler, JobQueue},
+    models::{
+        domain_event::{DomainEvent, PublishedEvent},
+        email::Email,
+        job::Job,
+        notification::DeliveryStatus,
+    },
+    outbox::EventSubscriber,
+    repositories::{EmailRepository, StoreRepository, UserRepository},
+};
+
+pub mod log;
+pub mod ses;
+pub mod smtp;
+pub mod templates;
+
+pub use log::LogEmailService;
+pub use ses::SesEmailService;
+pub use smtp::SmtpEmailService;
+pub use templates::EmailTemplate;
+
+/// Job kind of email sends.
+pub const EMAIL_JOB_KIND: &str = "email";
+
+/// A rendered plain text email.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text: String,
+}
+
+/// Hands emails to a mail provider. Returns the id the provider gave the
+/// message, when it gives one. An error means the email was not accepted
+/// and will be retried.
+#[async_trait]
+pub trait EmailService: Send + Sync {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<Option<String>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmailBackend {
+    /// Writes emails to the log; for development.
+    #[default]
+    Log,
+    Smtp,
+    Ses,
+}
+
+impl FromStr for EmailBackend {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "log" => Ok(EmailBackend::Log),
+            "smtp" => Ok(EmailBackend::Smtp),
+            "ses" => Ok(EmailBackend::Ses),
+            other => Err(format!("unknown email backend '{}'", other)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct EmailJobPayload {
+    email_id: Uuid,
+}
+
+/// Queues emails for sending.
+#[derive(Clone)]
+pub struct Mailer {
+    emails: EmailRepository,
+    jobs: JobQueue,
+}
+
+impl Mailer {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            emails: EmailRepository::new(pool.clone()),
+            jobs: JobQueue::new(pool),
+        }
+    }
+
+    /// Queues `template` rendered for `to`. An email queued before under the
+    /// same `dedupe_key` is not queued again; `None` is returned instead.
+    pub async fn queue(
+        &self,
+        to: &str,
+        template: &EmailTemplate,
+        dedupe_key: Option<&str>,
+    ) -> crate::Result<Option<Email>> {
+        let mut tx = self.emails.pool().begin().await?;
+        let email = self.queue_in_tx(&mut tx, to, template, dedupe_key).await?;
+        tx.commit().await?;
+        Ok(email)
+    }
+
+    /// Like `queue`, sending only if the transaction commits.
+    pub async fn queue_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        to: &str,
+        template: &EmailTemplate,
+        dedupe_key: Option<&str>,
+    ) -> crate::Result<Option<Email>> {
+        let message = template.render(to);
+        let Some(email) = self
+            .emails
+            .create_in_tx(
+                tx,
+                template.name(),
+                &message.to,
+                &message.subject,
+                &message.text,
+                dedupe_key,
+            )
+            .await?
+        else {
+            return Ok(None);
+        };
+        self.jobs
+            .enqueue_in_tx(tx, EMAIL_JOB_KIND, &EmailJobPayload { email_id: email.id })
+            .await?;
+        Ok(Some(email))
+    }
+
+    pub async fn find(&self, email_id: Uuid) -> crate::Result<Option<Email>> {
+        self.emails.find(email_id).await
+    }
+
+    /// Queued and sent emails newest first, for support.
+    pub async fn list(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<Email>> {
+        self.emails.list(status, limit, offset).await
+    }
+}
+
+/// Sends a queued email, recording each attempt on it. The email is marked
+/// failed once the job has no attempts left.
+pub struct EmailJob {
+    emails: EmailRepository,
+    service: Arc<dyn EmailService>,
+}
+
+impl EmailJob {
+    pub fn new(pool: PgPool, service: Arc<dyn EmailService>) -> Self {
+        Self {
+            emails: EmailRepository::new(pool),
+            service,
+        }
+    }
+}
+
+#[async_trait]
+impl JobHandler for EmailJob {
+    async fn run(&self, job: &Job) -> anyhow::Result<()> {
+        let payload: EmailJobPayload = serde_json::from_value(job.payload.clone())?;
+        let Some(email) = self.emails.find(payload.email_id).await? else {
+            return Ok(());
+        };
+        // A job retried after the send was recorded
+        if email.status == DeliveryStatus::Sent {
+            return Ok(());
+        }
+
+        let message = EmailMessage {
+            to: email.recipient,
+            subject: email.subject,
+            text: email.body,
+        };
+        match self.service.send(&message).await {
+            Ok(provider_message_id) => {
+                self.emails
+                    .mark_sent(email.id, provider_message_id.as_deref())
+                    .await?;
+                Ok(())
+            }
+            Err(err) => {
+                self.emails
+                    .mark_attempt_failed(
+                        email.id,
+                        &format!("{:#}", err),
+                        job.attempts >= job.max_attempts,
+                    )
+                    .await?;
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Emails users added to a store's staff by someone else. The event id is
+/// the dedupe key, so a repeated event sends one invitation.
+#[derive(Clone)]
+pub struct InvitationEmails {
+    mailer: Mailer,
+    users: UserRepository,
+    stores: StoreRepository,
+}
+
+impl InvitationEmails {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            mailer: Mailer::new(pool.clone()),
+            users: UserRepository::new(pool.clone()),
+            stores: StoreRepository::new(pool),
+        }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for InvitationEmails {
+    async fn handle(&self, published: &PublishedEvent) -> anyhow::Result<()> {
+        let DomainEvent::MemberInvited {
+            store_id,
+            user_id,
+            role,
+            invited_by,
+        } = &published.event
+        else {
+            return Ok(());
+        };
+        let (Some(member), Some(store)) = (
+            self.users.find_by_id(*user_id).await?,
+            self.stores.find_by_id(*store_id).await?,
+        ) else {
+            return Ok(());
+        };
+        let invited_by = self
+            .users
+            .find_by_id(*invited_by)
+            .await?
+            .map(|inviter| inviter.full_name);
+
+        let template = EmailTemplate::MemberInvitation {
+            name: member.full_name,
+            store_name: store.name,
+            role: *role,
+            invited_by,
+        };
+        self.mailer
+            .queue(&member.email, &template, Some(&published.id.to_string()))
+            .await?;
+        Ok(())
+    }
+}
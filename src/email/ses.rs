@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{EmailMessage, EmailService};
+use crate::{
+    metrics::SharedMetrics,
+    utils::{
+        breaker::{BreakerConfig, BreakerError, CircuitBreaker},
+        sigv4::{host_header, RequestSigner},
+    },
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const SERVICE: &str = "ses";
+const SEND_EMAIL_PATH: &str = "/v2/email/outbound-emails";
+
+/// Amazon SES, through the v2 `SendEmail` API signed with Signature
+/// Version 4.
+pub struct SesEmailService {
+    client: reqwest::Client,
+    endpoint: String,
+    from: String,
+    signer: RequestSigner,
+    breaker: CircuitBreaker,
+}
+
+impl SesEmailService {
+    pub fn new(
+        region: impl Into<String>,
+        from: impl Into<String>,
+        access_key_id: impl Into<String>,
+        secret_access_key: impl Into<String>,
+    ) -> anyhow::Result<Self> {
+        let region = region.into();
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        Ok(Self {
+            client,
+            endpoint: format!("https://email.{}.amazonaws.com", region),
+            from: from.into(),
+            signer: RequestSigner::new(SERVICE, region, access_key_id, secret_access_key),
+            breaker: CircuitBreaker::new(SERVICE, BreakerConfig::default()),
+        })
+    }
+
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.breaker = self.breaker.with_metrics(metrics);
+        self
+    }
+
+    pub fn with_session_token(mut self, token: impl Into<String>) -> Self {
+        self.signer = self.signer.with_session_token(token);
+        self
+    }
+
+    /// Overrides the regional endpoint, e.g. for VPC endpoints or LocalStack.
+    pub fn with_endpoint(mut self, endpoint: impl Into<String>) -> Self {
+        let endpoint = endpoint.into();
+        if !endpoint.is_empty() {
+            self.endpoint = endpoint.trim_end_matches('/').to_string();
+        }
+        self
+    }
+}
+
+#[derive(Deserialize)]
+struct SendEmailResponse {
+    #[serde(rename = "MessageId")]
+    message_id: Option<String>,
+}
+
+#[async_trait]
+impl EmailService for SesEmailService {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<Option<String>> {
+        let url = reqwest::Url::parse(&format!("{}{}", self.endpoint, SEND_EMAIL_PATH))
+            .context("Invalid SES_ENDPOINT")?;
+        let host = host_header(&url).context("Invalid SES_ENDPOINT")?;
+        let body = json!({
+            "FromEmailAddress": self.from,
+            "Destination": { "ToAddresses": [message.to] },
+            "Content": {
+                "Simple": {
+                    "Subject": { "Data": message.subject, "Charset": "UTF-8" },
+                    "Body": { "Text": { "Data": message.text, "Charset": "UTF-8" } },
+                },
+            },
+        })
+        .to_string();
+
+        let headers = vec![("content-type", "application/json".to_string())];
+        let mut request = self.client.post(url);
+        for (name, value) in
+            self.signer
+                .post_headers(&host, SEND_EMAIL_PATH, headers, body.as_bytes(), Utc::now())
+        {
+            request = request.header(name, value);
+        }
+        // Only transport failures and server errors count against SES; a
+        // rejected email is not SES being down.
+        let response = self
+            .breaker
+            .call(async {
+                let response = request.body(body).send().await?;
+                if response.status().is_server_error() {
+                    return response.error_for_status();
+                }
+                Ok(response)
+            })
+            .await
+            .map_err(|err| match err {
+                BreakerError::Inner(err) => err.into(),
+                other => anyhow!(other.to_string()),
+            })
+            .and_then(|response| response.error_for_status().map_err(Into::into))
+            .context("SES request failed")?;
+        let response: SendEmailResponse =
+            response.json().await.context("Unexpected SES response")?;
+        Ok(response.message_id)
+    }
+}
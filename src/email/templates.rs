@@ -0,0 +1,121 @@
+use rust_decimal::Decimal;
+
+use super::EmailMessage;
+use crate::models::store::MemberRole;
+
+/// The emails the marketplace sends, with what each one shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmailTemplate {
+    /// A store's part of a checkout was placed.
+    OrderConfirmation {
+        name: String,
+        order_number: String,
+        store_name: String,
+        total_amount: Decimal,
+        currency: String,
+    },
+    /// The user was added to a store's staff.
+    MemberInvitation {
+        name: String,
+        store_name: String,
+        role: MemberRole,
+        invited_by: Option<String>,
+    },
+}
+
+impl EmailTemplate {
+    /// Recorded with each email, to tell them apart.
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::OrderConfirmation { .. } => "order_confirmation",
+            EmailTemplate::MemberInvitation { .. } => "member_invitation",
+        }
+    }
+
+    pub fn render(&self, to: &str) -> EmailMessage {
+        let (subject, text) = match self {
+            EmailTemplate::OrderConfirmation {
+                name,
+                order_number,
+                store_name,
+                total_amount,
+                currency,
+            } => (
+                format!("Your order {} from {}", order_number, store_name),
+                format!(
+                    "Hi {},\n\n\
+                     Thanks for your order. {} has received order {} totalling {} {} \
+                     and will let you know when it ships.\n",
+                    name,
+                    store_name,
+                    order_number,
+                    total_amount.round_dp(2),
+                    currency
+                ),
+            ),
+            EmailTemplate::MemberInvitation {
+                name,
+                store_name,
+                role,
+                invited_by,
+            } => (
+                format!("You've joined {} on Markethub", store_name),
+                format!(
+                    "Hi {},\n\n\
+                     {} added you to {} as {}. \
+                     Sign in to Markethub to start working on the store.\n",
+                    name,
+                    invited_by.as_deref().unwrap_or("A store administrator"),
+                    store_name,
+                    role_name(*role)
+                ),
+            ),
+        };
+        EmailMessage {
+            to: to.to_string(),
+            subject,
+            text,
+        }
+    }
+}
+
+fn role_name(role: MemberRole) -> &'static str {
+    match role {
+        MemberRole::Owner => "an owner",
+        MemberRole::Admin => "an admin",
+        MemberRole::Manager => "a manager",
+        MemberRole::Staff => "a staff member",
+        MemberRole::Custom => "a team member",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_render_their_details() {
+        let message = EmailTemplate::OrderConfirmation {
+            name: "Ada".into(),
+            order_number: "ORD-1234".into(),
+            store_name: "Mugs & Co".into(),
+            total_amount: Decimal::new(2050, 2),
+            currency: "USD".into(),
+        }
+        .render("ada@example.com");
+        assert_eq!(message.to, "ada@example.com");
+        assert_eq!(message.subject, "Your order ORD-1234 from Mugs & Co");
+        assert!(message.text.contains("order ORD-1234 totalling 20.50 USD"));
+
+        let message = EmailTemplate::MemberInvitation {
+            name: "Ada".into(),
+            store_name: "Mugs & Co".into(),
+            role: MemberRole::Manager,
+            invited_by: None,
+        }
+        .render("ada@example.com");
+        assert!(message
+            .text
+            .contains("A store administrator added you to Mugs & Co as a manager."));
+    }
+}
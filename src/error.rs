@@ -4,6 +4,9 @@ use axum::Json;
 use serde_json::json;
 use thiserror::Error;
 
+use crate::middleware::request_id::current_request_id;
+use crate::reporting;
+
 pub type Result<T> = std::result::Result<T, AppError>;
 
 #[derive(Debug, Error)]
@@ -29,6 +32,9 @@ pub enum AppError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 }
@@ -43,11 +49,12 @@ impl AppError {
             Self::NotFound(_) => StatusCode::NOT_FOUND,
             Self::Conflict(_) => StatusCode::CONFLICT,
             Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::QuotaExceeded(_) => StatusCode::FORBIDDEN,
             Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
-    fn error_code(&self) -> &str {
+    pub fn error_code(&self) -> &'static str {
         match self {
             Self::Database(_) => "DATABASE_ERROR",
             Self::Validation(_) => "VALIDATION_ERROR",
@@ -56,6 +63,7 @@ impl AppError {
             Self::NotFound(_) => "NOT_FOUND",
             Self::Conflict(_) => "CONFLICT",
             Self::BadRequest(_) => "BAD_REQUEST",
+            Self::QuotaExceeded(_) => "QUOTA_EXCEEDED",
             Self::Internal(_) => "INTERNAL_ERROR",
         }
     }
@@ -67,17 +75,21 @@ impl IntoResponse for AppError {
         let error_code = self.error_code();
         let message = self.to_string();
 
-        // Log internal errors
+        // Log internal errors and send them to the error tracker
         if matches!(self, Self::Internal(_) | Self::Database(_)) {
             tracing::error!("Internal error: {}", message);
+            reporting::report(&self);
         }
 
-        let body = Json(json!({
-            "error": {
-                "code": error_code,
-                "message": message,
-            }
-        }));
+        let mut error = json!({
+            "code": error_code,
+            "message": message,
+        });
+        // Lets users quote the failing request when reporting it.
+        if let Some(request_id) = current_request_id() {
+            error["request_id"] = json!(request_id);
+        }
+        let body = Json(json!({ "error": error }));
 
         (status, body).into_response()
     }
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::{Event, EventHandler};
+use crate::{
+    email::{EmailTemplate, Mailer},
+    error::AppError,
+    repositories::{StoreRepository, UserRepository},
+};
+
+/// Emails buyers a confirmation of each order placed, queued with the order
+/// so it only goes out if the checkout commits.
+#[derive(Clone)]
+pub struct EmailHandler {
+    mailer: Mailer,
+    users: UserRepository,
+    stores: StoreRepository,
+}
+
+impl EmailHandler {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            mailer: Mailer::new(pool.clone()),
+            users: UserRepository::new(pool.clone()),
+            stores: StoreRepository::new(pool),
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for EmailHandler {
+    async fn handle_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: &Event,
+    ) -> crate::Result<()> {
+        let Event::OrderPlaced { order, .. } = event else {
+            return Ok(());
+        };
+        let buyer = self
+            .users
+            .find_by_id(order.user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+        let store = self
+            .stores
+            .find_by_id(order.store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+
+        let template = EmailTemplate::OrderConfirmation {
+            name: buyer.full_name,
+            order_number: order.order_number.clone(),
+            store_name: store.name,
+            total_amount: order.total_amount,
+            currency: order.currency.clone(),
+        };
+        self.mailer
+            .queue_in_tx(
+                tx,
+                &buyer.email,
+                &template,
+                Some(&format!("order_confirmation:{}", order.id)),
+            )
+            .await?;
+        Ok(())
+    }
+}
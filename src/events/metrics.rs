@@ -0,0 +1,33 @@
+use async_trait::async_trait;
+
+use super::{Event, EventHandler};
+use crate::metrics::SharedMetrics;
+
+/// Counts placed orders, their value, failed checkouts and sign-ups.
+#[derive(Clone)]
+pub struct MetricsHandler {
+    metrics: SharedMetrics,
+}
+
+impl MetricsHandler {
+    pub fn new(metrics: SharedMetrics) -> Self {
+        Self { metrics }
+    }
+}
+
+#[async_trait]
+impl EventHandler for MetricsHandler {
+    async fn handle(&self, event: &Event) -> anyhow::Result<()> {
+        match event {
+            Event::OrderPlaced { order, .. } => self.metrics.observe_order_created(
+                order.store_id,
+                &order.currency,
+                order.total_amount,
+            ),
+            Event::CheckoutFailed { reason } => self.metrics.observe_checkout_failure(reason),
+            Event::UserRegistered { .. } => self.metrics.observe_user_registered(),
+            Event::OrderStatusChanged { .. } | Event::StockChanged { .. } => {}
+        }
+        Ok(())
+    }
+}
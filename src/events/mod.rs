@@ -0,0 +1,172 @@
+//! In-process event bus. Services announce what happened and the bus hands
+//! it to every subscribed handler, so side effects such as notifications,
+//! metrics and the outbox feeding webhooks stay out of the services.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::models::{
+    order::{Order, OrderStatus},
+    product::Availability,
+};
+
+pub mod email;
+pub mod metrics;
+pub mod notifications;
+pub mod outbox;
+
+pub use email::EmailHandler;
+pub use metrics::MetricsHandler;
+pub use notifications::NotificationHandler;
+pub use outbox::OutboxHandler;
+
+/// Something a service did, announced on the bus.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A store's sub-order was placed at checkout; `paid` when store credit
+    /// covered the whole checkout.
+    OrderPlaced {
+        order: Box<Order>,
+        paid: bool,
+    },
+    /// An order moved from `from` to the status it now has.
+    OrderStatusChanged {
+        order: Box<Order>,
+        from: OrderStatus,
+    },
+    /// A checkout was rejected, with the error code it failed with.
+    CheckoutFailed {
+        reason: &'static str,
+    },
+    /// A sale changed a product's stock.
+    StockChanged {
+        product_id: Uuid,
+        store_id: Uuid,
+        product_name: String,
+        stock_before: i32,
+        stock_after: i32,
+    },
+    UserRegistered {
+        user_id: Uuid,
+        email: String,
+    },
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::OrderPlaced { .. } => "OrderPlaced",
+            Event::OrderStatusChanged { .. } => "OrderStatusChanged",
+            Event::CheckoutFailed { .. } => "CheckoutFailed",
+            Event::StockChanged { .. } => "StockChanged",
+            Event::UserRegistered { .. } => "UserRegistered",
+        }
+    }
+
+    /// Whether this is the stock change that took the product to the low
+    /// stock threshold, so a product running low is reported once rather
+    /// than on every later sale.
+    pub fn crossed_low_stock(&self) -> bool {
+        matches!(
+            self,
+            Event::StockChanged { stock_before, stock_after, .. }
+                if *stock_before > Availability::LOW_STOCK_THRESHOLD
+                    && *stock_after <= Availability::LOW_STOCK_THRESHOLD
+        )
+    }
+}
+
+/// Reacts to events. Both hooks default to doing nothing.
+#[async_trait]
+pub trait EventHandler: Send + Sync {
+    /// Runs inside the publishing transaction, for side effects that must
+    /// commit or roll back with the change. An error aborts the change.
+    async fn handle_in_tx(
+        &self,
+        _tx: &mut Transaction<'_, Postgres>,
+        _event: &Event,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+
+    /// Runs once the change is committed, or straight away for events
+    /// published outside a transaction. Errors are logged and never reach
+    /// the publisher.
+    async fn handle(&self, _event: &Event) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hands events to the subscribed handlers in the order they subscribed.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    handlers: Vec<Arc<dyn EventHandler>>,
+}
+
+impl EventBus {
+    /// A bus without subscribers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A bus with the handlers every change needs: store notifications and
+    /// the outbox.
+    pub fn with_defaults(pool: PgPool) -> Self {
+        Self::new()
+            .subscribe(Arc::new(NotificationHandler::new(pool.clone())))
+            .subscribe(Arc::new(OutboxHandler::new(pool)))
+    }
+
+    pub fn subscribe(mut self, handler: Arc<dyn EventHandler>) -> Self {
+        self.handlers.push(handler);
+        self
+    }
+
+    /// Runs the transactional side effects of `event`. Callers publish the
+    /// same event with `publish` once the transaction commits.
+    pub async fn publish_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: &Event,
+    ) -> crate::Result<()> {
+        for handler in &self.handlers {
+            handler.handle_in_tx(tx, event).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn publish(&self, event: &Event) {
+        for handler in &self.handlers {
+            if let Err(err) = handler.handle(event).await {
+                tracing::error!(event = event.name(), "Event handler failed: {:#}", err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock_changed(stock_before: i32, stock_after: i32) -> Event {
+        Event::StockChanged {
+            product_id: Uuid::nil(),
+            store_id: Uuid::nil(),
+            product_name: "Mug".into(),
+            stock_before,
+            stock_after,
+        }
+    }
+
+    #[test]
+    fn only_the_change_reaching_the_threshold_crosses_it() {
+        let threshold = Availability::LOW_STOCK_THRESHOLD;
+        assert!(stock_changed(threshold + 1, threshold).crossed_low_stock());
+        assert!(stock_changed(threshold + 3, 0).crossed_low_stock());
+        assert!(!stock_changed(threshold, threshold - 1).crossed_low_stock());
+        assert!(!stock_changed(threshold + 3, threshold + 1).crossed_low_stock());
+    }
+}
@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::{Event, EventHandler};
+use crate::{
+    models::notification::{LOW_STOCK, NEW_ORDER},
+    repositories::NotificationRepository,
+};
+
+/// Notifies stores of new orders and of products running low, with the
+/// change that caused it.
+#[derive(Clone)]
+pub struct NotificationHandler {
+    notifications: NotificationRepository,
+}
+
+impl NotificationHandler {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            notifications: NotificationRepository::new(pool),
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for NotificationHandler {
+    async fn handle_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: &Event,
+    ) -> crate::Result<()> {
+        match event {
+            Event::OrderPlaced { order, .. } => {
+                self.notifications
+                    .dispatch_store_event_in_tx(
+                        tx,
+                        order.store_id,
+                        NEW_ORDER,
+                        &format!(
+                            "New order {} for {}",
+                            order.order_number, order.total_amount
+                        ),
+                    )
+                    .await?;
+            }
+            Event::StockChanged {
+                store_id,
+                product_name,
+                stock_after,
+                ..
+            } if event.crossed_low_stock() => {
+                self.notifications
+                    .dispatch_store_event_in_tx(
+                        tx,
+                        *store_id,
+                        LOW_STOCK,
+                        &format!("{} is running low: {} left", product_name, stock_after),
+                    )
+                    .await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use sqlx::{PgPool, Postgres, Transaction};
+
+use super::{Event, EventHandler};
+use crate::{models::domain_event::DomainEvent, repositories::OutboxRepository};
+
+/// Records the domain events of orders, status changes and stock changes in the outbox,
+/// from which webhooks and other durable subscribers are served.
+#[derive(Clone)]
+pub struct OutboxHandler {
+    outbox: OutboxRepository,
+}
+
+impl OutboxHandler {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            outbox: OutboxRepository::new(pool),
+        }
+    }
+}
+
+#[async_trait]
+impl EventHandler for OutboxHandler {
+    async fn handle_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: &Event,
+    ) -> crate::Result<()> {
+        match event {
+            Event::OrderPlaced { order, paid } => {
+                self.outbox
+                    .record_in_tx(
+                        tx,
+                        &DomainEvent::OrderCreated {
+                            order_id: order.id,
+                            order_group_id: order.order_group_id,
+                            store_id: order.store_id,
+                            buyer_id: order.user_id,
+                            total_amount: order.total_amount,
+                            currency: order.currency.clone(),
+                        },
+                    )
+                    .await?;
+                if *paid {
+                    self.outbox
+                        .record_in_tx(
+                            tx,
+                            &DomainEvent::OrderPaid {
+                                order_id: order.id,
+                                order_group_id: order.order_group_id,
+                                store_id: order.store_id,
+                                buyer_id: order.user_id,
+                                total_amount: order.total_amount,
+                                currency: order.currency.clone(),
+                            },
+                        )
+                        .await?;
+                }
+            }
+            Event::OrderStatusChanged { order, from } => {
+                self.outbox
+                    .record_in_tx(
+                        tx,
+                        &DomainEvent::OrderStatusChanged {
+                            order_id: order.id,
+                            store_id: order.store_id,
+                            buyer_id: order.user_id,
+                            from: *from,
+                            to: order.status,
+                        },
+                    )
+                    .await?;
+            }
+            Event::StockChanged {
+                product_id,
+                store_id,
+                stock_after,
+                ..
+            } => {
+                if event.crossed_low_stock() {
+                    self.outbox
+                        .record_in_tx(
+                            tx,
+                            &DomainEvent::LowStock {
+                                product_id: *product_id,
+                                store_id: *store_id,
+                                stock_quantity: *stock_after,
+                            },
+                        )
+                        .await?;
+                }
+                if *stock_after == 0 {
+                    self.outbox
+                        .record_in_tx(
+                            tx,
+                            &DomainEvent::StockDepleted {
+                                product_id: *product_id,
+                                store_id: *store_id,
+                            },
+                        )
+                        .await?;
+                }
+            }
+            Event::CheckoutFailed { .. } | Event::UserRegistered { .. } => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    email::Mailer,
+    middleware::{auth::AuthenticatedUser, permissions::ensure_platform_admin},
+    models::{
+        self,
+        audit::{AuditEvent, AuditEventFilter},
+        email::Email,
+        fulfillment::{
+            AssignFulfillmentPartnerRequest, CreateFulfillmentPartnerRequest, FulfillmentPartner,
+        },
+        notification::DeliveryStatus,
+        store::{
+            AppealStatus, ChangePlanRequest, ModerateStoreRequest, ResolveAppealRequest, Store,
+            StoreAppeal, StoreModerationAction, StoreModerationResponse,
+        },
+    },
+    repositories::{
+        AuditRepository, FulfillmentRepository, MemberRepository, ModerationRepository,
+        StoreRepository,
+    },
+    services::{AuditService, FulfillmentService, ModerationService, StoreService},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+struct AppealsQuery {
+    status: Option<AppealStatus>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmailsQuery {
+    status: Option<DeliveryStatus>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AuditEventsQuery {
+    store_id: Option<Uuid>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/stores/{store_id}/status", post(moderate_store))
+        .route("/stores/{store_id}/moderation", get(moderation_history))
+        .route("/appeals", get(list_appeals))
+        .route("/appeals/{appeal_id}/resolve", post(resolve_appeal))
+        .route(
+            "/fulfillment-partners",
+            post(create_fulfillment_partner).get(list_fulfillment_partners),
+        )
+        .route(
+            "/stores/{store_id}/fulfillment-partner",
+            put(assign_fulfillment_partner),
+        )
+        .route("/stores/{store_id}/plan", put(change_store_plan))
+        .route("/audit-events", get(list_audit_events))
+        .route("/emails", get(list_emails))
+}
+
+async fn moderate_store(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<ModerateStoreRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreModerationResponse>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = moderation_service(&state);
+    let response = service
+        .moderate_store(user.user_id, store_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(response)))
+}
+
+async fn moderation_history(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreModerationAction>>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = moderation_service(&state);
+    let actions = service.moderation_history(store_id).await?;
+    Ok(Json(models::ApiResponse::new(actions)))
+}
+
+async fn list_appeals(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(query): Query<AppealsQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreAppeal>>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let service = moderation_service(&state);
+    let appeals = service.list_appeals(query.status, limit, offset).await?;
+    Ok(Json(models::ApiResponse::new(appeals)))
+}
+
+async fn resolve_appeal(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(appeal_id): Path<Uuid>,
+    Json(payload): Json<ResolveAppealRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreAppeal>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = moderation_service(&state);
+    let appeal = service
+        .resolve_appeal(user.user_id, appeal_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(appeal)))
+}
+
+async fn create_fulfillment_partner(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(payload): Json<CreateFulfillmentPartnerRequest>,
+) -> crate::Result<Json<models::ApiResponse<FulfillmentPartner>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = fulfillment_service(&state);
+    let partner = service.create_partner(payload).await?;
+    Ok(Json(models::ApiResponse::new(partner)))
+}
+
+async fn list_fulfillment_partners(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<FulfillmentPartner>>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = fulfillment_service(&state);
+    let partners = service.list_partners().await?;
+    Ok(Json(models::ApiResponse::new(partners)))
+}
+
+async fn assign_fulfillment_partner(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<AssignFulfillmentPartnerRequest>,
+) -> crate::Result<Json<models::ApiResponse<Store>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = fulfillment_service(&state);
+    let store = service.assign_store(store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(store)))
+}
+
+async fn change_store_plan(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<ChangePlanRequest>,
+) -> crate::Result<Json<models::ApiResponse<Store>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let service = StoreService::new(
+        StoreRepository::new(state.db.clone()),
+        MemberRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+    );
+    let store = service.change_plan(store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(store)))
+}
+
+/// Sensitive actions across the platform, including sign-ins, optionally
+/// narrowed to one store.
+async fn list_audit_events(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(query): Query<AuditEventsQuery>,
+    Query(filter): Query<AuditEventFilter>,
+) -> crate::Result<Json<models::ApiResponse<Vec<AuditEvent>>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let events = AuditService::new(AuditRepository::new(state.db.clone()))
+        .list(query.store_id, &filter, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(events)))
+}
+
+async fn list_emails(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(query): Query<EmailsQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<Email>>>> {
+    ensure_platform_admin(&state, user.user_id).await?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let emails = Mailer::new(state.db.clone())
+        .list(query.status, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(emails)))
+}
+
+fn moderation_service(state: &AppState) -> ModerationService {
+    ModerationService::new(
+        StoreRepository::new(state.db.clone()),
+        ModerationRepository::new(state.db.clone()),
+    )
+}
+
+fn fulfillment_service(state: &AppState) -> FulfillmentService {
+    FulfillmentService::new(
+        FulfillmentRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+    )
+}
@@ -1,12 +1,19 @@
-use axum::{extract::State, routing::post, Json, Router};
+use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+use serde_json::json;
+use uuid::Uuid;
 
 use crate::{
+    error::AppError,
+    middleware::auth::{guest_session_id, AuthenticatedUser, GUEST_TOKEN_HEADER},
     models::{
         self,
-        user::{AuthTokenResponse, LoginRequest, RegisterUserRequest},
+        audit::{AuditAction, NewAuditEvent},
+        user::{AuthTokenResponse, ChangePasswordRequest, LoginRequest, RegisterUserRequest},
     },
-    repositories::UserRepository,
-    services::AuthService,
+    repositories::{
+        AuditRepository, CartRepository, ProductRepository, StoreSettingsRepository, UserRepository,
+    },
+    services::{AuditService, AuthService, CartService},
     state::AppState,
 };
 
@@ -14,26 +21,94 @@ pub fn router() -> Router<AppState> {
     Router::new()
         .route("/register", post(register))
         .route("/login", post(login))
+        .route("/password", post(change_password))
 }
 
 async fn register(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<RegisterUserRequest>,
 ) -> crate::Result<Json<models::ApiResponse<AuthTokenResponse>>> {
     let service = auth_service(&state);
     let response = service.register(payload).await?;
+    adopt_guest_cart(&state, &headers, response.user.id).await;
     Ok(Json(models::ApiResponse::new(response)))
 }
 
 async fn login(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> crate::Result<Json<models::ApiResponse<AuthTokenResponse>>> {
     let service = auth_service(&state);
-    let response = service.login(payload).await?;
+    let audit = AuditService::new(AuditRepository::new(state.db.clone()));
+    let email = payload.email.clone();
+    let response = match service.login(payload).await {
+        Ok(response) => response,
+        Err(err) => {
+            if matches!(err, AppError::Authentication(_)) {
+                audit
+                    .record(NewAuditEvent {
+                        store_id: None,
+                        actor_id: None,
+                        action: AuditAction::LoginFailed,
+                        entity_type: "user",
+                        entity_id: None,
+                        diff: json!({ "email": email }),
+                    })
+                    .await;
+            }
+            return Err(err);
+        }
+    };
+    audit
+        .record(NewAuditEvent {
+            store_id: None,
+            actor_id: Some(response.user.id),
+            action: AuditAction::Login,
+            entity_type: "user",
+            entity_id: Some(response.user.id),
+            diff: json!({}),
+        })
+        .await;
+    adopt_guest_cart(&state, &headers, response.user.id).await;
+    Ok(Json(models::ApiResponse::new(response)))
+}
+
+/// Moves the cart of the guest token sent with a sign-in into the account.
+/// The sign-in itself never fails over the cart: unknown tokens are ignored
+/// and merge errors only logged.
+async fn adopt_guest_cart(state: &AppState, headers: &HeaderMap, user_id: Uuid) {
+    let Some(session_id) = headers
+        .get(GUEST_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|token| guest_session_id(state, token))
+    else {
+        return;
+    };
+
+    let carts = CartService::new(
+        CartRepository::new(state.db.clone()),
+        ProductRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    )
+    .with_reservation_ttl(state.reservation_ttl);
+    if let Err(err) = carts.merge_guest(session_id, user_id).await {
+        tracing::warn!(%user_id, "Guest cart merge failed: {}", err);
+    }
+}
+
+async fn change_password(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> crate::Result<Json<models::ApiResponse<AuthTokenResponse>>> {
+    let service = auth_service(&state);
+    let response = service.change_password(user.user_id, payload).await?;
     Ok(Json(models::ApiResponse::new(response)))
 }
 
 fn auth_service(state: &AppState) -> AuthService {
     AuthService::new(UserRepository::new(state.db.clone()), state.jwt.clone())
+        .with_events(state.events.clone())
 }
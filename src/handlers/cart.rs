@@ -1,54 +1,153 @@
 use axum::{
     extract::{Path, State},
-    routing::{delete, post},
+    routing::{delete, get, post},
     Json, Router,
 };
 use serde_json::json;
 use uuid::Uuid;
 
 use crate::{
-    middleware::auth::AuthenticatedUser,
+    middleware::{auth::AuthenticatedUser, currency::DisplayCurrency},
     models::{
         self,
-        order::{AddCartItemRequest, CartItem, CartItemDetail},
+        coupon::{AppliedCoupon, ApplyCouponRequest},
+        order::{AddCartItemRequest, CartItem, CartItemDetail, CartOwner, GuestCartSession},
     },
-    repositories::{CartRepository, ProductRepository},
-    services::CartService,
+    repositories::{
+        CartRepository, CouponRepository, ProductRepository, StoreRepository,
+        StoreSettingsRepository,
+    },
+    services::{CartService, CouponService, CurrencyService},
     state::AppState,
+    utils::public_id::PublicIdKind,
 };
 
 pub fn router() -> Router<AppState> {
     Router::new()
+        .route("/", delete(clear_cart))
+        .route("/guest", post(start_guest_cart))
         .route("/items", post(add_item).get(list_items))
         .route("/items/{product_id}", delete(remove_item))
+        .route("/items/{product_id}/save", post(save_item))
+        .route("/items/{product_id}/restore", post(restore_item))
+        .route("/saved", get(list_saved))
+        .route("/apply-coupon", post(apply_coupon))
+        .route("/coupons/{store_id}", delete(remove_coupon))
+}
+
+/// Issues a token for an anonymous cart. Guests send it in the
+/// `X-Guest-Token` header; signing in or registering with it moves the cart
+/// into the account.
+async fn start_guest_cart(
+    State(state): State<AppState>,
+) -> crate::Result<Json<models::ApiResponse<GuestCartSession>>> {
+    let guest_token = state
+        .public_ids
+        .encode(PublicIdKind::GuestCart, Uuid::new_v4());
+    Ok(Json(models::ApiResponse::new(GuestCartSession {
+        guest_token,
+    })))
 }
 
 async fn add_item(
     State(state): State<AppState>,
-    user: AuthenticatedUser,
+    owner: CartOwner,
     Json(payload): Json<AddCartItemRequest>,
 ) -> crate::Result<Json<models::ApiResponse<CartItem>>> {
     let service = cart_service(&state);
-    let item = service.add_item(user.user_id, payload).await?;
+    let item = service.add_item(owner, payload).await?;
+    Ok(Json(models::ApiResponse::new(item)))
+}
+
+async fn list_saved(
+    State(state): State<AppState>,
+    owner: CartOwner,
+    DisplayCurrency(currency): DisplayCurrency,
+) -> crate::Result<Json<models::ApiResponse<Vec<CartItemDetail>>>> {
+    let service = cart_service(&state);
+    let mut items = service.list_saved(owner).await?;
+    if let Some(currency) = currency {
+        currency_service(&state)
+            .present_cart(&mut items, &currency)
+            .await?;
+    }
+    Ok(Json(models::ApiResponse::new(items)))
+}
+
+async fn save_item(
+    State(state): State<AppState>,
+    owner: CartOwner,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<CartItem>>> {
+    let service = cart_service(&state);
+    let item = service.save_for_later(owner, product_id).await?;
+    Ok(Json(models::ApiResponse::new(item)))
+}
+
+async fn restore_item(
+    State(state): State<AppState>,
+    owner: CartOwner,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<CartItem>>> {
+    let service = cart_service(&state);
+    let item = service.restore_saved(owner, product_id).await?;
     Ok(Json(models::ApiResponse::new(item)))
 }
 
 async fn list_items(
     State(state): State<AppState>,
-    user: AuthenticatedUser,
+    owner: CartOwner,
+    DisplayCurrency(currency): DisplayCurrency,
 ) -> crate::Result<Json<models::ApiResponse<Vec<CartItemDetail>>>> {
     let service = cart_service(&state);
-    let items = service.list_items(user.user_id).await?;
+    let mut items = service.list_items(owner).await?;
+    if let Some(currency) = currency {
+        currency_service(&state)
+            .present_cart(&mut items, &currency)
+            .await?;
+    }
     Ok(Json(models::ApiResponse::new(items)))
 }
 
 async fn remove_item(
     State(state): State<AppState>,
-    user: AuthenticatedUser,
+    owner: CartOwner,
     Path(product_id): Path<Uuid>,
 ) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
     let service = cart_service(&state);
-    service.remove_item(user.user_id, product_id).await?;
+    service.remove_item(owner, product_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn clear_cart(
+    State(state): State<AppState>,
+    owner: CartOwner,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = cart_service(&state);
+    let removed = service.clear(owner).await?;
+    Ok(Json(models::ApiResponse::new(
+        json!({ "removed": removed }),
+    )))
+}
+
+/// Coupons are redeemed at checkout, so only signed-in carts can hold one.
+async fn apply_coupon(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Json(payload): Json<ApplyCouponRequest>,
+) -> crate::Result<Json<models::ApiResponse<AppliedCoupon>>> {
+    let service = coupon_service(&state);
+    let applied = service.apply_coupon(user.user_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(applied)))
+}
+
+async fn remove_coupon(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = coupon_service(&state);
+    service.remove_coupon(user.user_id, store_id).await?;
     Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
 }
 
@@ -56,5 +155,23 @@ fn cart_service(state: &AppState) -> CartService {
     CartService::new(
         CartRepository::new(state.db.clone()),
         ProductRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    )
+    .with_policy(state.cart_policy)
+    .with_reservation_ttl(state.reservation_ttl)
+}
+
+fn coupon_service(state: &AppState) -> CouponService {
+    CouponService::new(
+        CouponRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+        CartRepository::new(state.db.clone()),
+    )
+}
+
+fn currency_service(state: &AppState) -> CurrencyService {
+    CurrencyService::new(
+        StoreSettingsRepository::new(state.db.clone()),
+        state.exchange_rates.clone(),
     )
 }
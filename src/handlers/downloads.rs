@@ -0,0 +1,47 @@
+use axum::{
+    extract::{Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+
+use crate::{
+    repositories::{OrderRepository, ProductFileRepository},
+    services::DownloadService,
+    state::AppState,
+};
+
+/// Signed download links carry their own authorization, so these routes do
+/// not require a session.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/{token}", get(download_file))
+}
+
+async fn download_file(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> crate::Result<Response> {
+    let service = download_service(&state);
+    let (file, data) = service.open(&token).await?;
+
+    let disposition = format!("attachment; filename=\"{}\"", file.file_name);
+    Ok((
+        [
+            (header::CONTENT_TYPE, file.content_type),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::CACHE_CONTROL, "private, no-store".to_string()),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+fn download_service(state: &AppState) -> DownloadService {
+    DownloadService::new(
+        OrderRepository::new(state.db.clone()),
+        ProductFileRepository::new(state.db.clone()),
+        state.private_storage.clone(),
+        state.jwt.clone(),
+    )
+}
@@ -0,0 +1,33 @@
+use axum::{extract::State, routing::post, Json, Router};
+
+use crate::{
+    middleware::auth::MaybeAuthenticatedUser,
+    models::{
+        self,
+        event::{IngestEventsRequest, IngestEventsResponse},
+    },
+    repositories::{EventRepository, ProductRepository, RecentlyViewedRepository, StoreRepository},
+    services::EventService,
+    state::AppState,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", post(ingest_events))
+}
+
+async fn ingest_events(
+    State(state): State<AppState>,
+    MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+    Json(payload): Json<IngestEventsRequest>,
+) -> crate::Result<Json<models::ApiResponse<IngestEventsResponse>>> {
+    let service = EventService::new(
+        EventRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+        ProductRepository::new(state.db.clone()),
+        RecentlyViewedRepository::new(state.db.clone()),
+        state.event_sample_rate,
+    );
+    let user_id = maybe_user.map(|user| user.user_id);
+    let response = service.ingest(user_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(response)))
+}
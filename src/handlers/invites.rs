@@ -0,0 +1,38 @@
+use axum::{
+    extract::{Path, State},
+    routing::post,
+    Json, Router,
+};
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    models::{self, store::StoreAccessGrant},
+    repositories::{AccessGrantRepository, InviteLinkRepository},
+    services::InviteLinkService,
+    state::AppState,
+};
+
+/// Invite links carry the store and access level in their signed token;
+/// opening one only needs a signed-in user.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/{token}", post(redeem_invite))
+}
+
+async fn redeem_invite(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(token): Path<String>,
+) -> crate::Result<Json<models::ApiResponse<StoreAccessGrant>>> {
+    let grant = invite_link_service(&state)
+        .redeem(user.user_id, &token)
+        .await?;
+    Ok(Json(models::ApiResponse::new(grant)))
+}
+
+fn invite_link_service(state: &AppState) -> InviteLinkService {
+    InviteLinkService::new(
+        InviteLinkRepository::new(state.db.clone()),
+        AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+        state.jwt.clone(),
+    )
+}
@@ -1,26 +1,97 @@
 use axum::{
-    extract::{Path, State},
-    routing::post,
+    extract::{Path, Query, State},
+    handler::Handler,
+    routing::{delete, get, patch, post, put},
     Json, Router,
 };
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
 use uuid::Uuid;
 
 use crate::{
-    middleware::{auth::AuthenticatedUser, permissions::ensure_store_permission},
+    error::AppError,
+    middleware::{
+        auth::AuthenticatedUser, idempotency::idempotent, permissions::require_permission,
+    },
     models::{
         self,
+        audit::{self, AuditAction, NewAuditEvent},
         permission::Permission,
-        store::{InviteMemberRequest, StoreAccessGrant},
+        store::{
+            BulkGrantOutcome, BulkGrantRequest, BulkGrantResult, CreateInviteLinkRequest,
+            CreatedInviteLink, GrantStatus, InviteMemberRequest, MemberRole, StoreAccessGrant,
+            StoreMember, StoreRole, StoreRoleRequest, UpdateAccessGrantRequest,
+            UpdateMemberRequest,
+        },
+    },
+    repositories::{
+        AccessGrantRepository, AuditRepository, InviteLinkRepository, MemberRepository,
+        StoreRepository, StoreRoleRepository,
     },
-    repositories::{AccessGrantRepository, MemberRepository},
+    services::{AccessGrantService, AuditService, InviteLinkService, StoreRoleService},
     state::AppState,
 };
 
 pub fn router() -> Router<AppState> {
     Router::new()
-        .route("/{store_id}/invite", post(invite_member))
-        .route("/{store_id}/grant", post(grant_access))
-        .route("/{store_id}/revoke/{user_id}", post(revoke_access))
+        .route(
+            "/{store_id}/invite",
+            post(invite_member)
+                .route_layer(idempotent())
+                .route_layer(require_permission(Permission::InviteMembers)),
+        )
+        .route(
+            "/{store_id}/members/{user_id}",
+            put(update_member)
+                .delete(remove_member)
+                .route_layer(require_permission(Permission::EditPermissions)),
+        )
+        .route(
+            "/{store_id}/roles",
+            get(list_roles.layer(require_permission(Permission::ViewMembers)))
+                .post(create_role.layer(require_permission(Permission::EditPermissions))),
+        )
+        .route(
+            "/{store_id}/roles/{role_id}",
+            put(update_role)
+                .delete(delete_role)
+                .route_layer(require_permission(Permission::EditPermissions)),
+        )
+        .route(
+            "/{store_id}/grant",
+            post(grant_access)
+                .route_layer(idempotent())
+                .route_layer(require_permission(Permission::GrantAccess)),
+        )
+        .route(
+            "/{store_id}/grant-bulk",
+            post(grant_bulk)
+                .route_layer(idempotent())
+                .route_layer(require_permission(Permission::GrantAccess)),
+        )
+        .route(
+            "/{store_id}/grants",
+            get(list_grants).route_layer(require_permission(Permission::GrantAccess)),
+        )
+        .route(
+            "/{store_id}/grants/{grant_id}",
+            patch(update_grant)
+                .route_layer(idempotent())
+                .route_layer(require_permission(Permission::GrantAccess)),
+        )
+        .route(
+            "/{store_id}/revoke/{user_id}",
+            post(revoke_access).route_layer(require_permission(Permission::RevokeAccess)),
+        )
+        .route(
+            "/{store_id}/invite-links",
+            post(create_invite_link).route_layer(require_permission(Permission::GrantAccess)),
+        )
+        .route(
+            "/{store_id}/invite-links/{link_id}",
+            delete(revoke_invite_link).route_layer(require_permission(Permission::RevokeAccess)),
+        )
 }
 
 async fn invite_member(
@@ -28,21 +99,210 @@ async fn invite_member(
     user: AuthenticatedUser,
     Path(store_id): Path<Uuid>,
     Json(payload): Json<InviteMemberRequest>,
-) -> crate::Result<Json<models::ApiResponse<crate::models::store::StoreMember>>> {
-    ensure_store_permission(&state, user.user_id, store_id, Permission::InviteMembers).await?;
-    let repo = MemberRepository::new(state.db.clone());
+) -> crate::Result<Json<models::ApiResponse<StoreMember>>> {
+    ensure_assignable_role(&state, store_id, payload.role, payload.custom_role_id).await?;
+    let repo = MemberRepository::new(state.db.clone()).with_cache(state.cache.clone());
     let member = repo
         .add_member(
             store_id,
             payload.user_id,
             payload.role,
             &payload.permissions,
+            payload.custom_role_id,
             Some(user.user_id),
         )
         .await?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_member",
+        member.id,
+        None::<&StoreMember>,
+        Some(&member),
+    )
+    .await;
+    Ok(Json(models::ApiResponse::new(member)))
+}
+
+async fn update_member(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, member_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateMemberRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreMember>>> {
+    ensure_not_owner(&state, store_id, member_id).await?;
+    ensure_assignable_role(&state, store_id, payload.role, payload.custom_role_id).await?;
+    let repo = MemberRepository::new(state.db.clone()).with_cache(state.cache.clone());
+    let before = repo.find_membership(store_id, member_id).await?;
+    let member = repo
+        .update_member(
+            store_id,
+            member_id,
+            payload.role,
+            &payload.permissions,
+            payload.custom_role_id,
+            user.user_id,
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("Member not found".into()))?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_member",
+        member.id,
+        before.as_ref(),
+        Some(&member),
+    )
+    .await;
     Ok(Json(models::ApiResponse::new(member)))
 }
 
+async fn remove_member(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, member_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    ensure_not_owner(&state, store_id, member_id).await?;
+    let repo = MemberRepository::new(state.db.clone()).with_cache(state.cache.clone());
+    let before = repo.find_membership(store_id, member_id).await?;
+    let removed = repo
+        .remove_member(store_id, member_id, user.user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Member not found".into()))?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_member",
+        removed.id,
+        before.as_ref(),
+        Some(&removed),
+    )
+    .await;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+/// The owner's membership is tied to the store itself and cannot be edited.
+async fn ensure_not_owner(state: &AppState, store_id: Uuid, user_id: Uuid) -> crate::Result<()> {
+    let store = StoreRepository::new(state.db.clone())
+        .find_by_id(store_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+    if store.owner_id == user_id {
+        return Err(AppError::BadRequest(
+            "The store owner's membership cannot be changed".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Store roles can only be held by Custom members, and only in the store
+/// that defines them.
+async fn ensure_assignable_role(
+    state: &AppState,
+    store_id: Uuid,
+    role: MemberRole,
+    custom_role_id: Option<Uuid>,
+) -> crate::Result<()> {
+    let Some(role_id) = custom_role_id else {
+        return Ok(());
+    };
+    if role != MemberRole::Custom {
+        return Err(AppError::Validation(
+            "Only Custom members can hold a store role".into(),
+        ));
+    }
+    StoreRoleRepository::new(state.db.clone())
+        .with_cache(state.cache.clone())
+        .find(store_id, role_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Role not found".into()))?;
+    Ok(())
+}
+
+async fn list_roles(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreRole>>>> {
+    let roles = store_role_service(&state).list(store_id).await?;
+    Ok(Json(models::ApiResponse::new(roles)))
+}
+
+async fn create_role(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<StoreRoleRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreRole>>> {
+    let role = store_role_service(&state)
+        .create(user.user_id, store_id, payload)
+        .await?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_role",
+        role.id,
+        None::<&StoreRole>,
+        Some(&role),
+    )
+    .await;
+    Ok(Json(models::ApiResponse::new(role)))
+}
+
+async fn update_role(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, role_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<StoreRoleRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreRole>>> {
+    let before = StoreRoleRepository::new(state.db.clone())
+        .with_cache(state.cache.clone())
+        .find(store_id, role_id)
+        .await?;
+    let role = store_role_service(&state)
+        .update(user.user_id, store_id, role_id, payload)
+        .await?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_role",
+        role.id,
+        before.as_ref(),
+        Some(&role),
+    )
+    .await;
+    Ok(Json(models::ApiResponse::new(role)))
+}
+
+async fn delete_role(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, role_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let before = StoreRoleRepository::new(state.db.clone())
+        .with_cache(state.cache.clone())
+        .find(store_id, role_id)
+        .await?;
+    store_role_service(&state)
+        .delete(user.user_id, store_id, role_id)
+        .await?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_role",
+        role_id,
+        before.as_ref(),
+        None::<&StoreRole>,
+    )
+    .await;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct GrantAccessRequest {
     user_id: Uuid,
@@ -60,8 +320,7 @@ async fn grant_access(
     Path(store_id): Path<Uuid>,
     Json(payload): Json<GrantAccessRequest>,
 ) -> crate::Result<Json<models::ApiResponse<StoreAccessGrant>>> {
-    ensure_store_permission(&state, user.user_id, store_id, Permission::GrantAccess).await?;
-    let repo = AccessGrantRepository::new(state.db.clone());
+    let repo = AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone());
     let grant = repo
         .grant(
             store_id,
@@ -70,6 +329,104 @@ async fn grant_access(
             payload.access_level,
         )
         .await?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_access_grant",
+        grant.id,
+        None::<&StoreAccessGrant>,
+        Some(&grant),
+    )
+    .await;
+    Ok(Json(models::ApiResponse::new(grant)))
+}
+
+async fn grant_bulk(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<BulkGrantRequest>,
+) -> crate::Result<Json<models::ApiResponse<Vec<BulkGrantResult>>>> {
+    let results = AccessGrantService::new(
+        AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+        MemberRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+    )
+    .grant_bulk(user.user_id, store_id, payload)
+    .await?;
+    for result in &results {
+        if let BulkGrantOutcome::Granted { grant } = &result.outcome {
+            audit_permission_change(
+                &state,
+                user.user_id,
+                store_id,
+                "store_access_grant",
+                grant.id,
+                None,
+                Some(grant),
+            )
+            .await;
+        }
+    }
+    Ok(Json(models::ApiResponse::new(results)))
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct GrantListQuery {
+    status: Option<GrantStatus>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+async fn list_grants(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<GrantListQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreAccessGrant>>>> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let repo = AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone());
+    let grants = repo
+        .list_for_store(store_id, query.status, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(grants)))
+}
+
+async fn update_grant(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, grant_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateAccessGrantRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreAccessGrant>>> {
+    if payload
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(AppError::Validation(
+            "expires_at must be in the future".into(),
+        ));
+    }
+    let repo = AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone());
+    let grant = repo
+        .update(
+            store_id,
+            grant_id,
+            payload.access_level,
+            payload.expires_at,
+            user.user_id,
+        )
+        .await?
+        .ok_or_else(|| AppError::NotFound("Grant not found".into()))?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_access_grant",
+        grant.id,
+        None::<&StoreAccessGrant>,
+        Some(&grant),
+    )
+    .await;
     Ok(Json(models::ApiResponse::new(grant)))
 }
 
@@ -78,11 +435,80 @@ async fn revoke_access(
     user: AuthenticatedUser,
     Path((store_id, revoke_user_id)): Path<(Uuid, Uuid)>,
 ) -> crate::Result<Json<models::ApiResponse<StoreAccessGrant>>> {
-    ensure_store_permission(&state, user.user_id, store_id, Permission::RevokeAccess).await?;
-    let repo = AccessGrantRepository::new(state.db.clone());
+    let repo = AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone());
     let grant = repo
-        .revoke(store_id, revoke_user_id)
+        .revoke(store_id, revoke_user_id, user.user_id)
         .await?
-        .ok_or_else(|| crate::error::AppError::NotFound("Grant not found".into()))?;
+        .ok_or_else(|| AppError::NotFound("Grant not found".into()))?;
+    audit_permission_change(
+        &state,
+        user.user_id,
+        store_id,
+        "store_access_grant",
+        grant.id,
+        None::<&StoreAccessGrant>,
+        Some(&grant),
+    )
+    .await;
     Ok(Json(models::ApiResponse::new(grant)))
 }
+
+async fn create_invite_link(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateInviteLinkRequest>,
+) -> crate::Result<Json<models::ApiResponse<CreatedInviteLink>>> {
+    let link = invite_link_service(&state)
+        .create(user.user_id, store_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(link)))
+}
+
+async fn revoke_invite_link(
+    State(state): State<AppState>,
+    Path((store_id, link_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    invite_link_service(&state)
+        .revoke(store_id, link_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(json!({ "revoked": true }))))
+}
+
+/// Puts a membership, role or grant change in the audit log next to the
+/// store's other sensitive actions. The permission audit log keeps its own,
+/// more detailed entry of the same change.
+async fn audit_permission_change<T: Serialize>(
+    state: &AppState,
+    actor_id: Uuid,
+    store_id: Uuid,
+    entity_type: &'static str,
+    entity_id: Uuid,
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    AuditService::new(AuditRepository::new(state.db.clone()))
+        .record(NewAuditEvent {
+            store_id: Some(store_id),
+            actor_id: Some(actor_id),
+            action: AuditAction::PermissionChange,
+            entity_type,
+            entity_id: Some(entity_id),
+            diff: audit::change(before, after),
+        })
+        .await;
+}
+
+fn invite_link_service(state: &AppState) -> InviteLinkService {
+    InviteLinkService::new(
+        InviteLinkRepository::new(state.db.clone()),
+        AccessGrantRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+        state.jwt.clone(),
+    )
+}
+
+fn store_role_service(state: &AppState) -> StoreRoleService {
+    StoreRoleService::new(
+        StoreRoleRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+    )
+}
@@ -1,29 +1,73 @@
-use axum::{extract::State, routing::get, Json, Router};
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, middleware, routing::get, Json, Router};
 use serde_json::{json, Value};
 
-use crate::{error::AppError, state::AppState};
+use crate::{
+    error::AppError,
+    middleware::access::{require_access, EndpointAccess},
+    services::{
+        readiness_service::{CacheCheck, DatabaseCheck, Readiness},
+        ReadinessService,
+    },
+    state::AppState,
+};
 
+pub mod admin;
 pub mod auth;
 pub mod cart;
+pub mod downloads;
+pub mod events;
+pub mod invites;
 pub mod members;
+pub mod order_groups;
 pub mod orders;
+pub mod payments;
 pub mod products;
 pub mod stores;
 pub mod users;
 
-pub fn api_router() -> Router<AppState> {
+/// The public API. Admin routes additionally pass through `admin_access`,
+/// which can restrict them to operator networks.
+pub fn api_router(admin_access: EndpointAccess) -> Router<AppState> {
     Router::new()
         .route("/health", get(health))
-        .route("/metrics", get(metrics))
+        .route("/healthz", get(health))
+        .route("/readyz", get(ready))
         .nest("/api/v1/auth", auth::router())
         .nest("/api/v1/users", users::router())
         .nest("/api/v1/stores", stores::router())
         .nest("/api/v1/products", products::router())
         .nest("/api/v1/cart", cart::router())
         .nest("/api/v1/orders", orders::router())
+        .nest("/api/v1/order-groups", order_groups::router())
+        .nest("/api/v1/payments", payments::router())
+        .nest("/api/v1/downloads", downloads::router())
+        .nest("/api/v1/events", events::router())
         .nest("/api/v1/members", members::router())
+        .nest("/api/v1/invites", invites::router())
+        .nest(
+            "/api/v1/admin",
+            admin::router().route_layer(middleware::from_fn_with_state(
+                Arc::new(admin_access),
+                require_access,
+            )),
+        )
+}
+
+/// `/metrics` on its own router so it can be served from the public listener
+/// or from a separate internal one.
+pub fn metrics_router(access: EndpointAccess) -> Router<AppState> {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .route_layer(middleware::from_fn_with_state(
+            Arc::new(access),
+            require_access,
+        ))
 }
 
+/// Liveness: answers as long as the process can serve requests, without
+/// touching any dependency. `/health` is kept for existing monitors.
 pub async fn health() -> Json<Value> {
     Json(json!({
         "status": "healthy",
@@ -32,7 +76,25 @@ pub async fn health() -> Json<Value> {
     }))
 }
 
+/// Readiness: pings every dependency and answers 503 while any of them is
+/// down or slower than the readiness timeout, so the instance is taken out
+/// of rotation instead of restarted.
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<Readiness>) {
+    let readiness = ReadinessService::new(state.readiness_timeout)
+        .with_check(Arc::new(DatabaseCheck::new(state.db.clone())))
+        .with_check(Arc::new(CacheCheck::new(state.cache.backend().clone())))
+        .check()
+        .await;
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
 pub async fn metrics(State(state): State<AppState>) -> Result<String, AppError> {
+    state.metrics.sample_pool(&state.db).await;
     state
         .metrics
         .encode()
@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    middleware::auth::AuthenticatedUser,
+    models::{
+        self,
+        order::{OrderGroupDetail, OrderGroupEntry},
+        payment::{PayOrderGroupRequest, Payment},
+    },
+    repositories::{
+        CartRepository, CouponRepository, CreditRepository, FulfillmentRepository,
+        NotificationRepository, OrderRepository, PaymentRepository, ProductRepository,
+        StoreSettingsRepository,
+    },
+    services::{OrderService, PaymentService},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+struct PaginationQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_order_groups))
+        .route("/{group_id}", get(order_group_detail))
+        .route("/{group_id}/pay", post(pay_order_group))
+}
+
+async fn list_order_groups(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<OrderGroupEntry>>>> {
+    let limit = pagination.limit.unwrap_or(20).clamp(1, 50);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let groups = order_service(&state)
+        .list_order_groups(user.user_id, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(groups)))
+}
+
+async fn order_group_detail(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(group_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<OrderGroupDetail>>> {
+    let detail = order_service(&state)
+        .order_group_detail(user.user_id, group_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(detail)))
+}
+
+async fn pay_order_group(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(group_id): Path<Uuid>,
+    payload: Option<Json<PayOrderGroupRequest>>,
+) -> crate::Result<Json<models::ApiResponse<Vec<Payment>>>> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let payments = payment_service(&state)
+        .pay(user.user_id, group_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(payments)))
+}
+
+fn order_service(state: &AppState) -> OrderService {
+    OrderService::new(
+        OrderRepository::new(state.db.clone()),
+        ProductRepository::new(state.db.clone()),
+        CartRepository::new(state.db.clone()),
+        FulfillmentRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+        CreditRepository::new(state.db.clone()),
+        NotificationRepository::new(state.db.clone()),
+        CouponRepository::new(state.db.clone()),
+    )
+}
+
+fn payment_service(state: &AppState) -> PaymentService {
+    PaymentService::new(
+        PaymentRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    )
+    .with_providers(state.payment_providers.clone())
+}
@@ -1,19 +1,42 @@
 use crate::{
-    middleware::auth::AuthenticatedUser,
+    error::AppError,
+    middleware::{
+        auth::AuthenticatedUser, currency::DisplayCurrency, permissions::ensure_member_permission,
+    },
     models::{
         self,
-        order::{CheckoutRequest, CheckoutSummary, Order},
+        invoice::{InvoiceDocument, InvoiceFormat},
+        message::{MessageSender, OrderMessage, PostOrderMessageRequest, UnreadThread},
+        order::{
+            CancelOrderRequest, CheckoutConfirmation, CheckoutPreview, CheckoutRequest,
+            DownloadLink, Order, OrderDetail, OrderListEntry, PaymentStatus,
+            PublicOrderConfirmation,
+        },
+        permission::Permission,
+    },
+    repositories::{
+        CartRepository, CouponRepository, CreditRepository, FulfillmentRepository,
+        InvoiceRepository, NotificationRepository, OrderMessageRepository, OrderRepository,
+        PaymentRepository, ProductFileRepository, ProductRepository, StoreRepository,
+        StoreSettingsRepository,
+    },
+    services::{
+        invoice_service::{render_html, render_pdf},
+        CurrencyService, DownloadService, InvoiceService, OrderMessageService, OrderService,
+        PaymentService, PublicOrderService,
     },
-    repositories::{CartRepository, OrderRepository, ProductRepository},
-    services::OrderService,
     state::AppState,
 };
 use axum::{
-    extract::{Query, State},
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use serde::Deserialize;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 struct PaginationQuery {
@@ -21,27 +44,82 @@ struct PaginationQuery {
     offset: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+struct InvoiceQuery {
+    format: Option<InvoiceFormat>,
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", get(list_orders))
         .route("/checkout", post(checkout))
+        .route("/preview", post(preview))
+        .route("/confirmation/{reference}", get(confirmation))
+        .route("/{order_id}", get(order_detail))
+        .route("/groups/{order_group_id}/invoice", get(group_invoice))
+        .route("/{order_id}/cancel", post(cancel_order))
+        .route("/{order_id}/invoice", get(order_invoice))
+        .route("/{order_id}/downloads", get(list_downloads))
+        .route(
+            "/{order_id}/messages",
+            get(list_messages).post(post_message),
+        )
+        .route("/unread-messages", get(unread_messages))
 }
 
 async fn checkout(
     State(state): State<AppState>,
     user: AuthenticatedUser,
+    DisplayCurrency(currency): DisplayCurrency,
+    Json(payload): Json<CheckoutRequest>,
+) -> crate::Result<Json<models::ApiResponse<CheckoutConfirmation>>> {
+    let service = order_service(&state);
+    let summary = service
+        .checkout_with_currency(user.user_id, payload, currency.as_deref())
+        .await?;
+    let payments = if summary.order_group.payment_status == PaymentStatus::Pending {
+        payment_service(&state)
+            .start(summary.order_group.id)
+            .await?
+    } else {
+        Vec::new()
+    };
+    let confirmation = public_order_service(&state).confirm_checkout(summary, payments);
+    Ok(Json(models::ApiResponse::new(confirmation)))
+}
+
+async fn preview(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    DisplayCurrency(currency): DisplayCurrency,
     Json(payload): Json<CheckoutRequest>,
-) -> crate::Result<Json<models::ApiResponse<CheckoutSummary>>> {
+) -> crate::Result<Json<models::ApiResponse<CheckoutPreview>>> {
     let service = order_service(&state);
-    let summary = service.checkout(user.user_id, payload).await?;
-    Ok(Json(models::ApiResponse::new(summary)))
+    let mut preview = service.preview(user.user_id, payload).await?;
+    if let Some(currency) = currency {
+        currency_service(&state)
+            .present_preview(&mut preview, &currency)
+            .await?;
+    }
+    Ok(Json(models::ApiResponse::new(preview)))
+}
+
+/// Public confirmation page behind the link sent to buyers. Needs no sign-in;
+/// the signed reference is the credential.
+async fn confirmation(
+    State(state): State<AppState>,
+    Path(reference): Path<String>,
+) -> crate::Result<Json<models::ApiResponse<PublicOrderConfirmation>>> {
+    let service = public_order_service(&state);
+    let confirmation = service.confirmation(&reference).await?;
+    Ok(Json(models::ApiResponse::new(confirmation)))
 }
 
 async fn list_orders(
     State(state): State<AppState>,
     user: AuthenticatedUser,
     Query(pagination): Query<PaginationQuery>,
-) -> crate::Result<Json<models::ApiResponse<Vec<Order>>>> {
+) -> crate::Result<Json<models::ApiResponse<Vec<OrderListEntry>>>> {
     let service = order_service(&state);
     let limit = pagination.limit.unwrap_or(20).clamp(1, 50);
     let offset = pagination.offset.unwrap_or(0).max(0);
@@ -49,10 +127,235 @@ async fn list_orders(
     Ok(Json(models::ApiResponse::new(orders)))
 }
 
+async fn order_detail(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<OrderDetail>>> {
+    let detail = order_service(&state).order_detail(order_id).await?;
+    ensure_can_view_order(&state, user.user_id, &detail.order).await?;
+    Ok(Json(models::ApiResponse::new(detail)))
+}
+
+/// Same visibility as the order detail. The invoice is issued, and takes
+/// the store's next invoice number, the first time it is fetched.
+async fn order_invoice(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_id): Path<Uuid>,
+    Query(query): Query<InvoiceQuery>,
+) -> crate::Result<Response> {
+    let service = invoice_service(&state);
+    let order = service.find_order(order_id).await?;
+    ensure_can_view_order(&state, user.user_id, &order).await?;
+    let document = service.order_invoice(order).await?;
+    let filename = document.invoice.invoice_number.clone();
+    Ok(invoice_response(
+        query.format.unwrap_or_default(),
+        &filename,
+        &[document],
+    ))
+}
+
+/// Every store's invoice for one checkout, for the buyer only.
+async fn group_invoice(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_group_id): Path<Uuid>,
+    Query(query): Query<InvoiceQuery>,
+) -> crate::Result<Response> {
+    let documents = invoice_service(&state)
+        .group_invoices(user.user_id, order_group_id)
+        .await?;
+    Ok(invoice_response(
+        query.format.unwrap_or_default(),
+        &format!("invoices-{}", order_group_id),
+        &documents,
+    ))
+}
+
+fn invoice_response(
+    format: InvoiceFormat,
+    filename: &str,
+    documents: &[InvoiceDocument],
+) -> Response {
+    let body = match format {
+        InvoiceFormat::Pdf => Body::from(render_pdf(documents)),
+        InvoiceFormat::Html => Body::from(render_html(documents)),
+    };
+    let disposition = format!("inline; filename=\"{}.{}\"", filename, format.extension());
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// The buyer can always see their order; otherwise it takes `ViewOrders` as
+/// a member of the selling store. Anyone else gets the same 404 as for an
+/// unknown id.
+async fn ensure_can_view_order(
+    state: &AppState,
+    user_id: Uuid,
+    order: &Order,
+) -> crate::Result<()> {
+    if order.user_id == user_id {
+        return Ok(());
+    }
+    ensure_member_permission(state, user_id, order.store_id, Permission::ViewOrders)
+        .await
+        .map_err(|err| match err {
+            AppError::Authorization(_) => AppError::NotFound("Order not found".into()),
+            err => err,
+        })
+}
+
+/// The buyer talks for themselves; anyone else must be store staff with
+/// `permission`, and is told the order does not exist otherwise.
+async fn message_sender(
+    state: &AppState,
+    user_id: Uuid,
+    order: &Order,
+    permission: Permission,
+) -> crate::Result<MessageSender> {
+    if order.user_id == user_id {
+        return Ok(MessageSender::Buyer);
+    }
+    ensure_member_permission(state, user_id, order.store_id, permission)
+        .await
+        .map_err(|err| match err {
+            AppError::Authorization(_) => AppError::NotFound("Order not found".into()),
+            err => err,
+        })?;
+    Ok(MessageSender::Store)
+}
+
+async fn list_messages(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<OrderMessage>>>> {
+    let service = message_service(&state);
+    let order = service.find_order(order_id).await?;
+    message_sender(&state, user.user_id, &order, Permission::ViewOrders).await?;
+    let messages = service.list(&order, user.user_id).await?;
+    Ok(Json(models::ApiResponse::new(messages)))
+}
+
+async fn post_message(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_id): Path<Uuid>,
+    Json(payload): Json<PostOrderMessageRequest>,
+) -> crate::Result<Json<models::ApiResponse<OrderMessage>>> {
+    let service = message_service(&state);
+    let order = service.find_order(order_id).await?;
+    let sender = message_sender(&state, user.user_id, &order, Permission::ProcessOrders).await?;
+    let message = service.post(&order, user.user_id, sender, payload).await?;
+    Ok(Json(models::ApiResponse::new(message)))
+}
+
+/// The buyer's orders with unread store replies.
+async fn unread_messages(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<UnreadThread>>>> {
+    let threads = message_service(&state)
+        .unread_for_buyer(user.user_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(threads)))
+}
+
+async fn cancel_order(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_id): Path<Uuid>,
+    payload: Option<Json<CancelOrderRequest>>,
+) -> crate::Result<Json<models::ApiResponse<Order>>> {
+    let payload = payload.map(|Json(payload)| payload).unwrap_or_default();
+    let order = order_service(&state)
+        .cancel(user.user_id, order_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(order)))
+}
+
+async fn list_downloads(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(order_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<DownloadLink>>>> {
+    let service = download_service(&state);
+    let links = service.links_for_order(user.user_id, order_id).await?;
+    Ok(Json(models::ApiResponse::new(links)))
+}
+
+fn public_order_service(state: &AppState) -> PublicOrderService {
+    PublicOrderService::new(
+        OrderRepository::new(state.db.clone()),
+        state.public_ids.clone(),
+    )
+}
+
 fn order_service(state: &AppState) -> OrderService {
     OrderService::new(
         OrderRepository::new(state.db.clone()),
         ProductRepository::new(state.db.clone()),
         CartRepository::new(state.db.clone()),
+        FulfillmentRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+        CreditRepository::new(state.db.clone()),
+        NotificationRepository::new(state.db.clone()),
+        CouponRepository::new(state.db.clone()),
+    )
+    .with_consolidation(state.shipping_consolidation.clone())
+    .with_tax_rates(state.tax_rates.clone())
+    .with_shipping_rates(state.shipping_rates.clone())
+    .with_exchange_rates(state.exchange_rates.clone())
+    .with_cart_policy(state.cart_policy)
+    .with_reservation_ttl(state.reservation_ttl)
+    .with_events(state.events.clone())
+}
+
+fn message_service(state: &AppState) -> OrderMessageService {
+    OrderMessageService::new(
+        OrderMessageRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        NotificationRepository::new(state.db.clone()),
+    )
+}
+
+fn payment_service(state: &AppState) -> PaymentService {
+    PaymentService::new(
+        PaymentRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    )
+    .with_providers(state.payment_providers.clone())
+}
+
+fn invoice_service(state: &AppState) -> InvoiceService {
+    InvoiceService::new(
+        OrderRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+        InvoiceRepository::new(state.db.clone()),
+    )
+}
+
+fn currency_service(state: &AppState) -> CurrencyService {
+    CurrencyService::new(
+        StoreSettingsRepository::new(state.db.clone()),
+        state.exchange_rates.clone(),
+    )
+}
+
+fn download_service(state: &AppState) -> DownloadService {
+    DownloadService::new(
+        OrderRepository::new(state.db.clone()),
+        ProductFileRepository::new(state.db.clone()),
+        state.private_storage.clone(),
+        state.jwt.clone(),
     )
 }
@@ -0,0 +1,46 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::HeaderMap,
+    routing::post,
+    Json, Router,
+};
+use serde_json::json;
+
+use crate::{
+    error::AppError,
+    models::{self, payment::PaymentProviderKind},
+    repositories::{OrderRepository, PaymentRepository, StoreSettingsRepository},
+    services::PaymentService,
+    state::AppState,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/webhooks/{provider}", post(provider_webhook))
+}
+
+/// Payment updates pushed by a provider. Needs no sign-in; the provider's
+/// signature is verified instead.
+async fn provider_webhook(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let provider: PaymentProviderKind = provider
+        .parse()
+        .map_err(|_| AppError::NotFound("Unknown payment provider".into()))?;
+    payment_service(&state)
+        .handle_webhook(provider, &headers, &body)
+        .await?;
+    Ok(Json(models::ApiResponse::new(json!({ "received": true }))))
+}
+
+fn payment_service(state: &AppState) -> PaymentService {
+    PaymentService::new(
+        PaymentRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    )
+    .with_providers(state.payment_providers.clone())
+}
@@ -1,23 +1,45 @@
 use axum::{
-    extract::{Path, Query, State},
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    handler::Handler,
+    http::header,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use serde::Deserialize;
+use serde_json::json;
 use uuid::Uuid;
 
 use crate::{
+    error::AppError,
     middleware::{
         auth::{AuthenticatedUser, MaybeAuthenticatedUser},
-        permissions::ensure_store_permission,
+        currency::DisplayCurrency,
+        idempotency::idempotent,
+        permissions::{ensure_store_permission, require_member_permission, require_permission},
     },
     models::{
         self,
+        audit::{self, AuditAction, NewAuditEvent},
+        inventory::{AdjustInventoryRequest, InventoryMovement},
         permission::Permission,
-        product::{CreateProductRequest, Product},
+        product::{
+            CompareProductsRequest, CreateProductRequest, Product, ProductComparison, ProductFile,
+            ProductImage, ProductShareCard, RelatedProduct, ReorderProductImagesRequest,
+            UpdateProductRequest,
+        },
+    },
+    repositories::{
+        AuditRepository, InventoryRepository, ProductFileRepository, ProductImageRepository,
+        ProductRepository, RecentlyViewedRepository, RecommendationRepository, StoreRepository,
+        StoreSettingsRepository,
+    },
+    services::{
+        product_file_service::MAX_PRODUCT_FILE_BYTES, product_image_service::MAX_IMAGE_BYTES,
+        AuditService, ComparisonService, CurrencyService, InventoryService, ProductFileService,
+        ProductImageService, ProductService, RecentlyViewedService, RecommendationService,
+        SearchService, ShareCardService,
     },
-    repositories::StoreRepository,
-    services::ProductService,
     state::AppState,
 };
 
@@ -27,10 +49,67 @@ struct PaginationQuery {
     offset: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+struct SuggestQuery {
+    #[serde(default)]
+    q: String,
+}
+
 pub fn router() -> Router<AppState> {
     Router::new()
-        .route("/", post(create_product))
+        .route("/", post(create_product).route_layer(idempotent()))
         .route("/store/{store_id}", get(list_store_products))
+        .route("/compare", post(compare_products))
+        .route("/suggest", get(suggest))
+        .route(
+            "/{product_id}",
+            get(get_product)
+                .patch(update_product.layer(require_permission(Permission::EditProducts)))
+                .delete(archive_product.layer(require_permission(Permission::DeleteProducts))),
+        )
+        .route(
+            "/{product_id}/unarchive",
+            post(unarchive_product).route_layer(require_permission(Permission::DeleteProducts)),
+        )
+        .route(
+            "/{product_id}/duplicate",
+            post(duplicate_product).route_layer(require_permission(Permission::CreateProducts)),
+        )
+        .route("/{product_id}/related", get(related_products))
+        .route("/{product_id}/share-card", get(share_card))
+        .route(
+            "/{product_id}/inventory-history",
+            get(inventory_history).route_layer(require_member_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{product_id}/inventory-adjustments",
+            post(adjust_inventory).route_layer(require_member_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{product_id}/images",
+            post(upload_image.layer(require_permission(Permission::EditProducts)))
+                .get(list_images)
+                .layer(DefaultBodyLimit::max(MAX_IMAGE_BYTES + 64 * 1024)),
+        )
+        .route(
+            "/{product_id}/images/order",
+            put(reorder_images).route_layer(require_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{product_id}/images/{image_id}",
+            delete(delete_image).route_layer(require_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{product_id}/files",
+            post(upload_file)
+                .get(list_files)
+                .layer(DefaultBodyLimit::max(MAX_PRODUCT_FILE_BYTES + 64 * 1024))
+                .route_layer(require_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{product_id}/files/{file_id}",
+            delete(delete_file).route_layer(require_permission(Permission::EditProducts)),
+        )
 }
 
 async fn create_product(
@@ -55,30 +134,387 @@ async fn list_store_products(
     Path(store_id): Path<Uuid>,
     Query(pagination): Query<PaginationQuery>,
     MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+    DisplayCurrency(currency): DisplayCurrency,
 ) -> crate::Result<Json<models::ApiResponse<Vec<Product>>>> {
     let store_repo = StoreRepository::new(state.db.clone());
     let store = store_repo
         .find_by_id(store_id)
         .await?
-        .ok_or_else(|| crate::error::AppError::NotFound("Store not found".into()))?;
+        .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
 
     if store.is_private {
-        let user = maybe_user.ok_or_else(|| {
-            crate::error::AppError::Authentication("Authentication required".into())
-        })?;
+        let user =
+            maybe_user.ok_or_else(|| AppError::Authentication("Authentication required".into()))?;
         ensure_store_permission(&state, user.user_id, store_id, Permission::ViewProducts).await?;
     }
 
     let limit = pagination.limit.unwrap_or(20).clamp(1, 50);
     let offset = pagination.offset.unwrap_or(0).max(0);
     let service = product_service(&state);
-    let products = service.list_by_store(store_id, limit, offset).await?;
+    let mut products = service.list_by_store(store_id, limit, offset).await?;
+    if let Some(currency) = currency {
+        currency_service(&state)
+            .present_products(&mut products, &currency)
+            .await?;
+    }
     Ok(Json(models::ApiResponse::new(products)))
 }
 
+/// Search-as-you-type suggestions across every store the caller can see.
+/// Anonymous results are shared, so they may be cached by any intermediary.
+async fn suggest(
+    State(state): State<AppState>,
+    Query(query): Query<SuggestQuery>,
+    MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+) -> crate::Result<impl IntoResponse> {
+    let viewer_id = maybe_user.map(|user| user.user_id);
+    let service = SearchService::new(
+        ProductRepository::new(state.db.clone()),
+        state.suggestions.clone(),
+    );
+    let suggestions = service.suggest(&query.q, viewer_id).await?;
+
+    let scope = if viewer_id.is_some() {
+        "private"
+    } else {
+        "public"
+    };
+    let cache_control = format!("{}, max-age={}", scope, state.suggestions.ttl().as_secs());
+    Ok((
+        [(header::CACHE_CONTROL, cache_control)],
+        Json(models::ApiResponse::new(suggestions)),
+    ))
+}
+
+/// Product detail page. Signed-in viewers get the product added to their
+/// recently-viewed history.
+async fn get_product(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+    DisplayCurrency(currency): DisplayCurrency,
+) -> crate::Result<Json<models::ApiResponse<Product>>> {
+    let service = product_service(&state);
+    let mut product = service.get_product(product_id).await?;
+    ensure_product_visible(&state, &product, maybe_user.as_ref()).await?;
+    if let Some(currency) = currency {
+        currency_service(&state)
+            .present_products(std::slice::from_mut(&mut product), &currency)
+            .await?;
+    }
+
+    if let Some(user) = maybe_user {
+        let recent = RecentlyViewedService::new(RecentlyViewedRepository::new(state.db.clone()));
+        if let Err(err) = recent.record_view(user.user_id, product.id).await {
+            tracing::warn!(product_id = %product.id, error = %err, "failed to record product view");
+        }
+    }
+
+    Ok(Json(models::ApiResponse::new(product)))
+}
+
+/// Partial update; omitted fields keep their current values. Stock changes
+/// are recorded in the inventory ledger as adjustments.
+/// Price changes are recorded in the audit log.
+async fn update_product(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<UpdateProductRequest>,
+) -> crate::Result<Json<models::ApiResponse<Product>>> {
+    let service = product_service(&state);
+    let previous_price = match payload.price {
+        Some(_) => Some(service.get_product(product_id).await?.price),
+        None => None,
+    };
+    let product = service
+        .update_product(product_id, user.user_id, payload)
+        .await?;
+    if let Some(previous_price) = previous_price.filter(|price| *price != product.price) {
+        AuditService::new(AuditRepository::new(state.db.clone()))
+            .record(NewAuditEvent {
+                store_id: Some(product.store_id),
+                actor_id: Some(user.user_id),
+                action: AuditAction::PriceChange,
+                entity_type: "product",
+                entity_id: Some(product.id),
+                diff: json!({ "price": audit::change(previous_price, product.price) }),
+            })
+            .await;
+    }
+    Ok(Json(models::ApiResponse::new(product)))
+}
+
+/// Cross-sell recommendations for a product page, limited to products the
+/// caller can see.
+async fn related_products(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<RelatedProduct>>>> {
+    let product = product_service(&state).get_product(product_id).await?;
+    ensure_product_visible(&state, &product, maybe_user.as_ref()).await?;
+
+    let service = RecommendationService::new(RecommendationRepository::new(state.db.clone()));
+    let related = service
+        .related(product.id, maybe_user.map(|user| user.user_id))
+        .await?;
+    Ok(Json(models::ApiResponse::new(related)))
+}
+
+/// Hidden products are reported as missing rather than forbidden.
+async fn ensure_product_visible(
+    state: &AppState,
+    product: &Product,
+    maybe_user: Option<&AuthenticatedUser>,
+) -> crate::Result<()> {
+    let store = StoreRepository::new(state.db.clone())
+        .find_by_id(product.store_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+    let required = if !product.is_active || product.is_archived() {
+        Some(Permission::EditProducts)
+    } else if store.is_private {
+        Some(Permission::ViewProducts)
+    } else {
+        None
+    };
+    if let Some(permission) = required {
+        let user = maybe_user.ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+        ensure_store_permission(state, user.user_id, store.id, permission)
+            .await
+            .map_err(|_| AppError::NotFound("Product not found".into()))?;
+    }
+    Ok(())
+}
+
+/// Soft delete: the product leaves listings and carts but remains resolvable
+/// for the order items that reference it.
+async fn archive_product(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = product_service(&state);
+    service.archive_product(product_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn unarchive_product(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Product>>> {
+    let service = product_service(&state);
+    let product = service.unarchive_product(product_id).await?;
+    Ok(Json(models::ApiResponse::new(product)))
+}
+
+async fn duplicate_product(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Product>>> {
+    let service = product_service(&state);
+    let copy = service.duplicate_product(product_id).await?;
+    image_service(&state)
+        .copy_gallery(product_id, copy.id)
+        .await?;
+    let copy = service.get_product(copy.id).await?;
+    Ok(Json(models::ApiResponse::new(copy)))
+}
+
+async fn share_card(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<ProductShareCard>>> {
+    let service = ShareCardService::new(
+        ProductRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    );
+    let card = service.product_card(product_id).await?;
+    Ok(Json(models::ApiResponse::new(card)))
+}
+
+async fn compare_products(
+    State(state): State<AppState>,
+    Json(payload): Json<CompareProductsRequest>,
+) -> crate::Result<Json<models::ApiResponse<ProductComparison>>> {
+    let service = ComparisonService::new(
+        ProductRepository::new(state.db.clone()),
+        ProductImageRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    );
+    let comparison = service.compare(payload).await?;
+    Ok(Json(models::ApiResponse::new(comparison)))
+}
+
+async fn upload_image(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> crate::Result<Json<models::ApiResponse<ProductImage>>> {
+    let service = image_service(&state);
+
+    let mut data = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| AppError::BadRequest(err.body_text()))?
+    {
+        if field.name() == Some("file") {
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|err| AppError::BadRequest(err.body_text()))?;
+            data = Some(bytes);
+            break;
+        }
+    }
+    let data = data.ok_or_else(|| AppError::BadRequest("Missing 'file' field".into()))?;
+
+    let image = service.upload(product_id, data).await?;
+    Ok(Json(models::ApiResponse::new(image)))
+}
+
+async fn list_images(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<ProductImage>>>> {
+    let service = image_service(&state);
+    let images = service.list(product_id).await?;
+    Ok(Json(models::ApiResponse::new(images)))
+}
+
+async fn reorder_images(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<ReorderProductImagesRequest>,
+) -> crate::Result<Json<models::ApiResponse<Vec<ProductImage>>>> {
+    let service = image_service(&state);
+    let images = service.reorder(product_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(images)))
+}
+
+async fn delete_image(
+    State(state): State<AppState>,
+    Path((product_id, image_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = image_service(&state);
+    service.delete(product_id, image_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn upload_file(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    mut multipart: Multipart,
+) -> crate::Result<Json<models::ApiResponse<ProductFile>>> {
+    let service = file_service(&state);
+
+    let mut upload = None;
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| AppError::BadRequest(err.body_text()))?
+    {
+        if field.name() == Some("file") {
+            let file_name = field.file_name().unwrap_or_default().to_string();
+            let content_type = field
+                .content_type()
+                .unwrap_or("application/octet-stream")
+                .to_string();
+            let bytes = field
+                .bytes()
+                .await
+                .map_err(|err| AppError::BadRequest(err.body_text()))?;
+            upload = Some((file_name, content_type, bytes));
+            break;
+        }
+    }
+    let (file_name, content_type, data) =
+        upload.ok_or_else(|| AppError::BadRequest("Missing 'file' field".into()))?;
+
+    let file = service
+        .upload(product_id, &file_name, &content_type, data)
+        .await?;
+    Ok(Json(models::ApiResponse::new(file)))
+}
+
+async fn list_files(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<ProductFile>>>> {
+    let service = file_service(&state);
+    let files = service.list(product_id).await?;
+    Ok(Json(models::ApiResponse::new(files)))
+}
+
+async fn delete_file(
+    State(state): State<AppState>,
+    Path((product_id, file_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = file_service(&state);
+    service.delete(product_id, file_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn inventory_history(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<InventoryMovement>>>> {
+    let service = inventory_service(&state);
+    let limit = pagination.limit.unwrap_or(20).clamp(1, 50);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let movements = service.history(product_id, limit, offset).await?;
+    Ok(Json(models::ApiResponse::new(movements)))
+}
+
+async fn adjust_inventory(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(product_id): Path<Uuid>,
+    Json(payload): Json<AdjustInventoryRequest>,
+) -> crate::Result<Json<models::ApiResponse<InventoryMovement>>> {
+    let service = inventory_service(&state);
+    let movement = service.adjust(user.user_id, product_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(movement)))
+}
+
 fn product_service(state: &AppState) -> ProductService {
     ProductService::new(
-        crate::repositories::ProductRepository::new(state.db.clone()),
+        ProductRepository::new(state.db.clone()).with_cache(state.cache.clone()),
         StoreRepository::new(state.db.clone()),
+        ProductImageRepository::new(state.db.clone()),
+    )
+}
+
+fn currency_service(state: &AppState) -> CurrencyService {
+    CurrencyService::new(
+        StoreSettingsRepository::new(state.db.clone()),
+        state.exchange_rates.clone(),
+    )
+}
+
+fn image_service(state: &AppState) -> ProductImageService {
+    ProductImageService::new(
+        ProductRepository::new(state.db.clone()),
+        ProductImageRepository::new(state.db.clone()),
+        state.storage.clone(),
+    )
+}
+
+fn file_service(state: &AppState) -> ProductFileService {
+    ProductFileService::new(
+        ProductRepository::new(state.db.clone()),
+        ProductFileRepository::new(state.db.clone()),
+        state.private_storage.clone(),
+    )
+}
+
+fn inventory_service(state: &AppState) -> InventoryService {
+    InventoryService::new(
+        ProductRepository::new(state.db.clone()),
+        InventoryRepository::new(state.db.clone()),
     )
 }
@@ -1,20 +1,66 @@
 use axum::{
-    extract::{Path, Query, State},
-    routing::{get, post},
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    handler::Handler,
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post},
     Json, Router,
 };
+use chrono::NaiveDate;
 use serde::Deserialize;
+use serde_json::json;
 use uuid::Uuid;
 
 use crate::{
-    middleware::{auth::AuthenticatedUser, permissions::ensure_store_permission},
+    error::AppError,
+    imports::ImportReport,
+    middleware::{
+        auth::{AuthenticatedUser, MaybeAuthenticatedUser},
+        permissions::{
+            ensure_member_permission, ensure_store_permission, require_member_permission,
+            require_permission,
+        },
+    },
     models::{
         self,
-        permission::Permission,
-        store::{CreateStoreRequest, Store, StoreAnalyticsResponse, StoreMember},
+        audit::{self, AuditAction, AuditEvent, AuditEventFilter, NewAuditEvent},
+        coupon::{Coupon, CreateCouponRequest},
+        credit::{IssueCreditRequest, StoreCreditEntry},
+        event::StoreFunnel,
+        inventory::StoreInventoryAnalytics,
+        message::UnreadThread,
+        notification::{CreateNotificationRuleRequest, NotificationRule},
+        order::{
+            BulkOrderStatusRequest, BulkOrderStatusResponse, CreateShipmentRequest, Order,
+            OrderListEntry, OrderStatus, PaymentStatus, Shipment, StoreOrderDetail,
+            StoreOrderFilter, UpdateOrderStatusRequest, UpdateShipmentRequest,
+        },
+        payment::{Payment, PaymentAttemptStatus},
+        permission::{Permission, PermissionAuditEntry, StorePermissions},
+        product::ExportFormat,
+        shipping::{CreateShippingRuleRequest, ShippingRule},
+        store::{
+            AnalyticsComparison, CheckoutField, CreateAppealRequest, CreateCheckoutFieldRequest,
+            CreateStoreRequest, Store, StoreAnalyticsParams, StoreAnalyticsResponse, StoreAppeal,
+            StoreMember, StoreSettings, TaxReportPeriod, UpdateStoreSettingsRequest,
+        },
+        webhook::{CreateWebhookRequest, CreatedWebhook, Webhook, WebhookDelivery, WebhookPing},
+    },
+    repositories::{
+        AnalyticsRepository, AuditRepository, CartRepository, CouponRepository, CreditRepository,
+        FulfillmentRepository, InventoryRepository, MemberRepository, ModerationRepository,
+        NotificationRepository, OrderMessageRepository, OrderRepository, PaymentRepository,
+        PermissionAuditRepository, ProductImageRepository, ProductRepository, StoreRepository,
+        StoreSettingsRepository, WebhookRepository,
+    },
+    services::{
+        analytics_service::{encode_analytics_csv, encode_tax_report_csv},
+        AnalyticsService, AuditService, CouponService, CreditService, InventoryService,
+        ModerationService, NotificationService, OrderMessageService, OrderService,
+        OrderStatusService, PaymentService, PermissionService, ProductService, ShippingService,
+        StoreService, StoreSettingsService, WebhookService,
     },
-    repositories::{AnalyticsRepository, MemberRepository, StoreRepository},
-    services::{AnalyticsService, StoreService},
     state::AppState,
 };
 
@@ -24,17 +70,244 @@ struct PaginationQuery {
     offset: Option<i64>,
 }
 
+#[derive(Debug, Deserialize)]
+struct StoreOrdersQuery {
+    status: Option<OrderStatus>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    format: Option<ExportFormat>,
+}
+
+/// Accounting export of the orders placed on the days `from` to `to`.
+#[derive(Debug, Deserialize)]
+struct OrderExportQuery {
+    format: Option<ExportFormat>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ImportQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
 #[derive(Debug, Deserialize)]
 struct AnalyticsQuery {
     days: Option<i64>,
     top: Option<i64>,
+    /// Count only orders with this payment status; paid orders by default.
+    payment_status: Option<PaymentStatus>,
+    /// `previous_period` or `previous_year` to compare the summary against.
+    compare: Option<AnalyticsComparison>,
+    /// Skip the analytics cache and recompute the report.
+    #[serde(default)]
+    refresh: bool,
 }
 
+impl AnalyticsQuery {
+    fn params(&self) -> StoreAnalyticsParams {
+        StoreAnalyticsParams {
+            timeframe_days: self.days.unwrap_or(30).clamp(1, 365),
+            top_products: self.top.unwrap_or(5).clamp(1, 50),
+            payment_status: self.payment_status.unwrap_or(PaymentStatus::Paid),
+            compare: self.compare,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TaxReportQuery {
+    period: Option<TaxReportPeriod>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+}
+
+const MAX_IMPORT_BYTES: usize = 10 * 1024 * 1024;
+
 pub fn router() -> Router<AppState> {
     Router::new()
         .route("/", post(create_store).get(list_stores))
-        .route("/{store_id}/members", get(list_members))
-        .route("/{store_id}/analytics", get(store_analytics))
+        .route("/{store_id}/my-permissions", get(my_permissions))
+        .route("/{store_id}/audit", get(permission_audit_log))
+        .route("/{store_id}/audit-events", get(audit_events))
+        .route(
+            "/{store_id}/members",
+            get(list_members).route_layer(require_permission(Permission::ViewMembers)),
+        )
+        .route(
+            "/{store_id}/orders",
+            get(list_store_orders).route_layer(require_member_permission(Permission::ViewOrders)),
+        )
+        .route(
+            "/{store_id}/orders/export",
+            get(export_orders)
+                .route_layer(require_member_permission(Permission::ExportReports))
+                .route_layer(require_member_permission(Permission::ViewOrders)),
+        )
+        .route("/{store_id}/orders/bulk-status", post(bulk_order_status))
+        .route(
+            "/{store_id}/orders/{order_id}",
+            get(store_order_detail).route_layer(require_member_permission(Permission::ViewOrders)),
+        )
+        .route(
+            "/{store_id}/unread-messages",
+            get(unread_messages).route_layer(require_member_permission(Permission::ViewOrders)),
+        )
+        .route(
+            "/{store_id}/orders/{order_id}/status",
+            post(update_order_status),
+        )
+        .route(
+            "/{store_id}/orders/{order_id}/shipments",
+            post(create_shipment).route_layer(require_member_permission(Permission::ProcessOrders)),
+        )
+        .route(
+            "/{store_id}/orders/{order_id}/shipments/{shipment_id}",
+            patch(update_shipment)
+                .route_layer(require_member_permission(Permission::ProcessOrders)),
+        )
+        .route(
+            "/{store_id}/orders/{order_id}/payment/capture",
+            post(capture_payment).route_layer(require_member_permission(Permission::ProcessOrders)),
+        )
+        .route(
+            "/{store_id}/orders/{order_id}/payment/refund",
+            post(refund_payment).route_layer(require_member_permission(Permission::CancelOrders)),
+        )
+        .route(
+            "/{store_id}/products/import",
+            post(import_products)
+                .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+                .route_layer(require_permission(Permission::CreateProducts))
+                .route_layer(require_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{store_id}/products/export",
+            get(export_products).route_layer(require_member_permission(Permission::ExportReports)),
+        )
+        .route(
+            "/{store_id}/prices/import",
+            post(import_prices)
+                .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+                .route_layer(require_member_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{store_id}/inventory/import",
+            post(import_inventory)
+                .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+                .route_layer(require_member_permission(Permission::EditProducts)),
+        )
+        .route(
+            "/{store_id}/members/import",
+            post(import_members)
+                .layer(DefaultBodyLimit::max(MAX_IMPORT_BYTES))
+                .route_layer(require_member_permission(Permission::InviteMembers))
+                .route_layer(require_member_permission(Permission::EditPermissions)),
+        )
+        .route(
+            "/{store_id}/coupons",
+            get(list_coupons)
+                .post(create_coupon)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/coupons/{coupon_id}",
+            delete(deactivate_coupon).route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/credit",
+            post(issue_credit).route_layer(require_member_permission(Permission::IssueCredit)),
+        )
+        .route(
+            "/{store_id}/credit/{user_id}",
+            get(credit_ledger).route_layer(require_member_permission(Permission::ViewOrders)),
+        )
+        .route(
+            "/{store_id}/analytics",
+            get(store_analytics).route_layer(require_permission(Permission::ViewStats)),
+        )
+        .route(
+            "/{store_id}/analytics/export",
+            get(export_analytics)
+                .route_layer(require_member_permission(Permission::ExportReports))
+                .route_layer(require_permission(Permission::ViewStats)),
+        )
+        .route(
+            "/{store_id}/analytics/inventory",
+            get(store_inventory).route_layer(require_permission(Permission::ViewStats)),
+        )
+        .route(
+            "/{store_id}/analytics/funnel",
+            get(store_funnel).route_layer(require_permission(Permission::ViewStats)),
+        )
+        .route(
+            "/{store_id}/reports/tax",
+            get(tax_report).route_layer(require_member_permission(Permission::ExportReports)),
+        )
+        .route("/{store_id}/appeals", post(submit_appeal).get(list_appeals))
+        .route(
+            "/{store_id}/settings",
+            get(get_settings)
+                .put(update_settings.layer(require_permission(Permission::ManageSettings))),
+        )
+        .route(
+            "/{store_id}/checkout-fields",
+            get(list_checkout_fields)
+                .post(add_checkout_field.layer(require_permission(Permission::ManageSettings))),
+        )
+        .route(
+            "/{store_id}/checkout-fields/{field_id}",
+            delete(remove_checkout_field)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/shipping-rules",
+            get(list_shipping_rules)
+                .post(add_shipping_rule)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/shipping-rules/{rule_id}",
+            delete(remove_shipping_rule)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/notification-rules",
+            get(list_notification_rules)
+                .post(add_notification_rule)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/notification-rules/{rule_id}",
+            delete(remove_notification_rule)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/webhooks",
+            get(list_webhooks)
+                .post(create_webhook)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/webhooks/{webhook_id}",
+            delete(remove_webhook).route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/webhooks/{webhook_id}/test",
+            post(test_webhook).route_layer(require_permission(Permission::ManageSettings)),
+        )
+        .route(
+            "/{store_id}/webhooks/{webhook_id}/deliveries",
+            get(list_webhook_deliveries)
+                .route_layer(require_permission(Permission::ManageSettings)),
+        )
 }
 
 async fn create_store(
@@ -60,36 +333,660 @@ async fn list_stores(
 
 async fn list_members(
     State(state): State<AppState>,
-    user: AuthenticatedUser,
     Path(store_id): Path<Uuid>,
 ) -> crate::Result<Json<models::ApiResponse<Vec<StoreMember>>>> {
-    ensure_store_permission(&state, user.user_id, store_id, Permission::ViewMembers).await?;
     let service = store_service(&state);
     let members = service.list_members(store_id).await?;
     Ok(Json(models::ApiResponse::new(members)))
 }
 
-async fn store_analytics(
+/// What the caller may do in the store, so clients can show only the
+/// actions that will succeed.
+async fn my_permissions(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<StorePermissions>>> {
+    let permissions = PermissionService::new(state.db.clone())
+        .with_cache(state.cache.clone())
+        .effective_permissions(user.user_id, store_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(permissions)))
+}
+
+/// Membership, grant and role changes in the store. Owner only.
+async fn permission_audit_log(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<PermissionAuditEntry>>>> {
+    PermissionService::new(state.db.clone())
+        .ensure_store_owner(user.user_id, store_id)
+        .await?;
+    let limit = pagination.limit.unwrap_or(50).clamp(1, 100);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let entries = PermissionAuditRepository::new(state.db.clone())
+        .list_for_store(store_id, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(entries)))
+}
+
+/// Sensitive actions taken in the store, such as price and order status
+/// changes and refunds. Owner only.
+async fn audit_events(
     State(state): State<AppState>,
     user: AuthenticatedUser,
     Path(store_id): Path<Uuid>,
+    Query(filter): Query<AuditEventFilter>,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<AuditEvent>>>> {
+    PermissionService::new(state.db.clone())
+        .ensure_store_owner(user.user_id, store_id)
+        .await?;
+    let limit = pagination.limit.unwrap_or(50).clamp(1, 100);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let events = audit_service(&state)
+        .list_for_store(store_id, &filter, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(events)))
+}
+
+async fn store_analytics(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
     Query(query): Query<AnalyticsQuery>,
 ) -> crate::Result<Json<models::ApiResponse<StoreAnalyticsResponse>>> {
-    ensure_store_permission(&state, user.user_id, store_id, Permission::ViewStats).await?;
+    let service = analytics_service(&state);
+    let analytics = service
+        .store_analytics(store_id, query.params(), query.refresh)
+        .await?;
 
-    let days = query.days.unwrap_or(30).clamp(1, 180);
-    let top = query.top.unwrap_or(5).clamp(1, 50);
+    Ok(Json(models::ApiResponse::new(analytics)))
+}
 
+/// The analytics report as a CSV download.
+async fn export_analytics(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<AnalyticsQuery>,
+) -> crate::Result<Response> {
     let service = analytics_service(&state);
-    let analytics = service.store_analytics(store_id, days, top).await?;
+    let analytics = service
+        .store_analytics(store_id, query.params(), query.refresh)
+        .await?;
 
-    Ok(Json(models::ApiResponse::new(analytics)))
+    Ok(attachment(
+        "text/csv; charset=utf-8",
+        &format!("analytics-{}.csv", store_id),
+        Body::from(encode_analytics_csv(&analytics)?),
+    ))
+}
+
+async fn store_funnel(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<AnalyticsQuery>,
+) -> crate::Result<Json<models::ApiResponse<StoreFunnel>>> {
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+    let service = analytics_service(&state);
+    let funnel = service.store_funnel(store_id, days).await?;
+    Ok(Json(models::ApiResponse::new(funnel)))
+}
+
+async fn store_inventory(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<AnalyticsQuery>,
+) -> crate::Result<Json<models::ApiResponse<StoreInventoryAnalytics>>> {
+    let days = query.days.unwrap_or(30).clamp(1, 365);
+    let service = analytics_service(&state);
+    let inventory = service.store_inventory(store_id, days).await?;
+    Ok(Json(models::ApiResponse::new(inventory)))
+}
+
+/// Tax collected per period, jurisdiction and rate, as CSV for filings.
+async fn tax_report(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<TaxReportQuery>,
+) -> crate::Result<Response> {
+    let service = analytics_service(&state);
+    let rows = service
+        .tax_report(
+            store_id,
+            query.period.unwrap_or_default(),
+            query.from,
+            query.to,
+        )
+        .await?;
+
+    Ok(attachment(
+        "text/csv; charset=utf-8",
+        &format!("tax-{}.csv", store_id),
+        Body::from(encode_tax_report_csv(&rows)?),
+    ))
+}
+
+async fn submit_appeal(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateAppealRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreAppeal>>> {
+    let service = moderation_service(&state);
+    let appeal = service
+        .submit_appeal(user.user_id, store_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(appeal)))
+}
+
+async fn list_appeals(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreAppeal>>>> {
+    let service = moderation_service(&state);
+    let appeals = service.list_store_appeals(user.user_id, store_id).await?;
+    Ok(Json(models::ApiResponse::new(appeals)))
+}
+
+async fn list_store_orders(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<StoreOrdersQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<OrderListEntry>>>> {
+    let limit = query.limit.unwrap_or(20).clamp(1, 50);
+    let offset = query.offset.unwrap_or(0).max(0);
+    let filter = StoreOrderFilter {
+        status: query.status,
+        from: query.from,
+        to: query.to,
+    };
+    let service = order_service(&state);
+    let orders = service
+        .list_store_orders(store_id, &filter, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(orders)))
+}
+
+async fn store_order_detail(
+    State(state): State<AppState>,
+    Path((store_id, order_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<StoreOrderDetail>>> {
+    let service = order_service(&state);
+    let detail = service.store_order_detail(store_id, order_id).await?;
+    Ok(Json(models::ApiResponse::new(detail)))
+}
+
+async fn bulk_order_status(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<BulkOrderStatusRequest>,
+) -> crate::Result<Json<models::ApiResponse<BulkOrderStatusResponse>>> {
+    let permission = if payload.status == OrderStatus::Cancelled {
+        Permission::CancelOrders
+    } else {
+        Permission::ProcessOrders
+    };
+    ensure_member_permission(&state, user.user_id, store_id, permission).await?;
+    let service = order_status_service(&state);
+    let response = service
+        .bulk_transition(user.user_id, store_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(response)))
+}
+
+async fn update_order_status(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, order_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateOrderStatusRequest>,
+) -> crate::Result<Json<models::ApiResponse<Order>>> {
+    let permission = if payload.status == OrderStatus::Cancelled {
+        Permission::CancelOrders
+    } else {
+        Permission::ProcessOrders
+    };
+    ensure_member_permission(&state, user.user_id, store_id, permission).await?;
+    let service = order_status_service(&state);
+    let order = service
+        .update_status(user.user_id, store_id, order_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(order)))
+}
+
+/// Orders of the store with buyer messages the caller has not read.
+async fn unread_messages(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<UnreadThread>>>> {
+    let threads = message_service(&state)
+        .unread_for_store(store_id, user.user_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(threads)))
+}
+
+async fn capture_payment(
+    State(state): State<AppState>,
+    Path((store_id, order_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<Payment>>> {
+    let payment = payment_service(&state)
+        .capture_order(store_id, order_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(payment)))
+}
+
+async fn refund_payment(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, order_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<Payment>>> {
+    let payment = payment_service(&state)
+        .refund_order(store_id, order_id)
+        .await?;
+    audit_service(&state)
+        .record(NewAuditEvent {
+            store_id: Some(store_id),
+            actor_id: Some(user.user_id),
+            action: AuditAction::Refund,
+            entity_type: "payment",
+            entity_id: Some(payment.id),
+            diff: json!({
+                "order_id": order_id,
+                "amount": payment.amount,
+                "currency": payment.currency,
+                "status": audit::change(PaymentAttemptStatus::Captured, payment.status),
+            }),
+        })
+        .await;
+    Ok(Json(models::ApiResponse::new(payment)))
+}
+
+async fn create_shipment(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((store_id, order_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<CreateShipmentRequest>,
+) -> crate::Result<Json<models::ApiResponse<Shipment>>> {
+    let service = order_status_service(&state);
+    let shipment = service
+        .ship(user.user_id, store_id, order_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(shipment)))
+}
+
+async fn update_shipment(
+    State(state): State<AppState>,
+    Path((store_id, order_id, shipment_id)): Path<(Uuid, Uuid, Uuid)>,
+    Json(payload): Json<UpdateShipmentRequest>,
+) -> crate::Result<Json<models::ApiResponse<Shipment>>> {
+    let service = order_status_service(&state);
+    let shipment = service
+        .update_shipment(store_id, order_id, shipment_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(shipment)))
+}
+
+async fn import_products(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> crate::Result<Json<models::ApiResponse<ImportReport>>> {
+    let data = read_csv_upload(multipart).await?;
+
+    let service = product_service(&state);
+    let report = service.import_csv(store_id, &data, query.dry_run).await?;
+    Ok(Json(models::ApiResponse::new(report)))
+}
+
+async fn import_prices(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> crate::Result<Json<models::ApiResponse<ImportReport>>> {
+    let data = read_csv_upload(multipart).await?;
+
+    let service = product_service(&state);
+    let report = service
+        .import_prices(store_id, &data, query.dry_run)
+        .await?;
+    Ok(Json(models::ApiResponse::new(report)))
+}
+
+async fn import_inventory(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> crate::Result<Json<models::ApiResponse<ImportReport>>> {
+    let data = read_csv_upload(multipart).await?;
+
+    let service = InventoryService::new(
+        ProductRepository::new(state.db.clone()),
+        InventoryRepository::new(state.db.clone()),
+    );
+    let report = service
+        .import_stock(user.user_id, store_id, &data, query.dry_run)
+        .await?;
+    Ok(Json(models::ApiResponse::new(report)))
+}
+
+async fn import_members(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<ImportQuery>,
+    multipart: Multipart,
+) -> crate::Result<Json<models::ApiResponse<ImportReport>>> {
+    let data = read_csv_upload(multipart).await?;
+
+    let service = store_service(&state);
+    let report = service
+        .import_members(user.user_id, store_id, &data, query.dry_run)
+        .await?;
+    Ok(Json(models::ApiResponse::new(report)))
+}
+
+/// Reads the `file` field of a multipart CSV upload.
+async fn read_csv_upload(mut multipart: Multipart) -> crate::Result<Bytes> {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| AppError::BadRequest(err.body_text()))?
+    {
+        if field.name() == Some("file") {
+            return field
+                .bytes()
+                .await
+                .map_err(|err| AppError::BadRequest(err.body_text()));
+        }
+    }
+    Err(AppError::BadRequest("Missing 'file' field".into()))
+}
+
+async fn export_products(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> crate::Result<Response> {
+    let format = query.format.unwrap_or_default();
+    let service = product_service(&state);
+    let stream = service.export_catalog(store_id, format).await?;
+    Ok(attachment(
+        format.content_type(),
+        &format!("products-{}.{}", store_id, format.extension()),
+        Body::from_stream(stream),
+    ))
+}
+
+async fn export_orders(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Query(query): Query<OrderExportQuery>,
+) -> crate::Result<Response> {
+    let format = query.format.unwrap_or_default();
+    let stream =
+        order_service(&state).export_store_orders(store_id, query.from, query.to, format)?;
+    Ok(attachment(
+        format.content_type(),
+        &format!("orders-{}.{}", store_id, format.extension()),
+        Body::from_stream(stream),
+    ))
+}
+
+/// File download response. Streamed bodies are sent chunked and still go
+/// through response compression.
+fn attachment(content_type: &str, filename: &str, body: Body) -> Response {
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+async fn list_coupons(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<Coupon>>>> {
+    let service = coupon_service(&state);
+    let coupons = service.list_coupons(store_id).await?;
+    Ok(Json(models::ApiResponse::new(coupons)))
+}
+
+async fn create_coupon(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateCouponRequest>,
+) -> crate::Result<Json<models::ApiResponse<Coupon>>> {
+    let service = coupon_service(&state);
+    let coupon = service.create_coupon(store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(coupon)))
+}
+
+/// Coupons are deactivated rather than deleted so redemptions keep their
+/// history.
+async fn deactivate_coupon(
+    State(state): State<AppState>,
+    Path((store_id, coupon_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = coupon_service(&state);
+    service.deactivate_coupon(store_id, coupon_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn issue_credit(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<IssueCreditRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreCreditEntry>>> {
+    let service = credit_service(&state);
+    let entry = service
+        .issue_credit(user.user_id, store_id, payload)
+        .await?;
+    Ok(Json(models::ApiResponse::new(entry)))
+}
+
+async fn credit_ledger(
+    State(state): State<AppState>,
+    Path((store_id, customer_id)): Path<(Uuid, Uuid)>,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreCreditEntry>>>> {
+    let limit = pagination.limit.unwrap_or(20).clamp(1, 50);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let service = credit_service(&state);
+    let entries = service.ledger(store_id, customer_id, limit, offset).await?;
+    Ok(Json(models::ApiResponse::new(entries)))
+}
+
+async fn get_settings(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<StoreSettings>>> {
+    let store = store_service(&state).get_store(store_id).await?;
+    if store.is_private {
+        let user =
+            maybe_user.ok_or_else(|| AppError::Authentication("Authentication required".into()))?;
+        ensure_store_permission(&state, user.user_id, store_id, Permission::ViewProducts).await?;
+    }
+
+    let service = settings_service(&state);
+    let settings = service.get_settings(store_id).await?;
+    Ok(Json(models::ApiResponse::new(settings)))
+}
+
+async fn update_settings(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<UpdateStoreSettingsRequest>,
+) -> crate::Result<Json<models::ApiResponse<StoreSettings>>> {
+    let service = settings_service(&state);
+    let settings = service.update_settings(store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(settings)))
+}
+
+async fn list_checkout_fields(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    MaybeAuthenticatedUser(maybe_user): MaybeAuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<CheckoutField>>>> {
+    let store = store_service(&state).get_store(store_id).await?;
+    if store.is_private {
+        let user =
+            maybe_user.ok_or_else(|| AppError::Authentication("Authentication required".into()))?;
+        ensure_store_permission(&state, user.user_id, store_id, Permission::ViewProducts).await?;
+    }
+
+    let service = settings_service(&state);
+    let fields = service.list_checkout_fields(store_id).await?;
+    Ok(Json(models::ApiResponse::new(fields)))
+}
+
+async fn add_checkout_field(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateCheckoutFieldRequest>,
+) -> crate::Result<Json<models::ApiResponse<CheckoutField>>> {
+    let service = settings_service(&state);
+    let field = service.add_checkout_field(store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(field)))
+}
+
+async fn remove_checkout_field(
+    State(state): State<AppState>,
+    Path((store_id, field_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = settings_service(&state);
+    service.remove_checkout_field(store_id, field_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn list_shipping_rules(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<ShippingRule>>>> {
+    let service = shipping_service(&state);
+    let rules = service.list_rules(store_id).await?;
+    Ok(Json(models::ApiResponse::new(rules)))
+}
+
+async fn add_shipping_rule(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateShippingRuleRequest>,
+) -> crate::Result<Json<models::ApiResponse<ShippingRule>>> {
+    let service = shipping_service(&state);
+    let rule = service.add_rule(store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(rule)))
+}
+
+async fn remove_shipping_rule(
+    State(state): State<AppState>,
+    Path((store_id, rule_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = shipping_service(&state);
+    service.remove_rule(store_id, rule_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn list_notification_rules(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<NotificationRule>>>> {
+    let service = notification_service(&state);
+    let rules = service.list_rules(store_id).await?;
+    Ok(Json(models::ApiResponse::new(rules)))
+}
+
+async fn add_notification_rule(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateNotificationRuleRequest>,
+) -> crate::Result<Json<models::ApiResponse<NotificationRule>>> {
+    let service = notification_service(&state);
+    let rule = service.create_rule(user.user_id, store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(rule)))
+}
+
+async fn remove_notification_rule(
+    State(state): State<AppState>,
+    Path((store_id, rule_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = notification_service(&state);
+    service.delete_rule(store_id, rule_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn list_webhooks(
+    State(state): State<AppState>,
+    Path(store_id): Path<Uuid>,
+) -> crate::Result<Json<models::ApiResponse<Vec<Webhook>>>> {
+    let service = webhook_service(&state);
+    let webhooks = service.list(store_id).await?;
+    Ok(Json(models::ApiResponse::new(webhooks)))
+}
+
+async fn create_webhook(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(store_id): Path<Uuid>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> crate::Result<Json<models::ApiResponse<CreatedWebhook>>> {
+    let service = webhook_service(&state);
+    let webhook = service.create(user.user_id, store_id, payload).await?;
+    Ok(Json(models::ApiResponse::new(webhook)))
+}
+
+async fn remove_webhook(
+    State(state): State<AppState>,
+    Path((store_id, webhook_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<serde_json::Value>>> {
+    let service = webhook_service(&state);
+    service.delete(store_id, webhook_id).await?;
+    Ok(Json(models::ApiResponse::new(json!({ "removed": true }))))
+}
+
+async fn test_webhook(
+    State(state): State<AppState>,
+    Path((store_id, webhook_id)): Path<(Uuid, Uuid)>,
+) -> crate::Result<Json<models::ApiResponse<WebhookPing>>> {
+    let service = webhook_service(&state);
+    let ping = service
+        .ping(&state.webhook_sender, store_id, webhook_id)
+        .await?;
+    Ok(Json(models::ApiResponse::new(ping)))
+}
+
+async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    Path((store_id, webhook_id)): Path<(Uuid, Uuid)>,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<WebhookDelivery>>>> {
+    let service = webhook_service(&state);
+    let limit = pagination.limit.unwrap_or(50).clamp(1, 100);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let deliveries = service
+        .deliveries(store_id, webhook_id, limit, offset)
+        .await?;
+    Ok(Json(models::ApiResponse::new(deliveries)))
 }
 
 fn store_service(state: &AppState) -> StoreService {
     StoreService::new(
         StoreRepository::new(state.db.clone()),
-        MemberRepository::new(state.db.clone()),
+        MemberRepository::new(state.db.clone()).with_cache(state.cache.clone()),
     )
 }
 
@@ -98,4 +995,103 @@ fn analytics_service(state: &AppState) -> AnalyticsService {
         StoreRepository::new(state.db.clone()),
         AnalyticsRepository::new(state.db.clone()),
     )
+    .with_cache(state.analytics_cache.clone())
+}
+
+fn moderation_service(state: &AppState) -> ModerationService {
+    ModerationService::new(
+        StoreRepository::new(state.db.clone()),
+        ModerationRepository::new(state.db.clone()),
+    )
+}
+
+fn settings_service(state: &AppState) -> StoreSettingsService {
+    StoreSettingsService::new(
+        StoreSettingsRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+    )
+}
+
+fn shipping_service(state: &AppState) -> ShippingService {
+    ShippingService::new(
+        StoreSettingsRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+    )
+}
+
+fn order_service(state: &AppState) -> OrderService {
+    OrderService::new(
+        OrderRepository::new(state.db.clone()),
+        ProductRepository::new(state.db.clone()),
+        CartRepository::new(state.db.clone()),
+        FulfillmentRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+        CreditRepository::new(state.db.clone()),
+        NotificationRepository::new(state.db.clone()),
+        CouponRepository::new(state.db.clone()),
+    )
+}
+
+fn message_service(state: &AppState) -> OrderMessageService {
+    OrderMessageService::new(
+        OrderMessageRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        NotificationRepository::new(state.db.clone()),
+    )
+}
+
+fn payment_service(state: &AppState) -> PaymentService {
+    PaymentService::new(
+        PaymentRepository::new(state.db.clone()),
+        OrderRepository::new(state.db.clone()),
+        StoreSettingsRepository::new(state.db.clone()),
+    )
+    .with_providers(state.payment_providers.clone())
+}
+
+fn audit_service(state: &AppState) -> AuditService {
+    AuditService::new(AuditRepository::new(state.db.clone()))
+}
+
+fn order_status_service(state: &AppState) -> OrderStatusService {
+    OrderStatusService::new(
+        OrderRepository::new(state.db.clone()),
+        NotificationRepository::new(state.db.clone()),
+        audit_service(state),
+    )
+    .with_events(state.events.clone())
+}
+
+fn notification_service(state: &AppState) -> NotificationService {
+    NotificationService::new(NotificationRepository::new(state.db.clone()))
+}
+
+fn webhook_service(state: &AppState) -> WebhookService {
+    WebhookService::new(
+        WebhookRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+    )
+}
+
+fn coupon_service(state: &AppState) -> CouponService {
+    CouponService::new(
+        CouponRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+        CartRepository::new(state.db.clone()),
+    )
+}
+
+fn credit_service(state: &AppState) -> CreditService {
+    CreditService::new(
+        CreditRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+    )
+}
+
+fn product_service(state: &AppState) -> ProductService {
+    ProductService::new(
+        ProductRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+        StoreRepository::new(state.db.clone()),
+        ProductImageRepository::new(state.db.clone()),
+    )
 }
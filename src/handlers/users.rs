@@ -1,15 +1,44 @@
-use axum::{extract::State, routing::get, Json, Router};
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
 
 use crate::{
     middleware::auth::AuthenticatedUser,
-    models::{self, user::UserProfileResponse},
-    repositories::UserRepository,
-    services::UserService,
+    models::{
+        self,
+        credit::StoreCreditBalance,
+        notification::Notification,
+        product::RecentlyViewedProduct,
+        store::{MemberStore, StoreMembership},
+        user::UserProfileResponse,
+    },
+    repositories::{
+        CreditRepository, MemberRepository, NotificationRepository, RecentlyViewedRepository,
+        StoreRepository, UserRepository,
+    },
+    services::{
+        CreditService, NotificationService, RecentlyViewedService, StoreService, UserService,
+    },
     state::AppState,
 };
 
+#[derive(Debug, Deserialize)]
+struct PaginationQuery {
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
 pub fn router() -> Router<AppState> {
-    Router::new().route("/me", get(me))
+    Router::new()
+        .route("/me", get(me))
+        .route("/me/stores", get(my_stores))
+        .route("/me/memberships", get(my_memberships))
+        .route("/me/notifications", get(my_notifications))
+        .route("/me/store-credit", get(my_store_credit))
+        .route("/me/recently-viewed", get(my_recently_viewed))
 }
 
 async fn me(
@@ -21,3 +50,60 @@ async fn me(
     let response = UserProfileResponse { user: profile };
     Ok(Json(models::ApiResponse::new(response)))
 }
+
+async fn my_stores(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<MemberStore>>>> {
+    let service = StoreService::new(
+        StoreRepository::new(state.db.clone()),
+        MemberRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+    );
+    let stores = service.list_user_stores(user.user_id).await?;
+    Ok(Json(models::ApiResponse::new(stores)))
+}
+
+async fn my_memberships(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreMembership>>>> {
+    let service = StoreService::new(
+        StoreRepository::new(state.db.clone()),
+        MemberRepository::new(state.db.clone()).with_cache(state.cache.clone()),
+    );
+    let memberships = service.list_memberships(user.user_id).await?;
+    Ok(Json(models::ApiResponse::new(memberships)))
+}
+
+async fn my_notifications(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Query(pagination): Query<PaginationQuery>,
+) -> crate::Result<Json<models::ApiResponse<Vec<Notification>>>> {
+    let limit = pagination.limit.unwrap_or(20).clamp(1, 50);
+    let offset = pagination.offset.unwrap_or(0).max(0);
+    let service = NotificationService::new(NotificationRepository::new(state.db.clone()));
+    let notifications = service.list_for_user(user.user_id, limit, offset).await?;
+    Ok(Json(models::ApiResponse::new(notifications)))
+}
+
+async fn my_store_credit(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<StoreCreditBalance>>>> {
+    let service = CreditService::new(
+        CreditRepository::new(state.db.clone()),
+        StoreRepository::new(state.db.clone()),
+    );
+    let balances = service.balances_for_user(user.user_id).await?;
+    Ok(Json(models::ApiResponse::new(balances)))
+}
+
+async fn my_recently_viewed(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> crate::Result<Json<models::ApiResponse<Vec<RecentlyViewedProduct>>>> {
+    let service = RecentlyViewedService::new(RecentlyViewedRepository::new(state.db.clone()));
+    let products = service.list(user.user_id).await?;
+    Ok(Json(models::ApiResponse::new(products)))
+}
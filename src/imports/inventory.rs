@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    imports::{FieldChange, ImportAction, ImportRow, Importer, Projection},
+    models::{inventory::InventoryImportRow, product::Product},
+    repositories::{InventoryRepository, ProductRepository},
+};
+
+const IMPORT_REASON: &str = "Stock count import";
+
+/// Applies a stock count: each row sets the quantity on hand of an existing
+/// SKU, and the difference lands in the inventory ledger as an adjustment.
+pub struct InventoryImporter {
+    products: ProductRepository,
+    inventory: InventoryRepository,
+    store_id: Uuid,
+    actor_id: Uuid,
+}
+
+impl InventoryImporter {
+    pub fn new(
+        products: ProductRepository,
+        inventory: InventoryRepository,
+        store_id: Uuid,
+        actor_id: Uuid,
+    ) -> Self {
+        Self {
+            products,
+            inventory,
+            store_id,
+            actor_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Importer for InventoryImporter {
+    type Row = InventoryImportRow;
+    type Record = i32;
+
+    const KEY: &'static str = "SKU";
+
+    fn key(row: &InventoryImportRow) -> String {
+        row.sku.clone()
+    }
+
+    fn validate(&self, row: InventoryImportRow) -> Result<i32, String> {
+        row.validate().map_err(|err| err.to_string())?;
+        Ok(row.stock_quantity)
+    }
+
+    async fn plan(&self, rows: &[ImportRow<i32>]) -> crate::Result<Vec<Projection>> {
+        let skus: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let existing: HashMap<String, Product> = self
+            .products
+            .find_by_skus(self.store_id, &skus)
+            .await?
+            .into_iter()
+            .map(|product| (product.sku.clone(), product))
+            .collect();
+
+        Ok(rows
+            .iter()
+            .map(|row| match existing.get(&row.key) {
+                None => Projection::Reject("Unknown SKU".into()),
+                Some(product) if product.is_archived() => {
+                    Projection::Reject("Product is archived".into())
+                }
+                Some(product) => Projection::Change(
+                    ImportAction::Update,
+                    FieldChange::between(
+                        "stock_quantity",
+                        Some(&product.stock_quantity),
+                        &row.record,
+                    )
+                    .into_iter()
+                    .collect(),
+                ),
+            })
+            .collect())
+    }
+
+    async fn apply(&self, rows: Vec<ImportRow<i32>>) -> crate::Result<()> {
+        let skus: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let quantities: Vec<i32> = rows.iter().map(|row| row.record).collect();
+        self.inventory
+            .set_stock_levels(
+                self.store_id,
+                self.actor_id,
+                &skus,
+                &quantities,
+                IMPORT_REASON,
+            )
+            .await?;
+        Ok(())
+    }
+}
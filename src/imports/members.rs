@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
+use validator::ValidateEmail;
+
+use crate::{
+    imports::{FieldChange, ImportAction, ImportRow, Importer, Projection},
+    models::{
+        permission::Permission,
+        store::{MemberImportRow, MemberRole, Store, StoreMember},
+    },
+    repositories::MemberRepository,
+};
+
+/// Adds store members by email, or updates the role and permissions of
+/// existing ones. Every email must belong to a registered user.
+pub struct MemberImporter {
+    members: MemberRepository,
+    store: Store,
+    actor_id: Uuid,
+}
+
+pub struct MemberRecord {
+    role: MemberRole,
+    permissions: Vec<Permission>,
+}
+
+impl MemberImporter {
+    pub fn new(members: MemberRepository, store: Store, actor_id: Uuid) -> Self {
+        Self {
+            members,
+            store,
+            actor_id,
+        }
+    }
+}
+
+#[async_trait]
+impl Importer for MemberImporter {
+    type Row = MemberImportRow;
+    type Record = MemberRecord;
+
+    const KEY: &'static str = "email";
+
+    fn key(row: &MemberImportRow) -> String {
+        row.email.to_lowercase()
+    }
+
+    fn validate(&self, row: MemberImportRow) -> Result<MemberRecord, String> {
+        if !row.email.validate_email() {
+            return Err("Invalid email address".into());
+        }
+        if row.role == MemberRole::Owner {
+            return Err("The Owner role cannot be assigned".into());
+        }
+
+        let mut permissions = Vec::new();
+        for name in row.permissions.iter().flat_map(|list| list.split(';')) {
+            let name = name.trim();
+            if name.is_empty() {
+                continue;
+            }
+            let permission = Permission::all()
+                .iter()
+                .find(|permission| permission.as_str() == name)
+                .ok_or_else(|| format!("Unknown permission '{}'", name))?;
+            if !permissions.contains(permission) {
+                permissions.push(*permission);
+            }
+        }
+        permissions.sort();
+
+        Ok(MemberRecord {
+            role: row.role,
+            permissions,
+        })
+    }
+
+    async fn plan(&self, rows: &[ImportRow<MemberRecord>]) -> crate::Result<Vec<Projection>> {
+        let emails: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let users: HashMap<String, Uuid> = self
+            .members
+            .find_user_ids_by_email(&emails)
+            .await?
+            .into_iter()
+            .collect();
+        let current: HashMap<Uuid, StoreMember> = self
+            .members
+            .list_members(self.store.id)
+            .await?
+            .into_iter()
+            .filter(|member| member.is_active)
+            .map(|member| (member.user_id, member))
+            .collect();
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let Some(user_id) = users.get(&row.key) else {
+                    return Projection::Reject("No user with this email".into());
+                };
+                if *user_id == self.store.owner_id {
+                    return Projection::Reject("The store owner cannot be imported".into());
+                }
+
+                let member = current.get(user_id);
+                let permissions = Value::from(
+                    row.record
+                        .permissions
+                        .iter()
+                        .map(|permission| permission.as_str())
+                        .collect::<Vec<_>>(),
+                );
+                let action = match member {
+                    Some(_) => ImportAction::Update,
+                    None => ImportAction::Create,
+                };
+                let fields = [
+                    FieldChange::between("role", member.map(|m| &m.role), &row.record.role),
+                    FieldChange::between(
+                        "permissions",
+                        member.map(|m| &m.permissions),
+                        &permissions,
+                    ),
+                ];
+                Projection::Change(action, fields.into_iter().flatten().collect())
+            })
+            .collect())
+    }
+
+    async fn apply(&self, rows: Vec<ImportRow<MemberRecord>>) -> crate::Result<()> {
+        let emails: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let users: HashMap<String, Uuid> = self
+            .members
+            .find_user_ids_by_email(&emails)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut tx = self.members.pool().begin().await?;
+        for row in &rows {
+            let Some(user_id) = users.get(&row.key) else {
+                continue;
+            };
+            self.members
+                .upsert_member_in_tx(
+                    &mut tx,
+                    self.store.id,
+                    *user_id,
+                    row.record.role,
+                    &row.record.permissions,
+                    self.actor_id,
+                )
+                .await?;
+        }
+        tx.commit().await?;
+        self.members.invalidate_permissions(self.store.id).await;
+        Ok(())
+    }
+}
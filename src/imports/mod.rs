@@ -0,0 +1,356 @@
+//! Shared pipeline behind the CSV bulk importers.
+//!
+//! Every importer goes through the same steps: rows are parsed and validated
+//! one by one, then checked against current data (`Importer::plan`), and only
+//! written (`Importer::apply`) when the whole file is clean. Imports are
+//! all-or-nothing. A dry run stops after planning and returns the same report
+//! a real run would, including the projected change of every row.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::AppError;
+
+pub mod inventory;
+pub mod members;
+pub mod prices;
+pub mod products;
+
+pub use inventory::InventoryImporter;
+pub use members::MemberImporter;
+pub use prices::PriceImporter;
+pub use products::ProductImporter;
+
+/// Largest CSV accepted by a single bulk import.
+pub const MAX_IMPORT_ROWS: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportAction {
+    Create,
+    Update,
+}
+
+/// One field a row changes, with its current value (`None` for new records).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub from: Option<Value>,
+    pub to: Value,
+}
+
+impl FieldChange {
+    /// Returns the change from `from` to `to`, or `None` when the value stays
+    /// the same.
+    pub fn between<T: Serialize + PartialEq>(
+        field: &str,
+        from: Option<&T>,
+        to: &T,
+    ) -> Option<Self> {
+        if from == Some(to) {
+            return None;
+        }
+        Some(Self {
+            field: field.to_string(),
+            from: from.map(|value| serde_json::to_value(value).unwrap_or(Value::Null)),
+            to: serde_json::to_value(to).unwrap_or(Value::Null),
+        })
+    }
+}
+
+/// What a valid row does (or, in a dry run, would do) to the store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportChange {
+    /// Line in the uploaded file, counting the header as line 1.
+    pub line: u64,
+    pub key: String,
+    pub action: ImportAction,
+    pub fields: Vec<FieldChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportError {
+    /// Line in the uploaded file, counting the header as line 1.
+    pub line: u64,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+/// Outcome of a bulk import. When `errors` is non-empty nothing was written
+/// and no changes are reported.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub errors: Vec<ImportError>,
+    pub changes: Vec<ImportChange>,
+}
+
+/// A validated row together with where it came from.
+#[derive(Debug, Clone)]
+pub struct ImportRow<R> {
+    pub line: u64,
+    pub key: String,
+    pub record: R,
+}
+
+/// The planned effect of one row: the change it makes, or why it cannot be
+/// applied against the current data.
+#[derive(Debug, Clone)]
+pub enum Projection {
+    Change(ImportAction, Vec<FieldChange>),
+    Reject(String),
+}
+
+#[async_trait]
+pub trait Importer: Send + Sync {
+    /// A CSV line as deserialized from the file.
+    type Row: DeserializeOwned + Send;
+    /// A row that passed validation.
+    type Record: Send + Sync;
+
+    /// Name of the column identifying rows, used in duplicate-row errors.
+    const KEY: &'static str;
+
+    fn key(row: &Self::Row) -> String;
+
+    /// Checks a row on its own, without looking at stored data.
+    fn validate(&self, row: Self::Row) -> Result<Self::Record, String>;
+
+    /// Works out each row's effect against current data, in row order.
+    /// File-wide problems such as plan quotas are returned as errors.
+    async fn plan(&self, rows: &[ImportRow<Self::Record>]) -> crate::Result<Vec<Projection>>;
+
+    /// Writes every row atomically. Only called for fully valid files.
+    async fn apply(&self, rows: Vec<ImportRow<Self::Record>>) -> crate::Result<()>;
+}
+
+/// Parses, validates and plans `data`, then applies it unless `dry_run` is set
+/// or any row has a problem.
+pub async fn run<I: Importer>(
+    importer: &I,
+    data: &[u8],
+    dry_run: bool,
+) -> crate::Result<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let headers = reader
+        .headers()
+        .map_err(|err| AppError::BadRequest(format!("Invalid CSV header: {}", err)))?
+        .clone();
+
+    let mut report = ImportReport {
+        dry_run,
+        ..ImportReport::default()
+    };
+    let mut rows = Vec::new();
+    let mut seen: HashMap<String, u64> = HashMap::new();
+
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                report.errors.push(ImportError {
+                    line: err.position().map(|pos| pos.line()).unwrap_or_default(),
+                    key: None,
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map(|pos| pos.line()).unwrap_or_default();
+        if rows.len() + report.errors.len() >= MAX_IMPORT_ROWS {
+            return Err(AppError::BadRequest(format!(
+                "Imports are limited to {} rows",
+                MAX_IMPORT_ROWS
+            )));
+        }
+
+        let row = match record.deserialize::<I::Row>(Some(&headers)) {
+            Ok(row) => row,
+            Err(err) => {
+                report.errors.push(ImportError {
+                    line,
+                    key: record.get(0).map(str::to_string),
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+
+        let key = I::key(&row);
+        let validated = importer
+            .validate(row)
+            .and_then(|record| match seen.get(&key) {
+                Some(first) => Err(format!(
+                    "Duplicate {} (first seen on line {})",
+                    I::KEY,
+                    first
+                )),
+                None => Ok(record),
+            });
+        match validated {
+            Ok(record) => {
+                seen.insert(key.clone(), line);
+                rows.push(ImportRow { line, key, record });
+            }
+            Err(message) => report.errors.push(ImportError {
+                line,
+                key: Some(key),
+                message,
+            }),
+        }
+    }
+
+    if !report.errors.is_empty() {
+        return Ok(report);
+    }
+    if rows.is_empty() {
+        return Err(AppError::Validation("CSV file contains no rows".into()));
+    }
+
+    let projections = importer.plan(&rows).await?;
+    let mut changes = Vec::with_capacity(rows.len());
+    for (row, projection) in rows.iter().zip(projections) {
+        match projection {
+            Projection::Change(action, fields) => changes.push(ImportChange {
+                line: row.line,
+                key: row.key.clone(),
+                action,
+                fields,
+            }),
+            Projection::Reject(message) => report.errors.push(ImportError {
+                line: row.line,
+                key: Some(row.key.clone()),
+                message,
+            }),
+        }
+    }
+    if !report.errors.is_empty() {
+        return Ok(report);
+    }
+
+    if !dry_run {
+        importer.apply(rows).await?;
+    }
+    report.created = changes
+        .iter()
+        .filter(|change| change.action == ImportAction::Create)
+        .count();
+    report.updated = changes.len() - report.created;
+    report.changes = changes;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    struct Numbers {
+        applied: Mutex<Vec<i32>>,
+    }
+
+    #[derive(Deserialize)]
+    struct NumberRow {
+        name: String,
+        value: i32,
+    }
+
+    #[async_trait]
+    impl Importer for Numbers {
+        type Row = NumberRow;
+        type Record = i32;
+
+        const KEY: &'static str = "name";
+
+        fn key(row: &NumberRow) -> String {
+            row.name.clone()
+        }
+
+        fn validate(&self, row: NumberRow) -> Result<i32, String> {
+            if row.value < 0 {
+                return Err("value must not be negative".into());
+            }
+            Ok(row.value)
+        }
+
+        async fn plan(&self, rows: &[ImportRow<i32>]) -> crate::Result<Vec<Projection>> {
+            Ok(rows
+                .iter()
+                .map(|row| match row.record {
+                    13 => Projection::Reject("unlucky".into()),
+                    value => Projection::Change(
+                        ImportAction::Create,
+                        FieldChange::between("value", None, &value)
+                            .into_iter()
+                            .collect(),
+                    ),
+                })
+                .collect())
+        }
+
+        async fn apply(&self, rows: Vec<ImportRow<i32>>) -> crate::Result<()> {
+            let mut applied = self.applied.lock().unwrap();
+            applied.extend(rows.into_iter().map(|row| row.record));
+            Ok(())
+        }
+    }
+
+    fn importer() -> Numbers {
+        Numbers {
+            applied: Mutex::new(Vec::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_reports_changes_without_applying() {
+        let numbers = importer();
+        let report = run(&numbers, b"name,value\na,1\nb,2\n", true)
+            .await
+            .unwrap();
+
+        assert!(report.dry_run);
+        assert_eq!(report.created, 2);
+        assert_eq!(report.changes[1].line, 3);
+        assert_eq!(report.changes[1].fields[0].to, Value::from(2));
+        assert!(numbers.applied.lock().unwrap().is_empty());
+
+        let report = run(&numbers, b"name,value\na,1\nb,2\n", false)
+            .await
+            .unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(*numbers.applied.lock().unwrap(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn row_and_plan_errors_block_the_whole_file() {
+        let numbers = importer();
+        let report = run(&numbers, b"name,value\na,1\nb,-1\na,3\nc,x\n", false)
+            .await
+            .unwrap();
+        let lines: Vec<u64> = report.errors.iter().map(|err| err.line).collect();
+        assert_eq!(lines, vec![3, 4, 5]);
+        assert!(report.errors[1].message.contains("Duplicate name"));
+
+        let report = run(&numbers, b"name,value\na,1\nb,13\n", false)
+            .await
+            .unwrap();
+        assert_eq!(report.errors[0].message, "unlucky");
+        assert!(report.changes.is_empty());
+        assert!(numbers.applied.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn field_change_skips_equal_values() {
+        assert_eq!(FieldChange::between("price", Some(&5), &5), None);
+        let change = FieldChange::between("price", Some(&5), &7).unwrap();
+        assert_eq!(change.from, Some(Value::from(5)));
+    }
+}
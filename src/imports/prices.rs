@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    imports::{FieldChange, ImportAction, ImportRow, Importer, Projection},
+    models::product::{PriceImportRow, Product},
+    repositories::ProductRepository,
+};
+
+/// Reprices existing products by SKU. Unknown or archived SKUs are rejected
+/// rather than created.
+pub struct PriceImporter {
+    products: ProductRepository,
+    store_id: Uuid,
+}
+
+impl PriceImporter {
+    pub fn new(products: ProductRepository, store_id: Uuid) -> Self {
+        Self { products, store_id }
+    }
+}
+
+#[async_trait]
+impl Importer for PriceImporter {
+    type Row = PriceImportRow;
+    type Record = Decimal;
+
+    const KEY: &'static str = "SKU";
+
+    fn key(row: &PriceImportRow) -> String {
+        row.sku.clone()
+    }
+
+    fn validate(&self, row: PriceImportRow) -> Result<Decimal, String> {
+        row.validate().map_err(|err| err.to_string())?;
+        Ok(Decimal::from_f64_retain(row.price).ok_or("Invalid price value")?)
+    }
+
+    async fn plan(&self, rows: &[ImportRow<Decimal>]) -> crate::Result<Vec<Projection>> {
+        let skus: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let existing: HashMap<String, Product> = self
+            .products
+            .find_by_skus(self.store_id, &skus)
+            .await?
+            .into_iter()
+            .map(|product| (product.sku.clone(), product))
+            .collect();
+
+        Ok(rows
+            .iter()
+            .map(|row| match existing.get(&row.key) {
+                None => Projection::Reject("Unknown SKU".into()),
+                Some(product) if product.is_archived() => {
+                    Projection::Reject("Product is archived".into())
+                }
+                Some(product) => Projection::Change(
+                    ImportAction::Update,
+                    FieldChange::between("price", Some(&product.price), &row.record)
+                        .into_iter()
+                        .collect(),
+                ),
+            })
+            .collect())
+    }
+
+    async fn apply(&self, rows: Vec<ImportRow<Decimal>>) -> crate::Result<()> {
+        let skus: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let prices: Vec<Decimal> = rows.iter().map(|row| row.record).collect();
+        self.products
+            .update_prices(self.store_id, &skus, &prices)
+            .await?;
+        Ok(())
+    }
+}
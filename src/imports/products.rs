@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use validator::Validate;
+
+use crate::{
+    imports::{FieldChange, ImportAction, ImportRow, Importer, Projection},
+    models::{
+        product::{CreateProductRequest, Product, ProductImportRow},
+        store::Store,
+    },
+    repositories::ProductRepository,
+    services::product_service::ensure_product_quota,
+};
+
+const BATCH_SIZE: usize = 500;
+
+/// Creates or updates products by SKU. New SKUs count against the store
+/// plan's product quota.
+pub struct ProductImporter {
+    products: ProductRepository,
+    store: Store,
+}
+
+pub struct ProductRecord {
+    request: CreateProductRequest,
+    price: Decimal,
+}
+
+impl ProductImporter {
+    pub fn new(products: ProductRepository, store: Store) -> Self {
+        Self { products, store }
+    }
+}
+
+#[async_trait]
+impl Importer for ProductImporter {
+    type Row = ProductImportRow;
+    type Record = ProductRecord;
+
+    const KEY: &'static str = "SKU";
+
+    fn key(row: &ProductImportRow) -> String {
+        row.sku.clone()
+    }
+
+    fn validate(&self, row: ProductImportRow) -> Result<ProductRecord, String> {
+        let request = row.into_request(self.store.id);
+        request.validate().map_err(|err| err.to_string())?;
+        let price = Decimal::from_f64_retain(request.price).ok_or("Invalid price value")?;
+        Ok(ProductRecord { request, price })
+    }
+
+    async fn plan(&self, rows: &[ImportRow<ProductRecord>]) -> crate::Result<Vec<Projection>> {
+        let skus: Vec<&str> = rows.iter().map(|row| row.key.as_str()).collect();
+        let existing: HashMap<String, Product> = self
+            .products
+            .find_by_skus(self.store.id, &skus)
+            .await?
+            .into_iter()
+            .map(|product| (product.sku.clone(), product))
+            .collect();
+
+        let new_products = rows
+            .iter()
+            .filter(|row| !existing.contains_key(&row.key))
+            .count() as i64;
+        ensure_product_quota(&self.products, &self.store, new_products).await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let current = existing.get(&row.key);
+                let request = &row.record.request;
+                let action = match current {
+                    Some(_) => ImportAction::Update,
+                    None => ImportAction::Create,
+                };
+                let fields = [
+                    FieldChange::between("name", current.map(|p| &p.name), &request.name),
+                    FieldChange::between(
+                        "description",
+                        current.map(|p| &p.description),
+                        &request.description,
+                    ),
+                    FieldChange::between("price", current.map(|p| &p.price), &row.record.price),
+                    FieldChange::between(
+                        "stock_quantity",
+                        current.map(|p| &p.stock_quantity),
+                        &request.stock_quantity,
+                    ),
+                    FieldChange::between(
+                        "category",
+                        current.map(|p| &p.category),
+                        &request.category,
+                    ),
+                    FieldChange::between(
+                        "thumbnail_url",
+                        current.map(|p| &p.thumbnail_url),
+                        &request.thumbnail_url,
+                    ),
+                ];
+                Projection::Change(action, fields.into_iter().flatten().collect())
+            })
+            .collect())
+    }
+
+    async fn apply(&self, rows: Vec<ImportRow<ProductRecord>>) -> crate::Result<()> {
+        let (requests, prices): (Vec<CreateProductRequest>, Vec<Decimal>) = rows
+            .into_iter()
+            .map(|row| (row.record.request, row.record.price))
+            .unzip();
+
+        let mut tx = self.products.pool().begin().await?;
+        for (batch, batch_prices) in requests.chunks(BATCH_SIZE).zip(prices.chunks(BATCH_SIZE)) {
+            self.products
+                .upsert_batch_in_tx(&mut tx, self.store.id, batch, batch_prices)
+                .await?;
+        }
+        tx.commit().await?;
+        self.products.invalidate_store_listing(self.store.id).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use std::{collections::HashMap, panic::AssertUnwindSafe, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use tokio::task::JoinHandle;
+
+use crate::{
+    error::AppError, metrics::SharedMetrics, models::job::Job, repositories::JobRepository,
+    utils::backoff::retry_delay,
+};
+
+/// Attempts a job gets unless its queue says otherwise.
+pub const MAX_JOB_ATTEMPTS: i32 = 5;
+
+/// How long a claimed job is held before another worker may retry it.
+/// Handlers must finish well within it.
+const JOB_LEASE_SECS: i64 = 300;
+
+const BATCH_SIZE: i64 = 20;
+
+/// Runs jobs of one kind. A job may run more than once, after a failed
+/// attempt or when a worker dies mid-run, so handlers must be idempotent.
+#[async_trait]
+pub trait JobHandler: Send + Sync {
+    async fn run(&self, job: &Job) -> anyhow::Result<()>;
+}
+
+/// The handlers this process runs, by job kind. Jobs of kinds without a
+/// handler stay queued for an instance that has one.
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    handlers: HashMap<String, Arc<dyn JobHandler>>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(mut self, kind: &str, handler: Arc<dyn JobHandler>) -> Self {
+        self.handlers.insert(kind.to_string(), handler);
+        self
+    }
+
+    pub fn kinds(&self) -> Vec<&str> {
+        self.handlers.keys().map(String::as_str).collect()
+    }
+}
+
+/// Queues jobs for the worker.
+#[derive(Clone)]
+pub struct JobQueue {
+    jobs: JobRepository,
+    max_attempts: i32,
+}
+
+impl JobQueue {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            jobs: JobRepository::new(pool),
+            max_attempts: MAX_JOB_ATTEMPTS,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: i32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub async fn enqueue<T: Serialize>(&self, kind: &str, payload: &T) -> crate::Result<Job> {
+        self.enqueue_at(kind, payload, Utc::now()).await
+    }
+
+    /// Queues a job that is not due before `run_at`.
+    pub async fn enqueue_at<T: Serialize>(
+        &self,
+        kind: &str,
+        payload: &T,
+        run_at: DateTime<Utc>,
+    ) -> crate::Result<Job> {
+        self.jobs
+            .enqueue(kind, &to_payload(payload)?, run_at, self.max_attempts)
+            .await
+    }
+
+    /// Queues a job that only runs if the transaction commits.
+    pub async fn enqueue_in_tx<T: Serialize>(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        kind: &str,
+        payload: &T,
+    ) -> crate::Result<Job> {
+        self.jobs
+            .enqueue_in_tx(
+                tx,
+                kind,
+                &to_payload(payload)?,
+                Utc::now(),
+                self.max_attempts,
+            )
+            .await
+    }
+}
+
+fn to_payload<T: Serialize>(payload: &T) -> crate::Result<serde_json::Value> {
+    serde_json::to_value(payload).map_err(|err| AppError::Internal(err.into()))
+}
+
+/// Outcome of one pass over the due jobs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerRun {
+    pub succeeded: u32,
+    pub retrying: u32,
+    pub failed: u32,
+}
+
+impl WorkerRun {
+    fn total(&self) -> u32 {
+        self.succeeded + self.retrying + self.failed
+    }
+}
+
+/// Claims due jobs and runs them with the registered handlers.
+#[derive(Clone)]
+pub struct JobWorker {
+    jobs: JobRepository,
+    registry: Arc<JobRegistry>,
+    metrics: SharedMetrics,
+}
+
+impl JobWorker {
+    pub fn new(pool: PgPool, registry: JobRegistry, metrics: SharedMetrics) -> Self {
+        Self {
+            jobs: JobRepository::new(pool),
+            registry: Arc::new(registry),
+            metrics,
+        }
+    }
+
+    /// Runs up to `limit` due jobs. A failed job is retried with a growing
+    /// delay until it runs out of attempts.
+    pub async fn run_due(&self, limit: i64) -> crate::Result<WorkerRun> {
+        let mut run = WorkerRun::default();
+        let kinds = self.registry.kinds();
+        if kinds.is_empty() {
+            return Ok(run);
+        }
+
+        for job in self.jobs.claim_due(&kinds, limit, JOB_LEASE_SECS).await? {
+            let handler = &self.registry.handlers[&job.kind];
+            // A panicking handler fails its job instead of the worker
+            let result = AssertUnwindSafe(handler.run(&job))
+                .catch_unwind()
+                .await
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Job handler panicked")));
+
+            let outcome = match result {
+                Ok(()) => {
+                    self.jobs.mark_succeeded(job.id).await?;
+                    run.succeeded += 1;
+                    "succeeded"
+                }
+                Err(err) => {
+                    let retry_at = (job.attempts < job.max_attempts)
+                        .then(|| Utc::now() + retry_delay(job.attempts));
+                    tracing::warn!(
+                        job_id = %job.id,
+                        kind = %job.kind,
+                        attempts = job.attempts,
+                        "Job failed: {:#}",
+                        err
+                    );
+                    self.jobs
+                        .mark_failed(job.id, &format!("{:#}", err), retry_at)
+                        .await?;
+                    if retry_at.is_some() {
+                        run.retrying += 1;
+                        "retrying"
+                    } else {
+                        run.failed += 1;
+                        "failed"
+                    }
+                }
+            };
+            self.metrics.observe_job_run(&job.kind, outcome);
+        }
+
+        Ok(run)
+    }
+
+    /// Publishes how many jobs of each kind are pending. Registered kinds
+    /// with nothing queued are reported as zero.
+    pub async fn sample_queue_depth(&self) -> crate::Result<()> {
+        let mut depth: HashMap<String, i64> = self
+            .registry
+            .kinds()
+            .into_iter()
+            .map(|kind| (kind.to_string(), 0))
+            .collect();
+        for queued in self.jobs.queue_depth().await? {
+            depth.insert(queued.kind, queued.pending);
+        }
+        for (kind, pending) in depth {
+            self.metrics.set_job_queue_depth(&kind, pending);
+        }
+        Ok(())
+    }
+
+    /// Polls for due jobs every `interval`, draining the queue a batch at a
+    /// time. Jobs cut off by a shutdown are retried once their lease ends.
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                loop {
+                    match self.run_due(BATCH_SIZE).await {
+                        Ok(run) if run == WorkerRun::default() => break,
+                        Ok(run) => {
+                            tracing::info!(
+                                succeeded = run.succeeded,
+                                retrying = run.retrying,
+                                failed = run.failed,
+                                "Jobs run"
+                            );
+                            if i64::from(run.total()) < BATCH_SIZE {
+                                break;
+                            }
+                        }
+                        Err(err) => {
+                            tracing::error!("Job run failed: {}", err);
+                            break;
+                        }
+                    }
+                }
+                if let Err(err) = self.sample_queue_depth().await {
+                    tracing::error!("Sampling the job queue failed: {}", err);
+                }
+            }
+        })
+    }
+}
@@ -1,14 +1,33 @@
+pub mod cache;
 pub mod config;
+pub mod currency;
+pub mod db;
+pub mod email;
 pub mod error;
+pub mod events;
 pub mod handlers;
+pub mod imports;
+pub mod jobs;
+pub mod logging;
 pub mod metrics;
 pub mod middleware;
 pub mod models;
+pub mod notifier;
+pub mod outbox;
+pub mod payments;
+pub mod reporting;
 pub mod repositories;
 pub mod server;
 pub mod services;
+pub mod shipping;
+pub mod shutdown;
 pub mod state;
+pub mod storage;
+pub mod tasks;
+pub mod tax;
+pub mod testing;
 pub mod utils;
+pub mod webhooks;
 
 pub use error::{AppError, Result};
 pub use state::AppState;
@@ -1,20 +1,35 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
 use markethub::config::Config;
-use markethub::server;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use markethub::{logging, server};
+
+const USAGE: &str = "usage: markethub [--config <file.toml>] [--print-config]";
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "info,markethub=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let mut config_file = None;
+    let mut print_config = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--config" => {
+                config_file = Some(PathBuf::from(args.next().context(USAGE)?));
+            }
+            "--print-config" => print_config = true,
+            other => anyhow::bail!("unknown argument '{other}'\n{USAGE}"),
+        }
+    }
 
     // Load configuration
-    let config = Config::from_env()?;
+    let config = Config::load_from(config_file.as_deref()).await?;
+    if print_config {
+        println!("{:#?}", config.masked());
+        return Ok(());
+    }
+
+    // Initialize tracing
+    logging::init(config.log_format);
     tracing::info!("Starting MarketHub on {}:{}", config.host, config.port);
 
     // Start server
@@ -1,9 +1,19 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use once_cell::sync::Lazy;
 use prometheus::{
-    Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+    CounterVec, Encoder, Gauge, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec, Opts, Registry, TextEncoder,
 };
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Longest a scrape waits for a pool connection when timing acquisition.
+const POOL_ACQUIRE_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
 
 static HTTP_DURATION_BUCKETS: Lazy<Vec<f64>> =
     Lazy::new(|| vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]);
@@ -13,6 +23,20 @@ pub struct Metrics {
     registry: Registry,
     http_requests_total: IntCounterVec,
     http_request_duration_seconds: HistogramVec,
+    circuit_breaker_state: IntGaugeVec,
+    circuit_breaker_calls_total: IntCounterVec,
+    carts_expired_total: IntCounter,
+    cart_items_expired_total: IntCounter,
+    db_pool_connections: IntGauge,
+    db_pool_idle_connections: IntGauge,
+    db_pool_max_connections: IntGauge,
+    db_pool_acquire_wait_seconds: Gauge,
+    orders_created_total: IntCounter,
+    checkout_failures_total: IntCounterVec,
+    revenue_total: CounterVec,
+    users_registered_total: IntCounter,
+    job_queue_depth: IntGaugeVec,
+    job_runs_total: IntCounterVec,
 }
 
 impl Metrics {
@@ -39,17 +63,171 @@ impl Metrics {
         )
         .expect("histogram vec should initialize");
 
+        let circuit_breaker_state = IntGaugeVec::new(
+            Opts::new(
+                "circuit_breaker_state",
+                "Outbound circuit breaker state by target (0 closed, 1 open, 2 half-open)",
+            ),
+            &["target"],
+        )
+        .expect("gauge vec should initialize");
+
+        let circuit_breaker_calls_total = IntCounterVec::new(
+            Opts::new(
+                "circuit_breaker_calls_total",
+                "Outbound calls through circuit breakers by target/outcome",
+            ),
+            &["target", "outcome"],
+        )
+        .expect("counter vec should initialize");
+
+        let carts_expired_total = IntCounter::new(
+            "carts_expired_total",
+            "Idle user carts removed by the cart expiry job",
+        )
+        .expect("counter should initialize");
+
+        let cart_items_expired_total = IntCounter::new(
+            "cart_items_expired_total",
+            "Cart lines removed by the cart expiry job",
+        )
+        .expect("counter should initialize");
+
+        let db_pool_connections = IntGauge::new(
+            "db_pool_connections",
+            "Open database connections, idle or in use",
+        )
+        .expect("gauge should initialize");
+
+        let db_pool_idle_connections = IntGauge::new(
+            "db_pool_idle_connections",
+            "Open database connections not in use",
+        )
+        .expect("gauge should initialize");
+
+        let db_pool_max_connections = IntGauge::new(
+            "db_pool_max_connections",
+            "Most database connections the pool will open",
+        )
+        .expect("gauge should initialize");
+
+        let db_pool_acquire_wait_seconds = Gauge::new(
+            "db_pool_acquire_wait_seconds",
+            "Time the last scrape waited for a database connection",
+        )
+        .expect("gauge should initialize");
+
+        let orders_created_total =
+            IntCounter::new("orders_created_total", "Store orders placed at checkout")
+                .expect("counter should initialize");
+
+        let users_registered_total =
+            IntCounter::new("users_registered_total", "Accounts registered")
+                .expect("counter should initialize");
+
+        let checkout_failures_total = IntCounterVec::new(
+            Opts::new(
+                "checkout_failures_total",
+                "Checkouts that placed no order, by error code",
+            ),
+            &["reason"],
+        )
+        .expect("counter vec should initialize");
+
+        let revenue_total = CounterVec::new(
+            Opts::new(
+                "revenue_total",
+                "Total value of orders placed at checkout by store/currency",
+            ),
+            &["store_id", "currency"],
+        )
+        .expect("counter vec should initialize");
+
+        let job_queue_depth = IntGaugeVec::new(
+            Opts::new(
+                "job_queue_depth",
+                "Background jobs waiting to run or be retried by kind",
+            ),
+            &["kind"],
+        )
+        .expect("gauge vec should initialize");
+
+        let job_runs_total = IntCounterVec::new(
+            Opts::new(
+                "job_runs_total",
+                "Background job attempts by kind/outcome (succeeded, retrying, failed)",
+            ),
+            &["kind", "outcome"],
+        )
+        .expect("counter vec should initialize");
+
         registry
             .register(Box::new(http_requests_total.clone()))
             .expect("registry should register counter");
         registry
             .register(Box::new(http_request_duration_seconds.clone()))
             .expect("registry should register histogram");
+        registry
+            .register(Box::new(circuit_breaker_state.clone()))
+            .expect("registry should register gauge");
+        registry
+            .register(Box::new(circuit_breaker_calls_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(carts_expired_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(cart_items_expired_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(db_pool_connections.clone()))
+            .expect("registry should register gauge");
+        registry
+            .register(Box::new(db_pool_idle_connections.clone()))
+            .expect("registry should register gauge");
+        registry
+            .register(Box::new(db_pool_max_connections.clone()))
+            .expect("registry should register gauge");
+        registry
+            .register(Box::new(db_pool_acquire_wait_seconds.clone()))
+            .expect("registry should register gauge");
+        registry
+            .register(Box::new(orders_created_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(checkout_failures_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(revenue_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(users_registered_total.clone()))
+            .expect("registry should register counter");
+        registry
+            .register(Box::new(job_queue_depth.clone()))
+            .expect("registry should register gauge");
+        registry
+            .register(Box::new(job_runs_total.clone()))
+            .expect("registry should register counter");
 
         Self {
             registry,
             http_requests_total,
             http_request_duration_seconds,
+            circuit_breaker_state,
+            circuit_breaker_calls_total,
+            carts_expired_total,
+            cart_items_expired_total,
+            db_pool_connections,
+            db_pool_idle_connections,
+            db_pool_max_connections,
+            db_pool_acquire_wait_seconds,
+            orders_created_total,
+            checkout_failures_total,
+            revenue_total,
+            users_registered_total,
+            job_queue_depth,
+            job_runs_total,
         }
     }
 
@@ -63,6 +241,68 @@ impl Metrics {
             .observe(duration.as_secs_f64());
     }
 
+    pub fn set_breaker_state(&self, target: &str, state: i64) {
+        self.circuit_breaker_state
+            .with_label_values(&[target])
+            .set(state);
+    }
+
+    pub fn observe_breaker_call(&self, target: &str, outcome: &str) {
+        self.circuit_breaker_calls_total
+            .with_label_values(&[target, outcome])
+            .inc();
+    }
+
+    pub fn observe_cart_expiry(&self, carts: u64, items: u64) {
+        self.carts_expired_total.inc_by(carts);
+        self.cart_items_expired_total.inc_by(items);
+    }
+
+    /// Records one order placed at checkout and its total.
+    pub fn observe_order_created(&self, store_id: Uuid, currency: &str, total: Decimal) {
+        self.orders_created_total.inc();
+        self.revenue_total
+            .with_label_values(&[&store_id.to_string(), currency])
+            .inc_by(total.to_f64().unwrap_or_default());
+    }
+
+    pub fn observe_checkout_failure(&self, reason: &str) {
+        self.checkout_failures_total
+            .with_label_values(&[reason])
+            .inc();
+    }
+
+    pub fn observe_user_registered(&self) {
+        self.users_registered_total.inc();
+    }
+
+    pub fn set_job_queue_depth(&self, kind: &str, pending: i64) {
+        self.job_queue_depth.with_label_values(&[kind]).set(pending);
+    }
+
+    pub fn observe_job_run(&self, kind: &str, outcome: &str) {
+        self.job_runs_total
+            .with_label_values(&[kind, outcome])
+            .inc();
+    }
+
+    /// Reads the pool's connection counts and times how long a connection
+    /// takes to acquire right now; waits longer than the probe timeout are
+    /// reported as the timeout.
+    pub async fn sample_pool(&self, pool: &PgPool) {
+        self.db_pool_connections.set(i64::from(pool.size()));
+        self.db_pool_idle_connections.set(pool.num_idle() as i64);
+        self.db_pool_max_connections
+            .set(i64::from(pool.options().get_max_connections()));
+
+        let start = Instant::now();
+        let wait = match tokio::time::timeout(POOL_ACQUIRE_PROBE_TIMEOUT, pool.acquire()).await {
+            Ok(_) => start.elapsed(),
+            Err(_) => POOL_ACQUIRE_PROBE_TIMEOUT,
+        };
+        self.db_pool_acquire_wait_seconds.set(wait.as_secs_f64());
+    }
+
     pub fn encode(&self) -> Result<String, prometheus::Error> {
         let metric_families = self.registry.gather();
         let mut buffer = Vec::new();
@@ -94,4 +334,52 @@ mod tests {
         assert!(encoded.contains("/health"));
         assert!(encoded.contains("GET"));
     }
+
+    #[test]
+    fn metrics_encode_breaker_series() {
+        let metrics = Metrics::new();
+        metrics.set_breaker_state("payments", 1);
+        metrics.observe_breaker_call("payments", "rejected");
+
+        let encoded = metrics.encode().expect("metrics should encode");
+        assert!(encoded.contains("markethub_circuit_breaker_state{target=\"payments\"} 1"));
+        assert!(encoded.contains("outcome=\"rejected\""));
+    }
+
+    #[test]
+    fn metrics_encode_checkout_counters() {
+        let metrics = Metrics::new();
+        let store_id = Uuid::nil();
+        metrics.observe_order_created(store_id, "USD", Decimal::new(1250, 2));
+        metrics.observe_order_created(store_id, "USD", Decimal::new(750, 2));
+        metrics.observe_checkout_failure("CONFLICT");
+
+        let encoded = metrics.encode().expect("metrics should encode");
+        assert!(encoded.contains("markethub_orders_created_total 2"));
+        assert!(encoded.contains(&format!(
+            "markethub_revenue_total{{currency=\"USD\",store_id=\"{store_id}\"}} 20"
+        )));
+        assert!(encoded.contains("markethub_checkout_failures_total{reason=\"CONFLICT\"} 1"));
+    }
+
+    #[test]
+    fn metrics_encode_cart_expiry_counters() {
+        let metrics = Metrics::new();
+        metrics.observe_cart_expiry(2, 5);
+
+        let encoded = metrics.encode().expect("metrics should encode");
+        assert!(encoded.contains("markethub_carts_expired_total 2"));
+        assert!(encoded.contains("markethub_cart_items_expired_total 5"));
+    }
+
+    #[test]
+    fn metrics_encode_job_series() {
+        let metrics = Metrics::new();
+        metrics.set_job_queue_depth("email", 3);
+        metrics.observe_job_run("email", "retrying");
+
+        let encoded = metrics.encode().expect("metrics should encode");
+        assert!(encoded.contains("markethub_job_queue_depth{kind=\"email\"} 3"));
+        assert!(encoded.contains("markethub_job_runs_total{kind=\"email\",outcome=\"retrying\"} 1"));
+    }
 }
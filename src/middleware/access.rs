@@ -0,0 +1,181 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, Request},
+    middleware::Next,
+    response::Response,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ipnet::IpNet;
+use subtle::ConstantTimeEq;
+
+use crate::error::{AppError, Result};
+
+/// Who may reach an operational endpoint such as `/metrics` or the admin API.
+/// With nothing configured every request is let through. An allowlist
+/// restricts callers by peer address; credentials require either the bearer
+/// token or the basic-auth pair, whichever the scraper is set up to send.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointAccess {
+    bearer_token: Option<String>,
+    basic_credentials: Option<String>,
+    allowed_networks: Vec<IpNet>,
+}
+
+impl EndpointAccess {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+
+    pub fn with_basic_auth(mut self, username: &str, password: &str) -> Self {
+        self.basic_credentials = Some(format!("{username}:{password}"));
+        self
+    }
+
+    pub fn with_allowed_networks(mut self, networks: Vec<IpNet>) -> Self {
+        self.allowed_networks = networks;
+        self
+    }
+
+    pub fn check(&self, headers: &HeaderMap, peer: Option<IpAddr>) -> Result<()> {
+        if !self.allowed_networks.is_empty() {
+            let allowed = peer.map(|ip| ip.to_canonical()).is_some_and(|ip| {
+                self.allowed_networks
+                    .iter()
+                    .any(|network| network.contains(&ip))
+            });
+            if !allowed {
+                return Err(AppError::Authorization(
+                    "Address not allowed for this endpoint".to_string(),
+                ));
+            }
+        }
+
+        if self.bearer_token.is_none() && self.basic_credentials.is_none() {
+            return Ok(());
+        }
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        let matches = |expected: &Option<String>, given: Option<Vec<u8>>| match (expected, given) {
+            (Some(expected), Some(given)) => bool::from(expected.as_bytes().ct_eq(&given)),
+            _ => false,
+        };
+        let bearer = presented
+            .strip_prefix("Bearer ")
+            .map(|token| token.trim().as_bytes().to_vec());
+        let basic = presented
+            .strip_prefix("Basic ")
+            .and_then(|encoded| STANDARD.decode(encoded.trim()).ok());
+
+        if matches(&self.bearer_token, bearer) || matches(&self.basic_credentials, basic) {
+            Ok(())
+        } else {
+            Err(AppError::Authentication(
+                "Missing or invalid endpoint credentials".to_string(),
+            ))
+        }
+    }
+}
+
+/// Parses a comma-separated list of addresses and CIDR ranges
+/// (`10.0.0.0/8, 127.0.0.1`); a bare address is a single-host range.
+pub fn parse_networks(list: &str) -> std::result::Result<Vec<IpNet>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<IpNet>()
+                .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| format!("'{entry}' is not an IP address or CIDR range"))
+        })
+        .collect()
+}
+
+pub async fn require_access(
+    State(access): State<Arc<EndpointAccess>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response> {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    access.check(req.headers(), peer)?;
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn open_access_lets_everything_through() {
+        assert!(EndpointAccess::new().check(&HeaderMap::new(), None).is_ok());
+    }
+
+    #[test]
+    fn credentials_accept_bearer_or_basic() {
+        let access = EndpointAccess::new()
+            .with_bearer_token("scrape-token")
+            .with_basic_auth("prometheus", "s3cret");
+
+        assert!(access
+            .check(&authorization("Bearer scrape-token"), None)
+            .is_ok());
+        let basic = format!("Basic {}", STANDARD.encode("prometheus:s3cret"));
+        assert!(access.check(&authorization(&basic), None).is_ok());
+
+        assert!(matches!(
+            access.check(&HeaderMap::new(), None),
+            Err(AppError::Authentication(_))
+        ));
+        assert!(matches!(
+            access.check(&authorization("Bearer wrong"), None),
+            Err(AppError::Authentication(_))
+        ));
+        let wrong = format!("Basic {}", STANDARD.encode("prometheus:guess"));
+        assert!(access.check(&authorization(&wrong), None).is_err());
+    }
+
+    #[test]
+    fn allowlist_checks_the_peer_address() {
+        let access = EndpointAccess::new()
+            .with_allowed_networks(parse_networks("10.0.0.0/8, 127.0.0.1").unwrap());
+
+        let inside: IpAddr = "10.1.2.3".parse().unwrap();
+        let loopback: IpAddr = "::ffff:127.0.0.1".parse().unwrap();
+        let outside: IpAddr = "192.168.1.10".parse().unwrap();
+        assert!(access.check(&HeaderMap::new(), Some(inside)).is_ok());
+        assert!(access.check(&HeaderMap::new(), Some(loopback)).is_ok());
+        assert!(matches!(
+            access.check(&HeaderMap::new(), Some(outside)),
+            Err(AppError::Authorization(_))
+        ));
+        assert!(access.check(&HeaderMap::new(), None).is_err());
+    }
+
+    #[test]
+    fn invalid_network_entries_are_rejected() {
+        assert!(parse_networks("").unwrap().is_empty());
+        assert!(parse_networks("10.0.0.0/33").is_err());
+        assert!(parse_networks("localhost").is_err());
+    }
+}
@@ -4,7 +4,14 @@ use axum::{
 };
 use uuid::Uuid;
 
-use crate::{error::AppError, state::AppState};
+use crate::{
+    error::AppError, middleware::request_user::record_request_user, models::order::CartOwner,
+    repositories::UserRepository, services::AuthService, state::AppState,
+    utils::public_id::PublicIdKind,
+};
+
+/// Header carrying the signed token of a guest cart.
+pub const GUEST_TOKEN_HEADER: &str = "x-guest-token";
 
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
@@ -12,6 +19,8 @@ pub struct AuthenticatedUser {
     pub email: String,
 }
 
+/// Routes behind `require_permission` have authenticated the caller already
+/// and left them in the request extensions.
 impl FromRequestParts<AppState> for AuthenticatedUser {
     type Rejection = AppError;
 
@@ -19,16 +28,19 @@ impl FromRequestParts<AppState> for AuthenticatedUser {
         parts: &mut Parts,
         state: &AppState,
     ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        let authenticated = parts.extensions.get::<AuthenticatedUser>().cloned();
         let token = bearer_token(parts).map(|value| value.to_string());
-        let jwt = state.jwt.clone();
+        let service = auth_service(state);
 
         async move {
+            if let Some(user) = authenticated {
+                return Ok(user);
+            }
             let token =
                 token.ok_or_else(|| AppError::Authentication("Missing bearer token".into()))?;
 
-            let claims = jwt
-                .verify(&token)
-                .map_err(|_| AppError::Authentication("Invalid token".into()))?;
+            let claims = service.authenticate(&token).await?;
+            record_request_user(claims.sub);
 
             Ok(Self {
                 user_id: claims.sub,
@@ -49,14 +61,13 @@ impl FromRequestParts<AppState> for MaybeAuthenticatedUser {
         state: &AppState,
     ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
         let token = bearer_token(parts).map(|value| value.to_string());
-        let jwt = state.jwt.clone();
+        let service = auth_service(state);
 
         async move {
             match token {
                 Some(token) => {
-                    let claims = jwt
-                        .verify(&token)
-                        .map_err(|_| AppError::Authentication("Invalid token".into()))?;
+                    let claims = service.authenticate(&token).await?;
+                    record_request_user(claims.sub);
                     Ok(Self(Some(AuthenticatedUser {
                         user_id: claims.sub,
                         email: claims.email,
@@ -68,6 +79,55 @@ impl FromRequestParts<AppState> for MaybeAuthenticatedUser {
     }
 }
 
+/// Signed-in users use their own cart; anyone else needs a guest token.
+impl FromRequestParts<AppState> for CartOwner {
+    type Rejection = AppError;
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        let token = bearer_token(parts).map(|value| value.to_string());
+        let guest = guest_token(parts).map(|value| guest_session_id(state, value));
+        let service = auth_service(state);
+
+        async move {
+            match (token, guest) {
+                (Some(token), _) => {
+                    let claims = service.authenticate(&token).await?;
+                    Ok(CartOwner::User(claims.sub))
+                }
+                (None, Some(session_id)) => session_id
+                    .map(CartOwner::Guest)
+                    .ok_or_else(|| AppError::Authentication("Invalid guest token".into())),
+                (None, None) => Err(AppError::Authentication(
+                    "Sign in or start a guest cart".into(),
+                )),
+            }
+        }
+    }
+}
+
+/// The guest session behind a token, or `None` for tokens this server did
+/// not issue.
+pub fn guest_session_id(state: &AppState, token: &str) -> Option<Uuid> {
+    match state.public_ids.decode(token)? {
+        (PublicIdKind::GuestCart, session_id) => Some(session_id),
+        _ => None,
+    }
+}
+
+fn guest_token(parts: &Parts) -> Option<&str> {
+    parts
+        .headers
+        .get(GUEST_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+}
+
+fn auth_service(state: &AppState) -> AuthService {
+    AuthService::new(UserRepository::new(state.db.clone()), state.jwt.clone())
+}
+
 fn bearer_token(parts: &mut Parts) -> Option<&str> {
     parts
         .headers
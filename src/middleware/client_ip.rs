@@ -0,0 +1,28 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request},
+    middleware::Next,
+    response::Response,
+};
+
+tokio::task_local! {
+    static CLIENT_IP: Option<IpAddr>;
+}
+
+/// The address of the peer that sent the request being handled, for code
+/// that records it away from the request itself, such as the audit log.
+pub fn current_client_ip() -> Option<IpAddr> {
+    CLIENT_IP.try_with(|ip| *ip).ok().flatten()
+}
+
+/// Makes the peer address of every request available through
+/// [`current_client_ip`] while it is handled.
+pub async fn capture_client_ip(req: Request<Body>, next: Next) -> Response {
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    CLIENT_IP.scope(ip, next.run(req)).await
+}
@@ -0,0 +1,97 @@
+use axum::http::{header, Extensions, HeaderMap, StatusCode, Version};
+use tower_http::compression::{
+    predicate::{And, Predicate, SizeAbove},
+    CompressionLayer,
+};
+
+type ContentTypeFilter = fn(StatusCode, Version, &HeaderMap, &Extensions) -> bool;
+
+pub type CompressionPredicate = And<SizeAbove, ContentTypeFilter>;
+
+/// Response compression for text-like bodies of at least `min_size` bytes.
+/// Streamed bodies have no known size and are always compressed when their
+/// type qualifies, chunk by chunk as they are produced.
+pub fn layer(min_size: u16) -> CompressionLayer<CompressionPredicate> {
+    CompressionLayer::new().compress_when(predicate(min_size))
+}
+
+pub fn predicate(min_size: u16) -> CompressionPredicate {
+    SizeAbove::new(min_size).and(compressible as ContentTypeFilter)
+}
+
+fn compressible(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    is_compressible(headers)
+}
+
+/// Text, JSON, XML and CSV compress well; images, archives, downloads of
+/// already-compressed files and event streams are passed through untouched.
+fn is_compressible(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return false;
+    };
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    if essence == "text/event-stream" {
+        return false;
+    }
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence.as_str(),
+            "application/json"
+                | "application/x-ndjson"
+                | "application/xml"
+                | "application/javascript"
+                | "image/svg+xml"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Response};
+    use bytes::Bytes;
+    use futures_util::stream;
+
+    fn response(content_type: &str, body: Body) -> Response<Body> {
+        Response::builder()
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+    }
+
+    fn should_compress(response: &Response<Body>) -> bool {
+        predicate(64).should_compress(response)
+    }
+
+    #[test]
+    fn small_and_binary_bodies_are_not_compressed() {
+        let json = response("application/json", Body::from("x".repeat(128)));
+        assert!(should_compress(&json));
+
+        let small = response("application/json", Body::from("{}"));
+        assert!(!should_compress(&small));
+
+        let image = response("image/png", Body::from(vec![0u8; 128]));
+        assert!(!should_compress(&image));
+
+        let events = response("text/event-stream", Body::from("x".repeat(128)));
+        assert!(!should_compress(&events));
+    }
+
+    #[test]
+    fn streamed_exports_are_compressed() {
+        let chunks = stream::iter([Ok::<_, std::io::Error>(Bytes::from_static(b"sku\n"))]);
+        let csv = response("text/csv; charset=utf-8", Body::from_stream(chunks));
+        assert!(should_compress(&csv));
+    }
+}
@@ -0,0 +1,30 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::{error::AppError, utils::validators::validate_currency_code};
+
+/// Header naming the currency prices should be shown in, e.g. `EUR`.
+pub const CURRENCY_HEADER: &str = "x-currency";
+
+/// The ISO 4217 code from `X-Currency`, upper-cased; `None` when the header
+/// is absent and prices stay in each store's currency.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayCurrency(pub Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for DisplayCurrency {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Some(value) = parts.headers.get(CURRENCY_HEADER) else {
+            return Ok(Self(None));
+        };
+        let code = value
+            .to_str()
+            .map(|code| code.trim().to_uppercase())
+            .ok()
+            .filter(|code| validate_currency_code(code).is_ok())
+            .ok_or_else(|| {
+                AppError::BadRequest("X-Currency must be a three-letter currency code".into())
+            })?;
+        Ok(Self(Some(code)))
+    }
+}
@@ -0,0 +1,333 @@
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use axum::{
+    body::{to_bytes, Body, Bytes},
+    extract::{FromRequestParts, Request},
+    http::{header, request::Parts, HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use sha2::{Digest, Sha256};
+use tower::{Layer, Service};
+
+use crate::{
+    error::{AppError, Result},
+    middleware::auth::AuthenticatedUser,
+    models::idempotency::IdempotencyRecord,
+    repositories::{idempotency_repo::IdempotencyClaim, IdempotencyRepository},
+    state::AppState,
+};
+
+/// Header a client sets to make a POST or PATCH safe to retry.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Set on responses replayed from an earlier request with the same key.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+const MAX_KEY_LEN: usize = 255;
+/// How long a claimed key waits for its response before a retry may take it
+/// over, in case the process handling it died.
+const CLAIM_LEASE_SECS: i64 = 60;
+/// Largest request or response body kept for replay.
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Makes POST and PATCH requests carrying an `Idempotency-Key` safe to
+/// retry: the first response is stored for the key, and later requests with
+/// the same key and the same method, path, query and body get it back without the
+/// handler running again. Keys are scoped to the authenticated user and
+/// last `AppState::idempotency_ttl`; server errors are not stored, so the
+/// request can be retried. A request that dies before answering holds its
+/// key for `CLAIM_LEASE_SECS` at most.
+///
+/// ```ignore
+/// .route("/{store_id}/grant", post(grant_access).route_layer(idempotent()))
+/// ```
+pub fn idempotent() -> Idempotent {
+    Idempotent
+}
+
+/// Layer storing and replaying responses; see [`idempotent`]. The app state
+/// is read from the request extensions, where `server::with_middleware`
+/// puts it.
+#[derive(Debug, Clone, Copy)]
+pub struct Idempotent;
+
+impl<S> Layer<S> for Idempotent {
+    type Service = IdempotentService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IdempotentService { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IdempotentService<S> {
+    inner: S,
+}
+
+impl<S> Service<Request> for IdempotentService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, std::result::Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Infallible>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The clone may not be ready; call the instance that was polled.
+        let ready = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, ready);
+
+        Box::pin(async move {
+            if !matches!(*request.method(), Method::POST | Method::PATCH)
+                || !request.headers().contains_key(IDEMPOTENCY_KEY_HEADER)
+            {
+                return inner.call(request).await;
+            }
+
+            let (mut parts, body) = request.into_parts();
+            let Ok(body) = to_bytes(body, MAX_BODY_BYTES).await else {
+                return Ok(AppError::BadRequest(
+                    "Request body is too large to be made idempotent".into(),
+                )
+                .into_response());
+            };
+            let attempt = match Attempt::start(&mut parts, body.clone()).await {
+                Ok(Some(attempt)) => attempt,
+                // Anonymous callers get no idempotency; the handler decides
+                // whether to let them in at all.
+                Ok(None) => {
+                    return inner
+                        .call(Request::from_parts(parts, Body::from(body)))
+                        .await
+                }
+                Err(err) => return Ok(err.into_response()),
+            };
+
+            match attempt.claim().await {
+                Ok(IdempotencyClaim::Claimed) => {
+                    let request = Request::from_parts(parts, Body::from(body));
+                    // Handled on its own task so a client hanging up does
+                    // not cancel it halfway: the response is still stored
+                    // for the retry. A panic frees the key instead.
+                    let handled = tokio::spawn({
+                        let attempt = attempt.clone();
+                        async move {
+                            let response = inner.call(request).await?;
+                            Ok(attempt.finish(response).await)
+                        }
+                    });
+                    match handled.await {
+                        Ok(result) => result,
+                        Err(err) => {
+                            attempt.release().await;
+                            Ok(AppError::Internal(anyhow::anyhow!(err)).into_response())
+                        }
+                    }
+                }
+                Ok(IdempotencyClaim::Existing(record)) => Ok(attempt.replay(record)),
+                Ok(IdempotencyClaim::Contended) => Ok(in_progress().into_response()),
+                Err(err) => Ok(err.into_response()),
+            }
+        })
+    }
+}
+
+/// A keyed request being made idempotent.
+#[derive(Clone)]
+struct Attempt {
+    repo: IdempotencyRepository,
+    ttl: chrono::Duration,
+    user: AuthenticatedUser,
+    key: String,
+    method: String,
+    path: String,
+    /// Path and query string, which the request fingerprint covers.
+    target: String,
+    body: Bytes,
+}
+
+impl Attempt {
+    /// Reads the key and caller of the request; `None` when the caller is
+    /// not signed in.
+    async fn start(parts: &mut Parts, body: Bytes) -> Result<Option<Self>> {
+        let state = parts
+            .extensions
+            .get::<AppState>()
+            .cloned()
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("App state is not installed")))?;
+        let key = parts
+            .headers
+            .get(IDEMPOTENCY_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|key| is_valid_key(key))
+            .ok_or_else(|| {
+                AppError::BadRequest(format!(
+                    "Idempotency-Key must be 1 to {} printable characters",
+                    MAX_KEY_LEN
+                ))
+            })?
+            .to_string();
+        let Ok(user) = AuthenticatedUser::from_request_parts(parts, &state).await else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            repo: IdempotencyRepository::new(state.db.clone()),
+            ttl: state.idempotency_ttl,
+            user,
+            key,
+            method: parts.method.to_string(),
+            path: parts.uri.path().to_string(),
+            target: parts
+                .uri
+                .path_and_query()
+                .map_or_else(|| parts.uri.path(), |target| target.as_str())
+                .to_string(),
+            body,
+        }))
+    }
+
+    fn request_hash(&self) -> Vec<u8> {
+        request_hash(&self.method, &self.target, &self.body)
+    }
+
+    async fn claim(&self) -> Result<IdempotencyClaim> {
+        let now = chrono::Utc::now();
+        self.repo
+            .claim(
+                self.user.user_id,
+                &self.key,
+                &self.method,
+                &self.path,
+                &self.request_hash(),
+                now + self.ttl,
+                now + chrono::Duration::seconds(CLAIM_LEASE_SECS),
+            )
+            .await
+    }
+
+    /// Stores the handler's response for the key, or frees the key when the
+    /// request failed on our side.
+    async fn finish(self, response: Response) -> Response {
+        let (parts, body) = response.into_parts();
+        let body = match to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(body) if !parts.status.is_server_error() => body,
+            result => {
+                self.release().await;
+                return match result {
+                    Ok(body) => Response::from_parts(parts, Body::from(body)),
+                    Err(err) => AppError::Internal(anyhow::anyhow!(err)).into_response(),
+                };
+            }
+        };
+
+        let content_type = parts
+            .headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if let Err(err) = self
+            .repo
+            .complete(
+                self.user.user_id,
+                &self.key,
+                parts.status.as_u16() as i16,
+                content_type,
+                &body,
+            )
+            .await
+        {
+            tracing::error!("Storing idempotent response failed: {}", err);
+            self.release().await;
+        }
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    async fn release(&self) {
+        if let Err(err) = self.repo.release(self.user.user_id, &self.key).await {
+            tracing::error!("Releasing idempotency key failed: {}", err);
+        }
+    }
+
+    /// The stored response for a request made earlier with the same key.
+    fn replay(&self, record: IdempotencyRecord) -> Response {
+        if record.request_hash != self.request_hash() {
+            return AppError::BadRequest(
+                "Idempotency-Key was already used for a different request".into(),
+            )
+            .into_response();
+        }
+        let Some(status) = record
+            .status_code
+            .and_then(|code| StatusCode::from_u16(code as u16).ok())
+        else {
+            return in_progress().into_response();
+        };
+
+        let mut response = Response::new(Body::from(record.response_body.unwrap_or_default()));
+        *response.status_mut() = status;
+        let headers = response.headers_mut();
+        if let Some(content_type) = record
+            .content_type
+            .and_then(|value| HeaderValue::from_str(&value).ok())
+        {
+            headers.insert(header::CONTENT_TYPE, content_type);
+        }
+        headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// Fingerprint telling a retry apart from another request reusing its key.
+fn request_hash(method: &str, target: &str, body: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update([0]);
+    hasher.update(target.as_bytes());
+    hasher.update([0]);
+    hasher.update(body);
+    hasher.finalize().to_vec()
+}
+
+fn in_progress() -> AppError {
+    AppError::Conflict("A request with this Idempotency-Key is still being processed".into())
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LEN && key.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_must_be_short_printable_ascii() {
+        assert!(is_valid_key("9b2f6c1e-retry"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("with space"));
+        assert!(!is_valid_key(&"k".repeat(MAX_KEY_LEN + 1)));
+    }
+
+    #[test]
+    fn fingerprints_cover_the_query_string() {
+        let body = br#"{"sku":"SKU-1"}"#;
+        let hash = request_hash("POST", "/api/v1/products?notify=true", body);
+        assert_eq!(
+            request_hash("POST", "/api/v1/products?notify=true", body),
+            hash
+        );
+        assert_ne!(
+            request_hash("POST", "/api/v1/products?notify=false", body),
+            hash
+        );
+        assert_ne!(request_hash("POST", "/api/v1/products", body), hash);
+    }
+}
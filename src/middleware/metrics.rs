@@ -1,16 +1,34 @@
 use std::time::Instant;
 
-use axum::{body::Body, extract::State, http::Request, middleware::Next, response::Response};
+use axum::{
+    body::Body,
+    extract::{MatchedPath, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
 
 use crate::state::AppState;
 
+/// Path label for requests that matched no route, so scans of random URLs
+/// share one series. Files served from `/uploads` are counted here too, as
+/// nested services have no route template.
+pub const UNMATCHED_PATH: &str = "unmatched";
+
+/// Records every request under the route template it matched
+/// (`/api/v1/stores/{store_id}/analytics`) rather than its raw path, which
+/// keeps the number of `path` label values bounded by the number of routes.
 pub async fn track_metrics(
     State(state): State<AppState>,
     req: Request<Body>,
     next: Next,
 ) -> Response {
     let method = req.method().clone();
-    let path = req.uri().path().to_string();
+    let path = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_PATH, MatchedPath::as_str)
+        .to_string();
     let start = Instant::now();
 
     let response = next.run(req).await;
@@ -1,3 +1,11 @@
+pub mod access;
 pub mod auth;
+pub mod client_ip;
+pub mod compression;
+pub mod currency;
+pub mod idempotency;
 pub mod metrics;
 pub mod permissions;
+pub mod request_id;
+pub mod request_user;
+pub mod security_headers;
@@ -1,7 +1,23 @@
+use std::{
+    convert::Infallible,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::{FromRequestParts, RawPathParams, Request},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use futures_util::future::BoxFuture;
+use tower::{Layer, Service};
 use uuid::Uuid;
 
 use crate::{
-    error::Result, models::permission::Permission, services::permission_service::PermissionService,
+    error::{AppError, Result},
+    middleware::auth::AuthenticatedUser,
+    models::permission::Permission,
+    repositories::ProductRepository,
+    services::permission_service::PermissionService,
     state::AppState,
 };
 
@@ -11,8 +27,156 @@ pub async fn ensure_store_permission(
     store_id: Uuid,
     permission: Permission,
 ) -> Result<()> {
-    let service = PermissionService::new(state.db.clone());
+    let service = PermissionService::new(state.db.clone()).with_cache(state.cache.clone());
     service
         .ensure_store_permission(user_id, store_id, permission)
         .await
 }
+
+pub async fn ensure_member_permission(
+    state: &AppState,
+    user_id: Uuid,
+    store_id: Uuid,
+    permission: Permission,
+) -> Result<()> {
+    let service = PermissionService::new(state.db.clone());
+    service
+        .ensure_member_permission(user_id, store_id, permission)
+        .await
+}
+
+pub async fn ensure_platform_admin(state: &AppState, user_id: Uuid) -> Result<()> {
+    let service = PermissionService::new(state.db.clone());
+    service.ensure_platform_admin(user_id).await
+}
+
+/// Requires the caller to hold `permission` in the store named by the
+/// route's `{store_id}` segment, or selling its `{product_id}`, checked as
+/// `ensure_store_permission` does, before the handler runs:
+///
+/// ```ignore
+/// .route("/{store_id}/members", get(list_members))
+/// .route_layer(require_permission(Permission::ViewMembers))
+/// ```
+pub fn require_permission(permission: Permission) -> RequirePermission {
+    RequirePermission {
+        permission,
+        members_only: false,
+    }
+}
+
+/// Like [`require_permission`], but only store members qualify, as with
+/// `ensure_member_permission`.
+pub fn require_member_permission(permission: Permission) -> RequirePermission {
+    RequirePermission {
+        permission,
+        members_only: true,
+    }
+}
+
+/// Layer enforcing a store permission. The app state is read from the
+/// request extensions, where `server::with_middleware` puts it, and the
+/// authenticated caller is left there for the handler's
+/// `AuthenticatedUser` extractor.
+#[derive(Debug, Clone, Copy)]
+pub struct RequirePermission {
+    permission: Permission,
+    members_only: bool,
+}
+
+impl RequirePermission {
+    async fn authorize(self, parts: &mut Parts) -> Result<()> {
+        let state = parts
+            .extensions
+            .get::<AppState>()
+            .cloned()
+            .ok_or_else(|| AppError::Internal(anyhow::anyhow!("App state is not installed")))?;
+        let user = AuthenticatedUser::from_request_parts(parts, &state).await?;
+        let store_id = store_id_param(parts, &state).await?;
+
+        if self.members_only {
+            ensure_member_permission(&state, user.user_id, store_id, self.permission).await?;
+        } else {
+            ensure_store_permission(&state, user.user_id, store_id, self.permission).await?;
+        }
+
+        parts.extensions.insert(user);
+        Ok(())
+    }
+}
+
+/// The store the matched route is about: its `{store_id}` segment or, for
+/// product routes, the store selling the `{product_id}`.
+async fn store_id_param(parts: &mut Parts, state: &AppState) -> Result<Uuid> {
+    let params = RawPathParams::from_request_parts(parts, state)
+        .await
+        .map_err(|err| AppError::Internal(anyhow::anyhow!(err.body_text())))?;
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.parse::<Uuid>())
+    };
+
+    if let Some(store_id) = param("store_id") {
+        return store_id.map_err(|_| AppError::BadRequest("Invalid store id".into()));
+    }
+    if let Some(product_id) = param("product_id") {
+        let product_id =
+            product_id.map_err(|_| AppError::BadRequest("Invalid product id".into()))?;
+        return ProductRepository::new(state.db.clone())
+            .find_by_id(product_id)
+            .await?
+            .map(|product| product.store_id)
+            .ok_or_else(|| AppError::NotFound("Product not found".into()));
+    }
+    Err(AppError::Internal(anyhow::anyhow!(
+        "Route names neither a store nor a product"
+    )))
+}
+
+impl<S> Layer<S> for RequirePermission {
+    type Service = RequirePermissionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RequirePermissionService {
+            inner,
+            requirement: *self,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RequirePermissionService<S> {
+    inner: S,
+    requirement: RequirePermission,
+}
+
+impl<S> Service<Request> for RequirePermissionService<S>
+where
+    S: Service<Request, Response = Response, Error = Infallible> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, std::result::Result<Response, Infallible>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Infallible>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The clone may not be ready; call the instance that was polled.
+        let ready = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, ready);
+        let requirement = self.requirement;
+
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            match requirement.authorize(&mut parts).await {
+                Ok(()) => inner.call(Request::from_parts(parts, body)).await,
+                Err(err) => Ok(err.into_response()),
+            }
+        })
+    }
+}
@@ -0,0 +1,118 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Span;
+use uuid::Uuid;
+
+use crate::middleware::metrics::UNMATCHED_PATH;
+
+/// Header carrying the id of a request, taken from the client when it sends
+/// one and generated otherwise. Responses echo it back.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied id that is kept; longer ones are replaced.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled, for responses and logs built away
+/// from the request itself.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Assigns every request an id, exposes it to the rest of the stack through
+/// the `X-Request-Id` request header and [`current_request_id`], and returns
+/// it on the response.
+pub async fn assign_request_id(mut req: Request<Body>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let header = HeaderValue::from_str(&request_id).expect("request ids are valid header values");
+    req.headers_mut().insert(REQUEST_ID_HEADER, header.clone());
+
+    let mut response = REQUEST_ID.scope(request_id, next.run(req)).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, header);
+    response
+}
+
+/// Tracing span for a request, tagged with its id and the route template it
+/// matched. It is an info span so every line logged while handling the
+/// request carries them.
+pub fn make_span(req: &Request<Body>) -> Span {
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_PATH, MatchedPath::as_str);
+    tracing::info_span!(
+        "request",
+        method = %req.method(),
+        uri = %req.uri(),
+        route = %route,
+        version = ?req.version(),
+        request_id = %request_id,
+    )
+}
+
+/// Logs the start of a request with its headers.
+pub fn log_request<B>(req: &Request<B>, _span: &Span) {
+    tracing::debug!(headers = ?req.headers(), "started processing request");
+}
+
+/// Logs the end of a request with its status and latency in milliseconds.
+pub fn log_response<B>(response: &Response<B>, latency: Duration, _span: &Span) {
+    tracing::info!(
+        status = response.status().as_u16(),
+        latency_ms = latency.as_secs_f64() * 1000.0,
+        headers = ?response.headers(),
+        "finished processing request"
+    );
+}
+
+/// Client ids are kept when they are short and made of printable ASCII
+/// without spaces, so they are safe to log and to echo back.
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_request_ids_must_be_short_printable_ascii() {
+        assert!(is_valid_request_id("4f1c2a9e-client"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("has space"));
+        assert!(!is_valid_request_id("bad\nline"));
+        assert!(!is_valid_request_id(&"a".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+
+    #[tokio::test]
+    async fn current_request_id_is_scoped_to_the_request() {
+        assert_eq!(current_request_id(), None);
+        let id = REQUEST_ID
+            .scope("req-1".to_string(), async { current_request_id() })
+            .await;
+        assert_eq!(id.as_deref(), Some("req-1"));
+    }
+}
@@ -0,0 +1,51 @@
+use std::sync::OnceLock;
+
+use axum::{body::Body, http::Request, middleware::Next, response::Response};
+use uuid::Uuid;
+
+tokio::task_local! {
+    static REQUEST_USER: OnceLock<Uuid>;
+}
+
+/// The user the request being handled was authenticated as, once a handler
+/// or middleware has done so; for error reports and logs built away from
+/// the request itself.
+pub fn current_request_user() -> Option<Uuid> {
+    REQUEST_USER
+        .try_with(|user| user.get().copied())
+        .ok()
+        .flatten()
+}
+
+/// Notes the authenticated caller of the current request. The first user
+/// recorded is kept.
+pub fn record_request_user(user_id: Uuid) {
+    let _ = REQUEST_USER.try_with(|user| user.set(user_id));
+}
+
+/// Gives every request a slot for [`record_request_user`] to fill.
+pub async fn track_request_user(req: Request<Body>, next: Next) -> Response {
+    REQUEST_USER.scope(OnceLock::new(), next.run(req)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn request_user_is_scoped_to_the_request() {
+        record_request_user(Uuid::nil());
+        assert_eq!(current_request_user(), None);
+
+        let user_id = Uuid::new_v4();
+        let seen = REQUEST_USER
+            .scope(OnceLock::new(), async {
+                assert_eq!(current_request_user(), None);
+                record_request_user(user_id);
+                record_request_user(Uuid::nil());
+                current_request_user()
+            })
+            .await;
+        assert_eq!(seen, Some(user_id));
+    }
+}
@@ -0,0 +1,147 @@
+use std::{str::FromStr, sync::Arc};
+
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+
+/// Whether browsers may show our responses inside frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameOptions {
+    Deny,
+    SameOrigin,
+}
+
+impl FrameOptions {
+    fn header_value(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            FrameOptions::Deny => "DENY",
+            FrameOptions::SameOrigin => "SAMEORIGIN",
+        })
+    }
+}
+
+impl FromStr for FrameOptions {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "DENY" => Ok(FrameOptions::Deny),
+            "SAMEORIGIN" => Ok(FrameOptions::SameOrigin),
+            other => Err(format!(
+                "unknown frame option '{other}', expected DENY or SAMEORIGIN"
+            )),
+        }
+    }
+}
+
+/// Headers every response carries so browsers treat it safely: HSTS,
+/// `X-Content-Type-Options: nosniff` and `X-Frame-Options`. Headers a
+/// handler already set are left alone.
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    hsts: Option<HeaderValue>,
+    frame_options: FrameOptions,
+}
+
+impl Default for SecurityHeaders {
+    /// HSTS for a year and no framing.
+    fn default() -> Self {
+        Self::new().with_hsts(31_536_000, false)
+    }
+}
+
+impl SecurityHeaders {
+    /// No HSTS and no framing.
+    pub fn new() -> Self {
+        Self {
+            hsts: None,
+            frame_options: FrameOptions::Deny,
+        }
+    }
+
+    /// Tells browsers to use HTTPS only for `max_age_secs`; 0 leaves HSTS
+    /// off, e.g. for deployments still reachable over plain HTTP.
+    pub fn with_hsts(mut self, max_age_secs: u64, include_subdomains: bool) -> Self {
+        self.hsts = (max_age_secs > 0).then(|| {
+            let mut value = format!("max-age={max_age_secs}");
+            if include_subdomains {
+                value.push_str("; includeSubDomains");
+            }
+            HeaderValue::from_str(&value).expect("HSTS values are valid header values")
+        });
+        self
+    }
+
+    pub fn with_frame_options(mut self, frame_options: FrameOptions) -> Self {
+        self.frame_options = frame_options;
+        self
+    }
+
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Some(hsts) = &self.hsts {
+            headers
+                .entry(header::STRICT_TRANSPORT_SECURITY)
+                .or_insert_with(|| hsts.clone());
+        }
+        headers
+            .entry(header::X_CONTENT_TYPE_OPTIONS)
+            .or_insert_with(|| HeaderValue::from_static("nosniff"));
+        headers
+            .entry(header::X_FRAME_OPTIONS)
+            .or_insert_with(|| self.frame_options.header_value());
+    }
+}
+
+pub async fn set_security_headers(
+    State(headers): State<Arc<SecurityHeaders>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let mut response = next.run(req).await;
+    headers.apply(response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_send_hsts_nosniff_and_deny_framing() {
+        let mut headers = HeaderMap::new();
+        SecurityHeaders::default().apply(&mut headers);
+
+        assert_eq!(
+            headers[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=31536000"
+        );
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+    }
+
+    #[test]
+    fn hsts_can_be_turned_off_and_handler_headers_are_kept() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::X_FRAME_OPTIONS,
+            HeaderValue::from_static("SAMEORIGIN"),
+        );
+        SecurityHeaders::default()
+            .with_hsts(0, true)
+            .apply(&mut headers);
+
+        assert!(headers.get(header::STRICT_TRANSPORT_SECURITY).is_none());
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+    }
+
+    #[test]
+    fn frame_options_parse_case_insensitively() {
+        assert_eq!("sameorigin".parse(), Ok(FrameOptions::SameOrigin));
+        assert_eq!("DENY".parse(), Ok(FrameOptions::Deny));
+        assert!("ALLOW-FROM x".parse::<FrameOptions>().is_err());
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "audit_action", rename_all = "PascalCase")]
+pub enum AuditAction {
+    Login,
+    LoginFailed,
+    PermissionChange,
+    PriceChange,
+    OrderStatusChange,
+    Refund,
+}
+
+/// A sensitive action as recorded in the audit log. `diff` maps each field
+/// that changed to its `from` and `to` values, or describes the action
+/// when nothing was changed, as for logins.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub store_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub entity_type: String,
+    pub entity_id: Option<Uuid>,
+    pub diff: Value,
+    pub ip_address: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An action to record. The client IP and request id are taken from the
+/// request being handled.
+#[derive(Debug, Clone)]
+pub struct NewAuditEvent {
+    pub store_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub action: AuditAction,
+    pub entity_type: &'static str,
+    pub entity_id: Option<Uuid>,
+    pub diff: Value,
+}
+
+/// Narrows an audit log query; unset fields match everything.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuditEventFilter {
+    pub action: Option<AuditAction>,
+    pub actor_id: Option<Uuid>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// The `from` and `to` values of a changed field, as stored in a diff.
+pub fn change<T: Serialize>(from: T, to: T) -> Value {
+    serde_json::json!({ "from": from, "to": to })
+}
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "coupon_kind", rename_all = "PascalCase")]
+pub enum CouponKind {
+    /// `value` is a percentage of the store subtotal.
+    Percent,
+    /// `value` is an amount off the store subtotal.
+    Fixed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Coupon {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub code: String,
+    pub kind: CouponKind,
+    pub value: Decimal,
+    pub min_order_value: Option<Decimal>,
+    pub usage_limit: Option<i32>,
+    pub times_used: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Coupon {
+    /// Why the coupon cannot be used on an order with this store subtotal,
+    /// if it cannot.
+    pub fn ineligibility(&self, subtotal: Decimal, now: DateTime<Utc>) -> Option<String> {
+        if !self.is_active {
+            return Some(format!("Coupon {} is no longer active", self.code));
+        }
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Some(format!("Coupon {} has expired", self.code));
+        }
+        if self
+            .usage_limit
+            .is_some_and(|limit| self.times_used >= limit)
+        {
+            return Some(format!("Coupon {} has been fully redeemed", self.code));
+        }
+        match self.min_order_value {
+            Some(minimum) if subtotal < minimum => Some(format!(
+                "Coupon {} needs an order of at least {}",
+                self.code, minimum
+            )),
+            _ => None,
+        }
+    }
+
+    /// The amount taken off `subtotal`, never more than the subtotal itself.
+    pub fn discount_for(&self, subtotal: Decimal) -> Decimal {
+        let discount = match self.kind {
+            CouponKind::Percent => (subtotal * self.value / Decimal::ONE_HUNDRED).round_dp(2),
+            CouponKind::Fixed => self.value,
+        };
+        discount.min(subtotal)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_coupon_value"))]
+pub struct CreateCouponRequest {
+    #[validate(
+        length(min = 3, max = 40),
+        custom(function = "crate::utils::validators::validate_coupon_code")
+    )]
+    pub code: String,
+
+    pub kind: CouponKind,
+
+    #[validate(range(min = 0.01, max = 100000.0))]
+    pub value: f64,
+
+    #[validate(range(min = 0.0, max = 1000000.0))]
+    pub min_order_value: Option<f64>,
+
+    #[validate(range(min = 1))]
+    pub usage_limit: Option<i32>,
+
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn validate_coupon_value(request: &CreateCouponRequest) -> Result<(), ValidationError> {
+    if request.kind == CouponKind::Percent && request.value > 100.0 {
+        return Err(ValidationError::new("percent_over_100"));
+    }
+    Ok(())
+}
+
+/// Codes are looked up among the stores in the cart; `store_id` picks one
+/// when several of them use the same code.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ApplyCouponRequest {
+    #[validate(length(min = 1, max = 40))]
+    pub code: String,
+
+    pub store_id: Option<Uuid>,
+}
+
+/// A coupon applied to the shopper's cart and what it takes off the
+/// current lines of its store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedCoupon {
+    pub store_id: Uuid,
+    pub code: String,
+    pub kind: CouponKind,
+    pub value: Decimal,
+    pub subtotal: Decimal,
+    pub discount: Decimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn coupon(kind: CouponKind, value: Decimal) -> Coupon {
+        Coupon {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            code: "SAVE".into(),
+            kind,
+            value,
+            min_order_value: None,
+            usage_limit: None,
+            times_used: 0,
+            expires_at: None,
+            is_active: true,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn discounts_are_capped_at_the_subtotal() {
+        assert_eq!(
+            coupon(CouponKind::Percent, Decimal::new(15, 0)).discount_for(Decimal::new(3333, 2)),
+            Decimal::new(500, 2)
+        );
+        assert_eq!(
+            coupon(CouponKind::Fixed, Decimal::new(10, 0)).discount_for(Decimal::new(25, 0)),
+            Decimal::new(10, 0)
+        );
+        assert_eq!(
+            coupon(CouponKind::Fixed, Decimal::new(10, 0)).discount_for(Decimal::new(450, 2)),
+            Decimal::new(450, 2)
+        );
+    }
+
+    #[test]
+    fn checks_expiry_usage_and_minimum_order() {
+        let now = Utc::now();
+        let mut coupon = coupon(CouponKind::Fixed, Decimal::new(5, 0));
+        coupon.min_order_value = Some(Decimal::new(20, 0));
+        assert!(coupon.ineligibility(Decimal::new(20, 0), now).is_none());
+        assert!(coupon.ineligibility(Decimal::new(1999, 2), now).is_some());
+
+        coupon.usage_limit = Some(3);
+        coupon.times_used = 3;
+        assert!(coupon.ineligibility(Decimal::new(50, 0), now).is_some());
+
+        coupon.usage_limit = None;
+        coupon.expires_at = Some(now - Duration::minutes(1));
+        assert!(coupon.ineligibility(Decimal::new(50, 0), now).is_some());
+    }
+
+    #[test]
+    fn percent_coupons_cannot_exceed_100() {
+        let mut request = CreateCouponRequest {
+            code: "HALF-OFF".into(),
+            kind: CouponKind::Percent,
+            value: 50.0,
+            min_order_value: None,
+            usage_limit: Some(100),
+            expires_at: None,
+        };
+        assert!(request.validate().is_ok());
+
+        request.value = 150.0;
+        assert!(request.validate().is_err());
+
+        request.kind = CouponKind::Fixed;
+        assert!(request.validate().is_ok());
+
+        request.code = "no spaces".into();
+        assert!(request.validate().is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "credit_entry_kind", rename_all = "PascalCase")]
+pub enum CreditEntryKind {
+    Goodwill,
+    Refund,
+    Redemption,
+}
+
+/// Ledger line; issuances are positive, redemptions at checkout negative.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreCreditEntry {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub user_id: Uuid,
+    pub kind: CreditEntryKind,
+    pub amount: Decimal,
+    pub order_id: Option<Uuid>,
+    pub issued_by: Option<Uuid>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreCreditBalance {
+    pub store_id: Uuid,
+    pub user_id: Uuid,
+    pub balance: Decimal,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct IssueCreditRequest {
+    pub user_id: Uuid,
+
+    #[validate(range(min = 0.01, max = 100000.0))]
+    pub amount: f64,
+
+    #[validate(custom(function = "validate_issuable_kind"))]
+    pub kind: CreditEntryKind,
+
+    pub order_id: Option<Uuid>,
+
+    #[validate(length(max = 500))]
+    pub note: Option<String>,
+}
+
+fn validate_issuable_kind(kind: &CreditEntryKind) -> Result<(), ValidationError> {
+    match kind {
+        CreditEntryKind::Goodwill | CreditEntryKind::Refund => Ok(()),
+        CreditEntryKind::Redemption => Err(ValidationError::new("not_issuable")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redemptions_cannot_be_issued_manually() {
+        let mut req = IssueCreditRequest {
+            user_id: Uuid::new_v4(),
+            amount: 15.0,
+            kind: CreditEntryKind::Goodwill,
+            order_id: None,
+            note: Some("Late delivery".into()),
+        };
+        assert!(req.validate().is_ok());
+
+        req.kind = CreditEntryKind::Redemption;
+        assert!(req.validate().is_err());
+
+        req.kind = CreditEntryKind::Refund;
+        req.amount = 0.0;
+        assert!(req.validate().is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// A price converted into the currency the shopper asked for. Orders are
+/// still charged in the store's own currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayPrice {
+    pub currency: String,
+    pub amount: Decimal,
+    /// Units of `currency` per unit of the store currency.
+    pub exchange_rate: Decimal,
+}
+
+/// Checkout totals converted into the shopper's currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayTotals {
+    pub currency: String,
+    pub exchange_rate: Decimal,
+    pub subtotal: Decimal,
+    pub tax: Decimal,
+    pub discount: Decimal,
+    pub shipping_cost: Decimal,
+    pub credit_applied: Decimal,
+    pub total_amount: Decimal,
+}
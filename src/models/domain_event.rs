@@ -0,0 +1,115 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{order::OrderStatus, store::MemberRole};
+
+/// Something that happened in the marketplace, recorded in the outbox by the
+/// transaction that made it happen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data")]
+pub enum DomainEvent {
+    OrderCreated {
+        order_id: Uuid,
+        order_group_id: Uuid,
+        store_id: Uuid,
+        buyer_id: Uuid,
+        total_amount: Decimal,
+        currency: String,
+    },
+    /// Every payment for the order's group has been captured.
+    OrderPaid {
+        order_id: Uuid,
+        order_group_id: Uuid,
+        store_id: Uuid,
+        buyer_id: Uuid,
+        total_amount: Decimal,
+        currency: String,
+    },
+    /// Staff or the order's shipments moved it to another status.
+    OrderStatusChanged {
+        order_id: Uuid,
+        store_id: Uuid,
+        buyer_id: Uuid,
+        from: OrderStatus,
+        to: OrderStatus,
+    },
+    /// A sale or manual adjustment took the product's stock to the low stock
+    /// threshold or below.
+    LowStock {
+        product_id: Uuid,
+        store_id: Uuid,
+        stock_quantity: i32,
+    },
+    /// A sale or manual adjustment took the product's stock to zero.
+    StockDepleted { product_id: Uuid, store_id: Uuid },
+    /// A store's staff added another user as a member.
+    MemberInvited {
+        store_id: Uuid,
+        user_id: Uuid,
+        role: MemberRole,
+        invited_by: Uuid,
+    },
+}
+
+impl DomainEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomainEvent::OrderCreated { .. } => "OrderCreated",
+            DomainEvent::OrderPaid { .. } => "OrderPaid",
+            DomainEvent::OrderStatusChanged { .. } => "OrderStatusChanged",
+            DomainEvent::LowStock { .. } => "LowStock",
+            DomainEvent::StockDepleted { .. } => "StockDepleted",
+            DomainEvent::MemberInvited { .. } => "MemberInvited",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OutboxEvent {
+    pub id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// An event as subscribers receive it. An event may be delivered more than
+/// once, always with the same `id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishedEvent {
+    pub id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    pub event: DomainEvent,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn events_are_tagged_with_their_type() {
+        let store_id = Uuid::nil();
+        let product_id = Uuid::nil();
+        let event = DomainEvent::StockDepleted {
+            product_id,
+            store_id,
+        };
+
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "type": "StockDepleted",
+                "data": { "product_id": product_id, "store_id": store_id },
+            })
+        );
+        assert_eq!(payload["type"], event.event_type());
+        assert_eq!(
+            serde_json::from_value::<DomainEvent>(payload).unwrap(),
+            event
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "client_event_type", rename_all = "PascalCase")]
+#[serde(rename_all = "snake_case")]
+pub enum ClientEventType {
+    ProductView,
+    AddToCart,
+    BeginCheckout,
+}
+
+impl ClientEventType {
+    fn requires_product(&self) -> bool {
+        matches!(
+            self,
+            ClientEventType::ProductView | ClientEventType::AddToCart
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_event_product"))]
+pub struct ClientEvent {
+    pub event_type: ClientEventType,
+    pub store_id: Uuid,
+    pub product_id: Option<Uuid>,
+
+    #[validate(length(min = 8, max = 64))]
+    pub session_id: String,
+
+    /// Client clock time of the event; defaults to the time of ingestion.
+    pub occurred_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct IngestEventsRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub events: Vec<ClientEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedEvent {
+    pub index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IngestEventsResponse {
+    pub accepted: usize,
+    pub sampled_out: usize,
+    pub rejected: Vec<RejectedEvent>,
+}
+
+/// Sessions reaching each storefront step within the timeframe, and the
+/// orders paid for in it. Paid orders come from the orders themselves
+/// rather than from client events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreFunnel {
+    pub timeframe_days: i64,
+    pub viewed_sessions: i64,
+    pub cart_sessions: i64,
+    pub checkout_sessions: i64,
+    pub paid_orders: i64,
+    pub view_to_cart_rate: f64,
+    pub cart_to_checkout_rate: f64,
+    pub checkout_to_paid_rate: f64,
+    /// Paid orders per viewing session.
+    pub overall_conversion_rate: f64,
+}
+
+impl StoreFunnel {
+    pub fn new(timeframe_days: i64, viewed: i64, cart: i64, checkout: i64, paid: i64) -> Self {
+        let rate = |part: i64, whole: i64| {
+            if whole == 0 {
+                0.0
+            } else {
+                part as f64 / whole as f64
+            }
+        };
+        Self {
+            timeframe_days,
+            viewed_sessions: viewed,
+            cart_sessions: cart,
+            checkout_sessions: checkout,
+            paid_orders: paid,
+            view_to_cart_rate: rate(cart, viewed),
+            cart_to_checkout_rate: rate(checkout, cart),
+            checkout_to_paid_rate: rate(paid, checkout),
+            overall_conversion_rate: rate(paid, viewed),
+        }
+    }
+}
+
+/// Sampling is decided per session rather than per event so that kept
+/// sessions retain their whole funnel.
+pub fn session_sampled_in(session_id: &str, sample_rate: f64) -> bool {
+    if sample_rate >= 1.0 {
+        return true;
+    }
+    if sample_rate <= 0.0 {
+        return false;
+    }
+    // FNV-1a: stable across processes and releases, unlike `DefaultHasher`.
+    let hash = session_id
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    (hash % 10_000) < (sample_rate * 10_000.0) as u64
+}
+
+fn validate_event_product(event: &ClientEvent) -> Result<(), ValidationError> {
+    if event.event_type.requires_product() && event.product_id.is_none() {
+        return Err(ValidationError::new("product_id_required"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: ClientEventType, product_id: Option<Uuid>) -> ClientEvent {
+        ClientEvent {
+            event_type,
+            store_id: Uuid::new_v4(),
+            product_id,
+            session_id: "session-1234".into(),
+            occurred_at: None,
+        }
+    }
+
+    #[test]
+    fn product_events_require_product_id() {
+        assert!(event(ClientEventType::ProductView, None)
+            .validate()
+            .is_err());
+        assert!(event(ClientEventType::AddToCart, Some(Uuid::new_v4()))
+            .validate()
+            .is_ok());
+        assert!(event(ClientEventType::BeginCheckout, None)
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn sampling_is_stable_per_session() {
+        assert!(session_sampled_in("any-session", 1.0));
+        assert!(!session_sampled_in("any-session", 0.0));
+
+        let kept = (0..1000)
+            .filter(|i| session_sampled_in(&format!("session-{}", i), 0.25))
+            .count();
+        assert!((150..350).contains(&kept));
+        assert_eq!(
+            session_sampled_in("session-42", 0.5),
+            session_sampled_in("session-42", 0.5)
+        );
+    }
+
+    #[test]
+    fn funnel_rates_handle_empty_steps() {
+        let funnel = StoreFunnel::new(30, 10, 4, 0, 0);
+        assert_eq!(funnel.view_to_cart_rate, 0.4);
+        assert_eq!(funnel.cart_to_checkout_rate, 0.0);
+        assert_eq!(funnel.checkout_to_paid_rate, 0.0);
+        assert_eq!(StoreFunnel::new(30, 0, 0, 0, 0).view_to_cart_rate, 0.0);
+
+        let funnel = StoreFunnel::new(30, 20, 8, 4, 2);
+        assert_eq!(funnel.checkout_to_paid_rate, 0.5);
+        assert_eq!(funnel.overall_conversion_rate, 0.1);
+    }
+}
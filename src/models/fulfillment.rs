@@ -0,0 +1,111 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FulfillmentPartner {
+    pub id: Uuid,
+    pub name: String,
+    pub rate_table: Json<Vec<RateTier>>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A shipping fee applying to consolidated shipments whose combined subtotal
+/// is at least `min_subtotal`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RateTier {
+    pub min_subtotal: Decimal,
+    pub fee: Decimal,
+}
+
+impl FulfillmentPartner {
+    /// Returns the fee of the highest tier the subtotal qualifies for.
+    pub fn fee_for(&self, subtotal: Decimal) -> Option<Decimal> {
+        self.rate_table
+            .iter()
+            .filter(|tier| tier.min_subtotal <= subtotal)
+            .max_by_key(|tier| tier.min_subtotal)
+            .map(|tier| tier.fee)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateFulfillmentPartnerRequest {
+    #[validate(length(min = 2, max = 255))]
+    pub name: String,
+
+    #[validate(custom(function = "validate_rate_table"))]
+    pub rate_table: Vec<RateTier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssignFulfillmentPartnerRequest {
+    pub fulfillment_partner_id: Option<Uuid>,
+}
+
+fn validate_rate_table(tiers: &[RateTier]) -> Result<(), ValidationError> {
+    if !tiers.iter().any(|tier| tier.min_subtotal.is_zero()) {
+        return Err(ValidationError::new("missing_base_tier"));
+    }
+    if tiers
+        .iter()
+        .any(|tier| tier.min_subtotal.is_sign_negative() || tier.fee.is_sign_negative())
+    {
+        return Err(ValidationError::new("negative_rate"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(min_subtotal: i64, fee: i64) -> RateTier {
+        RateTier {
+            min_subtotal: Decimal::new(min_subtotal, 0),
+            fee: Decimal::new(fee, 2),
+        }
+    }
+
+    #[test]
+    fn fee_uses_highest_qualifying_tier() {
+        let partner = FulfillmentPartner {
+            id: Uuid::new_v4(),
+            name: "Shared Logistics".into(),
+            rate_table: Json(vec![tier(0, 999), tier(100, 499), tier(250, 0)]),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        assert_eq!(
+            partner.fee_for(Decimal::new(50, 0)),
+            Some(Decimal::new(999, 2))
+        );
+        assert_eq!(
+            partner.fee_for(Decimal::new(100, 0)),
+            Some(Decimal::new(499, 2))
+        );
+        assert_eq!(partner.fee_for(Decimal::new(300, 0)), Some(Decimal::ZERO));
+    }
+
+    #[test]
+    fn rate_table_requires_base_tier() {
+        let valid = CreateFulfillmentPartnerRequest {
+            name: "Shared Logistics".into(),
+            rate_table: vec![tier(0, 999), tier(100, 0)],
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = CreateFulfillmentPartnerRequest {
+            name: "Shared Logistics".into(),
+            rate_table: vec![tier(100, 0)],
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
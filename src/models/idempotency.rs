@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// A request made with an `Idempotency-Key`, and the response it got once
+/// handled.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct IdempotencyRecord {
+    pub user_id: Uuid,
+    pub idempotency_key: String,
+    pub method: String,
+    pub path: String,
+    pub request_hash: Vec<u8>,
+    /// `None` while the request is still being handled.
+    pub status_code: Option<i16>,
+    pub content_type: Option<String>,
+    pub response_body: Option<Vec<u8>>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    /// How long an unfinished request keeps the key before a retry may
+    /// take it over.
+    pub locked_until: Option<DateTime<Utc>>,
+}
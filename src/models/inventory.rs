@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "inventory_movement_kind", rename_all = "PascalCase")]
+pub enum InventoryMovementKind {
+    Sale,
+    Restock,
+    Adjustment,
+    CancellationRestore,
+}
+
+/// Ledger line for one stock change; `stock_after` is the product's stock
+/// once the change applied.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct InventoryMovement {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub store_id: Uuid,
+    pub kind: InventoryMovementKind,
+    pub delta: i32,
+    pub stock_after: i32,
+    pub actor_id: Option<Uuid>,
+    pub order_id: Option<Uuid>,
+    pub reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct AdjustInventoryRequest {
+    #[validate(custom(function = "validate_manual_kind"))]
+    pub kind: InventoryMovementKind,
+
+    #[validate(range(min = -1000000, max = 1000000), custom(function = "validate_delta"))]
+    pub delta: i32,
+
+    #[validate(length(min = 1, max = 500))]
+    pub reason: String,
+}
+
+fn validate_manual_kind(kind: &InventoryMovementKind) -> Result<(), ValidationError> {
+    match kind {
+        InventoryMovementKind::Restock | InventoryMovementKind::Adjustment => Ok(()),
+        _ => Err(ValidationError::new("not_manual")),
+    }
+}
+
+fn validate_delta(delta: i32) -> Result<(), ValidationError> {
+    if delta == 0 {
+        return Err(ValidationError::new("zero_delta"));
+    }
+    Ok(())
+}
+
+/// One line of a stock count import: the quantity on hand for a SKU. The
+/// difference to the current stock is recorded as an adjustment.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct InventoryImportRow {
+    pub sku: String,
+
+    #[validate(range(min = 0, max = 1000000))]
+    pub stock_quantity: i32,
+}
+
+/// Stock KPIs for one product over an analytics timeframe.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductInventoryStats {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub stock_quantity: i32,
+    /// Units sold in the timeframe, net of cancelled orders.
+    pub units_sold: i64,
+    pub units_received: i64,
+    /// Share of the stock available in the timeframe (opening stock plus
+    /// restocks) that sold.
+    pub sell_through_rate: f64,
+    /// Days in the timeframe the product ended out of stock.
+    pub stockout_days: i64,
+    /// Average units sold per day.
+    pub daily_velocity: f64,
+    /// How long the current stock lasts at `daily_velocity`; `None` when
+    /// nothing sold.
+    pub days_of_stock: Option<f64>,
+}
+
+impl ProductInventoryStats {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        product_id: Uuid,
+        sku: String,
+        name: String,
+        stock_quantity: i32,
+        opening_stock: i64,
+        units_sold: i64,
+        units_received: i64,
+        stockout_days: i64,
+        timeframe_days: i64,
+    ) -> Self {
+        let available = opening_stock + units_received;
+        let sell_through_rate = if available > 0 {
+            (units_sold.max(0) as f64 / available as f64).min(1.0)
+        } else {
+            0.0
+        };
+        let daily_velocity = units_sold.max(0) as f64 / timeframe_days.max(1) as f64;
+        let days_of_stock = if stock_quantity <= 0 {
+            Some(0.0)
+        } else if daily_velocity > 0.0 {
+            Some(stock_quantity as f64 / daily_velocity)
+        } else {
+            None
+        };
+        Self {
+            product_id,
+            sku,
+            name,
+            stock_quantity,
+            units_sold,
+            units_received,
+            sell_through_rate,
+            stockout_days,
+            daily_velocity,
+            days_of_stock,
+        }
+    }
+}
+
+/// A store's products with their stock KPIs, those running out soonest
+/// first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreInventoryAnalytics {
+    pub timeframe_days: i64,
+    pub products: Vec<ProductInventoryStats>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_restocks_and_adjustments_are_manual() {
+        let mut req = AdjustInventoryRequest {
+            kind: InventoryMovementKind::Restock,
+            delta: 12,
+            reason: "Supplier delivery".into(),
+        };
+        assert!(req.validate().is_ok());
+
+        req.kind = InventoryMovementKind::Sale;
+        assert!(req.validate().is_err());
+
+        req.kind = InventoryMovementKind::Adjustment;
+        req.delta = 0;
+        assert!(req.validate().is_err());
+    }
+
+    #[test]
+    fn inventory_stats_project_remaining_stock() {
+        let stats = |stock, opening, sold, received| {
+            ProductInventoryStats::new(
+                Uuid::nil(),
+                "SKU-1".into(),
+                "Lamp".into(),
+                stock,
+                opening,
+                sold,
+                received,
+                0,
+                30,
+            )
+        };
+
+        let selling = stats(20, 40, 30, 10);
+        assert_eq!(selling.sell_through_rate, 0.6);
+        assert_eq!(selling.daily_velocity, 1.0);
+        assert_eq!(selling.days_of_stock, Some(20.0));
+
+        let idle = stats(5, 5, 0, 0);
+        assert_eq!(idle.sell_through_rate, 0.0);
+        assert_eq!(idle.days_of_stock, None);
+
+        assert_eq!(stats(0, 3, 3, 0).days_of_stock, Some(0.0));
+        assert_eq!(stats(0, 0, 0, 0).sell_through_rate, 0.0);
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::order::{Order, OrderItemDetail, OrderTaxLine};
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Invoice {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub store_id: Uuid,
+    /// Position in the store's invoice sequence, starting at 1.
+    pub sequence_number: i32,
+    pub invoice_number: String,
+    pub issued_at: DateTime<Utc>,
+}
+
+impl Invoice {
+    pub fn format_number(sequence_number: i32) -> String {
+        format!("INV-{:06}", sequence_number)
+    }
+}
+
+/// The seller as printed on an invoice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceSeller {
+    pub store_id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub description: Option<String>,
+}
+
+/// Everything an invoice for one order shows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceDocument {
+    pub invoice: Invoice,
+    pub seller: InvoiceSeller,
+    pub order: Order,
+    pub items: Vec<OrderItemDetail>,
+    pub tax_lines: Vec<OrderTaxLine>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InvoiceFormat {
+    #[default]
+    Pdf,
+    Html,
+}
+
+impl InvoiceFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            InvoiceFormat::Pdf => "application/pdf",
+            InvoiceFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            InvoiceFormat::Pdf => "pdf",
+            InvoiceFormat::Html => "html",
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "job_status", rename_all = "PascalCase")]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+/// A unit of background work queued for the job worker.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Job {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: serde_json::Value,
+    pub status: JobStatus,
+    /// Attempts started so far, counting the one in progress.
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    /// When the job is next due; pushed past the lease while it runs.
+    pub run_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Pending jobs of one kind, for the queue depth gauge.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct JobQueueDepth {
+    pub kind: String,
+    pub pending: i64,
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
+
+/// Which side of an order's conversation wrote a message.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "message_sender", rename_all = "PascalCase")]
+pub enum MessageSender {
+    Buyer,
+    Store,
+}
+
+impl MessageSender {
+    /// The side whose messages this side has to read.
+    pub fn other(self) -> Self {
+        match self {
+            MessageSender::Buyer => MessageSender::Store,
+            MessageSender::Store => MessageSender::Buyer,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderMessage {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub sender_id: Option<Uuid>,
+    pub sender: MessageSender,
+    /// `None` once the sender's account was deleted.
+    pub sender_name: Option<String>,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct PostOrderMessageRequest {
+    #[validate(length(min = 1, max = 4000))]
+    pub body: String,
+}
+
+/// An order whose thread has messages the viewer has not read.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UnreadThread {
+    pub order_id: Uuid,
+    pub order_number: String,
+    pub unread: i64,
+    pub last_message_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_body_must_fit() {
+        let request = |body: String| PostOrderMessageRequest { body };
+        assert!(request("Where is my parcel?".into()).validate().is_ok());
+        assert!(request(String::new()).validate().is_err());
+        assert!(request("x".repeat(4001)).validate().is_err());
+    }
+}
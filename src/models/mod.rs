@@ -1,11 +1,29 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+pub mod audit;
+pub mod coupon;
+pub mod credit;
+pub mod currency;
+pub mod domain_event;
+pub mod email;
+pub mod event;
+pub mod fulfillment;
+pub mod idempotency;
+pub mod inventory;
+pub mod invoice;
+pub mod job;
+pub mod message;
+pub mod notification;
 pub mod order;
+pub mod payment;
 pub mod permission;
 pub mod product;
+pub mod shipping;
 pub mod store;
+pub mod tax;
 pub mod user;
+pub mod webhook;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiResponse<T> {
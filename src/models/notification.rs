@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidateEmail, ValidateUrl, ValidationError};
+
+pub const STORE_CLOSED: &str = "store_closed";
+pub const ORDER_STATUS_CHANGED: &str = "order_status_changed";
+pub const NEW_ORDER: &str = "new_order";
+pub const LOW_STOCK: &str = "low_stock";
+pub const ORDER_CANCELLED: &str = "order_cancelled";
+pub const ORDER_MESSAGE: &str = "order_message";
+
+/// Store notifications that stores can route with notification rules.
+pub const ROUTABLE_KINDS: [&str; 4] = [NEW_ORDER, LOW_STOCK, ORDER_CANCELLED, ORDER_MESSAGE];
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub store_id: Option<Uuid>,
+    pub kind: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "notification_channel", rename_all = "PascalCase")]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    InApp,
+    Email,
+    Webhook,
+}
+
+/// Sends one kind of store notification over a channel. Without any rule
+/// for a kind, it goes in-app to the store owner.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationRule {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub kind: String,
+    pub channel: NotificationChannel,
+    /// Store member notified by in-app rules.
+    pub recipient_user_id: Option<Uuid>,
+    /// Email address or webhook URL of the other channels.
+    pub target: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[validate(schema(function = "validate_rule_target"))]
+pub struct CreateNotificationRuleRequest {
+    #[validate(custom(function = "validate_routable_kind"))]
+    pub kind: String,
+    pub channel: NotificationChannel,
+    pub recipient_user_id: Option<Uuid>,
+    #[validate(length(max = 2048))]
+    pub target: Option<String>,
+}
+
+fn validate_routable_kind(kind: &str) -> Result<(), ValidationError> {
+    if ROUTABLE_KINDS.contains(&kind) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("unknown_notification_kind"))
+    }
+}
+
+fn validate_rule_target(request: &CreateNotificationRuleRequest) -> Result<(), ValidationError> {
+    let target = request.target.as_deref();
+    let valid = match request.channel {
+        NotificationChannel::InApp => request.recipient_user_id.is_some() && target.is_none(),
+        NotificationChannel::Email => {
+            request.recipient_user_id.is_none() && target.is_some_and(|t| t.validate_email())
+        }
+        NotificationChannel::Webhook => {
+            request.recipient_user_id.is_none()
+                && target.is_some_and(|t| t.starts_with("https://") && t.validate_url())
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_rule_target"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "delivery_status", rename_all = "PascalCase")]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// An email or webhook notification queued for the delivery task.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct NotificationDelivery {
+    pub id: Uuid,
+    pub rule_id: Option<Uuid>,
+    pub store_id: Uuid,
+    pub kind: String,
+    pub channel: NotificationChannel,
+    pub target: String,
+    pub message: String,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(
+        channel: NotificationChannel,
+        recipient_user_id: Option<Uuid>,
+        target: Option<&str>,
+    ) -> CreateNotificationRuleRequest {
+        CreateNotificationRuleRequest {
+            kind: LOW_STOCK.into(),
+            channel,
+            recipient_user_id,
+            target: target.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn rule_target_must_match_channel() {
+        use NotificationChannel::*;
+
+        assert!(rule(InApp, Some(Uuid::new_v4()), None).validate().is_ok());
+        assert!(rule(InApp, None, Some("ops@example.com"))
+            .validate()
+            .is_err());
+        assert!(rule(Email, None, Some("ops@example.com"))
+            .validate()
+            .is_ok());
+        assert!(rule(Email, None, Some("not-an-email")).validate().is_err());
+        assert!(rule(Webhook, None, Some("https://hooks.example.com/x"))
+            .validate()
+            .is_ok());
+        assert!(rule(Webhook, None, Some("http://hooks.example.com/x"))
+            .validate()
+            .is_err());
+
+        let mut unknown = rule(Email, None, Some("ops@example.com"));
+        unknown.kind = "store_closed".into();
+        assert!(unknown.validate().is_err());
+    }
+}
@@ -1,12 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::models::{
+    currency::{DisplayPrice, DisplayTotals},
+    payment::Payment,
+    product::ProductType,
+    shipping::ShippingOption,
+    store::StoreStatus,
+};
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "order_status", rename_all = "PascalCase")]
 pub enum OrderStatus {
@@ -18,7 +26,23 @@ pub enum OrderStatus {
     Cancelled,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+impl OrderStatus {
+    /// Whether staff may move an order from this status to `next`. Orders
+    /// only move forward; cancelling is possible until they ship. Shipped
+    /// and Delivered are never set by hand: they follow the order's
+    /// shipments.
+    pub fn can_transition_to(self, next: OrderStatus) -> bool {
+        use OrderStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Confirmed)
+                | (Confirmed, Processing)
+                | (Pending | Confirmed | Processing, Cancelled)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash)]
 #[sqlx(type_name = "payment_status", rename_all = "PascalCase")]
 pub enum PaymentStatus {
     Pending,
@@ -50,10 +74,23 @@ pub struct Order {
     pub tax: Decimal,
     pub discount: Decimal,
     pub shipping_cost: Decimal,
+    pub credit_applied: Decimal,
     pub total_amount: Decimal,
     pub shipping_address: Value,
+    pub custom_fields: Value,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// The store currency every amount above is in.
+    pub currency: String,
+    /// The currency the shopper saw totals in, when not `currency`.
+    pub presentment_currency: Option<String>,
+    /// Units of `presentment_currency` per unit of `currency` at checkout.
+    pub exchange_rate: Option<Decimal>,
+    pub cancelled_at: Option<DateTime<Utc>>,
+    pub cancelled_by: Option<Uuid>,
+    pub cancellation_reason: Option<String>,
+    /// Name of the shipping option the buyer picked, for physical orders.
+    pub shipping_method: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
@@ -67,12 +104,268 @@ pub struct OrderItem {
     pub created_at: DateTime<Utc>,
 }
 
+/// An order line with the name of the product it was for.
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
-pub struct CartItem {
+pub struct OrderItemDetail {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub item: OrderItem,
+    pub product_name: String,
+}
+
+/// Tax charged on a sub-order, recorded at checkout with the rate and the
+/// jurisdiction it was collected for.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderTaxLine {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub store_id: Uuid,
+    pub jurisdiction: String,
+    pub rate: Decimal,
+    pub taxable_amount: Decimal,
+    pub tax_amount: Decimal,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "shipment_status", rename_all = "PascalCase")]
+pub enum ShipmentStatus {
+    Shipped,
+    Delivered,
+}
+
+/// A parcel the store sent for an order, with the order lines it carries.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Shipment {
     pub id: Uuid,
+    pub order_id: Uuid,
+    pub store_id: Uuid,
+    pub carrier: String,
+    pub tracking_number: String,
+    pub tracking_url: Option<String>,
+    pub shipped_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub status: ShipmentStatus,
+    pub delivered_at: Option<DateTime<Utc>>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub items: Vec<ShipmentItem>,
+}
+
+/// Units of one order line packed into a shipment.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShipmentItem {
+    pub shipment_id: Uuid,
+    pub order_item_id: Uuid,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ShipmentItemRequest {
+    pub order_item_id: Uuid,
+    #[validate(range(min = 1))]
+    pub quantity: i32,
+}
+
+/// Records a shipment; `shipped_at` defaults to now. Without `items` the
+/// shipment carries every unit not shipped yet.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateShipmentRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub carrier: String,
+    #[validate(length(min = 1, max = 100))]
+    pub tracking_number: String,
+    #[validate(url)]
+    pub tracking_url: Option<String>,
+    pub shipped_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    #[validate(length(min = 1), nested)]
+    pub items: Option<Vec<ShipmentItemRequest>>,
+}
+
+/// Corrects a shipment's tracking details or records its delivery; omitted
+/// fields are kept.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateShipmentRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub carrier: Option<String>,
+    #[validate(length(min = 1, max = 100))]
+    pub tracking_number: Option<String>,
+    #[validate(url)]
+    pub tracking_url: Option<String>,
+    pub shipped_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub status: Option<ShipmentStatus>,
+}
+
+/// How far an order's shipments cover its lines.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FulfillmentStatus {
+    Unfulfilled,
+    PartiallyShipped,
+    Shipped,
+    Delivered,
+}
+
+impl FulfillmentStatus {
+    pub fn of<'a>(items: impl IntoIterator<Item = &'a OrderItem>, shipments: &[Shipment]) -> Self {
+        let remaining = unshipped_quantities(items, shipments);
+        let shipped_any = shipments.iter().any(|shipment| !shipment.items.is_empty());
+        if remaining.values().any(|&quantity| quantity > 0) {
+            return if shipped_any {
+                Self::PartiallyShipped
+            } else {
+                Self::Unfulfilled
+            };
+        }
+        if shipped_any
+            && shipments
+                .iter()
+                .filter(|shipment| !shipment.items.is_empty())
+                .all(|shipment| shipment.status == ShipmentStatus::Delivered)
+        {
+            Self::Delivered
+        } else {
+            Self::Shipped
+        }
+    }
+}
+
+/// Units of each order line that no shipment carries yet.
+pub fn unshipped_quantities<'a>(
+    items: impl IntoIterator<Item = &'a OrderItem>,
+    shipments: &[Shipment],
+) -> HashMap<Uuid, i32> {
+    let mut remaining: HashMap<Uuid, i32> = items
+        .into_iter()
+        .map(|item| (item.id, item.quantity))
+        .collect();
+    for line in shipments.iter().flat_map(|shipment| &shipment.items) {
+        if let Some(quantity) = remaining.get_mut(&line.order_item_id) {
+            *quantity -= line.quantity;
+        }
+    }
+    remaining
+}
+
+/// Jurisdiction code for a shipping address: the country, suffixed with the
+/// state or region when one is given (`US-CA`), or `UNKNOWN`.
+pub fn tax_jurisdiction(address: &Value) -> String {
+    let part = |key: &str| {
+        address
+            .get(key)
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(str::to_uppercase)
+    };
+
+    match (part("country"), part("state").or_else(|| part("region"))) {
+        (Some(country), Some(region)) => format!("{}-{}", country, region),
+        (Some(country), None) => country,
+        (None, _) => "UNKNOWN".to_string(),
+    }
+}
+
+/// Row of the `order_list_view` projection, kept in sync by database triggers
+/// so listings never have to join users, stores and order items.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct OrderListEntry {
+    pub order_id: Uuid,
+    pub order_group_id: Uuid,
     pub user_id: Uuid,
+    pub store_id: Uuid,
+    pub order_number: String,
+    pub status: OrderStatus,
+    pub total_amount: Decimal,
+    pub currency: String,
+    pub buyer_name: String,
+    pub store_name: String,
+    pub item_count: i32,
+    pub first_item_product_id: Option<Uuid>,
+    pub first_item_name: Option<String>,
+    pub first_item_thumbnail_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One order line of a store's accounting export. The order's totals and
+/// tax are repeated on each of its lines so every row stands alone.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct OrderExportLine {
+    pub order_number: String,
+    pub status: OrderStatus,
+    pub created_at: DateTime<Utc>,
+    pub buyer_name: String,
+    pub currency: String,
+    pub sku: String,
+    pub product_name: String,
+    pub quantity: i32,
+    pub unit_price: Decimal,
+    pub line_subtotal: Decimal,
+    pub order_subtotal: Decimal,
+    pub discount: Decimal,
+    pub shipping_cost: Decimal,
+    pub tax: Decimal,
+    /// Jurisdictions the tax was collected for, `;`-separated.
+    pub tax_jurisdiction: Option<String>,
+    pub credit_applied: Decimal,
+    pub total_amount: Decimal,
+    pub order_id: Uuid,
+    pub item_id: Uuid,
+}
+
+/// Whose cart an operation applies to: a signed-in user, or a guest
+/// session identified by its signed guest token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CartOwner {
+    User(Uuid),
+    Guest(Uuid),
+}
+
+impl CartOwner {
+    pub fn user_id(&self) -> Option<Uuid> {
+        match self {
+            CartOwner::User(user_id) => Some(*user_id),
+            CartOwner::Guest(_) => None,
+        }
+    }
+
+    pub fn guest_session_id(&self) -> Option<Uuid> {
+        match self {
+            CartOwner::User(_) => None,
+            CartOwner::Guest(session_id) => Some(*session_id),
+        }
+    }
+}
+
+impl From<Uuid> for CartOwner {
+    fn from(user_id: Uuid) -> Self {
+        CartOwner::User(user_id)
+    }
+}
+
+/// Token a guest sends in the `X-Guest-Token` header to use their cart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestCartSession {
+    pub guest_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CartItem {
+    pub id: Uuid,
+    /// `None` for guest carts.
+    pub user_id: Option<Uuid>,
+    #[serde(skip)]
+    pub guest_session_id: Option<Uuid>,
     pub product_id: Uuid,
     pub quantity: i32,
+    /// Unit price when the shopper last added to the line.
+    pub added_price: Decimal,
+    /// Set aside by the shopper; skipped by checkout and holds no stock.
+    pub saved_for_later: bool,
     pub added_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -83,9 +376,167 @@ pub struct CartItemDetail {
     pub product_id: Uuid,
     pub store_id: Uuid,
     pub store_name: String,
+    pub store_status: StoreStatus,
     pub product_name: String,
     pub unit_price: Decimal,
+    /// Unit price when the shopper last added to the line; checkout charges
+    /// `unit_price`.
+    pub added_price: Decimal,
     pub quantity: i32,
+    pub product_type: ProductType,
+    pub weight_grams: i32,
+    /// False once the product is deactivated or archived.
+    pub product_available: bool,
+    /// Stock not held by other buyers' reservations.
+    pub available_quantity: i64,
+    pub saved_for_later: bool,
+    /// When the stock held for this line is released; `None` once the
+    /// reservation has lapsed.
+    pub reserved_until: Option<DateTime<Utc>>,
+    /// Problems checkout would run into, filled in for cart listings.
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub warnings: Vec<CartLineWarning>,
+    /// `unit_price` in the currency requested with `X-Currency`.
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_unit_price: Option<DisplayPrice>,
+}
+
+/// Something about a cart line the shopper should review before checking
+/// out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum CartLineWarning {
+    /// The price moved since the line was added.
+    PriceChanged {
+        added_price: Decimal,
+        current_price: Decimal,
+    },
+    /// The product was withdrawn or its store stopped taking orders.
+    Unavailable,
+    /// Fewer units are left than the line asks for.
+    InsufficientStock { requested: i32, available: i64 },
+}
+
+/// A user's cart at one store that sat idle until the expiry job removed
+/// it, with what it was worth at the time.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AbandonedCart {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub store_id: Uuid,
+    /// Distinct products in the expired lines.
+    pub item_count: i32,
+    pub cart_value: Decimal,
+    pub last_activity_at: DateTime<Utc>,
+    pub expired_at: DateTime<Utc>,
+}
+
+/// Stock held for one buyer's cart line so concurrent carts cannot claim
+/// the same units.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StockReservation {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Which of a store's orders to list. `from` and `to` are inclusive days
+/// the order was placed on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreOrderFilter {
+    pub status: Option<OrderStatus>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+/// Moves one store order to `status`. `reason` is recorded when cancelling.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateOrderStatusRequest {
+    pub status: OrderStatus,
+    #[validate(length(min = 1, max = 500))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BulkOrderStatusRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub order_ids: Vec<Uuid>,
+    pub status: OrderStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkOrderStatusFailure {
+    pub order_id: Uuid,
+    pub message: String,
+}
+
+/// Outcome of a bulk transition: each order succeeds or fails on its own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkOrderStatusResponse {
+    pub status: OrderStatus,
+    pub updated: Vec<Uuid>,
+    pub failed: Vec<BulkOrderStatusFailure>,
+}
+
+/// A sub-order as seen by store staff, with its lines and the buyer's
+/// answers to the store's checkout fields.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreOrderDetail {
+    pub order: Order,
+    pub items: Vec<OrderItem>,
+    pub shipments: Vec<Shipment>,
+    pub fulfillment: FulfillmentStatus,
+}
+
+/// A single sub-order with its lines and tracking, as shown to the buyer or
+/// to store staff. The shipping address is on the order itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderDetail {
+    pub order: Order,
+    pub items: Vec<OrderItemDetail>,
+    pub shipments: Vec<Shipment>,
+    pub fulfillment: FulfillmentStatus,
+}
+
+/// A checkout in the buyer's order history, with the order placed with
+/// each store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderGroupEntry {
+    #[serde(flatten)]
+    pub group: OrderGroup,
+    pub orders: Vec<OrderListEntry>,
+}
+
+/// One checkout in full: every store's order with its items and shipments.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderGroupDetail {
+    #[serde(flatten)]
+    pub group: OrderGroup,
+    pub orders: Vec<OrderDetail>,
+}
+
+/// A signed, expiring link to one file of a digital product in a paid order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DownloadLink {
+    pub file_id: Uuid,
+    pub product_id: Uuid,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A buyer cancelling their own order.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct CancelOrderRequest {
+    #[validate(length(min = 1, max = 500))]
+    pub reason: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -100,6 +551,112 @@ pub struct AddCartItemRequest {
 pub struct CheckoutRequest {
     #[validate(custom(function = "crate::utils::validators::validate_shipping_address"))]
     pub shipping_address: Value,
+
+    /// Places the order even if an identical checkout just completed.
+    #[serde(default)]
+    pub confirm_duplicate: bool,
+
+    /// Values for the stores' checkout fields, keyed by store id.
+    #[serde(default)]
+    pub custom_fields: HashMap<Uuid, Map<String, Value>>,
+
+    /// Code of the shipping option picked for each store, keyed by store id.
+    /// Stores left out ship with their cheapest option.
+    #[serde(default)]
+    pub shipping_options: HashMap<Uuid, String>,
+}
+
+/// What a checkout bought and what it cost before store credit, used to
+/// recognise accidental repeat submissions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckoutFingerprint {
+    pub items: BTreeMap<Uuid, i32>,
+    pub total: Decimal,
+}
+
+impl CheckoutFingerprint {
+    pub fn new(lines: impl IntoIterator<Item = (Uuid, i32)>, total: Decimal) -> Self {
+        let mut items = BTreeMap::new();
+        for (product_id, quantity) in lines {
+            *items.entry(product_id).or_insert(0) += quantity;
+        }
+        Self {
+            items,
+            total: total.normalize(),
+        }
+    }
+}
+
+/// What checking out the current cart would charge, per store and overall.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckoutPreview {
+    pub stores: Vec<StoreOrderPreview>,
+    /// The currency the order is charged in; a cart never mixes currencies.
+    pub currency: String,
+    pub subtotal: Decimal,
+    pub tax: Decimal,
+    pub discount: Decimal,
+    pub shipping_cost: Decimal,
+    pub credit_applied: Decimal,
+    pub total_amount: Decimal,
+    /// The totals in the currency requested with `X-Currency`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayTotals>,
+}
+
+impl CheckoutPreview {
+    pub fn new(stores: Vec<StoreOrderPreview>) -> Self {
+        let sum = |field: fn(&StoreOrderPreview) -> Decimal| {
+            stores
+                .iter()
+                .fold(Decimal::ZERO, |acc, store| acc + field(store))
+        };
+        Self {
+            currency: stores
+                .first()
+                .map(|store| store.currency.clone())
+                .unwrap_or_default(),
+            subtotal: sum(|store| store.subtotal),
+            tax: sum(|store| store.tax),
+            discount: sum(|store| store.discount),
+            shipping_cost: sum(|store| store.shipping_cost),
+            credit_applied: sum(|store| store.credit_applied),
+            total_amount: sum(|store| store.total_amount),
+            display: None,
+            stores,
+        }
+    }
+}
+
+/// The sub-order checkout would create for one store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreOrderPreview {
+    pub store_id: Uuid,
+    pub store_name: String,
+    pub currency: String,
+    pub items: Vec<CartItemDetail>,
+    pub subtotal: Decimal,
+    pub tax_rate: Decimal,
+    pub tax: Decimal,
+    pub discount: Decimal,
+    /// The applied coupon behind `discount`, if any.
+    pub coupon_code: Option<String>,
+    /// What the store can ship the order with, cheapest first. Empty when
+    /// nothing in the order ships.
+    pub shipping_options: Vec<ShippingOption>,
+    /// Code of the option `shipping_cost` is based on.
+    pub shipping_option: Option<String>,
+    pub shipping_cost: Decimal,
+    pub credit_applied: Decimal,
+    pub total_amount: Decimal,
+}
+
+/// Result of cancelling an order group that was never paid.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExpiredOrderGroup {
+    pub order_group_id: Uuid,
+    pub orders_cancelled: usize,
+    pub units_restored: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,7 +665,62 @@ pub struct CheckoutSummary {
     pub orders: Vec<Order>,
 }
 
+/// Checkout result returned to the buyer, with the public reference used in
+/// the confirmation link.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckoutConfirmation {
+    #[serde(flatten)]
+    pub summary: CheckoutSummary,
+    pub confirmation_reference: String,
+    /// The payments opened for the sub-orders; redirect-based providers
+    /// carry the URL the buyer approves them at.
+    #[serde(default)]
+    pub payments: Vec<Payment>,
+}
+
+/// What anyone holding a confirmation link may see: no buyer identity,
+/// address or internal ids.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicOrderConfirmation {
+    pub reference: String,
+    pub group_number: String,
+    pub payment_status: PaymentStatus,
+    pub total_amount: Decimal,
+    pub created_at: DateTime<Utc>,
+    pub orders: Vec<PublicOrderSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicOrderSummary {
+    pub reference: String,
+    pub order_number: String,
+    pub store_name: String,
+    pub status: OrderStatus,
+    pub item_count: i32,
+    pub total_amount: Decimal,
+}
+
 impl CartItemDetail {
+    pub fn detect_warnings(&self) -> Vec<CartLineWarning> {
+        if !self.product_available || self.store_status != StoreStatus::Active {
+            return vec![CartLineWarning::Unavailable];
+        }
+        let mut warnings = Vec::new();
+        if self.unit_price != self.added_price {
+            warnings.push(CartLineWarning::PriceChanged {
+                added_price: self.added_price,
+                current_price: self.unit_price,
+            });
+        }
+        if self.available_quantity < i64::from(self.quantity) {
+            warnings.push(CartLineWarning::InsufficientStock {
+                requested: self.quantity,
+                available: self.available_quantity.max(0),
+            });
+        }
+        warnings
+    }
+
     pub fn group_by_store(items: &[CartItemDetail]) -> HashMap<Uuid, Vec<CartItemDetail>> {
         let mut map: HashMap<Uuid, Vec<CartItemDetail>> = HashMap::new();
         for item in items {
@@ -136,4 +748,116 @@ mod tests {
         };
         assert!(invalid.validate().is_err());
     }
+
+    #[test]
+    fn fingerprints_ignore_line_order_and_scale() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = CheckoutFingerprint::new([(a, 1), (b, 2)], Decimal::new(2500, 2));
+        let second = CheckoutFingerprint::new([(b, 2), (a, 1)], Decimal::new(25, 0));
+        assert_eq!(first, second);
+
+        let more = CheckoutFingerprint::new([(a, 2), (b, 2)], Decimal::new(2500, 2));
+        assert_ne!(first, more);
+    }
+
+    #[test]
+    fn order_status_moves_forward_and_cancels_until_shipped() {
+        assert!(OrderStatus::Pending.can_transition_to(OrderStatus::Confirmed));
+        assert!(OrderStatus::Confirmed.can_transition_to(OrderStatus::Processing));
+        assert!(OrderStatus::Processing.can_transition_to(OrderStatus::Cancelled));
+
+        // Shipments move orders to Shipped and Delivered
+        assert!(!OrderStatus::Processing.can_transition_to(OrderStatus::Shipped));
+        assert!(!OrderStatus::Shipped.can_transition_to(OrderStatus::Delivered));
+
+        assert!(!OrderStatus::Pending.can_transition_to(OrderStatus::Shipped));
+        assert!(!OrderStatus::Shipped.can_transition_to(OrderStatus::Cancelled));
+        assert!(!OrderStatus::Delivered.can_transition_to(OrderStatus::Processing));
+        assert!(!OrderStatus::Cancelled.can_transition_to(OrderStatus::Confirmed));
+        assert!(!OrderStatus::Shipped.can_transition_to(OrderStatus::Shipped));
+    }
+
+    #[test]
+    fn fulfillment_follows_shipped_and_delivered_units() {
+        let order_id = Uuid::new_v4();
+        let item = |quantity| OrderItem {
+            id: Uuid::new_v4(),
+            order_id,
+            product_id: Uuid::new_v4(),
+            quantity,
+            unit_price: Decimal::ONE,
+            subtotal: Decimal::from(quantity),
+            created_at: Utc::now(),
+        };
+        let shipment = |lines: &[(&OrderItem, i32)], status| {
+            let id = Uuid::new_v4();
+            Shipment {
+                id,
+                order_id,
+                store_id: Uuid::new_v4(),
+                carrier: "UPS".into(),
+                tracking_number: "1Z999".into(),
+                tracking_url: None,
+                shipped_at: Utc::now(),
+                created_by: None,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                status,
+                delivered_at: None,
+                items: lines
+                    .iter()
+                    .map(|(item, quantity)| ShipmentItem {
+                        shipment_id: id,
+                        order_item_id: item.id,
+                        quantity: *quantity,
+                    })
+                    .collect(),
+            }
+        };
+        let (mug, poster) = (item(2), item(1));
+        let items = [mug.clone(), poster.clone()];
+
+        assert_eq!(
+            FulfillmentStatus::of(&items, &[]),
+            FulfillmentStatus::Unfulfilled
+        );
+
+        let first = [shipment(&[(&mug, 1)], ShipmentStatus::Delivered)];
+        assert_eq!(
+            FulfillmentStatus::of(&items, &first),
+            FulfillmentStatus::PartiallyShipped
+        );
+        assert_eq!(unshipped_quantities(&items, &first)[&mug.id], 1);
+
+        let second = shipment(&[(&mug, 1), (&poster, 1)], ShipmentStatus::Shipped);
+        let shipments = [first[0].clone(), second.clone()];
+        assert_eq!(
+            FulfillmentStatus::of(&items, &shipments),
+            FulfillmentStatus::Shipped
+        );
+
+        let delivered = Shipment {
+            status: ShipmentStatus::Delivered,
+            ..second
+        };
+        assert_eq!(
+            FulfillmentStatus::of(&items, &[shipments[0].clone(), delivered]),
+            FulfillmentStatus::Delivered
+        );
+    }
+
+    #[test]
+    fn tax_jurisdiction_combines_country_and_region() {
+        use serde_json::json;
+
+        assert_eq!(
+            tax_jurisdiction(&json!({"country": "us", "state": " ca "})),
+            "US-CA"
+        );
+        assert_eq!(
+            tax_jurisdiction(&json!({"country": "DE", "region": ""})),
+            "DE"
+        );
+        assert_eq!(tax_jurisdiction(&json!({"line1": "1 Main"})), "UNKNOWN");
+    }
 }
@@ -0,0 +1,88 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// The payment backend a store collects payments through.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq, Eq, Hash)]
+#[sqlx(type_name = "payment_provider_kind", rename_all = "PascalCase")]
+pub enum PaymentProviderKind {
+    /// Collected outside the platform, e.g. by bank transfer; store staff
+    /// capture the payment once it arrives.
+    #[default]
+    Manual,
+    PayPal,
+}
+
+impl PaymentProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentProviderKind::Manual => "manual",
+            PaymentProviderKind::PayPal => "paypal",
+        }
+    }
+}
+
+impl FromStr for PaymentProviderKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "manual" => Ok(PaymentProviderKind::Manual),
+            "paypal" => Ok(PaymentProviderKind::PayPal),
+            other => Err(format!("unknown payment provider '{}'", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "payment_attempt_status", rename_all = "PascalCase")]
+pub enum PaymentAttemptStatus {
+    /// Waiting for the buyer to approve or the store to confirm receipt.
+    Pending,
+    /// Approved and ready to be captured.
+    Authorized,
+    Captured,
+    Failed,
+    Refunded,
+}
+
+impl PaymentAttemptStatus {
+    /// Whether the attempt may still end up collecting the payment.
+    pub fn is_open(self) -> bool {
+        matches!(
+            self,
+            PaymentAttemptStatus::Pending | PaymentAttemptStatus::Authorized
+        )
+    }
+}
+
+/// One attempt to collect a sub-order's total.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Payment {
+    pub id: Uuid,
+    pub order_group_id: Uuid,
+    pub order_id: Uuid,
+    pub store_id: Uuid,
+    pub provider: PaymentProviderKind,
+    pub reference: Option<String>,
+    pub status: PaymentAttemptStatus,
+    pub amount: Decimal,
+    pub currency: String,
+    /// Where the buyer approves the payment, for redirect-based providers.
+    pub approval_url: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of `POST /order-groups/{id}/pay`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PayOrderGroupRequest {
+    /// Pays through this provider instead of each store's own, e.g. to retry
+    /// a failed payment another way.
+    #[serde(default)]
+    pub method: Option<PaymentProviderKind>,
+}
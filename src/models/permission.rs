@@ -1,7 +1,12 @@
 use std::collections::{BTreeMap, BTreeSet};
 
+use chrono::{DateTime, Utc};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::models::store::{AccessLevel, MemberRole, StoreMember, StoreRole};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -15,6 +20,7 @@ pub enum Permission {
     ViewOrders,
     ProcessOrders,
     CancelOrders,
+    IssueCredit,
     // Members
     ViewMembers,
     InviteMembers,
@@ -25,6 +31,8 @@ pub enum Permission {
     // Analytics
     ViewStats,
     ExportReports,
+    // Store
+    ManageSettings,
 }
 
 impl Permission {
@@ -37,6 +45,7 @@ impl Permission {
             Permission::ViewOrders => "VIEW_ORDERS",
             Permission::ProcessOrders => "PROCESS_ORDERS",
             Permission::CancelOrders => "CANCEL_ORDERS",
+            Permission::IssueCredit => "ISSUE_CREDIT",
             Permission::ViewMembers => "VIEW_MEMBERS",
             Permission::InviteMembers => "INVITE_MEMBERS",
             Permission::EditPermissions => "EDIT_PERMISSIONS",
@@ -44,6 +53,7 @@ impl Permission {
             Permission::RevokeAccess => "REVOKE_ACCESS",
             Permission::ViewStats => "VIEW_STATS",
             Permission::ExportReports => "EXPORT_REPORTS",
+            Permission::ManageSettings => "MANAGE_SETTINGS",
         }
     }
 
@@ -58,7 +68,7 @@ impl std::fmt::Display for Permission {
     }
 }
 
-pub static PERMISSION_LIST: [Permission; 14] = [
+pub static PERMISSION_LIST: [Permission; 16] = [
     Permission::ViewProducts,
     Permission::CreateProducts,
     Permission::EditProducts,
@@ -66,6 +76,7 @@ pub static PERMISSION_LIST: [Permission; 14] = [
     Permission::ViewOrders,
     Permission::ProcessOrders,
     Permission::CancelOrders,
+    Permission::IssueCredit,
     Permission::ViewMembers,
     Permission::InviteMembers,
     Permission::EditPermissions,
@@ -73,6 +84,7 @@ pub static PERMISSION_LIST: [Permission; 14] = [
     Permission::RevokeAccess,
     Permission::ViewStats,
     Permission::ExportReports,
+    Permission::ManageSettings,
 ];
 
 pub static ROLE_PERMISSIONS: Lazy<BTreeMap<&'static str, BTreeSet<Permission>>> = Lazy::new(|| {
@@ -94,6 +106,7 @@ pub static ROLE_PERMISSIONS: Lazy<BTreeMap<&'static str, BTreeSet<Permission>>>
             ViewOrders,
             ProcessOrders,
             CancelOrders,
+            IssueCredit,
             ViewMembers,
             InviteMembers,
             EditPermissions,
@@ -101,6 +114,7 @@ pub static ROLE_PERMISSIONS: Lazy<BTreeMap<&'static str, BTreeSet<Permission>>>
             RevokeAccess,
             ViewStats,
             ExportReports,
+            ManageSettings,
         ]
         .into_iter()
         .collect(),
@@ -125,10 +139,151 @@ pub static ROLE_PERMISSIONS: Lazy<BTreeMap<&'static str, BTreeSet<Permission>>>
     map
 });
 
+/// What the caller may do in a store and where it comes from: their
+/// membership, the store being public, or an access grant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorePermissions {
+    pub store_id: Uuid,
+    pub role: Option<MemberRole>,
+    /// Name of the store role a Custom member holds.
+    pub custom_role: Option<String>,
+    pub access_level: Option<AccessLevel>,
+    pub permissions: BTreeSet<Permission>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "permission_audit_action", rename_all = "PascalCase")]
+pub enum PermissionAuditAction {
+    MemberAdded,
+    MemberUpdated,
+    MemberRemoved,
+    AccessGranted,
+    AccessUpdated,
+    AccessRevoked,
+    RoleCreated,
+    RoleUpdated,
+    RoleDeleted,
+}
+
+/// One change to who may do what in a store. `before` and `after` are
+/// snapshots of the membership, grant or role row that changed.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PermissionAuditEntry {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    /// Who made the change; `None` when it was not made by a user.
+    pub actor_id: Option<Uuid>,
+    pub action: PermissionAuditAction,
+    /// The member or grant holder affected; `None` for role changes.
+    pub target_user_id: Option<Uuid>,
+    pub subject_id: Uuid,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What an access grant at `level` allows.
+pub fn access_permissions(level: &AccessLevel) -> BTreeSet<Permission> {
+    match level {
+        AccessLevel::View => BTreeSet::from([Permission::ViewProducts, Permission::ViewOrders]),
+        AccessLevel::ViewAndBuy => BTreeSet::from([
+            Permission::ViewProducts,
+            Permission::ViewOrders,
+            Permission::ProcessOrders,
+        ]),
+    }
+}
+
+/// What a member may do in a store: everything for owners and admins,
+/// otherwise the permissions granted to them. Unknown entries are ignored.
+pub fn effective_permissions(role: MemberRole, granted: &Value) -> BTreeSet<Permission> {
+    if matches!(role, MemberRole::Owner | MemberRole::Admin) {
+        return PERMISSION_LIST.into_iter().collect();
+    }
+    let granted: Vec<&str> = granted
+        .as_array()
+        .map(|list| list.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    PERMISSION_LIST
+        .into_iter()
+        .filter(|permission| {
+            granted
+                .iter()
+                .any(|value| value.eq_ignore_ascii_case(permission.as_str()))
+        })
+        .collect()
+}
+
+/// A member's effective permissions, including those of the store role a
+/// Custom member holds.
+pub fn member_permissions(member: &StoreMember, role: Option<&StoreRole>) -> BTreeSet<Permission> {
+    let mut permissions = effective_permissions(member.role, &member.permissions);
+    if let (MemberRole::Custom, Some(role)) = (member.role, role) {
+        if member.custom_role_id == Some(role.id) {
+            permissions.extend(effective_permissions(member.role, &role.permissions));
+        }
+    }
+    permissions
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn effective_permissions_resolve_roles_and_grants() {
+        use serde_json::json;
+
+        let granted = json!(["view_orders", "PROCESS_ORDERS", "NOT_A_PERMISSION"]);
+        assert_eq!(
+            effective_permissions(MemberRole::Custom, &granted),
+            BTreeSet::from([Permission::ViewOrders, Permission::ProcessOrders])
+        );
+        assert_eq!(
+            effective_permissions(MemberRole::Admin, &json!([])).len(),
+            PERMISSION_LIST.len()
+        );
+        assert!(effective_permissions(MemberRole::Staff, &Value::Null).is_empty());
+    }
+
+    #[test]
+    fn custom_members_gain_their_store_role() {
+        use chrono::Utc;
+        use serde_json::json;
+        use uuid::Uuid;
+
+        let role = StoreRole {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            name: "Packer".into(),
+            permissions: json!(["PROCESS_ORDERS"]),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let mut member = StoreMember {
+            id: Uuid::new_v4(),
+            store_id: role.store_id,
+            user_id: Uuid::new_v4(),
+            role: MemberRole::Custom,
+            permissions: json!(["VIEW_ORDERS"]),
+            custom_role_id: Some(role.id),
+            invited_by: None,
+            joined_at: Utc::now(),
+            is_active: true,
+            updated_at: Utc::now(),
+        };
+        assert_eq!(
+            member_permissions(&member, Some(&role)),
+            BTreeSet::from([Permission::ViewOrders, Permission::ProcessOrders])
+        );
+
+        member.custom_role_id = None;
+        assert_eq!(
+            member_permissions(&member, Some(&role)),
+            BTreeSet::from([Permission::ViewOrders])
+        );
+    }
+
     #[test]
     fn owner_has_all_permissions() {
         let owner = ROLE_PERMISSIONS.get("Owner").unwrap();
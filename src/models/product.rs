@@ -1,9 +1,12 @@
 use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::models::currency::DisplayPrice;
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Product {
     pub id: Uuid,
@@ -14,9 +17,119 @@ pub struct Product {
     pub price: Decimal,
     pub stock_quantity: i32,
     pub category: Option<String>,
+    pub thumbnail_url: Option<String>,
     pub is_active: bool,
+    pub product_type: ProductType,
+    /// Shipping weight of one unit; weight-tiered shipping rules price the
+    /// parcel by it.
+    pub weight_grams: i32,
+    pub archived_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    #[sqlx(skip)]
+    #[serde(default)]
+    pub images: Vec<ProductImage>,
+    /// `price` in the currency requested with `X-Currency`.
+    #[sqlx(skip)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_price: Option<DisplayPrice>,
+}
+
+impl Product {
+    /// Archived products are soft-deleted: hidden from listings and carts but
+    /// kept so past orders still resolve them.
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
+    }
+
+    /// SKU for the `attempt`-th duplicate of this product: `-COPY`, then
+    /// `-COPY-2`, `-COPY-3`, ... The base is shortened so the result still
+    /// fits the 100-character SKU limit.
+    pub fn copy_sku(&self, attempt: u32) -> String {
+        let suffix = if attempt <= 1 {
+            "-COPY".to_string()
+        } else {
+            format!("-COPY-{}", attempt)
+        };
+        let base: String = self.sku.chars().take(100 - suffix.len()).collect();
+        format!("{}{}", base, suffix)
+    }
+}
+
+/// Digital products ship nothing: they carry no shipping cost and buyers
+/// receive download links for the product's files once payment clears.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "product_type", rename_all = "PascalCase")]
+pub enum ProductType {
+    #[default]
+    Physical,
+    Digital,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProductImage {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub url: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A file delivered to buyers of a digital product.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProductFile {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    #[serde(skip)]
+    pub storage_key: String,
+    pub file_name: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RecentlyViewedProduct {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub product: Product,
+    pub viewed_at: DateTime<Utc>,
+}
+
+/// A cross-sell recommendation shown alongside a product.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct RelatedProduct {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub product: Product,
+    /// Checkouts that included both products; `0` for same-category fill-ins.
+    pub co_purchases: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ProductSuggestion {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub name: String,
+    pub price: Decimal,
+    pub thumbnail_url: Option<String>,
+}
+
+/// Search-as-you-type results for a query prefix.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchSuggestions {
+    pub products: Vec<ProductSuggestion>,
+    pub categories: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ReorderProductImagesRequest {
+    #[validate(length(min = 1, max = 20))]
+    pub image_ids: Vec<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -40,6 +153,16 @@ pub struct CreateProductRequest {
 
     #[validate(length(max = 100))]
     pub category: Option<String>,
+
+    #[validate(url)]
+    pub thumbnail_url: Option<String>,
+
+    #[serde(default)]
+    pub product_type: ProductType,
+
+    #[serde(default)]
+    #[validate(range(min = 0, max = 1000000))]
+    pub weight_grams: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -59,7 +182,158 @@ pub struct UpdateProductRequest {
     #[validate(length(max = 100))]
     pub category: Option<String>,
 
+    #[validate(url)]
+    pub thumbnail_url: Option<String>,
+
     pub is_active: Option<bool>,
+
+    pub product_type: Option<ProductType>,
+
+    #[validate(range(min = 0, max = 1000000))]
+    pub weight_grams: Option<i32>,
+}
+
+/// Open Graph friendly summary used by storefronts and chat apps to unfurl
+/// product links.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductShareCard {
+    pub product_id: Uuid,
+    pub title: String,
+    pub description: Option<String>,
+    pub price: Decimal,
+    pub currency: String,
+    pub image_url: Option<String>,
+    pub site_name: String,
+}
+
+impl ProductShareCard {
+    /// Longest description unfurlers reliably display without cutting it.
+    pub const DESCRIPTION_LIMIT: usize = 200;
+
+    pub fn new(product: &Product, store_name: &str, currency: &str) -> Self {
+        Self {
+            product_id: product.id,
+            title: product.name.clone(),
+            description: product
+                .description
+                .as_deref()
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(|text| truncate_description(text, Self::DESCRIPTION_LIMIT)),
+            price: product.price,
+            currency: currency.to_string(),
+            image_url: product.thumbnail_url.clone(),
+            site_name: store_name.to_string(),
+        }
+    }
+}
+
+fn truncate_description(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(limit - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CompareProductsRequest {
+    #[validate(length(min = 2, max = 10))]
+    pub product_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Availability {
+    InStock,
+    LowStock,
+    OutOfStock,
+}
+
+impl Availability {
+    /// Stock level at or below which a product is reported as running low.
+    pub const LOW_STOCK_THRESHOLD: i32 = 5;
+
+    pub fn from_stock(stock_quantity: i32) -> Self {
+        match stock_quantity {
+            qty if qty <= 0 => Availability::OutOfStock,
+            qty if qty <= Self::LOW_STOCK_THRESHOLD => Availability::LowStock,
+            _ => Availability::InStock,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparedProduct {
+    pub product_id: Uuid,
+    pub store_id: Uuid,
+    pub store_name: String,
+    pub name: String,
+    pub thumbnail_url: Option<String>,
+}
+
+/// One attribute across every compared product; `values[i]` belongs to
+/// `products[i]` of the enclosing comparison.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComparisonRow {
+    pub attribute: String,
+    pub values: Vec<Value>,
+    pub differs: bool,
+}
+
+/// Column-aligned comparison table ready for storefronts to render as-is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProductComparison {
+    pub products: Vec<ComparedProduct>,
+    pub attributes: Vec<ComparisonRow>,
+}
+
+impl ProductComparison {
+    /// Builds the table from `(product, store name, currency)` entries in
+    /// display order.
+    pub fn new(entries: &[(&Product, &str, &str)]) -> Self {
+        let products = entries
+            .iter()
+            .map(|(product, store_name, _)| ComparedProduct {
+                product_id: product.id,
+                store_id: product.store_id,
+                store_name: store_name.to_string(),
+                name: product.name.clone(),
+                thumbnail_url: product.thumbnail_url.clone(),
+            })
+            .collect();
+
+        let row = |attribute: &str, value: &dyn Fn(&Product, &str) -> Value| {
+            let values: Vec<Value> = entries
+                .iter()
+                .map(|(product, _, currency)| value(product, currency))
+                .collect();
+            let differs = values.windows(2).any(|pair| pair[0] != pair[1]);
+            ComparisonRow {
+                attribute: attribute.to_string(),
+                values,
+                differs,
+            }
+        };
+
+        let attributes = vec![
+            row("price", &|product, _| json!(product.price)),
+            row("currency", &|_, currency| json!(currency)),
+            row("category", &|product, _| json!(product.category)),
+            row("availability", &|product, _| {
+                json!(Availability::from_stock(product.stock_quantity))
+            }),
+            row("stock_quantity", &|product, _| {
+                json!(product.stock_quantity)
+            }),
+            row("image_count", &|product, _| json!(product.images.len())),
+        ];
+
+        Self {
+            products,
+            attributes,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,10 +344,134 @@ pub struct ProductFilter {
     pub search: Option<String>,
 }
 
+/// One line of a bulk import CSV. Columns match `CreateProductRequest`;
+/// empty optional cells are treated as missing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProductImportRow {
+    pub sku: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: f64,
+    pub stock_quantity: i32,
+    pub category: Option<String>,
+    pub thumbnail_url: Option<String>,
+}
+
+impl ProductImportRow {
+    pub fn into_request(self, store_id: Uuid) -> CreateProductRequest {
+        CreateProductRequest {
+            store_id,
+            sku: self.sku,
+            name: self.name,
+            description: self.description,
+            price: self.price,
+            stock_quantity: self.stock_quantity,
+            category: self.category,
+            thumbnail_url: self.thumbnail_url,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
+        }
+    }
+}
+
+/// One line of a price list import; only existing SKUs can be repriced.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct PriceImportRow {
+    pub sku: String,
+
+    #[validate(range(min = 0.01, max = 1000000.0))]
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Catalog export line. The leading columns match `ProductImportRow`, so a
+/// CSV export can be edited and re-imported as-is.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductExportRow<'a> {
+    pub sku: &'a str,
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub price: Decimal,
+    pub stock_quantity: i32,
+    pub category: Option<&'a str>,
+    pub thumbnail_url: Option<&'a str>,
+    pub is_active: bool,
+    pub id: Uuid,
+}
+
+impl<'a> From<&'a Product> for ProductExportRow<'a> {
+    fn from(product: &'a Product) -> Self {
+        Self {
+            sku: &product.sku,
+            name: &product.name,
+            description: product.description.as_deref(),
+            price: product.price,
+            stock_quantity: product.stock_quantity,
+            category: product.category.as_deref(),
+            thumbnail_url: product.thumbnail_url.as_deref(),
+            is_active: product.is_active,
+            id: product.id,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn copy_sku_appends_numbered_suffix_within_limit() {
+        let mut product = Product {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            sku: "TEE-RED".into(),
+            name: "Tee".into(),
+            description: None,
+            price: Decimal::new(1500, 2),
+            stock_quantity: 0,
+            category: None,
+            thumbnail_url: None,
+            is_active: true,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
+            archived_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            images: Vec::new(),
+            display_price: None,
+        };
+        assert_eq!(product.copy_sku(1), "TEE-RED-COPY");
+        assert_eq!(product.copy_sku(3), "TEE-RED-COPY-3");
+
+        product.sku = "X".repeat(100);
+        let sku = product.copy_sku(12);
+        assert_eq!(sku.len(), 100);
+        assert!(sku.ends_with("-COPY-12"));
+    }
+
     #[test]
     fn create_product_validation() {
         let req = CreateProductRequest {
@@ -84,6 +482,9 @@ mod tests {
             price: 99.99,
             stock_quantity: 10,
             category: None,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
+            thumbnail_url: None,
         };
         assert!(req.validate().is_ok());
 
@@ -95,7 +496,97 @@ mod tests {
             price: -1.0,
             stock_quantity: -5,
             category: None,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
+            thumbnail_url: None,
         };
         assert!(invalid.validate().is_err());
     }
+
+    #[test]
+    fn share_card_truncates_long_descriptions() {
+        let mut product = Product {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            sku: "SKU-CARD".into(),
+            name: "Walnut Desk".into(),
+            description: Some("x".repeat(500)),
+            price: Decimal::new(19900, 2),
+            stock_quantity: 3,
+            category: None,
+            thumbnail_url: Some("https://cdn.markethub.dev/desk.png".into()),
+            is_active: true,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
+            archived_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            images: Vec::new(),
+            display_price: None,
+        };
+
+        let card = ProductShareCard::new(&product, "Woodshop", "EUR");
+        let description = card.description.unwrap();
+        assert_eq!(
+            description.chars().count(),
+            ProductShareCard::DESCRIPTION_LIMIT
+        );
+        assert!(description.ends_with('…'));
+        assert_eq!(card.currency, "EUR");
+
+        product.description = Some("   ".into());
+        assert!(ProductShareCard::new(&product, "Woodshop", "EUR")
+            .description
+            .is_none());
+    }
+
+    #[test]
+    fn comparison_flags_differing_attributes() {
+        let product = |price: i64, stock: i32| Product {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            sku: "SKU-CMP".into(),
+            name: "Chair".into(),
+            description: None,
+            price: Decimal::new(price, 2),
+            stock_quantity: stock,
+            category: Some("furniture".into()),
+            thumbnail_url: None,
+            is_active: true,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
+            archived_at: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            images: Vec::new(),
+            display_price: None,
+        };
+        let cheap = product(4999, 2);
+        let pricey = product(8999, 40);
+
+        let table = ProductComparison::new(&[(&cheap, "A", "USD"), (&pricey, "B", "USD")]);
+        let row = |name: &str| {
+            table
+                .attributes
+                .iter()
+                .find(|r| r.attribute == name)
+                .unwrap()
+        };
+
+        assert_eq!(table.products[1].product_id, pricey.id);
+        assert!(row("price").differs);
+        assert!(!row("category").differs);
+        assert!(!row("currency").differs);
+        assert_eq!(
+            row("availability").values,
+            vec![json!("low_stock"), json!("in_stock")]
+        );
+    }
+
+    #[test]
+    fn availability_thresholds() {
+        assert_eq!(Availability::from_stock(0), Availability::OutOfStock);
+        assert_eq!(Availability::from_stock(5), Availability::LowStock);
+        assert_eq!(Availability::from_stock(6), Availability::InStock);
+    }
 }
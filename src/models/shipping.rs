@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+/// How a shipping rule prices a parcel.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "shipping_rule_kind", rename_all = "PascalCase")]
+pub enum ShippingRuleKind {
+    /// The same `fee` for every parcel.
+    Flat,
+    /// `tiers` keyed by the parcel weight in grams.
+    WeightTiers,
+    /// `tiers` keyed by the shipped subtotal.
+    PriceTiers,
+    /// Live rates quoted by the configured carrier integration.
+    Carrier,
+}
+
+/// A shipping method a store offers at checkout.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShippingRule {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub name: String,
+    pub kind: ShippingRuleKind,
+    pub fee: Decimal,
+    pub tiers: Json<Vec<ShippingTier>>,
+    /// Shipped subtotal from which the method is free.
+    pub free_shipping_threshold: Option<Decimal>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A fee applying to parcels whose weight or subtotal, depending on the
+/// rule, is at least `min`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShippingTier {
+    pub min: Decimal,
+    pub fee: Decimal,
+}
+
+impl ShippingRule {
+    /// Whether the subtotal reaches the rule's free-shipping threshold.
+    pub fn ships_free(&self, subtotal: Decimal) -> bool {
+        self.free_shipping_threshold
+            .is_some_and(|threshold| subtotal >= threshold)
+    }
+
+    /// What the rule charges for a parcel, or `None` when it cannot price
+    /// it: carrier rules are quoted separately and tiers may not cover the
+    /// parcel.
+    pub fn price(&self, weight_grams: i64, subtotal: Decimal) -> Option<Decimal> {
+        if self.kind == ShippingRuleKind::Carrier {
+            return None;
+        }
+        if self.ships_free(subtotal) {
+            return Some(Decimal::ZERO);
+        }
+        let tier_fee = |measure: Decimal| {
+            self.tiers
+                .iter()
+                .filter(|tier| tier.min <= measure)
+                .max_by_key(|tier| tier.min)
+                .map(|tier| tier.fee)
+        };
+        match self.kind {
+            ShippingRuleKind::Flat => Some(self.fee),
+            ShippingRuleKind::WeightTiers => tier_fee(Decimal::from(weight_grams)),
+            ShippingRuleKind::PriceTiers => tier_fee(subtotal),
+            ShippingRuleKind::Carrier => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateShippingRuleRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+
+    pub kind: ShippingRuleKind,
+
+    #[validate(range(min = 0.0, max = 100000.0))]
+    pub fee: Option<f64>,
+
+    #[serde(default)]
+    #[validate(custom(function = "validate_tiers"))]
+    pub tiers: Vec<ShippingTier>,
+
+    #[validate(range(min = 0.0, max = 1000000.0))]
+    pub free_shipping_threshold: Option<f64>,
+}
+
+fn validate_tiers(tiers: &[ShippingTier]) -> Result<(), ValidationError> {
+    if tiers
+        .iter()
+        .any(|tier| tier.min.is_sign_negative() || tier.fee.is_sign_negative())
+    {
+        return Err(ValidationError::new("negative_tier"));
+    }
+    Ok(())
+}
+
+/// One way a store can ship an order, as offered at checkout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShippingOption {
+    /// Identifies the option in `CheckoutRequest::shipping_options`.
+    pub code: String,
+    pub name: String,
+    pub cost: Decimal,
+    /// The carrier that quoted the rate, for carrier options.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub carrier: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub estimated_days: Option<i32>,
+}
+
+impl ShippingOption {
+    /// Code of the option backed by the store's flat fee, offered when the
+    /// store has no shipping rules.
+    pub const STANDARD: &'static str = "standard";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(kind: ShippingRuleKind, tiers: Vec<(i64, i64)>) -> ShippingRule {
+        ShippingRule {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            name: "Ground".into(),
+            kind,
+            fee: Decimal::new(500, 2),
+            tiers: Json(
+                tiers
+                    .into_iter()
+                    .map(|(min, fee)| ShippingTier {
+                        min: Decimal::from(min),
+                        fee: Decimal::new(fee, 2),
+                    })
+                    .collect(),
+            ),
+            free_shipping_threshold: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn tiered_rules_use_highest_qualifying_tier() {
+        let by_weight = rule(
+            ShippingRuleKind::WeightTiers,
+            vec![(0, 400), (1000, 900), (5000, 1500)],
+        );
+        assert_eq!(
+            by_weight.price(250, Decimal::ZERO),
+            Some(Decimal::new(400, 2))
+        );
+        assert_eq!(
+            by_weight.price(1000, Decimal::ZERO),
+            Some(Decimal::new(900, 2))
+        );
+        assert_eq!(
+            by_weight.price(7200, Decimal::ZERO),
+            Some(Decimal::new(1500, 2))
+        );
+
+        let by_price = rule(ShippingRuleKind::PriceTiers, vec![(20, 600), (100, 300)]);
+        assert_eq!(by_price.price(0, Decimal::from(10)), None);
+        assert_eq!(
+            by_price.price(0, Decimal::from(150)),
+            Some(Decimal::new(300, 2))
+        );
+    }
+
+    #[test]
+    fn free_shipping_threshold_waives_the_fee() {
+        let mut flat = rule(ShippingRuleKind::Flat, vec![]);
+        flat.free_shipping_threshold = Some(Decimal::from(50));
+        assert_eq!(flat.price(0, Decimal::from(49)), Some(Decimal::new(500, 2)));
+        assert_eq!(flat.price(0, Decimal::from(50)), Some(Decimal::ZERO));
+
+        let mut carrier = rule(ShippingRuleKind::Carrier, vec![]);
+        carrier.free_shipping_threshold = Some(Decimal::from(50));
+        assert_eq!(carrier.price(0, Decimal::from(50)), None);
+        assert!(carrier.ships_free(Decimal::from(50)));
+    }
+}
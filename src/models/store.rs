@@ -1,10 +1,15 @@
-use chrono::{DateTime, NaiveDate, Utc};
-use rust_decimal::Decimal;
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Months, NaiveDate, Utc};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::models::permission::Permission;
+use crate::models::{
+    event::StoreFunnel, order::PaymentStatus, payment::PaymentProviderKind, permission::Permission,
+};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "store_status", rename_all = "PascalCase")]
@@ -14,6 +19,43 @@ pub enum StoreStatus {
     Closed,
 }
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "store_plan", rename_all = "PascalCase")]
+pub enum StorePlan {
+    Free,
+    Pro,
+}
+
+/// Quotas a store is held to under its plan.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlanLimits {
+    pub max_products: i64,
+    pub max_webhooks: i64,
+    pub analytics_retention_days: i64,
+}
+
+impl StorePlan {
+    pub fn limits(&self) -> PlanLimits {
+        match self {
+            StorePlan::Free => PlanLimits {
+                max_products: 100,
+                max_webhooks: 5,
+                analytics_retention_days: 90,
+            },
+            StorePlan::Pro => PlanLimits {
+                max_products: 10_000,
+                max_webhooks: 25,
+                analytics_retention_days: 365,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangePlanRequest {
+    pub plan: StorePlan,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Store {
     pub id: Uuid,
@@ -24,6 +66,8 @@ pub struct Store {
     pub logo_url: Option<String>,
     pub is_private: bool,
     pub status: StoreStatus,
+    pub fulfillment_partner_id: Option<Uuid>,
+    pub plan: StorePlan,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -62,6 +106,262 @@ pub struct UpdateStoreRequest {
     pub status: Option<StoreStatus>,
 }
 
+/// Where a store's checkout tax rates come from.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "tax_provider_kind", rename_all = "PascalCase")]
+pub enum TaxProviderKind {
+    /// The store's own `tax_rate`.
+    #[default]
+    Builtin,
+    /// The platform's external tax service, looked up by shipping address.
+    External,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreSettings {
+    pub store_id: Uuid,
+    pub currency_code: String,
+    pub tax_rate: Decimal,
+    pub flat_shipping_fee: Decimal,
+    pub free_shipping_threshold: Option<Decimal>,
+    /// Country codes physical goods can ship to; empty means anywhere.
+    pub ships_to: Vec<String>,
+    pub tax_provider: TaxProviderKind,
+    pub payment_provider: PaymentProviderKind,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl StoreSettings {
+    /// Settings applied to stores that never saved their own.
+    pub fn defaults(store_id: Uuid) -> Self {
+        let now = Utc::now();
+        Self {
+            store_id,
+            currency_code: "USD".into(),
+            tax_rate: Decimal::ZERO,
+            flat_shipping_fee: Decimal::ZERO,
+            free_shipping_threshold: None,
+            ships_to: Vec::new(),
+            tax_provider: TaxProviderKind::Builtin,
+            payment_provider: PaymentProviderKind::Manual,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    pub fn tax_for(&self, subtotal: Decimal) -> Decimal {
+        (subtotal * self.tax_rate).round_dp(2)
+    }
+
+    pub fn shipping_for(&self, subtotal: Decimal) -> Decimal {
+        match self.free_shipping_threshold {
+            Some(threshold) if subtotal >= threshold => Decimal::ZERO,
+            _ => self.flat_shipping_fee,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateStoreSettingsRequest {
+    #[validate(custom(function = "crate::utils::validators::validate_currency_code"))]
+    pub currency_code: Option<String>,
+
+    #[validate(range(min = 0.0, max = 1.0))]
+    pub tax_rate: Option<f64>,
+
+    #[validate(range(min = 0.0, max = 1000000.0))]
+    pub flat_shipping_fee: Option<f64>,
+
+    #[validate(range(min = 0.0, max = 1000000.0))]
+    pub free_shipping_threshold: Option<f64>,
+
+    /// Replaces the shipping destinations; an empty list ships anywhere.
+    #[validate(length(max = 250))]
+    #[validate(custom(function = "crate::utils::validators::validate_country_codes"))]
+    pub ships_to: Option<Vec<String>>,
+
+    pub tax_provider: Option<TaxProviderKind>,
+
+    pub payment_provider: Option<PaymentProviderKind>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "checkout_field_type", rename_all = "PascalCase")]
+pub enum CheckoutFieldType {
+    Text,
+    Number,
+    Boolean,
+}
+
+/// An extra field a store asks buyers to fill in at checkout, such as
+/// delivery instructions or a company name.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CheckoutField {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub key: String,
+    pub label: String,
+    pub field_type: CheckoutFieldType,
+    pub required: bool,
+    pub max_length: Option<i32>,
+    pub position: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl CheckoutField {
+    /// Text limit applied when the store did not set one.
+    pub const DEFAULT_MAX_LENGTH: usize = 500;
+
+    /// Checks one submitted value against the field definition. Blank text
+    /// counts as missing; accepted text is stored trimmed.
+    fn clean(&self, value: Option<&Value>) -> Result<Option<Value>, String> {
+        let value = match value {
+            None | Some(Value::Null) => None,
+            Some(Value::String(text)) if text.trim().is_empty() => None,
+            Some(value) => Some(value),
+        };
+        let Some(value) = value else {
+            if self.required {
+                return Err(format!("'{}' is required", self.label));
+            }
+            return Ok(None);
+        };
+
+        match (self.field_type, value) {
+            (CheckoutFieldType::Text, Value::String(text)) => {
+                let text = text.trim();
+                let limit = self
+                    .max_length
+                    .map(|max| max as usize)
+                    .unwrap_or(Self::DEFAULT_MAX_LENGTH);
+                if text.chars().count() > limit {
+                    return Err(format!(
+                        "'{}' must be at most {} characters",
+                        self.label, limit
+                    ));
+                }
+                Ok(Some(Value::String(text.to_string())))
+            }
+            (CheckoutFieldType::Number, Value::Number(_))
+            | (CheckoutFieldType::Boolean, Value::Bool(_)) => Ok(Some(value.clone())),
+            (field_type, _) => Err(format!(
+                "'{}' must be a {}",
+                self.label,
+                match field_type {
+                    CheckoutFieldType::Text => "string",
+                    CheckoutFieldType::Number => "number",
+                    CheckoutFieldType::Boolean => "boolean",
+                }
+            )),
+        }
+    }
+}
+
+/// Validates the values a buyer submitted for one store against that store's
+/// checkout fields and returns them cleaned, keyed by field key. Keys the
+/// store does not define are rejected.
+pub fn validate_checkout_values(
+    fields: &[CheckoutField],
+    submitted: &Map<String, Value>,
+) -> Result<Map<String, Value>, String> {
+    if let Some(unknown) = submitted
+        .keys()
+        .find(|key| !fields.iter().any(|field| &field.key == *key))
+    {
+        return Err(format!("Unknown checkout field '{}'", unknown));
+    }
+
+    let mut cleaned = Map::new();
+    for field in fields {
+        if let Some(value) = field.clean(submitted.get(&field.key))? {
+            cleaned.insert(field.key.clone(), value);
+        }
+    }
+    Ok(cleaned)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateCheckoutFieldRequest {
+    #[validate(
+        length(min = 1, max = 50),
+        custom(function = "crate::utils::validators::validate_field_key")
+    )]
+    pub key: String,
+
+    #[validate(length(min = 1, max = 100))]
+    pub label: String,
+
+    pub field_type: CheckoutFieldType,
+
+    #[serde(default)]
+    pub required: bool,
+
+    #[validate(range(min = 1, max = 2000))]
+    pub max_length: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreModerationAction {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub actor_id: Uuid,
+    pub previous_status: StoreStatus,
+    pub new_status: StoreStatus,
+    pub reason: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ModerateStoreRequest {
+    pub status: StoreStatus,
+
+    #[validate(length(min = 3, max = 1000))]
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreModerationResponse {
+    pub store: Store,
+    pub action: StoreModerationAction,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "appeal_status", rename_all = "PascalCase")]
+pub enum AppealStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreAppeal {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub submitted_by: Uuid,
+    pub message: String,
+    pub status: AppealStatus,
+    pub reviewed_by: Option<Uuid>,
+    pub review_note: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateAppealRequest {
+    #[validate(length(min = 10, max = 2000))]
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ResolveAppealRequest {
+    pub approve: bool,
+
+    #[validate(length(max = 1000))]
+    pub note: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "member_role", rename_all = "PascalCase")]
 pub enum MemberRole {
@@ -79,12 +379,61 @@ pub struct StoreMember {
     pub user_id: Uuid,
     pub role: MemberRole,
     pub permissions: serde_json::Value,
+    /// The store role whose permissions a Custom member holds on top of
+    /// their own.
+    pub custom_role_id: Option<Uuid>,
     pub invited_by: Option<Uuid>,
     pub joined_at: DateTime<Utc>,
     pub is_active: bool,
     pub updated_at: DateTime<Utc>,
 }
 
+/// A named permission set a store defines for its Custom members.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreRole {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub name: String,
+    pub permissions: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct StoreRoleRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+/// Summary of the cleanup performed after a store was closed.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreCleanup {
+    pub store_id: Uuid,
+    pub products_deactivated: i32,
+    pub cart_items_removed: i32,
+    pub customers_notified: i32,
+    pub completed_at: DateTime<Utc>,
+}
+
+/// A store the user owns or belongs to, together with their role in it.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct MemberStore {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    pub store: Store,
+    pub role: MemberRole,
+}
+
+/// A store the user belongs to, with the permissions their role and grants
+/// resolve to there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreMembership {
+    #[serde(flatten)]
+    pub store: MemberStore,
+    pub permissions: BTreeSet<Permission>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
 #[sqlx(type_name = "access_level", rename_all = "PascalCase")]
 pub enum AccessLevel {
@@ -105,13 +454,191 @@ pub struct StoreAccessGrant {
     pub revoked_at: Option<DateTime<Utc>>,
 }
 
+/// Where a grant stands, for filtering grant listings.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GrantStatus {
+    Active,
+    Expired,
+    Revoked,
+}
+
+/// Changes a live grant's access level or expiry; omitted fields are kept.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateAccessGrantRequest {
+    pub access_level: Option<AccessLevel>,
+    /// Must be in the future; extending an expired grant reinstates it.
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// Who a bulk grant is for: a user id, or the email of a registered user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GrantRecipient {
+    UserId(Uuid),
+    Email(String),
+}
+
+impl std::fmt::Display for GrantRecipient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GrantRecipient::UserId(id) => write!(f, "{}", id),
+            GrantRecipient::Email(email) => f.write_str(email),
+        }
+    }
+}
+
+/// Grants the same access to many users at once. Users who already hold a
+/// live grant have it replaced by this one.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct BulkGrantRequest {
+    #[validate(length(min = 1, max = 500))]
+    pub recipients: Vec<GrantRecipient>,
+    #[serde(default = "default_invite_access_level")]
+    pub access_level: AccessLevel,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// What happened to one recipient of a bulk grant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BulkGrantOutcome {
+    Granted { grant: StoreAccessGrant },
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkGrantResult {
+    /// The recipient as given in the request.
+    pub recipient: String,
+    #[serde(flatten)]
+    pub outcome: BulkGrantOutcome,
+}
+
+/// A shareable link granting access to a store to anyone who opens it,
+/// until it expires or runs out of uses.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreInviteLink {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub created_by: Uuid,
+    pub access_level: AccessLevel,
+    pub max_uses: i32,
+    pub use_count: i32,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateInviteLinkRequest {
+    #[serde(default = "default_invite_access_level")]
+    pub access_level: AccessLevel,
+    #[validate(range(min = 1, max = 1000))]
+    pub max_uses: i32,
+    /// How long the link stays valid, one week unless given.
+    #[serde(default = "default_invite_ttl_hours")]
+    #[validate(range(min = 1, max = 2160))]
+    pub expires_in_hours: i64,
+}
+
+fn default_invite_access_level() -> AccessLevel {
+    AccessLevel::ViewAndBuy
+}
+
+fn default_invite_ttl_hours() -> i64 {
+    168
+}
+
+/// A newly created invite link with the signed URL to share.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedInviteLink {
+    #[serde(flatten)]
+    pub link: StoreInviteLink,
+    pub token: String,
+    pub url: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InviteMemberRequest {
     pub user_id: Uuid,
     pub role: MemberRole,
     pub permissions: Vec<Permission>,
+    /// A store role for a Custom member.
+    #[serde(default)]
+    pub custom_role_id: Option<Uuid>,
+}
+
+/// One line of a member import. `permissions` lists extra permission names
+/// separated by `;`, e.g. `EDIT_PRODUCTS;VIEW_STATS`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MemberImportRow {
+    pub email: String,
+    pub role: MemberRole,
+    pub permissions: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateMemberRequest {
+    pub role: MemberRole,
+    pub permissions: Vec<Permission>,
+    /// A store role for a Custom member; omitting it removes their role.
+    #[serde(default)]
+    pub custom_role_id: Option<Uuid>,
+}
+
+/// What a store analytics report covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StoreAnalyticsParams {
+    pub timeframe_days: i64,
+    pub top_products: i64,
+    /// Only orders whose group has this payment status are counted.
+    pub payment_status: PaymentStatus,
+    /// Earlier window the summary is compared against, if any.
+    pub compare: Option<AnalyticsComparison>,
+}
+
+/// Earlier window an analytics summary can be compared against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyticsComparison {
+    /// The window of the same length just before the report's.
+    PreviousPeriod,
+    /// The report's window a year earlier.
+    PreviousYear,
+}
+
+impl AnalyticsComparison {
+    /// The comparison window for a report covering `since` to `until`.
+    pub fn window(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        match self {
+            AnalyticsComparison::PreviousPeriod => (since - (until - since), since),
+            AnalyticsComparison::PreviousYear => {
+                let year_earlier = |at: DateTime<Utc>| {
+                    at.checked_sub_months(Months::new(12))
+                        .unwrap_or(at - chrono::Duration::days(365))
+                };
+                (year_earlier(since), year_earlier(until))
+            }
+        }
+    }
+
+    /// How many days of history a report over `timeframe_days` needs once
+    /// compared.
+    pub fn lookback_days(&self, timeframe_days: i64) -> i64 {
+        match self {
+            AnalyticsComparison::PreviousPeriod => timeframe_days * 2,
+            AnalyticsComparison::PreviousYear => timeframe_days + 366,
+        }
+    }
+}
+
+/// Totals of the orders matching the report's payment status, followed by
+/// the paid and pending orders of the timeframe whatever the filter.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoreAnalyticsSummary {
     pub total_orders: i64,
@@ -119,6 +646,79 @@ pub struct StoreAnalyticsSummary {
     pub average_order_value: Decimal,
     pub unique_customers: i64,
     pub timeframe_days: i64,
+    pub paid_orders: i64,
+    pub paid_revenue: Decimal,
+    pub pending_orders: i64,
+    pub pending_revenue: Decimal,
+}
+
+/// A summary figure next to its value in the comparison window.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDelta {
+    pub current: Decimal,
+    pub previous: Decimal,
+    pub change: Decimal,
+    /// Change as a percentage of the previous value; absent when that was
+    /// zero.
+    pub percent_change: Option<f64>,
+}
+
+impl MetricDelta {
+    pub fn new(current: Decimal, previous: Decimal) -> Self {
+        let change = current - previous;
+        let percent_change = (!previous.is_zero())
+            .then(|| (change / previous * Decimal::ONE_HUNDRED).round_dp(2))
+            .and_then(|percent| percent.to_f64());
+        Self {
+            current,
+            previous,
+            change,
+            percent_change,
+        }
+    }
+}
+
+/// The report's summary computed again for the comparison window, with the
+/// change of each headline figure.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreSummaryComparison {
+    pub compare: AnalyticsComparison,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub summary: StoreAnalyticsSummary,
+    pub total_orders: MetricDelta,
+    pub total_revenue: MetricDelta,
+    pub average_order_value: MetricDelta,
+    pub unique_customers: MetricDelta,
+}
+
+impl StoreSummaryComparison {
+    pub fn new(
+        compare: AnalyticsComparison,
+        (period_start, period_end): (DateTime<Utc>, DateTime<Utc>),
+        current: &StoreAnalyticsSummary,
+        previous: StoreAnalyticsSummary,
+    ) -> Self {
+        Self {
+            compare,
+            period_start,
+            period_end,
+            total_orders: MetricDelta::new(
+                current.total_orders.into(),
+                previous.total_orders.into(),
+            ),
+            total_revenue: MetricDelta::new(current.total_revenue, previous.total_revenue),
+            average_order_value: MetricDelta::new(
+                current.average_order_value,
+                previous.average_order_value,
+            ),
+            unique_customers: MetricDelta::new(
+                current.unique_customers.into(),
+                previous.unique_customers.into(),
+            ),
+            summary: previous,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -136,18 +736,94 @@ pub struct StoreTopProduct {
     pub revenue: Decimal,
 }
 
+/// Granularity of the periods a tax report is broken down by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaxReportPeriod {
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl TaxReportPeriod {
+    /// Field name accepted by Postgres' `DATE_TRUNC`.
+    pub fn trunc_field(&self) -> &'static str {
+        match self {
+            TaxReportPeriod::Month => "month",
+            TaxReportPeriod::Quarter => "quarter",
+            TaxReportPeriod::Year => "year",
+        }
+    }
+}
+
+/// Tax collected in one period for one jurisdiction at one rate.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxReportRow {
+    pub period_start: NaiveDate,
+    pub jurisdiction: String,
+    pub rate: Decimal,
+    pub order_count: i64,
+    pub taxable_amount: Decimal,
+    pub tax_collected: Decimal,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StoreAnalyticsResponse {
     pub summary: StoreAnalyticsSummary,
     pub sales_trend: Vec<StoreSalesPoint>,
     pub top_products: Vec<StoreTopProduct>,
+    pub funnel: StoreFunnel,
+    /// Present when the report was asked to compare against an earlier
+    /// window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<StoreSummaryComparison>,
+    /// When the figures were computed. Cached reports can lag behind the
+    /// latest orders by up to the cache lifetime.
+    pub generated_at: DateTime<Utc>,
+    /// Whether the report was served from the analytics cache.
+    pub cached: bool,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
     use validator::Validate;
 
+    #[test]
+    fn metric_deltas_report_absolute_and_percentage_change() {
+        let delta = MetricDelta::new(Decimal::new(150, 0), Decimal::new(120, 0));
+        assert_eq!(delta.change, Decimal::new(30, 0));
+        assert_eq!(delta.percent_change, Some(25.0));
+
+        let drop = MetricDelta::new(Decimal::new(2, 0), Decimal::new(3, 0));
+        assert_eq!(drop.percent_change, Some(-33.33));
+
+        let from_zero = MetricDelta::new(Decimal::new(5, 0), Decimal::ZERO);
+        assert_eq!(from_zero.change, Decimal::new(5, 0));
+        assert_eq!(from_zero.percent_change, None);
+    }
+
+    #[test]
+    fn comparison_windows_precede_the_report() {
+        let since = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let until = Utc.with_ymd_and_hms(2024, 3, 31, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            AnalyticsComparison::PreviousPeriod.window(since, until),
+            (Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap(), since)
+        );
+        assert_eq!(
+            AnalyticsComparison::PreviousYear.window(since, until),
+            (
+                Utc.with_ymd_and_hms(2023, 3, 1, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 3, 31, 0, 0, 0).unwrap()
+            )
+        );
+        assert_eq!(AnalyticsComparison::PreviousPeriod.lookback_days(30), 60);
+    }
+
     #[test]
     fn create_store_validation() {
         let valid = CreateStoreRequest {
@@ -168,4 +844,67 @@ mod tests {
         };
         assert!(invalid.validate().is_err());
     }
+
+    #[test]
+    fn settings_compute_tax_and_free_shipping() {
+        let mut settings = StoreSettings::defaults(Uuid::new_v4());
+        settings.tax_rate = Decimal::new(825, 4);
+        settings.flat_shipping_fee = Decimal::new(599, 2);
+        settings.free_shipping_threshold = Some(Decimal::new(100, 0));
+
+        assert_eq!(
+            settings.tax_for(Decimal::new(4000, 2)),
+            Decimal::new(330, 2)
+        );
+        assert_eq!(
+            settings.shipping_for(Decimal::new(9999, 2)),
+            Decimal::new(599, 2)
+        );
+        assert_eq!(settings.shipping_for(Decimal::new(100, 0)), Decimal::ZERO);
+    }
+
+    fn checkout_field(key: &str, field_type: CheckoutFieldType, required: bool) -> CheckoutField {
+        CheckoutField {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            key: key.into(),
+            label: key.replace('_', " "),
+            field_type,
+            required,
+            max_length: Some(20),
+            position: 0,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn checkout_values_are_checked_against_field_definitions() {
+        let fields = vec![
+            checkout_field("company_name", CheckoutFieldType::Text, false),
+            checkout_field("gift", CheckoutFieldType::Boolean, true),
+        ];
+        let submitted = |value: Value| value.as_object().cloned().unwrap();
+
+        let cleaned = validate_checkout_values(
+            &fields,
+            &submitted(serde_json::json!({"company_name": "  Acme  ", "gift": false})),
+        )
+        .unwrap();
+        assert_eq!(cleaned["company_name"], "Acme");
+        assert_eq!(cleaned["gift"], false);
+
+        let cleaned =
+            validate_checkout_values(&fields, &submitted(serde_json::json!({"gift": true})))
+                .unwrap();
+        assert!(!cleaned.contains_key("company_name"));
+
+        for invalid in [
+            serde_json::json!({"company_name": "Acme"}),
+            serde_json::json!({"gift": "yes"}),
+            serde_json::json!({"gift": true, "company_name": "A".repeat(21)}),
+            serde_json::json!({"gift": true, "coupon": "FREE"}),
+        ] {
+            assert!(validate_checkout_values(&fields, &submitted(invalid)).is_err());
+        }
+    }
 }
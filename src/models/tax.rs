@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
+#[sqlx(type_name = "tax_transaction_status", rename_all = "PascalCase")]
+pub enum TaxTransactionStatus {
+    Pending,
+    Committed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxTransaction {
+    pub id: Uuid,
+    pub order_id: Uuid,
+    pub store_id: Uuid,
+    pub status: TaxTransactionStatus,
+    pub external_id: Option<String>,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub committed_at: Option<DateTime<Utc>>,
+}
+
+/// A sale as reported to a tax provider for filing.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TaxFiling {
+    pub transaction_id: Uuid,
+    pub attempts: i32,
+    pub order_id: Uuid,
+    pub order_number: String,
+    pub store_id: Uuid,
+    pub shipping_address: Value,
+    pub subtotal: Decimal,
+    pub shipping_cost: Decimal,
+    pub tax_amount: Decimal,
+    pub ordered_at: DateTime<Utc>,
+}
@@ -13,6 +13,8 @@ pub struct User {
     pub address: Option<serde_json::Value>,
     pub loyalty_points: i32,
     pub is_active: bool,
+    pub is_platform_admin: bool,
+    pub token_version: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -68,6 +70,15 @@ pub struct LoginRequest {
     pub password: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 8, max = 128))]
+    pub current_password: String,
+
+    #[validate(length(min = 8, max = 128))]
+    pub new_password: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuthTokenResponse {
     pub token: String,
@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::{Validate, ValidateUrl, ValidationError};
+
+use super::notification::DeliveryStatus;
+
+pub const ORDER_CREATED: &str = "order.created";
+pub const ORDER_PAID: &str = "order.paid";
+pub const PRODUCT_LOW_STOCK: &str = "product.low_stock";
+
+/// Events stores can subscribe their webhooks to.
+pub const WEBHOOK_EVENTS: [&str; 3] = [ORDER_CREATED, ORDER_PAID, PRODUCT_LOW_STOCK];
+
+/// The sample event sent when a store tests a webhook. Webhooks can't
+/// subscribe to it.
+pub const WEBHOOK_PING: &str = "webhook.ping";
+
+/// A store endpoint that receives signed POSTs for the events it lists.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub store_id: Uuid,
+    pub url: String,
+    /// Only shown once, when the webhook is created.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A newly created webhook together with its signing secret.
+#[derive(Debug, Clone, Serialize)]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateWebhookRequest {
+    #[validate(length(max = 2048), custom(function = "validate_webhook_url"))]
+    pub url: String,
+    #[validate(length(min = 1), custom(function = "validate_webhook_events"))]
+    pub events: Vec<String>,
+    /// Generated when not given.
+    #[validate(length(min = 16, max = 128))]
+    pub secret: Option<String>,
+}
+
+fn validate_webhook_url(url: &str) -> Result<(), ValidationError> {
+    if url.starts_with("https://") && url.validate_url() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_webhook_url"))
+    }
+}
+
+fn validate_webhook_events(events: &[String]) -> Result<(), ValidationError> {
+    if events
+        .iter()
+        .all(|event| WEBHOOK_EVENTS.contains(&event.as_str()))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("unknown_webhook_event"))
+    }
+}
+
+/// One event sent, or still to be sent, to a webhook.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    /// The outbox event delivered, also sent as the delivery id so
+    /// endpoints can recognize retries.
+    pub event_id: Uuid,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// How a webhook's endpoint answered a test delivery.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPing {
+    /// `None` when no answer was received.
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// A claimed delivery with where and how to send it.
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DueWebhookDelivery {
+    #[sqlx(flatten)]
+    pub delivery: WebhookDelivery,
+    pub url: String,
+    pub secret: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, events: &[&str]) -> CreateWebhookRequest {
+        CreateWebhookRequest {
+            url: url.into(),
+            events: events.iter().map(|event| event.to_string()).collect(),
+            secret: None,
+        }
+    }
+
+    #[test]
+    fn webhooks_need_an_https_url_and_known_events() {
+        assert!(request("https://example.com/hooks", &[ORDER_PAID])
+            .validate()
+            .is_ok());
+        assert!(request("http://example.com/hooks", &[ORDER_PAID])
+            .validate()
+            .is_err());
+        assert!(request("https://example.com/hooks", &[])
+            .validate()
+            .is_err());
+        assert!(request("https://example.com/hooks", &["order.shipped"])
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+
+use super::Notifier;
+use crate::models::notification::NotificationDelivery;
+
+/// Writes deliveries to the log instead of sending them. The default until a
+/// mail provider or HTTP client is configured; suitable for development.
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn send(&self, delivery: &NotificationDelivery) -> crate::Result<()> {
+        tracing::info!(
+            delivery_id = %delivery.id,
+            store_id = %delivery.store_id,
+            kind = %delivery.kind,
+            channel = ?delivery.channel,
+            target = %delivery.target,
+            "{}",
+            delivery.message
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+use async_trait::async_trait;
+
+use crate::models::notification::NotificationDelivery;
+
+pub mod log;
+
+pub use log::LogNotifier;
+
+/// Sends queued email and webhook notifications. An error leaves the
+/// delivery queued for another attempt.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, delivery: &NotificationDelivery) -> crate::Result<()>;
+}
@@ -0,0 +1,139 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    jobs::{JobHandler, JobQueue, JobRegistry},
+    models::{domain_event::PublishedEvent, job::Job},
+    repositories::OutboxRepository,
+};
+
+const BATCH_SIZE: i64 = 100;
+
+/// How long published events are kept for debugging before being removed.
+const PUBLISHED_RETENTION_DAYS: i64 = 7;
+
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Reacts to domain events. Events arrive at least once and in no
+/// particular order, so handlers must tolerate repeats, using the event id
+/// to recognize them.
+#[async_trait]
+pub trait EventSubscriber: Send + Sync {
+    async fn handle(&self, event: &PublishedEvent) -> anyhow::Result<()>;
+}
+
+/// Moves committed events from the outbox to their subscribers. Each
+/// subscriber gets its own background job per event, queued in the same
+/// transaction that marks the event published, so a crash at any point
+/// neither loses an event nor publishes it twice, and one failing
+/// subscriber is retried without the others seeing the event again.
+#[derive(Clone)]
+pub struct OutboxRelay {
+    outbox: OutboxRepository,
+    jobs: JobQueue,
+    subscribers: Vec<(String, Arc<dyn EventSubscriber>)>,
+}
+
+impl OutboxRelay {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            outbox: OutboxRepository::new(pool.clone()),
+            jobs: JobQueue::new(pool),
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Adds a subscriber under a name unique to it, which names its jobs.
+    pub fn subscribe(mut self, name: &str, subscriber: Arc<dyn EventSubscriber>) -> Self {
+        self.subscribers.push((name.to_string(), subscriber));
+        self
+    }
+
+    /// Registers the job handlers that hand events to the subscribers.
+    pub fn register_jobs(&self, registry: JobRegistry) -> JobRegistry {
+        self.subscribers
+            .iter()
+            .fold(registry, |registry, (name, subscriber)| {
+                registry.register(&job_kind(name), Arc::new(SubscriberJob(subscriber.clone())))
+            })
+    }
+
+    /// Publishes up to `limit` committed events, returning how many.
+    pub async fn relay_pending(&self, limit: i64) -> crate::Result<usize> {
+        let mut tx = self.outbox.pool().begin().await?;
+        let events = self.outbox.lock_unpublished_in_tx(&mut tx, limit).await?;
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        for event in &events {
+            // Passed on undecoded, so an event this build does not know
+            // fails its subscriber jobs instead of blocking the outbox
+            let published = json!({
+                "id": event.id,
+                "occurred_at": event.created_at,
+                "event": event.payload,
+            });
+            for (name, _) in &self.subscribers {
+                self.jobs
+                    .enqueue_in_tx(&mut tx, &job_kind(name), &published)
+                    .await?;
+            }
+        }
+        let ids: Vec<_> = events.iter().map(|event| event.id).collect();
+        self.outbox.mark_published_in_tx(&mut tx, &ids).await?;
+        tx.commit().await?;
+
+        Ok(events.len())
+    }
+
+    /// Relays events every `interval` and hourly removes published events
+    /// past their retention.
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            let mut cleanup = tokio::time::interval(CLEANUP_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => loop {
+                        match self.relay_pending(BATCH_SIZE).await {
+                            Ok(published) if (published as i64) < BATCH_SIZE => break,
+                            Ok(_) => {}
+                            Err(err) => {
+                                tracing::error!("Outbox relay failed: {}", err);
+                                break;
+                            }
+                        }
+                    },
+                    _ = cleanup.tick() => {
+                        let cutoff = Utc::now() - chrono::Duration::days(PUBLISHED_RETENTION_DAYS);
+                        match self.outbox.delete_published_before(cutoff).await {
+                            Ok(0) => {}
+                            Ok(removed) => tracing::info!(removed, "Published outbox events removed"),
+                            Err(err) => tracing::error!("Outbox cleanup failed: {}", err),
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+fn job_kind(subscriber: &str) -> String {
+    format!("event:{subscriber}")
+}
+
+struct SubscriberJob(Arc<dyn EventSubscriber>);
+
+#[async_trait]
+impl JobHandler for SubscriberJob {
+    async fn run(&self, job: &Job) -> anyhow::Result<()> {
+        let event: PublishedEvent = serde_json::from_value(job.payload.clone())?;
+        self.0.handle(&event).await
+    }
+}
@@ -0,0 +1,44 @@
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use rust_decimal::Decimal;
+
+use super::{PaymentEvent, PaymentIntent, PaymentProvider, PaymentRequest};
+use crate::{error::AppError, models::payment::PaymentAttemptStatus};
+
+/// Payments collected outside the platform. Nothing is charged: the payment
+/// waits until store staff confirm the money arrived by capturing it.
+pub struct ManualPaymentProvider;
+
+#[async_trait]
+impl PaymentProvider for ManualPaymentProvider {
+    async fn create_intent(&self, request: &PaymentRequest<'_>) -> crate::Result<PaymentIntent> {
+        Ok(PaymentIntent {
+            reference: format!("manual-{}", request.payment_id),
+            status: PaymentAttemptStatus::Pending,
+            approval_url: None,
+        })
+    }
+
+    async fn capture(&self, _reference: &str) -> crate::Result<PaymentAttemptStatus> {
+        Ok(PaymentAttemptStatus::Captured)
+    }
+
+    async fn refund(
+        &self,
+        _reference: &str,
+        _amount: Decimal,
+        _currency: &str,
+    ) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn parse_webhook(
+        &self,
+        _headers: &HeaderMap,
+        _body: &[u8],
+    ) -> crate::Result<Option<PaymentEvent>> {
+        Err(AppError::BadRequest(
+            "Manual payments have no webhooks".into(),
+        ))
+    }
+}
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::payment::{PaymentAttemptStatus, PaymentProviderKind},
+};
+
+pub mod manual;
+pub mod paypal;
+
+pub use manual::ManualPaymentProvider;
+pub use paypal::PayPalProvider;
+
+/// What a sub-order asks the provider to collect.
+pub struct PaymentRequest<'a> {
+    /// Our payment id, sent along so the provider can be reconciled with it.
+    pub payment_id: Uuid,
+    pub order_number: &'a str,
+    pub amount: Decimal,
+    pub currency: &'a str,
+}
+
+/// A payment the provider created.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaymentIntent {
+    /// The provider's id for the payment; captures, refunds and webhooks
+    /// refer to it.
+    pub reference: String,
+    pub status: PaymentAttemptStatus,
+    /// Where to send the buyer to approve the payment, if they have to.
+    pub approval_url: Option<String>,
+}
+
+/// A payment update a provider pushed to its webhook.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PaymentEvent {
+    /// The buyer approved the payment; it can be captured.
+    Approved {
+        reference: String,
+    },
+    Captured {
+        reference: String,
+    },
+    Failed {
+        reference: String,
+        reason: String,
+    },
+    Refunded {
+        reference: String,
+    },
+}
+
+/// A payment backend. Checkout only talks to this trait, so stores can take
+/// payments through whichever provider their settings name.
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    async fn create_intent(&self, request: &PaymentRequest<'_>) -> crate::Result<PaymentIntent>;
+
+    /// Collects an approved payment and returns its new status.
+    async fn capture(&self, reference: &str) -> crate::Result<PaymentAttemptStatus>;
+
+    async fn refund(&self, reference: &str, amount: Decimal, currency: &str) -> crate::Result<()>;
+
+    /// Verifies and decodes a webhook delivery. Events that do not change
+    /// a payment come back as `None`.
+    async fn parse_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> crate::Result<Option<PaymentEvent>>;
+}
+
+/// The providers payments can be taken through. Manual payments are always
+/// available; the others only when configured.
+pub struct PaymentProviders {
+    providers: HashMap<PaymentProviderKind, Arc<dyn PaymentProvider>>,
+}
+
+impl PaymentProviders {
+    pub fn manual_only() -> Self {
+        let mut providers: HashMap<PaymentProviderKind, Arc<dyn PaymentProvider>> = HashMap::new();
+        providers.insert(PaymentProviderKind::Manual, Arc::new(ManualPaymentProvider));
+        Self { providers }
+    }
+
+    pub fn with_provider(
+        mut self,
+        kind: PaymentProviderKind,
+        provider: Arc<dyn PaymentProvider>,
+    ) -> Self {
+        self.providers.insert(kind, provider);
+        self
+    }
+
+    pub fn get(&self, kind: PaymentProviderKind) -> crate::Result<&Arc<dyn PaymentProvider>> {
+        self.providers.get(&kind).ok_or_else(|| {
+            AppError::BadRequest(format!(
+                "Payment provider '{}' is not available",
+                kind.as_str()
+            ))
+        })
+    }
+}
+
+impl Default for PaymentProviders {
+    fn default() -> Self {
+        Self::manual_only()
+    }
+}
@@ -0,0 +1,426 @@
+use std::{
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{PaymentEvent, PaymentIntent, PaymentProvider, PaymentRequest};
+use crate::{
+    error::AppError,
+    metrics::SharedMetrics,
+    models::payment::PaymentAttemptStatus,
+    utils::breaker::{BreakerConfig, CircuitBreaker},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Access tokens are renewed this long before PayPal expires them.
+const TOKEN_MARGIN: Duration = Duration::from_secs(60);
+/// Currencies PayPal only accepts whole amounts in.
+const ZERO_DECIMAL_CURRENCIES: [&str; 3] = ["HUF", "JPY", "TWD"];
+
+/// PayPal Checkout through the Orders v2 REST API. Buyers approve each
+/// payment on PayPal; the approval webhook triggers the capture.
+pub struct PayPalProvider {
+    client: reqwest::Client,
+    base_url: String,
+    client_id: String,
+    client_secret: RwLock<String>,
+    /// Id of the webhook registered with PayPal, needed to verify
+    /// deliveries.
+    webhook_id: String,
+    token: Mutex<Option<(String, Instant)>>,
+    breaker: CircuitBreaker,
+}
+
+impl PayPalProvider {
+    pub fn new(
+        base_url: impl Into<String>,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        webhook_id: impl Into<String>,
+    ) -> crate::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| AppError::Internal(err.into()))?;
+        Ok(Self {
+            client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client_id: client_id.into(),
+            client_secret: RwLock::new(client_secret.into()),
+            webhook_id: webhook_id.into(),
+            token: Mutex::new(None),
+            breaker: CircuitBreaker::new("paypal", BreakerConfig::default()),
+        })
+    }
+
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.breaker = self.breaker.with_metrics(metrics);
+        self
+    }
+
+    /// Sends the request through the breaker. Only transport failures and
+    /// server errors count against PayPal; a rejected request is the
+    /// caller's to handle.
+    async fn execute(&self, request: reqwest::RequestBuilder) -> crate::Result<reqwest::Response> {
+        let response = self
+            .breaker
+            .call(async {
+                let response = request.send().await?;
+                if response.status().is_server_error() {
+                    return response.error_for_status();
+                }
+                Ok(response)
+            })
+            .await
+            .map_err(|err| AppError::Internal(anyhow::anyhow!(err.to_string())))?;
+        response
+            .error_for_status()
+            .map_err(|err| AppError::Internal(err.into()))
+    }
+
+    /// Swaps the client secret for subsequent token requests, e.g. after a
+    /// rotation.
+    pub fn set_client_secret(&self, secret: &str) {
+        *self.client_secret.write().unwrap() = secret.to_string();
+        *self.token.lock().unwrap() = None;
+    }
+
+    async fn access_token(&self) -> crate::Result<String> {
+        if let Some((token, expires_at)) = self.token.lock().unwrap().as_ref() {
+            if Instant::now() < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        let secret = self.client_secret.read().unwrap().clone();
+        let request = self
+            .client
+            .post(format!("{}/v1/oauth2/token", self.base_url))
+            .basic_auth(&self.client_id, Some(secret))
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "application/x-www-form-urlencoded",
+            )
+            .body("grant_type=client_credentials");
+        let response: TokenResponse = self
+            .execute(request)
+            .await?
+            .json()
+            .await
+            .map_err(|err| AppError::Internal(err.into()))?;
+
+        let lifetime = Duration::from_secs(response.expires_in).saturating_sub(TOKEN_MARGIN);
+        *self.token.lock().unwrap() =
+            Some((response.access_token.clone(), Instant::now() + lifetime));
+        Ok(response.access_token)
+    }
+
+    async fn send<R: for<'de> Deserialize<'de>>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<&Value>,
+    ) -> crate::Result<R> {
+        let token = self.access_token().await?;
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .bearer_auth(token);
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        self.execute(request)
+            .await?
+            .json::<R>()
+            .await
+            .map_err(|err| AppError::Internal(err.into()))
+    }
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct OrderResponse {
+    id: String,
+    #[serde(default)]
+    links: Vec<Link>,
+    #[serde(default)]
+    purchase_units: Vec<PurchaseUnit>,
+}
+
+#[derive(Deserialize)]
+struct Link {
+    href: String,
+    rel: String,
+}
+
+#[derive(Deserialize)]
+struct PurchaseUnit {
+    payments: Option<UnitPayments>,
+}
+
+#[derive(Deserialize)]
+struct UnitPayments {
+    #[serde(default)]
+    captures: Vec<Capture>,
+}
+
+#[derive(Deserialize)]
+struct Capture {
+    id: String,
+    status: String,
+}
+
+impl OrderResponse {
+    fn capture(&self) -> Option<&Capture> {
+        self.purchase_units
+            .iter()
+            .filter_map(|unit| unit.payments.as_ref())
+            .flat_map(|payments| payments.captures.iter())
+            .next()
+    }
+}
+
+#[derive(Serialize)]
+struct VerifyRequest<'a> {
+    auth_algo: &'a str,
+    cert_url: &'a str,
+    transmission_id: &'a str,
+    transmission_sig: &'a str,
+    transmission_time: &'a str,
+    webhook_id: &'a str,
+    webhook_event: &'a Value,
+}
+
+#[derive(Deserialize)]
+struct VerifyResponse {
+    verification_status: String,
+}
+
+/// `amount` as PayPal expects it: whole units for zero-decimal currencies,
+/// cents for the rest.
+fn amount_value(amount: Decimal, currency: &str) -> String {
+    let decimals = if ZERO_DECIMAL_CURRENCIES.contains(&currency.to_ascii_uppercase().as_str()) {
+        0
+    } else {
+        2
+    };
+    let amount = amount.round_dp_with_strategy(decimals, RoundingStrategy::MidpointAwayFromZero);
+    format!("{:.*}", decimals as usize, amount)
+}
+
+/// PayPal's capture status in our terms.
+fn capture_status(status: &str) -> PaymentAttemptStatus {
+    match status {
+        "COMPLETED" => PaymentAttemptStatus::Captured,
+        "PENDING" => PaymentAttemptStatus::Authorized,
+        _ => PaymentAttemptStatus::Failed,
+    }
+}
+
+/// The payment change a webhook notification describes. Capture events
+/// name the PayPal order they belong to in `supplementary_data`.
+fn event_from(notification: &Value) -> Option<PaymentEvent> {
+    let resource = notification.get("resource")?;
+    let order_id = || {
+        resource
+            .pointer("/supplementary_data/related_ids/order_id")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    match notification.get("event_type")?.as_str()? {
+        "CHECKOUT.ORDER.APPROVED" => Some(PaymentEvent::Approved {
+            reference: resource.get("id")?.as_str()?.to_string(),
+        }),
+        "PAYMENT.CAPTURE.COMPLETED" => Some(PaymentEvent::Captured {
+            reference: order_id()?,
+        }),
+        "PAYMENT.CAPTURE.DENIED" | "PAYMENT.CAPTURE.DECLINED" => Some(PaymentEvent::Failed {
+            reference: order_id()?,
+            reason: "Payment was declined by PayPal".into(),
+        }),
+        "PAYMENT.CAPTURE.REFUNDED" => Some(PaymentEvent::Refunded {
+            reference: order_id()?,
+        }),
+        _ => None,
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for PayPalProvider {
+    async fn create_intent(&self, request: &PaymentRequest<'_>) -> crate::Result<PaymentIntent> {
+        let body = json!({
+            "intent": "CAPTURE",
+            "purchase_units": [{
+                "reference_id": request.payment_id,
+                "invoice_id": request.order_number,
+                "amount": {
+                    "currency_code": request.currency,
+                    "value": amount_value(request.amount, request.currency),
+                },
+            }],
+        });
+        let order: OrderResponse = self
+            .send(reqwest::Method::POST, "/v2/checkout/orders", Some(&body))
+            .await?;
+        let approval_url = order
+            .links
+            .iter()
+            .find(|link| link.rel == "approve" || link.rel == "payer-action")
+            .map(|link| link.href.clone());
+
+        Ok(PaymentIntent {
+            reference: order.id,
+            status: PaymentAttemptStatus::Pending,
+            approval_url,
+        })
+    }
+
+    async fn capture(&self, reference: &str) -> crate::Result<PaymentAttemptStatus> {
+        let order: OrderResponse = self
+            .send(
+                reqwest::Method::POST,
+                &format!("/v2/checkout/orders/{}/capture", reference),
+                Some(&json!({})),
+            )
+            .await?;
+        Ok(order
+            .capture()
+            .map_or(PaymentAttemptStatus::Failed, |capture| {
+                capture_status(&capture.status)
+            }))
+    }
+
+    async fn refund(&self, reference: &str, amount: Decimal, currency: &str) -> crate::Result<()> {
+        let order: OrderResponse = self
+            .send(
+                reqwest::Method::GET,
+                &format!("/v2/checkout/orders/{}", reference),
+                None,
+            )
+            .await?;
+        let capture = order.capture().ok_or_else(|| {
+            AppError::Conflict(format!("PayPal order {} was never captured", reference))
+        })?;
+        let body = json!({
+            "amount": {
+                "currency_code": currency,
+                "value": amount_value(amount, currency),
+            },
+        });
+        let _: Value = self
+            .send(
+                reqwest::Method::POST,
+                &format!("/v2/payments/captures/{}/refund", capture.id),
+                Some(&body),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn parse_webhook(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> crate::Result<Option<PaymentEvent>> {
+        let notification: Value = serde_json::from_slice(body)
+            .map_err(|_| AppError::BadRequest("Malformed webhook payload".into()))?;
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| AppError::Authentication(format!("Missing {} header", name)))
+        };
+        let request = VerifyRequest {
+            auth_algo: header("paypal-auth-algo")?,
+            cert_url: header("paypal-cert-url")?,
+            transmission_id: header("paypal-transmission-id")?,
+            transmission_sig: header("paypal-transmission-sig")?,
+            transmission_time: header("paypal-transmission-time")?,
+            webhook_id: &self.webhook_id,
+            webhook_event: &notification,
+        };
+        let verification: VerifyResponse = self
+            .send(
+                reqwest::Method::POST,
+                "/v1/notifications/verify-webhook-signature",
+                Some(
+                    &serde_json::to_value(&request)
+                        .map_err(|err| AppError::Internal(err.into()))?,
+                ),
+            )
+            .await?;
+        if verification.verification_status != "SUCCESS" {
+            return Err(AppError::Authentication(
+                "Webhook signature verification failed".into(),
+            ));
+        }
+
+        Ok(event_from(&notification))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_events_map_to_payment_changes() {
+        let approved = json!({
+            "event_type": "CHECKOUT.ORDER.APPROVED",
+            "resource": { "id": "5O190127TN364715T" },
+        });
+        assert_eq!(
+            event_from(&approved),
+            Some(PaymentEvent::Approved {
+                reference: "5O190127TN364715T".into()
+            })
+        );
+
+        let captured = json!({
+            "event_type": "PAYMENT.CAPTURE.COMPLETED",
+            "resource": {
+                "id": "3C679366HH908993F",
+                "supplementary_data": { "related_ids": { "order_id": "5O190127TN364715T" } },
+            },
+        });
+        assert_eq!(
+            event_from(&captured),
+            Some(PaymentEvent::Captured {
+                reference: "5O190127TN364715T".into()
+            })
+        );
+
+        let unrelated = json!({
+            "event_type": "CUSTOMER.DISPUTE.CREATED",
+            "resource": { "id": "PP-D-1" },
+        });
+        assert_eq!(event_from(&unrelated), None);
+    }
+
+    #[test]
+    fn amounts_use_the_currency_decimals() {
+        assert_eq!(amount_value(Decimal::new(1999, 2), "USD"), "19.99");
+        assert_eq!(amount_value(Decimal::new(5, 0), "EUR"), "5.00");
+        assert_eq!(amount_value(Decimal::new(1500, 0), "JPY"), "1500");
+        assert_eq!(amount_value(Decimal::new(12345, 1), "huf"), "1235");
+        assert_eq!(amount_value(Decimal::new(99, 0), "TWD"), "99");
+    }
+
+    #[test]
+    fn capture_statuses_map_to_attempt_statuses() {
+        assert_eq!(capture_status("COMPLETED"), PaymentAttemptStatus::Captured);
+        assert_eq!(capture_status("PENDING"), PaymentAttemptStatus::Authorized);
+        assert_eq!(capture_status("DECLINED"), PaymentAttemptStatus::Failed);
+    }
+}
@@ -0,0 +1,95 @@
+//! Optional reporting of server errors to an external error tracker.
+//! Nothing is reported until a reporter is installed at startup; the JSON
+//! body sent to clients is the same either way.
+
+use std::{
+    error::Error as _,
+    sync::{Arc, OnceLock},
+};
+
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    middleware::{request_id::current_request_id, request_user::current_request_user},
+};
+
+#[cfg(feature = "sentry")]
+pub mod sentry;
+
+/// A server error as handed to the error tracker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    /// The `code` of the error response, e.g. `DATABASE_ERROR`.
+    pub code: String,
+    /// The error followed by its causes, outermost first.
+    pub chain: Vec<String>,
+    pub request_id: Option<String>,
+    pub user_id: Option<Uuid>,
+}
+
+impl ErrorReport {
+    /// Describes `err` within the request being handled.
+    pub fn new(err: &AppError) -> Self {
+        let mut chain = vec![err.to_string()];
+        let mut source = err.source();
+        while let Some(cause) = source {
+            chain.push(cause.to_string());
+            source = cause.source();
+        }
+
+        Self {
+            code: err.error_code().to_string(),
+            chain,
+            request_id: current_request_id(),
+            user_id: current_request_user(),
+        }
+    }
+}
+
+/// Where server errors are sent, such as Sentry.
+pub trait ErrorReporter: Send + Sync {
+    /// Sends the report without holding up the response; network calls
+    /// belong on a spawned task.
+    fn report(&self, report: ErrorReport);
+}
+
+static REPORTER: OnceLock<Arc<dyn ErrorReporter>> = OnceLock::new();
+
+/// Installs the reporter server errors are sent to. Only the first call
+/// takes effect; it returns `false` for later ones.
+pub fn install(reporter: Arc<dyn ErrorReporter>) -> bool {
+    REPORTER.set(reporter).is_ok()
+}
+
+/// Sends a server error to the installed reporter, if any.
+pub fn report(err: &AppError) {
+    if let Some(reporter) = REPORTER.get() {
+        reporter.report(ErrorReport::new(err));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_carry_the_error_chain_and_code() {
+        let err = AppError::Internal(
+            anyhow::anyhow!("connection reset").context("Capturing payment failed"),
+        );
+        let report = ErrorReport::new(&err);
+
+        assert_eq!(report.code, "INTERNAL_ERROR");
+        assert_eq!(
+            report.chain,
+            [
+                "Internal server error: Capturing payment failed",
+                "Capturing payment failed",
+                "connection reset",
+            ]
+        );
+        assert_eq!(report.request_id, None);
+        assert_eq!(report.user_id, None);
+    }
+}
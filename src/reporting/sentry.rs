@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+use reqwest::{Client, Url};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::{ErrorReport, ErrorReporter};
+
+/// Sends error reports to Sentry's store endpoint, one event per report.
+/// Delivery is best effort: failures are logged and the report dropped.
+#[derive(Debug, Clone)]
+pub struct SentryReporter {
+    client: Client,
+    store_url: Url,
+    auth_header: String,
+    environment: Option<String>,
+}
+
+impl SentryReporter {
+    /// Reads a project DSN such as `https://<key>@o1.ingest.sentry.io/42`.
+    pub fn from_dsn(dsn: &str, environment: Option<String>) -> anyhow::Result<Self> {
+        let dsn = Url::parse(dsn).context("SENTRY_DSN is not a URL")?;
+        let key = dsn.username();
+        if key.is_empty() {
+            return Err(anyhow!("SENTRY_DSN has no public key"));
+        }
+        let path = dsn.path().trim_end_matches('/');
+        let (prefix, project_id) = path
+            .rsplit_once('/')
+            .filter(|(_, project_id)| !project_id.is_empty())
+            .ok_or_else(|| anyhow!("SENTRY_DSN has no project id"))?;
+
+        let mut store_url = dsn.clone();
+        store_url
+            .set_username("")
+            .and_then(|_| store_url.set_password(None))
+            .map_err(|_| anyhow!("SENTRY_DSN has an invalid host"))?;
+        store_url.set_path(&format!("{prefix}/api/{project_id}/store/"));
+
+        Ok(Self {
+            client: Client::new(),
+            store_url,
+            auth_header: format!(
+                "Sentry sentry_version=7, sentry_client=markethub/{}, sentry_key={key}",
+                env!("CARGO_PKG_VERSION")
+            ),
+            environment,
+        })
+    }
+
+    fn event(&self, report: &ErrorReport) -> Value {
+        // Sentry lists exceptions innermost cause first.
+        let exceptions: Vec<Value> = report
+            .chain
+            .iter()
+            .rev()
+            .map(|message| json!({ "type": report.code, "value": message }))
+            .collect();
+        json!({
+            "event_id": Uuid::new_v4().simple().to_string(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "platform": "other",
+            "level": "error",
+            "logger": "markethub",
+            "release": concat!("markethub@", env!("CARGO_PKG_VERSION")),
+            "environment": self.environment,
+            "exception": { "values": exceptions },
+            "tags": { "error_code": report.code, "request_id": report.request_id },
+            "user": report.user_id.map(|id| json!({ "id": id })),
+        })
+    }
+}
+
+impl ErrorReporter for SentryReporter {
+    fn report(&self, report: ErrorReport) {
+        let request = self
+            .client
+            .post(self.store_url.clone())
+            .header("X-Sentry-Auth", &self.auth_header)
+            .json(&self.event(&report));
+        tokio::spawn(async move {
+            match request.send().await.and_then(|res| res.error_for_status()) {
+                Ok(_) => {}
+                Err(err) => tracing::warn!("Sending error report to Sentry failed: {}", err),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dsn_gives_store_url_and_key() {
+        let reporter =
+            SentryReporter::from_dsn("https://abc123@o7.ingest.sentry.io/42", None).unwrap();
+        assert_eq!(
+            reporter.store_url.as_str(),
+            "https://o7.ingest.sentry.io/api/42/store/"
+        );
+        assert!(reporter.auth_header.ends_with("sentry_key=abc123"));
+
+        let reporter =
+            SentryReporter::from_dsn("http://key@localhost:9000/sentry/3", None).unwrap();
+        assert_eq!(
+            reporter.store_url.as_str(),
+            "http://localhost:9000/sentry/api/3/store/"
+        );
+
+        assert!(SentryReporter::from_dsn("https://o7.ingest.sentry.io/42", None).is_err());
+        assert!(SentryReporter::from_dsn("https://key@o7.ingest.sentry.io/", None).is_err());
+    }
+
+    #[test]
+    fn events_carry_request_and_user() {
+        let reporter =
+            SentryReporter::from_dsn("https://abc123@o7.ingest.sentry.io/42", Some("prod".into()))
+                .unwrap();
+        let user_id = Uuid::new_v4();
+        let event = reporter.event(&ErrorReport {
+            code: "DATABASE_ERROR".into(),
+            chain: vec!["Database error: timed out".into(), "timed out".into()],
+            request_id: Some("req-1".into()),
+            user_id: Some(user_id),
+        });
+
+        assert_eq!(event["environment"], "prod");
+        assert_eq!(event["tags"]["request_id"], "req-1");
+        assert_eq!(event["user"]["id"], json!(user_id));
+        assert_eq!(event["exception"]["values"][0]["value"], "timed out");
+        assert_eq!(event["exception"]["values"][1]["type"], "DATABASE_ERROR");
+    }
+}
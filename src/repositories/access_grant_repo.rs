@@ -1,18 +1,40 @@
+use chrono::{DateTime, Utc};
+
 use crate::{
+    cache::CacheHandle,
     error::Result,
-    models::store::{AccessLevel, StoreAccessGrant},
+    models::store::{AccessLevel, GrantStatus, StoreAccessGrant},
 };
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct AccessGrantRepository {
     pool: PgPool,
+    cache: Option<CacheHandle>,
 }
 
 impl AccessGrantRepository {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self { pool, cache: None }
+    }
+
+    /// Drops the store's cached permissions in `cache` after access grant changes.
+    pub fn with_cache(mut self, cache: CacheHandle) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Drops everyone's cached permissions in the store, for callers that
+    /// write inside their own transaction, once it has committed.
+    pub async fn invalidate_permissions(&self, store_id: Uuid) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_permissions(store_id).await;
+        }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
     }
 
     pub async fn grant(
@@ -24,9 +46,17 @@ impl AccessGrantRepository {
     ) -> Result<StoreAccessGrant> {
         let grant = sqlx::query_as::<_, StoreAccessGrant>(
             r#"
-            INSERT INTO store_access_grants (store_id, user_id, granted_by, access_level)
-            VALUES ($1, $2, $3, $4)
-            RETURNING *
+            WITH granted AS (
+                INSERT INTO store_access_grants (store_id, user_id, granted_by, access_level)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, after)
+                SELECT store_id, granted_by, 'AccessGranted', user_id, id, to_jsonb(granted)
+                FROM granted
+            )
+            SELECT * FROM granted
             "#,
         )
         .bind(store_id)
@@ -36,6 +66,7 @@ impl AccessGrantRepository {
         .fetch_one(&self.pool)
         .await?;
 
+        self.invalidate_permissions(store_id).await;
         Ok(grant)
     }
 
@@ -61,23 +92,209 @@ impl AccessGrantRepository {
         Ok(grant)
     }
 
-    pub async fn revoke(&self, store_id: Uuid, user_id: Uuid) -> Result<Option<StoreAccessGrant>> {
+    pub async fn find_active_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<StoreAccessGrant>> {
         let grant = sqlx::query_as::<_, StoreAccessGrant>(
             r#"
-            UPDATE store_access_grants
-            SET is_revoked = true,
-                revoked_at = NOW()
+            SELECT * FROM store_access_grants
             WHERE store_id = $1
               AND user_id = $2
               AND is_revoked = false
-            RETURNING *
+              AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(grant)
+    }
+
+    /// Grants access until `expires_at`, or indefinitely, replacing the
+    /// user's live or expired grant if they have one. The holder's sessions
+    /// are invalidated when an existing grant's access level changes.
+    pub async fn grant_or_renew_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        user_id: Uuid,
+        granted_by: Uuid,
+        access_level: AccessLevel,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<StoreAccessGrant> {
+        let grant = sqlx::query_as::<_, StoreAccessGrant>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_access_grants
+                WHERE store_id = $1 AND user_id = $2 AND is_revoked = false
+            ), granted AS (
+                INSERT INTO store_access_grants
+                    (store_id, user_id, granted_by, access_level, expires_at)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (store_id, user_id) WHERE is_revoked = false
+                DO UPDATE SET granted_by = EXCLUDED.granted_by,
+                              access_level = EXCLUDED.access_level,
+                              granted_at = NOW(),
+                              expires_at = EXCLUDED.expires_at
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id = $2
+                  AND EXISTS (SELECT 1 FROM previous WHERE access_level <> $4)
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, before, after)
+                SELECT g.store_id, g.granted_by,
+                       CASE WHEN p.id IS NULL THEN 'AccessGranted'
+                            ELSE 'AccessUpdated' END::permission_audit_action,
+                       g.user_id, g.id, to_jsonb(p), to_jsonb(g)
+                FROM granted g LEFT JOIN previous p ON p.id = g.id
+            )
+            SELECT * FROM granted
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(granted_by)
+        .bind(access_level)
+        .bind(expires_at)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(grant)
+    }
+
+    /// The store's grants, newest first, optionally only those in `status`.
+    pub async fn list_for_store(
+        &self,
+        store_id: Uuid,
+        status: Option<GrantStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<StoreAccessGrant>> {
+        let status = status.map(|status| match status {
+            GrantStatus::Active => "active",
+            GrantStatus::Expired => "expired",
+            GrantStatus::Revoked => "revoked",
+        });
+        let grants = sqlx::query_as::<_, StoreAccessGrant>(
+            r#"
+            SELECT * FROM store_access_grants
+            WHERE store_id = $1
+              AND CASE $2::text
+                  WHEN 'active' THEN
+                      is_revoked = false AND (expires_at IS NULL OR expires_at > NOW())
+                  WHEN 'expired' THEN is_revoked = false AND expires_at <= NOW()
+                  WHEN 'revoked' THEN is_revoked = true
+                  ELSE true
+              END
+            ORDER BY granted_at DESC, id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(store_id)
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(grants)
+    }
+
+    /// Changes a grant that was not revoked on behalf of `actor_id`. The
+    /// holder's sessions are invalidated when their access level changes.
+    pub async fn update(
+        &self,
+        store_id: Uuid,
+        grant_id: Uuid,
+        access_level: Option<AccessLevel>,
+        expires_at: Option<DateTime<Utc>>,
+        actor_id: Uuid,
+    ) -> Result<Option<StoreAccessGrant>> {
+        let grant = sqlx::query_as::<_, StoreAccessGrant>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_access_grants
+                WHERE id = $1 AND store_id = $2 AND is_revoked = false
+            ), updated AS (
+                UPDATE store_access_grants
+                SET access_level = COALESCE($3, access_level),
+                    expires_at = COALESCE($4, expires_at)
+                WHERE id = $1 AND store_id = $2 AND is_revoked = false
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id IN (SELECT user_id FROM updated)
+                  AND EXISTS (SELECT 1 FROM previous WHERE access_level <> $3)
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, before, after)
+                SELECT u.store_id, $5, 'AccessUpdated', u.user_id, u.id,
+                       to_jsonb(p), to_jsonb(u)
+                FROM updated u JOIN previous p ON p.id = u.id
+            )
+            SELECT * FROM updated
+            "#,
+        )
+        .bind(grant_id)
+        .bind(store_id)
+        .bind(access_level)
+        .bind(expires_at)
+        .bind(actor_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        self.invalidate_permissions(store_id).await;
+        Ok(grant)
+    }
+
+    /// Revokes the user's grant on behalf of `actor_id` and invalidates
+    /// their sessions.
+    pub async fn revoke(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+        actor_id: Uuid,
+    ) -> Result<Option<StoreAccessGrant>> {
+        let grant = sqlx::query_as::<_, StoreAccessGrant>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_access_grants
+                WHERE store_id = $1 AND user_id = $2 AND is_revoked = false
+            ), revoked AS (
+                UPDATE store_access_grants
+                SET is_revoked = true,
+                    revoked_at = NOW()
+                WHERE store_id = $1
+                  AND user_id = $2
+                  AND is_revoked = false
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id IN (SELECT user_id FROM revoked)
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, before, after)
+                SELECT r.store_id, $3, 'AccessRevoked', r.user_id, r.id,
+                       to_jsonb(p), to_jsonb(r)
+                FROM revoked r JOIN previous p ON p.id = r.id
+            )
+            SELECT * FROM revoked
             "#,
         )
         .bind(store_id)
         .bind(user_id)
+        .bind(actor_id)
         .fetch_optional(&self.pool)
         .await?;
 
+        self.invalidate_permissions(store_id).await;
         Ok(grant)
     }
 }
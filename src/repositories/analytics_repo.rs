@@ -5,7 +5,14 @@ use uuid::Uuid;
 
 use crate::{
     error::Result,
-    models::store::{StoreAnalyticsSummary, StoreSalesPoint, StoreTopProduct},
+    models::{
+        event::StoreFunnel,
+        inventory::ProductInventoryStats,
+        order::PaymentStatus,
+        store::{
+            StoreAnalyticsSummary, StoreSalesPoint, StoreTopProduct, TaxReportPeriod, TaxReportRow,
+        },
+    },
 };
 
 #[derive(Clone)]
@@ -18,25 +25,75 @@ impl AnalyticsRepository {
         Self { pool }
     }
 
+    /// Totals of the orders placed between `since` and `until` whose group
+    /// has `payment_status`, with the paid and pending totals alongside.
+    /// Whole days up to the last rollup come from the daily rollups; the
+    /// partial first and last days and everything after the rollup are read
+    /// live.
     pub async fn store_summary(
         &self,
         store_id: Uuid,
         since: DateTime<Utc>,
+        until: DateTime<Utc>,
         timeframe_days: i64,
+        payment_status: PaymentStatus,
     ) -> Result<StoreAnalyticsSummary> {
         let row = sqlx::query_as::<_, StoreSummaryRow>(
             r#"
+            WITH bounds AS (
+                SELECT
+                    $2::date + 1 AS first_day,
+                    LEAST(
+                        COALESCE((SELECT rolled_up_through FROM analytics_rollup_state), $2::date),
+                        $4::date - 1
+                    ) AS last_day
+            ),
+            sales AS (
+                SELECT s.payment_status, s.order_count, s.revenue
+                FROM store_daily_sales s, bounds b
+                WHERE s.store_id = $1 AND s.day BETWEEN b.first_day AND b.last_day
+                UNION ALL
+                SELECT g.payment_status, 1, o.total_amount
+                FROM orders o
+                JOIN order_groups g ON g.id = o.order_group_id
+                CROSS JOIN bounds b
+                WHERE o.store_id = $1 AND o.created_at >= $2 AND o.created_at < $4
+                  AND o.created_at::date NOT BETWEEN b.first_day AND b.last_day
+            ),
+            customers AS (
+                SELECT c.user_id
+                FROM store_daily_customers c, bounds b
+                WHERE c.store_id = $1 AND c.payment_status = $3
+                  AND c.day BETWEEN b.first_day AND b.last_day
+                UNION
+                SELECT o.user_id
+                FROM orders o
+                JOIN order_groups g ON g.id = o.order_group_id
+                CROSS JOIN bounds b
+                WHERE o.store_id = $1 AND o.created_at >= $2 AND o.created_at < $4
+                  AND g.payment_status = $3
+                  AND o.created_at::date NOT BETWEEN b.first_day AND b.last_day
+            )
             SELECT
-                COUNT(*)::bigint AS total_orders,
-                COALESCE(SUM(total_amount), 0) AS total_revenue,
-                COALESCE(AVG(total_amount), 0) AS average_order_value,
-                COUNT(DISTINCT user_id)::bigint AS unique_customers
-            FROM orders
-            WHERE store_id = $1 AND created_at >= $2
+                COALESCE(SUM(order_count) FILTER (WHERE payment_status = $3), 0)::bigint AS total_orders,
+                COALESCE(SUM(revenue) FILTER (WHERE payment_status = $3), 0) AS total_revenue,
+                COALESCE(
+                    SUM(revenue) FILTER (WHERE payment_status = $3)
+                        / NULLIF(SUM(order_count) FILTER (WHERE payment_status = $3), 0),
+                    0
+                ) AS average_order_value,
+                (SELECT COUNT(*) FROM customers)::bigint AS unique_customers,
+                COALESCE(SUM(order_count) FILTER (WHERE payment_status = 'Paid'), 0)::bigint AS paid_orders,
+                COALESCE(SUM(revenue) FILTER (WHERE payment_status = 'Paid'), 0) AS paid_revenue,
+                COALESCE(SUM(order_count) FILTER (WHERE payment_status = 'Pending'), 0)::bigint AS pending_orders,
+                COALESCE(SUM(revenue) FILTER (WHERE payment_status = 'Pending'), 0) AS pending_revenue
+            FROM sales
             "#,
         )
         .bind(store_id)
         .bind(since)
+        .bind(payment_status)
+        .bind(until)
         .fetch_one(&self.pool)
         .await?;
 
@@ -46,28 +103,91 @@ impl AnalyticsRepository {
             average_order_value: row.average_order_value,
             unique_customers: row.unique_customers,
             timeframe_days,
+            paid_orders: row.paid_orders,
+            paid_revenue: row.paid_revenue,
+            pending_orders: row.pending_orders,
+            pending_revenue: row.pending_revenue,
         })
     }
 
+    /// Distinct sessions reaching each storefront step since `since`, and
+    /// the store's orders paid for since then.
+    pub async fn store_funnel(
+        &self,
+        store_id: Uuid,
+        since: DateTime<Utc>,
+        timeframe_days: i64,
+    ) -> Result<StoreFunnel> {
+        let row = sqlx::query_as::<_, StoreFunnelRow>(
+            r#"
+            SELECT
+                COUNT(DISTINCT session_id) FILTER (WHERE event_type = 'ProductView')::bigint AS viewed,
+                COUNT(DISTINCT session_id) FILTER (WHERE event_type = 'AddToCart')::bigint AS cart,
+                COUNT(DISTINCT session_id) FILTER (WHERE event_type = 'BeginCheckout')::bigint AS checkout,
+                (
+                    SELECT COUNT(*) FROM orders o
+                    JOIN order_groups g ON g.id = o.order_group_id
+                    WHERE o.store_id = $1 AND o.created_at >= $2
+                      AND o.status <> 'Cancelled' AND g.payment_status = 'Paid'
+                )::bigint AS paid
+            FROM client_events
+            WHERE store_id = $1 AND occurred_at >= $2
+            "#,
+        )
+        .bind(store_id)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(StoreFunnel::new(
+            timeframe_days,
+            row.viewed,
+            row.cart,
+            row.checkout,
+            row.paid,
+        ))
+    }
+
+    /// Orders and revenue per day since `since`, from the rollups and live
+    /// orders like [`Self::store_summary`].
     pub async fn store_sales_trend(
         &self,
         store_id: Uuid,
         since: DateTime<Utc>,
+        payment_status: PaymentStatus,
     ) -> Result<Vec<StoreSalesPoint>> {
         let rows = sqlx::query_as::<_, StoreSalesRow>(
             r#"
+            WITH bounds AS (
+                SELECT
+                    $2::date + 1 AS first_day,
+                    COALESCE((SELECT rolled_up_through FROM analytics_rollup_state), $2::date) AS last_day
+            ),
+            sales AS (
+                SELECT s.day, s.order_count, s.revenue
+                FROM store_daily_sales s, bounds b
+                WHERE s.store_id = $1 AND s.payment_status = $3
+                  AND s.day BETWEEN b.first_day AND b.last_day
+                UNION ALL
+                SELECT o.created_at::date, 1, o.total_amount
+                FROM orders o
+                JOIN order_groups g ON g.id = o.order_group_id
+                CROSS JOIN bounds b
+                WHERE o.store_id = $1 AND o.created_at >= $2 AND g.payment_status = $3
+                  AND o.created_at::date NOT BETWEEN b.first_day AND b.last_day
+            )
             SELECT
-                DATE_TRUNC('day', created_at)::date AS bucket,
-                COUNT(*)::bigint AS order_count,
-                COALESCE(SUM(total_amount), 0) AS total_revenue
-            FROM orders
-            WHERE store_id = $1 AND created_at >= $2
+                day AS bucket,
+                SUM(order_count)::bigint AS order_count,
+                COALESCE(SUM(revenue), 0) AS total_revenue
+            FROM sales
             GROUP BY bucket
             ORDER BY bucket ASC
             "#,
         )
         .bind(store_id)
         .bind(since)
+        .bind(payment_status)
         .fetch_all(&self.pool)
         .await?;
 
@@ -81,24 +201,180 @@ impl AnalyticsRepository {
             .collect())
     }
 
+    /// Rebuilds the daily rollups from the order history, through
+    /// yesterday. Days after the previous rollup are built, and the last
+    /// `lookback_days` are rebuilt so late payment status changes are picked
+    /// up. The first run builds every day. Returns the store-days written.
+    pub async fn refresh_rollups(&self, lookback_days: i64) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        let (from, through) = sqlx::query_as::<_, (NaiveDate, NaiveDate)>(
+            r#"
+            SELECT
+                CASE
+                    WHEN rolled_up_through IS NULL
+                        THEN COALESCE((SELECT MIN(created_at)::date FROM orders), CURRENT_DATE)
+                    ELSE LEAST(rolled_up_through + 1, CURRENT_DATE - $1::int)
+                END,
+                CURRENT_DATE - 1
+            FROM analytics_rollup_state
+            FOR UPDATE
+            "#,
+        )
+        .bind(lookback_days as i32)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            WITH customers AS (
+                DELETE FROM store_daily_customers WHERE day BETWEEN $1 AND $2
+            ),
+            products AS (
+                DELETE FROM store_daily_product_sales WHERE day BETWEEN $1 AND $2
+            )
+            DELETE FROM store_daily_sales WHERE day BETWEEN $1 AND $2
+            "#,
+        )
+        .bind(from)
+        .bind(through)
+        .execute(&mut *tx)
+        .await?;
+
+        let written = sqlx::query(
+            r#"
+            INSERT INTO store_daily_sales (store_id, day, payment_status, order_count, revenue)
+            SELECT o.store_id, o.created_at::date, g.payment_status, COUNT(*), SUM(o.total_amount)
+            FROM orders o
+            JOIN order_groups g ON g.id = o.order_group_id
+            WHERE o.created_at::date BETWEEN $1 AND $2
+            GROUP BY 1, 2, 3
+            "#,
+        )
+        .bind(from)
+        .bind(through)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        sqlx::query(
+            r#"
+            INSERT INTO store_daily_customers (store_id, day, payment_status, user_id)
+            SELECT DISTINCT o.store_id, o.created_at::date, g.payment_status, o.user_id
+            FROM orders o
+            JOIN order_groups g ON g.id = o.order_group_id
+            WHERE o.created_at::date BETWEEN $1 AND $2
+            "#,
+        )
+        .bind(from)
+        .bind(through)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO store_daily_product_sales
+                (store_id, day, payment_status, product_id, units_sold, revenue)
+            SELECT
+                o.store_id, o.created_at::date, g.payment_status, oi.product_id,
+                SUM(oi.quantity), SUM(oi.subtotal)
+            FROM order_items oi
+            JOIN orders o ON o.id = oi.order_id
+            JOIN order_groups g ON g.id = o.order_group_id
+            WHERE o.created_at::date BETWEEN $1 AND $2
+            GROUP BY 1, 2, 3, 4
+            "#,
+        )
+        .bind(from)
+        .bind(through)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("UPDATE analytics_rollup_state SET rolled_up_through = $1")
+            .bind(through)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(written)
+    }
+
+    /// Tax collected on paid, non-cancelled orders placed between `from` and
+    /// `until` (exclusive), grouped by period, jurisdiction and rate.
+    pub async fn tax_summary(
+        &self,
+        store_id: Uuid,
+        period: TaxReportPeriod,
+        from: NaiveDate,
+        until: NaiveDate,
+    ) -> Result<Vec<TaxReportRow>> {
+        let rows = sqlx::query_as::<_, TaxReportRow>(
+            r#"
+            SELECT
+                DATE_TRUNC($2, o.created_at AT TIME ZONE 'UTC')::date AS period_start,
+                t.jurisdiction,
+                t.rate,
+                COUNT(DISTINCT o.id)::bigint AS order_count,
+                COALESCE(SUM(t.taxable_amount), 0) AS taxable_amount,
+                COALESCE(SUM(t.tax_amount), 0) AS tax_collected
+            FROM order_tax_lines t
+            JOIN orders o ON o.id = t.order_id
+            JOIN order_groups g ON g.id = o.order_group_id
+            WHERE t.store_id = $1
+              AND o.status <> 'Cancelled'
+              AND g.payment_status = 'Paid'
+              AND o.created_at >= $3::date AT TIME ZONE 'UTC'
+              AND o.created_at < $4::date AT TIME ZONE 'UTC'
+            GROUP BY period_start, t.jurisdiction, t.rate
+            ORDER BY period_start ASC, t.jurisdiction ASC, t.rate ASC
+            "#,
+        )
+        .bind(store_id)
+        .bind(period.trunc_field())
+        .bind(from)
+        .bind(until)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows)
+    }
+
     pub async fn store_top_products(
         &self,
         store_id: Uuid,
         since: DateTime<Utc>,
         limit: i64,
+        payment_status: PaymentStatus,
     ) -> Result<Vec<StoreTopProduct>> {
         let rows = sqlx::query_as::<_, StoreTopProductRow>(
             r#"
+            WITH bounds AS (
+                SELECT
+                    $2::date + 1 AS first_day,
+                    COALESCE((SELECT rolled_up_through FROM analytics_rollup_state), $2::date) AS last_day
+            ),
+            sales AS (
+                SELECT ps.product_id, ps.units_sold, ps.revenue
+                FROM store_daily_product_sales ps, bounds b
+                WHERE ps.store_id = $1 AND ps.payment_status = $4
+                  AND ps.day BETWEEN b.first_day AND b.last_day
+                UNION ALL
+                SELECT oi.product_id, oi.quantity, oi.subtotal
+                FROM order_items oi
+                INNER JOIN orders o ON oi.order_id = o.id
+                INNER JOIN order_groups g ON o.order_group_id = g.id
+                CROSS JOIN bounds b
+                WHERE o.store_id = $1 AND o.created_at >= $2 AND g.payment_status = $4
+                  AND o.created_at::date NOT BETWEEN b.first_day AND b.last_day
+            )
             SELECT
-                oi.product_id,
+                s.product_id,
                 p.name AS product_name,
-                SUM(oi.quantity)::bigint AS units_sold,
-                COALESCE(SUM(oi.subtotal), 0) AS revenue
-            FROM order_items oi
-            INNER JOIN orders o ON oi.order_id = o.id
-            INNER JOIN products p ON oi.product_id = p.id
-            WHERE o.store_id = $1 AND o.created_at >= $2
-            GROUP BY oi.product_id, p.name
+                SUM(s.units_sold)::bigint AS units_sold,
+                COALESCE(SUM(s.revenue), 0) AS revenue
+            FROM sales s
+            INNER JOIN products p ON s.product_id = p.id
+            GROUP BY s.product_id, p.name
             ORDER BY units_sold DESC
             LIMIT $3
             "#,
@@ -106,6 +382,7 @@ impl AnalyticsRepository {
         .bind(store_id)
         .bind(since)
         .bind(limit)
+        .bind(payment_status)
         .fetch_all(&self.pool)
         .await?;
 
@@ -119,6 +396,83 @@ impl AnalyticsRepository {
             })
             .collect())
     }
+
+    /// Stock KPIs for each of the store's live products since `since`,
+    /// derived from the inventory ledger. A day counts as a stockout when
+    /// the product ended it with no stock; stock at the end of a past day is
+    /// the current stock less the movements recorded after it.
+    pub async fn store_inventory(
+        &self,
+        store_id: Uuid,
+        since: DateTime<Utc>,
+        timeframe_days: i64,
+    ) -> Result<Vec<ProductInventoryStats>> {
+        let rows = sqlx::query_as::<_, StoreInventoryRow>(
+            r#"
+            WITH live AS (
+                SELECT id, sku, name, stock_quantity, created_at FROM products
+                WHERE store_id = $1 AND archived_at IS NULL
+            ), days AS (
+                SELECT generate_series(
+                    DATE_TRUNC('day', $2::timestamptz), DATE_TRUNC('day', NOW()), INTERVAL '1 day'
+                ) AS day
+            ), moves AS (
+                SELECT m.product_id,
+                       -COALESCE(SUM(m.delta) FILTER (
+                           WHERE m.kind IN ('Sale', 'CancellationRestore')
+                       ), 0) AS units_sold,
+                       COALESCE(SUM(m.delta) FILTER (WHERE m.kind = 'Restock'), 0) AS units_received,
+                       SUM(m.delta) AS net_change
+                FROM inventory_movements m
+                JOIN live p ON p.id = m.product_id
+                WHERE m.created_at >= $2
+                GROUP BY m.product_id
+            ), stockouts AS (
+                SELECT p.id AS product_id, COUNT(*) AS stockout_days
+                FROM live p
+                JOIN days d ON d.day >= DATE_TRUNC('day', p.created_at)
+                WHERE p.stock_quantity - COALESCE((
+                    SELECT SUM(m.delta) FROM inventory_movements m
+                    WHERE m.product_id = p.id AND m.created_at >= d.day + INTERVAL '1 day'
+                ), 0) <= 0
+                GROUP BY p.id
+            )
+            SELECT
+                p.id AS product_id,
+                p.sku,
+                p.name,
+                p.stock_quantity,
+                (p.stock_quantity - COALESCE(mv.net_change, 0))::bigint AS opening_stock,
+                COALESCE(mv.units_sold, 0)::bigint AS units_sold,
+                COALESCE(mv.units_received, 0)::bigint AS units_received,
+                COALESCE(s.stockout_days, 0)::bigint AS stockout_days
+            FROM live p
+            LEFT JOIN moves mv ON mv.product_id = p.id
+            LEFT JOIN stockouts s ON s.product_id = p.id
+            "#,
+        )
+        .bind(store_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                ProductInventoryStats::new(
+                    row.product_id,
+                    row.sku,
+                    row.name,
+                    row.stock_quantity,
+                    row.opening_stock,
+                    row.units_sold,
+                    row.units_received,
+                    row.stockout_days,
+                    timeframe_days,
+                )
+            })
+            .collect())
+    }
 }
 
 #[derive(sqlx::FromRow)]
@@ -127,6 +481,10 @@ struct StoreSummaryRow {
     total_revenue: Decimal,
     average_order_value: Decimal,
     unique_customers: i64,
+    paid_orders: i64,
+    paid_revenue: Decimal,
+    pending_orders: i64,
+    pending_revenue: Decimal,
 }
 
 #[derive(sqlx::FromRow)]
@@ -143,3 +501,23 @@ struct StoreTopProductRow {
     units_sold: i64,
     revenue: Decimal,
 }
+
+#[derive(sqlx::FromRow)]
+struct StoreFunnelRow {
+    viewed: i64,
+    cart: i64,
+    checkout: i64,
+    paid: i64,
+}
+
+#[derive(sqlx::FromRow)]
+struct StoreInventoryRow {
+    product_id: Uuid,
+    sku: String,
+    name: String,
+    stock_quantity: i32,
+    opening_stock: i64,
+    units_sold: i64,
+    units_received: i64,
+    stockout_days: i64,
+}
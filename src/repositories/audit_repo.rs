@@ -0,0 +1,87 @@
+use std::net::IpAddr;
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::audit::{AuditEvent, AuditEventFilter, NewAuditEvent},
+};
+
+#[derive(Clone)]
+pub struct AuditRepository {
+    pool: PgPool,
+}
+
+impl AuditRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn insert(
+        &self,
+        event: &NewAuditEvent,
+        ip_address: Option<IpAddr>,
+        request_id: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_events
+                (store_id, actor_id, action, entity_type, entity_id, diff, ip_address, request_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7::inet, $8)
+            "#,
+        )
+        .bind(event.store_id)
+        .bind(event.actor_id)
+        .bind(event.action)
+        .bind(event.entity_type)
+        .bind(event.entity_id)
+        .bind(&event.diff)
+        .bind(ip_address.map(|ip| ip.to_string()))
+        .bind(request_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Events matching `filter`, newest first; only the store's when
+    /// `store_id` is set.
+    pub async fn list(
+        &self,
+        store_id: Option<Uuid>,
+        filter: &AuditEventFilter,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<AuditEvent>> {
+        let events = sqlx::query_as::<_, AuditEvent>(
+            r#"
+            SELECT id, store_id, actor_id, action, entity_type, entity_id, diff,
+                   host(ip_address) AS ip_address, request_id, created_at
+            FROM audit_events
+            WHERE ($1::uuid IS NULL OR store_id = $1)
+              AND ($2::audit_action IS NULL OR action = $2)
+              AND ($3::uuid IS NULL OR actor_id = $3)
+              AND ($4::text IS NULL OR entity_type = $4)
+              AND ($5::uuid IS NULL OR entity_id = $5)
+              AND ($6::timestamptz IS NULL OR created_at >= $6)
+              AND ($7::timestamptz IS NULL OR created_at < $7)
+            ORDER BY created_at DESC, id DESC
+            LIMIT $8 OFFSET $9
+            "#,
+        )
+        .bind(store_id)
+        .bind(filter.action)
+        .bind(filter.actor_id)
+        .bind(filter.entity_type.as_deref())
+        .bind(filter.entity_id)
+        .bind(filter.from)
+        .bind(filter.to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(events)
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
-    error::Result,
-    models::order::{CartItem, CartItemDetail},
+    error::{AppError, Result},
+    models::order::{AbandonedCart, CartItem, CartItemDetail, CartOwner, StockReservation},
 };
-use sqlx::PgPool;
+use chrono::Duration;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -15,29 +16,153 @@ impl CartRepository {
         Self { pool }
     }
 
+    /// Adds to the cart line and reserves stock for its new total quantity,
+    /// failing with a conflict when other buyers' reservations leave too
+    /// little stock. Guest carts only check availability: they hold no
+    /// stock until the guest signs in. A line saved for later moves back
+    /// into the cart, and the line takes the current price as its added
+    /// price.
     pub async fn upsert_item(
         &self,
-        user_id: Uuid,
+        owner: CartOwner,
         product_id: Uuid,
         quantity: i32,
+        reservation_ttl: Duration,
     ) -> Result<CartItem> {
-        let item = sqlx::query_as::<_, CartItem>(
+        let mut tx = self.pool.begin().await?;
+
+        let item = match owner {
+            CartOwner::User(user_id) => {
+                sqlx::query_as::<_, CartItem>(
+                    r#"
+                    INSERT INTO cart_items (user_id, product_id, quantity, added_price)
+                    SELECT $1, $2, $3, price FROM products WHERE id = $2
+                    ON CONFLICT (user_id, product_id)
+                    DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity,
+                                 added_price = EXCLUDED.added_price,
+                                 saved_for_later = false,
+                                 updated_at = NOW()
+                    RETURNING *
+                    "#,
+                )
+                .bind(user_id)
+                .bind(product_id)
+                .bind(quantity)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+            CartOwner::Guest(session_id) => {
+                sqlx::query_as::<_, CartItem>(
+                    r#"
+                    INSERT INTO cart_items (guest_session_id, product_id, quantity, added_price)
+                    SELECT $1, $2, $3, price FROM products WHERE id = $2
+                    ON CONFLICT (guest_session_id, product_id) WHERE guest_session_id IS NOT NULL
+                    DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity,
+                                 added_price = EXCLUDED.added_price,
+                                 saved_for_later = false,
+                                 updated_at = NOW()
+                    RETURNING *
+                    "#,
+                )
+                .bind(session_id)
+                .bind(product_id)
+                .bind(quantity)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        self.hold_in_tx(&mut tx, owner, product_id, item.quantity, reservation_ttl)
+            .await?;
+        tx.commit().await?;
+
+        Ok(item)
+    }
+
+    /// Moves a line out of the cart into the saved list, releasing its
+    /// reservation.
+    pub async fn save_for_later(&self, owner: CartOwner, product_id: Uuid) -> Result<CartItem> {
+        let mut tx = self.pool.begin().await?;
+        let item = self
+            .set_saved_in_tx(&mut tx, owner, product_id, true)
+            .await?;
+        if let CartOwner::User(user_id) = owner {
+            sqlx::query("DELETE FROM stock_reservations WHERE user_id = $1 AND product_id = $2")
+                .bind(user_id)
+                .bind(product_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(item)
+    }
+
+    /// Moves a saved line back into the cart, reserving its stock again. The
+    /// line stays saved when the stock is no longer there.
+    pub async fn restore_saved(
+        &self,
+        owner: CartOwner,
+        product_id: Uuid,
+        reservation_ttl: Duration,
+    ) -> Result<CartItem> {
+        let mut tx = self.pool.begin().await?;
+        let item = self
+            .set_saved_in_tx(&mut tx, owner, product_id, false)
+            .await?;
+        self.hold_in_tx(&mut tx, owner, product_id, item.quantity, reservation_ttl)
+            .await?;
+        tx.commit().await?;
+
+        Ok(item)
+    }
+
+    async fn set_saved_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        owner: CartOwner,
+        product_id: Uuid,
+        saved: bool,
+    ) -> Result<CartItem> {
+        sqlx::query_as::<_, CartItem>(
             r#"
-            INSERT INTO cart_items (user_id, product_id, quantity)
-            VALUES ($1, $2, $3)
-            ON CONFLICT (user_id, product_id)
-            DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity,
-                         updated_at = NOW()
+            UPDATE cart_items SET saved_for_later = $4, updated_at = NOW()
+            WHERE (user_id = $1 OR guest_session_id = $2) AND product_id = $3
             RETURNING *
             "#,
         )
-        .bind(user_id)
+        .bind(owner.user_id())
+        .bind(owner.guest_session_id())
         .bind(product_id)
-        .bind(quantity)
-        .fetch_one(&self.pool)
-        .await?;
+        .bind(saved)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Cart item not found".into()))
+    }
 
-        Ok(item)
+    /// Reserves stock for a user's line; guest lines only check that the
+    /// stock is there.
+    async fn hold_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        owner: CartOwner,
+        product_id: Uuid,
+        quantity: i32,
+        reservation_ttl: Duration,
+    ) -> Result<()> {
+        match owner {
+            CartOwner::User(user_id) => {
+                self.reserve_in_tx(tx, user_id, product_id, quantity, reservation_ttl)
+                    .await?;
+            }
+            CartOwner::Guest(_) => {
+                let available = self.available_in_tx(tx, product_id, None).await?;
+                if available < i64::from(quantity) {
+                    return Err(AppError::Conflict("Insufficient stock".into()));
+                }
+            }
+        }
+        Ok(())
     }
 
     pub async fn update_quantity(
@@ -62,17 +187,35 @@ impl CartRepository {
         Ok(item)
     }
 
-    pub async fn remove_item(&self, user_id: Uuid, product_id: Uuid) -> Result<()> {
-        sqlx::query("DELETE FROM cart_items WHERE user_id = $1 AND product_id = $2")
-            .bind(user_id)
-            .bind(product_id)
-            .execute(&self.pool)
-            .await?;
+    pub async fn remove_item(&self, owner: CartOwner, product_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            WITH released AS (
+                DELETE FROM stock_reservations WHERE user_id = $1 AND product_id = $3
+            )
+            DELETE FROM cart_items
+            WHERE (user_id = $1 OR guest_session_id = $2) AND product_id = $3
+            "#,
+        )
+        .bind(owner.user_id())
+        .bind(owner.guest_session_id())
+        .bind(product_id)
+        .execute(&self.pool)
+        .await?;
 
         Ok(())
     }
 
-    pub async fn list_with_products(&self, user_id: Uuid) -> Result<Vec<CartItemDetail>> {
+    /// Lines in the cart proper, the ones checkout buys.
+    pub async fn list_with_products(&self, owner: CartOwner) -> Result<Vec<CartItemDetail>> {
+        self.list_lines(owner, false).await
+    }
+
+    pub async fn list_saved(&self, owner: CartOwner) -> Result<Vec<CartItemDetail>> {
+        self.list_lines(owner, true).await
+    }
+
+    async fn list_lines(&self, owner: CartOwner, saved: bool) -> Result<Vec<CartItemDetail>> {
         let items = sqlx::query_as::<_, CartItemDetail>(
             r#"
             SELECT
@@ -80,29 +223,334 @@ impl CartRepository {
                 c.product_id,
                 p.store_id,
                 s.name as store_name,
+                s.status as store_status,
                 p.name as product_name,
                 p.price as unit_price,
-                c.quantity
+                c.added_price,
+                c.quantity,
+                p.product_type,
+                p.weight_grams,
+                (p.is_active AND p.archived_at IS NULL) as product_available,
+                p.stock_quantity - COALESCE((
+                    SELECT SUM(o.quantity)
+                    FROM stock_reservations o
+                    WHERE o.product_id = p.id
+                      AND o.user_id IS DISTINCT FROM c.user_id
+                      AND o.expires_at > NOW()
+                ), 0) as available_quantity,
+                c.saved_for_later,
+                r.expires_at as reserved_until
             FROM cart_items c
             JOIN products p ON p.id = c.product_id
             JOIN stores s ON s.id = p.store_id
-            WHERE c.user_id = $1
+            LEFT JOIN stock_reservations r
+                ON r.user_id = c.user_id
+               AND r.product_id = c.product_id
+               AND r.expires_at > NOW()
+            WHERE (c.user_id = $1 OR c.guest_session_id = $2)
+              AND c.saved_for_later = $3
             ORDER BY c.added_at DESC
             "#,
         )
-        .bind(user_id)
+        .bind(owner.user_id())
+        .bind(owner.guest_session_id())
+        .bind(saved)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(items)
     }
 
-    pub async fn clear_user(&self, user_id: Uuid) -> Result<()> {
-        sqlx::query("DELETE FROM cart_items WHERE user_id = $1")
+    /// Empties the cart and releases its reservations, returning the number
+    /// of cart lines removed. Saved-for-later lines are kept.
+    pub async fn clear(&self, owner: CartOwner) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            WITH released AS (
+                DELETE FROM stock_reservations WHERE user_id = $1
+            )
+            DELETE FROM cart_items
+            WHERE (user_id = $1 OR guest_session_id = $2) AND NOT saved_for_later
+            "#,
+        )
+        .bind(owner.user_id())
+        .bind(owner.guest_session_id())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Moves a guest cart into the user's, adding quantities for products
+    /// already there, and reserves stock for the merged lines. Lines whose
+    /// stock is taken stay in the cart unreserved; checkout reports them. A
+    /// product stays saved for later only if both carts saved it.
+    pub async fn merge_guest(
+        &self,
+        guest_session_id: Uuid,
+        user_id: Uuid,
+        reservation_ttl: Duration,
+    ) -> Result<Vec<CartItem>> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut merged = sqlx::query_as::<_, CartItem>(
+            r#"
+            WITH moved AS (
+                DELETE FROM cart_items
+                WHERE guest_session_id = $1
+                RETURNING product_id, quantity, added_price, saved_for_later, added_at
+            )
+            INSERT INTO cart_items (
+                user_id, product_id, quantity, added_price, saved_for_later, added_at
+            )
+            SELECT $2, product_id, quantity, added_price, saved_for_later, added_at FROM moved
+            ON CONFLICT (user_id, product_id)
+            DO UPDATE SET quantity = cart_items.quantity + EXCLUDED.quantity,
+                         saved_for_later = cart_items.saved_for_later
+                                           AND EXCLUDED.saved_for_later,
+                         updated_at = NOW()
+            RETURNING *
+            "#,
+        )
+        .bind(guest_session_id)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        // Lock products in id order, as checkout does, to avoid deadlocks.
+        merged.sort_by_key(|item| item.product_id);
+        for item in merged.iter().filter(|item| !item.saved_for_later) {
+            match self
+                .reserve_in_tx(
+                    &mut tx,
+                    user_id,
+                    item.product_id,
+                    item.quantity,
+                    reservation_ttl,
+                )
+                .await
+            {
+                Ok(_) | Err(AppError::Conflict(_)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        tx.commit().await?;
+
+        Ok(merged)
+    }
+
+    /// Deletes up to `limit` guest cart lines untouched for `max_age`.
+    pub async fn purge_stale_guest_items(&self, max_age: Duration, limit: i64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM cart_items
+            WHERE id IN (
+                SELECT id FROM cart_items
+                WHERE guest_session_id IS NOT NULL
+                  AND updated_at < NOW() - make_interval(secs => $1)
+                LIMIT $2
+            )
+            "#,
+        )
+        .bind(max_age.num_seconds() as f64)
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Expires up to `limit` user carts with no activity for `max_age`:
+    /// their lines and reservations are deleted and one abandoned-cart
+    /// record per user and store is written. Saved-for-later lines are kept.
+    pub async fn expire_idle_carts(
+        &self,
+        max_age: Duration,
+        limit: i64,
+    ) -> Result<Vec<AbandonedCart>> {
+        let mut tx = self.pool.begin().await?;
+
+        let user_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            SELECT user_id FROM cart_items
+            WHERE user_id IS NOT NULL AND NOT saved_for_later
+            GROUP BY user_id
+            HAVING MAX(updated_at) < NOW() - make_interval(secs => $1)
+            LIMIT $2
+            "#,
+        )
+        .bind(max_age.num_seconds() as f64)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+        if user_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        sqlx::query("DELETE FROM stock_reservations WHERE user_id = ANY($1)")
+            .bind(&user_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        let abandoned = sqlx::query_as::<_, AbandonedCart>(
+            r#"
+            WITH removed AS (
+                DELETE FROM cart_items
+                WHERE user_id = ANY($1) AND NOT saved_for_later
+                RETURNING user_id, product_id, quantity, updated_at
+            )
+            INSERT INTO abandoned_carts (user_id, store_id, item_count, cart_value, last_activity_at)
+            SELECT r.user_id, p.store_id, COUNT(*)::INT, SUM(p.price * r.quantity), MAX(r.updated_at)
+            FROM removed r
+            JOIN products p ON p.id = r.product_id
+            GROUP BY r.user_id, p.store_id
+            RETURNING *
+            "#,
+        )
+        .bind(&user_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(abandoned)
+    }
+
+    /// Removes every cart line referencing the store's products and returns
+    /// the owning user of each removed line; guest lines have no one to
+    /// notify.
+    pub async fn remove_store_items_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+    ) -> Result<Vec<Uuid>> {
+        sqlx::query(
+            r#"
+            DELETE FROM stock_reservations r
+            USING products p
+            WHERE r.product_id = p.id AND p.store_id = $1
+            "#,
+        )
+        .bind(store_id)
+        .execute(&mut **tx)
+        .await?;
+
+        let user_ids = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            WITH removed AS (
+                DELETE FROM cart_items ci
+                USING products p
+                WHERE ci.product_id = p.id AND p.store_id = $1
+                RETURNING ci.user_id
+            )
+            SELECT user_id FROM removed WHERE user_id IS NOT NULL
+            "#,
+        )
+        .bind(store_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(user_ids)
+    }
+
+    /// Holds `quantity` units of the product for the user until the TTL runs
+    /// out, replacing any earlier reservation of theirs. The product row stays
+    /// locked for the rest of the transaction so concurrent reservations and
+    /// sales are serialised.
+    pub async fn reserve_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+        product_id: Uuid,
+        quantity: i32,
+        ttl: Duration,
+    ) -> Result<StockReservation> {
+        let available = self.available_in_tx(tx, product_id, Some(user_id)).await?;
+        if available < i64::from(quantity) {
+            return Err(AppError::Conflict("Insufficient stock".into()));
+        }
+
+        let reservation = sqlx::query_as::<_, StockReservation>(
+            r#"
+            INSERT INTO stock_reservations (user_id, product_id, quantity, expires_at)
+            VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+            ON CONFLICT (user_id, product_id)
+            DO UPDATE SET quantity = EXCLUDED.quantity,
+                          expires_at = EXCLUDED.expires_at
+            RETURNING *
+            "#,
+        )
+        .bind(user_id)
+        .bind(product_id)
+        .bind(quantity)
+        .bind(ttl.num_seconds() as f64)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(reservation)
+    }
+
+    /// Stock not held by other buyers' live reservations, locking the product
+    /// row for the rest of the transaction. Reservations of `holder` do not
+    /// count against it.
+    async fn available_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        product_id: Uuid,
+        holder: Option<Uuid>,
+    ) -> Result<i64> {
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT p.stock_quantity - COALESCE((
+                SELECT SUM(r.quantity)
+                FROM stock_reservations r
+                WHERE r.product_id = p.id
+                  AND r.user_id IS DISTINCT FROM $2
+                  AND r.expires_at > NOW()
+            ), 0)
+            FROM products p
+            WHERE p.id = $1
+            FOR UPDATE OF p
+            "#,
+        )
+        .bind(product_id)
+        .bind(holder)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Product not found".into()))
+    }
+
+    /// Drops the user's reservations once checkout has turned them into sales.
+    pub async fn consume_reservations_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+    ) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM stock_reservations WHERE user_id = $1")
             .bind(user_id)
-            .execute(&self.pool)
+            .execute(&mut **tx)
             .await?;
 
-        Ok(())
+        Ok(result.rows_affected())
+    }
+
+    /// Deletes up to `limit` lapsed reservations. Expired rows already stop
+    /// holding stock; this only keeps the table small.
+    pub async fn release_expired(&self, limit: i64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM stock_reservations
+            WHERE id IN (
+                SELECT id FROM stock_reservations
+                WHERE expires_at <= NOW()
+                ORDER BY expires_at
+                LIMIT $1
+            )
+            "#,
+        )
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
     }
 }
@@ -0,0 +1,241 @@
+use crate::{
+    error::{AppError, Result},
+    models::coupon::{Coupon, CouponKind},
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+const STORE_CODE_CONSTRAINT: &str = "coupons_store_id_code_key";
+
+#[derive(Clone)]
+pub struct CouponRepository {
+    pool: PgPool,
+}
+
+impl CouponRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create(
+        &self,
+        store_id: Uuid,
+        code: &str,
+        kind: CouponKind,
+        value: Decimal,
+        min_order_value: Option<Decimal>,
+        usage_limit: Option<i32>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Coupon> {
+        let coupon = sqlx::query_as::<_, Coupon>(
+            r#"
+            INSERT INTO coupons (
+                store_id, code, kind, value, min_order_value, usage_limit, expires_at
+            )
+            VALUES ($1, UPPER($2), $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(code)
+        .bind(kind)
+        .bind(value)
+        .bind(min_order_value)
+        .bind(usage_limit)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_code_conflict)?;
+
+        Ok(coupon)
+    }
+
+    pub async fn list_for_store(&self, store_id: Uuid) -> Result<Vec<Coupon>> {
+        let coupons = sqlx::query_as::<_, Coupon>(
+            "SELECT * FROM coupons WHERE store_id = $1 ORDER BY created_at DESC",
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(coupons)
+    }
+
+    /// Turns the code off and takes it out of every cart it was applied to.
+    /// The row stays so past redemptions keep pointing at it.
+    pub async fn deactivate(&self, store_id: Uuid, coupon_id: Uuid) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            "UPDATE coupons SET is_active = false WHERE id = $1 AND store_id = $2 AND is_active",
+        )
+        .bind(coupon_id)
+        .bind(store_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM cart_coupons WHERE coupon_id = $1")
+            .bind(coupon_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Coupons with `code` (in any case) among the given stores.
+    pub async fn find_by_code(&self, code: &str, store_ids: &[Uuid]) -> Result<Vec<Coupon>> {
+        let coupons = sqlx::query_as::<_, Coupon>(
+            "SELECT * FROM coupons WHERE code = UPPER($1) AND store_id = ANY($2)",
+        )
+        .bind(code)
+        .bind(store_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(coupons)
+    }
+
+    /// Applies the coupon to the user's cart, replacing any other code for
+    /// the same store.
+    pub async fn apply(&self, user_id: Uuid, coupon: &Coupon) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO cart_coupons (user_id, store_id, coupon_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, store_id)
+            DO UPDATE SET coupon_id = EXCLUDED.coupon_id, applied_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(coupon.store_id)
+        .bind(coupon.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_applied(&self, user_id: Uuid, store_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM cart_coupons WHERE user_id = $1 AND store_id = $2")
+            .bind(user_id)
+            .bind(store_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The coupons applied to the user's cart, one per store at most.
+    pub async fn applied_for_user(&self, user_id: Uuid) -> Result<Vec<Coupon>> {
+        let coupons = sqlx::query_as::<_, Coupon>(
+            r#"
+            SELECT c.*
+            FROM cart_coupons cc
+            JOIN coupons c ON c.id = cc.coupon_id
+            WHERE cc.user_id = $1
+            ORDER BY cc.applied_at
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(coupons)
+    }
+
+    /// Counts a use of the coupon and records what it took off the order.
+    /// Returns `false`, recording nothing, if the coupon was deactivated,
+    /// expired or used up since it was applied.
+    pub async fn redeem_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        coupon_id: Uuid,
+        order_id: Uuid,
+        user_id: Uuid,
+        discount_amount: Decimal,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE coupons
+            SET times_used = times_used + 1
+            WHERE id = $1
+              AND is_active
+              AND (expires_at IS NULL OR expires_at > NOW())
+              AND (usage_limit IS NULL OR times_used < usage_limit)
+            "#,
+        )
+        .bind(coupon_id)
+        .execute(&mut **tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO coupon_redemptions (coupon_id, order_id, user_id, discount_amount)
+            VALUES ($1, $2, $3, $4)
+            "#,
+        )
+        .bind(coupon_id)
+        .bind(order_id)
+        .bind(user_id)
+        .bind(discount_amount)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(true)
+    }
+
+    /// Takes back the use a cancelled order made of its coupon, so it
+    /// counts against the usage limit no more.
+    pub async fn release_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            WITH released AS (
+                DELETE FROM coupon_redemptions WHERE order_id = $1
+                RETURNING coupon_id
+            )
+            UPDATE coupons c
+            SET times_used = c.times_used - 1
+            FROM released r
+            WHERE c.id = r.coupon_id
+            "#,
+        )
+        .bind(order_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn clear_applied_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query("DELETE FROM cart_coupons WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Codes are unique per store only; other stores may use the same code.
+fn map_code_conflict(err: sqlx::Error) -> AppError {
+    match &err {
+        sqlx::Error::Database(db) if db.constraint() == Some(STORE_CODE_CONSTRAINT) => {
+            AppError::Conflict("Coupon code already exists in this store".into())
+        }
+        _ => err.into(),
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    error::Result,
+    models::credit::{CreditEntryKind, StoreCreditBalance, StoreCreditEntry},
+};
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct CreditRepository {
+    pool: PgPool,
+}
+
+impl CreditRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Adds credit to the customer's balance and records the ledger entry.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn issue(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+        kind: CreditEntryKind,
+        amount: Decimal,
+        order_id: Option<Uuid>,
+        issued_by: Uuid,
+        note: Option<&str>,
+    ) -> Result<StoreCreditEntry> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO store_credit_balances (store_id, user_id, balance)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (store_id, user_id)
+            DO UPDATE SET balance = store_credit_balances.balance + EXCLUDED.balance
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(amount)
+        .execute(&mut *tx)
+        .await?;
+
+        let entry = insert_entry(
+            &mut tx,
+            store_id,
+            user_id,
+            kind,
+            amount,
+            order_id,
+            Some(issued_by),
+            note,
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(entry)
+    }
+
+    /// Locks the customer's balance for the rest of the checkout transaction.
+    pub async fn lock_balance_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Decimal> {
+        let balance = sqlx::query_scalar::<_, Decimal>(
+            r#"
+            SELECT balance FROM store_credit_balances
+            WHERE store_id = $1 AND user_id = $2
+            FOR UPDATE
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(balance.unwrap_or(Decimal::ZERO))
+    }
+
+    pub async fn redeem_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        user_id: Uuid,
+        amount: Decimal,
+        order_id: Uuid,
+    ) -> Result<StoreCreditEntry> {
+        sqlx::query(
+            r#"
+            UPDATE store_credit_balances SET balance = balance - $3
+            WHERE store_id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(amount)
+        .execute(&mut **tx)
+        .await?;
+
+        insert_entry(
+            tx,
+            store_id,
+            user_id,
+            CreditEntryKind::Redemption,
+            -amount,
+            Some(order_id),
+            None,
+            None,
+        )
+        .await
+    }
+
+    /// Gives back credit redeemed for an order that was cancelled.
+    pub async fn restore_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        user_id: Uuid,
+        amount: Decimal,
+        order_id: Uuid,
+    ) -> Result<StoreCreditEntry> {
+        sqlx::query(
+            r#"
+            INSERT INTO store_credit_balances (store_id, user_id, balance)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (store_id, user_id)
+            DO UPDATE SET balance = store_credit_balances.balance + EXCLUDED.balance
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(amount)
+        .execute(&mut **tx)
+        .await?;
+
+        insert_entry(
+            tx,
+            store_id,
+            user_id,
+            CreditEntryKind::Refund,
+            amount,
+            Some(order_id),
+            None,
+            Some("Order cancelled"),
+        )
+        .await
+    }
+
+    pub async fn balances_for_user(&self, user_id: Uuid) -> Result<Vec<StoreCreditBalance>> {
+        let balances = sqlx::query_as::<_, StoreCreditBalance>(
+            r#"
+            SELECT * FROM store_credit_balances
+            WHERE user_id = $1 AND balance > 0
+            ORDER BY updated_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(balances)
+    }
+
+    pub async fn list_entries(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<StoreCreditEntry>> {
+        let entries = sqlx::query_as::<_, StoreCreditEntry>(
+            r#"
+            SELECT * FROM store_credit_entries
+            WHERE store_id = $1 AND user_id = $2
+            ORDER BY created_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn insert_entry(
+    tx: &mut Transaction<'_, Postgres>,
+    store_id: Uuid,
+    user_id: Uuid,
+    kind: CreditEntryKind,
+    amount: Decimal,
+    order_id: Option<Uuid>,
+    issued_by: Option<Uuid>,
+    note: Option<&str>,
+) -> Result<StoreCreditEntry> {
+    let entry = sqlx::query_as::<_, StoreCreditEntry>(
+        r#"
+        INSERT INTO store_credit_entries (
+            store_id, user_id, kind, amount, order_id, issued_by, note
+        ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING *
+        "#,
+    )
+    .bind(store_id)
+    .bind(user_id)
+    .bind(kind)
+    .bind(amount)
+    .bind(order_id)
+    .bind(issued_by)
+    .bind(note)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(entry)
+}
@@ -0,0 +1,126 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::{email::Email, notification::DeliveryStatus},
+};
+
+#[derive(Clone)]
+pub struct EmailRepository {
+    pool: PgPool,
+}
+
+impl EmailRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Records a rendered email. Returns `None` when an email with the same
+    /// `dedupe_key` was already recorded.
+    pub async fn create_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        template: &str,
+        recipient: &str,
+        subject: &str,
+        body: &str,
+        dedupe_key: Option<&str>,
+    ) -> Result<Option<Email>> {
+        let email = sqlx::query_as::<_, Email>(
+            r#"
+            INSERT INTO emails (template, recipient, subject, body, dedupe_key)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (dedupe_key) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(template)
+        .bind(recipient)
+        .bind(subject)
+        .bind(body)
+        .bind(dedupe_key)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(email)
+    }
+
+    pub async fn find(&self, id: Uuid) -> Result<Option<Email>> {
+        let email = sqlx::query_as::<_, Email>("SELECT * FROM emails WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(email)
+    }
+
+    /// Emails newest first, optionally only those with `status`.
+    pub async fn list(
+        &self,
+        status: Option<DeliveryStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Email>> {
+        let emails = sqlx::query_as::<_, Email>(
+            r#"
+            SELECT * FROM emails
+            WHERE ($1::delivery_status IS NULL OR status = $1)
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(emails)
+    }
+
+    pub async fn mark_sent(&self, id: Uuid, provider_message_id: Option<&str>) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE emails
+            SET status = 'Sent',
+                attempts = attempts + 1,
+                provider_message_id = $2,
+                last_error = NULL,
+                sent_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(provider_message_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt; with `give_up` the email is marked failed
+    /// instead of left pending for the next one.
+    pub async fn mark_attempt_failed(&self, id: Uuid, error: &str, give_up: bool) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE emails
+            SET status = CASE WHEN $3 THEN 'Failed' ELSE 'Pending' END::delivery_status,
+                attempts = attempts + 1,
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(error)
+        .bind(give_up)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::{error::Result, models::event::ClientEvent};
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct EventRepository {
+    pool: PgPool,
+}
+
+impl EventRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Stores a batch of already-validated events for one (optional) user.
+    pub async fn insert_batch(&self, user_id: Option<Uuid>, events: &[ClientEvent]) -> Result<()> {
+        let now = Utc::now();
+        let types: Vec<_> = events.iter().map(|event| event.event_type).collect();
+        let stores: Vec<Uuid> = events.iter().map(|event| event.store_id).collect();
+        let products: Vec<Option<Uuid>> = events.iter().map(|event| event.product_id).collect();
+        let sessions: Vec<&str> = events
+            .iter()
+            .map(|event| event.session_id.as_str())
+            .collect();
+        let occurred: Vec<_> = events
+            .iter()
+            .map(|event| event.occurred_at.unwrap_or(now))
+            .collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO client_events (event_type, store_id, product_id, user_id, session_id, occurred_at)
+            SELECT e.event_type, e.store_id, e.product_id, $1, e.session_id, e.occurred_at
+            FROM UNNEST(
+                $2::client_event_type[], $3::uuid[], $4::uuid[], $5::varchar[], $6::timestamptz[]
+            ) AS e(event_type, store_id, product_id, session_id, occurred_at)
+            "#,
+        )
+        .bind(user_id)
+        .bind(&types)
+        .bind(&stores)
+        .bind(&products)
+        .bind(&sessions)
+        .bind(&occurred)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::Result,
+    models::{
+        fulfillment::{FulfillmentPartner, RateTier},
+        store::Store,
+    },
+};
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct FulfillmentRepository {
+    pool: PgPool,
+}
+
+impl FulfillmentRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(&self, name: &str, rate_table: &[RateTier]) -> Result<FulfillmentPartner> {
+        let partner = sqlx::query_as::<_, FulfillmentPartner>(
+            r#"
+            INSERT INTO fulfillment_partners (name, rate_table)
+            VALUES ($1, $2)
+            RETURNING *
+            "#,
+        )
+        .bind(name)
+        .bind(Json(rate_table))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(partner)
+    }
+
+    pub async fn find_by_id(&self, id: Uuid) -> Result<Option<FulfillmentPartner>> {
+        let partner = sqlx::query_as::<_, FulfillmentPartner>(
+            "SELECT * FROM fulfillment_partners WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(partner)
+    }
+
+    pub async fn find_by_name(&self, name: &str) -> Result<Option<FulfillmentPartner>> {
+        let partner = sqlx::query_as::<_, FulfillmentPartner>(
+            "SELECT * FROM fulfillment_partners WHERE name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(partner)
+    }
+
+    pub async fn list(&self) -> Result<Vec<FulfillmentPartner>> {
+        let partners = sqlx::query_as::<_, FulfillmentPartner>(
+            "SELECT * FROM fulfillment_partners ORDER BY name ASC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(partners)
+    }
+
+    pub async fn assign_store(&self, store_id: Uuid, partner_id: Option<Uuid>) -> Result<Store> {
+        let store = sqlx::query_as::<_, Store>(
+            r#"
+            UPDATE stores SET fulfillment_partner_id = $2 WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(partner_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(store)
+    }
+
+    /// Active fulfillment partners keyed by the stores they ship for.
+    pub async fn partners_for_stores(
+        &self,
+        store_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, FulfillmentPartner>> {
+        let rows = sqlx::query_as::<_, StorePartnerRow>(
+            r#"
+            SELECT s.id AS store_id, fp.*
+            FROM stores s
+            JOIN fulfillment_partners fp ON fp.id = s.fulfillment_partner_id
+            WHERE s.id = ANY($1) AND fp.is_active = true
+            "#,
+        )
+        .bind(store_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.store_id, row.partner))
+            .collect())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct StorePartnerRow {
+    store_id: Uuid,
+    #[sqlx(flatten)]
+    partner: FulfillmentPartner,
+}
@@ -0,0 +1,154 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{error::Result, models::idempotency::IdempotencyRecord};
+
+/// Outcome of claiming an idempotency key for a request.
+#[derive(Debug)]
+pub enum IdempotencyClaim {
+    /// The key was free, had expired or was held by a request whose lease
+    /// ran out, and now belongs to this request.
+    Claimed,
+    /// The key was already used; the record tells for which request and
+    /// whether it has a response yet.
+    Existing(IdempotencyRecord),
+    /// Another request claimed the key at the same moment.
+    Contended,
+}
+
+#[derive(Clone)]
+pub struct IdempotencyRepository {
+    pool: PgPool,
+}
+
+impl IdempotencyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Claims `key` for the caller's request until `expires_at`, unless an
+    /// unexpired request already holds it. The claim is leased until
+    /// `locked_until`: once that passes without a stored response, the
+    /// request is presumed dead and a retry takes the key over.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn claim(
+        &self,
+        user_id: Uuid,
+        key: &str,
+        method: &str,
+        path: &str,
+        request_hash: &[u8],
+        expires_at: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+    ) -> Result<IdempotencyClaim> {
+        let row = sqlx::query_as::<_, ClaimRow>(
+            r#"
+            WITH claimed AS (
+                INSERT INTO idempotency_keys
+                    (user_id, idempotency_key, method, path, request_hash, expires_at,
+                     locked_until)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (user_id, idempotency_key) DO UPDATE
+                SET method = EXCLUDED.method,
+                    path = EXCLUDED.path,
+                    request_hash = EXCLUDED.request_hash,
+                    status_code = NULL,
+                    content_type = NULL,
+                    response_body = NULL,
+                    created_at = NOW(),
+                    expires_at = EXCLUDED.expires_at,
+                    locked_until = EXCLUDED.locked_until
+                WHERE idempotency_keys.expires_at <= NOW()
+                   OR (idempotency_keys.status_code IS NULL
+                       AND COALESCE(idempotency_keys.locked_until, '-infinity') <= NOW())
+                RETURNING *
+            )
+            SELECT TRUE AS claimed, * FROM claimed
+            UNION ALL
+            SELECT FALSE AS claimed, * FROM idempotency_keys
+            WHERE user_id = $1 AND idempotency_key = $2
+              AND NOT EXISTS (SELECT 1 FROM claimed)
+            "#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(method)
+        .bind(path)
+        .bind(request_hash)
+        .bind(expires_at)
+        .bind(locked_until)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(match row {
+            Some(row) if row.claimed => IdempotencyClaim::Claimed,
+            Some(row) => IdempotencyClaim::Existing(row.record),
+            None => IdempotencyClaim::Contended,
+        })
+    }
+
+    /// Stores the response to replay for later requests with the key.
+    pub async fn complete(
+        &self,
+        user_id: Uuid,
+        key: &str,
+        status_code: i16,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE idempotency_keys
+            SET status_code = $3, content_type = $4, response_body = $5, locked_until = NULL
+            WHERE user_id = $1 AND idempotency_key = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(status_code)
+        .bind(content_type)
+        .bind(body)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Frees the key so the request can be retried, e.g. after it failed
+    /// with a server error.
+    pub async fn release(&self, user_id: Uuid, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM idempotency_keys WHERE user_id = $1 AND idempotency_key = $2")
+            .bind(user_id)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_expired(&self, limit: i64) -> Result<u64> {
+        let res = sqlx::query(
+            r#"
+            DELETE FROM idempotency_keys
+            WHERE (user_id, idempotency_key) IN (
+                SELECT user_id, idempotency_key FROM idempotency_keys
+                WHERE expires_at <= NOW()
+                LIMIT $1
+            )
+            "#,
+        )
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ClaimRow {
+    claimed: bool,
+    #[sqlx(flatten)]
+    record: IdempotencyRecord,
+}
@@ -0,0 +1,243 @@
+use crate::{
+    error::{AppError, Result},
+    models::{
+        domain_event::DomainEvent,
+        inventory::{InventoryMovement, InventoryMovementKind},
+        product::Availability,
+    },
+    repositories::OutboxRepository,
+};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InventoryRepository {
+    pool: PgPool,
+    outbox: OutboxRepository,
+}
+
+impl InventoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            outbox: OutboxRepository::new(pool.clone()),
+            pool,
+        }
+    }
+
+    /// Records a stock change the caller has already applied in `tx`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        product_id: Uuid,
+        kind: InventoryMovementKind,
+        delta: i32,
+        stock_after: i32,
+        actor_id: Option<Uuid>,
+        order_id: Option<Uuid>,
+        reason: Option<&str>,
+    ) -> Result<InventoryMovement> {
+        let movement = sqlx::query_as::<_, InventoryMovement>(
+            r#"
+            INSERT INTO inventory_movements (
+                product_id, store_id, kind, delta, stock_after, actor_id, order_id, reason
+            )
+            SELECT id, store_id, $2, $3, $4, $5, $6, $7
+            FROM products WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(product_id)
+        .bind(kind)
+        .bind(delta)
+        .bind(stock_after)
+        .bind(actor_id)
+        .bind(order_id)
+        .bind(reason)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(movement)
+    }
+
+    /// Changes the product's stock by `delta` and records the movement.
+    /// Stock may not go below zero. Crossing the low stock threshold
+    /// publishes `LowStock`, and taking it to zero `StockDepleted`.
+    pub async fn adjust(
+        &self,
+        product_id: Uuid,
+        kind: InventoryMovementKind,
+        delta: i32,
+        actor_id: Uuid,
+        reason: &str,
+    ) -> Result<InventoryMovement> {
+        let mut tx = self.pool.begin().await?;
+
+        let stock_after = sqlx::query_scalar::<_, i32>(
+            r#"
+            UPDATE products SET stock_quantity = stock_quantity + $2
+            WHERE id = $1 AND stock_quantity + $2 >= 0
+            RETURNING stock_quantity
+            "#,
+        )
+        .bind(product_id)
+        .bind(delta)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Stock cannot go below zero".into()))?;
+
+        let movement = self
+            .record_in_tx(
+                &mut tx,
+                product_id,
+                kind,
+                delta,
+                stock_after,
+                Some(actor_id),
+                None,
+                Some(reason),
+            )
+            .await?;
+        let stock_before = stock_after - delta;
+        if stock_before > Availability::LOW_STOCK_THRESHOLD
+            && stock_after <= Availability::LOW_STOCK_THRESHOLD
+        {
+            self.outbox
+                .record_in_tx(
+                    &mut tx,
+                    &DomainEvent::LowStock {
+                        product_id,
+                        store_id: movement.store_id,
+                        stock_quantity: stock_after,
+                    },
+                )
+                .await?;
+        }
+        if stock_after == 0 && delta < 0 {
+            self.outbox
+                .record_in_tx(
+                    &mut tx,
+                    &DomainEvent::StockDepleted {
+                        product_id,
+                        store_id: movement.store_id,
+                    },
+                )
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(movement)
+    }
+
+    /// Puts the order's items back into stock, recording one movement per
+    /// product. `actor_id` is `None` for automatic cancellations. Products
+    /// are locked in id order, like checkout locks them, so cancellations
+    /// and checkouts cannot deadlock.
+    pub async fn restore_order_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        actor_id: Option<Uuid>,
+    ) -> Result<Vec<InventoryMovement>> {
+        let movements = sqlx::query_as::<_, InventoryMovement>(
+            r#"
+            WITH lines AS (
+                SELECT product_id, SUM(quantity)::int AS quantity
+                FROM order_items
+                WHERE order_id = $1
+                GROUP BY product_id
+            ), locked AS (
+                SELECT id FROM products
+                WHERE id IN (SELECT product_id FROM lines)
+                ORDER BY id
+                FOR UPDATE
+            ), restored AS (
+                UPDATE products p
+                SET stock_quantity = p.stock_quantity + i.quantity
+                FROM lines i
+                WHERE p.id = i.product_id AND p.id IN (SELECT id FROM locked)
+                RETURNING p.id, p.store_id, i.quantity, p.stock_quantity
+            )
+            INSERT INTO inventory_movements (
+                product_id, store_id, kind, delta, stock_after, actor_id, order_id
+            )
+            SELECT id, store_id, 'CancellationRestore', quantity, stock_quantity, $2, $1
+            FROM restored
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(actor_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(movements)
+    }
+
+    /// Sets absolute stock levels by SKU, recording the difference to the
+    /// previous level of each product as an adjustment.
+    pub async fn set_stock_levels(
+        &self,
+        store_id: Uuid,
+        actor_id: Uuid,
+        skus: &[&str],
+        quantities: &[i32],
+        reason: &str,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            WITH target AS (
+                SELECT p.id, p.stock_quantity AS before, r.stock_quantity AS after
+                FROM products p
+                JOIN UNNEST($2::varchar[], $3::int[]) AS r(sku, stock_quantity)
+                    ON p.sku = r.sku
+                WHERE p.store_id = $1
+                FOR UPDATE OF p
+            ), updated AS (
+                UPDATE products p
+                SET stock_quantity = t.after
+                FROM target t
+                WHERE p.id = t.id AND t.before <> t.after
+                RETURNING p.id, p.store_id, p.stock_quantity, t.before
+            )
+            INSERT INTO inventory_movements (
+                product_id, store_id, kind, delta, stock_after, actor_id, reason
+            )
+            SELECT id, store_id, 'Adjustment', stock_quantity - before, stock_quantity, $4, $5
+            FROM updated
+            "#,
+        )
+        .bind(store_id)
+        .bind(skus)
+        .bind(quantities)
+        .bind(actor_id)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn list_for_product(
+        &self,
+        product_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<InventoryMovement>> {
+        let movements = sqlx::query_as::<_, InventoryMovement>(
+            r#"
+            SELECT * FROM inventory_movements
+            WHERE product_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(product_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(movements)
+    }
+}
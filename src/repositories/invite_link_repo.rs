@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::store::{AccessLevel, StoreInviteLink},
+};
+
+#[derive(Clone)]
+pub struct InviteLinkRepository {
+    pool: PgPool,
+}
+
+impl InviteLinkRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    pub async fn create(
+        &self,
+        store_id: Uuid,
+        created_by: Uuid,
+        access_level: AccessLevel,
+        max_uses: i32,
+        expires_at: DateTime<Utc>,
+    ) -> Result<StoreInviteLink> {
+        let link = sqlx::query_as::<_, StoreInviteLink>(
+            r#"
+            INSERT INTO store_invite_links (store_id, created_by, access_level, max_uses, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(created_by)
+        .bind(access_level)
+        .bind(max_uses)
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(link)
+    }
+
+    /// Locks a link that is neither revoked nor expired, so concurrent
+    /// redemptions cannot exceed its uses.
+    pub async fn lock_open_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        link_id: Uuid,
+    ) -> Result<Option<StoreInviteLink>> {
+        let link = sqlx::query_as::<_, StoreInviteLink>(
+            r#"
+            SELECT * FROM store_invite_links
+            WHERE id = $1 AND store_id = $2
+              AND revoked_at IS NULL AND expires_at > NOW()
+            FOR UPDATE
+            "#,
+        )
+        .bind(link_id)
+        .bind(store_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(link)
+    }
+
+    pub async fn has_redeemed_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        link_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool> {
+        let redeemed = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM store_invite_redemptions WHERE link_id = $1 AND user_id = $2
+            )
+            "#,
+        )
+        .bind(link_id)
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(redeemed)
+    }
+
+    /// Counts a use of the link by the user.
+    pub async fn record_redemption_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        link_id: Uuid,
+        user_id: Uuid,
+        grant_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            WITH redeemed AS (
+                INSERT INTO store_invite_redemptions (link_id, user_id, grant_id)
+                VALUES ($1, $2, $3)
+            )
+            UPDATE store_invite_links SET use_count = use_count + 1 WHERE id = $1
+            "#,
+        )
+        .bind(link_id)
+        .bind(user_id)
+        .bind(grant_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn revoke(&self, store_id: Uuid, link_id: Uuid) -> Result<Option<StoreInviteLink>> {
+        let link = sqlx::query_as::<_, StoreInviteLink>(
+            r#"
+            UPDATE store_invite_links SET revoked_at = NOW()
+            WHERE id = $1 AND store_id = $2 AND revoked_at IS NULL
+            RETURNING *
+            "#,
+        )
+        .bind(link_id)
+        .bind(store_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(link)
+    }
+}
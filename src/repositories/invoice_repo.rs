@@ -0,0 +1,78 @@
+use crate::{
+    error::{AppError, Result},
+    models::invoice::Invoice,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InvoiceRepository {
+    pool: PgPool,
+}
+
+impl InvoiceRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The order's invoice, issuing it with the store's next number on
+    /// first use. The counter row serialises issuance per store and is
+    /// rolled back when another request issued the invoice first, so
+    /// numbers never skip.
+    pub async fn issue_for_order(&self, order_id: Uuid, store_id: Uuid) -> Result<Invoice> {
+        if let Some(invoice) = self.find_for_order(order_id).await? {
+            return Ok(invoice);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let sequence_number = sqlx::query_scalar::<_, i32>(
+            r#"
+            INSERT INTO invoice_counters (store_id, last_number)
+            VALUES ($1, 1)
+            ON CONFLICT (store_id)
+            DO UPDATE SET last_number = invoice_counters.last_number + 1
+            RETURNING last_number
+            "#,
+        )
+        .bind(store_id)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let issued = sqlx::query_as::<_, Invoice>(
+            r#"
+            INSERT INTO invoices (order_id, store_id, sequence_number, invoice_number)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (order_id) DO NOTHING
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(store_id)
+        .bind(sequence_number)
+        .bind(Invoice::format_number(sequence_number))
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        match issued {
+            Some(invoice) => {
+                tx.commit().await?;
+                Ok(invoice)
+            }
+            None => {
+                tx.rollback().await?;
+                self.find_for_order(order_id)
+                    .await?
+                    .ok_or_else(|| AppError::NotFound("Invoice not found".into()))
+            }
+        }
+    }
+
+    pub async fn find_for_order(&self, order_id: Uuid) -> Result<Option<Invoice>> {
+        let invoice = sqlx::query_as::<_, Invoice>("SELECT * FROM invoices WHERE order_id = $1")
+            .bind(order_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(invoice)
+    }
+}
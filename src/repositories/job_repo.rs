@@ -0,0 +1,164 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::job::{Job, JobQueueDepth},
+};
+
+#[derive(Clone)]
+pub struct JobRepository {
+    pool: PgPool,
+}
+
+impl JobRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn enqueue(
+        &self,
+        kind: &str,
+        payload: &serde_json::Value,
+        run_at: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> Result<Job> {
+        let job = sqlx::query_as::<_, Job>(
+            r#"
+            INSERT INTO jobs (kind, payload, run_at, max_attempts)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(kind)
+        .bind(payload)
+        .bind(run_at)
+        .bind(max_attempts)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    /// Queues a job that only runs if the transaction commits.
+    pub async fn enqueue_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        kind: &str,
+        payload: &serde_json::Value,
+        run_at: DateTime<Utc>,
+        max_attempts: i32,
+    ) -> Result<Job> {
+        let job = sqlx::query_as::<_, Job>(
+            r#"
+            INSERT INTO jobs (kind, payload, run_at, max_attempts)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(kind)
+        .bind(payload)
+        .bind(run_at)
+        .bind(max_attempts)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(job)
+    }
+
+    pub async fn find_by_id(&self, job_id: Uuid) -> Result<Option<Job>> {
+        let job = sqlx::query_as::<_, Job>("SELECT * FROM jobs WHERE id = $1")
+            .bind(job_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(job)
+    }
+
+    /// Claims up to `limit` due jobs of the given kinds and counts the
+    /// attempt. Claimed jobs are pushed back by `lease_secs`, so a worker
+    /// that dies mid-run leaves them to be retried rather than lost.
+    pub async fn claim_due(&self, kinds: &[&str], limit: i64, lease_secs: i64) -> Result<Vec<Job>> {
+        let jobs = sqlx::query_as::<_, Job>(
+            r#"
+            UPDATE jobs
+            SET attempts = attempts + 1,
+                run_at = NOW() + make_interval(secs => $3)
+            WHERE id IN (
+                SELECT id FROM jobs
+                WHERE status = 'Pending' AND kind = ANY($1) AND run_at <= NOW()
+                ORDER BY run_at ASC
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(kinds)
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    pub async fn mark_succeeded(&self, job_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = 'Succeeded', finished_at = NOW(), last_error = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(job_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt; the job is retried at `retry_at`, or given
+    /// up on when that is `None`.
+    pub async fn mark_failed(
+        &self,
+        job_id: Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE jobs
+            SET status = CASE WHEN $3::timestamptz IS NULL THEN 'Failed' ELSE 'Pending' END::job_status,
+                run_at = COALESCE($3, run_at),
+                finished_at = CASE WHEN $3::timestamptz IS NULL THEN NOW() END,
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(job_id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Pending jobs by kind, including ones waiting for a retry or running.
+    pub async fn queue_depth(&self) -> Result<Vec<JobQueueDepth>> {
+        let depth = sqlx::query_as::<_, JobQueueDepth>(
+            r#"
+            SELECT kind, COUNT(*) AS pending
+            FROM jobs
+            WHERE status = 'Pending'
+            GROUP BY kind
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(depth)
+    }
+}
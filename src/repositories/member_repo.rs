@@ -1,49 +1,105 @@
 use crate::{
+    cache::CacheHandle,
     error::Result,
     models::{
+        domain_event::DomainEvent,
         permission::Permission,
-        store::{MemberRole, StoreMember},
+        store::{MemberRole, MemberStore, StoreMember, StoreRole},
     },
+    repositories::OutboxRepository,
 };
 use serde_json::json;
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct MemberRepository {
     pool: PgPool,
+    outbox: OutboxRepository,
+    cache: Option<CacheHandle>,
 }
 
 impl MemberRepository {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            outbox: OutboxRepository::new(pool.clone()),
+            pool,
+            cache: None,
+        }
     }
 
+    /// Drops the store's cached permissions in `cache` after membership changes.
+    pub fn with_cache(mut self, cache: CacheHandle) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Drops everyone's cached permissions in the store, for callers that
+    /// write inside their own transaction, once it has committed.
+    pub async fn invalidate_permissions(&self, store_id: Uuid) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_permissions(store_id).await;
+        }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Adds the member; one added by someone else publishes
+    /// `MemberInvited`.
     pub async fn add_member(
         &self,
         store_id: Uuid,
         user_id: Uuid,
         role: MemberRole,
         permissions: &[Permission],
+        custom_role_id: Option<Uuid>,
         invited_by: Option<Uuid>,
     ) -> Result<StoreMember> {
         let permissions_json = json!(permissions.iter().map(|p| p.as_str()).collect::<Vec<_>>());
 
+        let mut tx = self.pool.begin().await?;
         let member = sqlx::query_as::<_, StoreMember>(
             r#"
-            INSERT INTO store_members (store_id, user_id, role, permissions, invited_by)
-            VALUES ($1, $2, $3, $4, $5)
-            RETURNING *
+            WITH added AS (
+                INSERT INTO store_members
+                    (store_id, user_id, role, permissions, custom_role_id, invited_by)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, after)
+                SELECT store_id, $6, 'MemberAdded', user_id, id, to_jsonb(added)
+                FROM added
+            )
+            SELECT * FROM added
             "#,
         )
         .bind(store_id)
         .bind(user_id)
         .bind(role)
         .bind(permissions_json)
+        .bind(custom_role_id)
         .bind(invited_by)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *tx)
         .await?;
+        if let Some(invited_by) = invited_by.filter(|&inviter| inviter != user_id) {
+            self.outbox
+                .record_in_tx(
+                    &mut tx,
+                    &DomainEvent::MemberInvited {
+                        store_id,
+                        user_id,
+                        role,
+                        invited_by,
+                    },
+                )
+                .await?;
+        }
+        tx.commit().await?;
 
+        self.invalidate_permissions(store_id).await;
         Ok(member)
     }
 
@@ -80,4 +136,227 @@ impl MemberRepository {
 
         Ok(members)
     }
+
+    /// The user's active memberships across all stores.
+    pub async fn list_active_for_user(&self, user_id: Uuid) -> Result<Vec<StoreMember>> {
+        let members = sqlx::query_as::<_, StoreMember>(
+            "SELECT * FROM store_members WHERE user_id = $1 AND is_active = true",
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(members)
+    }
+
+    /// The store roles the user holds as a Custom member, across stores.
+    pub async fn list_custom_roles_for_user(&self, user_id: Uuid) -> Result<Vec<StoreRole>> {
+        let roles = sqlx::query_as::<_, StoreRole>(
+            r#"
+            SELECT r.* FROM store_roles r
+            JOIN store_members m ON m.custom_role_id = r.id
+            WHERE m.user_id = $1 AND m.is_active = true AND m.role = 'Custom'
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    /// Changes an active member's role, permissions and store role on behalf
+    /// of `actor_id`. The member's existing sessions are invalidated so the
+    /// change applies immediately.
+    pub async fn update_member(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+        role: MemberRole,
+        permissions: &[Permission],
+        custom_role_id: Option<Uuid>,
+        actor_id: Uuid,
+    ) -> Result<Option<StoreMember>> {
+        let permissions_json = json!(permissions.iter().map(|p| p.as_str()).collect::<Vec<_>>());
+
+        let member = sqlx::query_as::<_, StoreMember>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_members
+                WHERE store_id = $1 AND user_id = $2 AND is_active = true
+            ), updated AS (
+                UPDATE store_members
+                SET role = $3, permissions = $4, custom_role_id = $5
+                WHERE store_id = $1 AND user_id = $2 AND is_active = true
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id IN (SELECT user_id FROM updated)
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, before, after)
+                SELECT u.store_id, $6, 'MemberUpdated', u.user_id, u.id,
+                       to_jsonb(p), to_jsonb(u)
+                FROM updated u JOIN previous p ON p.id = u.id
+            )
+            SELECT * FROM updated
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(role)
+        .bind(permissions_json)
+        .bind(custom_role_id)
+        .bind(actor_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        self.invalidate_permissions(store_id).await;
+        Ok(member)
+    }
+
+    /// Deactivates a membership on behalf of `actor_id` and invalidates the
+    /// member's sessions.
+    pub async fn remove_member(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+        actor_id: Uuid,
+    ) -> Result<Option<StoreMember>> {
+        let member = sqlx::query_as::<_, StoreMember>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_members
+                WHERE store_id = $1 AND user_id = $2 AND is_active = true
+            ), removed AS (
+                UPDATE store_members
+                SET is_active = false
+                WHERE store_id = $1 AND user_id = $2 AND is_active = true
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id IN (SELECT user_id FROM removed)
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, before, after)
+                SELECT r.store_id, $3, 'MemberRemoved', r.user_id, r.id,
+                       to_jsonb(p), to_jsonb(r)
+                FROM removed r JOIN previous p ON p.id = r.id
+            )
+            SELECT * FROM removed
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(actor_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        self.invalidate_permissions(store_id).await;
+        Ok(member)
+    }
+
+    /// Resolves user ids for the given lowercase emails, matching
+    /// case-insensitively. Unknown or deactivated accounts are left out.
+    pub async fn find_user_ids_by_email(&self, emails: &[&str]) -> Result<Vec<(String, Uuid)>> {
+        let users = sqlx::query_as::<_, (String, Uuid)>(
+            "SELECT lower(email), id FROM users WHERE lower(email) = ANY($1) AND is_active = true",
+        )
+        .bind(emails)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    /// Which of the given user ids belong to active accounts.
+    pub async fn find_active_user_ids(&self, user_ids: &[Uuid]) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar::<_, Uuid>(
+            "SELECT id FROM users WHERE id = ANY($1) AND is_active = true",
+        )
+        .bind(user_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    /// Adds a member or, if they were a member before, updates their role
+    /// and permissions and reactivates them. Sessions of members who were
+    /// already active are invalidated when their role or permissions change.
+    /// Members moved off the Custom role lose their store role.
+    pub async fn upsert_member_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        user_id: Uuid,
+        role: MemberRole,
+        permissions: &[Permission],
+        invited_by: Uuid,
+    ) -> Result<StoreMember> {
+        let permissions_json = json!(permissions.iter().map(|p| p.as_str()).collect::<Vec<_>>());
+
+        let member = sqlx::query_as::<_, StoreMember>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_members
+                WHERE store_id = $1 AND user_id = $2
+            ), upserted AS (
+                INSERT INTO store_members (store_id, user_id, role, permissions, invited_by)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (store_id, user_id) DO UPDATE
+                SET role = EXCLUDED.role,
+                    permissions = EXCLUDED.permissions,
+                    custom_role_id = CASE WHEN EXCLUDED.role = 'Custom'
+                        THEN store_members.custom_role_id END,
+                    is_active = true
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id = $2 AND EXISTS (
+                    SELECT 1 FROM previous
+                    WHERE is_active AND (role <> $3 OR permissions <> $4)
+                )
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, target_user_id, subject_id, before, after)
+                SELECT u.store_id, $5,
+                       CASE WHEN p.is_active THEN 'MemberUpdated'
+                            ELSE 'MemberAdded' END::permission_audit_action,
+                       u.user_id, u.id, to_jsonb(p), to_jsonb(u)
+                FROM upserted u LEFT JOIN previous p ON p.id = u.id
+            )
+            SELECT * FROM upserted
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .bind(role)
+        .bind(permissions_json)
+        .bind(invited_by)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(member)
+    }
+
+    /// Stores the user owns or is an active member of, with their role.
+    pub async fn list_stores_for_user(&self, user_id: Uuid) -> Result<Vec<MemberStore>> {
+        let stores = sqlx::query_as::<_, MemberStore>(
+            r#"
+            SELECT s.*,
+                   CASE WHEN s.owner_id = $1 THEN 'Owner'::member_role ELSE sm.role END AS role
+            FROM stores s
+            LEFT JOIN store_members sm
+                ON sm.store_id = s.id AND sm.user_id = $1 AND sm.is_active = true
+            WHERE s.owner_id = $1 OR sm.id IS NOT NULL
+            ORDER BY s.name ASC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stores)
+    }
 }
@@ -0,0 +1,159 @@
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::message::{MessageSender, OrderMessage, UnreadThread},
+};
+
+#[derive(Clone)]
+pub struct OrderMessageRepository {
+    pool: PgPool,
+}
+
+impl OrderMessageRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    pub async fn create_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        sender_id: Uuid,
+        sender: MessageSender,
+        body: &str,
+    ) -> Result<OrderMessage> {
+        let message = sqlx::query_as::<_, OrderMessage>(
+            r#"
+            WITH created AS (
+                INSERT INTO order_messages (order_id, sender_id, sender, body)
+                VALUES ($1, $2, $3, $4)
+                RETURNING *
+            )
+            SELECT c.id, c.order_id, c.sender_id, c.sender, u.full_name AS sender_name,
+                   c.body, c.created_at
+            FROM created c
+            LEFT JOIN users u ON u.id = c.sender_id
+            "#,
+        )
+        .bind(order_id)
+        .bind(sender_id)
+        .bind(sender)
+        .bind(body)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(message)
+    }
+
+    /// The order's thread, oldest first.
+    pub async fn list_for_order(&self, order_id: Uuid) -> Result<Vec<OrderMessage>> {
+        let messages = sqlx::query_as::<_, OrderMessage>(
+            r#"
+            SELECT m.id, m.order_id, m.sender_id, m.sender, u.full_name AS sender_name,
+                   m.body, m.created_at
+            FROM order_messages m
+            LEFT JOIN users u ON u.id = m.sender_id
+            WHERE m.order_id = $1
+            ORDER BY m.created_at ASC, m.id ASC
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(messages)
+    }
+
+    pub async fn mark_read(&self, order_id: Uuid, user_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO order_message_reads (order_id, user_id)
+            VALUES ($1, $2)
+            ON CONFLICT (order_id, user_id) DO UPDATE SET last_read_at = NOW()
+            "#,
+        )
+        .bind(order_id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn mark_read_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO order_message_reads (order_id, user_id)
+            VALUES ($1, $2)
+            ON CONFLICT (order_id, user_id) DO UPDATE SET last_read_at = NOW()
+            "#,
+        )
+        .bind(order_id)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Store replies the buyer has not read, per order.
+    pub async fn unread_for_buyer(&self, user_id: Uuid) -> Result<Vec<UnreadThread>> {
+        let threads = sqlx::query_as::<_, UnreadThread>(
+            r#"
+            SELECT o.id AS order_id, o.order_number, COUNT(*) AS unread,
+                   MAX(m.created_at) AS last_message_at
+            FROM order_messages m
+            JOIN orders o ON o.id = m.order_id
+            LEFT JOIN order_message_reads r ON r.order_id = o.id AND r.user_id = $1
+            WHERE o.user_id = $1 AND m.sender = 'Store'
+              AND (r.last_read_at IS NULL OR m.created_at > r.last_read_at)
+            GROUP BY o.id, o.order_number
+            ORDER BY last_message_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(threads)
+    }
+
+    /// Buyer messages to the store that `user_id`, a staff member, has not
+    /// read, per order.
+    pub async fn unread_for_store(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Vec<UnreadThread>> {
+        let threads = sqlx::query_as::<_, UnreadThread>(
+            r#"
+            SELECT o.id AS order_id, o.order_number, COUNT(*) AS unread,
+                   MAX(m.created_at) AS last_message_at
+            FROM order_messages m
+            JOIN orders o ON o.id = m.order_id
+            LEFT JOIN order_message_reads r ON r.order_id = o.id AND r.user_id = $2
+            WHERE o.store_id = $1 AND m.sender = 'Buyer'
+              AND (r.last_read_at IS NULL OR m.created_at > r.last_read_at)
+            GROUP BY o.id, o.order_number
+            ORDER BY last_message_at DESC
+            "#,
+        )
+        .bind(store_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(threads)
+    }
+}
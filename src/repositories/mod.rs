@@ -1,17 +1,67 @@
 pub mod access_grant_repo;
 pub mod analytics_repo;
+pub mod audit_repo;
 pub mod cart_repo;
+pub mod coupon_repo;
+pub mod credit_repo;
+pub mod email_repo;
+pub mod event_repo;
+pub mod fulfillment_repo;
+pub mod idempotency_repo;
+pub mod inventory_repo;
+pub mod invite_link_repo;
+pub mod invoice_repo;
+pub mod job_repo;
 pub mod member_repo;
+pub mod message_repo;
+pub mod moderation_repo;
+pub mod notification_repo;
 pub mod order_repo;
+pub mod outbox_repo;
+pub mod payment_repo;
+pub mod permission_audit_repo;
+pub mod product_file_repo;
+pub mod product_image_repo;
 pub mod product_repo;
+pub mod recently_viewed_repo;
+pub mod recommendation_repo;
 pub mod store_repo;
+pub mod store_role_repo;
+pub mod store_settings_repo;
+pub mod tax_repo;
 pub mod user_repo;
+pub mod webhook_repo;
 
 pub use access_grant_repo::AccessGrantRepository;
 pub use analytics_repo::AnalyticsRepository;
+pub use audit_repo::AuditRepository;
 pub use cart_repo::CartRepository;
+pub use coupon_repo::CouponRepository;
+pub use credit_repo::CreditRepository;
+pub use email_repo::EmailRepository;
+pub use event_repo::EventRepository;
+pub use fulfillment_repo::FulfillmentRepository;
+pub use idempotency_repo::IdempotencyRepository;
+pub use inventory_repo::InventoryRepository;
+pub use invite_link_repo::InviteLinkRepository;
+pub use invoice_repo::InvoiceRepository;
+pub use job_repo::JobRepository;
 pub use member_repo::MemberRepository;
+pub use message_repo::OrderMessageRepository;
+pub use moderation_repo::ModerationRepository;
+pub use notification_repo::NotificationRepository;
 pub use order_repo::OrderRepository;
+pub use outbox_repo::OutboxRepository;
+pub use payment_repo::PaymentRepository;
+pub use permission_audit_repo::PermissionAuditRepository;
+pub use product_file_repo::ProductFileRepository;
+pub use product_image_repo::ProductImageRepository;
 pub use product_repo::ProductRepository;
+pub use recently_viewed_repo::RecentlyViewedRepository;
+pub use recommendation_repo::RecommendationRepository;
 pub use store_repo::StoreRepository;
+pub use store_role_repo::StoreRoleRepository;
+pub use store_settings_repo::StoreSettingsRepository;
+pub use tax_repo::TaxRepository;
 pub use user_repo::UserRepository;
+pub use webhook_repo::WebhookRepository;
@@ -0,0 +1,193 @@
+use crate::{
+    error::Result,
+    models::store::{AppealStatus, Store, StoreAppeal, StoreModerationAction, StoreStatus},
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ModerationRepository {
+    pool: PgPool,
+}
+
+impl ModerationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Changes the store status and records the moderation action atomically.
+    pub async fn apply_status(
+        &self,
+        store: &Store,
+        actor_id: Uuid,
+        status: StoreStatus,
+        reason: &str,
+    ) -> Result<(Store, StoreModerationAction)> {
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query_as::<_, Store>(
+            r#"
+            UPDATE stores SET status = $2 WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(store.id)
+        .bind(status)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let action = sqlx::query_as::<_, StoreModerationAction>(
+            r#"
+            INSERT INTO store_moderation_actions (
+                store_id, actor_id, previous_status, new_status, reason
+            ) VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(store.id)
+        .bind(actor_id)
+        .bind(store.status)
+        .bind(status)
+        .bind(reason)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // A reinstated store gets a fresh cleanup run if it is closed again.
+        if status != StoreStatus::Closed {
+            sqlx::query("DELETE FROM store_cleanups WHERE store_id = $1")
+                .bind(store.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok((updated, action))
+    }
+
+    pub async fn list_actions(&self, store_id: Uuid) -> Result<Vec<StoreModerationAction>> {
+        let actions = sqlx::query_as::<_, StoreModerationAction>(
+            r#"
+            SELECT * FROM store_moderation_actions
+            WHERE store_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(actions)
+    }
+
+    pub async fn create_appeal(
+        &self,
+        store_id: Uuid,
+        submitted_by: Uuid,
+        message: &str,
+    ) -> Result<StoreAppeal> {
+        let appeal = sqlx::query_as::<_, StoreAppeal>(
+            r#"
+            INSERT INTO store_appeals (store_id, submitted_by, message)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(submitted_by)
+        .bind(message)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(appeal)
+    }
+
+    pub async fn find_appeal(&self, appeal_id: Uuid) -> Result<Option<StoreAppeal>> {
+        let appeal = sqlx::query_as::<_, StoreAppeal>("SELECT * FROM store_appeals WHERE id = $1")
+            .bind(appeal_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(appeal)
+    }
+
+    pub async fn find_pending_appeal(&self, store_id: Uuid) -> Result<Option<StoreAppeal>> {
+        let appeal = sqlx::query_as::<_, StoreAppeal>(
+            r#"
+            SELECT * FROM store_appeals
+            WHERE store_id = $1 AND status = 'Pending'
+            "#,
+        )
+        .bind(store_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(appeal)
+    }
+
+    pub async fn list_appeals_for_store(&self, store_id: Uuid) -> Result<Vec<StoreAppeal>> {
+        let appeals = sqlx::query_as::<_, StoreAppeal>(
+            r#"
+            SELECT * FROM store_appeals
+            WHERE store_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(appeals)
+    }
+
+    pub async fn list_appeals(
+        &self,
+        status: Option<AppealStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<StoreAppeal>> {
+        let appeals = sqlx::query_as::<_, StoreAppeal>(
+            r#"
+            SELECT * FROM store_appeals
+            WHERE ($1::appeal_status IS NULL OR status = $1)
+            ORDER BY created_at ASC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(status)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(appeals)
+    }
+
+    pub async fn resolve_appeal(
+        &self,
+        appeal_id: Uuid,
+        reviewer_id: Uuid,
+        status: AppealStatus,
+        note: Option<&str>,
+    ) -> Result<Option<StoreAppeal>> {
+        let appeal = sqlx::query_as::<_, StoreAppeal>(
+            r#"
+            UPDATE store_appeals
+            SET status = $3,
+                reviewed_by = $2,
+                review_note = $4,
+                reviewed_at = NOW()
+            WHERE id = $1 AND status = 'Pending'
+            RETURNING *
+            "#,
+        )
+        .bind(appeal_id)
+        .bind(reviewer_id)
+        .bind(status)
+        .bind(note)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(appeal)
+    }
+}
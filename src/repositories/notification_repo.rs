@@ -0,0 +1,257 @@
+use crate::{
+    error::Result,
+    models::notification::{
+        CreateNotificationRuleRequest, Notification, NotificationDelivery, NotificationRule,
+    },
+};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct NotificationRepository {
+    pool: PgPool,
+}
+
+impl NotificationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Fans the same notification out to every listed user.
+    pub async fn create_for_users_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_ids: &[Uuid],
+        store_id: Option<Uuid>,
+        kind: &str,
+        message: &str,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO user_notifications (user_id, store_id, kind, message)
+            SELECT user_id, $2, $3, $4 FROM UNNEST($1::uuid[]) AS t(user_id)
+            "#,
+        )
+        .bind(user_ids)
+        .bind(store_id)
+        .bind(kind)
+        .bind(message)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Notification>> {
+        let notifications = sqlx::query_as::<_, Notification>(
+            r#"
+            SELECT * FROM user_notifications
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(notifications)
+    }
+
+    /// Sends a store notification wherever the store's rules for `kind`
+    /// route it: in-app rules notify their recipient while they are still
+    /// the owner or an active member, email and webhook rules queue a
+    /// delivery. Kinds without rules go in-app to the store owner.
+    pub async fn dispatch_store_event_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        kind: &str,
+        message: &str,
+    ) -> Result<i64> {
+        let routed = sqlx::query_scalar::<_, i64>(
+            r#"
+            WITH rules AS (
+                SELECT * FROM store_notification_rules
+                WHERE store_id = $1 AND kind = $2
+            ), recipients AS (
+                SELECT r.recipient_user_id AS user_id
+                FROM rules r
+                JOIN stores s ON s.id = r.store_id
+                WHERE r.channel = 'InApp'
+                  AND (
+                      r.recipient_user_id = s.owner_id
+                      OR EXISTS (
+                          SELECT 1 FROM store_members m
+                          WHERE m.store_id = r.store_id
+                            AND m.user_id = r.recipient_user_id
+                            AND m.is_active = true
+                      )
+                  )
+                UNION
+                SELECT s.owner_id FROM stores s
+                WHERE s.id = $1 AND NOT EXISTS (SELECT 1 FROM rules)
+            ), in_app AS (
+                INSERT INTO user_notifications (user_id, store_id, kind, message)
+                SELECT user_id, $1, $2, $3 FROM recipients
+                RETURNING id
+            ), queued AS (
+                INSERT INTO notification_deliveries (rule_id, store_id, kind, channel, target, message)
+                SELECT id, store_id, kind, channel, target, $3
+                FROM rules
+                WHERE channel <> 'InApp'
+                RETURNING id
+            )
+            SELECT (SELECT COUNT(*) FROM in_app) + (SELECT COUNT(*) FROM queued)
+            "#,
+        )
+        .bind(store_id)
+        .bind(kind)
+        .bind(message)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(routed)
+    }
+
+    pub async fn list_rules(&self, store_id: Uuid) -> Result<Vec<NotificationRule>> {
+        let rules = sqlx::query_as::<_, NotificationRule>(
+            r#"
+            SELECT * FROM store_notification_rules
+            WHERE store_id = $1
+            ORDER BY kind ASC, created_at ASC
+            "#,
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Returns `None` when an in-app rule names someone who is neither the
+    /// store owner nor an active member.
+    pub async fn create_rule(
+        &self,
+        store_id: Uuid,
+        rule: &CreateNotificationRuleRequest,
+        created_by: Uuid,
+    ) -> Result<Option<NotificationRule>> {
+        let rule = sqlx::query_as::<_, NotificationRule>(
+            r#"
+            INSERT INTO store_notification_rules (
+                store_id, kind, channel, recipient_user_id, target, created_by
+            )
+            SELECT $1, $2, $3, $4, $5, $6
+            WHERE $4::uuid IS NULL
+               OR EXISTS (SELECT 1 FROM stores WHERE id = $1 AND owner_id = $4)
+               OR EXISTS (
+                   SELECT 1 FROM store_members
+                   WHERE store_id = $1 AND user_id = $4 AND is_active = true
+               )
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(&rule.kind)
+        .bind(rule.channel)
+        .bind(rule.recipient_user_id)
+        .bind(&rule.target)
+        .bind(created_by)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(rule)
+    }
+
+    pub async fn delete_rule(&self, store_id: Uuid, rule_id: Uuid) -> Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM store_notification_rules WHERE id = $1 AND store_id = $2")
+                .bind(rule_id)
+                .bind(store_id)
+                .execute(&self.pool)
+                .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Claims up to `limit` due deliveries and counts the attempt. Claimed
+    /// rows are pushed back by `lease_secs`, so a worker that dies mid-send
+    /// leaves them to be retried rather than lost.
+    pub async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: i64,
+    ) -> Result<Vec<NotificationDelivery>> {
+        let deliveries = sqlx::query_as::<_, NotificationDelivery>(
+            r#"
+            UPDATE notification_deliveries
+            SET attempts = attempts + 1,
+                next_attempt_at = NOW() + make_interval(secs => $2)
+            WHERE id IN (
+                SELECT id FROM notification_deliveries
+                WHERE status = 'Pending' AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at ASC
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    pub async fn mark_delivery_sent(&self, delivery_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE notification_deliveries
+            SET status = 'Sent', sent_at = NOW(), last_error = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt; the delivery is retried at `retry_at`, or
+    /// given up on when that is `None`.
+    pub async fn mark_delivery_failed(
+        &self,
+        delivery_id: Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE notification_deliveries
+            SET status = CASE WHEN $3::timestamptz IS NULL THEN 'Failed' ELSE 'Pending' END::delivery_status,
+                next_attempt_at = COALESCE($3, next_attempt_at),
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
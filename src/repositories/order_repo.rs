@@ -1,5 +1,13 @@
+use std::collections::HashMap;
+
 use crate::error::Result;
-use crate::models::order::{Order, OrderGroup, OrderItem, OrderStatus, PaymentStatus};
+use crate::models::order::{
+    CheckoutFingerprint, Order, OrderExportLine, OrderGroup, OrderItem, OrderItemDetail,
+    OrderListEntry, OrderStatus, OrderTaxLine, PaymentStatus, Shipment, ShipmentItem,
+    UpdateShipmentRequest,
+};
+use crate::models::tax::TaxTransaction;
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde_json::Value;
 use sqlx::{postgres::PgQueryResult, PgPool, Postgres, Transaction};
@@ -56,17 +64,24 @@ impl OrderRepository {
         tax: Decimal,
         discount: Decimal,
         shipping_cost: Decimal,
+        credit_applied: Decimal,
         total_amount: Decimal,
         shipping_address: &Value,
+        custom_fields: &Value,
+        currency: &str,
+        presentment: Option<(&str, Decimal)>,
+        shipping_method: Option<&str>,
     ) -> Result<Order> {
         let order = sqlx::query_as::<_, Order>(
             r#"
             INSERT INTO orders (
-                order_group_id, user_id, store_id, order_number,
-                subtotal, tax, discount, shipping_cost, total_amount, shipping_address
+                order_group_id, user_id, store_id, order_number, subtotal, tax,
+                discount, shipping_cost, credit_applied, total_amount, shipping_address,
+                custom_fields, currency, presentment_currency, exchange_rate, shipping_method
             ) VALUES (
-                $1, $2, $3, $4,
-                $5, $6, $7, $8, $9, $10
+                $1, $2, $3, $4, $5, $6,
+                $7, $8, $9, $10, $11, $12,
+                $13, $14, $15, $16
             )
             RETURNING *
             "#,
@@ -79,8 +94,14 @@ impl OrderRepository {
         .bind(tax)
         .bind(discount)
         .bind(shipping_cost)
+        .bind(credit_applied)
         .bind(total_amount)
         .bind(shipping_address)
+        .bind(custom_fields)
+        .bind(currency)
+        .bind(presentment.map(|(code, _)| code))
+        .bind(presentment.map(|(_, rate)| rate))
+        .bind(shipping_method)
         .fetch_one(&mut **tx)
         .await?;
 
@@ -114,15 +135,68 @@ impl OrderRepository {
         Ok(item)
     }
 
-    pub async fn list_orders_for_user(
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_tax_line(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        store_id: Uuid,
+        jurisdiction: &str,
+        rate: Decimal,
+        taxable_amount: Decimal,
+        tax_amount: Decimal,
+    ) -> Result<OrderTaxLine> {
+        let line = sqlx::query_as::<_, OrderTaxLine>(
+            r#"
+            INSERT INTO order_tax_lines (
+                order_id, store_id, jurisdiction, rate, taxable_amount, tax_amount
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(store_id)
+        .bind(jurisdiction)
+        .bind(rate)
+        .bind(taxable_amount)
+        .bind(tax_amount)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(line)
+    }
+
+    /// Queues the order to be reported to the store's tax provider for filing.
+    pub async fn queue_tax_transaction_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        store_id: Uuid,
+    ) -> Result<TaxTransaction> {
+        let transaction = sqlx::query_as::<_, TaxTransaction>(
+            r#"
+            INSERT INTO tax_transactions (order_id, store_id)
+            VALUES ($1, $2)
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(store_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(transaction)
+    }
+
+    pub async fn list_entries_for_user(
         &self,
         user_id: Uuid,
         limit: i64,
         offset: i64,
-    ) -> Result<Vec<Order>> {
-        let orders = sqlx::query_as::<_, Order>(
+    ) -> Result<Vec<OrderListEntry>> {
+        let entries = sqlx::query_as::<_, OrderListEntry>(
             r#"
-            SELECT * FROM orders
+            SELECT * FROM order_list_view
             WHERE user_id = $1
             ORDER BY created_at DESC
             LIMIT $2 OFFSET $3
@@ -134,9 +208,445 @@ impl OrderRepository {
         .fetch_all(&self.pool)
         .await?;
 
+        Ok(entries)
+    }
+
+    /// Store orders, newest first, optionally in one status and placed
+    /// within `[placed_from, placed_before)`.
+    pub async fn list_entries_for_store(
+        &self,
+        store_id: Uuid,
+        status: Option<OrderStatus>,
+        placed_from: Option<DateTime<Utc>>,
+        placed_before: Option<DateTime<Utc>>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<OrderListEntry>> {
+        let entries = sqlx::query_as::<_, OrderListEntry>(
+            r#"
+            SELECT * FROM order_list_view
+            WHERE store_id = $1
+              AND ($2::order_status IS NULL OR status = $2)
+              AND ($3::timestamptz IS NULL OR created_at >= $3)
+              AND ($4::timestamptz IS NULL OR created_at < $4)
+            ORDER BY created_at DESC
+            LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(store_id)
+        .bind(status)
+        .bind(placed_from)
+        .bind(placed_before)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn list_groups_for_user(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<OrderGroup>> {
+        let groups = sqlx::query_as::<_, OrderGroup>(
+            r#"
+            SELECT * FROM order_groups
+            WHERE user_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(groups)
+    }
+
+    pub async fn list_entries_for_groups(
+        &self,
+        order_group_ids: &[Uuid],
+    ) -> Result<Vec<OrderListEntry>> {
+        let entries = sqlx::query_as::<_, OrderListEntry>(
+            r#"
+            SELECT * FROM order_list_view
+            WHERE order_group_id = ANY($1)
+            ORDER BY store_name ASC, order_id ASC
+            "#,
+        )
+        .bind(order_group_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    pub async fn list_entries_for_group(
+        &self,
+        order_group_id: Uuid,
+    ) -> Result<Vec<OrderListEntry>> {
+        let entries = sqlx::query_as::<_, OrderListEntry>(
+            r#"
+            SELECT * FROM order_list_view
+            WHERE order_group_id = $1
+            ORDER BY store_name ASC, order_id ASC
+            "#,
+        )
+        .bind(order_group_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+
+    /// Lines of store orders placed within `[placed_from, placed_before)`,
+    /// oldest first, starting after the `(created_at, order_id, item_id)`
+    /// keyset cursor `after`; for accounting exports.
+    pub async fn list_export_lines_after(
+        &self,
+        store_id: Uuid,
+        placed_from: Option<DateTime<Utc>>,
+        placed_before: Option<DateTime<Utc>>,
+        after: Option<(DateTime<Utc>, Uuid, Uuid)>,
+        limit: i64,
+    ) -> Result<Vec<OrderExportLine>> {
+        let (after_created, after_order, after_item) = match after {
+            Some((created_at, order_id, item_id)) => {
+                (Some(created_at), Some(order_id), Some(item_id))
+            }
+            None => (None, None, None),
+        };
+        let lines = sqlx::query_as::<_, OrderExportLine>(
+            r#"
+            SELECT o.order_number, o.status, o.created_at, v.buyer_name, o.currency,
+                   p.sku, p.name AS product_name, i.quantity, i.unit_price,
+                   i.subtotal AS line_subtotal, o.subtotal AS order_subtotal, o.discount,
+                   o.shipping_cost, o.tax,
+                   (
+                       SELECT string_agg(t.jurisdiction, ';' ORDER BY t.jurisdiction)
+                       FROM order_tax_lines t
+                       WHERE t.order_id = o.id
+                   ) AS tax_jurisdiction,
+                   o.credit_applied, o.total_amount, o.id AS order_id, i.id AS item_id
+            FROM orders o
+            JOIN order_list_view v ON v.order_id = o.id
+            JOIN order_items i ON i.order_id = o.id
+            JOIN products p ON p.id = i.product_id
+            WHERE o.store_id = $1
+              AND ($2::timestamptz IS NULL OR o.created_at >= $2)
+              AND ($3::timestamptz IS NULL OR o.created_at < $3)
+              AND ($4::timestamptz IS NULL OR (o.created_at, o.id, i.id) > ($4, $5, $6))
+            ORDER BY o.created_at ASC, o.id ASC, i.id ASC
+            LIMIT $7
+            "#,
+        )
+        .bind(store_id)
+        .bind(placed_from)
+        .bind(placed_before)
+        .bind(after_created)
+        .bind(after_order)
+        .bind(after_item)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lines)
+    }
+
+    /// Fingerprints of the user's checkouts placed since `since`, with totals
+    /// taken before store credit so repeat submissions still match after the
+    /// first one consumed the balance.
+    pub async fn recent_checkout_fingerprints(
+        &self,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<CheckoutFingerprint>> {
+        let rows = sqlx::query_as::<_, (Uuid, Decimal, Uuid, i64)>(
+            r#"
+            WITH recent AS (
+                SELECT og.id, SUM(o.total_amount + o.credit_applied) AS gross_total
+                FROM order_groups og
+                JOIN orders o ON o.order_group_id = og.id
+                WHERE og.user_id = $1 AND og.created_at >= $2
+                GROUP BY og.id
+            )
+            SELECT r.id, r.gross_total, oi.product_id, SUM(oi.quantity) AS quantity
+            FROM recent r
+            JOIN orders o ON o.order_group_id = r.id
+            JOIN order_items oi ON oi.order_id = o.id
+            GROUP BY r.id, r.gross_total, oi.product_id
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut groups: HashMap<Uuid, CheckoutFingerprint> = HashMap::new();
+        for (group_id, total, product_id, quantity) in rows {
+            groups
+                .entry(group_id)
+                .or_insert_with(|| CheckoutFingerprint::new([], total))
+                .items
+                .insert(product_id, quantity as i32);
+        }
+
+        Ok(groups.into_values().collect())
+    }
+
+    pub async fn find_by_id(&self, order_id: Uuid) -> Result<Option<Order>> {
+        let order = sqlx::query_as::<_, Order>("SELECT * FROM orders WHERE id = $1")
+            .bind(order_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(order)
+    }
+
+    pub async fn list_items(&self, order_id: Uuid) -> Result<Vec<OrderItem>> {
+        let items = sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM order_items WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    pub async fn list_items_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+    ) -> Result<Vec<OrderItem>> {
+        let items = sqlx::query_as::<_, OrderItem>(
+            "SELECT * FROM order_items WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+        )
+        .bind(order_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Locks one of the store's orders so its shipments and status change
+    /// together.
+    pub async fn lock_store_order_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        order_id: Uuid,
+    ) -> Result<Option<Order>> {
+        let order = sqlx::query_as::<_, Order>(
+            "SELECT * FROM orders WHERE id = $1 AND store_id = $2 FOR UPDATE",
+        )
+        .bind(order_id)
+        .bind(store_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(order)
+    }
+
+    pub async fn list_item_details(&self, order_id: Uuid) -> Result<Vec<OrderItemDetail>> {
+        let items = sqlx::query_as::<_, OrderItemDetail>(
+            r#"
+            SELECT oi.*, p.name AS product_name
+            FROM order_items oi
+            JOIN products p ON p.id = oi.product_id
+            WHERE oi.order_id = $1
+            ORDER BY oi.created_at ASC, oi.id ASC
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_shipment_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        store_id: Uuid,
+        carrier: &str,
+        tracking_number: &str,
+        tracking_url: Option<&str>,
+        shipped_at: DateTime<Utc>,
+        created_by: Uuid,
+    ) -> Result<Shipment> {
+        let shipment = sqlx::query_as::<_, Shipment>(
+            r#"
+            INSERT INTO order_shipments (
+                order_id, store_id, carrier, tracking_number, tracking_url, shipped_at,
+                created_by
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(store_id)
+        .bind(carrier)
+        .bind(tracking_number)
+        .bind(tracking_url)
+        .bind(shipped_at)
+        .bind(created_by)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(shipment)
+    }
+
+    /// Adds order lines to a shipment, `(order_item_id, quantity)` each.
+    pub async fn create_shipment_items_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        shipment_id: Uuid,
+        lines: &[(Uuid, i32)],
+    ) -> Result<Vec<ShipmentItem>> {
+        let (item_ids, quantities): (Vec<Uuid>, Vec<i32>) = lines.iter().copied().unzip();
+        let items = sqlx::query_as::<_, ShipmentItem>(
+            r#"
+            INSERT INTO order_shipment_items (shipment_id, order_item_id, quantity)
+            SELECT $1, line.order_item_id, line.quantity
+            FROM UNNEST($2::uuid[], $3::int[]) AS line(order_item_id, quantity)
+            RETURNING *
+            "#,
+        )
+        .bind(shipment_id)
+        .bind(&item_ids)
+        .bind(&quantities)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(items)
+    }
+
+    /// Applies the changes to a shipment; delivering it stamps
+    /// `delivered_at`, which is cleared again if it goes back to Shipped.
+    pub async fn update_shipment_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        order_id: Uuid,
+        shipment_id: Uuid,
+        changes: &UpdateShipmentRequest,
+    ) -> Result<Option<Shipment>> {
+        let shipment = sqlx::query_as::<_, Shipment>(
+            r#"
+            UPDATE order_shipments
+            SET carrier = COALESCE($4, carrier),
+                tracking_number = COALESCE($5, tracking_number),
+                tracking_url = COALESCE($6, tracking_url),
+                shipped_at = COALESCE($7, shipped_at),
+                status = COALESCE($8, status),
+                delivered_at = CASE $8
+                    WHEN 'Delivered'::shipment_status THEN COALESCE(delivered_at, NOW())
+                    WHEN 'Shipped'::shipment_status THEN NULL
+                    ELSE delivered_at
+                END
+            WHERE id = $1 AND order_id = $2 AND store_id = $3
+            RETURNING *
+            "#,
+        )
+        .bind(shipment_id)
+        .bind(order_id)
+        .bind(store_id)
+        .bind(changes.carrier.as_deref())
+        .bind(changes.tracking_number.as_deref())
+        .bind(changes.tracking_url.as_deref())
+        .bind(changes.shipped_at)
+        .bind(changes.status)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(shipment)
+    }
+
+    /// The order's shipments, oldest first, with the lines each carries.
+    pub async fn list_shipments(&self, order_id: Uuid) -> Result<Vec<Shipment>> {
+        let shipments = sqlx::query_as::<_, Shipment>(
+            "SELECT * FROM order_shipments WHERE order_id = $1 ORDER BY shipped_at ASC, id ASC",
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+        let items = sqlx::query_as::<_, ShipmentItem>(SHIPMENT_ITEMS_FOR_ORDER)
+            .bind(order_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(with_shipment_items(shipments, items))
+    }
+
+    pub async fn list_shipments_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+    ) -> Result<Vec<Shipment>> {
+        let shipments = sqlx::query_as::<_, Shipment>(
+            "SELECT * FROM order_shipments WHERE order_id = $1 ORDER BY shipped_at ASC, id ASC",
+        )
+        .bind(order_id)
+        .fetch_all(&mut **tx)
+        .await?;
+        let items = sqlx::query_as::<_, ShipmentItem>(SHIPMENT_ITEMS_FOR_ORDER)
+            .bind(order_id)
+            .fetch_all(&mut **tx)
+            .await?;
+
+        Ok(with_shipment_items(shipments, items))
+    }
+
+    pub async fn has_shipments(&self, order_id: Uuid) -> Result<bool> {
+        let shipped = sqlx::query_scalar::<_, bool>(
+            "SELECT EXISTS (SELECT 1 FROM order_shipments WHERE order_id = $1)",
+        )
+        .bind(order_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(shipped)
+    }
+
+    pub async fn list_tax_lines(&self, order_id: Uuid) -> Result<Vec<OrderTaxLine>> {
+        let lines = sqlx::query_as::<_, OrderTaxLine>(
+            "SELECT * FROM order_tax_lines WHERE order_id = $1 ORDER BY created_at ASC, id ASC",
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(lines)
+    }
+
+    pub async fn list_for_group(&self, order_group_id: Uuid) -> Result<Vec<Order>> {
+        let orders = sqlx::query_as::<_, Order>(
+            "SELECT * FROM orders WHERE order_group_id = $1 ORDER BY created_at ASC, id ASC",
+        )
+        .bind(order_group_id)
+        .fetch_all(&self.pool)
+        .await?;
+
         Ok(orders)
     }
 
+    pub async fn find_group(&self, order_group_id: Uuid) -> Result<Option<OrderGroup>> {
+        let group = sqlx::query_as::<_, OrderGroup>("SELECT * FROM order_groups WHERE id = $1")
+            .bind(order_group_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(group)
+    }
+
     pub async fn update_status(&self, order_id: Uuid, status: OrderStatus) -> Result<Order> {
         let order =
             sqlx::query_as::<_, Order>("UPDATE orders SET status = $2 WHERE id = $1 RETURNING *")
@@ -148,6 +658,195 @@ impl OrderRepository {
         Ok(order)
     }
 
+    /// Moves an order to `to` only if it is still in `from` and belongs to
+    /// the store, so concurrent updates cannot skip a transition.
+    pub async fn transition_status_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        order_id: Uuid,
+        from: OrderStatus,
+        to: OrderStatus,
+    ) -> Result<Option<Order>> {
+        let order = sqlx::query_as::<_, Order>(
+            r#"
+            UPDATE orders SET status = $4
+            WHERE id = $1 AND store_id = $2 AND status = $3
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(store_id)
+        .bind(from)
+        .bind(to)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(order)
+    }
+
+    /// Cancels an order still in `from`, recording who cancelled it and
+    /// why.
+    pub async fn cancel_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        from: OrderStatus,
+        cancelled_by: Uuid,
+        reason: Option<&str>,
+    ) -> Result<Option<Order>> {
+        let order = sqlx::query_as::<_, Order>(
+            r#"
+            UPDATE orders
+            SET status = 'Cancelled', cancelled_at = NOW(), cancelled_by = $3,
+                cancellation_reason = $4
+            WHERE id = $1 AND status = $2
+              AND NOT EXISTS (SELECT 1 FROM order_shipments s WHERE s.order_id = orders.id)
+            RETURNING *
+            "#,
+        )
+        .bind(order_id)
+        .bind(from)
+        .bind(cancelled_by)
+        .bind(reason)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(order)
+    }
+
+    /// Resets the group total to the sum of its orders that are still live.
+    pub async fn recompute_group_total_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+    ) -> Result<Decimal> {
+        let total = sqlx::query_scalar::<_, Decimal>(
+            r#"
+            UPDATE order_groups g
+            SET total_amount = (
+                SELECT COALESCE(SUM(o.total_amount), 0)
+                FROM orders o
+                WHERE o.order_group_id = g.id AND o.status <> 'Cancelled'
+            )
+            WHERE g.id = $1
+            RETURNING total_amount
+            "#,
+        )
+        .bind(order_group_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(total)
+    }
+
+    /// Groups still awaiting payment `max_age` after checkout, oldest first.
+    pub async fn list_stale_unpaid_groups(
+        &self,
+        max_age: chrono::Duration,
+        limit: i64,
+    ) -> Result<Vec<OrderGroup>> {
+        let groups = sqlx::query_as::<_, OrderGroup>(
+            r#"
+            SELECT * FROM order_groups
+            WHERE payment_status = 'Pending'
+              AND created_at < NOW() - make_interval(secs => $1)
+            ORDER BY created_at ASC
+            LIMIT $2
+            "#,
+        )
+        .bind(max_age.num_seconds() as f64)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(groups)
+    }
+
+    /// Locks a group that is still awaiting payment; `None` once it was
+    /// paid or expired concurrently.
+    pub async fn lock_unpaid_group_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+    ) -> Result<Option<OrderGroup>> {
+        let group = sqlx::query_as::<_, OrderGroup>(
+            r#"
+            SELECT * FROM order_groups
+            WHERE id = $1 AND payment_status = 'Pending'
+            FOR UPDATE
+            "#,
+        )
+        .bind(order_group_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(group)
+    }
+
+    /// Cancels the group's orders that can still be cancelled and were not
+    /// paid for, without a cancelling user.
+    pub async fn cancel_unpaid_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+        reason: &str,
+    ) -> Result<Vec<Order>> {
+        let orders = sqlx::query_as::<_, Order>(
+            r#"
+            UPDATE orders o
+            SET status = 'Cancelled', cancelled_at = NOW(), cancellation_reason = $2
+            WHERE o.order_group_id = $1
+              AND o.status IN ('Pending', 'Confirmed', 'Processing')
+              AND NOT EXISTS (
+                  SELECT 1 FROM payments p
+                  WHERE p.order_id = o.id AND p.status = 'Captured'
+              )
+              AND NOT EXISTS (SELECT 1 FROM order_shipments s WHERE s.order_id = o.id)
+            RETURNING *
+            "#,
+        )
+        .bind(order_group_id)
+        .bind(reason)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(orders)
+    }
+
+    pub async fn mark_payment_status_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+        status: PaymentStatus,
+    ) -> Result<PgQueryResult> {
+        let res = sqlx::query("UPDATE order_groups SET payment_status = $2 WHERE id = $1")
+            .bind(order_group_id)
+            .bind(status)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(res)
+    }
+
+    /// Sets the group's payment status, returning whether it changed.
+    pub async fn change_payment_status_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+        status: PaymentStatus,
+    ) -> Result<bool> {
+        let res = sqlx::query(
+            "UPDATE order_groups SET payment_status = $2 WHERE id = $1 AND payment_status <> $2",
+        )
+        .bind(order_group_id)
+        .bind(status)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
     pub async fn mark_payment_status(
         &self,
         order_group_id: Uuid,
@@ -162,3 +861,21 @@ impl OrderRepository {
         Ok(res)
     }
 }
+
+const SHIPMENT_ITEMS_FOR_ORDER: &str = r#"
+    SELECT si.*
+    FROM order_shipment_items si
+    JOIN order_shipments s ON s.id = si.shipment_id
+    WHERE s.order_id = $1
+"#;
+
+fn with_shipment_items(mut shipments: Vec<Shipment>, items: Vec<ShipmentItem>) -> Vec<Shipment> {
+    let mut by_shipment: HashMap<Uuid, Vec<ShipmentItem>> = HashMap::new();
+    for item in items {
+        by_shipment.entry(item.shipment_id).or_default().push(item);
+    }
+    for shipment in &mut shipments {
+        shipment.items = by_shipment.remove(&shipment.id).unwrap_or_default();
+    }
+    shipments
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::{AppError, Result},
+    models::domain_event::{DomainEvent, OutboxEvent},
+};
+
+#[derive(Clone)]
+pub struct OutboxRepository {
+    pool: PgPool,
+}
+
+impl OutboxRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Records the event as part of the transaction, so it is published if
+    /// and only if the transaction commits.
+    pub async fn record_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event: &DomainEvent,
+    ) -> Result<Uuid> {
+        let payload = serde_json::to_value(event).map_err(|err| AppError::Internal(err.into()))?;
+        let id = sqlx::query_scalar::<_, Uuid>(
+            r#"
+            INSERT INTO outbox_events (event_type, payload)
+            VALUES ($1, $2)
+            RETURNING id
+            "#,
+        )
+        .bind(event.event_type())
+        .bind(payload)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Locks up to `limit` unpublished events, oldest first, for the rest of
+    /// the transaction. Events locked by another relay are skipped.
+    pub async fn lock_unpublished_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        limit: i64,
+    ) -> Result<Vec<OutboxEvent>> {
+        let events = sqlx::query_as::<_, OutboxEvent>(
+            r#"
+            SELECT * FROM outbox_events
+            WHERE published_at IS NULL
+            ORDER BY created_at ASC
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(events)
+    }
+
+    pub async fn mark_published_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        event_ids: &[Uuid],
+    ) -> Result<()> {
+        sqlx::query("UPDATE outbox_events SET published_at = NOW() WHERE id = ANY($1)")
+            .bind(event_ids)
+            .execute(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_published_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM outbox_events WHERE published_at < $1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
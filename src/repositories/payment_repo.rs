@@ -0,0 +1,208 @@
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::payment::{Payment, PaymentAttemptStatus, PaymentProviderKind},
+};
+
+#[derive(Clone)]
+pub struct PaymentRepository {
+    pool: PgPool,
+}
+
+impl PaymentRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        order_group_id: Uuid,
+        order_id: Uuid,
+        store_id: Uuid,
+        provider: PaymentProviderKind,
+        amount: Decimal,
+        currency: &str,
+    ) -> Result<Payment> {
+        let payment = sqlx::query_as::<_, Payment>(
+            r#"
+            INSERT INTO payments (order_group_id, order_id, store_id, provider, amount, currency)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(order_group_id)
+        .bind(order_id)
+        .bind(store_id)
+        .bind(provider)
+        .bind(amount)
+        .bind(currency)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(payment)
+    }
+
+    /// Records the provider's payment for an attempt.
+    pub async fn record_intent(
+        &self,
+        payment_id: Uuid,
+        reference: &str,
+        status: PaymentAttemptStatus,
+        approval_url: Option<&str>,
+    ) -> Result<Payment> {
+        let payment = sqlx::query_as::<_, Payment>(
+            r#"
+            UPDATE payments
+            SET reference = $2, status = $3, approval_url = $4
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(payment_id)
+        .bind(reference)
+        .bind(status)
+        .bind(approval_url)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(payment)
+    }
+
+    pub async fn set_status(
+        &self,
+        payment_id: Uuid,
+        status: PaymentAttemptStatus,
+        failure_reason: Option<&str>,
+    ) -> Result<Payment> {
+        let payment = sqlx::query_as::<_, Payment>(
+            r#"
+            UPDATE payments
+            SET status = $2, failure_reason = COALESCE($3, failure_reason)
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(payment_id)
+        .bind(status)
+        .bind(failure_reason)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(payment)
+    }
+
+    pub async fn find_by_reference(
+        &self,
+        provider: PaymentProviderKind,
+        reference: &str,
+    ) -> Result<Option<Payment>> {
+        let payment = sqlx::query_as::<_, Payment>(
+            "SELECT * FROM payments WHERE provider = $1 AND reference = $2",
+        )
+        .bind(provider)
+        .bind(reference)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(payment)
+    }
+
+    /// Fails the group's attempts that are still open, e.g. when the group
+    /// expires unpaid.
+    pub async fn fail_open_for_group_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+        reason: &str,
+    ) -> Result<u64> {
+        let res = sqlx::query(
+            r#"
+            UPDATE payments SET status = 'Failed', failure_reason = $2
+            WHERE order_group_id = $1 AND status IN ('Pending', 'Authorized')
+            "#,
+        )
+        .bind(order_group_id)
+        .bind(reason)
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+
+    /// Whether any of the group's live orders has been paid for.
+    pub async fn any_captured_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+    ) -> Result<bool> {
+        let captured = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM payments p
+                JOIN orders o ON o.id = p.order_id
+                WHERE p.order_group_id = $1 AND p.status = 'Captured'
+                  AND o.status <> 'Cancelled'
+            )
+            "#,
+        )
+        .bind(order_group_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(captured)
+    }
+
+    /// The most recent attempt for each of the group's orders.
+    pub async fn latest_for_group(&self, order_group_id: Uuid) -> Result<Vec<Payment>> {
+        let payments = sqlx::query_as::<_, Payment>(
+            r#"
+            SELECT DISTINCT ON (order_id) *
+            FROM payments
+            WHERE order_group_id = $1
+            ORDER BY order_id, created_at DESC, id DESC
+            "#,
+        )
+        .bind(order_group_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(payments)
+    }
+
+    /// Whether the order's payment was captured and not refunded. Locks
+    /// the order's payments so a capture cannot slip in before the caller
+    /// commits.
+    pub async fn captured_for_order_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+    ) -> Result<bool> {
+        let statuses = sqlx::query_scalar::<_, PaymentAttemptStatus>(
+            "SELECT status FROM payments WHERE order_id = $1 FOR UPDATE",
+        )
+        .bind(order_id)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(statuses.contains(&PaymentAttemptStatus::Captured))
+    }
+
+    pub async fn latest_for_order(&self, order_id: Uuid) -> Result<Option<Payment>> {
+        let payment = sqlx::query_as::<_, Payment>(
+            r#"
+            SELECT * FROM payments
+            WHERE order_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(payment)
+    }
+}
@@ -0,0 +1,41 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{error::Result, models::permission::PermissionAuditEntry};
+
+/// Reads the permission audit log. Entries are written by the member,
+/// grant and role queries themselves, in the same statement as the change.
+#[derive(Clone)]
+pub struct PermissionAuditRepository {
+    pool: PgPool,
+}
+
+impl PermissionAuditRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// The store's entries, newest first.
+    pub async fn list_for_store(
+        &self,
+        store_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<PermissionAuditEntry>> {
+        let entries = sqlx::query_as::<_, PermissionAuditEntry>(
+            r#"
+            SELECT * FROM permission_audit_log
+            WHERE store_id = $1
+            ORDER BY created_at DESC, id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(store_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{error::Result, models::product::ProductFile};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ProductFileRepository {
+    pool: PgPool,
+}
+
+impl ProductFileRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create(
+        &self,
+        product_id: Uuid,
+        storage_key: &str,
+        file_name: &str,
+        content_type: &str,
+        size_bytes: i64,
+    ) -> Result<ProductFile> {
+        let file = sqlx::query_as::<_, ProductFile>(
+            r#"
+            INSERT INTO product_files (product_id, storage_key, file_name, content_type, size_bytes)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(product_id)
+        .bind(storage_key)
+        .bind(file_name)
+        .bind(content_type)
+        .bind(size_bytes)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(file)
+    }
+
+    pub async fn find_by_id(&self, file_id: Uuid) -> Result<Option<ProductFile>> {
+        let file = sqlx::query_as::<_, ProductFile>("SELECT * FROM product_files WHERE id = $1")
+            .bind(file_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(file)
+    }
+
+    pub async fn list_for_product(&self, product_id: Uuid) -> Result<Vec<ProductFile>> {
+        let files = sqlx::query_as::<_, ProductFile>(
+            "SELECT * FROM product_files WHERE product_id = $1 ORDER BY created_at ASC, id ASC",
+        )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(files)
+    }
+
+    /// Files of every product bought in the order.
+    pub async fn list_for_order(&self, order_id: Uuid) -> Result<Vec<ProductFile>> {
+        let files = sqlx::query_as::<_, ProductFile>(
+            r#"
+            SELECT DISTINCT f.*
+            FROM order_items oi
+            JOIN product_files f ON f.product_id = oi.product_id
+            WHERE oi.order_id = $1
+            ORDER BY f.created_at ASC, f.id ASC
+            "#,
+        )
+        .bind(order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(files)
+    }
+
+    pub async fn delete(&self, file_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM product_files WHERE id = $1")
+            .bind(file_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
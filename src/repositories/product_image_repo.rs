@@ -0,0 +1,144 @@
+use crate::{error::Result, models::product::ProductImage};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ProductImageRepository {
+    pool: PgPool,
+}
+
+impl ProductImageRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Appends an image to the end of the product's gallery.
+    pub async fn create(
+        &self,
+        product_id: Uuid,
+        storage_key: &str,
+        url: &str,
+        content_type: &str,
+        size_bytes: i64,
+    ) -> Result<ProductImage> {
+        let image = sqlx::query_as::<_, ProductImage>(
+            r#"
+            INSERT INTO product_images (product_id, storage_key, url, content_type, size_bytes, position)
+            VALUES (
+                $1, $2, $3, $4, $5,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM product_images WHERE product_id = $1)
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(product_id)
+        .bind(storage_key)
+        .bind(url)
+        .bind(content_type)
+        .bind(size_bytes)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(image)
+    }
+
+    pub async fn find_by_id(&self, image_id: Uuid) -> Result<Option<ProductImage>> {
+        let image = sqlx::query_as::<_, ProductImage>("SELECT * FROM product_images WHERE id = $1")
+            .bind(image_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(image)
+    }
+
+    pub async fn count_for_product(&self, product_id: Uuid) -> Result<i64> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM product_images WHERE product_id = $1")
+                .bind(product_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count)
+    }
+
+    pub async fn list_for_product(&self, product_id: Uuid) -> Result<Vec<ProductImage>> {
+        let images = sqlx::query_as::<_, ProductImage>(
+            r#"
+            SELECT * FROM product_images
+            WHERE product_id = $1
+            ORDER BY position ASC
+            "#,
+        )
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(images)
+    }
+
+    pub async fn list_for_products(&self, product_ids: &[Uuid]) -> Result<Vec<ProductImage>> {
+        let images = sqlx::query_as::<_, ProductImage>(
+            r#"
+            SELECT * FROM product_images
+            WHERE product_id = ANY($1)
+            ORDER BY product_id, position ASC
+            "#,
+        )
+        .bind(product_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(images)
+    }
+
+    /// Rewrites gallery positions to follow `image_ids` and returns the
+    /// reordered gallery. Callers must pass every image of the product.
+    pub async fn reorder(&self, product_id: Uuid, image_ids: &[Uuid]) -> Result<Vec<ProductImage>> {
+        sqlx::query(
+            r#"
+            UPDATE product_images pi
+            SET position = ordered.idx - 1
+            FROM UNNEST($2::uuid[]) WITH ORDINALITY AS ordered(id, idx)
+            WHERE pi.id = ordered.id AND pi.product_id = $1
+            "#,
+        )
+        .bind(product_id)
+        .bind(image_ids)
+        .execute(&self.pool)
+        .await?;
+
+        self.list_for_product(product_id).await
+    }
+
+    pub async fn delete(&self, image_id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM product_images WHERE id = $1")
+            .bind(image_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Points the product thumbnail at the first gallery image, or clears it
+    /// when the gallery is empty.
+    pub async fn sync_thumbnail(&self, product_id: Uuid) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE products
+            SET thumbnail_url = (
+                SELECT url FROM product_images
+                WHERE product_id = $1
+                ORDER BY position ASC
+                LIMIT 1
+            ),
+            updated_at = NOW()
+            WHERE id = $1
+            "#,
+        )
+        .bind(product_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
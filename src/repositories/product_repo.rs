@@ -1,19 +1,37 @@
 use crate::{
+    cache::{keys, CacheHandle},
     error::{AppError, Result},
-    models::product::Product,
+    models::product::{
+        CreateProductRequest, Product, ProductSuggestion, ProductType, UpdateProductRequest,
+    },
 };
 use rust_decimal::Decimal;
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
+/// Name Postgres gave the `UNIQUE (store_id, sku)` constraint on products.
+const STORE_SKU_CONSTRAINT: &str = "products_store_id_sku_key";
+
 #[derive(Clone)]
 pub struct ProductRepository {
     pool: PgPool,
+    cache: Option<CacheHandle>,
 }
 
 impl ProductRepository {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self { pool, cache: None }
+    }
+
+    /// Serves store listings from `cache` and drops a store's listings when
+    /// this repository changes its catalog.
+    pub fn with_cache(mut self, cache: CacheHandle) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -26,13 +44,25 @@ impl ProductRepository {
         price: Decimal,
         stock_quantity: i32,
         category: Option<&str>,
+        thumbnail_url: Option<&str>,
+        product_type: ProductType,
+        weight_grams: i32,
     ) -> Result<Product> {
         let product = sqlx::query_as::<_, Product>(
             r#"
-            INSERT INTO products (
-                store_id, sku, name, description, price, stock_quantity, category
-            ) VALUES ($1, $2, $3, $4, $5, $6, $7)
-            RETURNING *
+            WITH created AS (
+                INSERT INTO products (
+                    store_id, sku, name, description, price, stock_quantity, category,
+                    thumbnail_url, product_type, weight_grams
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING *
+            ), opening AS (
+                INSERT INTO inventory_movements (product_id, store_id, kind, delta, stock_after, reason)
+                SELECT id, store_id, 'Adjustment', stock_quantity, stock_quantity, 'Initial stock'
+                FROM created
+                WHERE stock_quantity <> 0
+            )
+            SELECT * FROM created
             "#,
         )
         .bind(store_id)
@@ -42,9 +72,40 @@ impl ProductRepository {
         .bind(price)
         .bind(stock_quantity)
         .bind(category)
+        .bind(thumbnail_url)
+        .bind(product_type)
+        .bind(weight_grams)
         .fetch_one(&self.pool)
-        .await?;
+        .await
+        .map_err(map_sku_conflict)?;
+
+        self.invalidate_store_listing(product.store_id).await;
+        Ok(product)
+    }
+
+    /// Inserts a copy of the product under a new SKU. The copy starts with no
+    /// stock and inactive so it stays hidden until the merchant reviews it.
+    pub async fn duplicate(&self, product_id: Uuid, sku: &str) -> Result<Product> {
+        let product = sqlx::query_as::<_, Product>(
+            r#"
+            INSERT INTO products (
+                store_id, sku, name, description, price, stock_quantity, category,
+                thumbnail_url, product_type, weight_grams, is_active
+            )
+            SELECT store_id, $2, name, description, price, 0, category,
+                   thumbnail_url, product_type, weight_grams, false
+            FROM products
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(product_id)
+        .bind(sku)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(map_sku_conflict)?;
 
+        self.invalidate_store_listing(product.store_id).await;
         Ok(product)
     }
 
@@ -57,16 +118,186 @@ impl ProductRepository {
         Ok(product)
     }
 
+    pub async fn find_by_sku(&self, store_id: Uuid, sku: &str) -> Result<Option<Product>> {
+        let product =
+            sqlx::query_as::<_, Product>("SELECT * FROM products WHERE store_id = $1 AND sku = $2")
+                .bind(store_id)
+                .bind(sku)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(product)
+    }
+
+    /// Live products whose name starts with `prefix`, or has a word that
+    /// does, in stores the viewer can see. `prefix` must be lowercase with
+    /// LIKE wildcards escaped.
+    pub async fn suggest(
+        &self,
+        prefix: &str,
+        viewer_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<ProductSuggestion>> {
+        let suggestions = sqlx::query_as::<_, ProductSuggestion>(
+            r#"
+            SELECT p.id, p.store_id, p.name, p.price, p.thumbnail_url
+            FROM products p
+            JOIN stores s ON s.id = p.store_id
+            WHERE p.is_active = true
+              AND p.archived_at IS NULL
+              AND (lower(p.name) LIKE $1 || '%' OR lower(p.name) LIKE '% ' || $1 || '%')
+              AND s.status = 'Active'
+              AND (s.is_private = false OR p.store_id = ANY(
+                  SELECT m.store_id FROM store_members m
+                  WHERE m.user_id = $2 AND m.is_active = true
+                  UNION
+                  SELECT g.store_id FROM store_access_grants g
+                  WHERE g.user_id = $2
+                    AND g.is_revoked = false
+                    AND (g.expires_at IS NULL OR g.expires_at > NOW())
+              ))
+            ORDER BY lower(p.name) LIKE $1 || '%' DESC, length(p.name) ASC, p.name ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(prefix)
+        .bind(viewer_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(suggestions)
+    }
+
+    /// Categories starting with `prefix`, most used first. Same visibility
+    /// and escaping rules as `suggest`.
+    pub async fn suggest_categories(
+        &self,
+        prefix: &str,
+        viewer_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<String>> {
+        let categories = sqlx::query_scalar::<_, String>(
+            r#"
+            SELECT p.category
+            FROM products p
+            JOIN stores s ON s.id = p.store_id
+            WHERE p.category IS NOT NULL
+              AND p.is_active = true
+              AND p.archived_at IS NULL
+              AND lower(p.category) LIKE $1 || '%'
+              AND s.status = 'Active'
+              AND (s.is_private = false OR p.store_id = ANY(
+                  SELECT m.store_id FROM store_members m
+                  WHERE m.user_id = $2 AND m.is_active = true
+                  UNION
+                  SELECT g.store_id FROM store_access_grants g
+                  WHERE g.user_id = $2
+                    AND g.is_revoked = false
+                    AND (g.expires_at IS NULL OR g.expires_at > NOW())
+              ))
+            GROUP BY p.category
+            ORDER BY COUNT(*) DESC, p.category ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(prefix)
+        .bind(viewer_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(categories)
+    }
+
+    pub async fn count_for_store(&self, store_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM products WHERE store_id = $1 AND archived_at IS NULL",
+        )
+        .bind(store_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Products of the store with any of the given SKUs, archived ones
+    /// included.
+    pub async fn find_by_skus(&self, store_id: Uuid, skus: &[&str]) -> Result<Vec<Product>> {
+        let products = sqlx::query_as::<_, Product>(
+            "SELECT * FROM products WHERE store_id = $1 AND sku = ANY($2)",
+        )
+        .bind(store_id)
+        .bind(skus)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(products)
+    }
+
+    /// Sets the price of each listed SKU in one statement.
+    pub async fn update_prices(
+        &self,
+        store_id: Uuid,
+        skus: &[&str],
+        prices: &[Decimal],
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            UPDATE products p
+            SET price = r.price
+            FROM UNNEST($2::varchar[], $3::numeric[]) AS r(sku, price)
+            WHERE p.store_id = $1 AND p.sku = r.sku
+            "#,
+        )
+        .bind(store_id)
+        .bind(skus)
+        .bind(prices)
+        .execute(&self.pool)
+        .await?;
+
+        self.invalidate_store_listing(store_id).await;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn find_by_ids(&self, product_ids: &[Uuid]) -> Result<Vec<Product>> {
+        let products = sqlx::query_as::<_, Product>("SELECT * FROM products WHERE id = ANY($1)")
+            .bind(product_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(products)
+    }
+
+    /// A page of the store's listing. Pages are cached until this repository
+    /// changes the store's catalog. Stock moved by checkout or inventory
+    /// adjustments and thumbnails synced from the gallery may lag by up to
+    /// the cache TTL, which is fine for display because checkout re-reads
+    /// stock under lock.
     pub async fn list_by_store(
         &self,
         store_id: Uuid,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<Product>> {
+        let key = match &self.cache {
+            Some(cache) => {
+                let generation = cache
+                    .generation(&keys::store_products_scope(store_id))
+                    .await;
+                let key = keys::store_products_page(&generation, store_id, limit, offset);
+                if let Some(items) = cache.get_json::<Vec<Product>>(&key).await {
+                    return Ok(items);
+                }
+                Some(key)
+            }
+            None => None,
+        };
+
         let items = sqlx::query_as::<_, Product>(
             r#"
             SELECT * FROM products
-            WHERE store_id = $1
+            WHERE store_id = $1 AND archived_at IS NULL
             ORDER BY created_at DESC
             LIMIT $2 OFFSET $3
             "#,
@@ -77,92 +308,303 @@ impl ProductRepository {
         .fetch_all(&self.pool)
         .await?;
 
+        if let (Some(cache), Some(key)) = (&self.cache, key) {
+            cache.set_json(&key, &items).await;
+        }
+        Ok(items)
+    }
+
+    /// Keyset page of a store's whole catalog, inactive products included,
+    /// ordered by id. Pass the last id of the previous page as `after`.
+    pub async fn list_page_after(
+        &self,
+        store_id: Uuid,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<Product>> {
+        let items = sqlx::query_as::<_, Product>(
+            r#"
+            SELECT * FROM products
+            WHERE store_id = $1 AND archived_at IS NULL AND ($2::uuid IS NULL OR id > $2)
+            ORDER BY id ASC
+            LIMIT $3
+            "#,
+        )
+        .bind(store_id)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
         Ok(items)
     }
 
-    pub async fn update_stock(&self, product_id: Uuid, new_stock: i32) -> Result<Product> {
+    /// Drops every cached page of the store's listing, for callers that
+    /// change products inside their own transaction, once it has committed.
+    pub async fn invalidate_store_listing(&self, store_id: Uuid) {
+        if let Some(cache) = &self.cache {
+            cache
+                .invalidate_scope(&keys::store_products_scope(store_id))
+                .await;
+        }
+    }
+
+    /// Inserts or updates products by SKU within the store. `prices` holds
+    /// the already-converted price of each row. Returns how many rows were
+    /// newly created; the rest updated existing products.
+    pub async fn upsert_batch_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        rows: &[CreateProductRequest],
+        prices: &[Decimal],
+    ) -> Result<usize> {
+        let skus: Vec<&str> = rows.iter().map(|row| row.sku.as_str()).collect();
+        let names: Vec<&str> = rows.iter().map(|row| row.name.as_str()).collect();
+        let descriptions: Vec<Option<&str>> =
+            rows.iter().map(|row| row.description.as_deref()).collect();
+        let stock: Vec<i32> = rows.iter().map(|row| row.stock_quantity).collect();
+        let categories: Vec<Option<&str>> =
+            rows.iter().map(|row| row.category.as_deref()).collect();
+        let thumbnails: Vec<Option<&str>> = rows
+            .iter()
+            .map(|row| row.thumbnail_url.as_deref())
+            .collect();
+
+        // Stock changes are written to the inventory ledger in the same
+        // statement; `previous` sees the rows as they were before the upsert.
+        let inserted: Vec<bool> = sqlx::query_scalar(
+            r#"
+            WITH previous AS (
+                SELECT id, stock_quantity FROM products
+                WHERE store_id = $1 AND sku = ANY($2)
+            ), upserted AS (
+                INSERT INTO products (
+                    store_id, sku, name, description, price, stock_quantity, category, thumbnail_url
+                )
+                SELECT $1, r.sku, r.name, r.description, r.price, r.stock_quantity, r.category, r.thumbnail_url
+                FROM UNNEST(
+                    $2::varchar[], $3::varchar[], $4::text[], $5::numeric[], $6::int[], $7::varchar[], $8::text[]
+                ) AS r(sku, name, description, price, stock_quantity, category, thumbnail_url)
+                ON CONFLICT (store_id, sku) DO UPDATE
+                SET name = EXCLUDED.name,
+                    description = EXCLUDED.description,
+                    price = EXCLUDED.price,
+                    stock_quantity = EXCLUDED.stock_quantity,
+                    category = EXCLUDED.category,
+                    thumbnail_url = EXCLUDED.thumbnail_url
+                RETURNING id, store_id, stock_quantity, (xmax = 0) AS inserted
+            ), moved AS (
+                INSERT INTO inventory_movements (product_id, store_id, kind, delta, stock_after, reason)
+                SELECT u.id, u.store_id, 'Adjustment',
+                       u.stock_quantity - COALESCE(p.stock_quantity, 0), u.stock_quantity, 'CSV import'
+                FROM upserted u
+                LEFT JOIN previous p ON p.id = u.id
+                WHERE u.stock_quantity <> COALESCE(p.stock_quantity, 0)
+            )
+            SELECT inserted FROM upserted
+            "#,
+        )
+        .bind(store_id)
+        .bind(&skus)
+        .bind(&names)
+        .bind(&descriptions)
+        .bind(prices)
+        .bind(&stock)
+        .bind(&categories)
+        .bind(&thumbnails)
+        .fetch_all(&mut **tx)
+        .await?;
+
+        Ok(inserted.into_iter().filter(|created| *created).count())
+    }
+
+    /// Archives the product and drops it from every cart in one transaction
+    /// so it can no longer be checked out.
+    pub async fn archive(&self, product_id: Uuid) -> Result<Product> {
+        let mut tx = self.pool.begin().await?;
+
         let product = sqlx::query_as::<_, Product>(
             r#"
-            UPDATE products SET stock_quantity = $2
+            UPDATE products SET archived_at = COALESCE(archived_at, NOW())
             WHERE id = $1
             RETURNING *
             "#,
         )
         .bind(product_id)
-        .bind(new_stock)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM cart_items WHERE product_id = $1")
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM stock_reservations WHERE product_id = $1")
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        self.invalidate_store_listing(product.store_id).await;
+        Ok(product)
+    }
+
+    pub async fn unarchive(&self, product_id: Uuid) -> Result<Product> {
+        let product = sqlx::query_as::<_, Product>(
+            "UPDATE products SET archived_at = NULL WHERE id = $1 RETURNING *",
+        )
+        .bind(product_id)
         .fetch_one(&self.pool)
         .await?;
 
+        self.invalidate_store_listing(product.store_id).await;
         Ok(product)
     }
 
-    pub async fn save(&self, product: &Product) -> Result<Product> {
+    /// Writes only the fields present in `changes`, so an edit never puts
+    /// back a stock level checkouts have moved on from. A stock change is
+    /// recorded as an adjustment by `actor_id` in the inventory ledger.
+    pub async fn update(
+        &self,
+        product_id: Uuid,
+        changes: &UpdateProductRequest,
+        price: Option<Decimal>,
+        actor_id: Uuid,
+    ) -> Result<Option<Product>> {
         let updated = sqlx::query_as::<_, Product>(
             r#"
-            UPDATE products
-            SET name = $2,
-                description = $3,
-                price = $4,
-                stock_quantity = $5,
-                category = $6,
-                is_active = $7
-            WHERE id = $1
-            RETURNING *
+            WITH previous AS (
+                SELECT stock_quantity FROM products WHERE id = $1 FOR UPDATE
+            ), updated AS (
+                UPDATE products
+                SET name = COALESCE($2, name),
+                    description = COALESCE($3, description),
+                    price = COALESCE($4, price),
+                    stock_quantity = COALESCE($5, stock_quantity),
+                    category = COALESCE($6, category),
+                    thumbnail_url = COALESCE($7, thumbnail_url),
+                    is_active = COALESCE($8, is_active),
+                    product_type = COALESCE($9, product_type),
+                    weight_grams = COALESCE($10, weight_grams)
+                -- Referencing `previous` makes it lock and read the row first.
+                WHERE id = $1 AND EXISTS (SELECT 1 FROM previous)
+                RETURNING *
+            ), moved AS (
+                INSERT INTO inventory_movements (
+                    product_id, store_id, kind, delta, stock_after, actor_id, reason
+                )
+                SELECT u.id, u.store_id, 'Adjustment', u.stock_quantity - p.stock_quantity,
+                       u.stock_quantity, $11, 'Product edited'
+                FROM updated u, previous p
+                WHERE u.stock_quantity <> p.stock_quantity
+            )
+            SELECT * FROM updated
             "#,
         )
-        .bind(product.id)
-        .bind(&product.name)
-        .bind(&product.description)
-        .bind(product.price)
-        .bind(product.stock_quantity)
-        .bind(&product.category)
-        .bind(product.is_active)
-        .fetch_one(&self.pool)
+        .bind(product_id)
+        .bind(&changes.name)
+        .bind(&changes.description)
+        .bind(price)
+        .bind(changes.stock_quantity)
+        .bind(&changes.category)
+        .bind(&changes.thumbnail_url)
+        .bind(changes.is_active)
+        .bind(changes.product_type)
+        .bind(changes.weight_grams)
+        .bind(actor_id)
+        .fetch_optional(&self.pool)
         .await?;
 
+        if let Some(product) = &updated {
+            self.invalidate_store_listing(product.store_id).await;
+        }
         Ok(updated)
     }
 
-    pub async fn decrement_stock(&self, product_id: Uuid, qty: i32) -> Result<()> {
-        let result = sqlx::query(
+    /// Locks the products' rows for the rest of the transaction. Rows are
+    /// locked in product id order, so checkouts that share products queue
+    /// behind each other instead of deadlocking.
+    pub async fn lock_for_sale_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        product_ids: &[Uuid],
+    ) -> Result<()> {
+        let locked = sqlx::query_scalar::<_, Uuid>(
             r#"
-            UPDATE products SET stock_quantity = stock_quantity - $2
-            WHERE id = $1 AND stock_quantity >= $2
+            SELECT id FROM products
+            WHERE id = ANY($1)
+            ORDER BY id
+            FOR UPDATE
             "#,
         )
-        .bind(product_id)
-        .bind(qty)
-        .execute(&self.pool)
+        .bind(product_ids)
+        .fetch_all(&mut **tx)
         .await?;
-
-        if result.rows_affected() == 0 {
-            return Err(AppError::Conflict("Insufficient stock".into()));
+        if locked.len() < product_ids.len() {
+            return Err(AppError::NotFound("Product not found".into()));
         }
 
         Ok(())
     }
 
+    /// Takes `qty` units out of stock for an order line and records the sale
+    /// in the inventory ledger.
+    /// Returns the stock left after the sale. Callers lock the row first
+    /// with `lock_for_sale_in_tx`.
     pub async fn decrement_stock_in_tx(
         &self,
         tx: &mut Transaction<'_, Postgres>,
         product_id: Uuid,
         qty: i32,
-    ) -> Result<()> {
-        let result = sqlx::query(
+        buyer_id: Uuid,
+        order_id: Uuid,
+    ) -> Result<i32> {
+        sqlx::query_scalar::<_, i32>(
             r#"
-            UPDATE products SET stock_quantity = stock_quantity - $2
-            WHERE id = $1 AND stock_quantity >= $2
+            WITH sold AS (
+                UPDATE products SET stock_quantity = stock_quantity - $2
+                WHERE id = $1 AND stock_quantity >= $2
+                RETURNING id, store_id, stock_quantity
+            )
+            INSERT INTO inventory_movements (
+                product_id, store_id, kind, delta, stock_after, actor_id, order_id
+            )
+            SELECT id, store_id, 'Sale', -$2, stock_quantity, $3, $4
+            FROM sold
+            RETURNING stock_after
             "#,
         )
         .bind(product_id)
         .bind(qty)
+        .bind(buyer_id)
+        .bind(order_id)
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| AppError::Conflict("Insufficient stock".into()))
+    }
+
+    pub async fn deactivate_for_store_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE products SET is_active = false WHERE store_id = $1 AND is_active = true",
+        )
+        .bind(store_id)
         .execute(&mut **tx)
         .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(AppError::Conflict("Insufficient stock".into()));
-        }
+        Ok(result.rows_affected())
+    }
+}
 
-        Ok(())
+/// SKUs are unique per store only; the same SKU may exist in other stores.
+fn map_sku_conflict(err: sqlx::Error) -> AppError {
+    match &err {
+        sqlx::Error::Database(db) if db.constraint() == Some(STORE_SKU_CONSTRAINT) => {
+            AppError::Conflict("SKU already exists in this store".into())
+        }
+        _ => err.into(),
     }
 }
@@ -0,0 +1,95 @@
+use crate::{error::Result, models::product::RecentlyViewedProduct};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct RecentlyViewedRepository {
+    pool: PgPool,
+}
+
+impl RecentlyViewedRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Moves the product to the front of the user's history and drops
+    /// entries beyond the newest `keep`.
+    pub async fn record(&self, user_id: Uuid, product_id: Uuid, keep: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO recently_viewed_products (user_id, product_id)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, product_id) DO UPDATE SET viewed_at = NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(product_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            DELETE FROM recently_viewed_products
+            WHERE user_id = $1
+              AND product_id NOT IN (
+                  SELECT product_id FROM recently_viewed_products
+                  WHERE user_id = $1
+                  ORDER BY viewed_at DESC
+                  LIMIT $2
+              )
+            "#,
+        )
+        .bind(user_id)
+        .bind(keep)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// History entries the user can still see: active products in active
+    /// stores that are public or that the user belongs to or was granted.
+    pub async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+    ) -> Result<Vec<RecentlyViewedProduct>> {
+        let items = sqlx::query_as::<_, RecentlyViewedProduct>(
+            r#"
+            SELECT p.*, rv.viewed_at
+            FROM recently_viewed_products rv
+            JOIN products p ON p.id = rv.product_id
+            JOIN stores s ON s.id = p.store_id
+            WHERE rv.user_id = $1
+              AND p.is_active = true
+              AND p.archived_at IS NULL
+              AND s.status = 'Active'
+              AND (
+                  s.is_private = false
+                  OR EXISTS (
+                      SELECT 1 FROM store_members m
+                      WHERE m.store_id = s.id AND m.user_id = $1 AND m.is_active = true
+                  )
+                  OR EXISTS (
+                      SELECT 1 FROM store_access_grants g
+                      WHERE g.store_id = s.id
+                        AND g.user_id = $1
+                        AND g.is_revoked = false
+                        AND (g.expires_at IS NULL OR g.expires_at > NOW())
+                  )
+              )
+            ORDER BY rv.viewed_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(items)
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{error::Result, models::product::RelatedProduct};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct RecommendationRepository {
+    pool: PgPool,
+}
+
+impl RecommendationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Rebuilds the co-purchase table from order history. Two products count
+    /// as bought together when they appear in the same checkout, even across
+    /// stores; cancelled orders are ignored. Only the `keep` strongest pairs
+    /// per product are stored. Returns the number of pairs written.
+    pub async fn refresh(&self, keep: i64) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM product_co_purchases")
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO product_co_purchases (product_id, related_product_id, order_count)
+            SELECT product_id, related_product_id, order_count
+            FROM (
+                SELECT
+                    a.product_id,
+                    b.product_id AS related_product_id,
+                    COUNT(DISTINCT oa.order_group_id)::INT AS order_count,
+                    ROW_NUMBER() OVER (
+                        PARTITION BY a.product_id
+                        ORDER BY COUNT(DISTINCT oa.order_group_id) DESC, b.product_id
+                    ) AS rank
+                FROM order_items a
+                JOIN orders oa ON oa.id = a.order_id
+                JOIN orders ob ON ob.order_group_id = oa.order_group_id
+                JOIN order_items b ON b.order_id = ob.id AND b.product_id <> a.product_id
+                WHERE oa.status <> 'Cancelled' AND ob.status <> 'Cancelled'
+                GROUP BY a.product_id, b.product_id
+            ) pairs
+            WHERE rank <= $1
+            "#,
+        )
+        .bind(keep)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Products most often bought with `product_id` that the viewer can see.
+    pub async fn bought_together(
+        &self,
+        product_id: Uuid,
+        viewer_id: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<RelatedProduct>> {
+        let related = sqlx::query_as::<_, RelatedProduct>(
+            r#"
+            SELECT p.*, c.order_count AS co_purchases
+            FROM product_co_purchases c
+            JOIN products p ON p.id = c.related_product_id
+            JOIN stores s ON s.id = p.store_id
+            WHERE c.product_id = $1
+              AND p.is_active = true
+              AND p.archived_at IS NULL
+              AND s.status = 'Active'
+              AND (s.is_private = false OR p.store_id = ANY(
+                  SELECT m.store_id FROM store_members m
+                  WHERE m.user_id = $2 AND m.is_active = true
+                  UNION
+                  SELECT g.store_id FROM store_access_grants g
+                  WHERE g.user_id = $2
+                    AND g.is_revoked = false
+                    AND (g.expires_at IS NULL OR g.expires_at > NOW())
+              ))
+            ORDER BY c.order_count DESC, p.id
+            LIMIT $3
+            "#,
+        )
+        .bind(product_id)
+        .bind(viewer_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(related)
+    }
+
+    /// Visible products sharing the category of `product_id`, those from the
+    /// same store first, skipping `exclude`.
+    pub async fn same_category(
+        &self,
+        product_id: Uuid,
+        viewer_id: Option<Uuid>,
+        exclude: &[Uuid],
+        limit: i64,
+    ) -> Result<Vec<RelatedProduct>> {
+        let related = sqlx::query_as::<_, RelatedProduct>(
+            r#"
+            SELECT p.*, 0 AS co_purchases
+            FROM products source
+            JOIN products p
+              ON lower(p.category) = lower(source.category) AND p.id <> source.id
+            JOIN stores s ON s.id = p.store_id
+            WHERE source.id = $1
+              AND source.category IS NOT NULL
+              AND p.id <> ALL($3)
+              AND p.is_active = true
+              AND p.archived_at IS NULL
+              AND s.status = 'Active'
+              AND (s.is_private = false OR p.store_id = ANY(
+                  SELECT m.store_id FROM store_members m
+                  WHERE m.user_id = $2 AND m.is_active = true
+                  UNION
+                  SELECT g.store_id FROM store_access_grants g
+                  WHERE g.user_id = $2
+                    AND g.is_revoked = false
+                    AND (g.expires_at IS NULL OR g.expires_at > NOW())
+              ))
+            ORDER BY p.store_id = source.store_id DESC, p.created_at DESC, p.id
+            LIMIT $4
+            "#,
+        )
+        .bind(product_id)
+        .bind(viewer_id)
+        .bind(exclude)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(related)
+    }
+}
@@ -1,8 +1,8 @@
 use crate::{
     error::Result,
-    models::store::{CreateStoreRequest, Store, StoreStatus},
+    models::store::{CreateStoreRequest, Store, StoreCleanup, StorePlan, StoreStatus},
 };
-use sqlx::PgPool;
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -15,6 +15,10 @@ impl StoreRepository {
         Self { pool }
     }
 
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
     pub async fn create(&self, owner_id: Uuid, payload: &CreateStoreRequest) -> Result<Store> {
         let store = sqlx::query_as::<_, Store>(
             r#"
@@ -84,4 +88,84 @@ impl StoreRepository {
 
         Ok(store)
     }
+
+    pub async fn update_plan(&self, store_id: Uuid, plan: StorePlan) -> Result<Store> {
+        let store = sqlx::query_as::<_, Store>(
+            r#"
+            UPDATE stores SET plan = $2 WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(plan)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(store)
+    }
+
+    /// Closed stores whose cleanup pipeline has not run yet.
+    pub async fn list_pending_cleanup(&self, limit: i64) -> Result<Vec<Store>> {
+        let stores = sqlx::query_as::<_, Store>(
+            r#"
+            SELECT s.* FROM stores s
+            LEFT JOIN store_cleanups sc ON sc.store_id = s.id
+            WHERE s.status = 'Closed' AND sc.store_id IS NULL
+            ORDER BY s.updated_at ASC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(stores)
+    }
+
+    /// Locks a closed, not yet cleaned up store for the rest of the transaction.
+    pub async fn lock_pending_cleanup_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+    ) -> Result<Option<Store>> {
+        let store = sqlx::query_as::<_, Store>(
+            r#"
+            SELECT s.* FROM stores s
+            WHERE s.id = $1 AND s.status = 'Closed'
+              AND NOT EXISTS (SELECT 1 FROM store_cleanups sc WHERE sc.store_id = s.id)
+            FOR UPDATE
+            "#,
+        )
+        .bind(store_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(store)
+    }
+
+    pub async fn record_cleanup_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        store_id: Uuid,
+        products_deactivated: i32,
+        cart_items_removed: i32,
+        customers_notified: i32,
+    ) -> Result<StoreCleanup> {
+        let cleanup = sqlx::query_as::<_, StoreCleanup>(
+            r#"
+            INSERT INTO store_cleanups (
+                store_id, products_deactivated, cart_items_removed, customers_notified
+            ) VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(products_deactivated)
+        .bind(cart_items_removed)
+        .bind(customers_notified)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(cleanup)
+    }
 }
@@ -0,0 +1,208 @@
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    cache::CacheHandle,
+    error::Result,
+    models::{permission::Permission, store::StoreRole},
+};
+
+#[derive(Clone)]
+pub struct StoreRoleRepository {
+    pool: PgPool,
+    cache: Option<CacheHandle>,
+}
+
+impl StoreRoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool, cache: None }
+    }
+
+    /// Drops the store's cached permissions in `cache` after store role changes.
+    pub fn with_cache(mut self, cache: CacheHandle) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Drops everyone's cached permissions in the store, for callers that
+    /// write inside their own transaction, once it has committed.
+    pub async fn invalidate_permissions(&self, store_id: Uuid) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate_permissions(store_id).await;
+        }
+    }
+
+    pub async fn list_for_store(&self, store_id: Uuid) -> Result<Vec<StoreRole>> {
+        let roles = sqlx::query_as::<_, StoreRole>(
+            "SELECT * FROM store_roles WHERE store_id = $1 ORDER BY lower(name)",
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn find(&self, store_id: Uuid, role_id: Uuid) -> Result<Option<StoreRole>> {
+        let role = sqlx::query_as::<_, StoreRole>(
+            "SELECT * FROM store_roles WHERE id = $1 AND store_id = $2",
+        )
+        .bind(role_id)
+        .bind(store_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    /// Whether another of the store's roles already has this name, ignoring
+    /// case.
+    pub async fn name_taken(
+        &self,
+        store_id: Uuid,
+        name: &str,
+        except: Option<Uuid>,
+    ) -> Result<bool> {
+        let taken = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM store_roles
+                WHERE store_id = $1 AND lower(name) = lower($2)
+                  AND id IS DISTINCT FROM $3
+            )
+            "#,
+        )
+        .bind(store_id)
+        .bind(name)
+        .bind(except)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(taken)
+    }
+
+    pub async fn create(
+        &self,
+        store_id: Uuid,
+        name: &str,
+        permissions: &[Permission],
+        actor_id: Uuid,
+    ) -> Result<StoreRole> {
+        let permissions_json = json!(permissions.iter().map(|p| p.as_str()).collect::<Vec<_>>());
+
+        let role = sqlx::query_as::<_, StoreRole>(
+            r#"
+            WITH created AS (
+                INSERT INTO store_roles (store_id, name, permissions)
+                VALUES ($1, $2, $3)
+                RETURNING *
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, subject_id, after)
+                SELECT store_id, $4, 'RoleCreated', id, to_jsonb(created)
+                FROM created
+            )
+            SELECT * FROM created
+            "#,
+        )
+        .bind(store_id)
+        .bind(name)
+        .bind(permissions_json)
+        .bind(actor_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        self.invalidate_permissions(store_id).await;
+        Ok(role)
+    }
+
+    /// Renames a role and replaces its permissions. Sessions of the members
+    /// holding it are invalidated when the permissions change.
+    pub async fn update(
+        &self,
+        store_id: Uuid,
+        role_id: Uuid,
+        name: &str,
+        permissions: &[Permission],
+        actor_id: Uuid,
+    ) -> Result<Option<StoreRole>> {
+        let permissions_json = json!(permissions.iter().map(|p| p.as_str()).collect::<Vec<_>>());
+
+        let role = sqlx::query_as::<_, StoreRole>(
+            r#"
+            WITH previous AS (
+                SELECT * FROM store_roles WHERE id = $1 AND store_id = $2
+            ), updated AS (
+                UPDATE store_roles
+                SET name = $3, permissions = $4
+                WHERE id = $1 AND store_id = $2
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id IN (
+                    SELECT user_id FROM store_members
+                    WHERE custom_role_id = $1 AND is_active = true
+                )
+                AND EXISTS (SELECT 1 FROM previous WHERE permissions <> $4)
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, subject_id, before, after)
+                SELECT u.store_id, $5, 'RoleUpdated', u.id, to_jsonb(p), to_jsonb(u)
+                FROM updated u JOIN previous p ON p.id = u.id
+            )
+            SELECT * FROM updated
+            "#,
+        )
+        .bind(role_id)
+        .bind(store_id)
+        .bind(name)
+        .bind(permissions_json)
+        .bind(actor_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        self.invalidate_permissions(store_id).await;
+        Ok(role)
+    }
+
+    /// Deletes a role, leaving its members with only their own permissions,
+    /// and invalidates those members' sessions.
+    pub async fn delete(
+        &self,
+        store_id: Uuid,
+        role_id: Uuid,
+        actor_id: Uuid,
+    ) -> Result<Option<StoreRole>> {
+        let role = sqlx::query_as::<_, StoreRole>(
+            r#"
+            WITH deleted AS (
+                DELETE FROM store_roles
+                WHERE id = $1 AND store_id = $2
+                RETURNING *
+            ), bumped AS (
+                UPDATE users SET token_version = token_version + 1
+                WHERE id IN (
+                    SELECT m.user_id FROM store_members m
+                    JOIN deleted r ON r.id = m.custom_role_id
+                    WHERE m.is_active = true
+                )
+            ), audited AS (
+                INSERT INTO permission_audit_log
+                    (store_id, actor_id, action, subject_id, before)
+                SELECT store_id, $3, 'RoleDeleted', id, to_jsonb(deleted)
+                FROM deleted
+            )
+            SELECT * FROM deleted
+            "#,
+        )
+        .bind(role_id)
+        .bind(store_id)
+        .bind(actor_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        self.invalidate_permissions(store_id).await;
+        Ok(role)
+    }
+}
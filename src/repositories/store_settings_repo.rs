@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::Result,
+    models::{
+        shipping::{ShippingRule, ShippingRuleKind, ShippingTier},
+        store::{CheckoutField, CreateCheckoutFieldRequest, StoreSettings},
+    },
+};
+use rust_decimal::Decimal;
+use sqlx::{types::Json, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct StoreSettingsRepository {
+    pool: PgPool,
+}
+
+impl StoreSettingsRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find(&self, store_id: Uuid) -> Result<Option<StoreSettings>> {
+        let settings =
+            sqlx::query_as::<_, StoreSettings>("SELECT * FROM store_settings WHERE store_id = $1")
+                .bind(store_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(settings)
+    }
+
+    /// Settings for each requested store, falling back to defaults when unset.
+    pub async fn find_for_stores(
+        &self,
+        store_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, StoreSettings>> {
+        let rows = sqlx::query_as::<_, StoreSettings>(
+            "SELECT * FROM store_settings WHERE store_id = ANY($1)",
+        )
+        .bind(store_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut settings: HashMap<Uuid, StoreSettings> =
+            rows.into_iter().map(|row| (row.store_id, row)).collect();
+        for store_id in store_ids {
+            settings
+                .entry(*store_id)
+                .or_insert_with(|| StoreSettings::defaults(*store_id));
+        }
+
+        Ok(settings)
+    }
+
+    pub async fn upsert(&self, settings: &StoreSettings) -> Result<StoreSettings> {
+        let saved = sqlx::query_as::<_, StoreSettings>(
+            r#"
+            INSERT INTO store_settings (
+                store_id, currency_code, tax_rate, flat_shipping_fee, free_shipping_threshold,
+                ships_to, tax_provider, payment_provider
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (store_id)
+            DO UPDATE SET currency_code = EXCLUDED.currency_code,
+                          tax_rate = EXCLUDED.tax_rate,
+                          flat_shipping_fee = EXCLUDED.flat_shipping_fee,
+                          free_shipping_threshold = EXCLUDED.free_shipping_threshold,
+                          ships_to = EXCLUDED.ships_to,
+                          tax_provider = EXCLUDED.tax_provider,
+                          payment_provider = EXCLUDED.payment_provider
+            RETURNING *
+            "#,
+        )
+        .bind(settings.store_id)
+        .bind(&settings.currency_code)
+        .bind(settings.tax_rate)
+        .bind(settings.flat_shipping_fee)
+        .bind(settings.free_shipping_threshold)
+        .bind(&settings.ships_to)
+        .bind(settings.tax_provider)
+        .bind(settings.payment_provider)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(saved)
+    }
+
+    /// Appends a checkout field after the store's existing ones.
+    pub async fn create_checkout_field(
+        &self,
+        store_id: Uuid,
+        field: &CreateCheckoutFieldRequest,
+    ) -> Result<CheckoutField> {
+        let created = sqlx::query_as::<_, CheckoutField>(
+            r#"
+            INSERT INTO store_checkout_fields (
+                store_id, key, label, field_type, required, max_length, position
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6,
+                (SELECT COALESCE(MAX(position) + 1, 0) FROM store_checkout_fields WHERE store_id = $1)
+            )
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(&field.key)
+        .bind(&field.label)
+        .bind(field.field_type)
+        .bind(field.required)
+        .bind(field.max_length)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn list_checkout_fields(&self, store_id: Uuid) -> Result<Vec<CheckoutField>> {
+        let fields = sqlx::query_as::<_, CheckoutField>(
+            "SELECT * FROM store_checkout_fields WHERE store_id = $1 ORDER BY position ASC",
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(fields)
+    }
+
+    /// Checkout fields of each requested store, in display order. Stores
+    /// without fields are absent from the map.
+    pub async fn checkout_fields_for_stores(
+        &self,
+        store_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<CheckoutField>>> {
+        let rows = sqlx::query_as::<_, CheckoutField>(
+            r#"
+            SELECT * FROM store_checkout_fields
+            WHERE store_id = ANY($1)
+            ORDER BY store_id, position ASC
+            "#,
+        )
+        .bind(store_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut fields: HashMap<Uuid, Vec<CheckoutField>> = HashMap::new();
+        for row in rows {
+            fields.entry(row.store_id).or_default().push(row);
+        }
+        Ok(fields)
+    }
+
+    pub async fn delete_checkout_field(&self, store_id: Uuid, field_id: Uuid) -> Result<bool> {
+        let res = sqlx::query("DELETE FROM store_checkout_fields WHERE id = $1 AND store_id = $2")
+            .bind(field_id)
+            .bind(store_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+
+    pub async fn create_shipping_rule(
+        &self,
+        store_id: Uuid,
+        name: &str,
+        kind: ShippingRuleKind,
+        fee: Decimal,
+        tiers: &[ShippingTier],
+        free_shipping_threshold: Option<Decimal>,
+    ) -> Result<ShippingRule> {
+        let created = sqlx::query_as::<_, ShippingRule>(
+            r#"
+            INSERT INTO store_shipping_rules (
+                store_id, name, kind, fee, tiers, free_shipping_threshold
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(name)
+        .bind(kind)
+        .bind(fee)
+        .bind(Json(tiers))
+        .bind(free_shipping_threshold)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(created)
+    }
+
+    pub async fn list_shipping_rules(&self, store_id: Uuid) -> Result<Vec<ShippingRule>> {
+        let rules = sqlx::query_as::<_, ShippingRule>(
+            "SELECT * FROM store_shipping_rules WHERE store_id = $1 ORDER BY created_at ASC",
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    /// Shipping rules of each requested store. Stores without rules are
+    /// absent from the map.
+    pub async fn shipping_rules_for_stores(
+        &self,
+        store_ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Vec<ShippingRule>>> {
+        let rows = sqlx::query_as::<_, ShippingRule>(
+            r#"
+            SELECT * FROM store_shipping_rules
+            WHERE store_id = ANY($1)
+            ORDER BY store_id, created_at ASC
+            "#,
+        )
+        .bind(store_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut rules: HashMap<Uuid, Vec<ShippingRule>> = HashMap::new();
+        for row in rows {
+            rules.entry(row.store_id).or_default().push(row);
+        }
+        Ok(rules)
+    }
+
+    pub async fn delete_shipping_rule(&self, store_id: Uuid, rule_id: Uuid) -> Result<bool> {
+        let res = sqlx::query("DELETE FROM store_shipping_rules WHERE id = $1 AND store_id = $2")
+            .bind(rule_id)
+            .bind(store_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(res.rows_affected() > 0)
+    }
+}
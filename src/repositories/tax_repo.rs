@@ -0,0 +1,113 @@
+use crate::{
+    error::Result,
+    models::tax::{TaxFiling, TaxTransaction},
+};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct TaxRepository {
+    pool: PgPool,
+}
+
+impl TaxRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_for_order(&self, order_id: Uuid) -> Result<Option<TaxTransaction>> {
+        let transaction = sqlx::query_as::<_, TaxTransaction>(
+            "SELECT * FROM tax_transactions WHERE order_id = $1",
+        )
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(transaction)
+    }
+
+    /// Leases up to `limit` due transactions for `lease_secs`, counting the
+    /// attempt, and returns them with the order figures to file.
+    pub async fn claim_due(&self, limit: i64, lease_secs: i64) -> Result<Vec<TaxFiling>> {
+        let filings = sqlx::query_as::<_, TaxFiling>(
+            r#"
+            WITH claimed AS (
+                UPDATE tax_transactions
+                SET attempts = attempts + 1,
+                    next_attempt_at = NOW() + make_interval(secs => $2)
+                WHERE id IN (
+                    SELECT id FROM tax_transactions
+                    WHERE status = 'Pending' AND next_attempt_at <= NOW()
+                    ORDER BY next_attempt_at ASC
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING id, order_id, attempts
+            )
+            SELECT
+                c.id AS transaction_id,
+                c.attempts,
+                o.id AS order_id,
+                o.order_number,
+                o.store_id,
+                o.shipping_address,
+                o.subtotal - o.discount AS subtotal,
+                o.shipping_cost,
+                o.tax AS tax_amount,
+                o.created_at AS ordered_at
+            FROM claimed c
+            JOIN orders o ON o.id = c.order_id
+            ORDER BY o.created_at ASC
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(filings)
+    }
+
+    pub async fn mark_committed(&self, transaction_id: Uuid, external_id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE tax_transactions
+            SET status = 'Committed', external_id = $2, committed_at = NOW(), last_error = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(external_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt; the transaction is retried at `retry_at`,
+    /// or given up on when that is `None`.
+    pub async fn mark_failed(
+        &self,
+        transaction_id: Uuid,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE tax_transactions
+            SET status = CASE WHEN $3::timestamptz IS NULL THEN 'Failed' ELSE 'Pending' END::tax_transaction_status,
+                next_attempt_at = COALESCE($3, next_attempt_at),
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(transaction_id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
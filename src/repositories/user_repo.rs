@@ -71,4 +71,37 @@ impl UserRepository {
 
         Ok(exists.0)
     }
+
+    /// Current token version of an active user; `None` once the account is
+    /// gone or deactivated.
+    pub async fn token_version(&self, id: Uuid) -> Result<Option<i32>> {
+        let version = sqlx::query_as::<_, (i32,)>(
+            "SELECT token_version FROM users WHERE id = $1 AND is_active = true",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(version.map(|row| row.0))
+    }
+
+    /// Replaces the password hash and bumps the token version, invalidating
+    /// every session issued before the change.
+    pub async fn update_password(&self, id: Uuid, password_hash: &str) -> Result<User> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET password_hash = $2,
+                token_version = token_version + 1
+            WHERE id = $1
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(password_hash)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
 }
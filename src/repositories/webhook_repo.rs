@@ -0,0 +1,261 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    error::Result,
+    models::webhook::{DueWebhookDelivery, Webhook, WebhookDelivery},
+};
+
+#[derive(Clone)]
+pub struct WebhookRepository {
+    pool: PgPool,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn list_for_store(&self, store_id: Uuid) -> Result<Vec<Webhook>> {
+        let webhooks = sqlx::query_as::<_, Webhook>(
+            "SELECT * FROM webhooks WHERE store_id = $1 ORDER BY created_at ASC",
+        )
+        .bind(store_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(webhooks)
+    }
+
+    pub async fn find_for_store(
+        &self,
+        store_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Option<Webhook>> {
+        let webhook =
+            sqlx::query_as::<_, Webhook>("SELECT * FROM webhooks WHERE id = $1 AND store_id = $2")
+                .bind(webhook_id)
+                .bind(store_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn count_for_store(&self, store_id: Uuid) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM webhooks WHERE store_id = $1")
+            .bind(store_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(count)
+    }
+
+    pub async fn create(
+        &self,
+        store_id: Uuid,
+        url: &str,
+        secret: &str,
+        events: &[String],
+        created_by: Uuid,
+    ) -> Result<Webhook> {
+        let webhook = sqlx::query_as::<_, Webhook>(
+            r#"
+            INSERT INTO webhooks (store_id, url, secret, events, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+        )
+        .bind(store_id)
+        .bind(url)
+        .bind(secret)
+        .bind(events)
+        .bind(created_by)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    /// Removes the webhook along with its delivery log.
+    pub async fn delete(&self, store_id: Uuid, webhook_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = $1 AND store_id = $2")
+            .bind(webhook_id)
+            .bind(store_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Queues the event for every webhook of the store subscribed to it,
+    /// returning how many deliveries were queued. Queuing an event again
+    /// adds nothing.
+    pub async fn queue_deliveries(
+        &self,
+        store_id: Uuid,
+        event_type: &str,
+        event_id: Uuid,
+        payload: &serde_json::Value,
+    ) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event_id, event_type, payload)
+            SELECT id, $3, $2, $4
+            FROM webhooks
+            WHERE store_id = $1 AND $2 = ANY(events)
+            ON CONFLICT (webhook_id, event_id) DO NOTHING
+            "#,
+        )
+        .bind(store_id)
+        .bind(event_type)
+        .bind(event_id)
+        .bind(payload)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// The webhook's deliveries, newest first.
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>> {
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    /// Claims up to `limit` due deliveries and counts the attempt. Claimed
+    /// rows are pushed back by `lease_secs`, so a worker that dies mid-send
+    /// leaves them to be retried rather than lost.
+    pub async fn claim_due_deliveries(
+        &self,
+        limit: i64,
+        lease_secs: i64,
+    ) -> Result<Vec<DueWebhookDelivery>> {
+        let deliveries = sqlx::query_as::<_, DueWebhookDelivery>(
+            r#"
+            WITH claimed AS (
+                UPDATE webhook_deliveries
+                SET attempts = attempts + 1,
+                    next_attempt_at = NOW() + make_interval(secs => $2)
+                WHERE id IN (
+                    SELECT id FROM webhook_deliveries
+                    WHERE status = 'Pending' AND next_attempt_at <= NOW()
+                    ORDER BY next_attempt_at ASC
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING *
+            )
+            SELECT claimed.*, w.url, w.secret
+            FROM claimed
+            JOIN webhooks w ON w.id = claimed.webhook_id
+            "#,
+        )
+        .bind(limit)
+        .bind(lease_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    pub async fn mark_delivered(
+        &self,
+        delivery_id: Uuid,
+        response_status: i32,
+        response_body: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'Sent', delivered_at = NOW(), last_error = NULL,
+                response_status = $2, response_body = $3
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(response_status)
+        .bind(response_body)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Puts a claimed delivery that was not sent back in the queue until
+    /// `retry_at`, without counting the attempt.
+    pub async fn postpone(
+        &self,
+        delivery_id: Uuid,
+        error: &str,
+        retry_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET attempts = GREATEST(attempts - 1, 0),
+                next_attempt_at = $3,
+                last_error = $2
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(error)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Records a failed attempt and whatever the endpoint answered; the
+    /// delivery is retried at `retry_at`, or given up on when that is `None`.
+    pub async fn mark_failed(
+        &self,
+        delivery_id: Uuid,
+        error: &str,
+        response_status: Option<i32>,
+        response_body: Option<&str>,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE WHEN $5::timestamptz IS NULL THEN 'Failed' ELSE 'Pending' END::delivery_status,
+                next_attempt_at = COALESCE($5, next_attempt_at),
+                last_error = $2,
+                response_status = $3,
+                response_body = $4
+            WHERE id = $1
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(error)
+        .bind(response_status)
+        .bind(response_body)
+        .bind(retry_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
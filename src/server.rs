@@ -1,45 +1,282 @@
+use crate::cache::{CacheBackend, CacheHandle, MemoryCache, RedisCache, SharedCache};
 use crate::config::Config;
+use crate::currency::{ExchangeRates, HttpExchangeRates};
+use crate::email::{
+    EmailBackend, EmailJob, EmailService, InvitationEmails, LogEmailService, SesEmailService,
+    SmtpEmailService, EMAIL_JOB_KIND,
+};
 use crate::handlers;
-use crate::metrics::Metrics;
-use crate::middleware::metrics::track_metrics;
+use crate::jobs::{JobRegistry, JobWorker};
+use crate::metrics::{Metrics, SharedMetrics};
+use crate::middleware::{
+    access::EndpointAccess,
+    client_ip, compression,
+    metrics::track_metrics,
+    request_id, request_user,
+    security_headers::{set_security_headers, SecurityHeaders},
+};
+use crate::models::payment::PaymentProviderKind;
+use crate::notifier::LogNotifier;
+use crate::outbox::OutboxRelay;
+use crate::payments::{PayPalProvider, PaymentProviders};
+use crate::repositories::WebhookRepository;
+use crate::services::analytics_service::AnalyticsCache;
+use crate::services::cart_policy::CartPolicy;
+use crate::shipping::{HttpCarrierRates, ShippingRates};
+use crate::shutdown::Shutdown;
 use crate::state::AppState;
+use crate::storage::LocalStorage;
+use crate::tasks;
+use crate::tax::{HttpTaxProvider, TaxProvider, TaxRates};
 use crate::utils::jwt::JwtConfig;
-use axum::middleware;
-use sqlx::postgres::PgPoolOptions;
-use std::{net::SocketAddr, sync::Arc};
-use tower_http::{
-    compression::CompressionLayer,
-    cors::CorsLayer,
-    trace::{DefaultMakeSpan, DefaultOnResponse, TraceLayer},
-};
+use crate::webhooks::{WebhookSender, WebhookSubscriber};
+use axum::{middleware, Extension, Router};
+use sqlx::postgres::PgConnectOptions;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
 
 pub async fn run(config: Config) -> anyhow::Result<()> {
+    install_error_reporter(&config)?;
+
     // Database connection pool
-    let db_pool = PgPoolOptions::new()
-        .max_connections(5)
-        .connect(&config.database_url)
-        .await?;
+    let pool_settings = config.db_pool();
+    let db_pool = pool_settings.connect(&config.database_url).await?;
 
     // Run migrations
     sqlx::migrate!("./migrations").run(&db_pool).await?;
 
     tracing::info!("Database connected and migrations applied");
 
+    tasks::store_cleanup::spawn(
+        db_pool.clone(),
+        Duration::from_secs(config.store_cleanup_interval_secs),
+    );
+    tasks::reservation_expiry::spawn(
+        db_pool.clone(),
+        Duration::from_secs(config.reservation_expiry_interval_secs),
+    );
+    tasks::guest_cart_cleanup::spawn(
+        db_pool.clone(),
+        chrono::Duration::seconds(config.guest_cart_ttl_secs),
+        Duration::from_secs(config.guest_cart_cleanup_interval_secs),
+    );
+    tasks::idempotency_cleanup::spawn(
+        db_pool.clone(),
+        Duration::from_secs(config.idempotency_cleanup_interval_secs),
+    );
+    tasks::notification_delivery::spawn(
+        db_pool.clone(),
+        Arc::new(LogNotifier),
+        Duration::from_secs(config.notification_delivery_interval_secs),
+    );
+    tasks::recommendation_refresh::spawn(
+        db_pool.clone(),
+        Duration::from_secs(config.recommendation_refresh_interval_secs),
+    );
+    tasks::analytics_rollup::spawn(
+        db_pool.clone(),
+        config.analytics_rollup_lookback_days,
+        Duration::from_secs(config.analytics_rollup_interval_secs),
+    );
+
     let jwt_config = JwtConfig::new(&config.jwt_secret, config.jwt_expiration_hours);
     let metrics = Arc::new(Metrics::default());
-    let state = AppState::new(db_pool.clone(), jwt_config, metrics.clone());
+    tasks::cart_expiry::spawn(
+        db_pool.clone(),
+        metrics.clone(),
+        chrono::Duration::seconds(config.cart_expiry_age_secs),
+        Duration::from_secs(config.cart_expiry_interval_secs),
+    );
+    tasks::unpaid_order_expiry::spawn(
+        db_pool.clone(),
+        chrono::Duration::seconds(config.unpaid_order_ttl_secs),
+        Duration::from_secs(config.unpaid_order_expiry_interval_secs),
+    );
 
-    // Build router
-    let app = handlers::api_router()
-        .layer(middleware::from_fn_with_state(state.clone(), track_metrics))
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::new().include_headers(true))
-                .on_response(DefaultOnResponse::new().include_headers(true)),
+    // Domain event subscribers run as jobs, one per subscriber and event
+    let outbox_relay = OutboxRelay::new(db_pool.clone())
+        .subscribe(
+            "webhooks",
+            Arc::new(WebhookSubscriber::new(WebhookRepository::new(
+                db_pool.clone(),
+            ))),
         )
-        .layer(CompressionLayer::new())
-        .layer(CorsLayer::permissive())
-        .with_state(state);
+        .subscribe("emails", Arc::new(InvitationEmails::new(db_pool.clone())));
+
+    // Job kinds this process runs; others stay queued for another instance
+    let job_registry = outbox_relay.register_jobs(JobRegistry::new()).register(
+        EMAIL_JOB_KIND,
+        Arc::new(EmailJob::new(
+            db_pool.clone(),
+            email_service(&config, metrics.clone())?,
+        )),
+    );
+    JobWorker::new(db_pool.clone(), job_registry, metrics.clone())
+        .spawn(Duration::from_secs(config.job_poll_interval_secs));
+    outbox_relay.spawn(Duration::from_secs(config.outbox_relay_interval_secs));
+    let webhook_sender = WebhookSender::new()?;
+    tasks::webhook_delivery::spawn(
+        db_pool.clone(),
+        webhook_sender.clone(),
+        Duration::from_secs(config.webhook_delivery_interval_secs),
+    );
+
+    let mut tax_rates = TaxRates::builtin().with_metrics(metrics.clone());
+    if let Some(url) = &config.tax_provider_url {
+        let client = Arc::new(HttpTaxProvider::new(
+            url,
+            config.tax_provider_api_key.clone().unwrap_or_default(),
+        )?);
+        let rotated = client.clone();
+        config
+            .secrets
+            .on_rotate("TAX_PROVIDER_API_KEY", move |key| rotated.set_api_key(key));
+        let provider: Arc<dyn TaxProvider> = client;
+        tax_rates = tax_rates.with_external(
+            provider.clone(),
+            Duration::from_secs(config.tax_rate_cache_secs),
+        );
+        tasks::tax_commit::spawn(
+            db_pool.clone(),
+            provider,
+            Duration::from_secs(config.tax_commit_interval_secs),
+        );
+    }
+
+    let mut shipping_rates = ShippingRates::builtin().with_metrics(metrics.clone());
+    if let Some(url) = &config.carrier_rates_url {
+        let client = Arc::new(HttpCarrierRates::new(
+            url,
+            config.carrier_rates_api_key.clone().unwrap_or_default(),
+        )?);
+        let rotated = client.clone();
+        config
+            .secrets
+            .on_rotate("CARRIER_RATES_API_KEY", move |key| rotated.set_api_key(key));
+        shipping_rates = shipping_rates.with_carrier(client);
+    }
+
+    let mut payment_providers = PaymentProviders::manual_only();
+    if let Some(client_id) = &config.paypal_client_id {
+        let client = Arc::new(
+            PayPalProvider::new(
+                &config.paypal_api_url,
+                client_id,
+                config.paypal_client_secret.clone().unwrap_or_default(),
+                config.paypal_webhook_id.clone().unwrap_or_default(),
+            )?
+            .with_metrics(metrics.clone()),
+        );
+        let rotated = client.clone();
+        config
+            .secrets
+            .on_rotate("PAYPAL_CLIENT_SECRET", move |secret| {
+                rotated.set_client_secret(secret)
+            });
+        payment_providers = payment_providers.with_provider(PaymentProviderKind::PayPal, client);
+    }
+
+    let exchange_rates = match (&config.exchange_rates_url, &config.static_exchange_rates) {
+        (Some(url), _) => {
+            let client = Arc::new(HttpExchangeRates::new(
+                url,
+                config.exchange_rates_api_key.clone().unwrap_or_default(),
+            )?);
+            let rotated = client.clone();
+            config
+                .secrets
+                .on_rotate("EXCHANGE_RATES_API_KEY", move |key| {
+                    rotated.set_api_key(key)
+                });
+            ExchangeRates::new(client)
+        }
+        (None, Some(rates)) => ExchangeRates::new(Arc::new(rates.clone())),
+        (None, None) => ExchangeRates::none(),
+    }
+    .with_cache_ttl(Duration::from_secs(config.exchange_rate_cache_secs));
+
+    // Rotated secrets take effect without a restart: new JWTs are signed with
+    // the new secret (old ones still verify) and new connections log in with
+    // the new database credentials.
+    let jwt = jwt_config.clone();
+    config
+        .secrets
+        .on_rotate("JWT_SECRET", move |secret| jwt.rotate(secret));
+    let pool = db_pool.clone();
+    config.secrets.on_rotate("DATABASE_URL", move |url| {
+        match url.parse::<PgConnectOptions>() {
+            Ok(options) => pool.set_connect_options(pool_settings.connect_options(options)),
+            Err(err) => tracing::error!("Ignoring rotated DATABASE_URL: {}", err),
+        }
+    });
+    if let Some(interval) = config.secrets_refresh_interval_secs {
+        tasks::secret_refresh::spawn(config.secrets.clone(), Duration::from_secs(interval));
+    }
+
+    let cache: SharedCache = match (config.cache_backend, &config.redis_url) {
+        (CacheBackend::Redis, Some(url)) => Arc::new(RedisCache::from_url(url)?),
+        _ => Arc::new(MemoryCache::new(config.cache_capacity)),
+    };
+
+    let state = AppState::new(db_pool.clone(), jwt_config, metrics.clone())
+        .with_webhook_sender(webhook_sender)
+        .with_shipping_consolidation(config.shipping_consolidation)
+        .with_tax_rates(tax_rates)
+        .with_shipping_rates(shipping_rates)
+        .with_payment_providers(payment_providers)
+        .with_exchange_rates(exchange_rates)
+        .with_cart_policy(CartPolicy {
+            max_stores: config.cart_max_stores,
+        })
+        .with_reservation_ttl(chrono::Duration::seconds(config.stock_reservation_ttl_secs))
+        .with_idempotency_ttl(chrono::Duration::seconds(config.idempotency_key_ttl_secs))
+        .with_cache(CacheHandle::new(
+            cache,
+            Duration::from_secs(config.cache_ttl_secs),
+        ))
+        .with_readiness_timeout(Duration::from_millis(config.readiness_timeout_ms))
+        .with_event_sample_rate(config.event_sample_rate)
+        .with_analytics_cache(AnalyticsCache::new(
+            Duration::from_secs(config.analytics_cache_secs),
+            10_000,
+        ))
+        .with_storage(Arc::new(LocalStorage::new(
+            &config.storage_local_dir,
+            &config.storage_public_url,
+        )))
+        .with_private_storage(Arc::new(LocalStorage::new(&config.storage_private_dir, "")));
+    let state = match &config.public_id_secret {
+        Some(secret) => state.with_public_id_secret(secret),
+        None => state,
+    };
+
+    let mut metrics_access =
+        EndpointAccess::new().with_allowed_networks(config.metrics_allowed_networks.clone());
+    if let Some(token) = &config.metrics_bearer_token {
+        metrics_access = metrics_access.with_bearer_token(token);
+    }
+    if let Some((username, password)) = &config.metrics_basic_auth {
+        metrics_access = metrics_access.with_basic_auth(username, password);
+    }
+    let admin_access =
+        EndpointAccess::new().with_allowed_networks(config.admin_allowed_networks.clone());
+
+    // Build router; with an internal port, /metrics is only served there
+    let metrics_app = handlers::metrics_router(metrics_access).with_state(state.clone());
+    let mut app = handlers::api_router(admin_access).with_state(state.clone());
+    if config.internal_port.is_none() {
+        app = app.merge(metrics_app.clone());
+    }
+    let security_headers = SecurityHeaders::new()
+        .with_hsts(config.hsts_max_age_secs, config.hsts_include_subdomains)
+        .with_frame_options(config.frame_options);
+    let app = with_middleware(
+        app,
+        state,
+        &config.storage_local_dir,
+        config.compression_min_bytes,
+        security_headers,
+    );
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
@@ -47,7 +284,137 @@ pub async fn run(config: Config) -> anyhow::Result<()> {
 
     tracing::info!("Server listening on {}", addr);
 
-    axum::serve(listener, app).await?;
+    // On SIGTERM/SIGINT both listeners stop accepting connections and wait
+    // for in-flight requests, such as checkouts, to finish
+    let shutdown = Shutdown::on_signal();
+    let public = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown({
+        let shutdown = shutdown.clone();
+        async move { shutdown.requested().await }
+    });
+    let internal = match config.internal_port {
+        Some(port) => {
+            let internal_addr = SocketAddr::from(([0, 0, 0, 0], port));
+            let internal_listener = tokio::net::TcpListener::bind(internal_addr).await?;
+            tracing::info!("Internal listener (metrics) on {}", internal_addr);
+            let shutdown = shutdown.clone();
+            Some(
+                axum::serve(
+                    internal_listener,
+                    metrics_app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(async move { shutdown.requested().await }),
+            )
+        }
+        None => None,
+    };
+    let servers = async {
+        match internal {
+            Some(internal) => tokio::try_join!(public, internal).map(|_| ()),
+            None => public.await,
+        }
+    };
+
+    let grace_period = Duration::from_secs(config.shutdown_grace_period_secs);
+    tokio::select! {
+        result = servers => {
+            result?;
+            // Every request has finished, so no connection is left checked out
+            db_pool.close().await;
+            tracing::info!("Server stopped");
+        }
+        () = shutdown.grace_period_elapsed(grace_period) => {
+            // Requests still running are cut off when the runtime stops; the
+            // pool is not waited on as they may still hold connections
+            tracing::warn!(
+                "Requests still in flight after the {}s grace period; exiting anyway",
+                grace_period.as_secs()
+            );
+        }
+    }
 
     Ok(())
 }
+
+/// Sends server errors to Sentry when `SENTRY_DSN` is set and the `sentry`
+/// feature is built in.
+fn install_error_reporter(config: &Config) -> anyhow::Result<()> {
+    let Some(dsn) = &config.sentry_dsn else {
+        return Ok(());
+    };
+    #[cfg(feature = "sentry")]
+    {
+        let reporter = crate::reporting::sentry::SentryReporter::from_dsn(
+            dsn,
+            config.sentry_environment.clone(),
+        )?;
+        crate::reporting::install(Arc::new(reporter));
+        tracing::info!("Reporting server errors to Sentry");
+    }
+    #[cfg(not(feature = "sentry"))]
+    {
+        let _ = dsn;
+        tracing::warn!("SENTRY_DSN is set but this build has no sentry feature; not reporting");
+    }
+    Ok(())
+}
+
+/// The backend emails are sent with, as selected by `EMAIL_BACKEND`.
+fn email_service(config: &Config, metrics: SharedMetrics) -> anyhow::Result<Arc<dyn EmailService>> {
+    let service: Arc<dyn EmailService> = match config.email_backend {
+        EmailBackend::Log => Arc::new(LogEmailService),
+        EmailBackend::Smtp => Arc::new(
+            SmtpEmailService::new(
+                config.smtp_url.as_deref().unwrap_or_default(),
+                &config.email_from,
+            )?
+            .with_metrics(metrics),
+        ),
+        EmailBackend::Ses => Arc::new(
+            SesEmailService::new(
+                config.ses_region.clone().unwrap_or_default(),
+                config.email_from.clone(),
+                config.ses_access_key_id.clone().unwrap_or_default(),
+                config.ses_secret_access_key.clone().unwrap_or_default(),
+            )?
+            .with_endpoint(config.ses_endpoint.clone().unwrap_or_default())
+            .with_metrics(metrics),
+        ),
+    };
+    Ok(service)
+}
+
+/// Wraps routes in the middleware stack every public response goes through:
+/// uploaded files, request metrics, client addresses, tracing, request ids,
+/// security headers, compression and CORS. The app state is also put in the request
+/// extensions for `require_permission`.
+pub fn with_middleware(
+    routes: Router,
+    state: AppState,
+    uploads_dir: &str,
+    compression_min_bytes: u16,
+    security_headers: SecurityHeaders,
+) -> Router {
+    routes
+        .nest_service("/uploads", ServeDir::new(uploads_dir))
+        .layer(middleware::from_fn_with_state(state.clone(), track_metrics))
+        .layer(Extension(state))
+        .layer(middleware::from_fn(client_ip::capture_client_ip))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(request_id::make_span)
+                .on_request(request_id::log_request)
+                .on_response(request_id::log_response),
+        )
+        .layer(middleware::from_fn(request_user::track_request_user))
+        .layer(middleware::from_fn(request_id::assign_request_id))
+        .layer(middleware::from_fn_with_state(
+            Arc::new(security_headers),
+            set_security_headers,
+        ))
+        .layer(compression::layer(compression_min_bytes))
+        .layer(CorsLayer::permissive())
+}
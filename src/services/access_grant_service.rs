@@ -0,0 +1,129 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use uuid::Uuid;
+use validator::{Validate, ValidateEmail};
+
+use crate::{
+    error::AppError,
+    models::store::{BulkGrantOutcome, BulkGrantRequest, BulkGrantResult, GrantRecipient},
+    repositories::{AccessGrantRepository, MemberRepository},
+};
+
+/// Grants access to private stores in bulk, e.g. when a wholesale store
+/// onboards its customer list.
+#[derive(Clone)]
+pub struct AccessGrantService {
+    grants: AccessGrantRepository,
+    members: MemberRepository,
+}
+
+impl AccessGrantService {
+    pub fn new(grants: AccessGrantRepository, members: MemberRepository) -> Self {
+        Self { grants, members }
+    }
+
+    /// Grants every recipient that resolves to an active user in a single
+    /// transaction. Recipients that do not resolve, or appear more than
+    /// once, fail on their own without holding up the rest.
+    pub async fn grant_bulk(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        payload: BulkGrantRequest,
+    ) -> crate::Result<Vec<BulkGrantResult>> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+        if payload
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(AppError::Validation(
+                "expires_at must be in the future".into(),
+            ));
+        }
+
+        let emails: Vec<String> = payload
+            .recipients
+            .iter()
+            .filter_map(|recipient| match recipient {
+                GrantRecipient::Email(email) => Some(email.trim().to_lowercase()),
+                GrantRecipient::UserId(_) => None,
+            })
+            .collect();
+        let email_refs: Vec<&str> = emails.iter().map(String::as_str).collect();
+        let by_email: HashMap<String, Uuid> = self
+            .members
+            .find_user_ids_by_email(&email_refs)
+            .await?
+            .into_iter()
+            .collect();
+        let ids: Vec<Uuid> = payload
+            .recipients
+            .iter()
+            .filter_map(|recipient| match recipient {
+                GrantRecipient::UserId(id) => Some(*id),
+                GrantRecipient::Email(_) => None,
+            })
+            .collect();
+        let active_ids: HashSet<Uuid> = self
+            .members
+            .find_active_user_ids(&ids)
+            .await?
+            .into_iter()
+            .collect();
+
+        let mut seen = HashSet::new();
+        let mut tx = self.grants.pool().begin().await?;
+        let mut results = Vec::with_capacity(payload.recipients.len());
+        for recipient in &payload.recipients {
+            let user_id = match recipient {
+                GrantRecipient::UserId(id) => active_ids
+                    .contains(id)
+                    .then_some(*id)
+                    .ok_or("No active user with this id"),
+                GrantRecipient::Email(email) => {
+                    let email = email.trim().to_lowercase();
+                    if email.validate_email() {
+                        by_email
+                            .get(&email)
+                            .copied()
+                            .ok_or("No user with this email")
+                    } else {
+                        Err("Invalid email address")
+                    }
+                }
+            };
+            let outcome = match user_id {
+                Err(error) => BulkGrantOutcome::Failed {
+                    error: error.into(),
+                },
+                Ok(user_id) if !seen.insert(user_id) => BulkGrantOutcome::Failed {
+                    error: "User is listed more than once".into(),
+                },
+                Ok(user_id) => BulkGrantOutcome::Granted {
+                    grant: self
+                        .grants
+                        .grant_or_renew_in_tx(
+                            &mut tx,
+                            store_id,
+                            user_id,
+                            actor_id,
+                            payload.access_level.clone(),
+                            payload.expires_at,
+                        )
+                        .await?,
+                },
+            };
+            results.push(BulkGrantResult {
+                recipient: recipient.to_string(),
+                outcome,
+            });
+        }
+        tx.commit().await?;
+        self.grants.invalidate_permissions(store_id).await;
+
+        Ok(results)
+    }
+}
@@ -1,53 +1,519 @@
-use chrono::{Duration, Utc};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use bytes::Bytes;
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
 use uuid::Uuid;
 
 use crate::{
     error::AppError,
-    models::store::StoreAnalyticsResponse,
+    models::{
+        event::StoreFunnel,
+        inventory::{ProductInventoryStats, StoreInventoryAnalytics},
+        store::{
+            MetricDelta, Store, StoreAnalyticsParams, StoreAnalyticsResponse,
+            StoreSummaryComparison, TaxReportPeriod, TaxReportRow,
+        },
+    },
     repositories::{AnalyticsRepository, StoreRepository},
 };
 
+type AnalyticsCacheKey = (Uuid, StoreAnalyticsParams);
+
+/// Short-lived cache of analytics reports. Dashboards reload them on every
+/// page view, while the figures only move as orders come in.
+pub struct AnalyticsCache {
+    ttl: std::time::Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<AnalyticsCacheKey, (Instant, StoreAnalyticsResponse)>>,
+}
+
+impl AnalyticsCache {
+    pub fn new(ttl: std::time::Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &AnalyticsCacheKey) -> Option<StoreAnalyticsResponse> {
+        let entries = self.entries.lock().expect("analytics cache poisoned");
+        entries
+            .get(key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, analytics)| analytics.clone())
+    }
+
+    fn insert(&self, key: AnalyticsCacheKey, analytics: StoreAnalyticsResponse) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("analytics cache poisoned");
+        if entries.len() >= self.capacity {
+            entries.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+            if entries.len() >= self.capacity {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (Instant::now(), analytics));
+    }
+}
+
+impl Default for AnalyticsCache {
+    fn default() -> Self {
+        Self::new(std::time::Duration::from_secs(60), 10_000)
+    }
+}
+
 #[derive(Clone)]
 pub struct AnalyticsService {
     stores: StoreRepository,
     analytics: AnalyticsRepository,
+    cache: Option<Arc<AnalyticsCache>>,
 }
 
 impl AnalyticsService {
     pub fn new(stores: StoreRepository, analytics: AnalyticsRepository) -> Self {
-        Self { stores, analytics }
+        Self {
+            stores,
+            analytics,
+            cache: None,
+        }
+    }
+
+    pub fn with_cache(mut self, cache: Arc<AnalyticsCache>) -> Self {
+        self.cache = Some(cache);
+        self
     }
 
+    /// The store's analytics report, served from the cache when one is set
+    /// and it holds a recent enough copy. `refresh` always recomputes it.
     pub async fn store_analytics(
         &self,
         store_id: Uuid,
-        timeframe_days: i64,
-        top_products_limit: i64,
+        params: StoreAnalyticsParams,
+        refresh: bool,
     ) -> crate::Result<StoreAnalyticsResponse> {
+        let StoreAnalyticsParams {
+            timeframe_days,
+            top_products,
+            payment_status,
+            compare,
+        } = params;
+        let lookback_days = compare.map_or(timeframe_days, |compare| {
+            compare.lookback_days(timeframe_days)
+        });
         let store = self
-            .stores
-            .find_by_id(store_id)
-            .await?
-            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+            .get_store_within_retention(store_id, lookback_days)
+            .await?;
 
-        let since = Utc::now() - Duration::days(timeframe_days);
+        let key = (store.id, params);
+        if let (Some(cache), false) = (&self.cache, refresh) {
+            if let Some(cached) = cache.get(&key) {
+                return Ok(StoreAnalyticsResponse {
+                    cached: true,
+                    ..cached
+                });
+            }
+        }
+
+        let generated_at = Utc::now();
+        let since = generated_at - Duration::days(timeframe_days);
 
         let summary = self
             .analytics
-            .store_summary(store.id, since, timeframe_days)
+            .store_summary(
+                store.id,
+                since,
+                generated_at,
+                timeframe_days,
+                payment_status,
+            )
             .await?;
 
-        let sales_trend = self.analytics.store_sales_trend(store.id, since).await?;
+        let comparison = match compare {
+            Some(compare) => {
+                let (from, until) = compare.window(since, generated_at);
+                let previous = self
+                    .analytics
+                    .store_summary(store.id, from, until, timeframe_days, payment_status)
+                    .await?;
+                Some(StoreSummaryComparison::new(
+                    compare,
+                    (from, until),
+                    &summary,
+                    previous,
+                ))
+            }
+            None => None,
+        };
+
+        let sales_trend = self
+            .analytics
+            .store_sales_trend(store.id, since, payment_status)
+            .await?;
 
         let top_products = self
             .analytics
-            .store_top_products(store.id, since, top_products_limit)
+            .store_top_products(store.id, since, top_products, payment_status)
+            .await?;
+
+        let funnel = self
+            .analytics
+            .store_funnel(store.id, since, timeframe_days)
             .await?;
 
-        Ok(StoreAnalyticsResponse {
+        let analytics = StoreAnalyticsResponse {
             summary,
             sales_trend,
             top_products,
+            funnel,
+            comparison,
+            generated_at,
+            cached: false,
+        };
+        if let Some(cache) = &self.cache {
+            cache.insert(key, analytics.clone());
+        }
+        Ok(analytics)
+    }
+
+    pub async fn store_funnel(
+        &self,
+        store_id: Uuid,
+        timeframe_days: i64,
+    ) -> crate::Result<StoreFunnel> {
+        let store = self
+            .get_store_within_retention(store_id, timeframe_days)
+            .await?;
+        let since = Utc::now() - Duration::days(timeframe_days);
+        self.analytics
+            .store_funnel(store.id, since, timeframe_days)
+            .await
+    }
+
+    /// Stock KPIs for the store's products over the timeframe, the ones
+    /// projected to run out soonest first and those not selling last.
+    pub async fn store_inventory(
+        &self,
+        store_id: Uuid,
+        timeframe_days: i64,
+    ) -> crate::Result<StoreInventoryAnalytics> {
+        let store = self
+            .get_store_within_retention(store_id, timeframe_days)
+            .await?;
+        let since = Utc::now() - Duration::days(timeframe_days);
+        let mut products = self
+            .analytics
+            .store_inventory(store.id, since, timeframe_days)
+            .await?;
+        products.sort_by(|a, b| {
+            let days = |stats: &ProductInventoryStats| stats.days_of_stock.unwrap_or(f64::INFINITY);
+            days(a)
+                .total_cmp(&days(b))
+                .then_with(|| b.stockout_days.cmp(&a.stockout_days))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        Ok(StoreInventoryAnalytics {
+            timeframe_days,
+            products,
         })
     }
+
+    /// Tax collected per period, jurisdiction and rate between `from` and
+    /// `to` inclusive, defaulting to the year to date. Not bound by the
+    /// plan's analytics retention, since stores need it for filings.
+    pub async fn tax_report(
+        &self,
+        store_id: Uuid,
+        period: TaxReportPeriod,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+    ) -> crate::Result<Vec<TaxReportRow>> {
+        self.stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+
+        let today = Utc::now().date_naive();
+        let to = to.unwrap_or(today);
+        let from = match from {
+            Some(from) => from,
+            None => NaiveDate::from_ymd_opt(to.year(), 1, 1).unwrap_or(to),
+        };
+        if from > to {
+            return Err(AppError::BadRequest(
+                "Report start must not be after its end".into(),
+            ));
+        }
+
+        self.analytics
+            .tax_summary(store_id, period, from, to + Duration::days(1))
+            .await
+    }
+
+    /// Brings the daily sales rollups up to yesterday, rebuilding the last
+    /// `lookback_days` (at least one) along the way.
+    pub async fn refresh_rollups(&self, lookback_days: i64) -> crate::Result<u64> {
+        self.analytics.refresh_rollups(lookback_days.max(1)).await
+    }
+
+    async fn get_store_within_retention(
+        &self,
+        store_id: Uuid,
+        timeframe_days: i64,
+    ) -> crate::Result<Store> {
+        let store = self
+            .stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+
+        let retention = store.plan.limits().analytics_retention_days;
+        if timeframe_days > retention {
+            return Err(AppError::QuotaExceeded(format!(
+                "The {:?} plan keeps {} days of analytics",
+                store.plan, retention
+            )));
+        }
+        Ok(store)
+    }
+}
+
+const TAX_REPORT_CSV_HEADER: [&str; 6] = [
+    "period_start",
+    "jurisdiction",
+    "rate",
+    "order_count",
+    "taxable_amount",
+    "tax_collected",
+];
+
+pub fn encode_tax_report_csv(rows: &[TaxReportRow]) -> crate::Result<Bytes> {
+    Ok(Bytes::from(encode_csv(&TAX_REPORT_CSV_HEADER, rows)?))
+}
+
+const ANALYTICS_SUMMARY_CSV_HEADER: [&str; 9] = [
+    "total_orders",
+    "total_revenue",
+    "average_order_value",
+    "unique_customers",
+    "timeframe_days",
+    "paid_orders",
+    "paid_revenue",
+    "pending_orders",
+    "pending_revenue",
+];
+const ANALYTICS_FUNNEL_CSV_HEADER: [&str; 9] = [
+    "timeframe_days",
+    "viewed_sessions",
+    "cart_sessions",
+    "checkout_sessions",
+    "paid_orders",
+    "view_to_cart_rate",
+    "cart_to_checkout_rate",
+    "checkout_to_paid_rate",
+    "overall_conversion_rate",
+];
+const ANALYTICS_COMPARISON_CSV_HEADER: [&str; 5] =
+    ["metric", "current", "previous", "change", "percent_change"];
+const ANALYTICS_TREND_CSV_HEADER: [&str; 3] = ["date", "order_count", "total_revenue"];
+const ANALYTICS_TOP_PRODUCTS_CSV_HEADER: [&str; 4] =
+    ["product_id", "product_name", "units_sold", "revenue"];
+
+#[derive(Serialize)]
+struct ComparisonCsvRow<'a> {
+    metric: &'a str,
+    current: Decimal,
+    previous: Decimal,
+    change: Decimal,
+    percent_change: Option<f64>,
+}
+
+impl<'a> ComparisonCsvRow<'a> {
+    fn new(metric: &'a str, delta: &MetricDelta) -> Self {
+        Self {
+            metric,
+            current: delta.current,
+            previous: delta.previous,
+            change: delta.change,
+            percent_change: delta.percent_change,
+        }
+    }
+}
+
+/// The analytics report as CSV: the summary, the comparison when asked for,
+/// the conversion funnel, the daily sales trend and the top products, each
+/// with its own header and separated by a blank line.
+pub fn encode_analytics_csv(analytics: &StoreAnalyticsResponse) -> crate::Result<Bytes> {
+    let mut sections = vec![encode_csv(
+        &ANALYTICS_SUMMARY_CSV_HEADER,
+        std::slice::from_ref(&analytics.summary),
+    )?];
+    if let Some(comparison) = &analytics.comparison {
+        let rows = [
+            ("total_orders", &comparison.total_orders),
+            ("total_revenue", &comparison.total_revenue),
+            ("average_order_value", &comparison.average_order_value),
+            ("unique_customers", &comparison.unique_customers),
+        ]
+        .map(|(metric, delta)| ComparisonCsvRow::new(metric, delta));
+        sections.push(encode_csv(&ANALYTICS_COMPARISON_CSV_HEADER, &rows)?);
+    }
+    sections.extend([
+        encode_csv(
+            &ANALYTICS_FUNNEL_CSV_HEADER,
+            std::slice::from_ref(&analytics.funnel),
+        )?,
+        encode_csv(&ANALYTICS_TREND_CSV_HEADER, &analytics.sales_trend)?,
+        encode_csv(&ANALYTICS_TOP_PRODUCTS_CSV_HEADER, &analytics.top_products)?,
+    ]);
+    Ok(Bytes::from(sections.join(&b"\n"[..])))
+}
+
+fn encode_csv<T: Serialize>(header: &[&str], rows: &[T]) -> crate::Result<Vec<u8>> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    writer.write_record(header).map_err(anyhow::Error::new)?;
+    for row in rows {
+        writer.serialize(row).map_err(anyhow::Error::new)?;
+    }
+    let data = writer
+        .into_inner()
+        .map_err(|err| anyhow::anyhow!(err.to_string()))?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        order::PaymentStatus,
+        store::{StoreAnalyticsSummary, StoreSalesPoint, StoreTopProduct},
+    };
+
+    #[test]
+    fn tax_report_csv_has_header_and_rows() {
+        let empty = encode_tax_report_csv(&[]).unwrap();
+        assert_eq!(
+            empty,
+            "period_start,jurisdiction,rate,order_count,taxable_amount,tax_collected\n"
+        );
+
+        let rows = [TaxReportRow {
+            period_start: NaiveDate::from_ymd_opt(2026, 4, 1).unwrap(),
+            jurisdiction: "US-CA".into(),
+            rate: Decimal::new(725, 4),
+            order_count: 3,
+            taxable_amount: Decimal::new(20000, 2),
+            tax_collected: Decimal::new(1450, 2),
+        }];
+        let csv = encode_tax_report_csv(&rows).unwrap();
+        assert!(csv.ends_with(b"2026-04-01,US-CA,0.0725,3,200.00,14.50\n"));
+    }
+
+    #[test]
+    fn cache_expires_entries() {
+        let report = StoreAnalyticsResponse {
+            summary: StoreAnalyticsSummary {
+                total_orders: 0,
+                total_revenue: Decimal::ZERO,
+                average_order_value: Decimal::ZERO,
+                unique_customers: 0,
+                timeframe_days: 30,
+                paid_orders: 0,
+                paid_revenue: Decimal::ZERO,
+                pending_orders: 0,
+                pending_revenue: Decimal::ZERO,
+            },
+            sales_trend: Vec::new(),
+            top_products: Vec::new(),
+            funnel: StoreFunnel::new(30, 0, 0, 0, 0),
+            comparison: None,
+            generated_at: Utc::now(),
+            cached: false,
+        };
+        let params = StoreAnalyticsParams {
+            timeframe_days: 30,
+            top_products: 5,
+            payment_status: PaymentStatus::Paid,
+            compare: None,
+        };
+        let key = (Uuid::nil(), params);
+
+        let cache = AnalyticsCache::new(std::time::Duration::ZERO, 10);
+        cache.insert(key, report.clone());
+        assert!(cache.get(&key).is_none());
+
+        let cache = AnalyticsCache::default();
+        cache.insert(key, report);
+        assert!(cache.get(&key).is_some());
+        let pending = StoreAnalyticsParams {
+            payment_status: PaymentStatus::Pending,
+            ..params
+        };
+        assert!(cache.get(&(Uuid::nil(), pending)).is_none());
+    }
+
+    #[test]
+    fn analytics_csv_lists_each_section() {
+        let product_id = Uuid::nil();
+        let analytics = StoreAnalyticsResponse {
+            summary: StoreAnalyticsSummary {
+                total_orders: 2,
+                total_revenue: Decimal::new(5000, 2),
+                average_order_value: Decimal::new(2500, 2),
+                unique_customers: 2,
+                timeframe_days: 30,
+                paid_orders: 2,
+                paid_revenue: Decimal::new(5000, 2),
+                pending_orders: 1,
+                pending_revenue: Decimal::new(1200, 2),
+            },
+            sales_trend: vec![StoreSalesPoint {
+                date: NaiveDate::from_ymd_opt(2026, 4, 1).unwrap(),
+                order_count: 2,
+                total_revenue: Decimal::new(5000, 2),
+            }],
+            top_products: vec![StoreTopProduct {
+                product_id,
+                product_name: "Desk lamp".into(),
+                units_sold: 3,
+                revenue: Decimal::new(5000, 2),
+            }],
+            funnel: StoreFunnel::new(30, 10, 4, 2, 1),
+            comparison: None,
+            generated_at: Utc::now(),
+            cached: false,
+        };
+
+        let csv = encode_analytics_csv(&analytics).unwrap();
+        assert_eq!(
+            String::from_utf8(csv.to_vec()).unwrap(),
+            format!(
+                "total_orders,total_revenue,average_order_value,unique_customers,timeframe_days,\
+                 paid_orders,paid_revenue,pending_orders,pending_revenue\n\
+                 2,50.00,25.00,2,30,2,50.00,1,12.00\n\
+                 \n\
+                 timeframe_days,viewed_sessions,cart_sessions,checkout_sessions,paid_orders,\
+                 view_to_cart_rate,cart_to_checkout_rate,checkout_to_paid_rate,overall_conversion_rate\n\
+                 30,10,4,2,1,0.4,0.5,0.5,0.1\n\
+                 \n\
+                 date,order_count,total_revenue\n\
+                 2026-04-01,2,50.00\n\
+                 \n\
+                 product_id,product_name,units_sold,revenue\n\
+                 {},Desk lamp,3,50.00\n",
+                product_id
+            )
+        );
+    }
 }
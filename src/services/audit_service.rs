@@ -0,0 +1,64 @@
+use uuid::Uuid;
+
+use crate::{
+    middleware::{client_ip::current_client_ip, request_id::current_request_id},
+    models::audit::{AuditEvent, AuditEventFilter, NewAuditEvent},
+    repositories::AuditRepository,
+};
+
+/// Records sensitive actions (sign-ins, permission, price and order status
+/// changes, refunds) in the audit log, and reads it back for store owners
+/// and platform admins.
+#[derive(Clone)]
+pub struct AuditService {
+    repo: AuditRepository,
+}
+
+impl AuditService {
+    pub fn new(repo: AuditRepository) -> Self {
+        Self { repo }
+    }
+
+    /// Records an action that has already happened, with the client IP and
+    /// request id of the request being handled. The action is never undone
+    /// over its audit entry: failures are only logged.
+    pub async fn record(&self, event: NewAuditEvent) {
+        let request_id = current_request_id();
+        if let Err(err) = self
+            .repo
+            .insert(&event, current_client_ip(), request_id.as_deref())
+            .await
+        {
+            tracing::error!(
+                "Recording {:?} audit event for {} {:?} failed: {}",
+                event.action,
+                event.entity_type,
+                event.entity_id,
+                err
+            );
+        }
+    }
+
+    /// The store's events, newest first.
+    pub async fn list_for_store(
+        &self,
+        store_id: Uuid,
+        filter: &AuditEventFilter,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<AuditEvent>> {
+        self.repo.list(Some(store_id), filter, limit, offset).await
+    }
+
+    /// Events across the platform, or of one store when `store_id` is set,
+    /// newest first.
+    pub async fn list(
+        &self,
+        store_id: Option<Uuid>,
+        filter: &AuditEventFilter,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<AuditEvent>> {
+        self.repo.list(store_id, filter, limit, offset).await
+    }
+}
@@ -4,20 +4,39 @@ use validator::Validate;
 
 use crate::{
     error::AppError,
-    models::user::{AuthTokenResponse, LoginRequest, PublicUser, RegisterUserRequest, User},
+    events::{Event, EventBus},
+    models::user::{
+        AuthTokenResponse, ChangePasswordRequest, LoginRequest, PublicUser, RegisterUserRequest,
+        User,
+    },
     repositories::UserRepository,
-    utils::{jwt::JwtConfig, password},
+    utils::{
+        jwt::{Claims, JwtConfig},
+        password,
+    },
 };
+use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct AuthService {
     users: UserRepository,
     jwt: Arc<JwtConfig>,
+    events: EventBus,
 }
 
 impl AuthService {
     pub fn new(users: UserRepository, jwt: Arc<JwtConfig>) -> Self {
-        Self { users, jwt }
+        Self {
+            users,
+            jwt,
+            events: EventBus::new(),
+        }
+    }
+
+    /// Announces sign-ups on `events`.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
     }
 
     pub async fn register(&self, payload: RegisterUserRequest) -> crate::Result<AuthTokenResponse> {
@@ -41,6 +60,12 @@ impl AuthService {
                 payload.phone.as_deref(),
             )
             .await?;
+        self.events
+            .publish(&Event::UserRegistered {
+                user_id: user.id,
+                email: user.email.clone(),
+            })
+            .await;
 
         self.build_response(user)
     }
@@ -66,8 +91,53 @@ impl AuthService {
         self.build_response(user)
     }
 
+    pub async fn change_password(
+        &self,
+        user_id: Uuid,
+        payload: ChangePasswordRequest,
+    ) -> crate::Result<AuthTokenResponse> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let user = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("User not found".into()))?;
+
+        let is_valid = password::verify_password(&payload.current_password, &user.password_hash)
+            .map_err(AppError::Internal)?;
+
+        if !is_valid {
+            return Err(AppError::Authentication("Invalid credentials".into()));
+        }
+
+        let password_hash =
+            password::hash_password(&payload.new_password).map_err(AppError::Internal)?;
+        let user = self.users.update_password(user.id, &password_hash).await?;
+
+        self.build_response(user)
+    }
+
+    /// Verifies a session token and checks it was issued for the user's
+    /// current token version.
+    pub async fn authenticate(&self, token: &str) -> crate::Result<Claims> {
+        let claims = self
+            .jwt
+            .verify(token)
+            .map_err(|_| AppError::Authentication("Invalid token".into()))?;
+
+        match self.users.token_version(claims.sub).await? {
+            Some(version) if version == claims.ver => Ok(claims),
+            _ => Err(AppError::Authentication("Token has been revoked".into())),
+        }
+    }
+
     fn build_response(&self, user: User) -> crate::Result<AuthTokenResponse> {
-        let claims = self.jwt.claims_for(user.id, user.email.clone());
+        let claims = self
+            .jwt
+            .claims_for(user.id, user.email.clone(), user.token_version);
         let token = self
             .jwt
             .generate(&claims)
@@ -0,0 +1,232 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{error::AppError, models::store::StoreSettings};
+
+/// The part of a store's settings that decides whether its items can share
+/// a cart with other stores' items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CartStore {
+    pub store_id: Uuid,
+    pub name: String,
+    pub currency_code: String,
+    pub ships_to: Vec<String>,
+    /// Whether the cart holds (or would hold) physical items from the store.
+    pub ships: bool,
+}
+
+impl CartStore {
+    pub fn new(name: String, settings: &StoreSettings, ships: bool) -> Self {
+        Self {
+            store_id: settings.store_id,
+            name,
+            currency_code: settings.currency_code.clone(),
+            ships_to: settings.ships_to.clone(),
+            ships,
+        }
+    }
+
+    fn restricts_shipping(&self) -> bool {
+        self.ships && !self.ships_to.is_empty()
+    }
+}
+
+/// Platform rules for carts spanning several stores, checked when items are
+/// added so shoppers learn about conflicts before reaching checkout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CartPolicy {
+    pub max_stores: usize,
+}
+
+impl Default for CartPolicy {
+    fn default() -> Self {
+        Self { max_stores: 10 }
+    }
+}
+
+impl CartPolicy {
+    /// Checks that `incoming` can join a cart already holding `existing`.
+    pub fn check_add(&self, existing: &[CartStore], incoming: &CartStore) -> crate::Result<()> {
+        let is_new_store = existing
+            .iter()
+            .all(|store| store.store_id != incoming.store_id);
+        if is_new_store && existing.len() >= self.max_stores {
+            return Err(AppError::Conflict(format!(
+                "A cart can hold items from at most {} stores. Check out or remove items from another store first",
+                self.max_stores
+            )));
+        }
+        check_compatible(existing, incoming)
+    }
+
+    /// Re-checks the whole cart at checkout, since store settings may have
+    /// changed since items were added, and that every store shipping
+    /// physical items delivers to the address.
+    pub fn check_checkout(
+        &self,
+        stores: &[CartStore],
+        shipping_address: &Value,
+    ) -> crate::Result<()> {
+        for (index, store) in stores.iter().enumerate() {
+            check_compatible(&stores[..index], store)?;
+        }
+
+        let country = shipping_address
+            .get("country")
+            .and_then(Value::as_str)
+            .map(|country| country.trim().to_uppercase())
+            .filter(|country| !country.is_empty());
+        for store in stores.iter().filter(|store| store.restricts_shipping()) {
+            match &country {
+                Some(country) if store.ships_to.contains(country) => {}
+                Some(country) => {
+                    return Err(AppError::BadRequest(format!(
+                        "{} does not ship to {}",
+                        store.name, country
+                    )))
+                }
+                None => {
+                    return Err(AppError::BadRequest(format!(
+                        "Shipping address needs a country; {} only ships to {}",
+                        store.name,
+                        store.ships_to.join(", ")
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Currency and shipping-destination conflicts between `incoming` and the
+/// other stores in the cart.
+fn check_compatible(existing: &[CartStore], incoming: &CartStore) -> crate::Result<()> {
+    let others: Vec<&CartStore> = existing
+        .iter()
+        .filter(|store| store.store_id != incoming.store_id)
+        .collect();
+
+    if let Some(other) = others
+        .iter()
+        .find(|store| store.currency_code != incoming.currency_code)
+    {
+        return Err(AppError::Conflict(format!(
+            "{} sells in {}, but your cart holds items priced in {} from {}. Check out or remove those items first",
+            incoming.name, incoming.currency_code, other.currency_code, other.name
+        )));
+    }
+
+    if !incoming.restricts_shipping() {
+        return Ok(());
+    }
+    let restricted: Vec<&&CartStore> = others
+        .iter()
+        .filter(|store| store.restricts_shipping())
+        .collect();
+    if let Some(other) = restricted
+        .iter()
+        .find(|store| !store.ships_to.iter().any(|c| incoming.ships_to.contains(c)))
+    {
+        return Err(AppError::Conflict(format!(
+            "{} ships to {} and {} ships to {}, so no address can receive both. Check out or remove those items first",
+            incoming.name,
+            incoming.ships_to.join(", "),
+            other.name,
+            other.ships_to.join(", ")
+        )));
+    }
+
+    let mut common: BTreeSet<&String> = incoming.ships_to.iter().collect();
+    for store in &restricted {
+        common.retain(|country| store.ships_to.contains(country));
+    }
+    if common.is_empty() {
+        return Err(AppError::Conflict(format!(
+            "No country is served by {} and every other store in your cart. Check out or remove some items first",
+            incoming.name
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store(name: &str, currency: &str, ships_to: &[&str]) -> CartStore {
+        CartStore {
+            store_id: Uuid::new_v4(),
+            name: name.into(),
+            currency_code: currency.into(),
+            ships_to: ships_to.iter().map(|c| c.to_string()).collect(),
+            ships: true,
+        }
+    }
+
+    #[test]
+    fn rejects_mixed_currencies() {
+        let policy = CartPolicy::default();
+        let cart = [store("Acme", "USD", &[])];
+        assert!(policy.check_add(&cart, &store("Bolt", "USD", &[])).is_ok());
+
+        let err = policy
+            .check_add(&cart, &store("Euro Shop", "EUR", &[]))
+            .unwrap_err();
+        assert!(err.to_string().contains("priced in USD from Acme"));
+    }
+
+    #[test]
+    fn rejects_stores_without_a_common_destination() {
+        let policy = CartPolicy::default();
+        let cart = [
+            store("Acme", "USD", &["US", "CA"]),
+            store("Bolt", "USD", &["CA", "DE"]),
+        ];
+        assert!(policy
+            .check_add(&cart, &store("Maple", "USD", &["CA"]))
+            .is_ok());
+        assert!(policy
+            .check_add(&cart, &store("Anywhere", "USD", &[]))
+            .is_ok());
+
+        let err = policy
+            .check_add(&cart[..1], &store("Berlin", "USD", &["DE"]))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Conflict(_)));
+        assert!(policy
+            .check_add(&cart, &store("Overlap", "USD", &["US", "DE"]))
+            .is_err());
+
+        let mut digital = store("Ebooks", "USD", &["DE"]);
+        digital.ships = false;
+        assert!(policy.check_add(&cart[..1], &digital).is_ok());
+    }
+
+    #[test]
+    fn limits_distinct_stores() {
+        let policy = CartPolicy { max_stores: 2 };
+        let cart = [store("Acme", "USD", &[]), store("Bolt", "USD", &[])];
+        assert!(policy.check_add(&cart, &cart[1]).is_ok());
+        assert!(policy.check_add(&cart, &store("Cove", "USD", &[])).is_err());
+    }
+
+    #[test]
+    fn checkout_requires_a_served_country() {
+        let policy = CartPolicy::default();
+        let stores = [store("Acme", "USD", &["US"]), store("Bolt", "USD", &[])];
+        assert!(policy
+            .check_checkout(&stores, &json!({"country": "us"}))
+            .is_ok());
+
+        let err = policy
+            .check_checkout(&stores, &json!({"country": "DE"}))
+            .unwrap_err();
+        assert!(err.to_string().contains("Acme does not ship to DE"));
+        assert!(policy
+            .check_checkout(&stores, &json!({"line1": "1 Main"}))
+            .is_err());
+    }
+}
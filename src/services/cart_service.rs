@@ -1,28 +1,60 @@
+use chrono::Duration;
 use validator::Validate;
 
 use crate::{
     error::AppError,
-    models::order::{AddCartItemRequest, CartItem, CartItemDetail},
-    repositories::{CartRepository, ProductRepository},
+    models::{
+        order::{AddCartItemRequest, CartItem, CartItemDetail, CartOwner},
+        product::{Product, ProductType},
+    },
+    repositories::{CartRepository, ProductRepository, StoreSettingsRepository},
+    services::cart_policy::{CartPolicy, CartStore},
 };
 use uuid::Uuid;
 
+/// How long stock stays reserved for a cart line unless configured otherwise.
+pub const DEFAULT_RESERVATION_TTL_SECS: i64 = 15 * 60;
+
 #[derive(Clone)]
 pub struct CartService {
     carts: CartRepository,
     products: ProductRepository,
+    settings: StoreSettingsRepository,
+    policy: CartPolicy,
+    reservation_ttl: Duration,
 }
 
 impl CartService {
-    pub fn new(carts: CartRepository, products: ProductRepository) -> Self {
-        Self { carts, products }
+    pub fn new(
+        carts: CartRepository,
+        products: ProductRepository,
+        settings: StoreSettingsRepository,
+    ) -> Self {
+        Self {
+            carts,
+            products,
+            settings,
+            policy: CartPolicy::default(),
+            reservation_ttl: Duration::seconds(DEFAULT_RESERVATION_TTL_SECS),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: CartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.reservation_ttl = ttl;
+        self
     }
 
     pub async fn add_item(
         &self,
-        user_id: Uuid,
+        owner: impl Into<CartOwner>,
         payload: AddCartItemRequest,
     ) -> crate::Result<CartItem> {
+        let owner = owner.into();
         payload
             .validate()
             .map_err(|err| AppError::Validation(err.to_string()))?;
@@ -33,28 +65,143 @@ impl CartService {
             .await?
             .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
 
+        if product.is_archived() {
+            return Err(AppError::NotFound("Product not found".into()));
+        }
         if !product.is_active {
             return Err(AppError::BadRequest("Product is inactive".into()));
         }
 
-        if product.stock_quantity < payload.quantity {
-            return Err(AppError::Conflict("Insufficient stock".into()));
+        self.ensure_fits_cart(owner, &product).await?;
+
+        self.carts
+            .upsert_item(
+                owner,
+                payload.product_id,
+                payload.quantity,
+                self.reservation_ttl,
+            )
+            .await
+    }
+
+    /// The cart's lines, each flagged with any price change, withdrawal or
+    /// stock shortfall checkout would trip over.
+    pub async fn list_items(
+        &self,
+        owner: impl Into<CartOwner>,
+    ) -> crate::Result<Vec<CartItemDetail>> {
+        let items = self.carts.list_with_products(owner.into()).await?;
+        Ok(with_warnings(items))
+    }
+
+    pub async fn list_saved(
+        &self,
+        owner: impl Into<CartOwner>,
+    ) -> crate::Result<Vec<CartItemDetail>> {
+        let items = self.carts.list_saved(owner.into()).await?;
+        Ok(with_warnings(items))
+    }
+
+    /// Sets a cart line aside; it stays with the shopper but is not bought
+    /// at checkout and stops holding stock.
+    pub async fn save_for_later(
+        &self,
+        owner: impl Into<CartOwner>,
+        product_id: Uuid,
+    ) -> crate::Result<CartItem> {
+        self.carts.save_for_later(owner.into(), product_id).await
+    }
+
+    /// Moves a saved line back into the cart, subject to the cart policy
+    /// and available stock like a fresh add.
+    pub async fn restore_saved(
+        &self,
+        owner: impl Into<CartOwner>,
+        product_id: Uuid,
+    ) -> crate::Result<CartItem> {
+        let owner = owner.into();
+        let product = self
+            .products
+            .find_by_id(product_id)
+            .await?
+            .filter(|product| !product.is_archived())
+            .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+        if !product.is_active {
+            return Err(AppError::BadRequest("Product is inactive".into()));
         }
 
+        self.ensure_fits_cart(owner, &product).await?;
+
         self.carts
-            .upsert_item(user_id, payload.product_id, payload.quantity)
+            .restore_saved(owner, product_id, self.reservation_ttl)
             .await
     }
 
-    pub async fn list_items(&self, user_id: Uuid) -> crate::Result<Vec<CartItemDetail>> {
-        self.carts.list_with_products(user_id).await
+    pub async fn remove_item(
+        &self,
+        owner: impl Into<CartOwner>,
+        product_id: Uuid,
+    ) -> crate::Result<()> {
+        self.carts.remove_item(owner.into(), product_id).await
+    }
+
+    /// Applies the platform cart policy to the cart as it would be with
+    /// `product` added.
+    async fn ensure_fits_cart(&self, owner: CartOwner, product: &Product) -> crate::Result<()> {
+        let items = self.carts.list_with_products(owner).await?;
+        let grouped = CartItemDetail::group_by_store(&items);
+        let mut store_ids: Vec<Uuid> = grouped.keys().copied().collect();
+        store_ids.push(product.store_id);
+        let settings = self.settings.find_for_stores(&store_ids).await?;
+
+        let mut existing: Vec<CartStore> = grouped
+            .iter()
+            .map(|(store_id, items)| {
+                let ships = items
+                    .iter()
+                    .any(|item| item.product_type == ProductType::Physical);
+                CartStore::new(items[0].store_name.clone(), &settings[store_id], ships)
+            })
+            .collect();
+        existing.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let incoming = match existing
+            .iter()
+            .find(|store| store.store_id == product.store_id)
+        {
+            Some(current) => CartStore {
+                ships: current.ships || product.product_type == ProductType::Physical,
+                ..current.clone()
+            },
+            None => CartStore::new(
+                "This store".into(),
+                &settings[&product.store_id],
+                product.product_type == ProductType::Physical,
+            ),
+        };
+        self.policy.check_add(&existing, &incoming)
     }
 
-    pub async fn remove_item(&self, user_id: Uuid, product_id: Uuid) -> crate::Result<()> {
-        self.carts.remove_item(user_id, product_id).await
+    pub async fn clear(&self, owner: impl Into<CartOwner>) -> crate::Result<u64> {
+        self.carts.clear(owner.into()).await
     }
 
-    pub async fn clear(&self, user_id: Uuid) -> crate::Result<()> {
-        self.carts.clear_user(user_id).await
+    /// Moves a guest's cart into the user's when they sign in or register,
+    /// returning the user's merged lines.
+    pub async fn merge_guest(
+        &self,
+        guest_session_id: Uuid,
+        user_id: Uuid,
+    ) -> crate::Result<Vec<CartItem>> {
+        self.carts
+            .merge_guest(guest_session_id, user_id, self.reservation_ttl)
+            .await
+    }
+}
+
+fn with_warnings(mut items: Vec<CartItemDetail>) -> Vec<CartItemDetail> {
+    for item in &mut items {
+        item.warnings = item.detect_warnings();
     }
+    items
 }
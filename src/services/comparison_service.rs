@@ -0,0 +1,113 @@
+use std::collections::{HashMap, HashSet};
+
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::{
+        product::{CompareProductsRequest, Product, ProductComparison},
+        store::{Store, StoreStatus},
+    },
+    repositories::{
+        ProductImageRepository, ProductRepository, StoreRepository, StoreSettingsRepository,
+    },
+};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ComparisonService {
+    products: ProductRepository,
+    images: ProductImageRepository,
+    stores: StoreRepository,
+    settings: StoreSettingsRepository,
+}
+
+impl ComparisonService {
+    pub fn new(
+        products: ProductRepository,
+        images: ProductImageRepository,
+        stores: StoreRepository,
+        settings: StoreSettingsRepository,
+    ) -> Self {
+        Self {
+            products,
+            images,
+            stores,
+            settings,
+        }
+    }
+
+    /// Compares products in the order they were requested. Like share cards,
+    /// comparisons are public: products a guest could not browse to are
+    /// reported as missing.
+    pub async fn compare(
+        &self,
+        payload: CompareProductsRequest,
+    ) -> crate::Result<ProductComparison> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let unique: HashSet<Uuid> = payload.product_ids.iter().copied().collect();
+        if unique.len() != payload.product_ids.len() {
+            return Err(AppError::Validation(
+                "product_ids must not contain duplicates".into(),
+            ));
+        }
+
+        let mut products: HashMap<Uuid, Product> = self
+            .products
+            .find_by_ids(&payload.product_ids)
+            .await?
+            .into_iter()
+            .filter(|product| product.is_active && !product.is_archived())
+            .map(|product| (product.id, product))
+            .collect();
+
+        let mut stores: HashMap<Uuid, Store> = HashMap::new();
+        for product in products.values() {
+            if stores.contains_key(&product.store_id) {
+                continue;
+            }
+            if let Some(store) = self.stores.find_by_id(product.store_id).await? {
+                stores.insert(store.id, store);
+            }
+        }
+        stores.retain(|_, store| !store.is_private && store.status == StoreStatus::Active);
+
+        let mut ordered: Vec<Product> = payload
+            .product_ids
+            .iter()
+            .map(|id| {
+                products
+                    .remove(id)
+                    .filter(|product| stores.contains_key(&product.store_id))
+                    .ok_or_else(|| AppError::NotFound(format!("Product {} not found", id)))
+            })
+            .collect::<crate::Result<_>>()?;
+
+        let mut galleries: HashMap<Uuid, Vec<_>> = HashMap::new();
+        for image in self.images.list_for_products(&payload.product_ids).await? {
+            galleries.entry(image.product_id).or_default().push(image);
+        }
+        for product in &mut ordered {
+            product.images = galleries.remove(&product.id).unwrap_or_default();
+        }
+
+        let store_ids: Vec<Uuid> = stores.keys().copied().collect();
+        let settings = self.settings.find_for_stores(&store_ids).await?;
+
+        let entries: Vec<(&Product, &str, &str)> = ordered
+            .iter()
+            .map(|product| {
+                (
+                    product,
+                    stores[&product.store_id].name.as_str(),
+                    settings[&product.store_id].currency_code.as_str(),
+                )
+            })
+            .collect();
+
+        Ok(ProductComparison::new(&entries))
+    }
+}
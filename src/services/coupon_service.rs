@@ -0,0 +1,148 @@
+use chrono::Utc;
+use rust_decimal::Decimal;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::{
+        coupon::{AppliedCoupon, ApplyCouponRequest, Coupon, CreateCouponRequest},
+        order::{CartItemDetail, CartOwner},
+    },
+    repositories::{CartRepository, CouponRepository, StoreRepository},
+};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct CouponService {
+    coupons: CouponRepository,
+    stores: StoreRepository,
+    carts: CartRepository,
+}
+
+impl CouponService {
+    pub fn new(coupons: CouponRepository, stores: StoreRepository, carts: CartRepository) -> Self {
+        Self {
+            coupons,
+            stores,
+            carts,
+        }
+    }
+
+    pub async fn create_coupon(
+        &self,
+        store_id: Uuid,
+        payload: CreateCouponRequest,
+    ) -> crate::Result<Coupon> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        self.stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+
+        let value = decimal_from_f64(payload.value)?.round_dp(2);
+        let min_order_value = payload
+            .min_order_value
+            .map(decimal_from_f64)
+            .transpose()?
+            .map(|value| value.round_dp(2));
+        self.coupons
+            .create(
+                store_id,
+                &payload.code,
+                payload.kind,
+                value,
+                min_order_value,
+                payload.usage_limit,
+                payload.expires_at,
+            )
+            .await
+    }
+
+    pub async fn list_coupons(&self, store_id: Uuid) -> crate::Result<Vec<Coupon>> {
+        self.coupons.list_for_store(store_id).await
+    }
+
+    pub async fn deactivate_coupon(&self, store_id: Uuid, coupon_id: Uuid) -> crate::Result<()> {
+        if !self.coupons.deactivate(store_id, coupon_id).await? {
+            return Err(AppError::NotFound("Coupon not found".into()));
+        }
+        Ok(())
+    }
+
+    /// Applies a code to the store in the cart that issued it, after
+    /// checking it against that store's current lines. Checkout re-checks
+    /// it, so a coupon that lapses in the meantime simply stops applying.
+    pub async fn apply_coupon(
+        &self,
+        user_id: Uuid,
+        payload: ApplyCouponRequest,
+    ) -> crate::Result<AppliedCoupon> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let items = self
+            .carts
+            .list_with_products(CartOwner::User(user_id))
+            .await?;
+        if items.is_empty() {
+            return Err(AppError::BadRequest("Cart is empty".into()));
+        }
+        let grouped = CartItemDetail::group_by_store(&items);
+        let store_ids: Vec<Uuid> = match payload.store_id {
+            Some(store_id) => vec![store_id],
+            None => grouped.keys().copied().collect(),
+        };
+
+        let mut matches = self.coupons.find_by_code(&payload.code, &store_ids).await?;
+        let coupon = match matches.len() {
+            0 => return Err(AppError::NotFound("Coupon not found".into())),
+            1 => matches.remove(0),
+            _ => {
+                return Err(AppError::BadRequest(
+                    "Several stores in the cart use this code; set store_id".into(),
+                ))
+            }
+        };
+        let subtotal = grouped
+            .get(&coupon.store_id)
+            .map(|items| store_subtotal(items))
+            .ok_or_else(|| AppError::BadRequest("Cart has no items from this store".into()))?;
+        if let Some(reason) = coupon.ineligibility(subtotal, Utc::now()) {
+            return Err(AppError::BadRequest(reason));
+        }
+
+        self.coupons.apply(user_id, &coupon).await?;
+        Ok(AppliedCoupon {
+            store_id: coupon.store_id,
+            discount: coupon.discount_for(subtotal),
+            code: coupon.code,
+            kind: coupon.kind,
+            value: coupon.value,
+            subtotal,
+        })
+    }
+
+    pub async fn remove_coupon(&self, user_id: Uuid, store_id: Uuid) -> crate::Result<()> {
+        if !self.coupons.remove_applied(user_id, store_id).await? {
+            return Err(AppError::NotFound(
+                "No coupon applied for this store".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn store_subtotal(items: &[CartItemDetail]) -> Decimal {
+    items.iter().fold(Decimal::ZERO, |acc, item| {
+        acc + item.unit_price * Decimal::from(item.quantity)
+    })
+}
+
+fn decimal_from_f64(value: f64) -> crate::Result<Decimal> {
+    Decimal::from_f64_retain(value)
+        .ok_or_else(|| AppError::Validation("Invalid decimal value".into()))
+}
@@ -0,0 +1,71 @@
+use rust_decimal::Decimal;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::credit::{IssueCreditRequest, StoreCreditBalance, StoreCreditEntry},
+    repositories::{CreditRepository, StoreRepository},
+};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct CreditService {
+    credits: CreditRepository,
+    stores: StoreRepository,
+}
+
+impl CreditService {
+    pub fn new(credits: CreditRepository, stores: StoreRepository) -> Self {
+        Self { credits, stores }
+    }
+
+    pub async fn issue_credit(
+        &self,
+        issuer_id: Uuid,
+        store_id: Uuid,
+        payload: IssueCreditRequest,
+    ) -> crate::Result<StoreCreditEntry> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        self.stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+
+        let amount = decimal_from_f64(payload.amount)?.round_dp(2);
+        self.credits
+            .issue(
+                store_id,
+                payload.user_id,
+                payload.kind,
+                amount,
+                payload.order_id,
+                issuer_id,
+                payload.note.as_deref(),
+            )
+            .await
+    }
+
+    pub async fn balances_for_user(&self, user_id: Uuid) -> crate::Result<Vec<StoreCreditBalance>> {
+        self.credits.balances_for_user(user_id).await
+    }
+
+    pub async fn ledger(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<StoreCreditEntry>> {
+        self.credits
+            .list_entries(store_id, user_id, limit, offset)
+            .await
+    }
+}
+
+fn decimal_from_f64(value: f64) -> crate::Result<Decimal> {
+    Decimal::from_f64_retain(value)
+        .ok_or_else(|| AppError::Validation("Invalid decimal value".into()))
+}
@@ -0,0 +1,116 @@
+use std::{collections::HashMap, sync::Arc};
+
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    currency::{convert, ExchangeRates},
+    models::{
+        currency::{DisplayPrice, DisplayTotals},
+        order::{CartItemDetail, CheckoutPreview},
+        product::Product,
+    },
+    repositories::StoreSettingsRepository,
+};
+
+/// Fills in the `display_*` amounts of responses for shoppers browsing in
+/// another currency. Each store prices in the currency of its settings.
+#[derive(Clone)]
+pub struct CurrencyService {
+    settings: StoreSettingsRepository,
+    rates: Arc<ExchangeRates>,
+}
+
+impl CurrencyService {
+    pub fn new(settings: StoreSettingsRepository, rates: Arc<ExchangeRates>) -> Self {
+        Self { settings, rates }
+    }
+
+    pub async fn present_products(
+        &self,
+        products: &mut [Product],
+        currency: &str,
+    ) -> crate::Result<()> {
+        let store_ids: Vec<Uuid> = products.iter().map(|product| product.store_id).collect();
+        let rates = self.rates_for_stores(&store_ids, currency).await?;
+        for product in products {
+            product.display_price = Some(display_price(
+                product.price,
+                currency,
+                rates[&product.store_id],
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn present_cart(
+        &self,
+        items: &mut [CartItemDetail],
+        currency: &str,
+    ) -> crate::Result<()> {
+        let store_ids: Vec<Uuid> = items.iter().map(|item| item.store_id).collect();
+        let rates = self.rates_for_stores(&store_ids, currency).await?;
+        for item in items {
+            item.display_unit_price = Some(display_price(
+                item.unit_price,
+                currency,
+                rates[&item.store_id],
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn present_preview(
+        &self,
+        preview: &mut CheckoutPreview,
+        currency: &str,
+    ) -> crate::Result<()> {
+        let rate = self.rates.rate(&preview.currency, currency).await?;
+        for store in &mut preview.stores {
+            for item in &mut store.items {
+                item.display_unit_price = Some(display_price(item.unit_price, currency, rate));
+            }
+        }
+        preview.display = Some(DisplayTotals {
+            currency: currency.to_string(),
+            exchange_rate: rate,
+            subtotal: convert(preview.subtotal, rate),
+            tax: convert(preview.tax, rate),
+            discount: convert(preview.discount, rate),
+            shipping_cost: convert(preview.shipping_cost, rate),
+            credit_applied: convert(preview.credit_applied, rate),
+            total_amount: convert(preview.total_amount, rate),
+        });
+        Ok(())
+    }
+
+    /// The rate from each store's currency to `currency`.
+    async fn rates_for_stores(
+        &self,
+        store_ids: &[Uuid],
+        currency: &str,
+    ) -> crate::Result<HashMap<Uuid, Decimal>> {
+        let mut unique = store_ids.to_vec();
+        unique.sort();
+        unique.dedup();
+        let settings = self.settings.find_for_stores(&unique).await?;
+
+        let mut rates = HashMap::new();
+        for (store_id, store_settings) in settings {
+            let rate = self
+                .rates
+                .rate(&store_settings.currency_code, currency)
+                .await?;
+            rates.insert(store_id, rate);
+        }
+        Ok(rates)
+    }
+}
+
+fn display_price(amount: Decimal, currency: &str, rate: Decimal) -> DisplayPrice {
+    DisplayPrice {
+        currency: currency.to_string(),
+        amount: convert(amount, rate),
+        exchange_rate: rate,
+    }
+}
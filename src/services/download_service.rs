@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AppError,
+    models::{
+        order::{DownloadLink, Order, PaymentStatus},
+        product::ProductFile,
+    },
+    repositories::{OrderRepository, ProductFileRepository},
+    storage::ObjectStorage,
+    utils::jwt::JwtConfig,
+};
+use uuid::Uuid;
+
+/// How long a download link stays valid after it was issued.
+pub const DOWNLOAD_LINK_TTL_MINUTES: i64 = 60;
+
+/// Claims of a download link token. Deliberately shares no shape with
+/// session `Claims` so neither token can stand in for the other.
+#[derive(Debug, Serialize, Deserialize)]
+struct DownloadClaims {
+    sub: Uuid,
+    order_id: Uuid,
+    file_id: Uuid,
+    exp: usize,
+}
+
+#[derive(Clone)]
+pub struct DownloadService {
+    orders: OrderRepository,
+    files: ProductFileRepository,
+    storage: Arc<dyn ObjectStorage>,
+    jwt: Arc<JwtConfig>,
+}
+
+impl DownloadService {
+    pub fn new(
+        orders: OrderRepository,
+        files: ProductFileRepository,
+        storage: Arc<dyn ObjectStorage>,
+        jwt: Arc<JwtConfig>,
+    ) -> Self {
+        Self {
+            orders,
+            files,
+            storage,
+            jwt,
+        }
+    }
+
+    /// Issues fresh signed links for every digital file in one of the
+    /// user's orders. Links are only handed out once the order group is paid.
+    pub async fn links_for_order(
+        &self,
+        user_id: Uuid,
+        order_id: Uuid,
+    ) -> crate::Result<Vec<DownloadLink>> {
+        let order = self.get_paid_order(user_id, order_id).await?;
+        let expires_at = Utc::now() + Duration::minutes(DOWNLOAD_LINK_TTL_MINUTES);
+
+        self.files
+            .list_for_order(order.id)
+            .await?
+            .into_iter()
+            .map(|file| {
+                let claims = DownloadClaims {
+                    sub: user_id,
+                    order_id: order.id,
+                    file_id: file.id,
+                    exp: expires_at.timestamp() as usize,
+                };
+                let token = self
+                    .jwt
+                    .sign(&claims)
+                    .map_err(|err| AppError::Internal(err.into()))?;
+
+                Ok(DownloadLink {
+                    file_id: file.id,
+                    product_id: file.product_id,
+                    file_name: file.file_name,
+                    content_type: file.content_type,
+                    size_bytes: file.size_bytes,
+                    url: format!("/api/v1/downloads/{}", token),
+                    expires_at,
+                })
+            })
+            .collect()
+    }
+
+    /// Resolves a download token to the file it grants. Payment is checked
+    /// again so refunded orders stop serving files before links expire.
+    pub async fn open(&self, token: &str) -> crate::Result<(ProductFile, Bytes)> {
+        let claims: DownloadClaims = self.jwt.verify_as(token).map_err(|_| {
+            AppError::Authentication("Download link is invalid or has expired".into())
+        })?;
+
+        let order = self.get_paid_order(claims.sub, claims.order_id).await?;
+        let file = self
+            .files
+            .list_for_order(order.id)
+            .await?
+            .into_iter()
+            .find(|file| file.id == claims.file_id)
+            .ok_or_else(|| AppError::NotFound("File not found".into()))?;
+
+        let data = self.storage.get(&file.storage_key).await?;
+        Ok((file, data))
+    }
+
+    async fn get_paid_order(&self, user_id: Uuid, order_id: Uuid) -> crate::Result<Order> {
+        let order = self
+            .orders
+            .find_by_id(order_id)
+            .await?
+            .filter(|order| order.user_id == user_id)
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))?;
+
+        let group = self
+            .orders
+            .find_group(order.order_group_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))?;
+        if group.payment_status != PaymentStatus::Paid {
+            return Err(AppError::Conflict(
+                "Downloads become available once the order is paid".into(),
+            ));
+        }
+
+        Ok(order)
+    }
+}
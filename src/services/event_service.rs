@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, Utc};
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::event::{
+        session_sampled_in, ClientEvent, ClientEventType, IngestEventsRequest,
+        IngestEventsResponse, RejectedEvent,
+    },
+    repositories::{EventRepository, ProductRepository, RecentlyViewedRepository, StoreRepository},
+    services::recently_viewed_service::RECENTLY_VIEWED_LIMIT,
+};
+use uuid::Uuid;
+
+/// Events stamped further in the future than this are rejected as clock skew.
+const MAX_CLOCK_SKEW_MINUTES: i64 = 5;
+/// Oldest event accepted, to bound how far clients may backfill.
+const MAX_EVENT_AGE_DAYS: i64 = 7;
+
+#[derive(Clone)]
+pub struct EventService {
+    events: EventRepository,
+    stores: StoreRepository,
+    products: ProductRepository,
+    recent: RecentlyViewedRepository,
+    sample_rate: f64,
+}
+
+impl EventService {
+    pub fn new(
+        events: EventRepository,
+        stores: StoreRepository,
+        products: ProductRepository,
+        recent: RecentlyViewedRepository,
+        sample_rate: f64,
+    ) -> Self {
+        Self {
+            events,
+            stores,
+            products,
+            recent,
+            sample_rate,
+        }
+    }
+
+    /// Validates a telemetry batch event by event. Invalid events are
+    /// reported back by index; the rest are sampled per session and stored.
+    /// Product views from signed-in users also feed their recently-viewed
+    /// history.
+    pub async fn ingest(
+        &self,
+        user_id: Option<Uuid>,
+        payload: IngestEventsRequest,
+    ) -> crate::Result<IngestEventsResponse> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let product_ids: Vec<Uuid> = payload
+            .events
+            .iter()
+            .filter_map(|event| event.product_id)
+            .collect();
+        let product_stores: HashMap<Uuid, Uuid> = self
+            .products
+            .find_by_ids(&product_ids)
+            .await?
+            .into_iter()
+            .map(|product| (product.id, product.store_id))
+            .collect();
+
+        let mut known_stores = HashSet::new();
+        for store_id in payload
+            .events
+            .iter()
+            .map(|event| event.store_id)
+            .collect::<HashSet<_>>()
+        {
+            if self.stores.find_by_id(store_id).await?.is_some() {
+                known_stores.insert(store_id);
+            }
+        }
+
+        let now = Utc::now();
+        let mut response = IngestEventsResponse::default();
+        let mut kept: Vec<ClientEvent> = Vec::new();
+        for (index, event) in payload.events.into_iter().enumerate() {
+            let problem = if let Err(err) = event.validate() {
+                Some(err.to_string())
+            } else if !known_stores.contains(&event.store_id) {
+                Some("Unknown store".to_string())
+            } else if event
+                .product_id
+                .is_some_and(|id| product_stores.get(&id) != Some(&event.store_id))
+            {
+                Some("Product does not belong to the store".to_string())
+            } else if event.occurred_at.is_some_and(|at| {
+                at > now + Duration::minutes(MAX_CLOCK_SKEW_MINUTES)
+                    || at < now - Duration::days(MAX_EVENT_AGE_DAYS)
+            }) {
+                Some("occurred_at is outside the accepted window".to_string())
+            } else {
+                None
+            };
+
+            if let Some(message) = problem {
+                response.rejected.push(RejectedEvent { index, message });
+            } else if !session_sampled_in(&event.session_id, self.sample_rate) {
+                response.sampled_out += 1;
+            } else {
+                kept.push(event);
+            }
+        }
+
+        if kept.is_empty() {
+            return Ok(response);
+        }
+        self.events.insert_batch(user_id, &kept).await?;
+        response.accepted = kept.len();
+
+        if let Some(user_id) = user_id {
+            let mut views: Vec<&ClientEvent> = kept
+                .iter()
+                .filter(|event| event.event_type == ClientEventType::ProductView)
+                .collect();
+            views.sort_by_key(|event| event.occurred_at.unwrap_or(now));
+            for event in views {
+                if let Some(product_id) = event.product_id {
+                    self.recent
+                        .record(user_id, product_id, RECENTLY_VIEWED_LIMIT)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(response)
+    }
+}
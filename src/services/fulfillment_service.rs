@@ -0,0 +1,70 @@
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::{
+        fulfillment::{
+            AssignFulfillmentPartnerRequest, CreateFulfillmentPartnerRequest, FulfillmentPartner,
+        },
+        store::Store,
+    },
+    repositories::{FulfillmentRepository, StoreRepository},
+};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct FulfillmentService {
+    partners: FulfillmentRepository,
+    stores: StoreRepository,
+}
+
+impl FulfillmentService {
+    pub fn new(partners: FulfillmentRepository, stores: StoreRepository) -> Self {
+        Self { partners, stores }
+    }
+
+    pub async fn create_partner(
+        &self,
+        payload: CreateFulfillmentPartnerRequest,
+    ) -> crate::Result<FulfillmentPartner> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        if self.partners.find_by_name(&payload.name).await?.is_some() {
+            return Err(AppError::Conflict(
+                "Fulfillment partner name already in use".into(),
+            ));
+        }
+
+        self.partners
+            .create(&payload.name, &payload.rate_table)
+            .await
+    }
+
+    pub async fn list_partners(&self) -> crate::Result<Vec<FulfillmentPartner>> {
+        self.partners.list().await
+    }
+
+    pub async fn assign_store(
+        &self,
+        store_id: Uuid,
+        payload: AssignFulfillmentPartnerRequest,
+    ) -> crate::Result<Store> {
+        if self.stores.find_by_id(store_id).await?.is_none() {
+            return Err(AppError::NotFound("Store not found".into()));
+        }
+
+        if let Some(partner_id) = payload.fulfillment_partner_id {
+            if self.partners.find_by_id(partner_id).await?.is_none() {
+                return Err(AppError::NotFound("Fulfillment partner not found".into()));
+            }
+        }
+
+        let store = self
+            .partners
+            .assign_store(store_id, payload.fulfillment_partner_id)
+            .await?;
+        Ok(store)
+    }
+}
@@ -0,0 +1,86 @@
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    imports::{self, ImportReport, InventoryImporter},
+    models::inventory::{AdjustInventoryRequest, InventoryMovement},
+    repositories::{InventoryRepository, ProductRepository},
+};
+use uuid::Uuid;
+
+/// Manual stock changes and the per-product inventory ledger. Sales and
+/// cancellations write their movements as part of checkout and order
+/// status changes.
+#[derive(Clone)]
+pub struct InventoryService {
+    products: ProductRepository,
+    inventory: InventoryRepository,
+}
+
+impl InventoryService {
+    pub fn new(products: ProductRepository, inventory: InventoryRepository) -> Self {
+        Self {
+            products,
+            inventory,
+        }
+    }
+
+    pub async fn product_store_id(&self, product_id: Uuid) -> crate::Result<Uuid> {
+        self.products
+            .find_by_id(product_id)
+            .await?
+            .map(|product| product.store_id)
+            .ok_or_else(|| AppError::NotFound("Product not found".into()))
+    }
+
+    /// Applies a `sku,stock_quantity` stock count CSV for the store.
+    pub async fn import_stock(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        data: &[u8],
+        dry_run: bool,
+    ) -> crate::Result<ImportReport> {
+        let importer = InventoryImporter::new(
+            self.products.clone(),
+            self.inventory.clone(),
+            store_id,
+            actor_id,
+        );
+        imports::run(&importer, data, dry_run).await
+    }
+
+    pub async fn adjust(
+        &self,
+        actor_id: Uuid,
+        product_id: Uuid,
+        payload: AdjustInventoryRequest,
+    ) -> crate::Result<InventoryMovement> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        self.product_store_id(product_id).await?;
+        self.inventory
+            .adjust(
+                product_id,
+                payload.kind,
+                payload.delta,
+                actor_id,
+                payload.reason.trim(),
+            )
+            .await
+    }
+
+    pub async fn history(
+        &self,
+        product_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<InventoryMovement>> {
+        self.product_store_id(product_id).await?;
+        self.inventory
+            .list_for_product(product_id, limit, offset)
+            .await
+    }
+}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::store::{CreateInviteLinkRequest, CreatedInviteLink, StoreAccessGrant},
+    repositories::{AccessGrantRepository, InviteLinkRepository},
+    utils::jwt::JwtConfig,
+};
+use uuid::Uuid;
+
+/// Claims of an invite link token. Deliberately shares no shape with
+/// session or download claims so none can stand in for another.
+#[derive(Debug, Serialize, Deserialize)]
+struct InviteClaims {
+    invite_id: Uuid,
+    store_id: Uuid,
+    exp: usize,
+}
+
+/// Issues signed invite links to private stores and turns them into access
+/// grants for the users who open them.
+#[derive(Clone)]
+pub struct InviteLinkService {
+    links: InviteLinkRepository,
+    grants: AccessGrantRepository,
+    jwt: Arc<JwtConfig>,
+}
+
+impl InviteLinkService {
+    pub fn new(
+        links: InviteLinkRepository,
+        grants: AccessGrantRepository,
+        jwt: Arc<JwtConfig>,
+    ) -> Self {
+        Self { links, grants, jwt }
+    }
+
+    pub async fn create(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        payload: CreateInviteLinkRequest,
+    ) -> crate::Result<CreatedInviteLink> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let expires_at = Utc::now() + Duration::hours(payload.expires_in_hours);
+        let link = self
+            .links
+            .create(
+                store_id,
+                actor_id,
+                payload.access_level,
+                payload.max_uses,
+                expires_at,
+            )
+            .await?;
+        let claims = InviteClaims {
+            invite_id: link.id,
+            store_id,
+            exp: expires_at.timestamp() as usize,
+        };
+        let token = self
+            .jwt
+            .sign(&claims)
+            .map_err(|err| AppError::Internal(err.into()))?;
+
+        Ok(CreatedInviteLink {
+            url: format!("/api/v1/invites/{}", token),
+            token,
+            link,
+        })
+    }
+
+    /// Gives the user the link's access to its store. Users who already
+    /// have access keep their grant and do not use the link up.
+    pub async fn redeem(&self, user_id: Uuid, token: &str) -> crate::Result<StoreAccessGrant> {
+        let claims: InviteClaims = self.jwt.verify_as(token).map_err(|_| {
+            AppError::Authentication("Invite link is invalid or has expired".into())
+        })?;
+
+        let mut tx = self.links.pool().begin().await?;
+        let link = self
+            .links
+            .lock_open_in_tx(&mut tx, claims.store_id, claims.invite_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Invite link is no longer valid".into()))?;
+        if let Some(grant) = self
+            .grants
+            .find_active_in_tx(&mut tx, link.store_id, user_id)
+            .await?
+        {
+            return Ok(grant);
+        }
+        if self
+            .links
+            .has_redeemed_in_tx(&mut tx, link.id, user_id)
+            .await?
+        {
+            return Err(AppError::Conflict(
+                "You have already used this invite link".into(),
+            ));
+        }
+        if link.use_count >= link.max_uses {
+            return Err(AppError::Conflict("Invite link has been used up".into()));
+        }
+
+        let grant = self
+            .grants
+            .grant_or_renew_in_tx(
+                &mut tx,
+                link.store_id,
+                user_id,
+                link.created_by,
+                link.access_level,
+                None,
+            )
+            .await?;
+        self.links
+            .record_redemption_in_tx(&mut tx, link.id, user_id, grant.id)
+            .await?;
+        tx.commit().await?;
+        self.grants.invalidate_permissions(link.store_id).await;
+
+        Ok(grant)
+    }
+
+    pub async fn revoke(&self, store_id: Uuid, link_id: Uuid) -> crate::Result<()> {
+        self.links
+            .revoke(store_id, link_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Invite link not found".into()))?;
+        Ok(())
+    }
+}
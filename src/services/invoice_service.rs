@@ -0,0 +1,509 @@
+use std::fmt::Write as _;
+
+use bytes::Bytes;
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{
+        invoice::{InvoiceDocument, InvoiceSeller},
+        order::{Order, OrderStatus},
+    },
+    repositories::{InvoiceRepository, OrderRepository, StoreRepository},
+};
+
+/// Issues invoices for orders and gathers what they show. Each store
+/// numbers its invoices on its own; a multi-store checkout gets one
+/// invoice per store.
+#[derive(Clone)]
+pub struct InvoiceService {
+    orders: OrderRepository,
+    stores: StoreRepository,
+    invoices: InvoiceRepository,
+}
+
+impl InvoiceService {
+    pub fn new(
+        orders: OrderRepository,
+        stores: StoreRepository,
+        invoices: InvoiceRepository,
+    ) -> Self {
+        Self {
+            orders,
+            stores,
+            invoices,
+        }
+    }
+
+    pub async fn find_order(&self, order_id: Uuid) -> crate::Result<Order> {
+        self.orders
+            .find_by_id(order_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))
+    }
+
+    /// The order's invoice, issued on first request. Callers decide whether
+    /// the viewer may see the order.
+    pub async fn order_invoice(&self, order: Order) -> crate::Result<InvoiceDocument> {
+        if order.status == OrderStatus::Cancelled {
+            return Err(AppError::Conflict(
+                "Cancelled orders are not invoiced".into(),
+            ));
+        }
+        self.document(order).await
+    }
+
+    /// Invoices for every order of the buyer's checkout that was not
+    /// cancelled.
+    pub async fn group_invoices(
+        &self,
+        user_id: Uuid,
+        order_group_id: Uuid,
+    ) -> crate::Result<Vec<InvoiceDocument>> {
+        self.orders
+            .find_group(order_group_id)
+            .await?
+            .filter(|group| group.user_id == user_id)
+            .ok_or_else(|| AppError::NotFound("Order group not found".into()))?;
+
+        let orders: Vec<Order> = self
+            .orders
+            .list_for_group(order_group_id)
+            .await?
+            .into_iter()
+            .filter(|order| order.status != OrderStatus::Cancelled)
+            .collect();
+        if orders.is_empty() {
+            return Err(AppError::Conflict(
+                "Cancelled orders are not invoiced".into(),
+            ));
+        }
+
+        let mut documents = Vec::with_capacity(orders.len());
+        for order in orders {
+            documents.push(self.document(order).await?);
+        }
+        Ok(documents)
+    }
+
+    async fn document(&self, order: Order) -> crate::Result<InvoiceDocument> {
+        let store = self
+            .stores
+            .find_by_id(order.store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+        let invoice = self
+            .invoices
+            .issue_for_order(order.id, order.store_id)
+            .await?;
+        let items = self.orders.list_item_details(order.id).await?;
+        let tax_lines = self.orders.list_tax_lines(order.id).await?;
+
+        Ok(InvoiceDocument {
+            invoice,
+            seller: InvoiceSeller {
+                store_id: store.id,
+                name: store.name,
+                slug: store.slug,
+                description: store.description,
+            },
+            order,
+            items,
+            tax_lines,
+        })
+    }
+}
+
+/// Address fields printed on invoices, in print order.
+const ADDRESS_FIELDS: [&str; 8] = [
+    "name",
+    "line1",
+    "line2",
+    "city",
+    "state",
+    "region",
+    "postal_code",
+    "country",
+];
+
+fn address_lines(address: &Value) -> Vec<&str> {
+    ADDRESS_FIELDS
+        .iter()
+        .filter_map(|field| address.get(*field).and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+fn money(amount: Decimal, currency: &str) -> String {
+    format!("{:.2} {}", amount, currency)
+}
+
+fn percent(rate: Decimal) -> String {
+    format!("{}%", (rate * Decimal::ONE_HUNDRED).normalize())
+}
+
+/// The totals block of an invoice, skipping amounts that do not apply.
+fn totals(order: &Order) -> Vec<(&'static str, String)> {
+    let currency = order.currency.as_str();
+    let mut rows = vec![("Subtotal", money(order.subtotal, currency))];
+    if !order.discount.is_zero() {
+        rows.push(("Discount", money(-order.discount, currency)));
+    }
+    rows.push(("Tax", money(order.tax, currency)));
+    rows.push(("Shipping", money(order.shipping_cost, currency)));
+    if !order.credit_applied.is_zero() {
+        rows.push(("Store credit", money(-order.credit_applied, currency)));
+    }
+    rows.push(("Total", money(order.total_amount, currency)));
+    rows
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// A standalone HTML page with one section per invoice.
+pub fn render_html(documents: &[InvoiceDocument]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Invoice</title>\
+         <style>body{font-family:sans-serif;margin:2em}section{page-break-after:always}\
+         table{border-collapse:collapse;width:100%}th,td{padding:4px 8px;text-align:left}\
+         td.amount,th.amount{text-align:right}</style></head><body>\n",
+    );
+    for doc in documents {
+        let order = &doc.order;
+        let currency = order.currency.as_str();
+        let _ = write!(
+            html,
+            "<section><h1>Invoice {}</h1>\n<p>Issued {}<br>Order {} placed {}</p>\n",
+            escape_html(&doc.invoice.invoice_number),
+            doc.invoice.issued_at.format("%Y-%m-%d"),
+            escape_html(&order.order_number),
+            order.created_at.format("%Y-%m-%d"),
+        );
+        let _ = write!(
+            html,
+            "<h2>Seller</h2>\n<p>{}<br>{}</p>\n",
+            escape_html(&doc.seller.name),
+            escape_html(&doc.seller.slug),
+        );
+        html.push_str("<h2>Ship to</h2>\n<p>");
+        let address: Vec<String> = address_lines(&order.shipping_address)
+            .into_iter()
+            .map(escape_html)
+            .collect();
+        html.push_str(&address.join("<br>"));
+        html.push_str("</p>\n");
+
+        html.push_str(
+            "<table><thead><tr><th>Item</th><th class=\"amount\">Qty</th>\
+             <th class=\"amount\">Unit price</th><th class=\"amount\">Amount</th></tr></thead>\n<tbody>",
+        );
+        for line in &doc.items {
+            let _ = writeln!(
+                html,
+                "<tr><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td>\
+                 <td class=\"amount\">{}</td></tr>",
+                escape_html(&line.product_name),
+                line.item.quantity,
+                money(line.item.unit_price, currency),
+                money(line.item.subtotal, currency),
+            );
+        }
+        html.push_str("</tbody></table>\n");
+
+        if !doc.tax_lines.is_empty() {
+            html.push_str(
+                "<h2>Tax</h2>\n<table><thead><tr><th>Jurisdiction</th><th class=\"amount\">Rate</th>\
+                 <th class=\"amount\">Taxable</th><th class=\"amount\">Tax</th></tr></thead>\n<tbody>",
+            );
+            for line in &doc.tax_lines {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td class=\"amount\">{}</td><td class=\"amount\">{}</td>\
+                     <td class=\"amount\">{}</td></tr>",
+                    escape_html(&line.jurisdiction),
+                    percent(line.rate),
+                    money(line.taxable_amount, currency),
+                    money(line.tax_amount, currency),
+                );
+            }
+            html.push_str("</tbody></table>\n");
+        }
+
+        html.push_str("<table class=\"totals\"><tbody>");
+        for (label, amount) in totals(order) {
+            let _ = write!(
+                html,
+                "<tr><th>{}</th><td class=\"amount\">{}</td></tr>",
+                label, amount
+            );
+        }
+        html.push_str("</tbody></table></section>\n");
+    }
+    html.push_str("</body></html>\n");
+    html
+}
+
+/// A4 PDF with each invoice starting on a new page.
+pub fn render_pdf(documents: &[InvoiceDocument]) -> Bytes {
+    let mut layout = PdfLayout::new();
+    for (index, doc) in documents.iter().enumerate() {
+        if index > 0 {
+            layout.new_page();
+        }
+        let order = &doc.order;
+        let currency = order.currency.as_str();
+
+        layout.line(&[(LEFT, "INVOICE")], 20.0, true);
+        layout.gap(6.0);
+        layout.line(
+            &[
+                (LEFT, "Invoice number"),
+                (300.0, &doc.invoice.invoice_number),
+            ],
+            10.0,
+            false,
+        );
+        let issued = doc.invoice.issued_at.format("%Y-%m-%d").to_string();
+        layout.line(&[(LEFT, "Issued"), (300.0, &issued)], 10.0, false);
+        layout.line(
+            &[(LEFT, "Order number"), (300.0, &order.order_number)],
+            10.0,
+            false,
+        );
+        let placed = order.created_at.format("%Y-%m-%d").to_string();
+        layout.line(&[(LEFT, "Order date"), (300.0, &placed)], 10.0, false);
+
+        layout.gap(12.0);
+        layout.line(&[(LEFT, "Seller"), (300.0, "Ship to")], 11.0, true);
+        let seller = [doc.seller.name.as_str(), doc.seller.slug.as_str()];
+        let address = address_lines(&order.shipping_address);
+        for row in 0..seller.len().max(address.len()) {
+            layout.line(
+                &[
+                    (LEFT, seller.get(row).copied().unwrap_or_default()),
+                    (300.0, address.get(row).copied().unwrap_or_default()),
+                ],
+                10.0,
+                false,
+            );
+        }
+
+        layout.gap(12.0);
+        layout.line(
+            &[
+                (LEFT, "Item"),
+                (330.0, "Qty"),
+                (380.0, "Unit price"),
+                (470.0, "Amount"),
+            ],
+            10.0,
+            true,
+        );
+        for line in &doc.items {
+            let quantity = line.item.quantity.to_string();
+            let unit_price = money(line.item.unit_price, currency);
+            let amount = money(line.item.subtotal, currency);
+            layout.line(
+                &[
+                    (LEFT, &line.product_name),
+                    (330.0, &quantity),
+                    (380.0, &unit_price),
+                    (470.0, &amount),
+                ],
+                10.0,
+                false,
+            );
+        }
+
+        if !doc.tax_lines.is_empty() {
+            layout.gap(12.0);
+            layout.line(
+                &[
+                    (LEFT, "Tax jurisdiction"),
+                    (330.0, "Rate"),
+                    (380.0, "Taxable"),
+                    (470.0, "Tax"),
+                ],
+                10.0,
+                true,
+            );
+            for line in &doc.tax_lines {
+                let rate = percent(line.rate);
+                let taxable = money(line.taxable_amount, currency);
+                let tax = money(line.tax_amount, currency);
+                layout.line(
+                    &[
+                        (LEFT, &line.jurisdiction),
+                        (330.0, &rate),
+                        (380.0, &taxable),
+                        (470.0, &tax),
+                    ],
+                    10.0,
+                    false,
+                );
+            }
+        }
+
+        layout.gap(12.0);
+        for (label, amount) in totals(order) {
+            layout.line(&[(380.0, label), (470.0, &amount)], 10.0, label == "Total");
+        }
+    }
+    Bytes::from(layout.finish())
+}
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 50.0;
+const LEFT: f32 = MARGIN;
+const REGULAR_FONT: Name<'static> = Name(b"F1");
+const BOLD_FONT: Name<'static> = Name(b"F2");
+
+/// Writes lines of text top to bottom, starting a new page when one fills
+/// up. Uses the standard Helvetica fonts, so no font files are embedded.
+struct PdfLayout {
+    pages: Vec<Content>,
+    y: f32,
+}
+
+impl PdfLayout {
+    fn new() -> Self {
+        let mut layout = Self {
+            pages: Vec::new(),
+            y: 0.0,
+        };
+        layout.new_page();
+        layout
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Content::new());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn gap(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn line(&mut self, cells: &[(f32, &str)], size: f32, bold: bool) {
+        let height = size * 1.4;
+        if self.y - height < MARGIN {
+            self.new_page();
+        }
+        self.y -= height;
+        let font = if bold { BOLD_FONT } else { REGULAR_FONT };
+        let content = self.pages.last_mut().expect("layout always has a page");
+        for (x, text) in cells {
+            if text.is_empty() {
+                continue;
+            }
+            content
+                .begin_text()
+                .set_font(font, size)
+                .next_line(*x, self.y)
+                .show(Str(&win_ansi(text)))
+                .end_text();
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let catalog_id = Ref::new(1);
+        let page_tree_id = Ref::new(2);
+        let regular_id = Ref::new(3);
+        let bold_id = Ref::new(4);
+        let page_ids: Vec<Ref> = (0..self.pages.len())
+            .map(|index| Ref::new(5 + 2 * index as i32))
+            .collect();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        for (font_id, base_font) in [(regular_id, "Helvetica"), (bold_id, "Helvetica-Bold")] {
+            pdf.type1_font(font_id)
+                .base_font(Name(base_font.as_bytes()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+
+        for (page_id, content) in page_ids.into_iter().zip(self.pages) {
+            let content_id = Ref::new(page_id.get() + 1);
+            let mut page = pdf.page(page_id);
+            page.parent(page_tree_id)
+                .media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT))
+                .contents(content_id);
+            page.resources()
+                .fonts()
+                .pair(REGULAR_FONT, regular_id)
+                .pair(BOLD_FONT, bold_id);
+            page.finish();
+            pdf.stream(content_id, &content.finish());
+        }
+
+        pdf.finish()
+    }
+}
+
+/// Encodes text for the standard fonts' WinAnsi encoding, replacing
+/// characters it cannot represent.
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|ch| match u32::from(ch) {
+            code @ (0x20..=0x7e | 0xa0..=0xff) => code as u8,
+            0x20ac => 0x80,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn address_lines_follow_print_order_and_skip_blanks() {
+        let address = json!({
+            "country": "US",
+            "line1": "1 Main St",
+            "city": "Springfield",
+            "line2": " ",
+            "notes": "Leave at door",
+        });
+        assert_eq!(address_lines(&address), ["1 Main St", "Springfield", "US"]);
+    }
+
+    #[test]
+    fn win_ansi_keeps_latin1_and_replaces_the_rest() {
+        assert_eq!(win_ansi("Café €5"), b"Caf\xe9 \x805");
+        assert_eq!(win_ansi("東京"), b"??");
+    }
+
+    #[test]
+    fn pdf_layout_breaks_onto_new_pages() {
+        let mut layout = PdfLayout::new();
+        for _ in 0..100 {
+            layout.line(&[(LEFT, "Line")], 10.0, false);
+        }
+        assert!(layout.pages.len() > 1);
+        let pdf = layout.finish();
+        assert!(pdf.starts_with(b"%PDF-"));
+    }
+}
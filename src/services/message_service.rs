@@ -0,0 +1,115 @@
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::{
+        message::{MessageSender, OrderMessage, PostOrderMessageRequest, UnreadThread},
+        notification::ORDER_MESSAGE,
+        order::Order,
+    },
+    repositories::{NotificationRepository, OrderMessageRepository, OrderRepository},
+};
+
+/// Order-scoped conversations between the buyer and the store's staff.
+/// Callers establish which side the user is on; see `MessageSender`.
+#[derive(Clone)]
+pub struct OrderMessageService {
+    messages: OrderMessageRepository,
+    orders: OrderRepository,
+    notifications: NotificationRepository,
+}
+
+impl OrderMessageService {
+    pub fn new(
+        messages: OrderMessageRepository,
+        orders: OrderRepository,
+        notifications: NotificationRepository,
+    ) -> Self {
+        Self {
+            messages,
+            orders,
+            notifications,
+        }
+    }
+
+    pub async fn find_order(&self, order_id: Uuid) -> crate::Result<Order> {
+        self.orders
+            .find_by_id(order_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))
+    }
+
+    /// The order's thread, oldest first. Reading it marks it read for the
+    /// viewer.
+    pub async fn list(&self, order: &Order, viewer_id: Uuid) -> crate::Result<Vec<OrderMessage>> {
+        let messages = self.messages.list_for_order(order.id).await?;
+        self.messages.mark_read(order.id, viewer_id).await?;
+        Ok(messages)
+    }
+
+    /// Adds a message and lets the other side know: the buyer in-app, the
+    /// store through its notification rules.
+    pub async fn post(
+        &self,
+        order: &Order,
+        sender_id: Uuid,
+        sender: MessageSender,
+        payload: PostOrderMessageRequest,
+    ) -> crate::Result<OrderMessage> {
+        let body = payload.body.trim();
+        PostOrderMessageRequest { body: body.into() }
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let mut tx = self.messages.pool().begin().await?;
+        let message = self
+            .messages
+            .create_in_tx(&mut tx, order.id, sender_id, sender, body)
+            .await?;
+        self.messages
+            .mark_read_in_tx(&mut tx, order.id, sender_id)
+            .await?;
+        match sender {
+            MessageSender::Buyer => {
+                self.notifications
+                    .dispatch_store_event_in_tx(
+                        &mut tx,
+                        order.store_id,
+                        ORDER_MESSAGE,
+                        &format!(
+                            "The buyer sent a message about order {}",
+                            order.order_number
+                        ),
+                    )
+                    .await?;
+            }
+            MessageSender::Store => {
+                self.notifications
+                    .create_for_users_in_tx(
+                        &mut tx,
+                        &[order.user_id],
+                        Some(order.store_id),
+                        ORDER_MESSAGE,
+                        &format!("The store replied about order {}", order.order_number),
+                    )
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+
+        Ok(message)
+    }
+
+    pub async fn unread_for_buyer(&self, user_id: Uuid) -> crate::Result<Vec<UnreadThread>> {
+        self.messages.unread_for_buyer(user_id).await
+    }
+
+    pub async fn unread_for_store(
+        &self,
+        store_id: Uuid,
+        user_id: Uuid,
+    ) -> crate::Result<Vec<UnreadThread>> {
+        self.messages.unread_for_store(store_id, user_id).await
+    }
+}
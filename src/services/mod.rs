@@ -1,17 +1,86 @@
+pub mod access_grant_service;
 pub mod analytics_service;
+pub mod audit_service;
 pub mod auth_service;
+pub mod cart_policy;
 pub mod cart_service;
+pub mod comparison_service;
+pub mod coupon_service;
+pub mod credit_service;
+pub mod currency_service;
+pub mod download_service;
+pub mod event_service;
+pub mod export;
+pub mod fulfillment_service;
+pub mod inventory_service;
+pub mod invite_link_service;
+pub mod invoice_service;
+pub mod message_service;
+pub mod moderation_service;
+pub mod notification_service;
+pub mod order_cancellation;
 pub mod order_service;
+pub mod order_status_service;
+pub mod payment_service;
 pub mod permission_service;
+pub mod product_file_service;
+pub mod product_image_service;
 pub mod product_service;
+pub mod public_order_service;
+pub mod readiness_service;
+pub mod recently_viewed_service;
+pub mod recommendation_service;
+pub mod search_service;
+pub mod share_card_service;
+pub mod shipping_consolidation;
+pub mod shipping_service;
+pub mod store_lifecycle_service;
+pub mod store_role_service;
 pub mod store_service;
+pub mod store_settings_service;
+pub mod tax_service;
+pub mod unpaid_order_service;
 pub mod user_service;
+pub mod webhook_service;
 
+pub use access_grant_service::AccessGrantService;
 pub use analytics_service::AnalyticsService;
+pub use audit_service::AuditService;
 pub use auth_service::AuthService;
 pub use cart_service::CartService;
+pub use comparison_service::ComparisonService;
+pub use coupon_service::CouponService;
+pub use credit_service::CreditService;
+pub use currency_service::CurrencyService;
+pub use download_service::DownloadService;
+pub use event_service::EventService;
+pub use fulfillment_service::FulfillmentService;
+pub use inventory_service::InventoryService;
+pub use invite_link_service::InviteLinkService;
+pub use invoice_service::InvoiceService;
+pub use message_service::OrderMessageService;
+pub use moderation_service::ModerationService;
+pub use notification_service::NotificationService;
+pub use order_cancellation::OrderCancellation;
 pub use order_service::OrderService;
+pub use order_status_service::OrderStatusService;
+pub use payment_service::PaymentService;
 pub use permission_service::PermissionService;
+pub use product_file_service::ProductFileService;
+pub use product_image_service::ProductImageService;
 pub use product_service::ProductService;
+pub use public_order_service::PublicOrderService;
+pub use readiness_service::ReadinessService;
+pub use recently_viewed_service::RecentlyViewedService;
+pub use recommendation_service::RecommendationService;
+pub use search_service::SearchService;
+pub use share_card_service::ShareCardService;
+pub use shipping_service::ShippingService;
+pub use store_lifecycle_service::StoreLifecycleService;
+pub use store_role_service::StoreRoleService;
 pub use store_service::StoreService;
+pub use store_settings_service::StoreSettingsService;
+pub use tax_service::TaxService;
+pub use unpaid_order_service::UnpaidOrderService;
 pub use user_service::UserService;
+pub use webhook_service::WebhookService;
@@ -0,0 +1,175 @@
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::store::{
+        AppealStatus, CreateAppealRequest, ModerateStoreRequest, ResolveAppealRequest, Store,
+        StoreAppeal, StoreModerationAction, StoreModerationResponse, StoreStatus,
+    },
+    repositories::{ModerationRepository, StoreRepository},
+};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ModerationService {
+    stores: StoreRepository,
+    moderation: ModerationRepository,
+}
+
+impl ModerationService {
+    pub fn new(stores: StoreRepository, moderation: ModerationRepository) -> Self {
+        Self { stores, moderation }
+    }
+
+    pub async fn moderate_store(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        payload: ModerateStoreRequest,
+    ) -> crate::Result<StoreModerationResponse> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let store = self.get_store(store_id).await?;
+        if store.status == payload.status {
+            return Err(AppError::Conflict(format!(
+                "Store is already {:?}",
+                payload.status
+            )));
+        }
+
+        let (store, action) = self
+            .moderation
+            .apply_status(&store, actor_id, payload.status, &payload.reason)
+            .await?;
+
+        Ok(StoreModerationResponse { store, action })
+    }
+
+    pub async fn moderation_history(
+        &self,
+        store_id: Uuid,
+    ) -> crate::Result<Vec<StoreModerationAction>> {
+        self.get_store(store_id).await?;
+        self.moderation.list_actions(store_id).await
+    }
+
+    pub async fn submit_appeal(
+        &self,
+        user_id: Uuid,
+        store_id: Uuid,
+        payload: CreateAppealRequest,
+    ) -> crate::Result<StoreAppeal> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let store = self.get_owned_store(user_id, store_id).await?;
+        if store.status == StoreStatus::Active {
+            return Err(AppError::BadRequest(
+                "Only suspended or closed stores can be appealed".into(),
+            ));
+        }
+
+        if self
+            .moderation
+            .find_pending_appeal(store_id)
+            .await?
+            .is_some()
+        {
+            return Err(AppError::Conflict(
+                "An appeal is already pending for this store".into(),
+            ));
+        }
+
+        self.moderation
+            .create_appeal(store_id, user_id, &payload.message)
+            .await
+    }
+
+    pub async fn list_store_appeals(
+        &self,
+        user_id: Uuid,
+        store_id: Uuid,
+    ) -> crate::Result<Vec<StoreAppeal>> {
+        self.get_owned_store(user_id, store_id).await?;
+        self.moderation.list_appeals_for_store(store_id).await
+    }
+
+    pub async fn list_appeals(
+        &self,
+        status: Option<AppealStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<StoreAppeal>> {
+        self.moderation.list_appeals(status, limit, offset).await
+    }
+
+    pub async fn resolve_appeal(
+        &self,
+        reviewer_id: Uuid,
+        appeal_id: Uuid,
+        payload: ResolveAppealRequest,
+    ) -> crate::Result<StoreAppeal> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let appeal = self
+            .moderation
+            .find_appeal(appeal_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Appeal not found".into()))?;
+
+        if appeal.status != AppealStatus::Pending {
+            return Err(AppError::Conflict(
+                "Appeal has already been resolved".into(),
+            ));
+        }
+
+        let status = if payload.approve {
+            AppealStatus::Approved
+        } else {
+            AppealStatus::Rejected
+        };
+
+        let resolved = self
+            .moderation
+            .resolve_appeal(appeal_id, reviewer_id, status, payload.note.as_deref())
+            .await?
+            .ok_or_else(|| AppError::Conflict("Appeal has already been resolved".into()))?;
+
+        if payload.approve {
+            let store = self.get_store(appeal.store_id).await?;
+            if store.status != StoreStatus::Active {
+                let reason = match payload.note.as_deref() {
+                    Some(note) => format!("Appeal approved: {}", note),
+                    None => "Appeal approved".to_string(),
+                };
+                self.moderation
+                    .apply_status(&store, reviewer_id, StoreStatus::Active, &reason)
+                    .await?;
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    async fn get_store(&self, store_id: Uuid) -> crate::Result<Store> {
+        self.stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))
+    }
+
+    async fn get_owned_store(&self, user_id: Uuid, store_id: Uuid) -> crate::Result<Store> {
+        let store = self.get_store(store_id).await?;
+        if store.owner_id != user_id {
+            return Err(AppError::Authorization(
+                "Only the store owner can manage appeals".into(),
+            ));
+        }
+        Ok(store)
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::notification::{CreateNotificationRuleRequest, Notification, NotificationRule},
+    notifier::Notifier,
+    repositories::NotificationRepository,
+    utils::backoff::retry_delay,
+};
+
+/// Attempts made at an email or webhook delivery before it is marked failed.
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 5;
+
+/// How long a claimed delivery is held before another run may retry it.
+const DELIVERY_LEASE_SECS: i64 = 300;
+
+#[derive(Clone)]
+pub struct NotificationService {
+    notifications: NotificationRepository,
+}
+
+/// Outcome of one pass over the delivery queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeliveryRun {
+    pub sent: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+impl NotificationService {
+    pub fn new(notifications: NotificationRepository) -> Self {
+        Self { notifications }
+    }
+
+    pub async fn list_for_user(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<Notification>> {
+        self.notifications
+            .list_for_user(user_id, limit, offset)
+            .await
+    }
+
+    pub async fn list_rules(&self, store_id: Uuid) -> crate::Result<Vec<NotificationRule>> {
+        self.notifications.list_rules(store_id).await
+    }
+
+    pub async fn create_rule(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        payload: CreateNotificationRuleRequest,
+    ) -> crate::Result<NotificationRule> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        self.notifications
+            .create_rule(store_id, &payload, actor_id)
+            .await?
+            .ok_or_else(|| {
+                AppError::Validation("Recipient must be the store owner or a member".into())
+            })
+    }
+
+    pub async fn delete_rule(&self, store_id: Uuid, rule_id: Uuid) -> crate::Result<()> {
+        if !self.notifications.delete_rule(store_id, rule_id).await? {
+            return Err(AppError::NotFound("Notification rule not found".into()));
+        }
+        Ok(())
+    }
+
+    /// Sends up to `limit` due email and webhook deliveries. Failures are
+    /// retried with a growing delay until `MAX_DELIVERY_ATTEMPTS` is reached.
+    pub async fn deliver_due(
+        &self,
+        notifier: &dyn Notifier,
+        limit: i64,
+    ) -> crate::Result<DeliveryRun> {
+        let mut run = DeliveryRun::default();
+        let deliveries = self
+            .notifications
+            .claim_due_deliveries(limit, DELIVERY_LEASE_SECS)
+            .await?;
+
+        for delivery in deliveries {
+            match notifier.send(&delivery).await {
+                Ok(()) => {
+                    self.notifications.mark_delivery_sent(delivery.id).await?;
+                    run.sent += 1;
+                }
+                Err(err) => {
+                    let retry_at = (delivery.attempts < MAX_DELIVERY_ATTEMPTS)
+                        .then(|| Utc::now() + retry_delay(delivery.attempts));
+                    if retry_at.is_some() {
+                        run.retrying += 1;
+                    } else {
+                        run.failed += 1;
+                    }
+                    self.notifications
+                        .mark_delivery_failed(delivery.id, &err.to_string(), retry_at)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(run)
+    }
+}
@@ -0,0 +1,138 @@
+use rust_decimal::Decimal;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::order::{Order, OrderStatus},
+    repositories::{
+        CouponRepository, CreditRepository, InventoryRepository, OrderRepository, PaymentRepository,
+    },
+};
+
+/// An order cancelled together with the units put back into stock for it.
+#[derive(Debug, Clone)]
+pub struct CancelledOrder {
+    pub order: Order,
+    pub units_restored: i64,
+}
+
+/// The one way orders get cancelled, whether by the buyer, by store staff
+/// or because the group was never paid. Everything checkout took for the
+/// order is given back in the caller's transaction: stock, the store credit
+/// applied and the coupon use.
+#[derive(Clone)]
+pub struct OrderCancellation {
+    orders: OrderRepository,
+    inventory: InventoryRepository,
+    credits: CreditRepository,
+    coupons: CouponRepository,
+    payments: PaymentRepository,
+}
+
+impl OrderCancellation {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            orders: OrderRepository::new(pool.clone()),
+            inventory: InventoryRepository::new(pool.clone()),
+            credits: CreditRepository::new(pool.clone()),
+            coupons: CouponRepository::new(pool.clone()),
+            payments: PaymentRepository::new(pool),
+        }
+    }
+
+    /// Rejects cancelling an order whose payment was captured; the store
+    /// refunds it first.
+    pub async fn ensure_not_captured_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+    ) -> crate::Result<()> {
+        if self.payments.captured_for_order_in_tx(tx, order_id).await? {
+            return Err(AppError::Conflict(
+                "Paid orders must be refunded by the store before they can be cancelled".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Cancels an order still in `from` and recomputes its group total.
+    /// `None` when the order changed concurrently.
+    pub async fn cancel_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_id: Uuid,
+        from: OrderStatus,
+        cancelled_by: Uuid,
+        reason: Option<&str>,
+    ) -> crate::Result<Option<CancelledOrder>> {
+        let Some(order) = self
+            .orders
+            .cancel_in_tx(tx, order_id, from, cancelled_by, reason)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let cancelled = self
+            .reverse_checkout_in_tx(tx, order, Some(cancelled_by))
+            .await?;
+        self.orders
+            .recompute_group_total_in_tx(tx, cancelled.order.order_group_id)
+            .await?;
+        Ok(Some(cancelled))
+    }
+
+    /// Cancels the group's orders that were never paid for and recomputes
+    /// the group total.
+    pub async fn cancel_unpaid_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order_group_id: Uuid,
+        reason: &str,
+    ) -> crate::Result<Vec<CancelledOrder>> {
+        let orders = self
+            .orders
+            .cancel_unpaid_in_tx(tx, order_group_id, reason)
+            .await?;
+        let mut cancelled = Vec::with_capacity(orders.len());
+        for order in orders {
+            cancelled.push(self.reverse_checkout_in_tx(tx, order, None).await?);
+        }
+        self.orders
+            .recompute_group_total_in_tx(tx, order_group_id)
+            .await?;
+        Ok(cancelled)
+    }
+
+    async fn reverse_checkout_in_tx(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        order: Order,
+        actor_id: Option<Uuid>,
+    ) -> crate::Result<CancelledOrder> {
+        let movements = self
+            .inventory
+            .restore_order_in_tx(tx, order.id, actor_id)
+            .await?;
+        if order.credit_applied > Decimal::ZERO {
+            self.credits
+                .restore_in_tx(
+                    tx,
+                    order.store_id,
+                    order.user_id,
+                    order.credit_applied,
+                    order.id,
+                )
+                .await?;
+        }
+        self.coupons.release_in_tx(tx, order.id).await?;
+
+        Ok(CancelledOrder {
+            units_restored: movements
+                .iter()
+                .map(|movement| i64::from(movement.delta))
+                .sum(),
+            order,
+        })
+    }
+}
@@ -1,68 +1,229 @@
-use chrono::Utc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use futures_util::Stream;
 use rust_decimal::Decimal;
-use serde_json::Value;
+use serde_json::{Map, Value};
+use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    currency::ExchangeRates,
     error::AppError,
-    models::order::{CartItemDetail, CheckoutRequest, CheckoutSummary, Order, PaymentStatus},
-    repositories::{CartRepository, OrderRepository, ProductRepository},
+    events::{Event, EventBus},
+    models::{
+        coupon::Coupon,
+        fulfillment::FulfillmentPartner,
+        notification::ORDER_CANCELLED,
+        order::{
+            tax_jurisdiction, CancelOrderRequest, CartItemDetail, CartOwner, CheckoutFingerprint,
+            CheckoutPreview, CheckoutRequest, CheckoutSummary, FulfillmentStatus, Order,
+            OrderDetail, OrderExportLine, OrderGroupDetail, OrderGroupEntry, OrderListEntry,
+            OrderStatus, PaymentStatus, StoreOrderDetail, StoreOrderFilter, StoreOrderPreview,
+        },
+        product::{ExportFormat, ProductType},
+        shipping::ShippingOption,
+        store::{validate_checkout_values, StoreSettings, StoreStatus},
+    },
+    repositories::{
+        CartRepository, CouponRepository, CreditRepository, FulfillmentRepository,
+        NotificationRepository, OrderRepository, ProductRepository, StoreSettingsRepository,
+    },
+    services::{
+        cart_policy::{CartPolicy, CartStore},
+        cart_service::DEFAULT_RESERVATION_TTL_SECS,
+        export::{paged_export, ExportRecord},
+        order_cancellation::OrderCancellation,
+        shipping_consolidation::{PartnerRateConsolidation, ShipmentQuote, ShippingConsolidation},
+    },
+    shipping::{ShippingParcel, ShippingRates},
+    tax::{TaxLookup, TaxRates},
 };
 
+/// How long an identical checkout is treated as an accidental resubmission.
+const DUPLICATE_CHECKOUT_WINDOW_SECS: i64 = 120;
+
 #[derive(Clone)]
 pub struct OrderService {
     orders: OrderRepository,
     products: ProductRepository,
     carts: CartRepository,
+    partners: FulfillmentRepository,
+    settings: StoreSettingsRepository,
+    credits: CreditRepository,
+    notifications: NotificationRepository,
+    coupons: CouponRepository,
+    cancellation: OrderCancellation,
+    consolidation: Arc<dyn ShippingConsolidation>,
+    tax_rates: Arc<TaxRates>,
+    shipping_rates: Arc<ShippingRates>,
+    exchange_rates: Arc<ExchangeRates>,
+    cart_policy: CartPolicy,
+    reservation_ttl: Duration,
+    events: EventBus,
 }
 
 impl OrderService {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         orders: OrderRepository,
         products: ProductRepository,
         carts: CartRepository,
+        partners: FulfillmentRepository,
+        settings: StoreSettingsRepository,
+        credits: CreditRepository,
+        notifications: NotificationRepository,
+        coupons: CouponRepository,
     ) -> Self {
         Self {
+            events: EventBus::with_defaults(orders.pool().clone()),
+            cancellation: OrderCancellation::new(orders.pool().clone()),
             orders,
             products,
             carts,
+            partners,
+            settings,
+            credits,
+            notifications,
+            coupons,
+            consolidation: Arc::new(PartnerRateConsolidation),
+            tax_rates: Arc::new(TaxRates::builtin()),
+            shipping_rates: Arc::new(ShippingRates::builtin()),
+            exchange_rates: Arc::new(ExchangeRates::none()),
+            cart_policy: CartPolicy::default(),
+            reservation_ttl: Duration::seconds(DEFAULT_RESERVATION_TTL_SECS),
         }
     }
 
+    pub fn with_consolidation(mut self, consolidation: Arc<dyn ShippingConsolidation>) -> Self {
+        self.consolidation = consolidation;
+        self
+    }
+
+    pub fn with_tax_rates(mut self, tax_rates: Arc<TaxRates>) -> Self {
+        self.tax_rates = tax_rates;
+        self
+    }
+
+    pub fn with_shipping_rates(mut self, shipping_rates: Arc<ShippingRates>) -> Self {
+        self.shipping_rates = shipping_rates;
+        self
+    }
+
+    pub fn with_exchange_rates(mut self, exchange_rates: Arc<ExchangeRates>) -> Self {
+        self.exchange_rates = exchange_rates;
+        self
+    }
+
+    pub fn with_cart_policy(mut self, cart_policy: CartPolicy) -> Self {
+        self.cart_policy = cart_policy;
+        self
+    }
+
+    pub fn with_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.reservation_ttl = ttl;
+        self
+    }
+
+    /// Replaces the bus checkouts are announced on, which defaults to one
+    /// notifying stores and filling the outbox.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
     pub async fn checkout(
         &self,
         user_id: Uuid,
         payload: CheckoutRequest,
     ) -> crate::Result<CheckoutSummary> {
-        payload
-            .validate()
-            .map_err(|err| AppError::Validation(err.to_string()))?;
+        self.checkout_with_currency(user_id, payload, None).await
+    }
 
-        let items = self.carts.list_with_products(user_id).await?;
-        if items.is_empty() {
-            return Err(AppError::BadRequest("Cart is empty".into()));
+    /// Places the order like `checkout`, also recording the currency the
+    /// shopper saw the totals in and the rate used, for receipts and
+    /// support.
+    pub async fn checkout_with_currency(
+        &self,
+        user_id: Uuid,
+        payload: CheckoutRequest,
+        display_currency: Option<&str>,
+    ) -> crate::Result<CheckoutSummary> {
+        match self.place_orders(user_id, payload, display_currency).await {
+            Ok((summary, events)) => {
+                for event in &events {
+                    self.events.publish(event).await;
+                }
+                Ok(summary)
+            }
+            Err(err) => {
+                self.events
+                    .publish(&Event::CheckoutFailed {
+                        reason: err.error_code(),
+                    })
+                    .await;
+                Err(err)
+            }
         }
+    }
+
+    /// Places the orders, returning them with the events published inside
+    /// the transaction, to publish again once it has committed.
+    async fn place_orders(
+        &self,
+        user_id: Uuid,
+        payload: CheckoutRequest,
+        display_currency: Option<&str>,
+    ) -> crate::Result<(CheckoutSummary, Vec<Event>)> {
+        let mut calculations = self.calculate(user_id, &payload).await?;
+        let mut presentment = HashMap::new();
+        for calc in &calculations {
+            match display_currency {
+                Some(display) if display != calc.currency => {
+                    let rate = self.exchange_rates.rate(&calc.currency, display).await?;
+                    presentment.insert(calc.store_id, (display, rate));
+                }
+                _ => {}
+            }
+        }
+
+        if !payload.confirm_duplicate {
+            self.ensure_not_duplicate(user_id, &calculations).await?;
+        }
+
+        let mut tx = self.orders.pool().begin().await?;
+        let lines = sorted_lines(&calculations);
+        let product_ids: Vec<Uuid> = lines.iter().map(|&(product_id, _)| product_id).collect();
+        self.products
+            .lock_for_sale_in_tx(&mut tx, &product_ids)
+            .await?;
+        self.reserve_lines(&mut tx, user_id, &lines).await?;
+        self.apply_store_credit(&mut tx, user_id, &mut calculations)
+            .await?;
 
-        let calculations = self.prepare_calculations(items, payload.shipping_address.clone());
         let group_total = calculations
             .iter()
             .fold(Decimal::ZERO, |acc, calc| acc + calc.total_amount);
+        // Nothing left to charge when store credit covers the whole checkout.
+        let payment_status = if group_total.is_zero() {
+            PaymentStatus::Paid
+        } else {
+            PaymentStatus::Pending
+        };
 
-        let mut tx = self.orders.pool().begin().await?;
         let group_number = format!("GRP-{}", short_id());
         let order_group = self
             .orders
-            .create_group(
-                &mut tx,
-                user_id,
-                &group_number,
-                group_total,
-                PaymentStatus::Pending,
-            )
+            .create_group(&mut tx, user_id, &group_number, group_total, payment_status)
             .await?;
 
         let mut created_orders: Vec<Order> = Vec::new();
+        let mut events = Vec::new();
         for calc in &calculations {
             let order_number = format!("ORD-{}", short_id());
             let order = self
@@ -77,10 +238,59 @@ impl OrderService {
                     calc.tax,
                     calc.discount,
                     calc.shipping_cost,
+                    calc.credit_applied,
                     calc.total_amount,
                     &calc.shipping_address,
+                    &calc.custom_fields,
+                    &calc.currency,
+                    presentment.get(&calc.store_id).copied(),
+                    calc.shipping_option
+                        .as_ref()
+                        .map(|option| option.name.as_str()),
+                )
+                .await?;
+
+            if !calc.credit_applied.is_zero() {
+                self.credits
+                    .redeem_in_tx(
+                        &mut tx,
+                        calc.store_id,
+                        user_id,
+                        calc.credit_applied,
+                        order.id,
+                    )
+                    .await?;
+            }
+
+            if let Some(coupon) = &calc.coupon {
+                let redeemed = self
+                    .coupons
+                    .redeem_in_tx(&mut tx, coupon.id, order.id, user_id, calc.discount)
+                    .await?;
+                if !redeemed {
+                    return Err(AppError::Conflict(format!(
+                        "Coupon {} is no longer available",
+                        coupon.code
+                    )));
+                }
+            }
+
+            self.orders
+                .create_tax_line(
+                    &mut tx,
+                    order.id,
+                    calc.store_id,
+                    &tax_jurisdiction(&calc.shipping_address),
+                    calc.tax_rate,
+                    calc.subtotal - calc.discount,
+                    calc.tax,
                 )
                 .await?;
+            if calc.files_tax {
+                self.orders
+                    .queue_tax_transaction_in_tx(&mut tx, order.id, calc.store_id)
+                    .await?;
+            }
 
             for line in &calc.items {
                 let line_subtotal = line.unit_price * Decimal::from(line.quantity);
@@ -95,21 +305,87 @@ impl OrderService {
                     )
                     .await?;
 
-                self.products
-                    .decrement_stock_in_tx(&mut tx, line.product_id, line.quantity)
+                let stock_after = self
+                    .products
+                    .decrement_stock_in_tx(
+                        &mut tx,
+                        line.product_id,
+                        line.quantity,
+                        user_id,
+                        order.id,
+                    )
                     .await?;
+                let stock_changed = Event::StockChanged {
+                    product_id: line.product_id,
+                    store_id: calc.store_id,
+                    product_name: line.product_name.clone(),
+                    stock_before: stock_after + line.quantity,
+                    stock_after,
+                };
+                self.events.publish_in_tx(&mut tx, &stock_changed).await?;
+                events.push(stock_changed);
             }
 
+            let order_placed = Event::OrderPlaced {
+                order: Box::new(order.clone()),
+                paid: payment_status == PaymentStatus::Paid,
+            };
+            self.events.publish_in_tx(&mut tx, &order_placed).await?;
+            events.push(order_placed);
             created_orders.push(order);
         }
 
+        self.carts
+            .consume_reservations_in_tx(&mut tx, user_id)
+            .await?;
+        self.coupons.clear_applied_in_tx(&mut tx, user_id).await?;
         tx.commit().await?;
-        self.carts.clear_user(user_id).await?;
+        self.carts.clear(CartOwner::User(user_id)).await?;
 
-        Ok(CheckoutSummary {
+        let summary = CheckoutSummary {
             order_group,
             orders: created_orders,
-        })
+        };
+        Ok((summary, events))
+    }
+
+    /// Runs the checkout calculation without placing anything, returning the
+    /// per-store breakdown `checkout` would charge for the current cart.
+    pub async fn preview(
+        &self,
+        user_id: Uuid,
+        payload: CheckoutRequest,
+    ) -> crate::Result<CheckoutPreview> {
+        let mut calculations = self.calculate(user_id, &payload).await?;
+
+        // Same credit logic as checkout; the transaction is rolled back so
+        // nothing is redeemed.
+        let mut tx = self.orders.pool().begin().await?;
+        self.apply_store_credit(&mut tx, user_id, &mut calculations)
+            .await?;
+        tx.rollback().await?;
+
+        let stores: Vec<StoreOrderPreview> = calculations
+            .into_iter()
+            .map(|calc| StoreOrderPreview {
+                store_id: calc.store_id,
+                store_name: calc.items[0].store_name.clone(),
+                currency: calc.currency,
+                subtotal: calc.subtotal,
+                tax_rate: calc.tax_rate,
+                tax: calc.tax,
+                discount: calc.discount,
+                coupon_code: calc.coupon.map(|coupon| coupon.code),
+                shipping_options: calc.shipping_options,
+                shipping_option: calc.shipping_option.map(|option| option.code),
+                shipping_cost: calc.shipping_cost,
+                credit_applied: calc.credit_applied,
+                total_amount: calc.total_amount,
+                items: calc.items,
+            })
+            .collect();
+
+        Ok(CheckoutPreview::new(stores))
     }
 
     pub async fn list_orders(
@@ -117,56 +393,628 @@ impl OrderService {
         user_id: Uuid,
         limit: i64,
         offset: i64,
-    ) -> crate::Result<Vec<Order>> {
+    ) -> crate::Result<Vec<OrderListEntry>> {
         self.orders
-            .list_orders_for_user(user_id, limit, offset)
+            .list_entries_for_user(user_id, limit, offset)
             .await
     }
 
-    fn prepare_calculations(
+    /// The buyer's checkouts, newest first, each with its per-store orders.
+    pub async fn list_order_groups(
+        &self,
+        user_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<OrderGroupEntry>> {
+        let groups = self
+            .orders
+            .list_groups_for_user(user_id, limit, offset)
+            .await?;
+        let group_ids: Vec<Uuid> = groups.iter().map(|group| group.id).collect();
+        let mut entries: HashMap<Uuid, Vec<OrderListEntry>> = HashMap::new();
+        for entry in self.orders.list_entries_for_groups(&group_ids).await? {
+            entries.entry(entry.order_group_id).or_default().push(entry);
+        }
+
+        Ok(groups
+            .into_iter()
+            .map(|group| OrderGroupEntry {
+                orders: entries.remove(&group.id).unwrap_or_default(),
+                group,
+            })
+            .collect())
+    }
+
+    /// One of the buyer's checkouts with every order in full.
+    pub async fn order_group_detail(
+        &self,
+        user_id: Uuid,
+        order_group_id: Uuid,
+    ) -> crate::Result<OrderGroupDetail> {
+        let group = self
+            .orders
+            .find_group(order_group_id)
+            .await?
+            .filter(|group| group.user_id == user_id)
+            .ok_or_else(|| AppError::NotFound("Order group not found".into()))?;
+
+        let mut orders = Vec::new();
+        for order in self.orders.list_for_group(group.id).await? {
+            let items = self.orders.list_item_details(order.id).await?;
+            let shipments = self.orders.list_shipments(order.id).await?;
+            let fulfillment =
+                FulfillmentStatus::of(items.iter().map(|line| &line.item), &shipments);
+            orders.push(OrderDetail {
+                order,
+                items,
+                shipments,
+                fulfillment,
+            });
+        }
+
+        Ok(OrderGroupDetail { group, orders })
+    }
+
+    pub async fn list_store_orders(
+        &self,
+        store_id: Uuid,
+        filter: &StoreOrderFilter,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<OrderListEntry>> {
+        let (placed_from, placed_before) = placed_between(filter.from, filter.to)?;
+        self.orders
+            .list_entries_for_store(
+                store_id,
+                filter.status,
+                placed_from,
+                placed_before,
+                limit,
+                offset,
+            )
+            .await
+    }
+
+    /// Every line of the store's orders placed on the days `from` to `to`,
+    /// oldest first, with order totals and tax, streamed in `format` for
+    /// accounting.
+    pub fn export_store_orders(
+        &self,
+        store_id: Uuid,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        format: ExportFormat,
+    ) -> crate::Result<impl Stream<Item = crate::Result<Bytes>> + Send + 'static> {
+        let (placed_from, placed_before) = placed_between(from, to)?;
+        let orders = self.orders.clone();
+        Ok(paged_export(format, move |after, limit| {
+            let orders = orders.clone();
+            async move {
+                orders
+                    .list_export_lines_after(store_id, placed_from, placed_before, after, limit)
+                    .await
+            }
+        }))
+    }
+
+    pub async fn store_order_detail(
         &self,
+        store_id: Uuid,
+        order_id: Uuid,
+    ) -> crate::Result<StoreOrderDetail> {
+        let order = self
+            .orders
+            .find_by_id(order_id)
+            .await?
+            .filter(|order| order.store_id == store_id)
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))?;
+        let items = self.orders.list_items(order.id).await?;
+        let shipments = self.orders.list_shipments(order.id).await?;
+
+        let fulfillment = FulfillmentStatus::of(&items, &shipments);
+
+        Ok(StoreOrderDetail {
+            order,
+            items,
+            shipments,
+            fulfillment,
+        })
+    }
+
+    /// Any order by id; callers decide whether the viewer may see it.
+    pub async fn order_detail(&self, order_id: Uuid) -> crate::Result<OrderDetail> {
+        let order = self
+            .orders
+            .find_by_id(order_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))?;
+        let items = self.orders.list_item_details(order.id).await?;
+        let shipments = self.orders.list_shipments(order.id).await?;
+
+        let fulfillment = FulfillmentStatus::of(items.iter().map(|line| &line.item), &shipments);
+
+        Ok(OrderDetail {
+            order,
+            items,
+            shipments,
+            fulfillment,
+        })
+    }
+
+    /// Cancels the buyer's own order: its items go back into stock, the
+    /// group total drops by the order's amount and the store is notified.
+    pub async fn cancel(
+        &self,
+        user_id: Uuid,
+        order_id: Uuid,
+        payload: CancelOrderRequest,
+    ) -> crate::Result<Order> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let current = self
+            .orders
+            .find_by_id(order_id)
+            .await?
+            .filter(|order| order.user_id == user_id)
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))?;
+        if !current.status.can_transition_to(OrderStatus::Cancelled) {
+            return Err(AppError::Conflict(format!(
+                "Cannot cancel an order that is {:?}",
+                current.status
+            )));
+        }
+        if self.orders.has_shipments(order_id).await? {
+            return Err(AppError::Conflict(
+                "Cannot cancel an order that has shipments".into(),
+            ));
+        }
+
+        let mut tx = self.orders.pool().begin().await?;
+        self.cancellation
+            .ensure_not_captured_in_tx(&mut tx, order_id)
+            .await?;
+        let order = self
+            .cancellation
+            .cancel_in_tx(
+                &mut tx,
+                order_id,
+                current.status,
+                user_id,
+                payload.reason.as_deref(),
+            )
+            .await?
+            .ok_or_else(|| AppError::Conflict("Order status changed concurrently".into()))?
+            .order;
+        let message = match &order.cancellation_reason {
+            Some(reason) => format!("Order {} was cancelled: {}", order.order_number, reason),
+            None => format!("Order {} was cancelled by the buyer", order.order_number),
+        };
+        self.notifications
+            .dispatch_store_event_in_tx(&mut tx, order.store_id, ORDER_CANCELLED, &message)
+            .await?;
+        tx.commit().await?;
+
+        Ok(order)
+    }
+
+    /// Validates the request and cart and prices every store's sub-order,
+    /// before store credit.
+    async fn calculate(
+        &self,
+        user_id: Uuid,
+        payload: &CheckoutRequest,
+    ) -> crate::Result<Vec<StoreCalculation>> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let items = self
+            .carts
+            .list_with_products(CartOwner::User(user_id))
+            .await?;
+        if items.is_empty() {
+            return Err(AppError::BadRequest("Cart is empty".into()));
+        }
+
+        if let Some(item) = items
+            .iter()
+            .find(|item| item.store_status != StoreStatus::Active)
+        {
+            return Err(AppError::Conflict(format!(
+                "Store '{}' is not accepting orders",
+                item.store_name
+            )));
+        }
+
+        let mut calculations = self
+            .prepare_calculations(
+                user_id,
+                items,
+                payload.shipping_address.clone(),
+                &payload.shipping_options,
+            )
+            .await?;
+        self.apply_checkout_fields(&mut calculations, &payload.custom_fields)
+            .await?;
+        Ok(calculations)
+    }
+
+    async fn prepare_calculations(
+        &self,
+        user_id: Uuid,
         grouped_items: Vec<CartItemDetail>,
         shipping_address: Value,
-    ) -> Vec<StoreCalculation> {
+        selected_shipping: &HashMap<Uuid, String>,
+    ) -> crate::Result<Vec<StoreCalculation>> {
         let grouped = CartItemDetail::group_by_store(&grouped_items);
-        grouped
+        let store_ids: Vec<Uuid> = grouped.keys().copied().collect();
+        let settings = self.settings.find_for_stores(&store_ids).await?;
+        let mut cart_stores: Vec<CartStore> = grouped
+            .iter()
+            .map(|(store_id, items)| {
+                let ships = items
+                    .iter()
+                    .any(|item| item.product_type == ProductType::Physical);
+                CartStore::new(items[0].store_name.clone(), &settings[store_id], ships)
+            })
+            .collect();
+        cart_stores.sort_by(|a, b| a.name.cmp(&b.name));
+        self.cart_policy
+            .check_checkout(&cart_stores, &shipping_address)?;
+        let partners = self.partners.partners_for_stores(&store_ids).await?;
+        let mut coupons: HashMap<Uuid, Coupon> = self
+            .coupons
+            .applied_for_user(user_id)
+            .await?
+            .into_iter()
+            .map(|coupon| (coupon.store_id, coupon))
+            .collect();
+        let now = Utc::now();
+
+        let mut tax_rates = HashMap::new();
+        for (store_id, items) in &grouped {
+            let amount = items.iter().fold(Decimal::ZERO, |acc, item| {
+                acc + item.unit_price * Decimal::from(item.quantity)
+            });
+            let lookup = TaxLookup {
+                settings: &settings[store_id],
+                shipping_address: &shipping_address,
+                amount,
+            };
+            tax_rates.insert(*store_id, self.tax_rates.rate(&lookup).await);
+        }
+
+        // Stores pick the buyer's shipping option, or their cheapest one,
+        // for the physical lines.
+        let rules = self.settings.shipping_rules_for_stores(&store_ids).await?;
+        let mut shipping = HashMap::new();
+        for (store_id, items) in &grouped {
+            let shipped: Vec<&CartItemDetail> = items
+                .iter()
+                .filter(|item| item.product_type == ProductType::Physical)
+                .collect();
+            if shipped.is_empty() {
+                continue;
+            }
+            let parcel = ShippingParcel {
+                settings: &settings[store_id],
+                rules: rules.get(store_id).map(Vec::as_slice).unwrap_or_default(),
+                destination: &shipping_address,
+                weight_grams: shipped.iter().fold(0, |acc, item| {
+                    acc + i64::from(item.weight_grams) * i64::from(item.quantity)
+                }),
+                subtotal: shipped.iter().fold(Decimal::ZERO, |acc, item| {
+                    acc + item.unit_price * Decimal::from(item.quantity)
+                }),
+            };
+            let options = self.shipping_rates.options(&parcel).await;
+            let chosen = match selected_shipping.get(store_id) {
+                Some(code) => options
+                    .iter()
+                    .find(|option| &option.code == code)
+                    .cloned()
+                    .ok_or_else(|| {
+                        AppError::BadRequest(format!(
+                            "Shipping option '{}' is not available from '{}'",
+                            code, items[0].store_name
+                        ))
+                    })?,
+                None => options[0].clone(),
+            };
+            shipping.insert(*store_id, (options, chosen));
+        }
+
+        let mut calculations: Vec<StoreCalculation> = grouped
             .into_iter()
             .map(|(store_id, items)| {
                 let subtotal = items.iter().fold(Decimal::ZERO, |acc, item| {
                     acc + item.unit_price * Decimal::from(item.quantity)
                 });
-                let tax = Decimal::ZERO;
-                let discount = Decimal::ZERO;
-                let shipping_cost = Decimal::ZERO;
+                // Digital items never ship, so only physical lines count
+                // towards the shipping fee and free-shipping threshold.
+                let shippable_subtotal = items
+                    .iter()
+                    .filter(|item| item.product_type == ProductType::Physical)
+                    .fold(Decimal::ZERO, |acc, item| {
+                        acc + item.unit_price * Decimal::from(item.quantity)
+                    });
+                let ships = items
+                    .iter()
+                    .any(|item| item.product_type == ProductType::Physical);
+                let store_settings = settings
+                    .get(&store_id)
+                    .cloned()
+                    .unwrap_or_else(|| StoreSettings::defaults(store_id));
+                // A coupon that lapsed or no longer fits the cart since it
+                // was applied is left out rather than failing the checkout.
+                let coupon = coupons
+                    .remove(&store_id)
+                    .filter(|coupon| coupon.ineligibility(subtotal, now).is_none());
+                let discount = coupon
+                    .as_ref()
+                    .map_or(Decimal::ZERO, |coupon| coupon.discount_for(subtotal));
+                let currency = store_settings.currency_code.clone();
+                let tax_rate = tax_rates[&store_id];
+                let tax = ((subtotal - discount) * tax_rate).round_dp(2);
+                let (shipping_options, shipping_option) = shipping
+                    .remove(&store_id)
+                    .map_or((Vec::new(), None), |(options, chosen)| {
+                        (options, Some(chosen))
+                    });
+                let shipping_cost = shipping_option
+                    .as_ref()
+                    .map_or(Decimal::ZERO, |option| option.cost);
+                let shipping_selected = selected_shipping.contains_key(&store_id);
                 let total_amount = subtotal + tax + shipping_cost - discount;
 
                 StoreCalculation {
                     store_id,
+                    currency,
                     items,
                     subtotal,
+                    ships,
+                    shippable_subtotal,
+                    tax_rate,
                     tax,
+                    files_tax: self.tax_rates.files_for(&store_settings),
                     discount,
+                    coupon,
+                    shipping_options,
+                    shipping_option,
+                    shipping_selected,
                     shipping_cost,
+                    credit_applied: Decimal::ZERO,
                     total_amount,
                     shipping_address: shipping_address.clone(),
+                    custom_fields: Value::Object(Map::new()),
                 }
             })
-            .collect()
+            .collect();
+
+        self.consolidate_shipping(&mut calculations, &partners);
+        Ok(calculations)
+    }
+
+    /// Refreshes the buyer's reservation for every line, re-acquiring any
+    /// that lapsed. Checkout has locked the product rows by then.
+    async fn reserve_lines(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+        lines: &[(Uuid, i32)],
+    ) -> crate::Result<()> {
+        for &(product_id, quantity) in lines {
+            self.carts
+                .reserve_in_tx(tx, user_id, product_id, quantity, self.reservation_ttl)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Validates the buyer's answers against each store's checkout fields
+    /// and records the cleaned values on the matching sub-order.
+    async fn apply_checkout_fields(
+        &self,
+        calculations: &mut [StoreCalculation],
+        submitted: &HashMap<Uuid, Map<String, Value>>,
+    ) -> crate::Result<()> {
+        let store_ids: Vec<Uuid> = calculations.iter().map(|calc| calc.store_id).collect();
+        let fields = self.settings.checkout_fields_for_stores(&store_ids).await?;
+        let empty = Map::new();
+
+        for calc in calculations.iter_mut() {
+            let store_fields = fields
+                .get(&calc.store_id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let values = submitted.get(&calc.store_id).unwrap_or(&empty);
+            let cleaned =
+                validate_checkout_values(store_fields, values).map_err(AppError::Validation)?;
+            calc.custom_fields = Value::Object(cleaned);
+        }
+        Ok(())
+    }
+
+    async fn ensure_not_duplicate(
+        &self,
+        user_id: Uuid,
+        calculations: &[StoreCalculation],
+    ) -> crate::Result<()> {
+        let since = Utc::now() - Duration::seconds(DUPLICATE_CHECKOUT_WINDOW_SECS);
+        let recent = self
+            .orders
+            .recent_checkout_fingerprints(user_id, since)
+            .await?;
+        if recent.is_empty() {
+            return Ok(());
+        }
+
+        let current = CheckoutFingerprint::new(
+            calculations
+                .iter()
+                .flat_map(|calc| calc.items.iter())
+                .map(|item| (item.product_id, item.quantity)),
+            calculations
+                .iter()
+                .fold(Decimal::ZERO, |acc, calc| acc + calc.total_amount),
+        );
+
+        if recent.contains(&current) {
+            return Err(AppError::Conflict(
+                "An identical order was just placed; set confirm_duplicate to order again".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Applies each store's credit balance to its sub-order before anything
+    /// is charged. Balances are locked in store order to avoid deadlocks
+    /// between concurrent checkouts.
+    async fn apply_store_credit(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        user_id: Uuid,
+        calculations: &mut [StoreCalculation],
+    ) -> crate::Result<()> {
+        calculations.sort_by_key(|calc| calc.store_id);
+        for calc in calculations.iter_mut() {
+            let balance = self
+                .credits
+                .lock_balance_in_tx(tx, calc.store_id, user_id)
+                .await?;
+            let applied = balance.min(calc.total_amount);
+            calc.credit_applied = applied;
+            calc.total_amount -= applied;
+        }
+        Ok(())
+    }
+
+    /// Prices shipments through shared fulfillment partners. Stores that
+    /// ship for free, or whose option the buyer picked, keep their own cost.
+    fn consolidate_shipping(
+        &self,
+        calculations: &mut [StoreCalculation],
+        partners: &HashMap<Uuid, FulfillmentPartner>,
+    ) {
+        let consolidates = |calc: &StoreCalculation| {
+            calc.ships && !calc.shipping_selected && !calc.shipping_cost.is_zero()
+        };
+        let mut quotes: Vec<ShipmentQuote> = calculations
+            .iter()
+            .filter(|calc| consolidates(calc))
+            .map(|calc| ShipmentQuote {
+                store_id: calc.store_id,
+                currency: calc.currency.clone(),
+                subtotal: calc.shippable_subtotal,
+                shipping_cost: calc.shipping_cost,
+            })
+            .collect();
+
+        self.consolidation.consolidate(&mut quotes, partners);
+
+        let shipping_calcs = calculations.iter_mut().filter(|calc| consolidates(calc));
+        for (calc, quote) in shipping_calcs.zip(quotes) {
+            calc.shipping_cost = quote.shipping_cost;
+            calc.total_amount = calc.subtotal + calc.tax + calc.shipping_cost - calc.discount;
+        }
     }
 }
 
 struct StoreCalculation {
     store_id: Uuid,
+    currency: String,
     items: Vec<CartItemDetail>,
     subtotal: Decimal,
+    /// Whether any line is a physical product that has to be shipped.
+    ships: bool,
+    shippable_subtotal: Decimal,
+    tax_rate: Decimal,
     tax: Decimal,
+    /// Whether the sale is reported to the external tax provider.
+    files_tax: bool,
     discount: Decimal,
+    coupon: Option<Coupon>,
+    shipping_options: Vec<ShippingOption>,
+    /// The option `shipping_cost` was priced with, for orders that ship.
+    shipping_option: Option<ShippingOption>,
+    /// Whether the buyer picked `shipping_option` rather than taking the
+    /// cheapest.
+    shipping_selected: bool,
     shipping_cost: Decimal,
+    credit_applied: Decimal,
     total_amount: Decimal,
     shipping_address: Value,
+    custom_fields: Value,
+}
+
+/// Quantities to sell per product, in product id order: the order checkout
+/// locks product rows in.
+fn sorted_lines(calculations: &[StoreCalculation]) -> Vec<(Uuid, i32)> {
+    let mut lines: BTreeMap<Uuid, i32> = BTreeMap::new();
+    for item in calculations.iter().flat_map(|calc| calc.items.iter()) {
+        *lines.entry(item.product_id).or_insert(0) += item.quantity;
+    }
+    lines.into_iter().collect()
 }
 
 fn short_id() -> String {
     let now = Utc::now().timestamp_millis();
     format!("{:x}", now)
 }
+
+/// Half-open `[start, end)` range of instants; either end may be open.
+type PlacedRange = (Option<DateTime<Utc>>, Option<DateTime<Utc>>);
+
+/// The range covering the inclusive days `from` to `to`.
+fn placed_between(from: Option<NaiveDate>, to: Option<NaiveDate>) -> crate::Result<PlacedRange> {
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(AppError::BadRequest(
+                "Order date range start must not be after its end".into(),
+            ));
+        }
+    }
+    let day_start = |day: NaiveDate| day.and_time(NaiveTime::MIN).and_utc();
+    Ok((
+        from.map(day_start),
+        to.map(|to| day_start(to) + Duration::days(1)),
+    ))
+}
+
+impl ExportRecord for OrderExportLine {
+    type Key = (DateTime<Utc>, Uuid, Uuid);
+    type Row<'a> = &'a OrderExportLine;
+
+    const CSV_HEADER: &'static [&'static str] = &[
+        "order_number",
+        "status",
+        "created_at",
+        "buyer_name",
+        "currency",
+        "sku",
+        "product_name",
+        "quantity",
+        "unit_price",
+        "line_subtotal",
+        "order_subtotal",
+        "discount",
+        "shipping_cost",
+        "tax",
+        "tax_jurisdiction",
+        "credit_applied",
+        "total_amount",
+        "order_id",
+        "item_id",
+    ];
+
+    fn export_key(&self) -> (DateTime<Utc>, Uuid, Uuid) {
+        (self.created_at, self.order_id, self.item_id)
+    }
+
+    fn export_row(&self) -> &OrderExportLine {
+        self
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::Utc;
+use serde_json::json;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    events::{Event, EventBus},
+    models::{
+        audit::{self, AuditAction, NewAuditEvent},
+        notification::ORDER_STATUS_CHANGED,
+        order::{
+            unshipped_quantities, BulkOrderStatusFailure, BulkOrderStatusRequest,
+            BulkOrderStatusResponse, CreateShipmentRequest, FulfillmentStatus, Order, OrderStatus,
+            Shipment, ShipmentItemRequest, UpdateOrderStatusRequest, UpdateShipmentRequest,
+        },
+    },
+    repositories::{NotificationRepository, OrderRepository},
+    services::{AuditService, OrderCancellation},
+};
+use uuid::Uuid;
+
+/// Moves store orders through their fulfillment lifecycle and tells buyers
+/// about each change. Cancelled orders go back into stock, and every change
+/// is recorded in the audit log and announced on the event bus.
+#[derive(Clone)]
+pub struct OrderStatusService {
+    orders: OrderRepository,
+    notifications: NotificationRepository,
+    cancellation: OrderCancellation,
+    audit: AuditService,
+    events: EventBus,
+}
+
+impl OrderStatusService {
+    pub fn new(
+        orders: OrderRepository,
+        notifications: NotificationRepository,
+        audit: AuditService,
+    ) -> Self {
+        Self {
+            cancellation: OrderCancellation::new(orders.pool().clone()),
+            events: EventBus::with_defaults(orders.pool().clone()),
+            orders,
+            notifications,
+            audit,
+        }
+    }
+
+    /// Replaces the bus status changes are announced on, which defaults to
+    /// one notifying stores and filling the outbox.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub async fn update_status(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        order_id: Uuid,
+        payload: UpdateOrderStatusRequest,
+    ) -> crate::Result<Order> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+        self.transition(
+            actor_id,
+            store_id,
+            order_id,
+            payload.status,
+            payload.reason.as_deref(),
+        )
+        .await
+    }
+
+    /// Records a shipment of some or all of the units still to ship. The
+    /// order moves to Shipped once every unit is on its way; orders already
+    /// shipped may get further parcels.
+    pub async fn ship(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        order_id: Uuid,
+        payload: CreateShipmentRequest,
+    ) -> crate::Result<Shipment> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let mut tx = self.orders.pool().begin().await?;
+        let current = self
+            .orders
+            .lock_store_order_in_tx(&mut tx, store_id, order_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))?;
+        if !matches!(
+            current.status,
+            OrderStatus::Processing | OrderStatus::Shipped
+        ) {
+            return Err(AppError::Conflict(format!(
+                "Cannot ship an order that is {:?}",
+                current.status
+            )));
+        }
+
+        let items = self.orders.list_items_in_tx(&mut tx, current.id).await?;
+        let mut shipments = self
+            .orders
+            .list_shipments_in_tx(&mut tx, current.id)
+            .await?;
+        let lines = shipment_lines(
+            unshipped_quantities(&items, &shipments),
+            payload.items.as_deref(),
+        )?;
+        if lines.is_empty() && current.status == OrderStatus::Processing {
+            return Err(AppError::Conflict("Nothing left to ship".into()));
+        }
+
+        let mut shipment = self
+            .orders
+            .create_shipment_in_tx(
+                &mut tx,
+                current.id,
+                store_id,
+                &payload.carrier,
+                &payload.tracking_number,
+                payload.tracking_url.as_deref(),
+                payload.shipped_at.unwrap_or_else(Utc::now),
+                actor_id,
+            )
+            .await?;
+        if !lines.is_empty() {
+            shipment.items = self
+                .orders
+                .create_shipment_items_in_tx(&mut tx, shipment.id, &lines)
+                .await?;
+        }
+        shipments.push(shipment.clone());
+
+        let fulfillment = FulfillmentStatus::of(&items, &shipments);
+        let now_shipped =
+            current.status == OrderStatus::Processing && fulfillment == FulfillmentStatus::Shipped;
+        let mut status_changed = None;
+        if now_shipped {
+            let order = self
+                .orders
+                .transition_status_in_tx(
+                    &mut tx,
+                    store_id,
+                    current.id,
+                    OrderStatus::Processing,
+                    OrderStatus::Shipped,
+                )
+                .await?
+                .ok_or_else(|| AppError::Conflict("Order status changed concurrently".into()))?;
+            let event = Event::OrderStatusChanged {
+                order: Box::new(order),
+                from: OrderStatus::Processing,
+            };
+            self.events.publish_in_tx(&mut tx, &event).await?;
+            status_changed = Some(event);
+        }
+        let what = if fulfillment == FulfillmentStatus::PartiallyShipped {
+            "Part of order"
+        } else {
+            "Order"
+        };
+        self.notifications
+            .create_for_users_in_tx(
+                &mut tx,
+                &[current.user_id],
+                Some(store_id),
+                ORDER_STATUS_CHANGED,
+                &format!(
+                    "{} {} shipped with {}, tracking number {}",
+                    what, current.order_number, shipment.carrier, shipment.tracking_number
+                ),
+            )
+            .await?;
+        tx.commit().await?;
+
+        if let Some(event) = status_changed {
+            self.events.publish(&event).await;
+            self.record_status_change(
+                store_id,
+                Some(actor_id),
+                current.id,
+                OrderStatus::Processing,
+                OrderStatus::Shipped,
+                None,
+            )
+            .await;
+        }
+
+        Ok(shipment)
+    }
+
+    /// Corrects a shipment or records its delivery. The order is Delivered
+    /// once every unit was shipped and every parcel has arrived.
+    pub async fn update_shipment(
+        &self,
+        store_id: Uuid,
+        order_id: Uuid,
+        shipment_id: Uuid,
+        payload: UpdateShipmentRequest,
+    ) -> crate::Result<Shipment> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let mut tx = self.orders.pool().begin().await?;
+        let current = self
+            .orders
+            .lock_store_order_in_tx(&mut tx, store_id, order_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Shipment not found".into()))?;
+        let mut shipment = self
+            .orders
+            .update_shipment_in_tx(&mut tx, store_id, order_id, shipment_id, &payload)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Shipment not found".into()))?;
+
+        let items = self.orders.list_items_in_tx(&mut tx, order_id).await?;
+        let shipments = self.orders.list_shipments_in_tx(&mut tx, order_id).await?;
+        if let Some(updated) = shipments.iter().find(|s| s.id == shipment.id) {
+            shipment.items = updated.items.clone();
+        }
+        let now_delivered = current.status == OrderStatus::Shipped
+            && FulfillmentStatus::of(&items, &shipments) == FulfillmentStatus::Delivered;
+        let mut status_changed = None;
+        if now_delivered {
+            let order = self
+                .orders
+                .transition_status_in_tx(
+                    &mut tx,
+                    store_id,
+                    order_id,
+                    OrderStatus::Shipped,
+                    OrderStatus::Delivered,
+                )
+                .await?
+                .ok_or_else(|| AppError::Conflict("Order status changed concurrently".into()))?;
+            let event = Event::OrderStatusChanged {
+                order: Box::new(order),
+                from: OrderStatus::Shipped,
+            };
+            self.events.publish_in_tx(&mut tx, &event).await?;
+            status_changed = Some(event);
+            self.notifications
+                .create_for_users_in_tx(
+                    &mut tx,
+                    &[current.user_id],
+                    Some(store_id),
+                    ORDER_STATUS_CHANGED,
+                    &format!(
+                        "Order {} is now {:?}",
+                        current.order_number,
+                        OrderStatus::Delivered
+                    ),
+                )
+                .await?;
+        }
+        tx.commit().await?;
+
+        if let Some(event) = status_changed {
+            self.events.publish(&event).await;
+            self.record_status_change(
+                store_id,
+                None,
+                order_id,
+                OrderStatus::Shipped,
+                OrderStatus::Delivered,
+                None,
+            )
+            .await;
+        }
+
+        Ok(shipment)
+    }
+
+    /// Applies the same transition to many orders. Each order is validated
+    /// and committed on its own, so one illegal transition does not block
+    /// the rest of the batch.
+    pub async fn bulk_transition(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        payload: BulkOrderStatusRequest,
+    ) -> crate::Result<BulkOrderStatusResponse> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let mut response = BulkOrderStatusResponse {
+            status: payload.status,
+            updated: Vec::new(),
+            failed: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+
+        for order_id in payload.order_ids {
+            if !seen.insert(order_id) {
+                continue;
+            }
+
+            match self
+                .transition(actor_id, store_id, order_id, payload.status, None)
+                .await
+            {
+                Ok(order) => response.updated.push(order.id),
+                Err(AppError::NotFound(message) | AppError::Conflict(message)) => {
+                    response
+                        .failed
+                        .push(BulkOrderStatusFailure { order_id, message });
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(response)
+    }
+
+    async fn transition(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        order_id: Uuid,
+        status: OrderStatus,
+        reason: Option<&str>,
+    ) -> crate::Result<Order> {
+        if matches!(status, OrderStatus::Shipped | OrderStatus::Delivered) {
+            return Err(AppError::Conflict(format!(
+                "Orders become {:?} through their shipments",
+                status
+            )));
+        }
+        let current = self.store_order(store_id, order_id).await?;
+        if !current.status.can_transition_to(status) {
+            return Err(AppError::Conflict(format!(
+                "Cannot move order from {:?} to {:?}",
+                current.status, status
+            )));
+        }
+        if status == OrderStatus::Cancelled && self.orders.has_shipments(order_id).await? {
+            return Err(AppError::Conflict(
+                "Cannot cancel an order that has shipments".into(),
+            ));
+        }
+
+        let mut tx = self.orders.pool().begin().await?;
+        let order = if status == OrderStatus::Cancelled {
+            self.cancellation
+                .ensure_not_captured_in_tx(&mut tx, order_id)
+                .await?;
+            self.cancellation
+                .cancel_in_tx(&mut tx, order_id, current.status, actor_id, reason)
+                .await?
+                .map(|cancelled| cancelled.order)
+        } else {
+            self.orders
+                .transition_status_in_tx(&mut tx, store_id, order_id, current.status, status)
+                .await?
+        }
+        .ok_or_else(|| AppError::Conflict("Order status changed concurrently".into()))?;
+        self.notifications
+            .create_for_users_in_tx(
+                &mut tx,
+                &[order.user_id],
+                Some(store_id),
+                ORDER_STATUS_CHANGED,
+                &format!("Order {} is now {:?}", order.order_number, status),
+            )
+            .await?;
+        let status_changed = Event::OrderStatusChanged {
+            order: Box::new(order.clone()),
+            from: current.status,
+        };
+        self.events.publish_in_tx(&mut tx, &status_changed).await?;
+        tx.commit().await?;
+        self.events.publish(&status_changed).await;
+
+        self.record_status_change(
+            store_id,
+            Some(actor_id),
+            order.id,
+            current.status,
+            order.status,
+            reason,
+        )
+        .await;
+
+        Ok(order)
+    }
+
+    async fn record_status_change(
+        &self,
+        store_id: Uuid,
+        actor_id: Option<Uuid>,
+        order_id: Uuid,
+        from: OrderStatus,
+        to: OrderStatus,
+        reason: Option<&str>,
+    ) {
+        self.audit
+            .record(NewAuditEvent {
+                store_id: Some(store_id),
+                actor_id,
+                action: AuditAction::OrderStatusChange,
+                entity_type: "order",
+                entity_id: Some(order_id),
+                diff: json!({ "status": audit::change(from, to), "reason": reason }),
+            })
+            .await;
+    }
+
+    async fn store_order(&self, store_id: Uuid, order_id: Uuid) -> crate::Result<Order> {
+        self.orders
+            .find_by_id(order_id)
+            .await?
+            .filter(|order| order.store_id == store_id)
+            .ok_or_else(|| AppError::NotFound("Order not found".into()))
+    }
+}
+
+/// The `(order_item_id, quantity)` lines a new shipment carries: the ones
+/// asked for, or everything still unshipped.
+fn shipment_lines(
+    unshipped: HashMap<Uuid, i32>,
+    requested: Option<&[ShipmentItemRequest]>,
+) -> crate::Result<Vec<(Uuid, i32)>> {
+    let Some(requested) = requested else {
+        let mut lines: Vec<_> = unshipped
+            .into_iter()
+            .filter(|&(_, quantity)| quantity > 0)
+            .collect();
+        lines.sort();
+        return Ok(lines);
+    };
+
+    let mut lines: BTreeMap<Uuid, i32> = BTreeMap::new();
+    for line in requested {
+        *lines.entry(line.order_item_id).or_insert(0) += line.quantity;
+    }
+    for (&item_id, &quantity) in &lines {
+        let left = unshipped.get(&item_id).copied().ok_or_else(|| {
+            AppError::Validation(format!("Item {} is not part of this order", item_id))
+        })?;
+        if quantity > left {
+            return Err(AppError::Conflict(format!(
+                "Only {} unit(s) of item {} are left to ship",
+                left.max(0),
+                item_id
+            )));
+        }
+    }
+    Ok(lines.into_iter().collect())
+}
@@ -0,0 +1,426 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::http::HeaderMap;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{
+        domain_event::DomainEvent,
+        order::{Order, OrderStatus, PaymentStatus},
+        payment::{PayOrderGroupRequest, Payment, PaymentAttemptStatus, PaymentProviderKind},
+    },
+    payments::{PaymentEvent, PaymentProviders, PaymentRequest},
+    repositories::{OrderRepository, OutboxRepository, PaymentRepository, StoreSettingsRepository},
+};
+
+/// Collects order groups through each store's payment provider and keeps the
+/// group's payment status in step with the attempts.
+#[derive(Clone)]
+pub struct PaymentService {
+    payments: PaymentRepository,
+    orders: OrderRepository,
+    settings: StoreSettingsRepository,
+    outbox: OutboxRepository,
+    providers: Arc<PaymentProviders>,
+}
+
+impl PaymentService {
+    pub fn new(
+        payments: PaymentRepository,
+        orders: OrderRepository,
+        settings: StoreSettingsRepository,
+    ) -> Self {
+        Self {
+            outbox: OutboxRepository::new(orders.pool().clone()),
+            payments,
+            orders,
+            settings,
+            providers: Arc::new(PaymentProviders::manual_only()),
+        }
+    }
+
+    pub fn with_providers(mut self, providers: Arc<PaymentProviders>) -> Self {
+        self.providers = providers;
+        self
+    }
+
+    /// Opens the group's payments through each store's provider, as
+    /// checkout does.
+    pub async fn start(&self, order_group_id: Uuid) -> crate::Result<Vec<Payment>> {
+        self.open_group(order_group_id, None).await
+    }
+
+    /// The buyer (re)attempting payment for their group. Paid and refunded
+    /// groups are closed; a failed group can be retried, optionally through
+    /// a different provider.
+    pub async fn pay(
+        &self,
+        user_id: Uuid,
+        order_group_id: Uuid,
+        payload: PayOrderGroupRequest,
+    ) -> crate::Result<Vec<Payment>> {
+        let group = self
+            .orders
+            .find_group(order_group_id)
+            .await?
+            .filter(|group| group.user_id == user_id)
+            .ok_or_else(|| AppError::NotFound("Order group not found".into()))?;
+        match group.payment_status {
+            PaymentStatus::Paid => {
+                return Err(AppError::Conflict(
+                    "This order group has already been paid".into(),
+                ))
+            }
+            PaymentStatus::Refunded => {
+                return Err(AppError::Conflict(
+                    "This order group has been refunded".into(),
+                ))
+            }
+            PaymentStatus::Pending | PaymentStatus::Failed => {}
+        }
+        if let Some(method) = payload.method {
+            self.providers.get(method)?;
+        }
+
+        let orders = self.orders.list_for_group(order_group_id).await?;
+        let latest = self.latest_by_order(order_group_id).await?;
+        let awaiting = orders
+            .iter()
+            .filter(|order| owes_payment(order))
+            .any(|order| {
+                latest
+                    .get(&order.id)
+                    .is_none_or(|payment| payment.status != PaymentAttemptStatus::Captured)
+            });
+        if !awaiting {
+            return Err(AppError::Conflict(
+                "Nothing in this order group is awaiting payment".into(),
+            ));
+        }
+        self.open_group(order_group_id, payload.method).await
+    }
+
+    /// Store staff confirming a payment, e.g. a manual transfer arriving.
+    pub async fn capture_order(&self, store_id: Uuid, order_id: Uuid) -> crate::Result<Payment> {
+        let payment = self.store_payment(store_id, order_id).await?;
+        if !payment.status.is_open() {
+            return Err(AppError::Conflict(format!(
+                "A {:?} payment cannot be captured",
+                payment.status
+            )));
+        }
+        let payment = self.capture(payment).await?;
+        self.settle(payment.order_group_id).await?;
+        Ok(payment)
+    }
+
+    /// Returns a captured payment in full.
+    pub async fn refund_order(&self, store_id: Uuid, order_id: Uuid) -> crate::Result<Payment> {
+        let payment = self.store_payment(store_id, order_id).await?;
+        if payment.status != PaymentAttemptStatus::Captured {
+            return Err(AppError::Conflict(format!(
+                "A {:?} payment cannot be refunded",
+                payment.status
+            )));
+        }
+        let provider = self.providers.get(payment.provider)?;
+        provider
+            .refund(reference(&payment)?, payment.amount, &payment.currency)
+            .await?;
+        let payment = self
+            .payments
+            .set_status(payment.id, PaymentAttemptStatus::Refunded, None)
+            .await?;
+        self.settle(payment.order_group_id).await?;
+        Ok(payment)
+    }
+
+    /// Applies a provider's webhook delivery. Deliveries about payments we
+    /// do not know are acknowledged and ignored.
+    pub async fn handle_webhook(
+        &self,
+        provider: PaymentProviderKind,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> crate::Result<()> {
+        let Some(event) = self
+            .providers
+            .get(provider)?
+            .parse_webhook(headers, body)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let reference = match &event {
+            PaymentEvent::Approved { reference }
+            | PaymentEvent::Captured { reference }
+            | PaymentEvent::Failed { reference, .. }
+            | PaymentEvent::Refunded { reference } => reference,
+        };
+        let Some(payment) = self.payments.find_by_reference(provider, reference).await? else {
+            tracing::warn!(
+                provider = provider.as_str(),
+                "Ignoring webhook for unknown payment {}",
+                reference
+            );
+            return Ok(());
+        };
+
+        let group_id = payment.order_group_id;
+        match event {
+            PaymentEvent::Approved { .. } if payment.status.is_open() => {
+                self.capture(payment).await?;
+            }
+            PaymentEvent::Captured { .. } if payment.status.is_open() => {
+                self.payments
+                    .set_status(payment.id, PaymentAttemptStatus::Captured, None)
+                    .await?;
+            }
+            PaymentEvent::Failed { reason, .. } if payment.status.is_open() => {
+                self.payments
+                    .set_status(payment.id, PaymentAttemptStatus::Failed, Some(&reason))
+                    .await?;
+            }
+            PaymentEvent::Refunded { .. } => {
+                self.payments
+                    .set_status(payment.id, PaymentAttemptStatus::Refunded, None)
+                    .await?;
+            }
+            // Redelivered or out-of-order events leave the payment as is.
+            _ => {}
+        }
+        self.settle(group_id).await
+    }
+
+    /// Opens a payment for every sub-order still owing money, through
+    /// `method` or else its store's provider. Open attempts through another
+    /// provider are replaced; a provider error fails that attempt rather than
+    /// the call, so the rest of the group can still be paid.
+    async fn open_group(
+        &self,
+        order_group_id: Uuid,
+        method: Option<PaymentProviderKind>,
+    ) -> crate::Result<Vec<Payment>> {
+        let orders = self.orders.list_for_group(order_group_id).await?;
+        let latest = self.latest_by_order(order_group_id).await?;
+        let store_ids: Vec<Uuid> = orders.iter().map(|order| order.store_id).collect();
+        let settings = self.settings.find_for_stores(&store_ids).await?;
+
+        for order in orders.iter().filter(|order| owes_payment(order)) {
+            let provider = method.unwrap_or(settings[&order.store_id].payment_provider);
+            match latest.get(&order.id) {
+                Some(payment) if payment.status == PaymentAttemptStatus::Captured => continue,
+                Some(payment) if payment.status.is_open() && payment.provider == provider => {
+                    continue
+                }
+                Some(payment) if payment.status.is_open() => {
+                    self.payments
+                        .set_status(
+                            payment.id,
+                            PaymentAttemptStatus::Failed,
+                            Some("Replaced by a new payment attempt"),
+                        )
+                        .await?;
+                }
+                _ => {}
+            }
+            self.open(order, provider).await?;
+        }
+
+        self.settle(order_group_id).await?;
+        self.payments.latest_for_group(order_group_id).await
+    }
+
+    async fn open(&self, order: &Order, provider: PaymentProviderKind) -> crate::Result<Payment> {
+        let payment = self
+            .payments
+            .create(
+                order.order_group_id,
+                order.id,
+                order.store_id,
+                provider,
+                order.total_amount,
+                &order.currency,
+            )
+            .await?;
+        let request = PaymentRequest {
+            payment_id: payment.id,
+            order_number: &order.order_number,
+            amount: payment.amount,
+            currency: &payment.currency,
+        };
+        let intent = match self.providers.get(provider) {
+            Ok(provider) => provider.create_intent(&request).await,
+            Err(err) => Err(err),
+        };
+
+        match intent {
+            Ok(intent) => {
+                self.payments
+                    .record_intent(
+                        payment.id,
+                        &intent.reference,
+                        intent.status,
+                        intent.approval_url.as_deref(),
+                    )
+                    .await
+            }
+            Err(err) => {
+                tracing::warn!(
+                    order_id = %order.id,
+                    provider = provider.as_str(),
+                    "Could not open payment: {}",
+                    err
+                );
+                self.payments
+                    .set_status(
+                        payment.id,
+                        PaymentAttemptStatus::Failed,
+                        Some(&err.to_string()),
+                    )
+                    .await
+            }
+        }
+    }
+
+    async fn capture(&self, payment: Payment) -> crate::Result<Payment> {
+        let provider = self.providers.get(payment.provider)?;
+        let status = provider.capture(reference(&payment)?).await?;
+        let reason = (status == PaymentAttemptStatus::Failed).then_some("Capture was declined");
+        self.payments.set_status(payment.id, status, reason).await
+    }
+
+    async fn store_payment(&self, store_id: Uuid, order_id: Uuid) -> crate::Result<Payment> {
+        self.payments
+            .latest_for_order(order_id)
+            .await?
+            .filter(|payment| payment.store_id == store_id)
+            .ok_or_else(|| AppError::NotFound("Payment not found".into()))
+    }
+
+    async fn latest_by_order(&self, order_group_id: Uuid) -> crate::Result<HashMap<Uuid, Payment>> {
+        Ok(self
+            .payments
+            .latest_for_group(order_group_id)
+            .await?
+            .into_iter()
+            .map(|payment| (payment.order_id, payment))
+            .collect())
+    }
+
+    /// Derives the group's payment status from the latest attempt of each
+    /// sub-order that is, or was, being paid for. The group becoming paid
+    /// publishes `OrderPaid` for each sub-order paid for.
+    async fn settle(&self, order_group_id: Uuid) -> crate::Result<()> {
+        let orders = self.orders.list_for_group(order_group_id).await?;
+        let latest = self.latest_by_order(order_group_id).await?;
+        let attempts: Vec<Option<PaymentAttemptStatus>> = orders
+            .iter()
+            .map(|order| (order, latest.get(&order.id).map(|payment| payment.status)))
+            .filter(|(order, status)| {
+                owes_payment(order)
+                    || matches!(
+                        status,
+                        Some(PaymentAttemptStatus::Captured | PaymentAttemptStatus::Refunded)
+                    )
+            })
+            .map(|(_, status)| status)
+            .collect();
+
+        let Some(status) = group_payment_status(&attempts) else {
+            return Ok(());
+        };
+        let mut tx = self.orders.pool().begin().await?;
+        let changed = self
+            .orders
+            .change_payment_status_in_tx(&mut tx, order_group_id, status)
+            .await?;
+        // Settling again, e.g. on a redelivered provider webhook, changes
+        // nothing and publishes nothing.
+        if changed && status == PaymentStatus::Paid {
+            for order in orders.iter().filter(|order| owes_payment(order)) {
+                self.outbox
+                    .record_in_tx(
+                        &mut tx,
+                        &DomainEvent::OrderPaid {
+                            order_id: order.id,
+                            order_group_id,
+                            store_id: order.store_id,
+                            buyer_id: order.user_id,
+                            total_amount: order.total_amount,
+                            currency: order.currency.clone(),
+                        },
+                    )
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+fn owes_payment(order: &Order) -> bool {
+    order.status != OrderStatus::Cancelled && order.total_amount > Decimal::ZERO
+}
+
+fn reference(payment: &Payment) -> crate::Result<&str> {
+    payment
+        .reference
+        .as_deref()
+        .ok_or_else(|| AppError::Conflict("The payment was never opened with the provider".into()))
+}
+
+/// The group's payment status given each sub-order's latest attempt, or
+/// `None` when nothing in the group is paid through a provider.
+fn group_payment_status(attempts: &[Option<PaymentAttemptStatus>]) -> Option<PaymentStatus> {
+    if attempts.is_empty() {
+        return None;
+    }
+    let status = if attempts
+        .iter()
+        .any(|attempt| attempt.is_none_or(PaymentAttemptStatus::is_open))
+    {
+        PaymentStatus::Pending
+    } else if attempts.contains(&Some(PaymentAttemptStatus::Failed)) {
+        PaymentStatus::Failed
+    } else if attempts.contains(&Some(PaymentAttemptStatus::Captured)) {
+        PaymentStatus::Paid
+    } else {
+        PaymentStatus::Refunded
+    };
+    Some(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use PaymentAttemptStatus::*;
+
+    #[test]
+    fn group_status_follows_the_sub_order_attempts() {
+        assert_eq!(group_payment_status(&[]), None);
+        assert_eq!(
+            group_payment_status(&[Some(Captured), None]),
+            Some(PaymentStatus::Pending)
+        );
+        assert_eq!(
+            group_payment_status(&[Some(Captured), Some(Authorized)]),
+            Some(PaymentStatus::Pending)
+        );
+        assert_eq!(
+            group_payment_status(&[Some(Captured), Some(Failed)]),
+            Some(PaymentStatus::Failed)
+        );
+        assert_eq!(
+            group_payment_status(&[Some(Captured), Some(Refunded)]),
+            Some(PaymentStatus::Paid)
+        );
+        assert_eq!(
+            group_payment_status(&[Some(Refunded), Some(Refunded)]),
+            Some(PaymentStatus::Refunded)
+        );
+    }
+}
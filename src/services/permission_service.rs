@@ -1,9 +1,18 @@
+use std::collections::BTreeSet;
+
 use crate::{
+    cache::{keys, CacheHandle},
     error::AppError,
-    models::{permission::Permission, store::AccessLevel},
-    repositories::{AccessGrantRepository, MemberRepository, StoreRepository},
+    models::{
+        permission::{access_permissions, member_permissions, Permission, StorePermissions},
+        store::{MemberRole, StoreMember, StoreRole},
+    },
+    repositories::{
+        AccessGrantRepository, MemberRepository, StoreRepository, StoreRoleRepository,
+        UserRepository,
+    },
 };
-use serde_json::Value;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -12,6 +21,9 @@ pub struct PermissionService {
     stores: StoreRepository,
     members: MemberRepository,
     access_grants: AccessGrantRepository,
+    roles: StoreRoleRepository,
+    users: UserRepository,
+    cache: Option<CacheHandle>,
 }
 
 impl PermissionService {
@@ -19,83 +31,182 @@ impl PermissionService {
         Self {
             stores: StoreRepository::new(pool.clone()),
             members: MemberRepository::new(pool.clone()),
-            access_grants: AccessGrantRepository::new(pool),
+            access_grants: AccessGrantRepository::new(pool.clone()),
+            roles: StoreRoleRepository::new(pool.clone()),
+            users: UserRepository::new(pool),
+            cache: None,
         }
     }
 
-    pub async fn ensure_store_permission(
+    /// Serves `effective_permissions` from `cache`. The repositories that
+    /// change memberships, store roles and access grants must share it so
+    /// their writes drop the cached answers.
+    pub fn with_cache(mut self, cache: CacheHandle) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub async fn ensure_platform_admin(&self, user_id: Uuid) -> crate::Result<()> {
+        let is_admin = self
+            .users
+            .find_by_id(user_id)
+            .await?
+            .map(|user| user.is_platform_admin)
+            .unwrap_or(false);
+
+        if !is_admin {
+            return Err(AppError::Authorization(
+                "Platform administrator access required".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Everything the user may do in the store: what their membership
+    /// allows, viewing if the store is public, and what an active access
+    /// grant allows.
+    pub async fn effective_permissions(
         &self,
         user_id: Uuid,
         store_id: Uuid,
-        permission: Permission,
-    ) -> crate::Result<()> {
+    ) -> crate::Result<StorePermissions> {
+        let Some(cache) = &self.cache else {
+            return Ok(self.resolve_permissions(user_id, store_id).await?.0);
+        };
+
+        let generation = cache
+            .generation(&keys::store_permissions_scope(store_id))
+            .await;
+        let key = keys::store_permissions(&generation, store_id, user_id);
+        if let Some(resolved) = cache.get_json::<StorePermissions>(&key).await {
+            return Ok(resolved);
+        }
+
+        let (resolved, grant_expires_at) = self.resolve_permissions(user_id, store_id).await?;
+        // Permissions from a grant must not outlive it
+        let ttl = match grant_expires_at {
+            Some(expires_at) => (expires_at - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .min(cache.ttl()),
+            None => cache.ttl(),
+        };
+        cache.set_json_for(&key, &resolved, ttl).await;
+        Ok(resolved)
+    }
+
+    /// Resolves the user's permissions from the database, along with when
+    /// the access grant contributing to them expires.
+    async fn resolve_permissions(
+        &self,
+        user_id: Uuid,
+        store_id: Uuid,
+    ) -> crate::Result<(StorePermissions, Option<DateTime<Utc>>)> {
         let store = self
             .stores
             .find_by_id(store_id)
             .await?
             .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
 
-        if self
-            .members
-            .find_membership(store_id, user_id)
-            .await?
-            .map(|member| self.member_has_permission(&member.permissions, member.role, permission))
-            .unwrap_or(false)
-        {
-            return Ok(());
+        let mut resolved = StorePermissions {
+            store_id,
+            role: None,
+            custom_role: None,
+            access_level: None,
+            permissions: BTreeSet::new(),
+        };
+        if let Some(member) = self.members.find_membership(store_id, user_id).await? {
+            let (permissions, custom_role) = self.resolve_member(&member).await?;
+            resolved.role = Some(member.role);
+            resolved.custom_role = custom_role.map(|role| role.name);
+            resolved.permissions = permissions;
         }
-
-        if !store.is_private
-            && matches!(
-                permission,
-                Permission::ViewProducts | Permission::ViewOrders
-            )
-        {
-            return Ok(());
+        if !store.is_private {
+            resolved
+                .permissions
+                .extend([Permission::ViewProducts, Permission::ViewOrders]);
         }
+        let mut grant_expires_at = None;
+        if let Some(grant) = self.access_grants.find_active(store_id, user_id).await? {
+            resolved
+                .permissions
+                .extend(access_permissions(&grant.access_level));
+            resolved.access_level = Some(grant.access_level);
+            grant_expires_at = grant.expires_at;
+        }
+
+        Ok((resolved, grant_expires_at))
+    }
 
-        if self
-            .access_grants
-            .find_active(store_id, user_id)
+    /// Some records, like the permission audit log, are only for the store
+    /// owner's eyes.
+    pub async fn ensure_store_owner(&self, user_id: Uuid, store_id: Uuid) -> crate::Result<()> {
+        let store = self
+            .stores
+            .find_by_id(store_id)
             .await?
-            .map(|grant| access_allows(grant.access_level, permission))
-            .unwrap_or(false)
-        {
-            return Ok(());
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+        if store.owner_id != user_id {
+            return Err(AppError::Authorization(
+                "Only the store owner can do this".into(),
+            ));
         }
-
-        Err(AppError::Authorization("Insufficient permissions".into()))
+        Ok(())
     }
 
-    fn member_has_permission(
+    pub async fn ensure_store_permission(
         &self,
-        permissions: &Value,
-        role: crate::models::store::MemberRole,
+        user_id: Uuid,
+        store_id: Uuid,
         permission: Permission,
-    ) -> bool {
-        use crate::models::store::MemberRole;
-        if matches!(role, MemberRole::Owner | MemberRole::Admin) {
-            return true;
+    ) -> crate::Result<()> {
+        let resolved = self.effective_permissions(user_id, store_id).await?;
+        if !resolved.permissions.contains(&permission) {
+            return Err(AppError::Authorization("Insufficient permissions".into()));
         }
-        if let Some(list) = permissions.as_array() {
-            return list
-                .iter()
-                .filter_map(|v| v.as_str())
-                .any(|value| value.eq_ignore_ascii_case(permission.as_str()));
+
+        Ok(())
+    }
+
+    /// Like `ensure_store_permission`, but only store members qualify: public
+    /// visibility and customer access grants are not enough. Used for
+    /// back-office data such as buyer details.
+    pub async fn ensure_member_permission(
+        &self,
+        user_id: Uuid,
+        store_id: Uuid,
+        permission: Permission,
+    ) -> crate::Result<()> {
+        self.stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+
+        let allowed = match self.members.find_membership(store_id, user_id).await? {
+            Some(member) => self.resolve_member(&member).await?.0.contains(&permission),
+            None => false,
+        };
+
+        if !allowed {
+            return Err(AppError::Authorization("Insufficient permissions".into()));
         }
-        false
+
+        Ok(())
     }
-}
 
-fn access_allows(level: AccessLevel, permission: Permission) -> bool {
-    match level {
-        AccessLevel::View => matches!(
-            permission,
-            Permission::ViewProducts | Permission::ViewOrders
-        ),
-        AccessLevel::ViewAndBuy => matches!(
-            permission,
-            Permission::ViewProducts | Permission::ViewOrders | Permission::ProcessOrders
-        ),
+    /// Resolves the member's role, own permissions and, for Custom members,
+    /// the permissions of the store role they hold, which is returned too.
+    async fn resolve_member(
+        &self,
+        member: &StoreMember,
+    ) -> crate::Result<(BTreeSet<Permission>, Option<StoreRole>)> {
+        let role = match (member.role, member.custom_role_id) {
+            (MemberRole::Custom, Some(role_id)) => {
+                self.roles.find(member.store_id, role_id).await?
+            }
+            _ => None,
+        };
+        Ok((member_permissions(member, role.as_ref()), role))
     }
 }
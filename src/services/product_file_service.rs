@@ -0,0 +1,172 @@
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::{
+    error::AppError,
+    models::product::{ProductFile, ProductType},
+    repositories::{ProductFileRepository, ProductRepository},
+    storage::ObjectStorage,
+};
+use uuid::Uuid;
+
+pub const MAX_PRODUCT_FILE_BYTES: usize = 100 * 1024 * 1024;
+const MAX_FILE_NAME_CHARS: usize = 255;
+
+/// Manages the files buyers of digital products download. Files live in
+/// private storage and are only handed out through signed download links.
+#[derive(Clone)]
+pub struct ProductFileService {
+    products: ProductRepository,
+    files: ProductFileRepository,
+    storage: Arc<dyn ObjectStorage>,
+}
+
+impl ProductFileService {
+    pub fn new(
+        products: ProductRepository,
+        files: ProductFileRepository,
+        storage: Arc<dyn ObjectStorage>,
+    ) -> Self {
+        Self {
+            products,
+            files,
+            storage,
+        }
+    }
+
+    pub async fn upload(
+        &self,
+        product_id: Uuid,
+        file_name: &str,
+        content_type: &str,
+        data: Bytes,
+    ) -> crate::Result<ProductFile> {
+        let product = self
+            .products
+            .find_by_id(product_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+        if product.product_type != ProductType::Digital {
+            return Err(AppError::BadRequest(
+                "Files can only be attached to digital products".into(),
+            ));
+        }
+
+        if data.is_empty() {
+            return Err(AppError::Validation("File is empty".into()));
+        }
+        if data.len() > MAX_PRODUCT_FILE_BYTES {
+            return Err(AppError::Validation(format!(
+                "Files must be at most {} bytes",
+                MAX_PRODUCT_FILE_BYTES
+            )));
+        }
+
+        let file_name = sanitize_file_name(file_name);
+        let content_type = normalize_content_type(content_type);
+        let key = format!("products/{}/files/{}", product_id, Uuid::new_v4());
+        let size = data.len() as i64;
+        self.storage.put(&key, content_type, data).await?;
+
+        match self
+            .files
+            .create(product_id, &key, &file_name, content_type, size)
+            .await
+        {
+            Ok(file) => Ok(file),
+            Err(err) => {
+                if let Err(cleanup) = self.storage.delete(&key).await {
+                    tracing::warn!(key = %key, error = %cleanup, "failed to remove orphaned upload");
+                }
+                Err(err)
+            }
+        }
+    }
+
+    pub async fn list(&self, product_id: Uuid) -> crate::Result<Vec<ProductFile>> {
+        self.product_store_id(product_id).await?;
+        self.files.list_for_product(product_id).await
+    }
+
+    pub async fn delete(&self, product_id: Uuid, file_id: Uuid) -> crate::Result<()> {
+        let file = self
+            .files
+            .find_by_id(file_id)
+            .await?
+            .filter(|file| file.product_id == product_id)
+            .ok_or_else(|| AppError::NotFound("File not found".into()))?;
+
+        self.files.delete(file.id).await?;
+        if let Err(err) = self.storage.delete(&file.storage_key).await {
+            tracing::warn!(key = %file.storage_key, error = %err, "failed to remove product file");
+        }
+        Ok(())
+    }
+
+    /// Store owning the product, for permission checks in handlers.
+    pub async fn product_store_id(&self, product_id: Uuid) -> crate::Result<Uuid> {
+        self.products
+            .find_by_id(product_id)
+            .await?
+            .map(|product| product.store_id)
+            .ok_or_else(|| AppError::NotFound("Product not found".into()))
+    }
+}
+
+/// Keeps the declared media type if it is plausible, falling back to a
+/// generic binary type so it can always be served back as a header.
+fn normalize_content_type(content_type: &str) -> &str {
+    let plausible = content_type.len() <= 100
+        && content_type.contains('/')
+        && content_type
+            .bytes()
+            .all(|b| b.is_ascii_graphic() || b == b' ');
+    if plausible {
+        content_type
+    } else {
+        "application/octet-stream"
+    }
+}
+
+/// Reduces a client-supplied file name to something safe to echo back in a
+/// `Content-Disposition` header: no directories, quotes or control characters.
+fn sanitize_file_name(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(MAX_FILE_NAME_CHARS)
+        .collect();
+    let cleaned = cleaned.trim();
+    if cleaned.is_empty() || cleaned == "." || cleaned == ".." {
+        "download".to_string()
+    } else {
+        cleaned.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_are_stripped_of_paths_and_quotes() {
+        assert_eq!(sanitize_file_name("guide.pdf"), "guide.pdf");
+        assert_eq!(sanitize_file_name("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_file_name("C:\\books\\\"ch1\".epub"), "ch1.epub");
+        assert_eq!(sanitize_file_name("line\r\nbreak.zip"), "linebreak.zip");
+        assert_eq!(sanitize_file_name("  "), "download");
+        assert_eq!(sanitize_file_name("dir/"), "download");
+    }
+
+    #[test]
+    fn implausible_content_types_fall_back_to_binary() {
+        assert_eq!(normalize_content_type("application/pdf"), "application/pdf");
+        assert_eq!(normalize_content_type("pdf"), "application/octet-stream");
+        assert_eq!(
+            normalize_content_type("text/plain\r\nX-Evil: 1"),
+            "application/octet-stream"
+        );
+    }
+}
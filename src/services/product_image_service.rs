@@ -0,0 +1,257 @@
+use std::{collections::HashSet, sync::Arc};
+
+use bytes::Bytes;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::product::{ProductImage, ReorderProductImagesRequest},
+    repositories::{ProductImageRepository, ProductRepository},
+    storage::ObjectStorage,
+};
+use uuid::Uuid;
+
+pub const MAX_IMAGE_BYTES: usize = 5 * 1024 * 1024;
+pub const MAX_IMAGES_PER_PRODUCT: i64 = 20;
+
+#[derive(Clone)]
+pub struct ProductImageService {
+    products: ProductRepository,
+    images: ProductImageRepository,
+    storage: Arc<dyn ObjectStorage>,
+}
+
+impl ProductImageService {
+    pub fn new(
+        products: ProductRepository,
+        images: ProductImageRepository,
+        storage: Arc<dyn ObjectStorage>,
+    ) -> Self {
+        Self {
+            products,
+            images,
+            storage,
+        }
+    }
+
+    /// Stores an uploaded image and appends it to the product gallery. The
+    /// format is detected from the file contents; the client-declared content
+    /// type is not trusted.
+    pub async fn upload(&self, product_id: Uuid, data: Bytes) -> crate::Result<ProductImage> {
+        self.ensure_product_exists(product_id).await?;
+
+        if data.is_empty() {
+            return Err(AppError::Validation("Image file is empty".into()));
+        }
+        if data.len() > MAX_IMAGE_BYTES {
+            return Err(AppError::Validation(format!(
+                "Images must be at most {} bytes",
+                MAX_IMAGE_BYTES
+            )));
+        }
+        let format = ImageFormat::detect(&data)
+            .ok_or_else(|| AppError::Validation("Images must be JPEG, PNG, WebP or GIF".into()))?;
+
+        if self.images.count_for_product(product_id).await? >= MAX_IMAGES_PER_PRODUCT {
+            return Err(AppError::Conflict(format!(
+                "Products can have at most {} images",
+                MAX_IMAGES_PER_PRODUCT
+            )));
+        }
+
+        let key = format!(
+            "products/{}/{}.{}",
+            product_id,
+            Uuid::new_v4(),
+            format.extension()
+        );
+        let size = data.len() as i64;
+        self.storage.put(&key, format.content_type(), data).await?;
+
+        let url = self.storage.public_url(&key);
+        let image = match self
+            .images
+            .create(product_id, &key, &url, format.content_type(), size)
+            .await
+        {
+            Ok(image) => image,
+            Err(err) => {
+                if let Err(cleanup) = self.storage.delete(&key).await {
+                    tracing::warn!(key = %key, error = %cleanup, "failed to remove orphaned upload");
+                }
+                return Err(err);
+            }
+        };
+
+        if image.position == 0 {
+            self.images.sync_thumbnail(product_id).await?;
+        }
+
+        Ok(image)
+    }
+
+    /// Copies every gallery image of `source_id` onto `target_id`, giving
+    /// each copy its own stored file so either product's images can be
+    /// deleted independently.
+    pub async fn copy_gallery(
+        &self,
+        source_id: Uuid,
+        target_id: Uuid,
+    ) -> crate::Result<Vec<ProductImage>> {
+        let mut copies = Vec::new();
+        for image in self.images.list_for_product(source_id).await? {
+            let data = self.storage.get(&image.storage_key).await?;
+            let size = data.len() as i64;
+            let extension = image
+                .storage_key
+                .rsplit_once('.')
+                .map(|(_, ext)| ext)
+                .unwrap_or("bin");
+            let key = format!("products/{}/{}.{}", target_id, Uuid::new_v4(), extension);
+            self.storage.put(&key, &image.content_type, data).await?;
+
+            let url = self.storage.public_url(&key);
+            match self
+                .images
+                .create(target_id, &key, &url, &image.content_type, size)
+                .await
+            {
+                Ok(copy) => copies.push(copy),
+                Err(err) => {
+                    if let Err(cleanup) = self.storage.delete(&key).await {
+                        tracing::warn!(key = %key, error = %cleanup, "failed to remove orphaned copy");
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        if !copies.is_empty() {
+            self.images.sync_thumbnail(target_id).await?;
+        }
+        Ok(copies)
+    }
+
+    pub async fn list(&self, product_id: Uuid) -> crate::Result<Vec<ProductImage>> {
+        self.ensure_product_exists(product_id).await?;
+        self.images.list_for_product(product_id).await
+    }
+
+    pub async fn reorder(
+        &self,
+        product_id: Uuid,
+        payload: ReorderProductImagesRequest,
+    ) -> crate::Result<Vec<ProductImage>> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let current = self.list(product_id).await?;
+        let requested: HashSet<Uuid> = payload.image_ids.iter().copied().collect();
+        let existing: HashSet<Uuid> = current.iter().map(|image| image.id).collect();
+        if requested.len() != payload.image_ids.len() || requested != existing {
+            return Err(AppError::Validation(
+                "image_ids must list every image of the product exactly once".into(),
+            ));
+        }
+
+        let images = self.images.reorder(product_id, &payload.image_ids).await?;
+        self.images.sync_thumbnail(product_id).await?;
+        Ok(images)
+    }
+
+    pub async fn delete(&self, product_id: Uuid, image_id: Uuid) -> crate::Result<()> {
+        let image = self
+            .images
+            .find_by_id(image_id)
+            .await?
+            .filter(|image| image.product_id == product_id)
+            .ok_or_else(|| AppError::NotFound("Image not found".into()))?;
+
+        self.images.delete(image.id).await?;
+        self.images.sync_thumbnail(product_id).await?;
+
+        if let Err(err) = self.storage.delete(&image.storage_key).await {
+            tracing::warn!(key = %image.storage_key, error = %err, "failed to remove image file");
+        }
+        Ok(())
+    }
+
+    /// Store owning the product, for permission checks in handlers.
+    pub async fn product_store_id(&self, product_id: Uuid) -> crate::Result<Uuid> {
+        self.products
+            .find_by_id(product_id)
+            .await?
+            .map(|product| product.store_id)
+            .ok_or_else(|| AppError::NotFound("Product not found".into()))
+    }
+
+    async fn ensure_product_exists(&self, product_id: Uuid) -> crate::Result<()> {
+        self.product_store_id(product_id).await.map(|_| ())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+    Gif,
+}
+
+impl ImageFormat {
+    fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageFormat::Jpeg)
+        } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageFormat::Png)
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            Some(ImageFormat::Gif)
+        } else if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+            Some(ImageFormat::Webp)
+        } else {
+            None
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Gif => "image/gif",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Gif => "gif",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_formats_from_magic_bytes() {
+        assert_eq!(
+            ImageFormat::detect(b"\x89PNG\r\n\x1a\n...."),
+            Some(ImageFormat::Png)
+        );
+        assert_eq!(
+            ImageFormat::detect(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Some(ImageFormat::Jpeg)
+        );
+        assert_eq!(
+            ImageFormat::detect(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some(ImageFormat::Webp)
+        );
+        assert_eq!(ImageFormat::detect(b"GIF89a"), Some(ImageFormat::Gif));
+        assert_eq!(ImageFormat::detect(b"<svg></svg>"), None);
+    }
+}
@@ -1,22 +1,43 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use futures_util::Stream;
 use rust_decimal::Decimal;
 use validator::Validate;
 
 use crate::{
     error::AppError,
-    models::product::{CreateProductRequest, Product, UpdateProductRequest},
-    repositories::{ProductRepository, StoreRepository},
+    imports::{self, ImportReport, PriceImporter, ProductImporter},
+    models::product::{
+        CreateProductRequest, ExportFormat, Product, ProductExportRow, UpdateProductRequest,
+    },
+    models::store::Store,
+    repositories::{ProductImageRepository, ProductRepository, StoreRepository},
+    services::export::{paged_export, ExportRecord},
 };
 use uuid::Uuid;
 
+/// How many `-COPY-n` SKUs are tried before duplication gives up.
+const MAX_COPY_ATTEMPTS: u32 = 20;
+
 #[derive(Clone)]
 pub struct ProductService {
     products: ProductRepository,
     stores: StoreRepository,
+    images: ProductImageRepository,
 }
 
 impl ProductService {
-    pub fn new(products: ProductRepository, stores: StoreRepository) -> Self {
-        Self { products, stores }
+    pub fn new(
+        products: ProductRepository,
+        stores: StoreRepository,
+        images: ProductImageRepository,
+    ) -> Self {
+        Self {
+            products,
+            stores,
+            images,
+        }
     }
 
     pub async fn create_product(&self, payload: CreateProductRequest) -> crate::Result<Product> {
@@ -24,7 +45,8 @@ impl ProductService {
             .validate()
             .map_err(|err| AppError::Validation(err.to_string()))?;
 
-        self.ensure_store_exists(payload.store_id).await?;
+        let store = self.get_store(payload.store_id).await?;
+        self.ensure_product_quota(&store, 1).await?;
         let price = decimal_from_f64(payload.price)?;
 
         self.products
@@ -36,10 +58,78 @@ impl ProductService {
                 price,
                 payload.stock_quantity,
                 payload.category.as_deref(),
+                payload.thumbnail_url.as_deref(),
+                payload.product_type,
+                payload.weight_grams,
             )
             .await
     }
 
+    /// Clones a product under the first free `-COPY` SKU. Gallery images are
+    /// copied separately by `ProductImageService::copy_gallery`.
+    pub async fn duplicate_product(&self, product_id: Uuid) -> crate::Result<Product> {
+        let source = self.find_product(product_id).await?;
+        let store = self.get_store(source.store_id).await?;
+        self.ensure_product_quota(&store, 1).await?;
+
+        for attempt in 1..=MAX_COPY_ATTEMPTS {
+            let sku = source.copy_sku(attempt);
+            if self
+                .products
+                .find_by_sku(source.store_id, &sku)
+                .await?
+                .is_none()
+            {
+                return self.products.duplicate(source.id, &sku).await;
+            }
+        }
+
+        Err(AppError::Conflict(
+            "Too many copies of this product already exist".into(),
+        ))
+    }
+
+    /// Creates or updates products from a CSV by SKU. See `imports` for
+    /// how rows are validated and reported.
+    pub async fn import_csv(
+        &self,
+        store_id: Uuid,
+        data: &[u8],
+        dry_run: bool,
+    ) -> crate::Result<ImportReport> {
+        let store = self.get_store(store_id).await?;
+        let importer = ProductImporter::new(self.products.clone(), store);
+        imports::run(&importer, data, dry_run).await
+    }
+
+    /// Updates prices of existing products from a `sku,price` CSV.
+    pub async fn import_prices(
+        &self,
+        store_id: Uuid,
+        data: &[u8],
+        dry_run: bool,
+    ) -> crate::Result<ImportReport> {
+        self.ensure_store_exists(store_id).await?;
+        let importer = PriceImporter::new(self.products.clone(), store_id);
+        imports::run(&importer, data, dry_run).await
+    }
+
+    /// Streams a store's entire catalog page by page, so exports are not
+    /// bound by listing pagination caps or held in memory at once.
+    pub async fn export_catalog(
+        &self,
+        store_id: Uuid,
+        format: ExportFormat,
+    ) -> crate::Result<impl Stream<Item = crate::Result<Bytes>> + Send + 'static> {
+        self.ensure_store_exists(store_id).await?;
+
+        let products = self.products.clone();
+        Ok(paged_export(format, move |after, limit| {
+            let products = products.clone();
+            async move { products.list_page_after(store_id, after, limit).await }
+        }))
+    }
+
     pub async fn list_by_store(
         &self,
         store_id: Uuid,
@@ -47,61 +137,143 @@ impl ProductService {
         offset: i64,
     ) -> crate::Result<Vec<Product>> {
         self.ensure_store_exists(store_id).await?;
-        self.products.list_by_store(store_id, limit, offset).await
+        let mut products = self.products.list_by_store(store_id, limit, offset).await?;
+
+        let ids: Vec<Uuid> = products.iter().map(|product| product.id).collect();
+        let mut galleries: HashMap<Uuid, Vec<_>> = HashMap::new();
+        for image in self.images.list_for_products(&ids).await? {
+            galleries.entry(image.product_id).or_default().push(image);
+        }
+        for product in &mut products {
+            product.images = galleries.remove(&product.id).unwrap_or_default();
+        }
+
+        Ok(products)
     }
 
+    /// Applies the fields present in `payload`; `actor_id` is recorded with
+    /// any stock change.
     pub async fn update_product(
         &self,
         product_id: Uuid,
+        actor_id: Uuid,
         payload: UpdateProductRequest,
     ) -> crate::Result<Product> {
         payload
             .validate()
             .map_err(|err| AppError::Validation(err.to_string()))?;
+        let price = payload.price.map(decimal_from_f64).transpose()?;
 
+        let mut updated = self
+            .products
+            .update(product_id, &payload, price, actor_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+        updated.images = self.images.list_for_product(product_id).await?;
+
+        Ok(updated)
+    }
+
+    pub async fn get_product(&self, product_id: Uuid) -> crate::Result<Product> {
         let mut product = self
             .products
             .find_by_id(product_id)
             .await?
             .ok_or_else(|| AppError::NotFound("Product not found".into()))?;
+        product.images = self.images.list_for_product(product_id).await?;
+        Ok(product)
+    }
 
-        if let Some(name) = payload.name {
-            product.name = name;
-        }
-        if let Some(desc) = payload.description {
-            product.description = Some(desc);
-        }
-        if let Some(price) = payload.price {
-            product.price = decimal_from_f64(price)?;
-        }
-        if let Some(stock) = payload.stock_quantity {
-            product.stock_quantity = stock;
-        }
-        if let Some(category) = payload.category {
-            product.category = Some(category);
+    async fn ensure_store_exists(&self, store_id: Uuid) -> crate::Result<()> {
+        if self.stores.find_by_id(store_id).await?.is_none() {
+            return Err(AppError::NotFound("Store not found".into()));
         }
-        if let Some(is_active) = payload.is_active {
-            product.is_active = is_active;
+        Ok(())
+    }
+
+    async fn get_store(&self, store_id: Uuid) -> crate::Result<Store> {
+        self.stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))
+    }
+
+    pub async fn archive_product(&self, product_id: Uuid) -> crate::Result<()> {
+        let product = self.find_product(product_id).await?;
+        if product.is_archived() {
+            return Err(AppError::Conflict("Product is already archived".into()));
         }
+        self.products.archive(product_id).await?;
+        Ok(())
+    }
 
-        // Persist changes
-        let updated = self.products.save(&product).await?;
+    /// Restores an archived product. It counts towards the plan's product
+    /// quota again, so the quota is checked first.
+    pub async fn unarchive_product(&self, product_id: Uuid) -> crate::Result<Product> {
+        let product = self.find_product(product_id).await?;
+        if !product.is_archived() {
+            return Err(AppError::Conflict("Product is not archived".into()));
+        }
+        let store = self.get_store(product.store_id).await?;
+        self.ensure_product_quota(&store, 1).await?;
 
-        Ok(updated)
+        let mut restored = self.products.unarchive(product_id).await?;
+        restored.images = self.images.list_for_product(product_id).await?;
+        Ok(restored)
     }
 
-    pub async fn get_product(&self, product_id: Uuid) -> crate::Result<Product> {
+    async fn find_product(&self, product_id: Uuid) -> crate::Result<Product> {
         self.products
             .find_by_id(product_id)
             .await?
             .ok_or_else(|| AppError::NotFound("Product not found".into()))
     }
 
-    async fn ensure_store_exists(&self, store_id: Uuid) -> crate::Result<()> {
-        if self.stores.find_by_id(store_id).await?.is_none() {
-            return Err(AppError::NotFound("Store not found".into()));
-        }
-        Ok(())
+    async fn ensure_product_quota(&self, store: &Store, additional: i64) -> crate::Result<()> {
+        ensure_product_quota(&self.products, store, additional).await
+    }
+}
+
+/// Rejects adding `additional` products when the store's plan would be
+/// exceeded.
+pub(crate) async fn ensure_product_quota(
+    products: &ProductRepository,
+    store: &Store,
+    additional: i64,
+) -> crate::Result<()> {
+    let limit = store.plan.limits().max_products;
+    let current = products.count_for_store(store.id).await?;
+    if current + additional > limit {
+        return Err(AppError::QuotaExceeded(format!(
+            "The {:?} plan allows at most {} products; the store has {}",
+            store.plan, limit, current
+        )));
+    }
+    Ok(())
+}
+
+impl ExportRecord for Product {
+    type Key = Uuid;
+    type Row<'a> = ProductExportRow<'a>;
+
+    const CSV_HEADER: &'static [&'static str] = &[
+        "sku",
+        "name",
+        "description",
+        "price",
+        "stock_quantity",
+        "category",
+        "thumbnail_url",
+        "is_active",
+        "id",
+    ];
+
+    fn export_key(&self) -> Uuid {
+        self.id
+    }
+
+    fn export_row(&self) -> ProductExportRow<'_> {
+        ProductExportRow::from(self)
     }
 }
 
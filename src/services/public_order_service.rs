@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use crate::{
+    error::AppError,
+    models::{
+        order::{
+            CheckoutConfirmation, CheckoutSummary, PublicOrderConfirmation, PublicOrderSummary,
+        },
+        payment::Payment,
+    },
+    repositories::OrderRepository,
+    utils::public_id::{PublicIdKind, PublicIds},
+};
+
+/// Order confirmations reachable without signing in, addressed by signed
+/// public references instead of UUIDs.
+#[derive(Clone)]
+pub struct PublicOrderService {
+    orders: OrderRepository,
+    ids: Arc<PublicIds>,
+}
+
+impl PublicOrderService {
+    pub fn new(orders: OrderRepository, ids: Arc<PublicIds>) -> Self {
+        Self { orders, ids }
+    }
+
+    pub fn confirm_checkout(
+        &self,
+        summary: CheckoutSummary,
+        payments: Vec<Payment>,
+    ) -> CheckoutConfirmation {
+        let confirmation_reference = self
+            .ids
+            .encode(PublicIdKind::OrderGroup, summary.order_group.id);
+        CheckoutConfirmation {
+            summary,
+            confirmation_reference,
+            payments,
+        }
+    }
+
+    /// A checkout reference shows every order placed in it; an order
+    /// reference shows only that order.
+    pub async fn confirmation(&self, reference: &str) -> crate::Result<PublicOrderConfirmation> {
+        let not_found = || AppError::NotFound("Order not found".into());
+        let (kind, id) = self.ids.decode(reference).ok_or_else(not_found)?;
+
+        let (group_id, order_id) = match kind {
+            PublicIdKind::OrderGroup => (id, None),
+            PublicIdKind::Order => {
+                let order = self.orders.find_by_id(id).await?.ok_or_else(not_found)?;
+                (order.order_group_id, Some(order.id))
+            }
+            PublicIdKind::GuestCart => return Err(not_found()),
+        };
+        let group = self
+            .orders
+            .find_group(group_id)
+            .await?
+            .ok_or_else(not_found)?;
+
+        let orders = self
+            .orders
+            .list_entries_for_group(group.id)
+            .await?
+            .into_iter()
+            .filter(|entry| order_id.is_none_or(|id| id == entry.order_id))
+            .map(|entry| PublicOrderSummary {
+                reference: self.ids.encode(PublicIdKind::Order, entry.order_id),
+                order_number: entry.order_number,
+                store_name: entry.store_name,
+                status: entry.status,
+                item_count: entry.item_count,
+                total_amount: entry.total_amount,
+            })
+            .collect();
+
+        Ok(PublicOrderConfirmation {
+            reference: self.ids.encode(PublicIdKind::OrderGroup, group.id),
+            group_number: group.group_number,
+            payment_status: group.payment_status,
+            total_amount: group.total_amount,
+            created_at: group.created_at,
+            orders,
+        })
+    }
+}
@@ -0,0 +1,204 @@
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use serde::Serialize;
+use sqlx::PgPool;
+use tokio::time::Instant;
+
+use crate::cache::SharedCache;
+
+/// How long each dependency gets to answer unless configured otherwise.
+pub const DEFAULT_READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A backing service the app cannot serve traffic without.
+#[async_trait]
+pub trait DependencyCheck: Send + Sync {
+    /// Key the dependency is reported under.
+    fn name(&self) -> &'static str;
+
+    async fn check(&self) -> anyhow::Result<()>;
+}
+
+/// Pings the database with a trivial query, which also proves a pooled
+/// connection can be checked out.
+pub struct DatabaseCheck {
+    pool: PgPool,
+}
+
+impl DatabaseCheck {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl DependencyCheck for DatabaseCheck {
+    fn name(&self) -> &'static str {
+        "database"
+    }
+
+    async fn check(&self) -> anyhow::Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+}
+
+/// Pings the cache backend. The in-memory one is always up; Redis has to
+/// answer.
+pub struct CacheCheck {
+    cache: SharedCache,
+}
+
+impl CacheCheck {
+    pub fn new(cache: SharedCache) -> Self {
+        Self { cache }
+    }
+}
+
+#[async_trait]
+impl DependencyCheck for CacheCheck {
+    fn name(&self) -> &'static str {
+        "cache"
+    }
+
+    async fn check(&self) -> anyhow::Result<()> {
+        self.cache.ping().await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyState {
+    Ok,
+    Unavailable,
+    TimedOut,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyStatus {
+    pub name: &'static str,
+    pub status: DependencyState,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// Runs every dependency check concurrently, each under the same timeout, so
+/// a hung dependency makes the app report not ready instead of hanging the
+/// probe.
+#[derive(Clone)]
+pub struct ReadinessService {
+    checks: Vec<Arc<dyn DependencyCheck>>,
+    timeout: Duration,
+}
+
+impl ReadinessService {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout,
+        }
+    }
+
+    pub fn with_check(mut self, check: Arc<dyn DependencyCheck>) -> Self {
+        self.checks.push(check);
+        self
+    }
+
+    pub async fn check(&self) -> Readiness {
+        let dependencies = join_all(self.checks.iter().map(|check| self.run(check.as_ref()))).await;
+        Readiness {
+            ready: dependencies
+                .iter()
+                .all(|dependency| dependency.status == DependencyState::Ok),
+            dependencies,
+        }
+    }
+
+    async fn run(&self, check: &dyn DependencyCheck) -> DependencyStatus {
+        let started = Instant::now();
+        let (status, error) = match tokio::time::timeout(self.timeout, check.check()).await {
+            Ok(Ok(())) => (DependencyState::Ok, None),
+            Ok(Err(err)) => {
+                tracing::warn!(dependency = check.name(), error = %err, "Readiness check failed");
+                (DependencyState::Unavailable, Some(err.to_string()))
+            }
+            Err(_) => {
+                tracing::warn!(dependency = check.name(), "Readiness check timed out");
+                (
+                    DependencyState::TimedOut,
+                    Some(format!("no answer within {}ms", self.timeout.as_millis())),
+                )
+            }
+        };
+        DependencyStatus {
+            name: check.name(),
+            status,
+            latency_ms: started.elapsed().as_millis() as u64,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Fixed(&'static str, Option<Duration>);
+
+    #[async_trait]
+    impl DependencyCheck for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn check(&self) -> anyhow::Result<()> {
+            match self.1 {
+                Some(delay) => {
+                    tokio::time::sleep(delay).await;
+                    Ok(())
+                }
+                None => anyhow::bail!("connection refused"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn not_ready_when_any_dependency_fails_or_hangs() {
+        let service = ReadinessService::new(Duration::from_millis(50))
+            .with_check(Arc::new(Fixed("database", Some(Duration::ZERO))));
+        assert!(service.check().await.ready);
+
+        let readiness = service
+            .clone()
+            .with_check(Arc::new(Fixed("redis", None)))
+            .with_check(Arc::new(Fixed("s3", Some(Duration::from_secs(30)))))
+            .check()
+            .await;
+        assert!(!readiness.ready);
+        let states: Vec<_> = readiness
+            .dependencies
+            .iter()
+            .map(|dependency| (dependency.name, dependency.status))
+            .collect();
+        assert_eq!(
+            states,
+            [
+                ("database", DependencyState::Ok),
+                ("redis", DependencyState::Unavailable),
+                ("s3", DependencyState::TimedOut),
+            ]
+        );
+        assert_eq!(
+            readiness.dependencies[1].error.as_deref(),
+            Some("connection refused")
+        );
+    }
+}
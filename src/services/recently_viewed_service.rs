@@ -0,0 +1,28 @@
+use crate::{models::product::RecentlyViewedProduct, repositories::RecentlyViewedRepository};
+use uuid::Uuid;
+
+/// Number of products kept in each user's history.
+pub const RECENTLY_VIEWED_LIMIT: i64 = 20;
+
+#[derive(Clone)]
+pub struct RecentlyViewedService {
+    recent: RecentlyViewedRepository,
+}
+
+impl RecentlyViewedService {
+    pub fn new(recent: RecentlyViewedRepository) -> Self {
+        Self { recent }
+    }
+
+    pub async fn record_view(&self, user_id: Uuid, product_id: Uuid) -> crate::Result<()> {
+        self.recent
+            .record(user_id, product_id, RECENTLY_VIEWED_LIMIT)
+            .await
+    }
+
+    pub async fn list(&self, user_id: Uuid) -> crate::Result<Vec<RecentlyViewedProduct>> {
+        self.recent
+            .list_for_user(user_id, RECENTLY_VIEWED_LIMIT)
+            .await
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{models::product::RelatedProduct, repositories::RecommendationRepository};
+use uuid::Uuid;
+
+/// Number of related products returned for a product page.
+pub const RELATED_PRODUCTS_LIMIT: i64 = 8;
+
+/// Co-purchase pairs kept per product on each refresh.
+const PAIRS_PER_PRODUCT: i64 = 20;
+
+#[derive(Clone)]
+pub struct RecommendationService {
+    recommendations: RecommendationRepository,
+}
+
+impl RecommendationService {
+    pub fn new(recommendations: RecommendationRepository) -> Self {
+        Self { recommendations }
+    }
+
+    /// "Frequently bought together" products, topped up with products from
+    /// the same category when order history is too thin.
+    pub async fn related(
+        &self,
+        product_id: Uuid,
+        viewer_id: Option<Uuid>,
+    ) -> crate::Result<Vec<RelatedProduct>> {
+        let mut related = self
+            .recommendations
+            .bought_together(product_id, viewer_id, RELATED_PRODUCTS_LIMIT)
+            .await?;
+
+        let missing = RELATED_PRODUCTS_LIMIT - related.len() as i64;
+        if missing > 0 {
+            let seen: Vec<Uuid> = related.iter().map(|item| item.product.id).collect();
+            let fallback = self
+                .recommendations
+                .same_category(product_id, viewer_id, &seen, missing)
+                .await?;
+            related.extend(fallback);
+        }
+
+        Ok(related)
+    }
+
+    /// Recomputes co-purchase pairs from order history.
+    pub async fn refresh(&self) -> crate::Result<u64> {
+        self.recommendations.refresh(PAIRS_PER_PRODUCT).await
+    }
+}
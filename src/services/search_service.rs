@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{models::product::SearchSuggestions, repositories::ProductRepository};
+use uuid::Uuid;
+
+pub const MIN_QUERY_CHARS: usize = 2;
+pub const MAX_QUERY_CHARS: usize = 100;
+const PRODUCT_SUGGESTIONS: i64 = 8;
+const CATEGORY_SUGGESTIONS: i64 = 4;
+
+/// Short-lived cache of anonymous suggestion results. Anonymous viewers all
+/// see the same public catalog, so popular prefixes are served from memory.
+pub struct SuggestionCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, SearchSuggestions)>>,
+}
+
+impl SuggestionCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn get(&self, key: &str) -> Option<SearchSuggestions> {
+        let entries = self.entries.lock().expect("suggestion cache poisoned");
+        entries
+            .get(key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, suggestions)| suggestions.clone())
+    }
+
+    fn insert(&self, key: String, suggestions: SearchSuggestions) {
+        let mut entries = self.entries.lock().expect("suggestion cache poisoned");
+        if entries.len() >= self.capacity {
+            entries.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+            if entries.len() >= self.capacity {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (Instant::now(), suggestions));
+    }
+}
+
+impl Default for SuggestionCache {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60), 10_000)
+    }
+}
+
+#[derive(Clone)]
+pub struct SearchService {
+    products: ProductRepository,
+    cache: Arc<SuggestionCache>,
+}
+
+impl SearchService {
+    pub fn new(products: ProductRepository, cache: Arc<SuggestionCache>) -> Self {
+        Self { products, cache }
+    }
+
+    /// Product and category suggestions for a search box. Queries shorter
+    /// than `MIN_QUERY_CHARS` return nothing rather than the whole catalog.
+    pub async fn suggest(
+        &self,
+        query: &str,
+        viewer_id: Option<Uuid>,
+    ) -> crate::Result<SearchSuggestions> {
+        let Some(prefix) = normalize_query(query) else {
+            return Ok(SearchSuggestions::default());
+        };
+
+        if viewer_id.is_none() {
+            if let Some(cached) = self.cache.get(&prefix) {
+                return Ok(cached);
+            }
+        }
+
+        let (products, categories) = tokio::try_join!(
+            self.products
+                .suggest(&prefix, viewer_id, PRODUCT_SUGGESTIONS),
+            self.products
+                .suggest_categories(&prefix, viewer_id, CATEGORY_SUGGESTIONS),
+        )?;
+        let suggestions = SearchSuggestions {
+            products,
+            categories,
+        };
+
+        if viewer_id.is_none() {
+            self.cache.insert(prefix, suggestions.clone());
+        }
+        Ok(suggestions)
+    }
+}
+
+/// Lowercases and trims the query, collapses inner whitespace and escapes
+/// LIKE wildcards. Returns `None` when too short to be worth searching.
+fn normalize_query(query: &str) -> Option<String> {
+    let collapsed = query.split_whitespace().collect::<Vec<_>>().join(" ");
+    let count = collapsed.chars().count();
+    if !(MIN_QUERY_CHARS..=MAX_QUERY_CHARS).contains(&count) {
+        return None;
+    }
+
+    let mut escaped = String::with_capacity(collapsed.len());
+    for c in collapsed.to_lowercase().chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    Some(escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_are_normalized_and_escaped() {
+        assert_eq!(
+            normalize_query("  Walnut   DESK "),
+            Some("walnut desk".into())
+        );
+        assert_eq!(normalize_query("50%_off"), Some("50\\%\\_off".into()));
+        assert_eq!(normalize_query("a"), None);
+        assert_eq!(normalize_query(&"x".repeat(101)), None);
+    }
+
+    #[test]
+    fn cache_expires_entries() {
+        let cache = SuggestionCache::new(Duration::from_millis(0), 10);
+        cache.insert("desk".into(), SearchSuggestions::default());
+        assert!(cache.get("desk").is_none());
+
+        let cache = SuggestionCache::default();
+        cache.insert("desk".into(), SearchSuggestions::default());
+        assert_eq!(cache.get("desk"), Some(SearchSuggestions::default()));
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{
+    error::AppError,
+    models::{
+        product::ProductShareCard,
+        store::{StoreSettings, StoreStatus},
+    },
+    repositories::{ProductRepository, StoreRepository, StoreSettingsRepository},
+};
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct ShareCardService {
+    products: ProductRepository,
+    stores: StoreRepository,
+    settings: StoreSettingsRepository,
+}
+
+impl ShareCardService {
+    pub fn new(
+        products: ProductRepository,
+        stores: StoreRepository,
+        settings: StoreSettingsRepository,
+    ) -> Self {
+        Self {
+            products,
+            stores,
+            settings,
+        }
+    }
+
+    /// Share cards are public, so anything a guest could not browse to
+    /// (inactive products, private or unavailable stores) is reported as
+    /// missing rather than forbidden.
+    pub async fn product_card(&self, product_id: Uuid) -> crate::Result<ProductShareCard> {
+        let not_found = || AppError::NotFound("Product not found".into());
+
+        let product = self
+            .products
+            .find_by_id(product_id)
+            .await?
+            .filter(|product| product.is_active && !product.is_archived())
+            .ok_or_else(not_found)?;
+
+        let store = self
+            .stores
+            .find_by_id(product.store_id)
+            .await?
+            .filter(|store| !store.is_private && store.status == StoreStatus::Active)
+            .ok_or_else(not_found)?;
+
+        let settings = self
+            .settings
+            .find(store.id)
+            .await?
+            .unwrap_or_else(|| StoreSettings::defaults(store.id));
+
+        Ok(ProductShareCard::new(
+            &product,
+            &store.name,
+            &settings.currency_code,
+        ))
+    }
+}
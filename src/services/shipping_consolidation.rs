@@ -0,0 +1,220 @@
+use std::{collections::BTreeMap, collections::HashMap, str::FromStr, sync::Arc};
+
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use crate::models::fulfillment::FulfillmentPartner;
+
+/// Per-store shipping figures produced by the checkout calculator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShipmentQuote {
+    pub store_id: Uuid,
+    /// The store's currency; only quotes in the same currency combine.
+    pub currency: String,
+    pub subtotal: Decimal,
+    pub shipping_cost: Decimal,
+}
+
+/// Strategy deciding how shipping costs combine across sub-orders of one checkout.
+pub trait ShippingConsolidation: Send + Sync {
+    fn consolidate(
+        &self,
+        quotes: &mut [ShipmentQuote],
+        partners: &HashMap<Uuid, FulfillmentPartner>,
+    );
+}
+
+/// Every store ships on its own; quotes are left untouched.
+pub struct NoConsolidation;
+
+impl ShippingConsolidation for NoConsolidation {
+    fn consolidate(&self, _: &mut [ShipmentQuote], _: &HashMap<Uuid, FulfillmentPartner>) {}
+}
+
+/// Sub-orders from stores sharing a fulfillment partner and a currency ship
+/// together: the partner's rate table prices the combined subtotal once, and
+/// the fee is split across the sub-orders proportionally to their subtotals.
+/// Groups whose fee would not undercut their separate quotes are left alone.
+pub struct PartnerRateConsolidation;
+
+impl ShippingConsolidation for PartnerRateConsolidation {
+    fn consolidate(
+        &self,
+        quotes: &mut [ShipmentQuote],
+        partners: &HashMap<Uuid, FulfillmentPartner>,
+    ) {
+        let mut groups: BTreeMap<(Uuid, String), Vec<usize>> = BTreeMap::new();
+        for (index, quote) in quotes.iter().enumerate() {
+            if let Some(partner) = partners.get(&quote.store_id) {
+                groups
+                    .entry((partner.id, quote.currency.clone()))
+                    .or_default()
+                    .push(index);
+            }
+        }
+
+        for ((partner_id, _), mut indices) in groups {
+            if indices.len() < 2 {
+                continue;
+            }
+            let Some(partner) = partners.values().find(|p| p.id == partner_id) else {
+                continue;
+            };
+
+            let combined = indices
+                .iter()
+                .fold(Decimal::ZERO, |acc, &i| acc + quotes[i].subtotal);
+            let separate = indices
+                .iter()
+                .fold(Decimal::ZERO, |acc, &i| acc + quotes[i].shipping_cost);
+            let Some(fee) = partner.fee_for(combined).filter(|fee| *fee < separate) else {
+                continue;
+            };
+
+            indices.sort_by_key(|&i| quotes[i].store_id);
+            allocate(quotes, &indices, fee, combined);
+        }
+    }
+}
+
+fn allocate(quotes: &mut [ShipmentQuote], indices: &[usize], fee: Decimal, combined: Decimal) {
+    let mut remaining = fee;
+    for (position, &index) in indices.iter().enumerate() {
+        let share = if position + 1 == indices.len() || combined.is_zero() {
+            remaining
+        } else {
+            (fee * quotes[index].subtotal / combined).round_dp(2)
+        };
+        quotes[index].shipping_cost = share;
+        remaining -= share;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConsolidationMode {
+    None,
+    #[default]
+    PartnerRates,
+}
+
+impl ConsolidationMode {
+    pub fn strategy(&self) -> Arc<dyn ShippingConsolidation> {
+        match self {
+            ConsolidationMode::None => Arc::new(NoConsolidation),
+            ConsolidationMode::PartnerRates => Arc::new(PartnerRateConsolidation),
+        }
+    }
+}
+
+impl FromStr for ConsolidationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "none" => Ok(ConsolidationMode::None),
+            "partner_rates" => Ok(ConsolidationMode::PartnerRates),
+            other => Err(format!("unknown shipping consolidation mode '{}'", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fulfillment::RateTier;
+    use chrono::Utc;
+    use sqlx::types::Json;
+
+    fn partner(fee: i64) -> FulfillmentPartner {
+        FulfillmentPartner {
+            id: Uuid::new_v4(),
+            name: "Shared Logistics".into(),
+            rate_table: Json(vec![RateTier {
+                min_subtotal: Decimal::ZERO,
+                fee: Decimal::new(fee, 2),
+            }]),
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn quote(subtotal: i64) -> ShipmentQuote {
+        ShipmentQuote {
+            store_id: Uuid::new_v4(),
+            currency: "USD".into(),
+            subtotal: Decimal::new(subtotal, 0),
+            shipping_cost: Decimal::new(500, 2),
+        }
+    }
+
+    #[test]
+    fn shared_partner_fee_is_split_by_subtotal() {
+        let shared = partner(900);
+        let mut quotes = vec![quote(20), quote(40), quote(10)];
+        let partners = HashMap::from([
+            (quotes[0].store_id, shared.clone()),
+            (quotes[1].store_id, shared),
+        ]);
+
+        PartnerRateConsolidation.consolidate(&mut quotes, &partners);
+
+        let combined: Decimal = quotes[..2].iter().map(|q| q.shipping_cost).sum();
+        assert_eq!(combined, Decimal::new(900, 2));
+        let first = quotes.iter().find(|q| q.subtotal == Decimal::new(20, 0));
+        assert_eq!(first.unwrap().shipping_cost, Decimal::new(300, 2));
+        assert_eq!(quotes[2].shipping_cost, Decimal::new(500, 2));
+    }
+
+    #[test]
+    fn single_store_partners_are_not_consolidated() {
+        let mut quotes = vec![quote(20), quote(40)];
+        let partners = HashMap::from([
+            (quotes[0].store_id, partner(900)),
+            (quotes[1].store_id, partner(900)),
+        ]);
+
+        PartnerRateConsolidation.consolidate(&mut quotes, &partners);
+        assert!(quotes
+            .iter()
+            .all(|q| q.shipping_cost == Decimal::new(500, 2)));
+    }
+
+    #[test]
+    fn partner_fees_above_the_separate_quotes_are_not_applied() {
+        let shared = partner(1200);
+        let mut quotes = vec![quote(20), quote(40)];
+        let partners = HashMap::from([
+            (quotes[0].store_id, shared.clone()),
+            (quotes[1].store_id, shared),
+        ]);
+
+        PartnerRateConsolidation.consolidate(&mut quotes, &partners);
+        assert!(quotes
+            .iter()
+            .all(|q| q.shipping_cost == Decimal::new(500, 2)));
+    }
+
+    #[test]
+    fn stores_in_different_currencies_are_not_consolidated() {
+        let shared = partner(900);
+        let mut quotes = vec![quote(20), quote(40)];
+        quotes[1].currency = "EUR".into();
+        let partners = HashMap::from([
+            (quotes[0].store_id, shared.clone()),
+            (quotes[1].store_id, shared),
+        ]);
+
+        PartnerRateConsolidation.consolidate(&mut quotes, &partners);
+        assert!(quotes
+            .iter()
+            .all(|q| q.shipping_cost == Decimal::new(500, 2)));
+    }
+
+    #[test]
+    fn mode_parses_from_config_values() {
+        assert_eq!("none".parse(), Ok(ConsolidationMode::None));
+        assert_eq!("PARTNER_RATES".parse(), Ok(ConsolidationMode::PartnerRates));
+        assert!("bogus".parse::<ConsolidationMode>().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use rust_decimal::Decimal;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::shipping::{CreateShippingRuleRequest, ShippingRule, ShippingRuleKind},
+    repositories::{StoreRepository, StoreSettingsRepository},
+};
+
+pub const MAX_SHIPPING_RULES: usize = 10;
+
+/// Manages the shipping methods stores offer. Checkout prices them through
+/// `ShippingRates`.
+#[derive(Clone)]
+pub struct ShippingService {
+    settings: StoreSettingsRepository,
+    stores: StoreRepository,
+}
+
+impl ShippingService {
+    pub fn new(settings: StoreSettingsRepository, stores: StoreRepository) -> Self {
+        Self { settings, stores }
+    }
+
+    pub async fn list_rules(&self, store_id: Uuid) -> crate::Result<Vec<ShippingRule>> {
+        self.ensure_store_exists(store_id).await?;
+        self.settings.list_shipping_rules(store_id).await
+    }
+
+    pub async fn add_rule(
+        &self,
+        store_id: Uuid,
+        payload: CreateShippingRuleRequest,
+    ) -> crate::Result<ShippingRule> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let tiered = matches!(
+            payload.kind,
+            ShippingRuleKind::WeightTiers | ShippingRuleKind::PriceTiers
+        );
+        if tiered && !payload.tiers.iter().any(|tier| tier.min.is_zero()) {
+            return Err(AppError::Validation(
+                "Tiered rules need a tier starting at 0".into(),
+            ));
+        }
+        if !tiered && !payload.tiers.is_empty() {
+            return Err(AppError::Validation(
+                "tiers only apply to weight and price tier rules".into(),
+            ));
+        }
+        let fee = match (payload.kind, payload.fee) {
+            (ShippingRuleKind::Flat, Some(fee)) => decimal_from_f64(fee)?.round_dp(2),
+            (ShippingRuleKind::Flat, None) => {
+                return Err(AppError::Validation("Flat rules need a fee".into()))
+            }
+            (_, Some(_)) => {
+                return Err(AppError::Validation(
+                    "fee only applies to flat rules".into(),
+                ))
+            }
+            (_, None) => Decimal::ZERO,
+        };
+        let threshold = payload
+            .free_shipping_threshold
+            .map(decimal_from_f64)
+            .transpose()?
+            .map(|threshold| threshold.round_dp(2));
+
+        let existing = self.list_rules(store_id).await?;
+        if existing.iter().any(|rule| rule.name == payload.name) {
+            return Err(AppError::Conflict(format!(
+                "Shipping rule '{}' already exists",
+                payload.name
+            )));
+        }
+        if existing.len() >= MAX_SHIPPING_RULES {
+            return Err(AppError::Conflict(format!(
+                "Stores can define at most {} shipping rules",
+                MAX_SHIPPING_RULES
+            )));
+        }
+
+        self.settings
+            .create_shipping_rule(
+                store_id,
+                &payload.name,
+                payload.kind,
+                fee,
+                &payload.tiers,
+                threshold,
+            )
+            .await
+    }
+
+    pub async fn remove_rule(&self, store_id: Uuid, rule_id: Uuid) -> crate::Result<()> {
+        if !self
+            .settings
+            .delete_shipping_rule(store_id, rule_id)
+            .await?
+        {
+            return Err(AppError::NotFound("Shipping rule not found".into()));
+        }
+        Ok(())
+    }
+
+    async fn ensure_store_exists(&self, store_id: Uuid) -> crate::Result<()> {
+        if self.stores.find_by_id(store_id).await?.is_none() {
+            return Err(AppError::NotFound("Store not found".into()));
+        }
+        Ok(())
+    }
+}
+
+fn decimal_from_f64(value: f64) -> crate::Result<Decimal> {
+    Decimal::from_f64_retain(value)
+        .ok_or_else(|| AppError::Validation("Invalid decimal value".into()))
+}
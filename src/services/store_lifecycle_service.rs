@@ -0,0 +1,114 @@
+use std::collections::BTreeSet;
+
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        notification::STORE_CLOSED,
+        store::{Store, StoreCleanup},
+    },
+    repositories::{CartRepository, NotificationRepository, ProductRepository, StoreRepository},
+};
+
+/// Runs the data lifecycle for closed stores: products are deactivated, cart
+/// lines referencing them are removed and the affected customers notified.
+/// Orders are never touched so buyers and merchants keep their history.
+#[derive(Clone)]
+pub struct StoreLifecycleService {
+    stores: StoreRepository,
+    products: ProductRepository,
+    carts: CartRepository,
+    notifications: NotificationRepository,
+}
+
+impl StoreLifecycleService {
+    pub fn new(
+        stores: StoreRepository,
+        products: ProductRepository,
+        carts: CartRepository,
+        notifications: NotificationRepository,
+    ) -> Self {
+        Self {
+            stores,
+            products,
+            carts,
+            notifications,
+        }
+    }
+
+    /// Cleans up a batch of closed stores that have not been processed yet.
+    pub async fn cleanup_closed_stores(&self, limit: i64) -> crate::Result<Vec<StoreCleanup>> {
+        let pending = self.stores.list_pending_cleanup(limit).await?;
+
+        let mut cleanups = Vec::with_capacity(pending.len());
+        for store in pending {
+            if let Some(cleanup) = self.cleanup_store(store.id).await? {
+                cleanups.push(cleanup);
+            }
+        }
+
+        Ok(cleanups)
+    }
+
+    /// Returns `None` when the store is no longer closed or was already
+    /// cleaned up by a concurrent run.
+    pub async fn cleanup_store(&self, store_id: Uuid) -> crate::Result<Option<StoreCleanup>> {
+        let mut tx = self.stores.pool().begin().await?;
+
+        let Some(store) = self
+            .stores
+            .lock_pending_cleanup_in_tx(&mut tx, store_id)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let products_deactivated = self
+            .products
+            .deactivate_for_store_in_tx(&mut tx, store.id)
+            .await?;
+
+        let removed_for = self
+            .carts
+            .remove_store_items_in_tx(&mut tx, store.id)
+            .await?;
+        let customers: Vec<Uuid> = removed_for
+            .iter()
+            .copied()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        self.notifications
+            .create_for_users_in_tx(
+                &mut tx,
+                &customers,
+                Some(store.id),
+                STORE_CLOSED,
+                &closure_message(&store),
+            )
+            .await?;
+
+        let cleanup = self
+            .stores
+            .record_cleanup_in_tx(
+                &mut tx,
+                store.id,
+                products_deactivated as i32,
+                removed_for.len() as i32,
+                customers.len() as i32,
+            )
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(cleanup))
+    }
+}
+
+fn closure_message(store: &Store) -> String {
+    format!(
+        "{} has closed. Its products were removed from your cart; your past orders remain available.",
+        store.name
+    )
+}
@@ -0,0 +1,82 @@
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::store::{StoreRole, StoreRoleRequest},
+    repositories::StoreRoleRepository,
+};
+
+/// Manages the named roles a store defines for its Custom members.
+#[derive(Clone)]
+pub struct StoreRoleService {
+    roles: StoreRoleRepository,
+}
+
+impl StoreRoleService {
+    pub fn new(roles: StoreRoleRepository) -> Self {
+        Self { roles }
+    }
+
+    pub async fn list(&self, store_id: Uuid) -> crate::Result<Vec<StoreRole>> {
+        self.roles.list_for_store(store_id).await
+    }
+
+    pub async fn create(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        payload: StoreRoleRequest,
+    ) -> crate::Result<StoreRole> {
+        let name = self.validate(store_id, &payload, None).await?;
+        self.roles
+            .create(store_id, name, &payload.permissions, actor_id)
+            .await
+    }
+
+    pub async fn update(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        role_id: Uuid,
+        payload: StoreRoleRequest,
+    ) -> crate::Result<StoreRole> {
+        let name = self.validate(store_id, &payload, Some(role_id)).await?;
+        self.roles
+            .update(store_id, role_id, name, &payload.permissions, actor_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Role not found".into()))
+    }
+
+    pub async fn delete(&self, actor_id: Uuid, store_id: Uuid, role_id: Uuid) -> crate::Result<()> {
+        self.roles
+            .delete(store_id, role_id, actor_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Role not found".into()))?;
+        Ok(())
+    }
+
+    /// Checks the request and returns the trimmed role name, which must be
+    /// unique within the store.
+    async fn validate<'a>(
+        &self,
+        store_id: Uuid,
+        payload: &'a StoreRoleRequest,
+        role_id: Option<Uuid>,
+    ) -> crate::Result<&'a str> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+        let name = payload.name.trim();
+        if name.is_empty() {
+            return Err(AppError::Validation("Role name cannot be blank".into()));
+        }
+        if self.roles.name_taken(store_id, name, role_id).await? {
+            return Err(AppError::Conflict(format!(
+                "A role named '{}' already exists",
+                name
+            )));
+        }
+        Ok(name)
+    }
+}
@@ -1,9 +1,16 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
 use validator::Validate;
 
 use crate::{
     error::AppError,
-    models::permission::Permission,
-    models::store::{CreateStoreRequest, MemberRole, Store, StoreMember},
+    imports::{self, ImportReport, MemberImporter},
+    models::permission::{effective_permissions, member_permissions, Permission},
+    models::store::{
+        ChangePlanRequest, CreateStoreRequest, MemberRole, MemberStore, Store, StoreMember,
+        StoreMembership, StoreRole,
+    },
     repositories::{MemberRepository, StoreRepository},
 };
 use uuid::Uuid;
@@ -41,6 +48,7 @@ impl StoreService {
                 owner_id,
                 MemberRole::Owner,
                 Permission::all(),
+                None,
                 Some(owner_id),
             )
             .await?;
@@ -59,7 +67,71 @@ impl StoreService {
             .ok_or_else(|| AppError::NotFound("Store not found".into()))
     }
 
+    /// Moves the store to another plan. Downgrades never delete data; quotas
+    /// only block further growth.
+    pub async fn change_plan(
+        &self,
+        store_id: Uuid,
+        payload: ChangePlanRequest,
+    ) -> crate::Result<Store> {
+        self.get_store(store_id).await?;
+        self.stores.update_plan(store_id, payload.plan).await
+    }
+
     pub async fn list_members(&self, store_id: Uuid) -> crate::Result<Vec<StoreMember>> {
         self.members.list_members(store_id).await
     }
+
+    /// Adds or updates members from an `email,role,permissions` CSV.
+    pub async fn import_members(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        data: &[u8],
+        dry_run: bool,
+    ) -> crate::Result<ImportReport> {
+        let store = self.get_store(store_id).await?;
+        let importer = MemberImporter::new(self.members.clone(), store, actor_id);
+        imports::run(&importer, data, dry_run).await
+    }
+
+    pub async fn list_user_stores(&self, user_id: Uuid) -> crate::Result<Vec<MemberStore>> {
+        self.members.list_stores_for_user(user_id).await
+    }
+
+    /// The user's stores with the permissions they hold in each, so clients
+    /// can tailor their UI without probing endpoints.
+    pub async fn list_memberships(&self, user_id: Uuid) -> crate::Result<Vec<StoreMembership>> {
+        let roles: HashMap<Uuid, StoreRole> = self
+            .members
+            .list_custom_roles_for_user(user_id)
+            .await?
+            .into_iter()
+            .map(|role| (role.id, role))
+            .collect();
+        let mut members: HashMap<Uuid, StoreMember> = self
+            .members
+            .list_active_for_user(user_id)
+            .await?
+            .into_iter()
+            .map(|member| (member.store_id, member))
+            .collect();
+
+        Ok(self
+            .members
+            .list_stores_for_user(user_id)
+            .await?
+            .into_iter()
+            .map(|store| {
+                let permissions = match members.remove(&store.store.id) {
+                    Some(member) => {
+                        let role = member.custom_role_id.and_then(|id| roles.get(&id));
+                        member_permissions(&member, role)
+                    }
+                    None => effective_permissions(store.role, &Value::Null),
+                };
+                StoreMembership { permissions, store }
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,134 @@
+use rust_decimal::Decimal;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::store::{
+        CheckoutField, CheckoutFieldType, CreateCheckoutFieldRequest, StoreSettings,
+        UpdateStoreSettingsRequest,
+    },
+    repositories::{StoreRepository, StoreSettingsRepository},
+};
+use uuid::Uuid;
+
+pub const MAX_CHECKOUT_FIELDS: usize = 20;
+
+#[derive(Clone)]
+pub struct StoreSettingsService {
+    settings: StoreSettingsRepository,
+    stores: StoreRepository,
+}
+
+impl StoreSettingsService {
+    pub fn new(settings: StoreSettingsRepository, stores: StoreRepository) -> Self {
+        Self { settings, stores }
+    }
+
+    pub async fn get_settings(&self, store_id: Uuid) -> crate::Result<StoreSettings> {
+        self.ensure_store_exists(store_id).await?;
+        Ok(self
+            .settings
+            .find(store_id)
+            .await?
+            .unwrap_or_else(|| StoreSettings::defaults(store_id)))
+    }
+
+    pub async fn update_settings(
+        &self,
+        store_id: Uuid,
+        payload: UpdateStoreSettingsRequest,
+    ) -> crate::Result<StoreSettings> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+
+        let mut settings = self.get_settings(store_id).await?;
+
+        if let Some(currency_code) = payload.currency_code {
+            settings.currency_code = currency_code;
+        }
+        if let Some(tax_rate) = payload.tax_rate {
+            settings.tax_rate = decimal_from_f64(tax_rate)?.round_dp(4);
+        }
+        if let Some(fee) = payload.flat_shipping_fee {
+            settings.flat_shipping_fee = decimal_from_f64(fee)?.round_dp(2);
+        }
+        if let Some(threshold) = payload.free_shipping_threshold {
+            settings.free_shipping_threshold = Some(decimal_from_f64(threshold)?.round_dp(2));
+        }
+        if let Some(mut ships_to) = payload.ships_to {
+            ships_to.sort();
+            ships_to.dedup();
+            settings.ships_to = ships_to;
+        }
+        if let Some(tax_provider) = payload.tax_provider {
+            settings.tax_provider = tax_provider;
+        }
+        if let Some(payment_provider) = payload.payment_provider {
+            settings.payment_provider = payment_provider;
+        }
+
+        self.settings.upsert(&settings).await
+    }
+
+    pub async fn list_checkout_fields(&self, store_id: Uuid) -> crate::Result<Vec<CheckoutField>> {
+        self.ensure_store_exists(store_id).await?;
+        self.settings.list_checkout_fields(store_id).await
+    }
+
+    pub async fn add_checkout_field(
+        &self,
+        store_id: Uuid,
+        payload: CreateCheckoutFieldRequest,
+    ) -> crate::Result<CheckoutField> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+        if payload.max_length.is_some() && payload.field_type != CheckoutFieldType::Text {
+            return Err(AppError::Validation(
+                "max_length only applies to text fields".into(),
+            ));
+        }
+
+        let existing = self.list_checkout_fields(store_id).await?;
+        if existing.iter().any(|field| field.key == payload.key) {
+            return Err(AppError::Conflict(format!(
+                "Checkout field '{}' already exists",
+                payload.key
+            )));
+        }
+        if existing.len() >= MAX_CHECKOUT_FIELDS {
+            return Err(AppError::Conflict(format!(
+                "Stores can define at most {} checkout fields",
+                MAX_CHECKOUT_FIELDS
+            )));
+        }
+
+        self.settings
+            .create_checkout_field(store_id, &payload)
+            .await
+    }
+
+    pub async fn remove_checkout_field(&self, store_id: Uuid, field_id: Uuid) -> crate::Result<()> {
+        if !self
+            .settings
+            .delete_checkout_field(store_id, field_id)
+            .await?
+        {
+            return Err(AppError::NotFound("Checkout field not found".into()));
+        }
+        Ok(())
+    }
+
+    async fn ensure_store_exists(&self, store_id: Uuid) -> crate::Result<()> {
+        if self.stores.find_by_id(store_id).await?.is_none() {
+            return Err(AppError::NotFound("Store not found".into()));
+        }
+        Ok(())
+    }
+}
+
+fn decimal_from_f64(value: f64) -> crate::Result<Decimal> {
+    Decimal::from_f64_retain(value)
+        .ok_or_else(|| AppError::Validation("Invalid decimal value".into()))
+}
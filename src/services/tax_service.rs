@@ -0,0 +1,71 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+
+use crate::{repositories::TaxRepository, tax::TaxProvider, utils::backoff::retry_delay};
+
+/// Attempts made at reporting a sale before it is marked failed.
+pub const MAX_COMMIT_ATTEMPTS: i32 = 8;
+
+/// How long a claimed transaction is held before another run may retry it.
+const COMMIT_LEASE_SECS: i64 = 300;
+
+/// Reports placed orders of externally taxed stores to the tax provider so
+/// they are included in its filings.
+#[derive(Clone)]
+pub struct TaxService {
+    transactions: TaxRepository,
+    provider: Arc<dyn TaxProvider>,
+}
+
+/// Outcome of one pass over the pending tax transactions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaxCommitRun {
+    pub committed: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+impl TaxService {
+    pub fn new(transactions: TaxRepository, provider: Arc<dyn TaxProvider>) -> Self {
+        Self {
+            transactions,
+            provider,
+        }
+    }
+
+    /// Commits up to `limit` due transactions. Failures are retried with a
+    /// growing delay until `MAX_COMMIT_ATTEMPTS` is reached.
+    pub async fn commit_due(&self, limit: i64) -> crate::Result<TaxCommitRun> {
+        let mut run = TaxCommitRun::default();
+        let filings = self
+            .transactions
+            .claim_due(limit, COMMIT_LEASE_SECS)
+            .await?;
+
+        for filing in filings {
+            match self.provider.commit(&filing).await {
+                Ok(external_id) => {
+                    self.transactions
+                        .mark_committed(filing.transaction_id, &external_id)
+                        .await?;
+                    run.committed += 1;
+                }
+                Err(err) => {
+                    let retry_at = (filing.attempts < MAX_COMMIT_ATTEMPTS)
+                        .then(|| Utc::now() + retry_delay(filing.attempts));
+                    if retry_at.is_some() {
+                        run.retrying += 1;
+                    } else {
+                        run.failed += 1;
+                    }
+                    self.transactions
+                        .mark_failed(filing.transaction_id, &err.to_string(), retry_at)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(run)
+    }
+}
@@ -0,0 +1,137 @@
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        notification::ORDER_CANCELLED,
+        order::{ExpiredOrderGroup, OrderGroup, PaymentStatus},
+    },
+    repositories::{NotificationRepository, OrderRepository, PaymentRepository},
+    services::{order_cancellation::CancelledOrder, OrderCancellation},
+};
+
+const EXPIRY_REASON: &str = "Payment was not received in time";
+
+/// Cancels order groups that were never paid so their stock goes back on
+/// sale. Orders already paid for, or shipped, are left alone.
+#[derive(Clone)]
+pub struct UnpaidOrderService {
+    orders: OrderRepository,
+    payments: PaymentRepository,
+    notifications: NotificationRepository,
+    cancellation: OrderCancellation,
+}
+
+impl UnpaidOrderService {
+    pub fn new(
+        orders: OrderRepository,
+        payments: PaymentRepository,
+        notifications: NotificationRepository,
+    ) -> Self {
+        Self {
+            cancellation: OrderCancellation::new(orders.pool().clone()),
+            orders,
+            payments,
+            notifications,
+        }
+    }
+
+    /// Expires a batch of groups still awaiting payment `max_age` after
+    /// checkout.
+    pub async fn expire_unpaid_groups(
+        &self,
+        max_age: chrono::Duration,
+        limit: i64,
+    ) -> crate::Result<Vec<ExpiredOrderGroup>> {
+        let stale = self.orders.list_stale_unpaid_groups(max_age, limit).await?;
+
+        let mut expired = Vec::with_capacity(stale.len());
+        for group in stale {
+            if let Some(result) = self.expire_group(group.id).await? {
+                expired.push(result);
+            }
+        }
+
+        Ok(expired)
+    }
+
+    /// Cancels the group's unpaid orders, gives back their stock, store
+    /// credit and coupon uses, fails open payments and tells the buyer and
+    /// the stores, all in one transaction.
+    /// Returns `None` when the group was paid or expired concurrently.
+    pub async fn expire_group(
+        &self,
+        order_group_id: Uuid,
+    ) -> crate::Result<Option<ExpiredOrderGroup>> {
+        let mut tx = self.orders.pool().begin().await?;
+
+        let Some(group) = self
+            .orders
+            .lock_unpaid_group_in_tx(&mut tx, order_group_id)
+            .await?
+        else {
+            return Ok(None);
+        };
+
+        let cancelled = self
+            .cancellation
+            .cancel_unpaid_in_tx(&mut tx, group.id, EXPIRY_REASON)
+            .await?;
+        let mut units_restored = 0;
+        for CancelledOrder {
+            order,
+            units_restored: units,
+        } in &cancelled
+        {
+            units_restored += units;
+            self.notifications
+                .dispatch_store_event_in_tx(
+                    &mut tx,
+                    order.store_id,
+                    ORDER_CANCELLED,
+                    &format!(
+                        "Order {} was cancelled: {}",
+                        order.order_number, EXPIRY_REASON
+                    ),
+                )
+                .await?;
+        }
+        self.payments
+            .fail_open_for_group_in_tx(&mut tx, group.id, EXPIRY_REASON)
+            .await?;
+        // Orders paid for before the rest expired keep the group paid.
+        let status = if self.payments.any_captured_in_tx(&mut tx, group.id).await? {
+            PaymentStatus::Paid
+        } else {
+            PaymentStatus::Failed
+        };
+        self.orders
+            .mark_payment_status_in_tx(&mut tx, group.id, status)
+            .await?;
+        if !cancelled.is_empty() {
+            self.notifications
+                .create_for_users_in_tx(
+                    &mut tx,
+                    &[group.user_id],
+                    None,
+                    ORDER_CANCELLED,
+                    &expiry_message(&group),
+                )
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(Some(ExpiredOrderGroup {
+            order_group_id: group.id,
+            orders_cancelled: cancelled.len(),
+            units_restored,
+        }))
+    }
+}
+
+fn expiry_message(group: &OrderGroup) -> String {
+    format!(
+        "Order {} was cancelled because payment was not received in time. Its items are back on sale.",
+        group.group_number
+    )
+}
@@ -0,0 +1,211 @@
+use std::time::Instant;
+
+use chrono::Utc;
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    error::AppError,
+    models::webhook::{
+        CreateWebhookRequest, CreatedWebhook, Webhook, WebhookDelivery, WebhookPing, WEBHOOK_PING,
+    },
+    repositories::{StoreRepository, WebhookRepository},
+    services::notification_service::DeliveryRun,
+    utils::backoff::exponential_delay,
+    webhooks::{check_target, generate_secret, CircuitOpen, WebhookSender},
+};
+
+/// Attempts made at a webhook delivery before it is marked failed; with
+/// exponential backoff the last one comes about four hours after the first.
+pub const MAX_WEBHOOK_ATTEMPTS: i32 = 10;
+
+/// How long a claimed delivery is held before another run may retry it.
+const DELIVERY_LEASE_SECS: i64 = 120;
+
+#[derive(Clone)]
+pub struct WebhookService {
+    webhooks: WebhookRepository,
+    stores: StoreRepository,
+}
+
+impl WebhookService {
+    pub fn new(webhooks: WebhookRepository, stores: StoreRepository) -> Self {
+        Self { webhooks, stores }
+    }
+
+    pub async fn list(&self, store_id: Uuid) -> crate::Result<Vec<Webhook>> {
+        self.webhooks.list_for_store(store_id).await
+    }
+
+    /// Registers a webhook, returning it with its secret. The secret is not
+    /// shown again. Stores may register as many webhooks as their plan
+    /// allows.
+    pub async fn create(
+        &self,
+        actor_id: Uuid,
+        store_id: Uuid,
+        mut payload: CreateWebhookRequest,
+    ) -> crate::Result<CreatedWebhook> {
+        payload
+            .validate()
+            .map_err(|err| AppError::Validation(err.to_string()))?;
+        check_target(&payload.url)
+            .await
+            .map_err(|err| AppError::Validation(format!("{:#}", err)))?;
+        payload.events.sort();
+        payload.events.dedup();
+
+        let store = self
+            .stores
+            .find_by_id(store_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Store not found".into()))?;
+        let limit = store.plan.limits().max_webhooks;
+        let current = self.webhooks.count_for_store(store_id).await?;
+        if current >= limit {
+            return Err(AppError::QuotaExceeded(format!(
+                "The {:?} plan allows at most {} webhooks; the store has {}",
+                store.plan, limit, current
+            )));
+        }
+
+        let secret = payload.secret.unwrap_or_else(generate_secret);
+        let webhook = self
+            .webhooks
+            .create(store_id, &payload.url, &secret, &payload.events, actor_id)
+            .await?;
+        Ok(CreatedWebhook { webhook, secret })
+    }
+
+    pub async fn delete(&self, store_id: Uuid, webhook_id: Uuid) -> crate::Result<()> {
+        if !self.webhooks.delete(store_id, webhook_id).await? {
+            return Err(AppError::NotFound("Webhook not found".into()));
+        }
+        Ok(())
+    }
+
+    /// The webhook's delivery log, newest first.
+    pub async fn deliveries(
+        &self,
+        store_id: Uuid,
+        webhook_id: Uuid,
+        limit: i64,
+        offset: i64,
+    ) -> crate::Result<Vec<WebhookDelivery>> {
+        self.webhooks
+            .find_for_store(store_id, webhook_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Webhook not found".into()))?;
+        self.webhooks
+            .list_deliveries(webhook_id, limit, offset)
+            .await
+    }
+
+    /// Sends the webhook a signed `webhook.ping` event and reports how its
+    /// endpoint answered. Test deliveries are not retried or logged.
+    pub async fn ping(
+        &self,
+        sender: &WebhookSender,
+        store_id: Uuid,
+        webhook_id: Uuid,
+    ) -> crate::Result<WebhookPing> {
+        let webhook = self
+            .webhooks
+            .find_for_store(store_id, webhook_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Webhook not found".into()))?;
+        let event_id = Uuid::new_v4();
+        let payload = json!({
+            "id": event_id,
+            "type": WEBHOOK_PING,
+            "created_at": Utc::now(),
+            "data": { "webhook_id": webhook.id, "store_id": store_id },
+        });
+
+        let started = Instant::now();
+        let outcome = sender
+            .post(
+                &webhook.url,
+                &webhook.secret,
+                WEBHOOK_PING,
+                event_id,
+                &payload,
+            )
+            .await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        Ok(match outcome {
+            Ok(response) => WebhookPing {
+                status: Some(response.status),
+                latency_ms,
+                error: None,
+            },
+            Err(err) => WebhookPing {
+                status: None,
+                latency_ms,
+                error: Some(format!("{:#}", err)),
+            },
+        })
+    }
+
+    /// Sends up to `limit` due deliveries. Anything but a 2xx answer is
+    /// retried with exponentially growing delays until
+    /// `MAX_WEBHOOK_ATTEMPTS` is reached. Deliveries to a host whose breaker
+    /// is open are pushed back the same way without using up an attempt.
+    pub async fn deliver_due(
+        &self,
+        sender: &WebhookSender,
+        limit: i64,
+    ) -> crate::Result<DeliveryRun> {
+        let mut run = DeliveryRun::default();
+        let deliveries = self
+            .webhooks
+            .claim_due_deliveries(limit, DELIVERY_LEASE_SECS)
+            .await?;
+
+        for due in deliveries {
+            let delivery = &due.delivery;
+            let (error, response) = match sender.send(&due).await {
+                Ok(response) if response.is_success() => {
+                    self.webhooks
+                        .mark_delivered(delivery.id, i32::from(response.status), &response.body)
+                        .await?;
+                    run.sent += 1;
+                    continue;
+                }
+                Ok(response) => (
+                    format!("Endpoint answered {}", response.status),
+                    Some(response),
+                ),
+                Err(err) if err.is::<CircuitOpen>() => {
+                    let retry_at = Utc::now() + exponential_delay(delivery.attempts.max(1));
+                    self.webhooks
+                        .postpone(delivery.id, &err.to_string(), retry_at)
+                        .await?;
+                    run.retrying += 1;
+                    continue;
+                }
+                Err(err) => (format!("{:#}", err), None),
+            };
+
+            let retry_at = (delivery.attempts < MAX_WEBHOOK_ATTEMPTS)
+                .then(|| Utc::now() + exponential_delay(delivery.attempts));
+            if retry_at.is_some() {
+                run.retrying += 1;
+            } else {
+                run.failed += 1;
+            }
+            self.webhooks
+                .mark_failed(
+                    delivery.id,
+                    &error,
+                    response.as_ref().map(|response| i32::from(response.status)),
+                    response.as_ref().map(|response| response.body.as_str()),
+                    retry_at,
+                )
+                .await?;
+        }
+
+        Ok(run)
+    }
+}
@@ -0,0 +1,100 @@
+use std::{sync::RwLock, time::Duration};
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::{CarrierQuote, CarrierRateProvider, ShippingParcel};
+use crate::{error::AppError, models::order::tax_jurisdiction, tax::postal_code};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client for a multi-carrier rating API in the style of Shippo or
+/// EasyPost: `POST {base_url}/rates` quotes every service available for a
+/// parcel.
+pub struct HttpCarrierRates {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: RwLock<String>,
+}
+
+impl HttpCarrierRates {
+    pub fn new(base_url: impl Into<String>, api_key: impl Into<String>) -> crate::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| AppError::Internal(err.into()))?;
+        Ok(Self {
+            client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: RwLock::new(api_key.into()),
+        })
+    }
+
+    /// Swaps the API key for subsequent requests, e.g. after a rotation.
+    pub fn set_api_key(&self, api_key: &str) {
+        *self.api_key.write().unwrap() = api_key.to_string();
+    }
+}
+
+#[derive(Serialize)]
+struct RateRequest<'a> {
+    /// Country, suffixed with the region where known, e.g. `US-CA`.
+    to_jurisdiction: String,
+    to_zip: Option<&'a str>,
+    weight_grams: i64,
+    value: Decimal,
+    currency: &'a str,
+}
+
+#[derive(Deserialize)]
+struct RateResponse {
+    rates: Vec<RateBody>,
+}
+
+#[derive(Deserialize)]
+struct RateBody {
+    carrier: String,
+    service: String,
+    name: String,
+    amount: Decimal,
+    estimated_days: Option<i32>,
+}
+
+#[async_trait]
+impl CarrierRateProvider for HttpCarrierRates {
+    async fn quote(&self, parcel: &ShippingParcel<'_>) -> crate::Result<Vec<CarrierQuote>> {
+        let request = RateRequest {
+            to_jurisdiction: tax_jurisdiction(parcel.destination),
+            to_zip: postal_code(parcel.destination),
+            weight_grams: parcel.weight_grams,
+            value: parcel.subtotal,
+            currency: &parcel.settings.currency_code,
+        };
+        let api_key = self.api_key.read().unwrap().clone();
+        let response: RateResponse = self
+            .client
+            .post(format!("{}/rates", self.base_url))
+            .bearer_auth(api_key)
+            .json(&request)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AppError::Internal(err.into()))?
+            .json()
+            .await
+            .map_err(|err| AppError::Internal(err.into()))?;
+
+        Ok(response
+            .rates
+            .into_iter()
+            .map(|rate| CarrierQuote {
+                carrier: rate.carrier,
+                service: rate.service,
+                name: rate.name,
+                amount: rate.amount,
+                estimated_days: rate.estimated_days,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::{
+    metrics::SharedMetrics,
+    models::{
+        shipping::{ShippingOption, ShippingRule, ShippingRuleKind},
+        store::StoreSettings,
+    },
+    utils::breaker::{BreakerConfig, CircuitBreaker},
+};
+
+pub mod http;
+
+pub use http::HttpCarrierRates;
+
+/// One store's share of a checkout that has to be shipped.
+pub struct ShippingParcel<'a> {
+    pub settings: &'a StoreSettings,
+    pub rules: &'a [ShippingRule],
+    pub destination: &'a Value,
+    pub weight_grams: i64,
+    /// Subtotal of the lines that ship.
+    pub subtotal: Decimal,
+}
+
+/// A live rate for one of a carrier's services.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CarrierQuote {
+    pub carrier: String,
+    /// The carrier's code for the service, e.g. `ground`.
+    pub service: String,
+    pub name: String,
+    pub amount: Decimal,
+    pub estimated_days: Option<i32>,
+}
+
+/// Carrier integration quoting live rates for stores with a `Carrier`
+/// shipping rule.
+#[async_trait]
+pub trait CarrierRateProvider: Send + Sync {
+    async fn quote(&self, parcel: &ShippingParcel<'_>) -> crate::Result<Vec<CarrierQuote>>;
+}
+
+/// Turns a store's shipping rules into the options offered at checkout.
+/// Quotes never fail a checkout: when the carrier errors or is not
+/// configured its options are left out, and a store left without any option
+/// ships at its flat fee.
+pub struct ShippingRates {
+    carrier: Option<Arc<dyn CarrierRateProvider>>,
+    breaker: CircuitBreaker,
+}
+
+impl ShippingRates {
+    /// Only the stores' own rules; `Carrier` rules offer nothing.
+    pub fn builtin() -> Self {
+        Self {
+            carrier: None,
+            breaker: CircuitBreaker::new("carrier_rates", BreakerConfig::default()),
+        }
+    }
+
+    pub fn with_carrier(mut self, carrier: Arc<dyn CarrierRateProvider>) -> Self {
+        self.carrier = Some(carrier);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.breaker = self.breaker.with_metrics(metrics);
+        self
+    }
+
+    /// The parcel's shipping options, cheapest first.
+    pub async fn options(&self, parcel: &ShippingParcel<'_>) -> Vec<ShippingOption> {
+        let mut options = Vec::new();
+        for rule in parcel.rules {
+            if rule.kind == ShippingRuleKind::Carrier {
+                options.extend(self.carrier_options(rule, parcel).await);
+            } else if let Some(cost) = rule.price(parcel.weight_grams, parcel.subtotal) {
+                options.push(ShippingOption {
+                    code: rule.id.to_string(),
+                    name: rule.name.clone(),
+                    cost,
+                    carrier: None,
+                    estimated_days: None,
+                });
+            }
+        }
+
+        if options.is_empty() {
+            options.push(ShippingOption {
+                code: ShippingOption::STANDARD.into(),
+                name: "Standard shipping".into(),
+                cost: parcel.settings.shipping_for(parcel.subtotal),
+                carrier: None,
+                estimated_days: None,
+            });
+        }
+        options.sort_by(|a, b| a.cost.cmp(&b.cost).then_with(|| a.name.cmp(&b.name)));
+        options
+    }
+
+    async fn carrier_options(
+        &self,
+        rule: &ShippingRule,
+        parcel: &ShippingParcel<'_>,
+    ) -> Vec<ShippingOption> {
+        let Some(carrier) = &self.carrier else {
+            return Vec::new();
+        };
+        let quotes = match self.breaker.call(carrier.quote(parcel)).await {
+            Ok(quotes) => quotes,
+            Err(err) => {
+                tracing::warn!(
+                    store_id = %parcel.settings.store_id,
+                    "Carrier rate quote failed, leaving out '{}': {}",
+                    rule.name,
+                    err
+                );
+                return Vec::new();
+            }
+        };
+
+        let free = rule.ships_free(parcel.subtotal);
+        quotes
+            .into_iter()
+            .map(|quote| ShippingOption {
+                code: carrier_code(rule.id, &quote.service),
+                name: format!("{} ({})", rule.name, quote.name),
+                cost: if free { Decimal::ZERO } else { quote.amount },
+                carrier: Some(quote.carrier),
+                estimated_days: quote.estimated_days,
+            })
+            .collect()
+    }
+}
+
+impl Default for ShippingRates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+fn carrier_code(rule_id: Uuid, service: &str) -> String {
+    format!("{}:{}", rule_id, service)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error::AppError, models::shipping::ShippingTier};
+    use chrono::Utc;
+    use serde_json::json;
+    use sqlx::types::Json;
+
+    struct FixedCarrier(crate::Result<Vec<CarrierQuote>>);
+
+    #[async_trait]
+    impl CarrierRateProvider for FixedCarrier {
+        async fn quote(&self, _: &ShippingParcel<'_>) -> crate::Result<Vec<CarrierQuote>> {
+            match &self.0 {
+                Ok(quotes) => Ok(quotes.clone()),
+                Err(_) => Err(AppError::Internal(anyhow::anyhow!("carrier down"))),
+            }
+        }
+    }
+
+    fn rule(name: &str, kind: ShippingRuleKind, fee: i64) -> ShippingRule {
+        ShippingRule {
+            id: Uuid::new_v4(),
+            store_id: Uuid::new_v4(),
+            name: name.into(),
+            kind,
+            fee: Decimal::new(fee, 2),
+            tiers: Json(vec![ShippingTier {
+                min: Decimal::ZERO,
+                fee: Decimal::new(fee, 2),
+            }]),
+            free_shipping_threshold: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn quote(service: &str, amount: i64) -> CarrierQuote {
+        CarrierQuote {
+            carrier: "ACME".into(),
+            service: service.into(),
+            name: service.to_uppercase(),
+            amount: Decimal::new(amount, 2),
+            estimated_days: Some(3),
+        }
+    }
+
+    #[tokio::test]
+    async fn rules_and_carrier_quotes_are_offered_cheapest_first() {
+        let mut settings = StoreSettings::defaults(Uuid::new_v4());
+        settings.flat_shipping_fee = Decimal::new(999, 2);
+        let carrier = rule("ACME", ShippingRuleKind::Carrier, 0);
+        let rules = vec![
+            rule("Courier", ShippingRuleKind::Flat, 1200),
+            rule("Economy", ShippingRuleKind::WeightTiers, 450),
+            carrier.clone(),
+        ];
+        let destination = json!({ "country": "US" });
+        let parcel = ShippingParcel {
+            settings: &settings,
+            rules: &rules,
+            destination: &destination,
+            weight_grams: 800,
+            subtotal: Decimal::from(40),
+        };
+
+        let rates = ShippingRates::builtin()
+            .with_carrier(Arc::new(FixedCarrier(Ok(vec![quote("ground", 700)]))));
+        let options = rates.options(&parcel).await;
+        let names: Vec<&str> = options.iter().map(|o| o.name.as_str()).collect();
+        assert_eq!(names, ["Economy", "ACME (GROUND)", "Courier"]);
+        assert_eq!(options[1].code, format!("{}:ground", carrier.id));
+        assert_eq!(options[1].carrier.as_deref(), Some("ACME"));
+    }
+
+    #[tokio::test]
+    async fn stores_without_usable_rules_ship_at_their_flat_fee() {
+        let mut settings = StoreSettings::defaults(Uuid::new_v4());
+        settings.flat_shipping_fee = Decimal::new(599, 2);
+        let rules = vec![rule("ACME", ShippingRuleKind::Carrier, 0)];
+        let destination = json!({ "country": "US" });
+        let parcel = ShippingParcel {
+            settings: &settings,
+            rules: &rules,
+            destination: &destination,
+            weight_grams: 0,
+            subtotal: Decimal::from(10),
+        };
+
+        let rates = ShippingRates::builtin().with_carrier(Arc::new(FixedCarrier(Err(
+            AppError::BadRequest("unused".into()),
+        ))));
+        let options = rates.options(&parcel).await;
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].code, ShippingOption::STANDARD);
+        assert_eq!(options[0].cost, Decimal::new(599, 2));
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+/// Fans a shutdown request out to every listener and to whatever else needs
+/// to wind down. Clones observe the same request.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: watch::Receiver<bool>,
+    trigger: watch::Sender<bool>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    /// A shutdown only [`Shutdown::trigger`] requests.
+    pub fn new() -> Self {
+        let (trigger, requested) = watch::channel(false);
+        Self { requested, trigger }
+    }
+
+    /// A shutdown requested by SIGTERM (what orchestrators send on deploy) or
+    /// SIGINT (Ctrl+C).
+    pub fn on_signal() -> Self {
+        let shutdown = Self::new();
+        let trigger = shutdown.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            trigger.trigger();
+        });
+        shutdown
+    }
+
+    pub fn trigger(&self) {
+        let _ = self.trigger.send(true);
+    }
+
+    /// Resolves once shutdown is requested.
+    pub async fn requested(&self) {
+        let mut requested = self.requested.clone();
+        let _ = requested.wait_for(|requested| *requested).await;
+    }
+
+    /// Resolves `grace_period` after shutdown is requested.
+    pub async fn grace_period_elapsed(&self, grace_period: Duration) {
+        self.requested().await;
+        tokio::time::sleep(grace_period).await;
+    }
+}
+
+async fn wait_for_signal() {
+    let interrupt = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for SIGINT: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        () = interrupt => tracing::info!("Received SIGINT, shutting down"),
+        () = terminate => tracing::info!("Received SIGTERM, shutting down"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn clones_observe_a_trigger_and_the_grace_period_follows_it() {
+        let shutdown = Shutdown::new();
+        let waiter = shutdown.clone();
+        let grace = Duration::from_millis(30);
+        let elapsed = tokio::spawn(async move {
+            waiter.grace_period_elapsed(grace).await;
+            tokio::time::Instant::now()
+        });
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!elapsed.is_finished());
+
+        let triggered = tokio::time::Instant::now();
+        shutdown.trigger();
+        shutdown.requested().await;
+        assert!(elapsed.await.unwrap() >= triggered + grace);
+    }
+}
@@ -1,6 +1,27 @@
 use std::sync::Arc;
 
-use crate::{metrics::Metrics, utils::jwt::JwtConfig};
+use chrono::Duration;
+
+use crate::{
+    cache::{CacheHandle, MemoryCache},
+    currency::ExchangeRates,
+    events::{EmailHandler, EventBus, EventHandler, MetricsHandler},
+    metrics::Metrics,
+    payments::PaymentProviders,
+    services::{
+        analytics_service::AnalyticsCache,
+        cart_policy::CartPolicy,
+        cart_service::DEFAULT_RESERVATION_TTL_SECS,
+        readiness_service::DEFAULT_READINESS_TIMEOUT,
+        search_service::SuggestionCache,
+        shipping_consolidation::{ConsolidationMode, ShippingConsolidation},
+    },
+    shipping::ShippingRates,
+    storage::{LocalStorage, ObjectStorage},
+    tax::TaxRates,
+    utils::{jwt::JwtConfig, public_id::PublicIds},
+    webhooks::WebhookSender,
+};
 use sqlx::PgPool;
 
 #[derive(Clone)]
@@ -8,14 +29,157 @@ pub struct AppState {
     pub db: PgPool,
     pub jwt: Arc<JwtConfig>,
     pub metrics: Arc<Metrics>,
+    pub shipping_consolidation: Arc<dyn ShippingConsolidation>,
+    pub tax_rates: Arc<TaxRates>,
+    pub shipping_rates: Arc<ShippingRates>,
+    pub payment_providers: Arc<PaymentProviders>,
+    /// Converts prices for shoppers browsing in another currency.
+    pub exchange_rates: Arc<ExchangeRates>,
+    pub cart_policy: CartPolicy,
+    /// How long adding to cart holds stock for the buyer.
+    pub reservation_ttl: Duration,
+    /// How long responses to requests sent with an `Idempotency-Key` are
+    /// kept for replay.
+    pub idempotency_ttl: Duration,
+    pub storage: Arc<dyn ObjectStorage>,
+    /// Backend for files that must never be publicly reachable, such as
+    /// digital product downloads.
+    pub private_storage: Arc<dyn ObjectStorage>,
+    pub event_sample_rate: f64,
+    pub suggestions: Arc<SuggestionCache>,
+    pub analytics_cache: Arc<AnalyticsCache>,
+    /// Signs public order references; keyed with the JWT secret unless
+    /// configured separately.
+    pub public_ids: Arc<PublicIds>,
+    /// Speeds up product listings and permission checks.
+    pub cache: CacheHandle,
+    /// How long each dependency gets to answer `/readyz`.
+    pub readiness_timeout: std::time::Duration,
+    /// Where services announce orders, stock changes and sign-ups. Buyers
+    /// are emailed order confirmations from here.
+    pub events: EventBus,
+    /// Sends test deliveries; the server shares it with the delivery task
+    /// so both see the same per-host breakers.
+    pub webhook_sender: WebhookSender,
 }
 
 impl AppState {
     pub fn new(db: PgPool, jwt: JwtConfig, metrics: Arc<Metrics>) -> Self {
+        let public_ids = Arc::new(PublicIds::new(jwt.secret()));
+        let events = EventBus::with_defaults(db.clone())
+            .subscribe(Arc::new(EmailHandler::new(db.clone())))
+            .subscribe(Arc::new(MetricsHandler::new(metrics.clone())));
         Self {
             db,
             jwt: Arc::new(jwt),
             metrics,
+            shipping_consolidation: ConsolidationMode::default().strategy(),
+            tax_rates: Arc::new(TaxRates::builtin()),
+            shipping_rates: Arc::new(ShippingRates::builtin()),
+            payment_providers: Arc::new(PaymentProviders::manual_only()),
+            exchange_rates: Arc::new(ExchangeRates::none()),
+            cart_policy: CartPolicy::default(),
+            reservation_ttl: Duration::seconds(DEFAULT_RESERVATION_TTL_SECS),
+            idempotency_ttl: Duration::hours(24),
+            storage: Arc::new(LocalStorage::new("./uploads", "/uploads")),
+            private_storage: Arc::new(LocalStorage::new("./private-uploads", "")),
+            event_sample_rate: 1.0,
+            suggestions: Arc::new(SuggestionCache::default()),
+            analytics_cache: Arc::new(AnalyticsCache::default()),
+            public_ids,
+            cache: CacheHandle::new(
+                Arc::new(MemoryCache::new(10_000)),
+                std::time::Duration::from_secs(60),
+            ),
+            readiness_timeout: DEFAULT_READINESS_TIMEOUT,
+            events,
+            webhook_sender: WebhookSender::new().expect("webhook HTTP client should build"),
         }
     }
+
+    pub fn with_shipping_consolidation(mut self, mode: ConsolidationMode) -> Self {
+        self.shipping_consolidation = mode.strategy();
+        self
+    }
+
+    pub fn with_tax_rates(mut self, tax_rates: TaxRates) -> Self {
+        self.tax_rates = Arc::new(tax_rates);
+        self
+    }
+
+    pub fn with_shipping_rates(mut self, shipping_rates: ShippingRates) -> Self {
+        self.shipping_rates = Arc::new(shipping_rates);
+        self
+    }
+
+    pub fn with_payment_providers(mut self, providers: PaymentProviders) -> Self {
+        self.payment_providers = Arc::new(providers);
+        self
+    }
+
+    pub fn with_exchange_rates(mut self, exchange_rates: ExchangeRates) -> Self {
+        self.exchange_rates = Arc::new(exchange_rates);
+        self
+    }
+
+    pub fn with_cart_policy(mut self, policy: CartPolicy) -> Self {
+        self.cart_policy = policy;
+        self
+    }
+
+    pub fn with_reservation_ttl(mut self, ttl: Duration) -> Self {
+        self.reservation_ttl = ttl;
+        self
+    }
+
+    pub fn with_idempotency_ttl(mut self, ttl: Duration) -> Self {
+        self.idempotency_ttl = ttl;
+        self
+    }
+
+    pub fn with_event_sample_rate(mut self, rate: f64) -> Self {
+        self.event_sample_rate = rate;
+        self
+    }
+
+    pub fn with_analytics_cache(mut self, cache: AnalyticsCache) -> Self {
+        self.analytics_cache = Arc::new(cache);
+        self
+    }
+
+    pub fn with_storage(mut self, storage: Arc<dyn ObjectStorage>) -> Self {
+        self.storage = storage;
+        self
+    }
+
+    pub fn with_public_id_secret(mut self, secret: &str) -> Self {
+        self.public_ids = Arc::new(PublicIds::new(secret));
+        self
+    }
+
+    pub fn with_cache(mut self, cache: CacheHandle) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    pub fn with_readiness_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.readiness_timeout = timeout;
+        self
+    }
+
+    pub fn with_private_storage(mut self, storage: Arc<dyn ObjectStorage>) -> Self {
+        self.private_storage = storage;
+        self
+    }
+
+    pub fn with_webhook_sender(mut self, sender: WebhookSender) -> Self {
+        self.webhook_sender = sender;
+        self
+    }
+
+    /// Subscribes another handler to the events services publish.
+    pub fn with_event_handler(mut self, handler: Arc<dyn EventHandler>) -> Self {
+        self.events = self.events.subscribe(handler);
+        self
+    }
 }
@@ -0,0 +1,76 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+
+use super::ObjectStorage;
+use crate::error::AppError;
+
+/// Stores files on the local filesystem. Suitable for development and for
+/// single-node deployments with a CDN or reverse proxy in front of `root`.
+pub struct LocalStorage {
+    root: PathBuf,
+    public_base_url: String,
+}
+
+impl LocalStorage {
+    pub fn new(root: impl Into<PathBuf>, public_base_url: impl Into<String>) -> Self {
+        Self {
+            root: root.into(),
+            public_base_url: public_base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path_for(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+}
+
+#[async_trait]
+impl ObjectStorage for LocalStorage {
+    async fn put(&self, key: &str, _content_type: &str, data: Bytes) -> crate::Result<()> {
+        let path = self.path_for(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("creating {}", parent.display()))?;
+        }
+        tokio::fs::write(&path, &data)
+            .await
+            .with_context(|| format!("writing {}", path.display()))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> crate::Result<Bytes> {
+        let path = self.path_for(key);
+        match tokio::fs::read(&path).await {
+            Ok(data) => Ok(Bytes::from(data)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::NotFound("File not found".into()))
+            }
+            Err(err) => Err(anyhow::Error::new(err)
+                .context(format!("reading {}", path.display()))
+                .into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> crate::Result<()> {
+        let path = self.path_for(key);
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(anyhow::Error::new(err)
+                .context(format!("deleting {}", path.display()))
+                .into()),
+        }
+    }
+
+    fn public_url(&self, key: &str) -> String {
+        format!("{}/{}", self.public_base_url, key)
+    }
+}
@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+
+pub mod local;
+
+pub use local::LocalStorage;
+
+/// Backend holding uploaded files. Keys are opaque, slash-separated paths;
+/// `public_url` turns a key into the URL clients (or a CDN in front of the
+/// backend) serve it from.
+#[async_trait]
+pub trait ObjectStorage: Send + Sync {
+    async fn put(&self, key: &str, content_type: &str, data: Bytes) -> crate::Result<()>;
+
+    async fn get(&self, key: &str) -> crate::Result<Bytes>;
+
+    async fn delete(&self, key: &str) -> crate::Result<()>;
+
+    fn public_url(&self, key: &str) -> String;
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    repositories::{AnalyticsRepository, StoreRepository},
+    services::AnalyticsService,
+};
+
+/// Periodically rebuilds the daily analytics rollups that store reports
+/// read from. Runs once on startup, then every `interval`.
+pub fn spawn(pool: PgPool, lookback_days: i64, interval: Duration) -> JoinHandle<()> {
+    let service = AnalyticsService::new(
+        StoreRepository::new(pool.clone()),
+        AnalyticsRepository::new(pool),
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.refresh_rollups(lookback_days).await {
+                Ok(store_days) => tracing::info!(store_days, "Analytics rollups refreshed"),
+                Err(err) => tracing::error!("Analytics rollup refresh failed: {}", err),
+            }
+        }
+    })
+}
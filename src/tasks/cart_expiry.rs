@@ -0,0 +1,39 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{metrics::Metrics, repositories::CartRepository};
+
+const BATCH_SIZE: i64 = 200;
+
+/// Periodically expires user carts idle for `max_age`, recording each as an
+/// abandoned cart per store. Guest carts are left to the guest cleanup.
+pub fn spawn(
+    pool: PgPool,
+    metrics: Arc<Metrics>,
+    max_age: chrono::Duration,
+    interval: Duration,
+) -> JoinHandle<()> {
+    let carts = CartRepository::new(pool);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match carts.expire_idle_carts(max_age, BATCH_SIZE).await {
+                Ok(abandoned) if abandoned.is_empty() => {}
+                Ok(abandoned) => {
+                    let users: HashSet<_> = abandoned.iter().map(|cart| cart.user_id).collect();
+                    let items: i64 = abandoned
+                        .iter()
+                        .map(|cart| i64::from(cart.item_count))
+                        .sum();
+                    metrics.observe_cart_expiry(users.len() as u64, items as u64);
+                    tracing::info!(carts = users.len(), items, "Idle carts expired");
+                }
+                Err(err) => tracing::error!("Cart expiry failed: {}", err),
+            }
+        }
+    })
+}
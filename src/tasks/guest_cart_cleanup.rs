@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::repositories::CartRepository;
+
+const BATCH_SIZE: i64 = 500;
+
+/// Periodically deletes guest cart lines nobody has touched for `max_age`.
+/// Guests who come back after that start with an empty cart.
+pub fn spawn(pool: PgPool, max_age: chrono::Duration, interval: Duration) -> JoinHandle<()> {
+    let carts = CartRepository::new(pool);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match carts.purge_stale_guest_items(max_age, BATCH_SIZE).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "Stale guest cart lines removed"),
+                Err(err) => tracing::error!("Guest cart cleanup failed: {}", err),
+            }
+        }
+    })
+}
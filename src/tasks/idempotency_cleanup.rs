@@ -0,0 +1,25 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::repositories::IdempotencyRepository;
+
+const BATCH_SIZE: i64 = 1000;
+
+/// Periodically deletes idempotency keys whose stored response has expired.
+pub fn spawn(pool: PgPool, interval: Duration) -> JoinHandle<()> {
+    let keys = IdempotencyRepository::new(pool);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match keys.delete_expired(BATCH_SIZE).await {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(removed, "Expired idempotency keys removed"),
+                Err(err) => tracing::error!("Idempotency key cleanup failed: {}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,12 @@
+pub mod analytics_rollup;
+pub mod cart_expiry;
+pub mod guest_cart_cleanup;
+pub mod idempotency_cleanup;
+pub mod notification_delivery;
+pub mod recommendation_refresh;
+pub mod reservation_expiry;
+pub mod secret_refresh;
+pub mod store_cleanup;
+pub mod tax_commit;
+pub mod unpaid_order_expiry;
+pub mod webhook_delivery;
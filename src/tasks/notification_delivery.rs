@@ -0,0 +1,32 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    notifier::Notifier, repositories::NotificationRepository, services::NotificationService,
+};
+
+const BATCH_SIZE: i64 = 100;
+
+/// Periodically sends queued email and webhook notifications.
+pub fn spawn(pool: PgPool, notifier: Arc<dyn Notifier>, interval: Duration) -> JoinHandle<()> {
+    let service = NotificationService::new(NotificationRepository::new(pool));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.deliver_due(notifier.as_ref(), BATCH_SIZE).await {
+                Ok(run) if run == Default::default() => {}
+                Ok(run) => tracing::info!(
+                    sent = run.sent,
+                    retrying = run.retrying,
+                    failed = run.failed,
+                    "Notifications delivered"
+                ),
+                Err(err) => tracing::error!("Notification delivery failed: {}", err),
+            }
+        }
+    })
+}
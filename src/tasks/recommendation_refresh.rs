@@ -0,0 +1,23 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{repositories::RecommendationRepository, services::RecommendationService};
+
+/// Periodically rebuilds the "frequently bought together" pairs. Runs once
+/// on startup, then every `interval` (a day by default).
+pub fn spawn(pool: PgPool, interval: Duration) -> JoinHandle<()> {
+    let service = RecommendationService::new(RecommendationRepository::new(pool));
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.refresh().await {
+                Ok(pairs) => tracing::info!(pairs, "Product recommendations refreshed"),
+                Err(err) => tracing::error!("Recommendation refresh failed: {}", err),
+            }
+        }
+    })
+}
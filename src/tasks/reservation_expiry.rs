@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::repositories::CartRepository;
+
+const BATCH_SIZE: i64 = 500;
+
+/// Periodically deletes stock reservations whose TTL has run out. Lapsed
+/// reservations stop holding stock as soon as they expire; this keeps the
+/// table from growing with abandoned carts.
+pub fn spawn(pool: PgPool, interval: Duration) -> JoinHandle<()> {
+    let carts = CartRepository::new(pool);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match carts.release_expired(BATCH_SIZE).await {
+                Ok(0) => {}
+                Ok(released) => {
+                    tracing::info!(released, "Expired stock reservations released")
+                }
+                Err(err) => tracing::error!("Reservation expiry failed: {}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,23 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::task::JoinHandle;
+
+use crate::config::secrets::SecretStore;
+
+/// Periodically re-reads secrets from the backend, running the rotation hooks
+/// of any that changed. A failed read keeps the current values.
+pub fn spawn(secrets: Arc<SecretStore>, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        // The first tick fires immediately; secrets were just loaded.
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            match secrets.refresh().await {
+                Ok(rotated) if rotated.is_empty() => {}
+                Ok(rotated) => tracing::info!(secrets = ?rotated, "Secrets rotated"),
+                Err(err) => tracing::error!("Secret refresh failed: {:#}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    repositories::{CartRepository, NotificationRepository, ProductRepository, StoreRepository},
+    services::StoreLifecycleService,
+};
+
+const BATCH_SIZE: i64 = 50;
+
+/// Periodically runs the cleanup pipeline for stores that were closed since
+/// the last tick.
+pub fn spawn(pool: PgPool, interval: Duration) -> JoinHandle<()> {
+    let service = StoreLifecycleService::new(
+        StoreRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+        NotificationRepository::new(pool),
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.cleanup_closed_stores(BATCH_SIZE).await {
+                Ok(cleanups) => {
+                    for cleanup in cleanups {
+                        tracing::info!(
+                            store_id = %cleanup.store_id,
+                            products = cleanup.products_deactivated,
+                            cart_items = cleanup.cart_items_removed,
+                            customers = cleanup.customers_notified,
+                            "Closed store cleaned up"
+                        );
+                    }
+                }
+                Err(err) => tracing::error!("Store cleanup failed: {}", err),
+            }
+        }
+    })
+}
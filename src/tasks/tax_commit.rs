@@ -0,0 +1,30 @@
+use std::{sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{repositories::TaxRepository, services::TaxService, tax::TaxProvider};
+
+const BATCH_SIZE: i64 = 100;
+
+/// Periodically reports placed orders to the external tax provider.
+pub fn spawn(pool: PgPool, provider: Arc<dyn TaxProvider>, interval: Duration) -> JoinHandle<()> {
+    let service = TaxService::new(TaxRepository::new(pool), provider);
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.commit_due(BATCH_SIZE).await {
+                Ok(run) if run == Default::default() => {}
+                Ok(run) => tracing::info!(
+                    committed = run.committed,
+                    retrying = run.retrying,
+                    failed = run.failed,
+                    "Tax transactions committed"
+                ),
+                Err(err) => tracing::error!("Tax transaction commit failed: {}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,41 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    repositories::{NotificationRepository, OrderRepository, PaymentRepository},
+    services::UnpaidOrderService,
+};
+
+const BATCH_SIZE: i64 = 100;
+
+/// Periodically cancels order groups left unpaid for longer than `max_age`,
+/// putting their stock back on sale.
+pub fn spawn(pool: PgPool, max_age: chrono::Duration, interval: Duration) -> JoinHandle<()> {
+    let service = UnpaidOrderService::new(
+        OrderRepository::new(pool.clone()),
+        PaymentRepository::new(pool.clone()),
+        NotificationRepository::new(pool),
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.expire_unpaid_groups(max_age, BATCH_SIZE).await {
+                Ok(expired) => {
+                    for group in expired {
+                        tracing::info!(
+                            order_group_id = %group.order_group_id,
+                            orders = group.orders_cancelled,
+                            units = group.units_restored,
+                            "Unpaid order group cancelled"
+                        );
+                    }
+                }
+                Err(err) => tracing::error!("Unpaid order expiry failed: {}", err),
+            }
+        }
+    })
+}
@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+use crate::{
+    repositories::{StoreRepository, WebhookRepository},
+    services::WebhookService,
+    webhooks::WebhookSender,
+};
+
+const BATCH_SIZE: i64 = 50;
+
+/// Periodically sends queued store webhook deliveries.
+pub fn spawn(pool: PgPool, sender: WebhookSender, interval: Duration) -> JoinHandle<()> {
+    let service = WebhookService::new(
+        WebhookRepository::new(pool.clone()),
+        StoreRepository::new(pool),
+    );
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match service.deliver_due(&sender, BATCH_SIZE).await {
+                Ok(run) if run == Default::default() => {}
+                Ok(run) => tracing::info!(
+                    sent = run.sent,
+                    retrying = run.retrying,
+                    failed = run.failed,
+                    "Webhooks delivered"
+                ),
+                Err(err) => tracing::error!("Webhook delivery failed: {}", err),
+            }
+        }
+    })
+}
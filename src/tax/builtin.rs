@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use super::{TaxLookup, TaxProvider};
+use crate::models::tax::TaxFiling;
+
+/// Charges the flat `tax_rate` from the store's settings. Nothing is filed
+/// on the store's behalf, so committing only echoes the order number.
+pub struct BuiltinTaxProvider;
+
+#[async_trait]
+impl TaxProvider for BuiltinTaxProvider {
+    async fn rate(&self, lookup: &TaxLookup<'_>) -> crate::Result<Decimal> {
+        Ok(lookup.settings.tax_rate)
+    }
+
+    async fn commit(&self, filing: &TaxFiling) -> crate::Result<String> {
+        Ok(filing.order_number.clone())
+    }
+}
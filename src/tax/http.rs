@@ -0,0 +1,176 @@
+use std::{sync::RwLock, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{postal_code, TaxLookup, TaxProvider};
+use crate::{error::AppError, models::tax::TaxFiling};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client for a hosted sales tax API in the style of TaxJar or Avalara:
+/// `POST {base_url}/taxes` quotes a rate for a destination and
+/// `POST {base_url}/transactions/orders` records a sale for filing.
+pub struct HttpTaxProvider {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: RwLock<String>,
+}
+
+impl HttpTaxProvider {
+    pub fn new(base_url: impl Into<String>, api_key: impl Into<String>) -> crate::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|err| AppError::Internal(err.into()))?;
+        Ok(Self {
+            client,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            api_key: RwLock::new(api_key.into()),
+        })
+    }
+
+    /// Swaps the API key for subsequent requests, e.g. after a rotation.
+    pub fn set_api_key(&self, api_key: &str) {
+        *self.api_key.write().unwrap() = api_key.to_string();
+    }
+
+    async fn post<B: Serialize, R: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> crate::Result<R> {
+        let api_key = self.api_key.read().unwrap().clone();
+        self.client
+            .post(format!("{}{}", self.base_url, path))
+            .bearer_auth(api_key)
+            .json(body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| AppError::Internal(err.into()))?
+            .json::<R>()
+            .await
+            .map_err(|err| AppError::Internal(err.into()))
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct Destination<'a> {
+    to_country: Option<&'a str>,
+    to_state: Option<&'a str>,
+    to_zip: Option<&'a str>,
+    to_city: Option<&'a str>,
+}
+
+impl<'a> Destination<'a> {
+    fn from_address(address: &'a Value) -> Self {
+        let part = |key: &str| {
+            address
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+        };
+        Self {
+            to_country: part("country"),
+            to_state: part("state").or_else(|| part("region")),
+            to_zip: postal_code(address),
+            to_city: part("city"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RateRequest<'a> {
+    #[serde(flatten)]
+    destination: Destination<'a>,
+    amount: Decimal,
+    shipping: Decimal,
+}
+
+#[derive(Deserialize)]
+struct RateResponse {
+    tax: RateBody,
+}
+
+#[derive(Deserialize)]
+struct RateBody {
+    rate: Decimal,
+}
+
+#[derive(Serialize)]
+struct OrderTransaction<'a> {
+    transaction_id: &'a str,
+    transaction_date: DateTime<Utc>,
+    #[serde(flatten)]
+    destination: Destination<'a>,
+    amount: Decimal,
+    shipping: Decimal,
+    sales_tax: Decimal,
+}
+
+#[derive(Deserialize)]
+struct OrderTransactionResponse {
+    order: OrderTransactionBody,
+}
+
+#[derive(Deserialize)]
+struct OrderTransactionBody {
+    transaction_id: String,
+}
+
+#[async_trait]
+impl TaxProvider for HttpTaxProvider {
+    async fn rate(&self, lookup: &TaxLookup<'_>) -> crate::Result<Decimal> {
+        let request = RateRequest {
+            destination: Destination::from_address(lookup.shipping_address),
+            amount: lookup.amount,
+            shipping: Decimal::ZERO,
+        };
+        let response: RateResponse = self.post("/taxes", &request).await?;
+        Ok(response.tax.rate)
+    }
+
+    async fn commit(&self, filing: &TaxFiling) -> crate::Result<String> {
+        let transaction = OrderTransaction {
+            transaction_id: &filing.order_number,
+            transaction_date: filing.ordered_at,
+            destination: Destination::from_address(&filing.shipping_address),
+            amount: filing.subtotal + filing.shipping_cost,
+            shipping: filing.shipping_cost,
+            sales_tax: filing.tax_amount,
+        };
+        let response: OrderTransactionResponse =
+            self.post("/transactions/orders", &transaction).await?;
+        Ok(response.order.transaction_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn destination_reads_common_address_keys() {
+        let address = json!({
+            "country": "US",
+            "region": " CA ",
+            "postal_code": "94105",
+            "city": "San Francisco",
+        });
+        assert_eq!(
+            Destination::from_address(&address),
+            Destination {
+                to_country: Some("US"),
+                to_state: Some("CA"),
+                to_zip: Some("94105"),
+                to_city: Some("San Francisco"),
+            }
+        );
+    }
+}
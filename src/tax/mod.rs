@@ -0,0 +1,281 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use crate::{
+    metrics::SharedMetrics,
+    models::{
+        order::tax_jurisdiction,
+        store::{StoreSettings, TaxProviderKind},
+        tax::TaxFiling,
+    },
+    utils::breaker::{BreakerConfig, CircuitBreaker},
+};
+
+pub mod builtin;
+pub mod http;
+
+pub use builtin::BuiltinTaxProvider;
+pub use http::HttpTaxProvider;
+
+/// The sale a tax rate is requested for.
+pub struct TaxLookup<'a> {
+    pub settings: &'a StoreSettings,
+    pub shipping_address: &'a Value,
+    pub amount: Decimal,
+}
+
+/// Source of tax rates for checkout and sink for completed sales.
+#[async_trait]
+pub trait TaxProvider: Send + Sync {
+    /// Combined rate for the lookup's destination, e.g. `0.0825`.
+    async fn rate(&self, lookup: &TaxLookup<'_>) -> crate::Result<Decimal>;
+
+    /// Reports a completed sale for filing and returns the provider's
+    /// reference for it.
+    async fn commit(&self, filing: &TaxFiling) -> crate::Result<String>;
+}
+
+/// Picks the provider each store is configured for. Lookups never fail a
+/// checkout: when the external provider errors or is unavailable, the
+/// store's own rate applies. External rates are cached per store and
+/// destination since they do not depend on the amount.
+pub struct TaxRates {
+    builtin: BuiltinTaxProvider,
+    external: Option<Arc<dyn TaxProvider>>,
+    breaker: CircuitBreaker,
+    cache: RateCache,
+}
+
+impl TaxRates {
+    /// Only built-in rates; stores set to `External` fall back to them.
+    pub fn builtin() -> Self {
+        Self {
+            builtin: BuiltinTaxProvider,
+            external: None,
+            breaker: CircuitBreaker::new("tax_provider", BreakerConfig::default()),
+            cache: RateCache::new(Duration::ZERO),
+        }
+    }
+
+    pub fn with_external(mut self, provider: Arc<dyn TaxProvider>, cache_ttl: Duration) -> Self {
+        self.external = Some(provider);
+        self.cache = RateCache::new(cache_ttl);
+        self
+    }
+
+    pub fn with_breaker(mut self, config: BreakerConfig) -> Self {
+        self.breaker = CircuitBreaker::new(self.breaker.target(), config);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        self.breaker = self.breaker.with_metrics(metrics);
+        self
+    }
+
+    /// The external provider, when one is configured.
+    pub fn external(&self) -> Option<&Arc<dyn TaxProvider>> {
+        self.external.as_ref()
+    }
+
+    /// Whether sales of a store with these settings are reported for filing.
+    pub fn files_for(&self, settings: &StoreSettings) -> bool {
+        settings.tax_provider == TaxProviderKind::External && self.external.is_some()
+    }
+
+    pub async fn rate(&self, lookup: &TaxLookup<'_>) -> Decimal {
+        let fallback = || lookup.settings.tax_rate;
+        let external = match (&self.external, lookup.settings.tax_provider) {
+            (Some(external), TaxProviderKind::External) => external,
+            _ => {
+                return self
+                    .builtin
+                    .rate(lookup)
+                    .await
+                    .unwrap_or_else(|_| fallback())
+            }
+        };
+
+        let key = cache_key(lookup);
+        if let Some(rate) = self.cache.get(&key) {
+            return rate;
+        }
+
+        match self.breaker.call(external.rate(lookup)).await {
+            Ok(rate) => {
+                self.cache.insert(key, rate);
+                rate
+            }
+            Err(err) => {
+                tracing::warn!(
+                    store_id = %lookup.settings.store_id,
+                    "Tax provider lookup failed, using store rate: {}",
+                    err
+                );
+                fallback()
+            }
+        }
+    }
+}
+
+impl Default for TaxRates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Postal code of a shipping address, accepting the common key spellings.
+pub fn postal_code(address: &Value) -> Option<&str> {
+    ["postal_code", "zip", "postcode"]
+        .iter()
+        .find_map(|key| address.get(*key).and_then(Value::as_str))
+        .map(str::trim)
+        .filter(|code| !code.is_empty())
+}
+
+fn cache_key(lookup: &TaxLookup<'_>) -> String {
+    format!(
+        "{}:{}:{}",
+        lookup.settings.store_id,
+        tax_jurisdiction(lookup.shipping_address),
+        postal_code(lookup.shipping_address)
+            .unwrap_or_default()
+            .to_uppercase()
+    )
+}
+
+const RATE_CACHE_CAPACITY: usize = 10_000;
+
+struct RateCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Instant, Decimal)>>,
+}
+
+impl RateCache {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<Decimal> {
+        let entries = self.entries.lock().expect("tax rate cache poisoned");
+        entries
+            .get(key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, rate)| *rate)
+    }
+
+    fn insert(&self, key: String, rate: Decimal) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().expect("tax rate cache poisoned");
+        if entries.len() >= RATE_CACHE_CAPACITY {
+            entries.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+            if entries.len() >= RATE_CACHE_CAPACITY {
+                entries.clear();
+            }
+        }
+        entries.insert(key, (Instant::now(), rate));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AppError;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    struct CountingProvider {
+        calls: AtomicUsize,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl TaxProvider for CountingProvider {
+        async fn rate(&self, _lookup: &TaxLookup<'_>) -> crate::Result<Decimal> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.fail {
+                return Err(AppError::BadRequest("provider down".into()));
+            }
+            Ok(Decimal::new(725, 4))
+        }
+
+        async fn commit(&self, filing: &TaxFiling) -> crate::Result<String> {
+            Ok(filing.order_number.clone())
+        }
+    }
+
+    fn rates(fail: bool) -> (TaxRates, Arc<CountingProvider>) {
+        let provider = Arc::new(CountingProvider {
+            calls: AtomicUsize::new(0),
+            fail,
+        });
+        let rates = TaxRates::builtin().with_external(provider.clone(), Duration::from_secs(60));
+        (rates, provider)
+    }
+
+    fn settings(tax_provider: TaxProviderKind) -> StoreSettings {
+        let mut settings = StoreSettings::defaults(Uuid::new_v4());
+        settings.tax_rate = Decimal::new(5, 2);
+        settings.tax_provider = tax_provider;
+        settings
+    }
+
+    #[tokio::test]
+    async fn external_rates_are_cached_per_destination() {
+        let (rates, provider) = rates(false);
+        let settings = settings(TaxProviderKind::External);
+        let texas = json!({ "country": "us", "state": "tx", "zip": "73301" });
+        let lookup = |address| TaxLookup {
+            settings: &settings,
+            shipping_address: address,
+            amount: Decimal::new(100, 0),
+        };
+
+        assert_eq!(rates.rate(&lookup(&texas)).await, Decimal::new(725, 4));
+        assert_eq!(rates.rate(&lookup(&texas)).await, Decimal::new(725, 4));
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+
+        let other = json!({ "country": "US", "state": "TX", "zip": "75001" });
+        rates.rate(&lookup(&other)).await;
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn builtin_stores_and_failures_use_the_store_rate() {
+        let (rates, provider) = rates(true);
+        let address = json!({ "country": "US" });
+
+        let builtin = settings(TaxProviderKind::Builtin);
+        let lookup = TaxLookup {
+            settings: &builtin,
+            shipping_address: &address,
+            amount: Decimal::ONE,
+        };
+        assert_eq!(rates.rate(&lookup).await, Decimal::new(5, 2));
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 0);
+
+        let external = settings(TaxProviderKind::External);
+        let lookup = TaxLookup {
+            settings: &external,
+            shipping_address: &address,
+            amount: Decimal::ONE,
+        };
+        assert_eq!(rates.rate(&lookup).await, Decimal::new(5, 2));
+        assert_eq!(provider.calls.load(Ordering::SeqCst), 1);
+        assert!(rates.files_for(&external));
+        assert!(!TaxRates::builtin().files_for(&external));
+    }
+}
@@ -0,0 +1,250 @@
+//! Runs the real HTTP application on an ephemeral port for end-to-end tests.
+//!
+//! ```ignore
+//! #[sqlx::test(migrations = "./migrations")]
+//! async fn shopper_sees_products(pool: PgPool) {
+//!     let app = TestApp::spawn(pool).await;
+//!     let owner = app.register("owner@example.com").await;
+//!     let store = app.create_store(&owner, "corner-shop").await;
+//!     let response = app.get(&format!("/api/v1/products/store/{}", store.id)).send().await.unwrap();
+//!     assert!(response.status().is_success());
+//! }
+//! ```
+//!
+//! Helpers panic on unexpected responses, as test code would.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use reqwest::{Method, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::{
+    handlers,
+    metrics::Metrics,
+    middleware::{access::EndpointAccess, security_headers::SecurityHeaders},
+    models::{
+        product::{CreateProductRequest, Product, ProductType},
+        store::{CreateStoreRequest, Store},
+        user::AuthTokenResponse,
+        ApiResponse,
+    },
+    server,
+    state::AppState,
+    utils::jwt::JwtConfig,
+};
+
+pub const TEST_JWT_SECRET: &str = "test-secret";
+/// Password of every user created through [`TestApp::register`].
+pub const TEST_PASSWORD: &str = "SuperSecure123!";
+
+/// Application state with defaults suited to tests, signing tokens with
+/// [`TEST_JWT_SECRET`]. Adjust it with the `AppState::with_*` builders
+/// before passing it to [`TestApp::spawn_with`].
+pub fn build_state(pool: PgPool) -> AppState {
+    AppState::new(
+        pool,
+        JwtConfig::new(TEST_JWT_SECRET, 24),
+        Arc::new(Metrics::default()),
+    )
+}
+
+/// A signed-in user and the bearer token to send with their requests.
+#[derive(Debug, Clone)]
+pub struct TestUser {
+    pub id: Uuid,
+    pub email: String,
+    pub token: String,
+}
+
+/// The full app (API, `/metrics`, middleware) served on `127.0.0.1`. The
+/// server stops when the handle is dropped.
+pub struct TestApp {
+    pub addr: SocketAddr,
+    pub state: AppState,
+    client: reqwest::Client,
+    server: JoinHandle<()>,
+}
+
+impl TestApp {
+    pub async fn spawn(pool: PgPool) -> Self {
+        Self::spawn_with(build_state(pool)).await
+    }
+
+    pub async fn spawn_with(state: AppState) -> Self {
+        let routes = handlers::api_router(EndpointAccess::new())
+            .merge(handlers::metrics_router(EndpointAccess::new()))
+            .with_state(state.clone());
+        let app = server::with_middleware(
+            routes,
+            state.clone(),
+            "./uploads",
+            1024,
+            SecurityHeaders::default(),
+        );
+
+        let listener = tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], 0)))
+            .await
+            .expect("test listener should bind");
+        let addr = listener.local_addr().expect("listener has an address");
+        let server = tokio::spawn(async move {
+            axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .expect("test server should run");
+        });
+
+        Self {
+            addr,
+            state,
+            client: reqwest::Client::new(),
+            server,
+        }
+    }
+
+    pub fn db(&self) -> &PgPool {
+        &self.state.db
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client.request(method, self.url(path))
+    }
+
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.request(Method::GET, path)
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.request(Method::POST, path)
+    }
+
+    pub fn put(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PUT, path)
+    }
+
+    pub fn patch(&self, path: &str) -> RequestBuilder {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: &str) -> RequestBuilder {
+        self.request(Method::DELETE, path)
+    }
+
+    /// Registers a user through `/api/v1/auth/register` with [`TEST_PASSWORD`].
+    pub async fn register(&self, email: &str) -> TestUser {
+        let response = self
+            .post("/api/v1/auth/register")
+            .json(&json!({
+                "email": email,
+                "password": TEST_PASSWORD,
+                "full_name": "Test User",
+            }))
+            .send()
+            .await
+            .expect("register request should send");
+        let auth: AuthTokenResponse = data(response).await;
+        TestUser {
+            id: auth.user.id,
+            email: auth.user.email,
+            token: auth.token,
+        }
+    }
+
+    /// Grants platform admin rights directly in the database; there is no
+    /// route for it.
+    pub async fn promote_to_platform_admin(&self, user: &TestUser) {
+        sqlx::query("UPDATE users SET is_platform_admin = true WHERE id = $1")
+            .bind(user.id)
+            .execute(self.db())
+            .await
+            .expect("admin promotion should succeed");
+    }
+
+    pub async fn create_store(&self, owner: &TestUser, slug: &str) -> Store {
+        let payload = CreateStoreRequest {
+            name: format!("{} Store", slug.replace('-', " ")),
+            slug: slug.to_string(),
+            description: Some("A test store".into()),
+            logo_url: None,
+            is_private: false,
+        };
+        let response = self
+            .post("/api/v1/stores")
+            .bearer_auth(&owner.token)
+            .json(&payload)
+            .send()
+            .await
+            .expect("store request should send");
+        data(response).await
+    }
+
+    pub async fn create_product(
+        &self,
+        owner: &TestUser,
+        store_id: Uuid,
+        sku: &str,
+        price: f64,
+        stock_quantity: i32,
+    ) -> Product {
+        let payload = CreateProductRequest {
+            store_id,
+            sku: sku.to_string(),
+            name: format!("Product {}", sku),
+            description: None,
+            price,
+            stock_quantity,
+            category: None,
+            thumbnail_url: None,
+            product_type: ProductType::default(),
+            weight_grams: 0,
+        };
+        let response = self
+            .post("/api/v1/products")
+            .bearer_auth(&owner.token)
+            .json(&payload)
+            .send()
+            .await
+            .expect("product request should send");
+        data(response).await
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Unwraps the `data` of a successful `ApiResponse`, panicking with the
+/// status and body otherwise.
+pub async fn data<T: DeserializeOwned>(response: Response) -> T {
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or(Value::Null);
+    assert!(
+        status.is_success(),
+        "expected a successful response, got {}: {}",
+        status,
+        body
+    );
+    serde_json::from_value::<ApiResponse<T>>(body)
+        .expect("response should match the expected type")
+        .data
+}
+
+/// The `error.code` of a failed response, e.g. `AUTHENTICATION_ERROR`.
+pub async fn error_code(response: Response) -> String {
+    let body: Value = response.json().await.expect("error body should be JSON");
+    body["error"]["code"]
+        .as_str()
+        .unwrap_or_else(|| panic!("no error code in {}", body))
+        .to_string()
+}
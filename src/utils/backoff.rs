@@ -0,0 +1,33 @@
+use chrono::Duration;
+
+/// Delay before retrying after the `attempts`-th failed attempt at an
+/// outbound delivery: 1, 4, 9, 16... minutes.
+pub fn retry_delay(attempts: i32) -> Duration {
+    let attempts = i64::from(attempts.max(1));
+    Duration::minutes(attempts * attempts)
+}
+
+/// Delay before retrying after the `attempts`-th failed attempt at a
+/// webhook delivery: 30 seconds, doubling each time up to six hours.
+pub fn exponential_delay(attempts: i32) -> Duration {
+    let doublings = attempts.clamp(1, 16) - 1;
+    Duration::seconds(30 << doublings).min(Duration::hours(6))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_grows_quadratically() {
+        assert_eq!(retry_delay(1), Duration::minutes(1));
+        assert_eq!(retry_delay(3), Duration::minutes(9));
+    }
+
+    #[test]
+    fn exponential_delay_doubles_up_to_a_cap() {
+        assert_eq!(exponential_delay(1), Duration::seconds(30));
+        assert_eq!(exponential_delay(4), Duration::minutes(4));
+        assert_eq!(exponential_delay(30), Duration::hours(6));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use thiserror::Error;
+
+use crate::{error::AppError, metrics::SharedMetrics};
+
+/// Thresholds for one outbound target (payment provider, mail relay, webhook
+/// endpoint, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerConfig {
+    /// Consecutive failures that trip the breaker open.
+    pub failure_threshold: u32,
+    /// How long an open breaker rejects calls before letting a probe through.
+    pub open_for: Duration,
+    /// Calls running longer than this count as failures.
+    pub call_timeout: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_for: Duration::from_secs(30),
+            call_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl BreakerState {
+    fn gauge_value(self) -> i64 {
+        match self {
+            BreakerState::Closed => 0,
+            BreakerState::Open => 1,
+            BreakerState::HalfOpen => 2,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BreakerError<E> {
+    #[error("{0} is temporarily unavailable")]
+    Open(String),
+
+    #[error("{0} did not respond in time")]
+    Timeout(String),
+
+    #[error("{0}")]
+    Inner(E),
+}
+
+impl From<BreakerError<AppError>> for AppError {
+    fn from(err: BreakerError<AppError>) -> Self {
+        match err {
+            BreakerError::Inner(err) => err,
+            other => AppError::Internal(anyhow::anyhow!(other.to_string())),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Circuit {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// A single probe is in flight; it is considered abandoned once it has
+    /// run past the call timeout.
+    HalfOpen {
+        probe_started: Instant,
+    },
+}
+
+/// Fails fast on a target that keeps erroring or timing out, so callers are
+/// not held up waiting on it. Clones share state.
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    target: Arc<str>,
+    config: BreakerConfig,
+    circuit: Arc<Mutex<Circuit>>,
+    metrics: Option<SharedMetrics>,
+}
+
+impl CircuitBreaker {
+    pub fn new(target: &str, config: BreakerConfig) -> Self {
+        Self {
+            target: target.into(),
+            config,
+            circuit: Arc::new(Mutex::new(Circuit::Closed { failures: 0 })),
+            metrics: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: SharedMetrics) -> Self {
+        metrics.set_breaker_state(&self.target, BreakerState::Closed.gauge_value());
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn state(&self) -> BreakerState {
+        match *self.lock() {
+            Circuit::Closed { .. } => BreakerState::Closed,
+            Circuit::Open { .. } => BreakerState::Open,
+            Circuit::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    /// Runs `call` unless the breaker is open, bounding it by the configured
+    /// timeout. Errors and timeouts count towards tripping the breaker.
+    pub async fn call<T, E, F>(&self, call: F) -> Result<T, BreakerError<E>>
+    where
+        F: Future<Output = Result<T, E>>,
+    {
+        if !self.try_acquire() {
+            self.record("rejected");
+            return Err(BreakerError::Open(self.target.to_string()));
+        }
+
+        match tokio::time::timeout(self.config.call_timeout, call).await {
+            Ok(Ok(value)) => {
+                self.on_success();
+                self.record("success");
+                Ok(value)
+            }
+            Ok(Err(err)) => {
+                self.on_failure();
+                self.record("failure");
+                Err(BreakerError::Inner(err))
+            }
+            Err(_) => {
+                self.on_failure();
+                self.record("timeout");
+                Err(BreakerError::Timeout(self.target.to_string()))
+            }
+        }
+    }
+
+    fn try_acquire(&self) -> bool {
+        let now = Instant::now();
+        let mut circuit = self.lock();
+        match *circuit {
+            Circuit::Closed { .. } => true,
+            Circuit::Open { until } if now < until => false,
+            Circuit::HalfOpen { probe_started }
+                if now.duration_since(probe_started) < self.config.call_timeout =>
+            {
+                false
+            }
+            Circuit::Open { .. } | Circuit::HalfOpen { .. } => {
+                *circuit = Circuit::HalfOpen { probe_started: now };
+                self.publish(BreakerState::HalfOpen);
+                true
+            }
+        }
+    }
+
+    fn on_success(&self) {
+        let mut circuit = self.lock();
+        if !matches!(*circuit, Circuit::Closed { .. }) {
+            tracing::info!(target_name = %self.target, "Circuit breaker closed");
+            self.publish(BreakerState::Closed);
+        }
+        *circuit = Circuit::Closed { failures: 0 };
+    }
+
+    fn on_failure(&self) {
+        let mut circuit = self.lock();
+        let failures = match *circuit {
+            Circuit::Closed { failures } => failures + 1,
+            // A failed probe, or a late failure from before the trip.
+            Circuit::HalfOpen { .. } | Circuit::Open { .. } => self.config.failure_threshold,
+        };
+
+        if failures < self.config.failure_threshold {
+            *circuit = Circuit::Closed { failures };
+            return;
+        }
+        if !matches!(*circuit, Circuit::Open { .. }) {
+            tracing::warn!(
+                target_name = %self.target,
+                failures,
+                "Circuit breaker opened"
+            );
+            self.publish(BreakerState::Open);
+        }
+        *circuit = Circuit::Open {
+            until: Instant::now() + self.config.open_for,
+        };
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit.lock().expect("circuit breaker poisoned")
+    }
+
+    fn publish(&self, state: BreakerState) {
+        if let Some(metrics) = &self.metrics {
+            metrics.set_breaker_state(&self.target, state.gauge_value());
+        }
+    }
+
+    fn record(&self, outcome: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.observe_breaker_call(&self.target, outcome);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(open_for: Duration) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "payments",
+            BreakerConfig {
+                failure_threshold: 2,
+                open_for,
+                call_timeout: Duration::from_millis(50),
+            },
+        )
+    }
+
+    async fn fail(breaker: &CircuitBreaker) -> Result<(), BreakerError<&'static str>> {
+        breaker.call(async { Err::<(), _>("boom") }).await
+    }
+
+    #[tokio::test]
+    async fn opens_after_consecutive_failures_and_rejects() {
+        let breaker = breaker(Duration::from_secs(60));
+        assert!(matches!(
+            fail(&breaker).await,
+            Err(BreakerError::Inner("boom"))
+        ));
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        let rejected = breaker.call(async { Ok::<_, &str>(1) }).await;
+        assert!(matches!(rejected, Err(BreakerError::Open(_))));
+    }
+
+    #[tokio::test]
+    async fn success_resets_failure_count() {
+        let breaker = breaker(Duration::from_secs(60));
+        fail(&breaker).await.unwrap_err();
+        breaker.call(async { Ok::<_, &str>(()) }).await.unwrap();
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn half_open_probe_decides_recovery() {
+        let breaker = breaker(Duration::from_millis(10));
+        fail(&breaker).await.unwrap_err();
+        fail(&breaker).await.unwrap_err();
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(breaker.call(async { Ok::<_, &str>(7) }).await.unwrap(), 7);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[tokio::test]
+    async fn slow_calls_time_out_as_failures() {
+        let breaker = breaker(Duration::from_secs(60));
+        let slow = breaker
+            .call(async {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                Ok::<_, &str>(())
+            })
+            .await;
+        assert!(matches!(slow, Err(BreakerError::Timeout(_))));
+        fail(&breaker).await.unwrap_err();
+        assert_eq!(breaker.state(), BreakerState::Open);
+    }
+}
@@ -1,36 +1,91 @@
+use std::sync::{Arc, RwLock};
+
 use chrono::{Duration, Utc};
 use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Clone)]
-pub struct JwtConfig {
+#[derive(Clone)]
+struct SigningKeys {
     secret: String,
-    expiration: Duration,
     encoding: EncodingKey,
     decoding: DecodingKey,
+    /// The secret in use before the last rotation; tokens it signed keep
+    /// verifying until they expire or the secret rotates again.
+    previous: Option<DecodingKey>,
+}
+
+/// Clones share their keys, so a rotation is seen by every holder.
+#[derive(Clone)]
+pub struct JwtConfig {
+    keys: Arc<RwLock<SigningKeys>>,
+    expiration: Duration,
     validation: Validation,
 }
 
+impl std::fmt::Debug for JwtConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtConfig")
+            .field("expiration", &self.expiration)
+            .finish_non_exhaustive()
+    }
+}
+
 impl JwtConfig {
     pub fn new(secret: impl Into<String>, expiration_hours: i64) -> Self {
         let secret = secret.into();
-        let encoding = EncodingKey::from_secret(secret.as_bytes());
-        let decoding = DecodingKey::from_secret(secret.as_bytes());
         let mut validation = Validation::default();
         validation.validate_exp = true;
 
         Self {
-            secret,
+            keys: Arc::new(RwLock::new(SigningKeys {
+                encoding: EncodingKey::from_secret(secret.as_bytes()),
+                decoding: DecodingKey::from_secret(secret.as_bytes()),
+                previous: None,
+                secret,
+            })),
             expiration: Duration::hours(expiration_hours.max(1)),
-            encoding,
-            decoding,
             validation,
         }
     }
 
-    pub fn secret(&self) -> &str {
-        &self.secret
+    pub fn secret(&self) -> String {
+        self.keys.read().unwrap().secret.clone()
+    }
+
+    /// Signs with `secret` from now on while still accepting tokens signed
+    /// with the one it replaces, so sessions survive the rotation.
+    pub fn rotate(&self, secret: &str) {
+        let mut keys = self.keys.write().unwrap();
+        if keys.secret == secret {
+            return;
+        }
+        let previous = std::mem::replace(
+            &mut keys.decoding,
+            DecodingKey::from_secret(secret.as_bytes()),
+        );
+        keys.previous = Some(previous);
+        keys.encoding = EncodingKey::from_secret(secret.as_bytes());
+        keys.secret = secret.to_string();
+    }
+
+    fn decode<T: DeserializeOwned>(&self, token: &str) -> jsonwebtoken::errors::Result<T> {
+        let keys = self.keys.read().unwrap();
+        match jsonwebtoken::decode::<T>(token, &keys.decoding, &self.validation) {
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    jsonwebtoken::errors::ErrorKind::InvalidSignature
+                ) =>
+            {
+                match &keys.previous {
+                    Some(previous) => jsonwebtoken::decode::<T>(token, previous, &self.validation)
+                        .map(|data| data.claims),
+                    None => Err(err),
+                }
+            }
+            result => result.map(|data| data.claims),
+        }
     }
 
     pub fn expiration(&self) -> Duration {
@@ -38,23 +93,34 @@ impl JwtConfig {
     }
 
     pub fn generate(&self, claims: &Claims) -> jsonwebtoken::errors::Result<String> {
-        jsonwebtoken::encode(&Header::default(), claims, &self.encoding)
+        self.sign(claims)
+    }
+
+    /// Signs claims other than session tokens (e.g. download links) with the
+    /// same secret. Claim types must not be interchangeable with `Claims`.
+    pub fn sign<T: Serialize>(&self, claims: &T) -> jsonwebtoken::errors::Result<String> {
+        let keys = self.keys.read().unwrap();
+        jsonwebtoken::encode(&Header::default(), claims, &keys.encoding)
+    }
+
+    pub fn verify_as<T: DeserializeOwned>(&self, token: &str) -> jsonwebtoken::errors::Result<T> {
+        self.decode(token)
     }
 
-    pub fn claims_for(&self, user_id: Uuid, email: String) -> Claims {
+    pub fn claims_for(&self, user_id: Uuid, email: String, token_version: i32) -> Claims {
         let now = Utc::now();
         let exp = now + self.expiration;
         Claims {
             sub: user_id,
             email,
+            ver: token_version,
             iat: now.timestamp() as usize,
             exp: exp.timestamp() as usize,
         }
     }
 
     pub fn verify(&self, token: &str) -> jsonwebtoken::errors::Result<Claims> {
-        let token_data = jsonwebtoken::decode::<Claims>(token, &self.decoding, &self.validation)?;
-        Ok(token_data.claims)
+        self.decode(token)
     }
 }
 
@@ -62,6 +128,10 @@ impl JwtConfig {
 pub struct Claims {
     pub sub: Uuid,
     pub email: String,
+    /// The user's `token_version` at issue time; tokens from an older
+    /// version are rejected.
+    #[serde(default)]
+    pub ver: i32,
     pub iat: usize,
     pub exp: usize,
 }
@@ -74,7 +144,7 @@ mod tests {
     fn generates_and_verifies_tokens() {
         let config = JwtConfig::new("test-secret", 1);
         let user_id = Uuid::new_v4();
-        let claims = config.claims_for(user_id, "alice@example.com".into());
+        let claims = config.claims_for(user_id, "alice@example.com".into(), 3);
 
         let token = config.generate(&claims).expect("token should generate");
         assert!(!token.is_empty());
@@ -82,6 +152,25 @@ mod tests {
         let verified = config.verify(&token).expect("token should verify");
         assert_eq!(verified.sub, user_id);
         assert_eq!(verified.email, "alice@example.com");
+        assert_eq!(verified.ver, 3);
         assert!(verified.exp >= verified.iat);
     }
+
+    #[test]
+    fn rotation_keeps_previous_tokens_valid() {
+        let config = JwtConfig::new("old-secret", 1);
+        let shared = config.clone();
+        let claims = config.claims_for(Uuid::new_v4(), "bob@example.com".into(), 0);
+        let old_token = config.generate(&claims).unwrap();
+
+        shared.rotate("new-secret");
+        assert_eq!(config.secret(), "new-secret");
+        let new_token = config.generate(&claims).unwrap();
+        assert!(config.verify(&old_token).is_ok());
+        assert!(config.verify(&new_token).is_ok());
+
+        config.rotate("newest-secret");
+        assert!(config.verify(&old_token).is_err());
+        assert!(config.verify(&new_token).is_ok());
+    }
 }
@@ -1,3 +1,7 @@
+pub mod backoff;
+pub mod breaker;
 pub mod jwt;
 pub mod password;
+pub mod public_id;
+pub mod sigv4;
 pub mod validators;
@@ -0,0 +1,158 @@
+//! Opaque public references for records whose UUIDs stay internal, such as
+//! orders in confirmation links. A reference hides the UUID behind a keyed
+//! mask and carries a truncated HMAC tag, so references cannot be guessed,
+//! enumerated or forged without the secret, and reveal nothing about order
+//! volume or timing.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use uuid::Uuid;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const TAG_LEN: usize = 8;
+const ENCODED_LEN: usize = TAG_LEN + 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PublicIdKind {
+    Order,
+    OrderGroup,
+    GuestCart,
+}
+
+impl PublicIdKind {
+    const ALL: [PublicIdKind; 3] = [
+        PublicIdKind::Order,
+        PublicIdKind::OrderGroup,
+        PublicIdKind::GuestCart,
+    ];
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            PublicIdKind::Order => "ord_",
+            PublicIdKind::OrderGroup => "chk_",
+            PublicIdKind::GuestCart => "gst_",
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PublicIds {
+    secret: Vec<u8>,
+}
+
+impl PublicIds {
+    pub fn new(secret: impl AsRef<[u8]>) -> Self {
+        Self {
+            secret: secret.as_ref().to_vec(),
+        }
+    }
+
+    pub fn encode(&self, kind: PublicIdKind, id: Uuid) -> String {
+        let tag = self.tag(kind, id.as_bytes());
+        let mask = self.mask(kind, &tag);
+
+        let mut data = [0u8; ENCODED_LEN];
+        data[..TAG_LEN].copy_from_slice(&tag);
+        for (index, byte) in id.as_bytes().iter().enumerate() {
+            data[TAG_LEN + index] = byte ^ mask[index];
+        }
+        format!("{}{}", kind.prefix(), URL_SAFE_NO_PAD.encode(data))
+    }
+
+    /// Returns the kind and UUID behind a reference, or `None` for anything
+    /// this secret did not issue.
+    pub fn decode(&self, reference: &str) -> Option<(PublicIdKind, Uuid)> {
+        let kind = PublicIdKind::ALL
+            .into_iter()
+            .find(|kind| reference.starts_with(kind.prefix()))?;
+        let data = URL_SAFE_NO_PAD
+            .decode(&reference[kind.prefix().len()..])
+            .ok()?;
+        if data.len() != ENCODED_LEN {
+            return None;
+        }
+
+        let (tag, masked) = data.split_at(TAG_LEN);
+        let mask = self.mask(kind, tag);
+        let mut id = [0u8; 16];
+        for (index, byte) in masked.iter().enumerate() {
+            id[index] = byte ^ mask[index];
+        }
+
+        self.mac(&[b"tag", kind.prefix().as_bytes(), &id])
+            .verify_truncated_left(tag)
+            .ok()?;
+        Some((kind, Uuid::from_bytes(id)))
+    }
+
+    fn tag(&self, kind: PublicIdKind, id: &[u8]) -> [u8; TAG_LEN] {
+        let digest = self
+            .mac(&[b"tag", kind.prefix().as_bytes(), id])
+            .finalize()
+            .into_bytes();
+        let mut tag = [0u8; TAG_LEN];
+        tag.copy_from_slice(&digest[..TAG_LEN]);
+        tag
+    }
+
+    fn mask(&self, kind: PublicIdKind, tag: &[u8]) -> [u8; 16] {
+        let digest = self
+            .mac(&[b"mask", kind.prefix().as_bytes(), tag])
+            .finalize()
+            .into_bytes();
+        let mut mask = [0u8; 16];
+        mask.copy_from_slice(&digest[..16]);
+        mask
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        for part in parts {
+            mac.update(&(part.len() as u32).to_be_bytes());
+            mac.update(part);
+        }
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn references_round_trip_without_exposing_the_uuid() {
+        let ids = PublicIds::new("test-secret");
+        let id = Uuid::new_v4();
+
+        let reference = ids.encode(PublicIdKind::Order, id);
+        assert!(reference.starts_with("ord_"));
+        assert!(!reference.contains(&id.simple().to_string()));
+        assert_eq!(reference, ids.encode(PublicIdKind::Order, id));
+        assert_eq!(ids.decode(&reference), Some((PublicIdKind::Order, id)));
+
+        let group = ids.encode(PublicIdKind::OrderGroup, id);
+        assert_ne!(group[4..], reference[4..]);
+        assert_eq!(ids.decode(&group), Some((PublicIdKind::OrderGroup, id)));
+    }
+
+    #[test]
+    fn tampered_or_foreign_references_are_rejected() {
+        let ids = PublicIds::new("test-secret");
+        let reference = ids.encode(PublicIdKind::Order, Uuid::new_v4());
+
+        assert_eq!(PublicIds::new("other-secret").decode(&reference), None);
+        let swapped = reference.replacen("ord_", "chk_", 1);
+        assert_eq!(ids.decode(&swapped), None);
+
+        let mut tampered = reference.into_bytes();
+        let last = tampered.len() - 1;
+        tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+        assert_eq!(ids.decode(&String::from_utf8(tampered).unwrap()), None);
+
+        assert_eq!(ids.decode("ord_short"), None);
+        assert_eq!(ids.decode(&Uuid::new_v4().to_string()), None);
+    }
+}
@@ -14,6 +14,55 @@ pub fn validate_slug(value: &str) -> Result<(), ValidationError> {
     }
 }
 
+pub static CURRENCY_CODE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Z]{3}$").expect("Currency regex should compile"));
+
+pub fn validate_currency_code(value: &str) -> Result<(), ValidationError> {
+    if CURRENCY_CODE_REGEX.is_match(value) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_currency_code"))
+    }
+}
+
+pub static COUNTRY_CODE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Z]{2}$").expect("Country regex should compile"));
+
+pub fn validate_country_codes(values: &[String]) -> Result<(), ValidationError> {
+    if values
+        .iter()
+        .all(|value| COUNTRY_CODE_REGEX.is_match(value))
+    {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_country_code"))
+    }
+}
+
+pub static FIELD_KEY_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[a-z][a-z0-9_]*$").expect("Field key regex should compile"));
+
+pub fn validate_field_key(value: &str) -> Result<(), ValidationError> {
+    if FIELD_KEY_REGEX.is_match(value) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_field_key"))
+    }
+}
+
+/// Letters, digits, `-` and `_`; codes are matched case-insensitively.
+pub static COUPON_CODE_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[A-Za-z0-9][A-Za-z0-9_-]*$").expect("Coupon code regex should compile")
+});
+
+pub fn validate_coupon_code(value: &str) -> Result<(), ValidationError> {
+    if COUPON_CODE_REGEX.is_match(value) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("invalid_coupon_code"))
+    }
+}
+
 pub fn validate_shipping_address(value: &Value) -> Result<(), ValidationError> {
     if let Some(obj) = value.as_object() {
         if obj.is_empty() {
@@ -34,6 +83,28 @@ mod tests {
         assert!(!SLUG_REGEX.is_match("Invalid Slug"));
     }
 
+    #[test]
+    fn currency_code_validation() {
+        assert!(validate_currency_code("USD").is_ok());
+        assert!(validate_currency_code("usd").is_err());
+        assert!(validate_currency_code("EURO").is_err());
+    }
+
+    #[test]
+    fn country_code_validation() {
+        assert!(validate_country_codes(&["US".into(), "CA".into()]).is_ok());
+        assert!(validate_country_codes(&["us".into()]).is_err());
+        assert!(validate_country_codes(&["USA".into()]).is_err());
+    }
+
+    #[test]
+    fn coupon_code_validation() {
+        assert!(validate_coupon_code("SUMMER-10").is_ok());
+        assert!(validate_coupon_code("welcome_5").is_ok());
+        assert!(validate_coupon_code("-LEADING").is_err());
+        assert!(validate_coupon_code("TWO WORDS").is_err());
+    }
+
     #[test]
     fn shipping_address_validation() {
         let valid = serde_json::json!({"line1": "123 Main", "city": "NY"});
@@ -0,0 +1,504 @@
+//! Store webhooks: the outbox subscriber that queues deliveries and the
+//! signed HTTP requests that carry them. Receivers verify a delivery by
+//! recomputing `v1` over `"{t}.{body}"` with their webhook secret.
+//!
+//! Deliveries only go to public addresses: a webhook host is checked when it
+//! is registered and its addresses are checked again each time it is called,
+//! so it can't be pointed, or re-pointed by DNS, at our own network.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde_json::json;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::{
+    models::{
+        domain_event::{DomainEvent, PublishedEvent},
+        webhook::{DueWebhookDelivery, ORDER_CREATED, ORDER_PAID, PRODUCT_LOW_STOCK},
+    },
+    outbox::EventSubscriber,
+    repositories::WebhookRepository,
+    utils::breaker::{BreakerConfig, BreakerError, CircuitBreaker},
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_HEADER: &str = "X-Markethub-Signature";
+pub const EVENT_HEADER: &str = "X-Markethub-Event";
+/// The event id, the same on every retry of a delivery.
+pub const DELIVERY_HEADER: &str = "X-Markethub-Delivery";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How much of an endpoint's answer the delivery log keeps.
+const MAX_LOGGED_RESPONSE: usize = 1024;
+
+/// Signature header value for `body` sent at `timestamp` (Unix seconds).
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let digest: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("t={timestamp},v1={digest}")
+}
+
+/// A random secret for a webhook created without one.
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Whether deliveries may go to `ip`. Loopback, private, link-local, unique
+/// local, unspecified, broadcast and multicast addresses are refused, as are
+/// IPv4 addresses written as IPv6.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    let this_network = first == 0;
+    let shared = first == 100 && (second & 0xc0) == 64;
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || this_network
+        || shared)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    let unique_local = (first & 0xfe00) == 0xfc00;
+    let link_local = (first & 0xffc0) == 0xfe80;
+    !(ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || unique_local || link_local)
+}
+
+/// The addresses of `host`, failing unless every one of them is public.
+async fn public_addresses(host: &str, port: u16) -> anyhow::Result<Vec<SocketAddr>> {
+    let literal = host.trim_start_matches('[').trim_end_matches(']');
+    let addresses: Vec<SocketAddr> = match literal.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .with_context(|| format!("Could not resolve {host}"))?
+            .collect(),
+    };
+    if addresses.is_empty() {
+        bail!("Could not resolve {host}");
+    }
+    if addresses
+        .iter()
+        .any(|address| !is_public_address(address.ip()))
+    {
+        bail!("{host} is not a public address");
+    }
+    Ok(addresses)
+}
+
+/// Checks that a webhook URL's host resolves only to public addresses.
+pub async fn check_target(url: &str) -> anyhow::Result<()> {
+    let url = reqwest::Url::parse(url).context("Invalid webhook URL")?;
+    let host = url.host_str().context("Webhook URL has no host")?;
+    public_addresses(host, url.port_or_known_default().unwrap_or(443)).await?;
+    Ok(())
+}
+
+/// Resolves hosts for the delivery client, refusing any that resolve to a
+/// non-public address. Connections use the addresses checked here, so a
+/// host can't answer the check with one address and the request with another.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addresses = public_addresses(name.as_str(), 0).await?;
+            Ok(Box::new(addresses.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// The webhook event name and store of a domain event, for the events
+/// stores can subscribe to.
+fn webhook_event(event: &DomainEvent) -> Option<(&'static str, Uuid)> {
+    match event {
+        DomainEvent::OrderCreated { store_id, .. } => Some((ORDER_CREATED, *store_id)),
+        DomainEvent::OrderPaid { store_id, .. } => Some((ORDER_PAID, *store_id)),
+        DomainEvent::LowStock { store_id, .. } => Some((PRODUCT_LOW_STOCK, *store_id)),
+        DomainEvent::OrderStatusChanged { .. }
+        | DomainEvent::StockDepleted { .. }
+        | DomainEvent::MemberInvited { .. } => None,
+    }
+}
+
+/// Queues a delivery of each published event to the store's webhooks
+/// subscribed to it.
+#[derive(Clone)]
+pub struct WebhookSubscriber {
+    webhooks: WebhookRepository,
+}
+
+impl WebhookSubscriber {
+    pub fn new(webhooks: WebhookRepository) -> Self {
+        Self { webhooks }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for WebhookSubscriber {
+    async fn handle(&self, published: &PublishedEvent) -> anyhow::Result<()> {
+        let Some((event_type, store_id)) = webhook_event(&published.event) else {
+            return Ok(());
+        };
+        let mut event = serde_json::to_value(&published.event)?;
+        let payload = json!({
+            "id": published.id,
+            "type": event_type,
+            "created_at": published.occurred_at,
+            "data": event["data"].take(),
+        });
+        self.webhooks
+            .queue_deliveries(store_id, event_type, published.id, &payload)
+            .await?;
+        Ok(())
+    }
+}
+
+/// What an endpoint answered to a delivery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+impl WebhookResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+/// A delivery was not attempted because its endpoint host keeps failing.
+#[derive(Debug, thiserror::Error)]
+#[error("{0} is temporarily unavailable")]
+pub struct CircuitOpen(pub String);
+
+/// Why a send through an endpoint's breaker counts as a failure.
+enum Failure {
+    /// The endpoint answered with a server error.
+    Answered(WebhookResponse),
+    Unanswered(anyhow::Error),
+}
+
+/// POSTs deliveries to their endpoints. Redirects are not followed, so a
+/// delivery only ever reaches the URL the store registered, and only when
+/// that URL is at a public address. Each endpoint host has its own circuit
+/// breaker, so one that is down stops holding up the delivery task.
+#[derive(Clone)]
+pub struct WebhookSender {
+    client: reqwest::Client,
+    public_only: bool,
+    breaker_config: BreakerConfig,
+    breakers: Arc<Mutex<HashMap<String, CircuitBreaker>>>,
+}
+
+impl WebhookSender {
+    pub fn new() -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()?;
+        Ok(Self {
+            client,
+            public_only: true,
+            breaker_config: BreakerConfig::default(),
+            breakers: Arc::default(),
+        })
+    }
+
+    /// A sender that also delivers to private addresses, for endpoints
+    /// running on the local machine in development and tests.
+    pub fn allowing_private_addresses() -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        Ok(Self {
+            client,
+            public_only: false,
+            breaker_config: BreakerConfig::default(),
+            breakers: Arc::default(),
+        })
+    }
+
+    pub fn with_breaker(mut self, config: BreakerConfig) -> Self {
+        self.breaker_config = config;
+        self.breakers = Arc::default();
+        self
+    }
+
+    fn breaker(&self, host: &str) -> CircuitBreaker {
+        self.breakers
+            .lock()
+            .expect("webhook breakers poisoned")
+            .entry(host.to_string())
+            .or_insert_with(|| CircuitBreaker::new(host, self.breaker_config))
+            .clone()
+    }
+
+    /// Sends the delivery, signed now. Errors mean no answer was received;
+    /// [`CircuitOpen`] means it was not sent at all.
+    pub async fn send(&self, due: &DueWebhookDelivery) -> anyhow::Result<WebhookResponse> {
+        self.post(
+            &due.url,
+            &due.secret,
+            &due.delivery.event_type,
+            due.delivery.event_id,
+            &due.delivery.payload,
+        )
+        .await
+    }
+
+    /// POSTs `payload` to `url` as event `event_id`, signed now with
+    /// `secret`. Errors are those of [`WebhookSender::send`].
+    pub async fn post(
+        &self,
+        url: &str,
+        secret: &str,
+        event_type: &str,
+        event_id: Uuid,
+        payload: &serde_json::Value,
+    ) -> anyhow::Result<WebhookResponse> {
+        let url = reqwest::Url::parse(url).context("Invalid webhook URL")?;
+        let host = url.host_str().context("Webhook URL has no host")?;
+        // Hosts given as an IP address never reach the resolver
+        if self.public_only {
+            let literal = host.trim_start_matches('[').trim_end_matches(']');
+            if let Ok(ip) = literal.parse::<IpAddr>() {
+                if !is_public_address(ip) {
+                    bail!("{host} is not a public address");
+                }
+            }
+        }
+
+        let body = serde_json::to_vec(payload)?;
+        let signature = sign(secret, Utc::now().timestamp(), &body);
+        let request = self
+            .client
+            .post(url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(EVENT_HEADER, event_type)
+            .header(DELIVERY_HEADER, event_id.to_string())
+            .body(body);
+
+        // Server errors count against the host; other answers are the
+        // receiver telling us about this delivery.
+        let outcome = self
+            .breaker(host)
+            .call(async {
+                let response = Self::read(request).await.map_err(Failure::Unanswered)?;
+                if response.status >= 500 {
+                    return Err(Failure::Answered(response));
+                }
+                Ok(response)
+            })
+            .await;
+        match outcome {
+            Ok(response) | Err(BreakerError::Inner(Failure::Answered(response))) => Ok(response),
+            Err(BreakerError::Inner(Failure::Unanswered(err))) => Err(err),
+            Err(BreakerError::Open(host)) => Err(CircuitOpen(host).into()),
+            Err(BreakerError::Timeout(host)) => Err(anyhow!("{host} did not respond in time")),
+        }
+    }
+
+    async fn read(request: reqwest::RequestBuilder) -> anyhow::Result<WebhookResponse> {
+        let response = request.send().await.context("Webhook request failed")?;
+
+        // Only the start of the answer is kept, so only that much is read.
+        let status = response.status().as_u16();
+        let mut stream = response.bytes_stream();
+        let mut body = Vec::new();
+        while body.len() < MAX_LOGGED_RESPONSE {
+            match stream.next().await {
+                Some(Ok(chunk)) => body.extend_from_slice(&chunk),
+                _ => break,
+            }
+        }
+        body.truncate(MAX_LOGGED_RESPONSE);
+        Ok(WebhookResponse {
+            status,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{notification::DeliveryStatus, webhook::WebhookDelivery};
+
+    fn due_delivery() -> DueWebhookDelivery {
+        DueWebhookDelivery {
+            delivery: WebhookDelivery {
+                id: Uuid::new_v4(),
+                webhook_id: Uuid::new_v4(),
+                event_id: Uuid::new_v4(),
+                event_type: ORDER_PAID.into(),
+                payload: json!({}),
+                status: DeliveryStatus::Pending,
+                attempts: 1,
+                response_status: None,
+                response_body: None,
+                last_error: None,
+                next_attempt_at: Utc::now(),
+                created_at: Utc::now(),
+                delivered_at: None,
+            },
+            url: "https://example.com/hooks".into(),
+            secret: "whsec_test".into(),
+        }
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_body() {
+        let body = br#"{"type":"order.paid"}"#;
+        let signature = sign("whsec_test", 1_700_000_000, body);
+
+        assert_eq!(
+            signature,
+            "t=1700000000,v1=5c6bdef8bdbac73dcf3494e152130af82b8f9ae942a4bd4b019e9ccc921fccce"
+        );
+        assert_ne!(sign("whsec_test", 1_700_000_001, body), signature);
+        assert_ne!(sign("whsec_other", 1_700_000_000, body), signature);
+    }
+
+    #[test]
+    fn only_public_addresses_receive_deliveries() {
+        for address in ["93.184.215.14", "2606:4700::1111"] {
+            assert!(is_public_address(address.parse().unwrap()), "{address}");
+        }
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "::ffff:169.254.169.254",
+        ] {
+            assert!(!is_public_address(address.parse().unwrap()), "{address}");
+        }
+    }
+
+    #[tokio::test]
+    async fn webhook_urls_must_not_point_inside_our_network() {
+        for url in [
+            "https://127.0.0.1:8443/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://10.0.0.5/hooks",
+            "https://[::1]/hooks",
+            "https://[fd12::1]/hooks",
+            "https://[::ffff:192.168.0.1]/hooks",
+            "https://localhost/hooks",
+        ] {
+            assert!(check_target(url).await.is_err(), "{url}");
+        }
+        assert!(check_target("https://93.184.215.14/hooks").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn the_sender_refuses_private_addresses() {
+        let due = DueWebhookDelivery {
+            url: "https://169.254.169.254/latest/meta-data".into(),
+            ..due_delivery()
+        };
+        let error = WebhookSender::new().unwrap().send(&due).await.unwrap_err();
+        assert!(error.to_string().contains("not a public address"));
+
+        let due = DueWebhookDelivery {
+            url: "https://localhost:1/hooks".into(),
+            ..due
+        };
+        let error = WebhookSender::new().unwrap().send(&due).await.unwrap_err();
+        assert!(format!("{error:?}").contains("not a public address"));
+    }
+
+    #[tokio::test]
+    async fn failing_hosts_are_skipped_until_their_breaker_recovers() {
+        let sender = WebhookSender::allowing_private_addresses()
+            .unwrap()
+            .with_breaker(BreakerConfig {
+                failure_threshold: 1,
+                open_for: Duration::from_secs(60),
+                call_timeout: Duration::from_secs(5),
+            });
+        let due = |url: &str| {
+            let mut due = due_delivery();
+            due.url = url.into();
+            due
+        };
+
+        // Nothing listens on port 1.
+        let error = sender
+            .send(&due("http://127.0.0.1:1/hooks"))
+            .await
+            .unwrap_err();
+        assert!(!error.is::<CircuitOpen>());
+        let error = sender
+            .send(&due("http://127.0.0.1:1/hooks"))
+            .await
+            .unwrap_err();
+        assert!(error.is::<CircuitOpen>());
+
+        // Other hosts keep their own breaker.
+        let error = sender
+            .send(&due("http://localhost:1/hooks"))
+            .await
+            .unwrap_err();
+        assert!(!error.is::<CircuitOpen>());
+    }
+
+    #[test]
+    fn generated_secrets_are_unique() {
+        let secret = generate_secret();
+        assert!(secret.starts_with("whsec_"));
+        assert_ne!(generate_secret(), secret);
+    }
+}
@@ -1,8 +1,18 @@
+mod common;
+
 use chrono::{Duration, TimeZone, Utc};
 use markethub::{
-    models::order::PaymentStatus,
-    repositories::{AnalyticsRepository, StoreRepository},
-    services::analytics_service::AnalyticsService,
+    error::AppError,
+    models::{
+        event::{ClientEvent, ClientEventType, IngestEventsRequest},
+        order::PaymentStatus,
+        store::{AnalyticsComparison, StoreAnalyticsParams},
+    },
+    repositories::{
+        AnalyticsRepository, EventRepository, ProductRepository, RecentlyViewedRepository,
+        StoreRepository,
+    },
+    services::{analytics_service::AnalyticsService, EventService, RecentlyViewedService},
 };
 use rust_decimal::Decimal;
 use serde_json::json;
@@ -78,8 +88,12 @@ impl AnalyticsFixture {
         .await
         .unwrap();
 
-        let day_one = Utc.with_ymd_and_hms(2025, 1, 10, 12, 0, 0).unwrap();
-        let day_two = Utc.with_ymd_and_hms(2025, 1, 11, 12, 0, 0).unwrap();
+        // Recent enough to fall inside every plan's analytics window.
+        let noon = (Utc::now().date_naive() - Duration::days(10))
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let day_one = Utc.from_utc_datetime(&noon);
+        let day_two = day_one + Duration::days(1);
 
         create_order_with_item(
             pool,
@@ -228,7 +242,7 @@ async fn analytics_repository_reports_expected_metrics(pool: PgPool) -> sqlx::Re
     let since = fixture.day_one - Duration::days(1);
 
     let summary = repo
-        .store_summary(fixture.store_id, since, 7)
+        .store_summary(fixture.store_id, since, Utc::now(), 7, PaymentStatus::Paid)
         .await
         .expect("summary");
 
@@ -238,7 +252,7 @@ async fn analytics_repository_reports_expected_metrics(pool: PgPool) -> sqlx::Re
     assert_eq!(summary.average_order_value, fixture.average_order);
 
     let trend = repo
-        .store_sales_trend(fixture.store_id, since)
+        .store_sales_trend(fixture.store_id, since, PaymentStatus::Paid)
         .await
         .expect("trend");
 
@@ -251,7 +265,7 @@ async fn analytics_repository_reports_expected_metrics(pool: PgPool) -> sqlx::Re
     assert_eq!(trend[1].total_revenue, Decimal::new(2000, 2));
 
     let top_products = repo
-        .store_top_products(fixture.store_id, since, 5)
+        .store_top_products(fixture.store_id, since, 5, PaymentStatus::Paid)
         .await
         .expect("top products");
 
@@ -274,8 +288,22 @@ async fn analytics_service_combines_all_sections(pool: PgPool) -> sqlx::Result<(
         AnalyticsRepository::new(pool.clone()),
     );
 
+    sqlx::query("UPDATE stores SET plan = 'Pro' WHERE id = $1")
+        .bind(fixture.store_id)
+        .execute(&pool)
+        .await?;
+
     let response = service
-        .store_analytics(fixture.store_id, 400, 5)
+        .store_analytics(
+            fixture.store_id,
+            StoreAnalyticsParams {
+                timeframe_days: 365,
+                top_products: 5,
+                payment_status: PaymentStatus::Paid,
+                compare: None,
+            },
+            false,
+        )
         .await
         .expect("service response");
 
@@ -283,6 +311,336 @@ async fn analytics_service_combines_all_sections(pool: PgPool) -> sqlx::Result<(
     assert_eq!(response.summary.total_revenue, fixture.total_revenue);
     assert_eq!(response.sales_trend.len(), 2);
     assert_eq!(response.top_products[0].product_id, fixture.product_a);
+    assert_eq!(response.funnel.paid_orders, 2);
+    assert_eq!(response.funnel.checkout_to_paid_rate, 0.0);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn analytics_compare_summary_with_previous_period(pool: PgPool) -> sqlx::Result<()> {
+    let fixture = AnalyticsFixture::seed(&pool).await;
+    let service = AnalyticsService::new(
+        StoreRepository::new(pool.clone()),
+        AnalyticsRepository::new(pool.clone()),
+    );
+
+    for (order_number, days_ago) in [("ORD-1001", 2), ("ORD-1002", 9)] {
+        sqlx::query(
+            "UPDATE orders SET created_at = NOW() - make_interval(days => $2) WHERE order_number = $1",
+        )
+        .bind(order_number)
+        .bind(days_ago)
+        .execute(&pool)
+        .await?;
+    }
+
+    let params = StoreAnalyticsParams {
+        timeframe_days: 7,
+        top_products: 5,
+        payment_status: PaymentStatus::Paid,
+        compare: Some(AnalyticsComparison::PreviousPeriod),
+    };
+    let response = service
+        .store_analytics(fixture.store_id, params, false)
+        .await
+        .expect("compared report");
+
+    let comparison = response.comparison.expect("comparison");
+    assert_eq!(comparison.compare, AnalyticsComparison::PreviousPeriod);
+    assert_eq!(
+        comparison.period_end,
+        response.generated_at - Duration::days(7)
+    );
+    assert_eq!(comparison.summary.total_orders, 1);
+    assert_eq!(comparison.total_revenue.current, Decimal::new(3000, 2));
+    assert_eq!(comparison.total_revenue.previous, Decimal::new(2000, 2));
+    assert_eq!(comparison.total_revenue.change, Decimal::new(1000, 2));
+    assert_eq!(comparison.total_revenue.percent_change, Some(50.0));
+    assert_eq!(comparison.total_orders.change, Decimal::ZERO);
+
+    // A year-over-year comparison reaches past the free plan's retention.
+    let err = service
+        .store_analytics(
+            fixture.store_id,
+            StoreAnalyticsParams {
+                compare: Some(AnalyticsComparison::PreviousYear),
+                ..params
+            },
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::QuotaExceeded(_)));
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn analytics_filter_orders_by_payment_status(pool: PgPool) -> sqlx::Result<()> {
+    let fixture = AnalyticsFixture::seed(&pool).await;
+    let repo = AnalyticsRepository::new(pool.clone());
+    let since = fixture.day_one - Duration::days(1);
+
+    sqlx::query(
+        "UPDATE order_groups SET payment_status = 'Pending' WHERE group_number = 'GRP-1002'",
+    )
+    .execute(&pool)
+    .await?;
+
+    let paid = repo
+        .store_summary(fixture.store_id, since, Utc::now(), 7, PaymentStatus::Paid)
+        .await
+        .expect("paid summary");
+    assert_eq!(paid.total_orders, 1);
+    assert_eq!(paid.total_revenue, Decimal::new(3000, 2));
+    assert_eq!(paid.paid_orders, 1);
+    assert_eq!(paid.pending_orders, 1);
+    assert_eq!(paid.pending_revenue, Decimal::new(2000, 2));
+
+    let pending = repo
+        .store_summary(
+            fixture.store_id,
+            since,
+            Utc::now(),
+            7,
+            PaymentStatus::Pending,
+        )
+        .await
+        .expect("pending summary");
+    assert_eq!(pending.total_orders, 1);
+    assert_eq!(pending.total_revenue, Decimal::new(2000, 2));
+    assert_eq!(pending.paid_revenue, Decimal::new(3000, 2));
+
+    let top_products = repo
+        .store_top_products(fixture.store_id, since, 5, PaymentStatus::Pending)
+        .await
+        .expect("top products");
+    assert_eq!(top_products.len(), 1);
+    assert_eq!(top_products[0].product_id, fixture.product_b);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn analytics_read_rollups_and_top_up_live_orders(pool: PgPool) -> sqlx::Result<()> {
+    let fixture = AnalyticsFixture::seed(&pool).await;
+    let repo = AnalyticsRepository::new(pool.clone());
+    let since = fixture.day_one - Duration::days(1);
+
+    assert_eq!(repo.refresh_rollups(7).await.expect("rollup"), 2);
+
+    // Rolled-up days are no longer read from the orders themselves.
+    sqlx::query("UPDATE orders SET total_amount = 99.00 WHERE order_number = 'ORD-1001'")
+        .execute(&pool)
+        .await?;
+
+    // Today's orders are not rolled up yet and are read live.
+    let late_buyer = Uuid::new_v4();
+    insert_user(&pool, late_buyer, "buyer3@markethub.dev").await;
+    create_order_with_item(
+        &pool,
+        fixture.store_id,
+        late_buyer,
+        fixture.product_b,
+        Decimal::new(2000, 2),
+        2,
+        "GRP-1003",
+        "ORD-1003",
+        Utc::now(),
+    )
+    .await;
+
+    let summary = repo
+        .store_summary(fixture.store_id, since, Utc::now(), 30, PaymentStatus::Paid)
+        .await
+        .expect("summary");
+    assert_eq!(summary.total_orders, 3);
+    assert_eq!(summary.unique_customers, 3);
+    assert_eq!(summary.total_revenue, Decimal::new(9000, 2));
+
+    let trend = repo
+        .store_sales_trend(fixture.store_id, since, PaymentStatus::Paid)
+        .await
+        .expect("trend");
+    assert_eq!(trend.len(), 3);
+    assert_eq!(trend[0].total_revenue, Decimal::new(3000, 2));
+    assert_eq!(trend[2].date, Utc::now().date_naive());
+
+    let top_products = repo
+        .store_top_products(fixture.store_id, since, 5, PaymentStatus::Paid)
+        .await
+        .expect("top products");
+    assert_eq!(top_products[0].product_id, fixture.product_b);
+    assert_eq!(top_products[0].units_sold, 3);
+
+    // A longer lookback rebuilds the older days from the orders again.
+    repo.refresh_rollups(30).await.expect("rollup");
+    let summary = repo
+        .store_summary(fixture.store_id, since, Utc::now(), 30, PaymentStatus::Paid)
+        .await
+        .expect("summary");
+    assert_eq!(summary.total_orders, 3);
+    assert_eq!(summary.total_revenue, Decimal::new(15900, 2));
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn client_events_feed_store_funnel(pool: PgPool) -> sqlx::Result<()> {
+    let owner = common::insert_user(&pool, "funnel-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "funnel-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "funnel-store", false).await;
+    let other = common::create_store(&pool, owner.id, "funnel-other", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-FUNNEL", 10.0, 5).await;
+
+    let events = EventService::new(
+        EventRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        RecentlyViewedRepository::new(pool.clone()),
+        1.0,
+    );
+    let event = |event_type, session: &str, product_id| ClientEvent {
+        event_type,
+        store_id: store.id,
+        product_id,
+        session_id: session.to_string(),
+        occurred_at: None,
+    };
+
+    let mut foreign = event(ClientEventType::ProductView, "session-b", Some(product.id));
+    foreign.store_id = other.id;
+    let response = events
+        .ingest(
+            Some(shopper.id),
+            IngestEventsRequest {
+                events: vec![
+                    event(ClientEventType::ProductView, "session-a", Some(product.id)),
+                    event(ClientEventType::ProductView, "session-b", Some(product.id)),
+                    event(ClientEventType::AddToCart, "session-a", Some(product.id)),
+                    event(ClientEventType::AddToCart, "session-a", None),
+                    foreign,
+                ],
+            },
+        )
+        .await
+        .expect("ingest");
+    assert_eq!(response.accepted, 3);
+    let rejected: Vec<usize> = response.rejected.iter().map(|r| r.index).collect();
+    assert_eq!(rejected, vec![3, 4]);
+
+    let analytics = AnalyticsService::new(
+        StoreRepository::new(pool.clone()),
+        AnalyticsRepository::new(pool.clone()),
+    );
+    let funnel = analytics.store_funnel(store.id, 30).await.expect("funnel");
+    assert_eq!(funnel.viewed_sessions, 2);
+    assert_eq!(funnel.cart_sessions, 1);
+    assert_eq!(funnel.checkout_sessions, 0);
+    assert_eq!(funnel.view_to_cart_rate, 0.5);
+    assert_eq!(funnel.paid_orders, 0);
+
+    let recent = RecentlyViewedService::new(RecentlyViewedRepository::new(pool.clone()));
+    let history = recent.list(shopper.id).await.expect("history");
+    assert_eq!(history[0].product.id, product.id);
+
+    let sampled = EventService::new(
+        EventRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        RecentlyViewedRepository::new(pool.clone()),
+        0.0,
+    );
+    let response = sampled
+        .ingest(
+            None,
+            IngestEventsRequest {
+                events: vec![event(ClientEventType::BeginCheckout, "session-a", None)],
+            },
+        )
+        .await
+        .expect("ingest");
+    assert_eq!((response.accepted, response.sampled_out), (0, 1));
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn inventory_analytics_project_days_of_stock(pool: PgPool) -> sqlx::Result<()> {
+    let owner = common::insert_user(&pool, "stock-owner@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "stock-store", false).await;
+    let lamp = common::create_product(&pool, store.id, "SKU-LAMP", 10.0, 20).await;
+    let shelf = common::create_product(&pool, store.id, "SKU-SHELF", 10.0, 5).await;
+    let chair = common::create_product(&pool, store.id, "SKU-CHAIR", 10.0, 3).await;
+
+    // Stocked well before the timeframe; since then the lamp sold 15 units
+    // and the chair sold out two days ago.
+    sqlx::query("UPDATE products SET created_at = NOW() - INTERVAL '40 days' WHERE store_id = $1")
+        .bind(store.id)
+        .execute(&pool)
+        .await?;
+    sqlx::query(
+        "UPDATE inventory_movements SET created_at = NOW() - INTERVAL '40 days' WHERE store_id = $1",
+    )
+    .bind(store.id)
+    .execute(&pool)
+    .await?;
+    let record = |product_id: Uuid, kind: &'static str, delta: i32, stock_after: i32, days_ago| {
+        let pool = pool.clone();
+        async move {
+            sqlx::query(
+                r#"
+                INSERT INTO inventory_movements
+                    (product_id, store_id, kind, delta, stock_after, created_at)
+                VALUES ($1, $2, $3::inventory_movement_kind, $4, $5, $6)
+                "#,
+            )
+            .bind(product_id)
+            .bind(store.id)
+            .bind(kind)
+            .bind(delta)
+            .bind(stock_after)
+            .bind(Utc::now() - Duration::days(days_ago))
+            .execute(&pool)
+            .await?;
+            sqlx::query("UPDATE products SET stock_quantity = $2 WHERE id = $1")
+                .bind(product_id)
+                .bind(stock_after)
+                .execute(&pool)
+                .await
+        }
+    };
+    record(lamp.id, "Sale", -15, 5, 1).await?;
+    record(chair.id, "Sale", -3, 0, 2).await?;
+
+    let service = AnalyticsService::new(
+        StoreRepository::new(pool.clone()),
+        AnalyticsRepository::new(pool.clone()),
+    );
+    let inventory = service
+        .store_inventory(store.id, 30)
+        .await
+        .expect("inventory");
+
+    let ids: Vec<Uuid> = inventory.products.iter().map(|p| p.product_id).collect();
+    assert_eq!(ids, vec![chair.id, lamp.id, shelf.id]);
+
+    let chair_stats = &inventory.products[0];
+    assert_eq!(chair_stats.days_of_stock, Some(0.0));
+    assert_eq!(chair_stats.stockout_days, 3);
+    assert_eq!(chair_stats.sell_through_rate, 1.0);
+
+    let lamp_stats = &inventory.products[1];
+    assert_eq!(lamp_stats.units_sold, 15);
+    assert_eq!(lamp_stats.sell_through_rate, 0.75);
+    assert_eq!(lamp_stats.daily_velocity, 0.5);
+    assert_eq!(lamp_stats.days_of_stock, Some(10.0));
+    assert_eq!(lamp_stats.stockout_days, 0);
+
+    let shelf_stats = &inventory.products[2];
+    assert_eq!(shelf_stats.units_sold, 0);
+    assert_eq!(shelf_stats.days_of_stock, None);
 
     Ok(())
 }
@@ -2,9 +2,13 @@ use std::sync::Arc;
 
 use markethub::{
     error::AppError,
-    models::user::{LoginRequest, RegisterUserRequest},
-    repositories::UserRepository,
-    services::AuthService,
+    models::{
+        permission::Permission,
+        store::{CreateStoreRequest, MemberRole},
+        user::{ChangePasswordRequest, LoginRequest, RegisterUserRequest},
+    },
+    repositories::{MemberRepository, StoreRepository, UserRepository},
+    services::{AuthService, StoreService},
     utils::jwt::JwtConfig,
 };
 use sqlx::PgPool;
@@ -70,3 +74,123 @@ async fn invalid_credentials_fail_login(pool: PgPool) {
 
     assert!(matches!(err, AppError::Authentication(_)));
 }
+
+#[sqlx::test(migrations = "./migrations")]
+async fn password_change_revokes_existing_tokens(pool: PgPool) {
+    let service = auth_service(&pool);
+    let register = service
+        .register(register_payload("carol@example.com"))
+        .await
+        .unwrap();
+    service.authenticate(&register.token).await.unwrap();
+
+    let changed = service
+        .change_password(
+            register.user.id,
+            ChangePasswordRequest {
+                current_password: "StrongPass123!".into(),
+                new_password: "EvenStronger456!".into(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let err = service.authenticate(&register.token).await.unwrap_err();
+    assert!(matches!(err, AppError::Authentication(_)));
+    let claims = service.authenticate(&changed.token).await.unwrap();
+    assert_eq!(claims.sub, register.user.id);
+
+    let err = service
+        .login(LoginRequest {
+            email: "carol@example.com".into(),
+            password: "StrongPass123!".into(),
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Authentication(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn member_changes_revoke_that_members_tokens(pool: PgPool) {
+    let service = auth_service(&pool);
+    let owner = service
+        .register(register_payload("owner@example.com"))
+        .await
+        .unwrap();
+    let staff = service
+        .register(register_payload("staff@example.com"))
+        .await
+        .unwrap();
+
+    let stores = StoreService::new(
+        StoreRepository::new(pool.clone()),
+        MemberRepository::new(pool.clone()),
+    );
+    let store = stores
+        .create_store(
+            owner.user.id,
+            CreateStoreRequest {
+                name: "Token Store".into(),
+                slug: "token-store".into(),
+                description: None,
+                logo_url: None,
+                is_private: false,
+            },
+        )
+        .await
+        .unwrap();
+
+    let members = MemberRepository::new(pool.clone());
+    members
+        .add_member(
+            store.id,
+            staff.user.id,
+            MemberRole::Staff,
+            &[Permission::EditProducts],
+            None,
+            Some(owner.user.id),
+        )
+        .await
+        .unwrap();
+    service.authenticate(&staff.token).await.unwrap();
+
+    members
+        .update_member(
+            store.id,
+            staff.user.id,
+            MemberRole::Custom,
+            &[],
+            None,
+            owner.user.id,
+        )
+        .await
+        .unwrap()
+        .expect("member exists");
+    let err = service.authenticate(&staff.token).await.unwrap_err();
+    assert!(matches!(err, AppError::Authentication(_)));
+
+    let relogin = service
+        .login(LoginRequest {
+            email: "staff@example.com".into(),
+            password: "StrongPass123!".into(),
+        })
+        .await
+        .unwrap();
+    service.authenticate(&relogin.token).await.unwrap();
+
+    members
+        .remove_member(store.id, staff.user.id, owner.user.id)
+        .await
+        .unwrap()
+        .expect("member exists");
+    let err = service.authenticate(&relogin.token).await.unwrap_err();
+    assert!(matches!(err, AppError::Authentication(_)));
+    assert!(members
+        .find_membership(store.id, staff.user.id)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Other users' sessions are untouched.
+    service.authenticate(&owner.token).await.unwrap();
+}
@@ -3,15 +3,15 @@
 use std::sync::Arc;
 
 use markethub::{
-    metrics::Metrics,
     models::{
-        product::{CreateProductRequest, Product},
+        product::{CreateProductRequest, Product, ProductType},
         store::{CreateStoreRequest, Store},
         user::User,
     },
-    repositories::{MemberRepository, ProductRepository, StoreRepository},
+    repositories::{MemberRepository, ProductImageRepository, ProductRepository, StoreRepository},
     services::{ProductService, StoreService},
     state::AppState,
+    testing,
     utils::{jwt::JwtConfig, password},
 };
 use serde_json::{json, Value};
@@ -19,15 +19,11 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 pub fn test_jwt() -> Arc<JwtConfig> {
-    Arc::new(JwtConfig::new("test-secret", 24))
+    Arc::new(JwtConfig::new(testing::TEST_JWT_SECRET, 24))
 }
 
 pub fn build_state(pool: PgPool) -> AppState {
-    AppState::new(
-        pool,
-        JwtConfig::new("test-secret", 24),
-        Arc::new(Metrics::default()),
-    )
+    testing::build_state(pool)
 }
 
 pub async fn insert_user(pool: &PgPool, email: &str) -> User {
@@ -49,6 +45,14 @@ pub async fn insert_user(pool: &PgPool, email: &str) -> User {
     .expect("user insert should succeed")
 }
 
+pub async fn promote_to_platform_admin(pool: &PgPool, user_id: Uuid) {
+    sqlx::query("UPDATE users SET is_platform_admin = true WHERE id = $1")
+        .bind(user_id)
+        .execute(pool)
+        .await
+        .expect("admin promotion should succeed");
+}
+
 pub async fn create_store(pool: &PgPool, owner_id: Uuid, slug: &str, is_private: bool) -> Store {
     let stores = StoreRepository::new(pool.clone());
     let members = MemberRepository::new(pool.clone());
@@ -78,7 +82,8 @@ pub async fn create_product(
 ) -> Product {
     let products = ProductRepository::new(pool.clone());
     let stores = StoreRepository::new(pool.clone());
-    let service = ProductService::new(products, stores);
+    let images = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(products, stores, images);
 
     service
         .create_product(CreateProductRequest {
@@ -89,6 +94,9 @@ pub async fn create_product(
             price,
             stock_quantity: stock,
             category: None,
+            thumbnail_url: None,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
         })
         .await
         .expect("product creation should succeed")
@@ -0,0 +1,57 @@
+// Connection pool settings applied to real connections
+use std::time::Duration;
+
+use markethub::db::PoolSettings;
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    PgPool,
+};
+
+#[sqlx::test(migrations = false)]
+async fn statement_timeout_is_set_on_every_connection(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) {
+    let settings = PoolSettings {
+        max_connections: 2,
+        statement_timeout: Some(Duration::from_millis(200)),
+        ..PoolSettings::default()
+    };
+    let pool: PgPool = settings
+        .pool_options()
+        .connect_with(settings.connect_options(connect_options))
+        .await
+        .unwrap();
+
+    let timeout: String = sqlx::query_scalar("SHOW statement_timeout")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(timeout, "200ms");
+
+    let err = sqlx::query("SELECT pg_sleep(2)")
+        .execute(&pool)
+        .await
+        .unwrap_err();
+    let code = err.as_database_error().and_then(|err| err.code());
+    assert_eq!(code.as_deref(), Some("57014"), "query_canceled: {err}");
+}
+
+#[sqlx::test(migrations = false)]
+async fn connections_keep_the_server_default_without_a_timeout(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) {
+    let settings = PoolSettings::default();
+    let pool = settings
+        .pool_options()
+        .connect_with(settings.connect_options(connect_options))
+        .await
+        .unwrap();
+
+    let timeout: String = sqlx::query_scalar("SHOW statement_timeout")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(timeout, "0");
+}
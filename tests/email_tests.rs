@@ -0,0 +1,306 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicU16, Ordering},
+    Arc, Mutex,
+};
+
+use async_trait::async_trait;
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Json, Router,
+};
+use common::shipping_address;
+use markethub::{
+    email::{
+        EmailJob, EmailMessage, EmailService, EmailTemplate, InvitationEmails, Mailer,
+        SesEmailService, EMAIL_JOB_KIND,
+    },
+    jobs::{JobRegistry, JobWorker},
+    metrics::Metrics,
+    models::{domain_event::PublishedEvent, notification::DeliveryStatus, store::MemberRole},
+    outbox::{EventSubscriber, OutboxRelay},
+    testing::{data, TestApp},
+    utils::breaker::BreakerConfig,
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+/// Accepts every email unless told to fail, keeping what it was given.
+#[derive(Default)]
+struct Outbox {
+    failing: AtomicBool,
+    sent: Mutex<Vec<EmailMessage>>,
+}
+
+#[async_trait]
+impl EmailService for Outbox {
+    async fn send(&self, message: &EmailMessage) -> anyhow::Result<Option<String>> {
+        if self.failing.load(Ordering::SeqCst) {
+            anyhow::bail!("Provider unavailable");
+        }
+        let mut sent = self.sent.lock().unwrap();
+        sent.push(message.clone());
+        Ok(Some(format!("msg-{}", sent.len())))
+    }
+}
+
+/// Requests the fake SES endpoint received, headers and body.
+type Received = Arc<Mutex<Vec<(HeaderMap, Bytes)>>>;
+
+fn email_worker(pool: &PgPool, outbox: Arc<Outbox>) -> JobWorker {
+    JobWorker::new(
+        pool.clone(),
+        JobRegistry::new().register(
+            EMAIL_JOB_KIND,
+            Arc::new(EmailJob::new(pool.clone(), outbox)),
+        ),
+        Arc::new(Metrics::new()),
+    )
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkouts_email_an_order_confirmation(pool: PgPool) {
+    let app = TestApp::spawn(pool.clone()).await;
+    let owner = app.register("mail-owner@example.com").await;
+    let store = app.create_store(&owner, "mail-store").await;
+    let product = app
+        .create_product(&owner, store.id, "MAIL-1", 12.5, 10)
+        .await;
+    let shopper = app.register("mail-shopper@example.com").await;
+
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 2 }))
+        .send()
+        .await
+        .unwrap();
+    let summary: Value = data(
+        app.post("/api/v1/orders/checkout")
+            .bearer_auth(&shopper.token)
+            .json(&json!({ "shipping_address": shipping_address() }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let order_number = summary["orders"][0]["order_number"].as_str().unwrap();
+
+    let mailer = Mailer::new(pool.clone());
+    let queued = mailer.list(None, 10, 0).await.unwrap();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].template, "order_confirmation");
+    assert_eq!(queued[0].recipient, shopper.email);
+    assert_eq!(queued[0].status, DeliveryStatus::Pending);
+    assert!(queued[0].subject.contains(order_number));
+
+    let outbox = Arc::new(Outbox::default());
+    let worker = email_worker(&pool, outbox.clone());
+    assert_eq!(worker.run_due(10).await.unwrap().succeeded, 1);
+    assert_eq!(worker.run_due(10).await.unwrap(), Default::default());
+
+    let email = mailer.find(queued[0].id).await.unwrap().unwrap();
+    assert_eq!(email.status, DeliveryStatus::Sent);
+    assert_eq!(email.attempts, 1);
+    assert_eq!(email.provider_message_id.as_deref(), Some("msg-1"));
+    assert!(email.sent_at.is_some());
+    let sent = outbox.sent.lock().unwrap().clone();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].to, shopper.email);
+    assert!(sent[0].text.contains("totalling 25.00"));
+
+    // Platform admins can look up what was sent
+    let response = app
+        .get("/api/v1/admin/emails?status=sent")
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    app.promote_to_platform_admin(&owner).await;
+    let listed: Vec<Value> = data(
+        app.get("/api/v1/admin/emails?status=sent")
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["status"], "sent");
+    assert_eq!(listed[0]["provider_message_id"], "msg-1");
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn failed_sends_are_retried_until_the_job_gives_up(pool: PgPool) {
+    let mailer = Mailer::new(pool.clone());
+    let template = EmailTemplate::MemberInvitation {
+        name: "Ada".into(),
+        store_name: "Mugs & Co".into(),
+        role: MemberRole::Staff,
+        invited_by: None,
+    };
+    let email = mailer
+        .queue("ada@example.com", &template, Some("invite:abc"))
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(mailer
+        .queue("ada@example.com", &template, Some("invite:abc"))
+        .await
+        .unwrap()
+        .is_none());
+
+    let outbox = Arc::new(Outbox::default());
+    outbox.failing.store(true, Ordering::SeqCst);
+    let worker = email_worker(&pool, outbox.clone());
+    assert_eq!(worker.run_due(10).await.unwrap().retrying, 1);
+
+    let retrying = mailer.find(email.id).await.unwrap().unwrap();
+    assert_eq!(retrying.status, DeliveryStatus::Pending);
+    assert_eq!(retrying.attempts, 1);
+    assert_eq!(retrying.last_error.as_deref(), Some("Provider unavailable"));
+
+    sqlx::query("UPDATE jobs SET run_at = NOW(), max_attempts = 2")
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(worker.run_due(10).await.unwrap().failed, 1);
+
+    let failed = mailer.find(email.id).await.unwrap().unwrap();
+    assert_eq!(failed.status, DeliveryStatus::Failed);
+    assert_eq!(failed.attempts, 2);
+    assert!(outbox.sent.lock().unwrap().is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn new_members_get_one_invitation(pool: PgPool) {
+    let app = TestApp::spawn(pool.clone()).await;
+    let owner = app.register("invite-owner@example.com").await;
+    let store = app.create_store(&owner, "invite-store").await;
+    let member = app.register("invite-member@example.com").await;
+
+    let response = app
+        .post(&format!("/api/v1/members/{}/invite", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "user_id": member.id, "role": "Manager", "permissions": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let invitations = Arc::new(InvitationEmails::new(pool.clone()));
+    let relay = OutboxRelay::new(pool.clone()).subscribe("emails", invitations.clone());
+    let worker = JobWorker::new(
+        pool.clone(),
+        relay.register_jobs(JobRegistry::new()),
+        Arc::new(Metrics::new()),
+    );
+    assert_eq!(relay.relay_pending(10).await.unwrap(), 1);
+    assert_eq!(worker.run_due(10).await.unwrap().succeeded, 1);
+
+    // The same event delivered again queues nothing new
+    let payload: Value = sqlx::query_scalar("SELECT payload FROM jobs WHERE kind = 'event:emails'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let event: PublishedEvent = serde_json::from_value(payload).unwrap();
+    invitations.handle(&event).await.unwrap();
+
+    let emails = Mailer::new(pool.clone()).list(None, 10, 0).await.unwrap();
+    assert_eq!(emails.len(), 1);
+    assert_eq!(emails[0].template, "member_invitation");
+    assert_eq!(emails[0].recipient, member.email);
+    assert!(emails[0].body.contains("as a manager"));
+}
+
+#[tokio::test]
+async fn ses_requests_are_signed() {
+    let received: Received = Arc::default();
+    let app = Router::new()
+        .route(
+            "/v2/email/outbound-emails",
+            post(
+                |State(received): State<Received>, headers: HeaderMap, body: Bytes| async move {
+                    received.lock().unwrap().push((headers, body));
+                    Json(json!({ "MessageId": "ses-123" }))
+                },
+            ),
+        )
+        .with_state(received.clone());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let ses = SesEmailService::new("eu-west-1", "shop@example.com", "AKID", "secret")
+        .unwrap()
+        .with_endpoint(endpoint);
+    let message = EmailMessage {
+        to: "ada@example.com".into(),
+        subject: "Hello".into(),
+        text: "Hi Ada".into(),
+    };
+    assert_eq!(
+        ses.send(&message).await.unwrap().as_deref(),
+        Some("ses-123")
+    );
+
+    let received = received.lock().unwrap();
+    let (headers, body) = &received[0];
+    let authorization = headers["authorization"].to_str().unwrap();
+    assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+    assert!(authorization.contains("/eu-west-1/ses/aws4_request"));
+    let body: Value = serde_json::from_slice(body).unwrap();
+    assert_eq!(body["FromEmailAddress"], "shop@example.com");
+    assert_eq!(
+        body["Destination"]["ToAddresses"],
+        json!(["ada@example.com"])
+    );
+    assert_eq!(body["Content"]["Simple"]["Body"]["Text"]["Data"], "Hi Ada");
+}
+
+#[tokio::test]
+async fn ses_outages_open_the_breaker_but_rejections_do_not() {
+    let status = Arc::new(AtomicU16::new(400));
+    let calls = Arc::new(AtomicU16::new(0));
+    let app = Router::new().route(
+        "/v2/email/outbound-emails",
+        post({
+            let (status, calls) = (status.clone(), calls.clone());
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                StatusCode::from_u16(status.load(Ordering::SeqCst)).unwrap()
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let ses = SesEmailService::new("eu-west-1", "shop@example.com", "AKID", "secret")
+        .unwrap()
+        .with_endpoint(endpoint);
+    let message = EmailMessage {
+        to: "ada@example.com".into(),
+        subject: "Hello".into(),
+        text: "Hi Ada".into(),
+    };
+    for _ in 0..10 {
+        assert!(ses.send(&message).await.is_err());
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 10);
+
+    status.store(503, Ordering::SeqCst);
+    for _ in 0..BreakerConfig::default().failure_threshold {
+        assert!(ses.send(&message).await.is_err());
+    }
+    let error = ses.send(&message).await.unwrap_err();
+    assert!(format!("{error:#}").contains("temporarily unavailable"));
+    assert_eq!(
+        calls.load(Ordering::SeqCst),
+        10 + BreakerConfig::default().failure_threshold as u16
+    );
+}
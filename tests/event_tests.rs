@@ -0,0 +1,166 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use common::shipping_address;
+use markethub::{
+    error::AppError,
+    events::{Event, EventHandler},
+    testing::{build_state, data, TestApp},
+};
+use serde_json::{json, Value};
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// Records what each hook saw, optionally failing the transactional one.
+#[derive(Default)]
+struct Recorder {
+    fail_in_tx: bool,
+    in_tx: Mutex<Vec<&'static str>>,
+    committed: Mutex<Vec<&'static str>>,
+}
+
+#[async_trait]
+impl EventHandler for Recorder {
+    async fn handle_in_tx(
+        &self,
+        _tx: &mut Transaction<'_, Postgres>,
+        event: &Event,
+    ) -> markethub::Result<()> {
+        self.in_tx.lock().unwrap().push(event.name());
+        if self.fail_in_tx {
+            return Err(AppError::Conflict("Rejected by a handler".into()));
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, event: &Event) -> anyhow::Result<()> {
+        self.committed.lock().unwrap().push(event.name());
+        anyhow::bail!("Post-commit failures are only logged")
+    }
+}
+
+async fn count(pool: &PgPool, query: &str) -> i64 {
+    sqlx::query_scalar(query).fetch_one(pool).await.unwrap()
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn services_announce_sign_ups_sales_and_orders(pool: PgPool) {
+    let recorder = Arc::new(Recorder::default());
+    let app =
+        TestApp::spawn_with(build_state(pool.clone()).with_event_handler(recorder.clone())).await;
+    let owner = app.register("bus-owner@example.com").await;
+    let store = app.create_store(&owner, "bus-store").await;
+    let product = app.create_product(&owner, store.id, "BUS-1", 10.0, 6).await;
+    let shopper = app.register("bus-shopper@example.com").await;
+
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 2 }))
+        .send()
+        .await
+        .unwrap();
+    let _: Value = data(
+        app.post("/api/v1/orders/checkout")
+            .bearer_auth(&shopper.token)
+            .json(&json!({ "shipping_address": shipping_address() }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    // Nothing left in the cart to check out
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+
+    assert_eq!(
+        *recorder.in_tx.lock().unwrap(),
+        ["StockChanged", "OrderPlaced"]
+    );
+    assert_eq!(
+        *recorder.committed.lock().unwrap(),
+        [
+            "UserRegistered",
+            "UserRegistered",
+            "StockChanged",
+            "OrderPlaced",
+            "CheckoutFailed"
+        ]
+    );
+
+    // The default handlers still notify the store and fill the outbox, and
+    // a failing post-commit handler did not undo the checkout.
+    let kinds: Vec<String> =
+        sqlx::query_scalar("SELECT kind FROM user_notifications WHERE store_id = $1 ORDER BY kind")
+            .bind(store.id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(kinds, ["low_stock", "new_order"]);
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM outbox_events").await, 2);
+
+    let metrics = app
+        .get("/metrics")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains("markethub_users_registered_total 2"));
+    assert!(metrics.contains("markethub_orders_created_total 1"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn a_failing_transactional_handler_rolls_the_checkout_back(pool: PgPool) {
+    let recorder = Arc::new(Recorder {
+        fail_in_tx: true,
+        ..Default::default()
+    });
+    let app =
+        TestApp::spawn_with(build_state(pool.clone()).with_event_handler(recorder.clone())).await;
+    let owner = app.register("rollback-owner@example.com").await;
+    let store = app.create_store(&owner, "rollback-store").await;
+    let product = app
+        .create_product(&owner, store.id, "ROLLBACK-1", 10.0, 6)
+        .await;
+    let shopper = app.register("rollback-shopper@example.com").await;
+
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 2 }))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM orders").await, 0);
+    assert_eq!(
+        count(&pool, "SELECT COUNT(*) FROM user_notifications").await,
+        0
+    );
+    assert_eq!(count(&pool, "SELECT COUNT(*) FROM outbox_events").await, 0);
+    let stock: i32 = sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1")
+        .bind(product.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stock, 6);
+    assert_eq!(
+        recorder.committed.lock().unwrap().last(),
+        Some(&"CheckoutFailed")
+    );
+}
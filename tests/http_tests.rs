@@ -0,0 +1,1667 @@
+mod common;
+
+use common::shipping_address;
+use markethub::{
+    models::{
+        order::{
+            CartItemDetail, GuestCartSession, Order, OrderDetail, OrderListEntry, OrderStatus,
+        },
+        store::{AccessLevel, StoreAccessGrant},
+    },
+    testing::{data, error_code, TestApp, TEST_PASSWORD},
+};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkout_through_the_http_api(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("owner@example.com").await;
+    let store = app.create_store(&owner, "http-store").await;
+    let product = app
+        .create_product(&owner, store.id, "HTTP-SKU-1", 12.5, 10)
+        .await;
+
+    let shopper = app.register("shopper@example.com").await;
+    let response = app
+        .post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    let confirmation: Value = data(response).await;
+    assert!(confirmation["confirmation_reference"].is_string());
+
+    let reference = confirmation["confirmation_reference"].as_str().unwrap();
+    let response = app
+        .get(&format!("/api/v1/orders/confirmation/{}", reference))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .get("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap();
+    let cart: Vec<CartItemDetail> = data(response).await;
+    assert!(cart.is_empty());
+
+    // A second checkout with the cart now empty fails.
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+    let failure = error_code(response).await;
+
+    let metrics = app
+        .get("/metrics")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(metrics.contains("markethub_orders_created_total 1"));
+    assert!(metrics.contains(&format!(
+        "markethub_revenue_total{{currency=\"USD\",store_id=\"{}\"}} 25",
+        store.id
+    )));
+    assert!(metrics.contains(&format!(
+        "markethub_checkout_failures_total{{reason=\"{}\"}} 1",
+        failure
+    )));
+    assert!(metrics.contains("markethub_db_pool_max_connections"));
+    assert!(metrics.contains("markethub_db_pool_acquire_wait_seconds"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn routes_enforce_authentication_and_admin_access(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let response = app.get("/health").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.get("/metrics").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.get("/api/v1/cart/items").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(error_code(response).await, "AUTHENTICATION_ERROR");
+
+    let response = app.post("/api/v1/cart/guest").send().await.unwrap();
+    let guest: GuestCartSession = data(response).await;
+    let response = app
+        .get("/api/v1/cart/items")
+        .header("X-Guest-Token", &guest.guest_token)
+        .send()
+        .await
+        .unwrap();
+    let cart: Vec<CartItemDetail> = data(response).await;
+    assert!(cart.is_empty());
+
+    let user = app.register("operator@example.com").await;
+    let response = app
+        .get("/api/v1/admin/appeals")
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    app.promote_to_platform_admin(&user).await;
+    let response = app
+        .get("/api/v1/admin/appeals")
+        .bearer_auth(&user.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn readiness_reports_dependencies_and_fails_without_the_database(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let response = app.get("/healthz").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.get("/readyz").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let readiness: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(readiness["ready"], true);
+    assert_eq!(readiness["dependencies"][0]["name"], "database");
+    assert_eq!(readiness["dependencies"][0]["status"], "ok");
+
+    app.db().close().await;
+    let response = app.get("/readyz").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let readiness: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(readiness["ready"], false);
+    assert_eq!(readiness["dependencies"][0]["status"], "unavailable");
+
+    let response = app.get("/healthz").send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn responses_carry_a_request_id_that_errors_quote(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let response = app.get("/health").send().await.unwrap();
+    let generated = response.headers()["x-request-id"].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(generated).is_ok());
+
+    let response = app
+        .get("/api/v1/cart/items")
+        .header("X-Request-Id", "client-7f3a")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(response.headers()["x-request-id"], "client-7f3a");
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "AUTHENTICATION_ERROR");
+    assert_eq!(body["error"]["request_id"], "client-7f3a");
+
+    // Ids that are unsafe to log are replaced.
+    let response = app
+        .get("/api/v1/cart/items")
+        .header("X-Request-Id", "two words")
+        .send()
+        .await
+        .unwrap();
+    let replaced = response.headers()["x-request-id"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_ne!(replaced, "two words");
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["request_id"], replaced.as_str());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn request_metrics_are_labelled_by_route_template(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let id = uuid::Uuid::new_v4();
+        app.get(&format!("/api/v1/products/{}", id))
+            .send()
+            .await
+            .unwrap();
+        app.get(&format!("/no-such-route/{}", id))
+            .send()
+            .await
+            .unwrap();
+        app.get(&format!("/uploads/{}.png", id))
+            .send()
+            .await
+            .unwrap();
+        ids.push(id);
+    }
+
+    let metrics = app
+        .get("/metrics")
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let paths: std::collections::BTreeSet<&str> = metrics
+        .lines()
+        .filter(|line| line.starts_with("markethub_http_requests_total{"))
+        .filter_map(|line| line.split("path=\"").nth(1)?.split('"').next())
+        .collect();
+    assert_eq!(
+        paths,
+        ["/api/v1/products/{product_id}", "unmatched"]
+            .into_iter()
+            .collect()
+    );
+    assert!(ids.iter().all(|id| !metrics.contains(&id.to_string())));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn responses_carry_security_headers(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+
+    for path in ["/health", "/api/v1/cart/items"] {
+        let response = app.get(path).send().await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers["strict-transport-security"], "max-age=31536000");
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert_eq!(headers["x-frame-options"], "DENY");
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn retried_requests_with_an_idempotency_key_are_replayed(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("idempotent-owner@example.com").await;
+    let other = app.register("idempotent-other@example.com").await;
+    let store = app.create_store(&owner, "idempotent-store").await;
+
+    let payload = json!({
+        "store_id": store.id,
+        "sku": "SKU-RETRY",
+        "name": "Retried product",
+        "price": 12.5,
+        "stock_quantity": 3,
+    });
+    let create = |user: &markethub::testing::TestUser, key: &str, body: &Value| {
+        app.post("/api/v1/products")
+            .bearer_auth(&user.token)
+            .header("Idempotency-Key", key)
+            .json(body)
+            .send()
+    };
+
+    let response = create(&owner, "create-retry-1", &payload).await.unwrap();
+    assert!(response.headers().get("idempotent-replayed").is_none());
+    let first: Value = data(response).await;
+
+    let response = create(&owner, "create-retry-1", &payload).await.unwrap();
+    assert_eq!(response.headers()["idempotent-replayed"], "true");
+    let replayed: Value = data(response).await;
+    assert_eq!(replayed["id"], first["id"]);
+    let (products,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM products WHERE sku = 'SKU-RETRY'")
+            .fetch_one(app.db())
+            .await
+            .unwrap();
+    assert_eq!(products, 1);
+
+    // The key can't be reused for another request.
+    let mut changed = payload.clone();
+    changed["sku"] = json!("SKU-OTHER");
+    let response = create(&owner, "create-retry-1", &changed).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = app
+        .post("/api/v1/products?source=retry")
+        .bearer_auth(&owner.token)
+        .header("Idempotency-Key", "create-retry-1")
+        .json(&payload)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.headers().get("idempotent-replayed").is_none());
+
+    // Keys are per user: another user's request with it runs on its own.
+    let response = create(&other, "create-retry-1", &payload).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response.headers().get("idempotent-replayed").is_none());
+
+    let response = app
+        .post(&format!("/api/v1/members/{}/grant", store.id))
+        .bearer_auth(&owner.token)
+        .header("Idempotency-Key", "bad key")
+        .json(&json!({ "user_id": other.id, "access_level": "View" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn abandoned_idempotency_claims_are_taken_over_by_retries(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("abandoned-owner@example.com").await;
+    let store = app.create_store(&owner, "abandoned-store").await;
+
+    let payload = json!({
+        "store_id": store.id,
+        "sku": "SKU-ABANDONED",
+        "name": "Abandoned product",
+        "price": 4.0,
+        "stock_quantity": 1,
+    });
+    let create = || {
+        app.post("/api/v1/products")
+            .bearer_auth(&owner.token)
+            .header("Idempotency-Key", "abandoned-1")
+            .json(&payload)
+            .send()
+    };
+    assert_eq!(create().await.unwrap().status(), StatusCode::OK);
+
+    // Turn the key into the claim of an attempt that went away without
+    // answering or committing anything.
+    sqlx::query("DELETE FROM products WHERE sku = 'SKU-ABANDONED'")
+        .execute(app.db())
+        .await
+        .unwrap();
+    let abandon = |lease: &'static str| {
+        sqlx::query(
+            "UPDATE idempotency_keys SET status_code = NULL, response_body = NULL, \
+             locked_until = NOW() + $1::interval",
+        )
+        .bind(lease)
+        .execute(app.db())
+    };
+    abandon("1 minute").await.unwrap();
+    let response = create().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    // Once its lease runs out, the retry runs instead of waiting a day.
+    abandon("-1 second").await.unwrap();
+    let response = create().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("idempotent-replayed").is_none());
+    let response = create().await.unwrap();
+    assert_eq!(response.headers()["idempotent-replayed"], "true");
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn sensitive_actions_are_audited_for_owners_and_admins(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("audited-owner@example.com").await;
+    let staff = app.register("audited-staff@example.com").await;
+    let admin = app.register("audited-admin@example.com").await;
+    app.promote_to_platform_admin(&admin).await;
+    let store = app.create_store(&owner, "audited-store").await;
+    let product = app
+        .create_product(&owner, store.id, "SKU-AUDIT", 10.0, 5)
+        .await;
+
+    let login = |password: &str| {
+        app.post("/api/v1/auth/login")
+            .json(&json!({ "email": "audited-staff@example.com", "password": password }))
+            .send()
+    };
+    let response = login("NotThePassword1!").await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = login(TEST_PASSWORD).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .patch(&format!("/api/v1/products/{}", product.id))
+        .bearer_auth(&owner.token)
+        .header("X-Request-Id", "audit-price-1")
+        .json(&json!({ "price": 12.5 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .post(&format!("/api/v1/members/{}/grant", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "user_id": staff.id, "access_level": "View" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let path = format!("/api/v1/stores/{}/audit-events", store.id);
+    let response = app
+        .get(&path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    let events: Vec<Value> = data(response).await;
+    let actions: Vec<&str> = events
+        .iter()
+        .map(|event| event["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(actions, ["PermissionChange", "PriceChange"]);
+    let price = &events[1];
+    assert_eq!(price["entity_id"], json!(product.id));
+    assert_eq!(price["actor_id"], json!(owner.id));
+    assert_eq!(price["ip_address"], "127.0.0.1");
+    assert_eq!(price["request_id"], "audit-price-1");
+    assert!(price["diff"]["price"]["from"].is_string());
+    assert_eq!(events[0]["diff"]["to"]["user_id"], json!(staff.id));
+
+    let response = app
+        .get(&format!("{}?action=PriceChange", path))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    let events: Vec<Value> = data(response).await;
+    assert_eq!(events.len(), 1);
+
+    // Staff see nothing, even once they have access to the store.
+    let response = app
+        .get(&path)
+        .bearer_auth(&staff.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .get("/api/v1/admin/audit-events")
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Admins see sign-ins across the platform.
+    let response = app
+        .get("/api/v1/admin/audit-events?entity_type=user")
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    let events: Vec<Value> = data(response).await;
+    let logins: Vec<(&str, &Value)> = events
+        .iter()
+        .map(|event| (event["action"].as_str().unwrap(), &event["actor_id"]))
+        .collect();
+    assert_eq!(
+        logins,
+        [("Login", &json!(staff.id)), ("LoginFailed", &Value::Null)]
+    );
+    assert_eq!(events[1]["diff"]["email"], "audited-staff@example.com");
+    let response = app
+        .get(&format!("/api/v1/admin/audit-events?store_id={}", store.id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    let events: Vec<Value> = data(response).await;
+    assert_eq!(events.len(), 2);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn product_edits_only_touch_the_fields_sent(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("editing-owner@example.com").await;
+    let store = app.create_store(&owner, "editing-store").await;
+    let product = app
+        .create_product(&owner, store.id, "SKU-EDIT", 10.0, 5)
+        .await;
+    let edit = |body: Value| {
+        app.patch(&format!("/api/v1/products/{}", product.id))
+            .bearer_auth(&owner.token)
+            .json(&body)
+            .send()
+    };
+    let edit_movements = || {
+        sqlx::query_as::<_, (i32, Option<Uuid>)>(
+            "SELECT delta, actor_id FROM inventory_movements \
+             WHERE product_id = $1 AND reason = 'Product edited'",
+        )
+        .bind(product.id)
+        .fetch_all(app.db())
+    };
+
+    // A sale moves stock on after the editor loaded the product.
+    sqlx::query("UPDATE products SET stock_quantity = 3 WHERE id = $1")
+        .bind(product.id)
+        .execute(app.db())
+        .await
+        .unwrap();
+    let response = edit(json!({ "name": "Renamed" })).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let edited: Value = data(response).await;
+    assert_eq!(edited["name"], "Renamed");
+    assert_eq!(edited["stock_quantity"], 3);
+    assert!(edit_movements().await.unwrap().is_empty());
+
+    let response = edit(json!({ "stock_quantity": 8 })).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let edited: Value = data(response).await;
+    assert_eq!(edited["name"], "Renamed");
+    assert_eq!(edited["stock_quantity"], 8);
+    assert_eq!(edit_movements().await.unwrap(), [(5, Some(owner.id))]);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn order_detail_is_visible_to_buyer_and_store_staff_only(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("detail-owner@example.com").await;
+    let store = app.create_store(&owner, "detail-store").await;
+    let product = app
+        .create_product(&owner, store.id, "DETAIL-SKU-1", 8.0, 10)
+        .await;
+
+    let shopper = app.register("detail-shopper@example.com").await;
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 3 }))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    let confirmation: Value = data(response).await;
+    let path = format!(
+        "/api/v1/orders/{}",
+        confirmation["orders"][0]["id"].as_str().unwrap()
+    );
+
+    for viewer in [&shopper, &owner] {
+        let response = app
+            .get(&path)
+            .bearer_auth(&viewer.token)
+            .send()
+            .await
+            .unwrap();
+        let detail: OrderDetail = data(response).await;
+        assert_eq!(detail.order.user_id, shopper.id);
+        assert_eq!(detail.order.shipping_address, shipping_address());
+        assert_eq!(detail.items.len(), 1);
+        assert_eq!(detail.items[0].product_name, product.name);
+        assert_eq!(detail.items[0].item.quantity, 3);
+    }
+
+    let stranger = app.register("detail-stranger@example.com").await;
+    let response = app
+        .get(&path)
+        .bearer_auth(&stranger.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn store_staff_filter_and_advance_incoming_orders(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("incoming-owner@example.com").await;
+    let store = app.create_store(&owner, "incoming-store").await;
+    let product = app
+        .create_product(&owner, store.id, "INCOMING-SKU-1", 5.0, 10)
+        .await;
+
+    let shopper = app.register("incoming-shopper@example.com").await;
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 1 }))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    let confirmation: Value = data(response).await;
+    let order_id = confirmation["orders"][0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let orders_path = format!("/api/v1/stores/{}/orders", store.id);
+    let today = chrono::Utc::now().date_naive();
+    let listed: Vec<OrderListEntry> = data(
+        app.get(&format!(
+            "{orders_path}?status=Pending&from={today}&to={today}"
+        ))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap(),
+    )
+    .await;
+    assert_eq!(listed.len(), 1);
+    let yesterday = today.pred_opt().unwrap();
+    let listed: Vec<OrderListEntry> = data(
+        app.get(&format!("{orders_path}?to={yesterday}"))
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(listed.is_empty());
+    let response = app
+        .get(&format!("{orders_path}?from={today}&to={yesterday}"))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let status_path = format!("{orders_path}/{order_id}/status");
+    let response = app
+        .post(&status_path)
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "status": "Confirmed" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let order: Order = data(
+        app.post(&status_path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "status": "Confirmed" }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(order.status, OrderStatus::Confirmed);
+
+    let response = app
+        .post(&status_path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "status": "Delivered" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let order: Order = data(
+        app.post(&status_path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "status": "Cancelled", "reason": "Out of stock" }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert_eq!(order.cancellation_reason.as_deref(), Some("Out of stock"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn invoices_render_as_pdf_or_html_with_sequential_numbers(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("invoice-owner@example.com").await;
+    let store = app.create_store(&owner, "invoice-store").await;
+    let product = app
+        .create_product(&owner, store.id, "INVOICE-SKU-1", 7.5, 10)
+        .await;
+
+    let shopper = app.register("invoice-shopper@example.com").await;
+    let mut confirmations = Vec::new();
+    for quantity in [2, 1] {
+        app.post("/api/v1/cart/items")
+            .bearer_auth(&shopper.token)
+            .json(&json!({ "product_id": product.id, "quantity": quantity }))
+            .send()
+            .await
+            .unwrap();
+        let response = app
+            .post("/api/v1/orders/checkout")
+            .bearer_auth(&shopper.token)
+            .json(&json!({ "shipping_address": shipping_address() }))
+            .send()
+            .await
+            .unwrap();
+        let confirmation: Value = data(response).await;
+        confirmations.push(confirmation);
+    }
+    let invoice_path = |confirmation: &Value| {
+        format!(
+            "/api/v1/orders/{}/invoice",
+            confirmation["orders"][0]["id"].as_str().unwrap()
+        )
+    };
+
+    let response = app
+        .get(&invoice_path(&confirmations[0]))
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/pdf");
+    assert!(response.bytes().await.unwrap().starts_with(b"%PDF-"));
+
+    let html = app
+        .get(&format!("{}?format=html", invoice_path(&confirmations[1])))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("Invoice INV-000002"));
+    assert!(html.contains(&product.name));
+    assert!(html.contains("7.50 USD"));
+
+    let html = app
+        .get(&format!("{}?format=html", invoice_path(&confirmations[0])))
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("Invoice INV-000001"));
+
+    let stranger = app.register("invoice-stranger@example.com").await;
+    let response = app
+        .get(&invoice_path(&confirmations[0]))
+        .bearer_auth(&stranger.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let group_path = format!(
+        "/api/v1/orders/groups/{}/invoice?format=html",
+        confirmations[1]["order_group"]["id"].as_str().unwrap()
+    );
+    let response = app
+        .get(&group_path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let html = app
+        .get(&group_path)
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html.contains("Invoice INV-000002"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn buyers_and_store_staff_message_about_an_order(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("messages-owner@example.com").await;
+    let store = app.create_store(&owner, "messages-store").await;
+    let product = app
+        .create_product(&owner, store.id, "MESSAGES-SKU-1", 9.0, 10)
+        .await;
+
+    let shopper = app.register("messages-shopper@example.com").await;
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 1 }))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    let confirmation: Value = data(response).await;
+    let order_id = confirmation["orders"][0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let path = format!("/api/v1/orders/{}/messages", order_id);
+    let store_unread = format!("/api/v1/stores/{}/unread-messages", store.id);
+
+    let response = app
+        .post(&path)
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "body": "  Can this ship before Friday?  " }))
+        .send()
+        .await
+        .unwrap();
+    let message: Value = data(response).await;
+    assert_eq!(message["sender"], "Buyer");
+    assert_eq!(message["body"], "Can this ship before Friday?");
+
+    let response = app
+        .post(&path)
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "body": "   " }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error_code(response).await, "VALIDATION_ERROR");
+
+    let stranger = app.register("messages-stranger@example.com").await;
+    for request in [
+        app.get(&path),
+        app.post(&path).json(&json!({ "body": "Hello?" })),
+    ] {
+        let response = request.bearer_auth(&stranger.token).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    let response = app
+        .get(&store_unread)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    let unread: Vec<Value> = data(response).await;
+    assert_eq!(unread.len(), 1);
+    assert_eq!(unread[0]["order_id"], order_id.as_str());
+    assert_eq!(unread[0]["unread"], 1);
+
+    let response = app
+        .get(&path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    let thread: Vec<Value> = data(response).await;
+    assert_eq!(thread.len(), 1);
+    let response = app
+        .get(&store_unread)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    let unread: Vec<Value> = data(response).await;
+    assert!(unread.is_empty());
+
+    let response = app
+        .post(&path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "body": "Yes, it leaves on Thursday." }))
+        .send()
+        .await
+        .unwrap();
+    let reply: Value = data(response).await;
+    assert_eq!(reply["sender"], "Store");
+
+    let response = app
+        .get("/api/v1/orders/unread-messages")
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap();
+    let unread: Vec<Value> = data(response).await;
+    assert_eq!(unread.len(), 1);
+    assert_eq!(unread[0]["unread"], 1);
+
+    let response = app
+        .get(&path)
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap();
+    let thread: Vec<Value> = data(response).await;
+    assert_eq!(thread.len(), 2);
+    assert_eq!(thread[1]["body"], "Yes, it leaves on Thursday.");
+    let response = app
+        .get("/api/v1/orders/unread-messages")
+        .bearer_auth(&shopper.token)
+        .send()
+        .await
+        .unwrap();
+    let unread: Vec<Value> = data(response).await;
+    assert!(unread.is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn store_access_grants_are_listed_and_extended(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("grants-owner@example.com").await;
+    let store = app.create_store(&owner, "grants-store").await;
+    let viewer = app.register("grants-viewer@example.com").await;
+    let lapsed = app.register("grants-lapsed@example.com").await;
+    let revoked = app.register("grants-revoked@example.com").await;
+
+    let mut grant_ids = Vec::new();
+    for (user, level) in [
+        (&viewer, "View"),
+        (&lapsed, "View"),
+        (&revoked, "ViewAndBuy"),
+    ] {
+        let grant: StoreAccessGrant = data(
+            app.post(&format!("/api/v1/members/{}/grant", store.id))
+                .bearer_auth(&owner.token)
+                .json(&json!({ "user_id": user.id, "access_level": level }))
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        grant_ids.push(grant.id);
+    }
+    app.post(&format!(
+        "/api/v1/members/{}/revoke/{}",
+        store.id, revoked.id
+    ))
+    .bearer_auth(&owner.token)
+    .send()
+    .await
+    .unwrap();
+    sqlx::query(
+        "UPDATE store_access_grants SET expires_at = NOW() - INTERVAL '1 hour' WHERE id = $1",
+    )
+    .bind(grant_ids[1])
+    .execute(app.db())
+    .await
+    .unwrap();
+
+    let grants_path = format!("/api/v1/members/{}/grants", store.id);
+    for (status, expected) in [
+        ("active", grant_ids[0]),
+        ("expired", grant_ids[1]),
+        ("revoked", grant_ids[2]),
+    ] {
+        let listed: Vec<StoreAccessGrant> = data(
+            app.get(&format!("{grants_path}?status={status}"))
+                .bearer_auth(&owner.token)
+                .send()
+                .await
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(
+            listed.iter().map(|grant| grant.id).collect::<Vec<_>>(),
+            vec![expected]
+        );
+    }
+    let listed: Vec<StoreAccessGrant> = data(
+        app.get(&grants_path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(listed.len(), 3);
+    let response = app
+        .get(&grants_path)
+        .bearer_auth(&viewer.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let next_month = chrono::Utc::now() + chrono::Duration::days(30);
+    let extended: StoreAccessGrant = data(
+        app.patch(&format!("{grants_path}/{}", grant_ids[1]))
+            .bearer_auth(&owner.token)
+            .json(&json!({ "access_level": "ViewAndBuy", "expires_at": next_month }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(extended.access_level, AccessLevel::ViewAndBuy);
+    assert!(extended.expires_at.unwrap() > chrono::Utc::now());
+
+    let response = app
+        .patch(&format!("{grants_path}/{}", grant_ids[0]))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "expires_at": chrono::Utc::now() - chrono::Duration::days(1) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error_code(response).await, "VALIDATION_ERROR");
+    let response = app
+        .patch(&format!("{grants_path}/{}", grant_ids[2]))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "access_level": "View" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn invite_links_grant_access_until_used_up(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("invite-owner@example.com").await;
+    let store = app.create_store(&owner, "invite-store").await;
+    let first = app.register("invite-first@example.com").await;
+    let second = app.register("invite-second@example.com").await;
+    let late = app.register("invite-late@example.com").await;
+
+    let links_path = format!("/api/v1/members/{}/invite-links", store.id);
+    let response = app
+        .post(&links_path)
+        .bearer_auth(&first.token)
+        .json(&json!({ "max_uses": 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let link: Value = data(
+        app.post(&links_path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "max_uses": 2, "access_level": "View" }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let url = link["url"].as_str().unwrap();
+
+    let response = app.post(url).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let grant: StoreAccessGrant = data(
+        app.post(url)
+            .bearer_auth(&first.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(grant.store_id, store.id);
+    assert_eq!(grant.access_level, AccessLevel::View);
+    let again: StoreAccessGrant = data(
+        app.post(url)
+            .bearer_auth(&first.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(again.id, grant.id);
+
+    let response = app
+        .post(url)
+        .bearer_auth(&second.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post(url).bearer_auth(&late.token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let response = app
+        .post(&format!("{}x", url))
+        .bearer_auth(&late.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .delete(&format!("{}/{}", links_path, link["id"].as_str().unwrap()))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post(url).bearer_auth(&late.token).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn bulk_grants_report_a_result_per_recipient(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("bulk-owner@example.com").await;
+    let store = app.create_store(&owner, "bulk-store").await;
+    let first = app.register("bulk-first@example.com").await;
+    let second = app.register("bulk-second@example.com").await;
+
+    let path = format!("/api/v1/members/{}/grant-bulk", store.id);
+    let response = app
+        .post(&path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "recipients": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let too_many: Vec<String> = (0..501).map(|i| format!("bulk{}@example.com", i)).collect();
+    let response = app
+        .post(&path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "recipients": too_many }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error_code(response).await, "VALIDATION_ERROR");
+
+    let results: Vec<Value> = data(
+        app.post(&path)
+            .bearer_auth(&owner.token)
+            .json(&json!({
+                "recipients": [
+                    "BULK-FIRST@example.com",
+                    second.id,
+                    "nobody@example.com",
+                    "not-an-email",
+                    store.id,
+                    "bulk-second@example.com",
+                ],
+                "access_level": "View",
+            }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let statuses: Vec<&str> = results
+        .iter()
+        .map(|result| result["status"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        statuses,
+        ["granted", "granted", "failed", "failed", "failed", "failed"]
+    );
+    assert_eq!(results[0]["grant"]["user_id"], json!(first.id));
+    assert_eq!(results[3]["error"], "Invalid email address");
+    assert_eq!(results[5]["error"], "User is listed more than once");
+
+    let grants: Vec<StoreAccessGrant> = data(
+        app.get(&format!("/api/v1/members/{}/grants", store.id))
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(grants.len(), 2);
+    assert!(grants
+        .iter()
+        .all(|grant| grant.access_level == AccessLevel::View));
+
+    let response = app
+        .post(&path)
+        .bearer_auth(&first.token)
+        .json(&json!({ "recipients": ["bulk-second@example.com"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn custom_members_hold_the_permissions_of_their_store_role(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("roles-owner@example.com").await;
+    let store = app.create_store(&owner, "roles-store").await;
+    let clerk = app.register("roles-clerk@example.com").await;
+    let analyst = app.register("roles-analyst@example.com").await;
+
+    let roles_path = format!("/api/v1/members/{}/roles", store.id);
+    let role: Value = data(
+        app.post(&roles_path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "name": "Access desk", "permissions": ["GRANT_ACCESS"] }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let response = app
+        .post(&roles_path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "name": "access DESK", "permissions": [] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let invite_path = format!("/api/v1/members/{}/invite", store.id);
+    let response = app
+        .post(&invite_path)
+        .bearer_auth(&owner.token)
+        .json(&json!({
+            "user_id": clerk.id,
+            "role": "Staff",
+            "permissions": [],
+            "custom_role_id": role["id"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error_code(response).await, "VALIDATION_ERROR");
+    let response = app
+        .post(&invite_path)
+        .bearer_auth(&owner.token)
+        .json(&json!({
+            "user_id": clerk.id,
+            "role": "Custom",
+            "permissions": [],
+            "custom_role_id": role["id"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let grants_path = format!("/api/v1/members/{}/grants", store.id);
+    let response = app
+        .get(&grants_path)
+        .bearer_auth(&clerk.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let role_path = format!("{}/{}", roles_path, role["id"].as_str().unwrap());
+    let renamed: Value = data(
+        app.put(&role_path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "name": "Analyst", "permissions": ["VIEW_STATS"] }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(renamed["permissions"], json!(["VIEW_STATS"]));
+    let response = app
+        .get(&grants_path)
+        .bearer_auth(&clerk.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    app.post(&invite_path)
+        .bearer_auth(&owner.token)
+        .json(&json!({
+            "user_id": analyst.id,
+            "role": "Custom",
+            "permissions": [],
+            "custom_role_id": role["id"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .get(&grants_path)
+        .bearer_auth(&analyst.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let roles: Vec<Value> = data(
+        app.get(&roles_path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(roles.len(), 1);
+    let response = app
+        .delete(&role_path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app
+        .delete(&role_path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn permission_layer_guards_store_and_product_routes(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("layer-owner@example.com").await;
+    let store = app.create_store(&owner, "layer-store").await;
+    let product = app
+        .create_product(&owner, store.id, "LAYER-SKU-1", 5.0, 3)
+        .await;
+    let outsider = app.register("layer-outsider@example.com").await;
+
+    let analytics = format!("/api/v1/stores/{}/analytics", store.id);
+    let response = app.get(&analytics).send().await.unwrap();
+    assert_eq!(error_code(response).await, "AUTHENTICATION_ERROR");
+    let response = app
+        .get(&analytics)
+        .bearer_auth(&outsider.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .get(&analytics)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .get("/api/v1/stores/not-a-uuid/analytics")
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let path = format!("/api/v1/products/{}", product.id);
+    let response = app
+        .patch(&path)
+        .bearer_auth(&outsider.token)
+        .json(&json!({ "name": "Renamed" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = app
+        .patch(&format!("/api/v1/products/{}", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "name": "Renamed" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let renamed: Value = data(
+        app.patch(&path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "name": "Renamed" }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(renamed["name"], "Renamed");
+
+    let response = app.get(&path).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn my_permissions_combine_membership_visibility_and_grants(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("mine-owner@example.com").await;
+    let store = app.create_store(&owner, "mine-store").await;
+    let packer = app.register("mine-packer@example.com").await;
+    let buyer = app.register("mine-buyer@example.com").await;
+    let path = format!("/api/v1/stores/{}/my-permissions", store.id);
+
+    let mine: Value = data(
+        app.get(&path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(mine["role"], "Owner");
+    assert_eq!(mine["permissions"].as_array().unwrap().len(), 16);
+
+    let mine: Value = data(
+        app.get(&path)
+            .bearer_auth(&buyer.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(mine["role"], Value::Null);
+    assert_eq!(mine["permissions"], json!(["VIEW_PRODUCTS", "VIEW_ORDERS"]));
+
+    let role: Value = data(
+        app.post(&format!("/api/v1/members/{}/roles", store.id))
+            .bearer_auth(&owner.token)
+            .json(&json!({ "name": "Packer", "permissions": ["PROCESS_ORDERS"] }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    app.post(&format!("/api/v1/members/{}/invite", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({
+            "user_id": packer.id,
+            "role": "Custom",
+            "permissions": ["VIEW_STATS"],
+            "custom_role_id": role["id"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    app.post(&format!("/api/v1/members/{}/grant", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "user_id": buyer.id, "access_level": "ViewAndBuy" }))
+        .send()
+        .await
+        .unwrap();
+
+    let mine: Value = data(
+        app.get(&path)
+            .bearer_auth(&packer.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(mine["custom_role"], "Packer");
+    assert_eq!(
+        mine["permissions"],
+        json!([
+            "VIEW_PRODUCTS",
+            "VIEW_ORDERS",
+            "PROCESS_ORDERS",
+            "VIEW_STATS"
+        ])
+    );
+    let mine: Value = data(
+        app.get(&path)
+            .bearer_auth(&buyer.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(mine["access_level"], "ViewAndBuy");
+    assert_eq!(
+        mine["permissions"],
+        json!(["VIEW_PRODUCTS", "VIEW_ORDERS", "PROCESS_ORDERS"])
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn permission_changes_are_audited_for_the_owner(pool: PgPool) {
+    let app = TestApp::spawn(pool.clone()).await;
+    let owner = app.register("audit-owner@example.com").await;
+    let store = app.create_store(&owner, "audit-store").await;
+    let admin = app.register("audit-admin@example.com").await;
+    let guest = app.register("audit-guest@example.com").await;
+
+    app.post(&format!("/api/v1/members/{}/invite", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "user_id": admin.id, "role": "Admin", "permissions": [] }))
+        .send()
+        .await
+        .unwrap();
+    app.post(&format!("/api/v1/members/{}/grant", store.id))
+        .bearer_auth(&admin.token)
+        .json(&json!({ "user_id": guest.id, "access_level": "View" }))
+        .send()
+        .await
+        .unwrap();
+    app.post(&format!("/api/v1/members/{}/revoke/{}", store.id, guest.id))
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+
+    let path = format!("/api/v1/stores/{}/audit", store.id);
+    let response = app
+        .get(&path)
+        .bearer_auth(&admin.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(error_code(response).await, "AUTHORIZATION_ERROR");
+
+    let entries: Value = data(
+        app.get(&path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let actions: Vec<&str> = entries
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["action"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            "AccessRevoked",
+            "AccessGranted",
+            "MemberAdded",
+            "MemberAdded"
+        ]
+    );
+    let revoked = &entries[0];
+    assert_eq!(revoked["actor_id"], admin.id.to_string());
+    assert_eq!(revoked["target_user_id"], guest.id.to_string());
+    assert_eq!(revoked["before"]["is_revoked"], false);
+    assert_eq!(revoked["after"]["is_revoked"], true);
+    assert_eq!(entries[2]["target_user_id"], admin.id.to_string());
+    assert!(entries[2]["before"].is_null());
+
+    let tampered = sqlx::query("DELETE FROM permission_audit_log")
+        .execute(&pool)
+        .await;
+    assert!(tampered.is_err());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn analytics_export_requires_export_reports(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("export-owner@example.com").await;
+    let store = app.create_store(&owner, "export-store").await;
+    let analyst = app.register("export-analyst@example.com").await;
+    app.post(&format!("/api/v1/members/{}/invite", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({
+            "user_id": analyst.id,
+            "role": "Custom",
+            "permissions": ["VIEW_STATS"],
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let path = format!("/api/v1/stores/{}/analytics/export", store.id);
+    let response = app
+        .get(&path)
+        .bearer_auth(&analyst.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .get(&path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.starts_with("total_orders,total_revenue,"));
+    assert!(body.contains("\ndate,order_count,total_revenue\n"));
+    assert!(body.ends_with("product_id,product_name,units_sold,revenue\n"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn analytics_reports_are_cached_until_refreshed(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("cache-owner@example.com").await;
+    let store = app.create_store(&owner, "cache-store").await;
+
+    let path = format!("/api/v1/stores/{}/analytics", store.id);
+    let fetch = |query: &'static str| {
+        let request = app
+            .get(&format!("{}{}", path, query))
+            .bearer_auth(&owner.token);
+        async move { data::<Value>(request.send().await.unwrap()).await }
+    };
+
+    let first = fetch("").await;
+    assert_eq!(first["cached"], false);
+    let second = fetch("").await;
+    assert_eq!(second["cached"], true);
+    assert_eq!(second["generated_at"], first["generated_at"]);
+
+    let other_range = fetch("?days=7").await;
+    assert_eq!(other_range["cached"], false);
+
+    let refreshed = fetch("?refresh=true").await;
+    assert_eq!(refreshed["cached"], false);
+    assert_ne!(refreshed["generated_at"], first["generated_at"]);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn cached_listings_and_permissions_follow_writes(pool: PgPool) {
+    let app = TestApp::spawn(pool.clone()).await;
+    let owner = app.register("cached-owner@example.com").await;
+    let buyer = app.register("cached-buyer@example.com").await;
+    let store = app.create_store(&owner, "cached-store").await;
+    let product = app
+        .create_product(&owner, store.id, "CACHED-1", 10.0, 5)
+        .await;
+
+    let listing_path = format!("/api/v1/products/store/{}", store.id);
+    let listing = || {
+        let request = app.get(&listing_path);
+        async move { data::<Vec<Value>>(request.send().await.unwrap()).await }
+    };
+    assert_eq!(listing().await[0]["name"], "Product CACHED-1");
+
+    // Written behind the repository's back, so the cached page is served
+    sqlx::query("UPDATE products SET name = 'Renamed in SQL' WHERE id = $1")
+        .bind(product.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    assert_eq!(listing().await[0]["name"], "Product CACHED-1");
+
+    let updated: Value = data(
+        app.patch(&format!("/api/v1/products/{}", product.id))
+            .bearer_auth(&owner.token)
+            .json(&json!({ "price": 12.5 }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let products = listing().await;
+    assert_eq!(products[0]["name"], "Renamed in SQL");
+    assert_eq!(products[0]["price"], updated["price"]);
+
+    let permissions_path = format!("/api/v1/stores/{}/my-permissions", store.id);
+    let mine = || {
+        let request = app.get(&permissions_path).bearer_auth(&buyer.token);
+        async move { data::<Value>(request.send().await.unwrap()).await }
+    };
+    assert_eq!(mine().await["access_level"], Value::Null);
+
+    app.post(&format!("/api/v1/members/{}/grant", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "user_id": buyer.id, "access_level": "ViewAndBuy" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(mine().await["access_level"], "ViewAndBuy");
+}
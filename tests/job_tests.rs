@@ -0,0 +1,132 @@
+use std::sync::{
+    atomic::{AtomicI32, Ordering},
+    Arc,
+};
+
+use async_trait::async_trait;
+use markethub::{
+    jobs::{JobHandler, JobQueue, JobRegistry, JobWorker, WorkerRun},
+    metrics::Metrics,
+    models::job::{Job, JobStatus},
+    repositories::JobRepository,
+};
+use serde_json::json;
+use sqlx::PgPool;
+
+/// Fails until the job's `succeed_on` attempt.
+#[derive(Default)]
+struct Flaky {
+    runs: AtomicI32,
+}
+
+#[async_trait]
+impl JobHandler for Flaky {
+    async fn run(&self, job: &Job) -> anyhow::Result<()> {
+        self.runs.fetch_add(1, Ordering::SeqCst);
+        if i64::from(job.attempts) < job.payload["succeed_on"].as_i64().unwrap() {
+            anyhow::bail!("not yet");
+        }
+        Ok(())
+    }
+}
+
+async fn make_due(pool: &PgPool) {
+    sqlx::query("UPDATE jobs SET run_at = NOW() WHERE status = 'Pending'")
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn failed_jobs_are_retried_with_backoff_until_they_succeed(pool: PgPool) {
+    let flaky = Arc::new(Flaky::default());
+    let metrics = Arc::new(Metrics::new());
+    let worker = JobWorker::new(
+        pool.clone(),
+        JobRegistry::new().register("flaky", flaky.clone()),
+        metrics.clone(),
+    );
+    let job = JobQueue::new(pool.clone())
+        .enqueue("flaky", &json!({ "succeed_on": 2 }))
+        .await
+        .unwrap();
+
+    let run = worker.run_due(10).await.unwrap();
+    assert_eq!(
+        run,
+        WorkerRun {
+            retrying: 1,
+            ..Default::default()
+        }
+    );
+    let retrying = JobRepository::new(pool.clone())
+        .find_by_id(job.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(retrying.status, JobStatus::Pending);
+    assert_eq!(retrying.attempts, 1);
+    assert_eq!(retrying.last_error.as_deref(), Some("not yet"));
+    assert!(retrying.run_at > job.run_at + chrono::Duration::seconds(30));
+
+    // Not due again until the backoff has passed
+    assert_eq!(worker.run_due(10).await.unwrap(), WorkerRun::default());
+
+    make_due(&pool).await;
+    let run = worker.run_due(10).await.unwrap();
+    assert_eq!(run.succeeded, 1);
+    let done = JobRepository::new(pool.clone())
+        .find_by_id(job.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(done.status, JobStatus::Succeeded);
+    assert!(done.finished_at.is_some());
+    assert_eq!(flaky.runs.load(Ordering::SeqCst), 2);
+
+    worker.sample_queue_depth().await.unwrap();
+    let encoded = metrics.encode().unwrap();
+    assert!(encoded.contains("markethub_job_runs_total{kind=\"flaky\",outcome=\"retrying\"} 1"));
+    assert!(encoded.contains("markethub_job_runs_total{kind=\"flaky\",outcome=\"succeeded\"} 1"));
+    assert!(encoded.contains("markethub_job_queue_depth{kind=\"flaky\"} 0"));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn jobs_out_of_attempts_fail_and_unknown_kinds_stay_queued(pool: PgPool) {
+    let metrics = Arc::new(Metrics::new());
+    let worker = JobWorker::new(
+        pool.clone(),
+        JobRegistry::new().register("flaky", Arc::new(Flaky::default())),
+        metrics.clone(),
+    );
+    let queue = JobQueue::new(pool.clone()).with_max_attempts(2);
+    let doomed = queue
+        .enqueue("flaky", &json!({ "succeed_on": 5 }))
+        .await
+        .unwrap();
+    let unknown = queue.enqueue("unknown", &json!({})).await.unwrap();
+
+    worker.run_due(10).await.unwrap();
+    make_due(&pool).await;
+    let run = worker.run_due(10).await.unwrap();
+    assert_eq!(
+        run,
+        WorkerRun {
+            failed: 1,
+            ..Default::default()
+        }
+    );
+
+    let jobs = JobRepository::new(pool.clone());
+    let doomed = jobs.find_by_id(doomed.id).await.unwrap().unwrap();
+    assert_eq!(doomed.status, JobStatus::Failed);
+    assert_eq!(doomed.attempts, 2);
+    let unknown = jobs.find_by_id(unknown.id).await.unwrap().unwrap();
+    assert_eq!(unknown.status, JobStatus::Pending);
+    assert_eq!(unknown.attempts, 0);
+
+    worker.sample_queue_depth().await.unwrap();
+    let encoded = metrics.encode().unwrap();
+    assert!(encoded.contains("markethub_job_runs_total{kind=\"flaky\",outcome=\"failed\"} 1"));
+    assert!(encoded.contains("markethub_job_queue_depth{kind=\"unknown\"} 1"));
+}
@@ -0,0 +1,321 @@
+mod common;
+
+use markethub::{
+    error::AppError,
+    models::{
+        order::{AddCartItemRequest, CheckoutRequest},
+        store::{
+            AppealStatus, CreateAppealRequest, ModerateStoreRequest, ResolveAppealRequest,
+            StoreStatus,
+        },
+    },
+    repositories::{
+        CartRepository, CouponRepository, CreditRepository, FulfillmentRepository,
+        ModerationRepository, NotificationRepository, OrderRepository, ProductRepository,
+        StoreRepository, StoreSettingsRepository,
+    },
+    services::{
+        CartService, ModerationService, OrderService, PermissionService, StoreLifecycleService,
+    },
+};
+use sqlx::PgPool;
+
+fn moderation_service(pool: &PgPool) -> ModerationService {
+    ModerationService::new(
+        StoreRepository::new(pool.clone()),
+        ModerationRepository::new(pool.clone()),
+    )
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn only_platform_admins_pass_admin_check(pool: PgPool) {
+    let admin = common::insert_user(&pool, "admin@markethub.dev").await;
+    let regular = common::insert_user(&pool, "regular@markethub.dev").await;
+    common::promote_to_platform_admin(&pool, admin.id).await;
+
+    let permissions = PermissionService::new(pool.clone());
+    permissions
+        .ensure_platform_admin(admin.id)
+        .await
+        .expect("admins should pass");
+
+    let err = permissions
+        .ensure_platform_admin(regular.id)
+        .await
+        .expect_err("regular users should be rejected");
+    assert!(matches!(err, AppError::Authorization(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn suspended_stores_block_checkout(pool: PgPool) {
+    let admin = common::insert_user(&pool, "mod-admin@markethub.dev").await;
+    let owner = common::insert_user(&pool, "mod-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "mod-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "moderated-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-MOD", 10.0, 5).await;
+
+    CartService::new(
+        CartRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+    )
+    .add_item(
+        shopper.id,
+        AddCartItemRequest {
+            product_id: product.id,
+            quantity: 1,
+        },
+    )
+    .await
+    .unwrap();
+
+    let moderation = moderation_service(&pool);
+    let response = moderation
+        .moderate_store(
+            admin.id,
+            store.id,
+            ModerateStoreRequest {
+                status: StoreStatus::Suspended,
+                reason: "Counterfeit listings reported".into(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.store.status, StoreStatus::Suspended);
+    assert_eq!(response.action.previous_status, StoreStatus::Active);
+    assert_eq!(response.action.reason, "Counterfeit listings reported");
+
+    let orders = OrderService::new(
+        OrderRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    );
+    let err = orders
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .expect_err("checkout should be blocked for suspended stores");
+    assert!(matches!(err, AppError::Conflict(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn approved_appeals_reinstate_the_store(pool: PgPool) {
+    let admin = common::insert_user(&pool, "appeal-admin@markethub.dev").await;
+    let owner = common::insert_user(&pool, "appeal-owner@markethub.dev").await;
+    let stranger = common::insert_user(&pool, "appeal-stranger@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "appeal-store", false).await;
+    let moderation = moderation_service(&pool);
+
+    let err = moderation
+        .submit_appeal(
+            owner.id,
+            store.id,
+            CreateAppealRequest {
+                message: "Nothing to appeal here yet".into(),
+            },
+        )
+        .await
+        .expect_err("active stores cannot be appealed");
+    assert!(matches!(err, AppError::BadRequest(_)));
+
+    moderation
+        .moderate_store(
+            admin.id,
+            store.id,
+            ModerateStoreRequest {
+                status: StoreStatus::Suspended,
+                reason: "Policy violation".into(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let err = moderation
+        .submit_appeal(
+            stranger.id,
+            store.id,
+            CreateAppealRequest {
+                message: "I am not the owner of this store".into(),
+            },
+        )
+        .await
+        .expect_err("only owners can appeal");
+    assert!(matches!(err, AppError::Authorization(_)));
+
+    let appeal = moderation
+        .submit_appeal(
+            owner.id,
+            store.id,
+            CreateAppealRequest {
+                message: "The reported listings have been removed".into(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(appeal.status, AppealStatus::Pending);
+
+    let err = moderation
+        .submit_appeal(
+            owner.id,
+            store.id,
+            CreateAppealRequest {
+                message: "Submitting a second appeal".into(),
+            },
+        )
+        .await
+        .expect_err("only one pending appeal is allowed");
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    let resolved = moderation
+        .resolve_appeal(
+            admin.id,
+            appeal.id,
+            ResolveAppealRequest {
+                approve: true,
+                note: Some("Listings verified".into()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(resolved.status, AppealStatus::Approved);
+    assert_eq!(resolved.reviewed_by, Some(admin.id));
+
+    let store = StoreRepository::new(pool.clone())
+        .find_by_id(store.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(store.status, StoreStatus::Active);
+
+    let history = moderation.moderation_history(store.id).await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].new_status, StoreStatus::Active);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn closing_a_store_cleans_up_products_and_carts(pool: PgPool) {
+    let admin = common::insert_user(&pool, "close-admin@markethub.dev").await;
+    let owner = common::insert_user(&pool, "close-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "close-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "closing-store", false).await;
+    let other = common::create_store(&pool, owner.id, "open-store", false).await;
+    let closing_product = common::create_product(&pool, store.id, "SKU-CLOSE", 10.0, 5).await;
+    let open_product = common::create_product(&pool, other.id, "SKU-OPEN", 10.0, 5).await;
+
+    let carts = CartService::new(
+        CartRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+    );
+    carts
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: closing_product.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    OrderService::new(
+        OrderRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    )
+    .checkout(
+        shopper.id,
+        CheckoutRequest {
+            shipping_address: common::shipping_address(),
+            confirm_duplicate: false,
+            custom_fields: Default::default(),
+            shipping_options: Default::default(),
+        },
+    )
+    .await
+    .unwrap();
+    for product_id in [closing_product.id, open_product.id] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    moderation_service(&pool)
+        .moderate_store(
+            admin.id,
+            store.id,
+            ModerateStoreRequest {
+                status: StoreStatus::Closed,
+                reason: "Merchant requested closure".into(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let lifecycle = StoreLifecycleService::new(
+        StoreRepository::new(pool.clone()),
+        ProductRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+    );
+    let cleanups = lifecycle.cleanup_closed_stores(10).await.unwrap();
+    assert_eq!(cleanups.len(), 1);
+    assert_eq!(cleanups[0].store_id, store.id);
+    assert_eq!(cleanups[0].products_deactivated, 1);
+    assert_eq!(cleanups[0].cart_items_removed, 1);
+    assert_eq!(cleanups[0].customers_notified, 1);
+
+    let product = ProductRepository::new(pool.clone())
+        .find_by_id(closing_product.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!product.is_active);
+
+    let cart = carts.list_items(shopper.id).await.unwrap();
+    assert_eq!(cart.len(), 1);
+    assert_eq!(cart[0].product_id, open_product.id);
+
+    let notifications = NotificationRepository::new(pool.clone())
+        .list_for_user(shopper.id, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].store_id, Some(store.id));
+
+    let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders WHERE store_id = $1")
+        .bind(store.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(orders, 1, "orders are retained after closure");
+
+    assert!(lifecycle
+        .cleanup_closed_stores(10)
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use common::shipping_address;
+use markethub::{
+    jobs::{JobRegistry, JobWorker},
+    metrics::Metrics,
+    models::{
+        domain_event::{DomainEvent, PublishedEvent},
+        store::MemberRole,
+    },
+    outbox::{EventSubscriber, OutboxRelay},
+    testing::{data, TestApp},
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+#[derive(Default)]
+struct Recorder {
+    events: Mutex<Vec<PublishedEvent>>,
+}
+
+#[async_trait]
+impl EventSubscriber for Recorder {
+    async fn handle(&self, event: &PublishedEvent) -> anyhow::Result<()> {
+        self.events.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+async fn unpublished_types(pool: &PgPool) -> Vec<String> {
+    sqlx::query_scalar(
+        "SELECT event_type FROM outbox_events WHERE published_at IS NULL ORDER BY event_type",
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap()
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn committed_changes_publish_their_events_to_every_subscriber(pool: PgPool) {
+    let app = TestApp::spawn(pool.clone()).await;
+    let owner = app.register("outbox-owner@example.com").await;
+    let store = app.create_store(&owner, "outbox-store").await;
+    let product = app
+        .create_product(&owner, store.id, "OUTBOX-1", 10.0, 2)
+        .await;
+    let shopper = app.register("outbox-shopper@example.com").await;
+
+    app.post(&format!("/api/v1/members/{}/invite", store.id))
+        .bearer_auth(&owner.token)
+        .json(&json!({ "user_id": shopper.id, "role": "Staff", "permissions": [] }))
+        .send()
+        .await
+        .unwrap();
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 2 }))
+        .send()
+        .await
+        .unwrap();
+    let _: Value = data(
+        app.post("/api/v1/orders/checkout")
+            .bearer_auth(&shopper.token)
+            .json(&json!({ "shipping_address": shipping_address() }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    // The product is sold out; a failed checkout leaves no event behind
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&owner.token)
+        .json(&json!({ "product_id": product.id, "quantity": 1 }))
+        .send()
+        .await
+        .unwrap();
+    let response = app
+        .post("/api/v1/orders/checkout")
+        .bearer_auth(&owner.token)
+        .json(&json!({ "shipping_address": shipping_address() }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_client_error());
+
+    // Recorded with their changes, waiting for the relay
+    assert_eq!(
+        unpublished_types(&pool).await,
+        ["MemberInvited", "OrderCreated", "StockDepleted"]
+    );
+
+    let audit = Arc::new(Recorder::default());
+    let mailer = Arc::new(Recorder::default());
+    let relay = OutboxRelay::new(pool.clone())
+        .subscribe("audit", audit.clone())
+        .subscribe("mailer", mailer.clone());
+    let worker = JobWorker::new(
+        pool.clone(),
+        relay.register_jobs(JobRegistry::new()),
+        Arc::new(Metrics::new()),
+    );
+
+    assert_eq!(relay.relay_pending(100).await.unwrap(), 3);
+    assert!(unpublished_types(&pool).await.is_empty());
+    assert_eq!(relay.relay_pending(100).await.unwrap(), 0);
+    assert_eq!(worker.run_due(100).await.unwrap().succeeded, 6);
+
+    let events: Vec<DomainEvent> = audit
+        .events
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|published| published.event.clone())
+        .collect();
+    assert_eq!(events.len(), 3);
+    assert!(events.contains(&DomainEvent::MemberInvited {
+        store_id: store.id,
+        user_id: shopper.id,
+        role: MemberRole::Staff,
+        invited_by: owner.id,
+    }));
+    assert!(events.contains(&DomainEvent::StockDepleted {
+        product_id: product.id,
+        store_id: store.id,
+    }));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        DomainEvent::OrderCreated { store_id, buyer_id, .. }
+            if *store_id == store.id && *buyer_id == shopper.id
+    )));
+    let ids = |recorder: &Recorder| {
+        let mut ids: Vec<_> = recorder
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|e| e.id)
+            .collect();
+        ids.sort();
+        ids
+    };
+    assert_eq!(ids(&mailer), ids(&audit));
+}
@@ -63,3 +63,27 @@ async fn public_stores_allow_guest_viewing(pool: PgPool) {
         .await
         .expect("public stores should allow viewing products without membership");
 }
+
+#[sqlx::test(migrations = "./migrations")]
+async fn member_permissions_ignore_public_visibility(pool: PgPool) {
+    let owner = common::insert_user(&pool, "owner4@markethub.dev").await;
+    let guest = common::insert_user(&pool, "guest2@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "public-orders-store", false).await;
+
+    let service = PermissionService::new(pool.clone());
+    service
+        .ensure_store_permission(guest.id, store.id, Permission::ViewOrders)
+        .await
+        .expect("public stores expose ViewOrders to everyone");
+
+    let err = service
+        .ensure_member_permission(guest.id, store.id, Permission::ViewOrders)
+        .await
+        .expect_err("non-members must not see store orders");
+    assert!(matches!(err, AppError::Authorization(_)));
+
+    service
+        .ensure_member_permission(owner.id, store.id, Permission::ViewOrders)
+        .await
+        .expect("owners can view their store's orders");
+}
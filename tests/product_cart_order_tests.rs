@@ -1,18 +1,74 @@
 mod common;
 
 use markethub::{
+    currency::{ExchangeRates, StaticRates},
     error::AppError,
-    models::order::{AddCartItemRequest, CheckoutRequest},
-    repositories::{CartRepository, OrderRepository, ProductRepository},
-    services::{cart_service::CartService, order_service::OrderService},
+    models::{
+        coupon::{ApplyCouponRequest, CouponKind, CreateCouponRequest},
+        credit::{CreditEntryKind, IssueCreditRequest},
+        fulfillment::{AssignFulfillmentPartnerRequest, CreateFulfillmentPartnerRequest, RateTier},
+        inventory::{AdjustInventoryRequest, InventoryMovementKind},
+        notification::{
+            CreateNotificationRuleRequest, DeliveryStatus, NotificationChannel,
+            NotificationDelivery, LOW_STOCK, NEW_ORDER, ORDER_CANCELLED,
+        },
+        order::{
+            AddCartItemRequest, BulkOrderStatusRequest, CancelOrderRequest, CartLineWarning,
+            CartOwner, CheckoutRequest, CreateShipmentRequest, FulfillmentStatus, OrderStatus,
+            PaymentStatus, ShipmentItemRequest, ShipmentStatus, StoreOrderFilter,
+            UpdateOrderStatusRequest, UpdateShipmentRequest,
+        },
+        payment::{PayOrderGroupRequest, PaymentAttemptStatus, PaymentProviderKind},
+        product::{CompareProductsRequest, ExportFormat, ReorderProductImagesRequest},
+        shipping::{CreateShippingRuleRequest, ShippingRuleKind, ShippingTier},
+        store::{
+            CheckoutFieldType, CreateCheckoutFieldRequest, MemberRole, TaxProviderKind,
+            TaxReportPeriod, UpdateStoreSettingsRequest,
+        },
+        tax::{TaxFiling, TaxTransactionStatus},
+    },
+    payments::{PaymentEvent, PaymentIntent, PaymentProvider, PaymentProviders, PaymentRequest},
+    repositories::StoreRepository,
+    repositories::{
+        AnalyticsRepository, AuditRepository, CartRepository, CouponRepository, CreditRepository,
+        FulfillmentRepository, InventoryRepository, MemberRepository, NotificationRepository,
+        OrderRepository, PaymentRepository, ProductFileRepository, ProductImageRepository,
+        ProductRepository, RecentlyViewedRepository, RecommendationRepository,
+        StoreSettingsRepository, TaxRepository,
+    },
+    services::{
+        analytics_service::AnalyticsService, cart_service::CartService,
+        credit_service::CreditService, fulfillment_service::FulfillmentService,
+        order_service::OrderService, recently_viewed_service::RECENTLY_VIEWED_LIMIT,
+        search_service::SuggestionCache, share_card_service::ShareCardService,
+        store_settings_service::StoreSettingsService, AuditService, ComparisonService,
+        CouponService, CurrencyService, DownloadService, InventoryService, NotificationService,
+        OrderStatusService, PaymentService, ProductFileService, ProductImageService,
+        ProductService, PublicOrderService, RecentlyViewedService, RecommendationService,
+        SearchService, ShippingService, StoreService, TaxService, UnpaidOrderService,
+    },
+    shipping::{CarrierQuote, CarrierRateProvider, ShippingParcel, ShippingRates},
+    storage::LocalStorage,
+    tax::{TaxLookup, TaxProvider, TaxRates},
+    utils::public_id::PublicIds,
 };
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use markethub::notifier::Notifier;
+
+use bytes::Bytes;
+use futures_util::TryStreamExt;
 use rust_decimal::Decimal;
 use sqlx::{query, PgPool};
+use uuid::Uuid;
 
 fn cart_service(pool: &PgPool) -> CartService {
     CartService::new(
         CartRepository::new(pool.clone()),
         ProductRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
     )
 }
 
@@ -21,7 +77,21 @@ fn order_service(pool: &PgPool) -> OrderService {
         OrderRepository::new(pool.clone()),
         ProductRepository::new(pool.clone()),
         CartRepository::new(pool.clone()),
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    )
+}
+
+fn payment_service(pool: &PgPool, providers: PaymentProviders) -> PaymentService {
+    PaymentService::new(
+        PaymentRepository::new(pool.clone()),
+        OrderRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
     )
+    .with_providers(Arc::new(providers))
 }
 
 #[sqlx::test(migrations = "./migrations")]
@@ -120,6 +190,9 @@ async fn checkout_groups_orders_and_clears_cart(pool: PgPool) {
             shopper.id,
             CheckoutRequest {
                 shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await
@@ -135,7 +208,7 @@ async fn checkout_groups_orders_and_clears_cart(pool: PgPool) {
     assert_eq!(summary.order_group.user_id, shopper.id);
 
     let cart_items = CartRepository::new(pool.clone())
-        .list_with_products(shopper.id)
+        .list_with_products(shopper.id.into())
         .await
         .unwrap();
     assert!(
@@ -149,3 +222,4377 @@ async fn checkout_groups_orders_and_clears_cart(pool: PgPool) {
     assert_eq!(updated_a.stock_quantity, 8);
     assert_eq!(updated_b.stock_quantity, 7);
 }
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkout_consolidates_shipping_for_shared_fulfillment_partner(pool: PgPool) {
+    let owner = common::insert_user(&pool, "consolidation-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "consolidation-shopper@markethub.dev").await;
+    let store_a = common::create_store(&pool, owner.id, "consolidated-a", false).await;
+    let store_b = common::create_store(&pool, owner.id, "consolidated-b", false).await;
+    let store_c = common::create_store(&pool, owner.id, "standalone-c", false).await;
+
+    let fulfillment = FulfillmentService::new(
+        FulfillmentRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    let partner = fulfillment
+        .create_partner(CreateFulfillmentPartnerRequest {
+            name: "Shared Logistics".into(),
+            rate_table: vec![
+                RateTier {
+                    min_subtotal: Decimal::ZERO,
+                    fee: Decimal::new(900, 2),
+                },
+                RateTier {
+                    min_subtotal: Decimal::new(500, 0),
+                    fee: Decimal::ZERO,
+                },
+            ],
+        })
+        .await
+        .unwrap();
+    let settings = StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    for store_id in [store_a.id, store_b.id] {
+        fulfillment
+            .assign_store(
+                store_id,
+                AssignFulfillmentPartnerRequest {
+                    fulfillment_partner_id: Some(partner.id),
+                },
+            )
+            .await
+            .unwrap();
+        settings
+            .update_settings(
+                store_id,
+                UpdateStoreSettingsRequest {
+                    currency_code: None,
+                    tax_rate: None,
+                    flat_shipping_fee: Some(5.0),
+                    free_shipping_threshold: None,
+                    ships_to: None,
+                    tax_provider: None,
+                    payment_provider: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let carts = cart_service(&pool);
+    for (store_id, sku, price) in [
+        (store_a.id, "SKU-CA", 20.0),
+        (store_b.id, "SKU-CB", 40.0),
+        (store_c.id, "SKU-CC", 10.0),
+    ] {
+        let product = common::create_product(&pool, store_id, sku, price, 5).await;
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let shipping_for = |store_id| {
+        summary
+            .orders
+            .iter()
+            .find(|order| order.store_id == store_id)
+            .map(|order| order.shipping_cost)
+            .unwrap()
+    };
+    assert_eq!(
+        shipping_for(store_a.id) + shipping_for(store_b.id),
+        Decimal::new(900, 2)
+    );
+    assert_eq!(shipping_for(store_a.id), Decimal::new(300, 2));
+    assert_eq!(shipping_for(store_c.id), Decimal::ZERO);
+
+    let total_orders: Decimal = summary.orders.iter().map(|order| order.total_amount).sum();
+    assert_eq!(summary.order_group.total_amount, total_orders);
+    assert_eq!(total_orders, Decimal::new(7900, 2));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkout_applies_store_tax_and_shipping_settings(pool: PgPool) {
+    let owner = common::insert_user(&pool, "settings-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "settings-shopper@markethub.dev").await;
+    let taxed = common::create_store(&pool, owner.id, "taxed-store", false).await;
+    let free_shipping = common::create_store(&pool, owner.id, "free-shipping-store", false).await;
+
+    let settings = StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    settings
+        .update_settings(
+            taxed.id,
+            UpdateStoreSettingsRequest {
+                currency_code: Some("USD".into()),
+                tax_rate: Some(0.1),
+                flat_shipping_fee: Some(4.5),
+                free_shipping_threshold: Some(100.0),
+                ships_to: None,
+                tax_provider: None,
+                payment_provider: None,
+            },
+        )
+        .await
+        .unwrap();
+    settings
+        .update_settings(
+            free_shipping.id,
+            UpdateStoreSettingsRequest {
+                currency_code: None,
+                tax_rate: None,
+                flat_shipping_fee: Some(7.0),
+                free_shipping_threshold: Some(20.0),
+                ships_to: None,
+                tax_provider: None,
+                payment_provider: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let product_taxed = common::create_product(&pool, taxed.id, "SKU-TAX", 25.0, 5).await;
+    let product_free = common::create_product(&pool, free_shipping.id, "SKU-FREE", 30.0, 5).await;
+    let carts = cart_service(&pool);
+    for product_id in [product_taxed.id, product_free.id] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity: 2,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let taxed_order = summary
+        .orders
+        .iter()
+        .find(|order| order.store_id == taxed.id)
+        .unwrap();
+    assert_eq!(taxed_order.subtotal, Decimal::new(5000, 2));
+    assert_eq!(taxed_order.tax, Decimal::new(500, 2));
+    assert_eq!(taxed_order.shipping_cost, Decimal::new(450, 2));
+    assert_eq!(taxed_order.total_amount, Decimal::new(5950, 2));
+
+    let free_order = summary
+        .orders
+        .iter()
+        .find(|order| order.store_id == free_shipping.id)
+        .unwrap();
+    assert_eq!(free_order.tax, Decimal::ZERO);
+    assert_eq!(free_order.shipping_cost, Decimal::ZERO);
+    assert_eq!(free_order.total_amount, Decimal::new(6000, 2));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn order_listings_read_from_projection(pool: PgPool) {
+    let owner = common::insert_user(&pool, "projection-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "projection-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "projection-store", false).await;
+
+    let first = common::create_product(&pool, store.id, "SKU-PROJ-1", 10.0, 10).await;
+    let second = common::create_product(&pool, store.id, "SKU-PROJ-2", 5.0, 10).await;
+    for product in [&first, &second] {
+        query("UPDATE products SET thumbnail_url = $2 WHERE id = $1")
+            .bind(product.id)
+            .bind(format!("https://cdn.markethub.dev/{}.png", product.sku))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let carts = cart_service(&pool);
+    for (product_id, quantity) in [(first.id, 2), (second.id, 3)] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let orders = order_service(&pool);
+    let summary = orders
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order_id = summary.orders[0].id;
+
+    let listed = orders.list_orders(shopper.id, 20, 0).await.unwrap();
+    assert_eq!(listed.len(), 1);
+    let entry = &listed[0];
+    assert_eq!(entry.order_id, order_id);
+    assert_eq!(entry.buyer_name, "Test User");
+    assert_eq!(entry.item_count, 5);
+    let first_item = [&first, &second]
+        .into_iter()
+        .find(|product| Some(product.id) == entry.first_item_product_id)
+        .expect("projection should reference an ordered product");
+    assert_eq!(
+        entry.first_item_thumbnail_url,
+        Some(format!("https://cdn.markethub.dev/{}.png", first_item.sku))
+    );
+
+    query("UPDATE users SET full_name = 'Renamed Shopper' WHERE id = $1")
+        .bind(shopper.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    OrderRepository::new(pool.clone())
+        .update_status(order_id, OrderStatus::Shipped)
+        .await
+        .unwrap();
+
+    let store_orders = orders
+        .list_store_orders(
+            store.id,
+            &StoreOrderFilter {
+                status: Some(OrderStatus::Shipped),
+                ..Default::default()
+            },
+            20,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(store_orders.len(), 1);
+    assert_eq!(store_orders[0].buyer_name, "Renamed Shopper");
+    assert_eq!(store_orders[0].status, OrderStatus::Shipped);
+
+    let pending = orders
+        .list_store_orders(
+            store.id,
+            &StoreOrderFilter {
+                status: Some(OrderStatus::Pending),
+                ..Default::default()
+            },
+            20,
+            0,
+        )
+        .await
+        .unwrap();
+    assert!(pending.is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkout_applies_store_credit_before_charging(pool: PgPool) {
+    let owner = common::insert_user(&pool, "credit-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "credit-shopper@markethub.dev").await;
+    let credited = common::create_store(&pool, owner.id, "credited-store", false).await;
+    let covered = common::create_store(&pool, owner.id, "covered-store", false).await;
+
+    let credits = CreditService::new(
+        CreditRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    for (store_id, amount) in [(credited.id, 15.0), (covered.id, 50.0)] {
+        credits
+            .issue_credit(
+                owner.id,
+                store_id,
+                IssueCreditRequest {
+                    user_id: shopper.id,
+                    amount,
+                    kind: CreditEntryKind::Goodwill,
+                    order_id: None,
+                    note: Some("Sorry for the delay".into()),
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let partial = common::create_product(&pool, credited.id, "SKU-CREDIT-1", 20.0, 5).await;
+    let full = common::create_product(&pool, covered.id, "SKU-CREDIT-2", 10.0, 5).await;
+    let carts = cart_service(&pool);
+    for product_id in [partial.id, full.id] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let partial_order = summary
+        .orders
+        .iter()
+        .find(|order| order.store_id == credited.id)
+        .unwrap();
+    assert_eq!(partial_order.credit_applied, Decimal::new(1500, 2));
+    assert_eq!(partial_order.total_amount, Decimal::new(500, 2));
+
+    let covered_order = summary
+        .orders
+        .iter()
+        .find(|order| order.store_id == covered.id)
+        .unwrap();
+    assert_eq!(covered_order.credit_applied, Decimal::new(1000, 2));
+    assert_eq!(covered_order.total_amount, Decimal::ZERO);
+    assert_eq!(summary.order_group.total_amount, Decimal::new(500, 2));
+
+    let balances = credits.balances_for_user(shopper.id).await.unwrap();
+    assert_eq!(balances.len(), 1);
+    assert_eq!(balances[0].store_id, covered.id);
+    assert_eq!(balances[0].balance, Decimal::new(4000, 2));
+
+    let ledger = credits
+        .ledger(credited.id, shopper.id, 10, 0)
+        .await
+        .unwrap();
+    assert_eq!(ledger.len(), 2);
+    let redemption = ledger
+        .iter()
+        .find(|entry| entry.kind == CreditEntryKind::Redemption)
+        .unwrap();
+    assert_eq!(redemption.amount, Decimal::new(-1500, 2));
+    assert_eq!(redemption.order_id, Some(partial_order.id));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn share_card_uses_store_currency_and_hides_private_products(pool: PgPool) {
+    let owner = common::insert_user(&pool, "card-owner@markethub.dev").await;
+    let public = common::create_store(&pool, owner.id, "card-store", false).await;
+    let private = common::create_store(&pool, owner.id, "card-private", true).await;
+    let listed = common::create_product(&pool, public.id, "SKU-CARD-1", 42.0, 3).await;
+    let hidden = common::create_product(&pool, private.id, "SKU-CARD-2", 42.0, 3).await;
+
+    StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    )
+    .update_settings(
+        public.id,
+        UpdateStoreSettingsRequest {
+            currency_code: Some("EUR".into()),
+            tax_rate: None,
+            flat_shipping_fee: None,
+            free_shipping_threshold: None,
+            ships_to: None,
+            tax_provider: None,
+            payment_provider: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let cards = ShareCardService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+    );
+    let card = cards.product_card(listed.id).await.unwrap();
+    assert_eq!(card.title, listed.name);
+    assert_eq!(card.currency, "EUR");
+    assert_eq!(card.price, Decimal::new(4200, 2));
+    assert_eq!(card.site_name, public.name);
+
+    let err = cards.product_card(hidden.id).await.unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn identical_repeat_checkout_requires_confirmation(pool: PgPool) {
+    let owner = common::insert_user(&pool, "dup-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "dup-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "dup-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-DUP", 12.5, 10).await;
+
+    let carts = cart_service(&pool);
+    let orders = order_service(&pool);
+    let checkout = |confirm_duplicate| CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate,
+        custom_fields: Default::default(),
+        shipping_options: Default::default(),
+    };
+    let fill_cart = |quantity| {
+        let carts = carts.clone();
+        async move {
+            carts
+                .add_item(
+                    shopper.id,
+                    AddCartItemRequest {
+                        product_id: product.id,
+                        quantity,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+    };
+
+    fill_cart(2).await;
+    orders.checkout(shopper.id, checkout(false)).await.unwrap();
+
+    fill_cart(2).await;
+    let err = orders
+        .checkout(shopper.id, checkout(false))
+        .await
+        .expect_err("identical checkout should be flagged");
+    assert!(matches!(err, AppError::Conflict(_)));
+    assert_eq!(carts.list_items(shopper.id).await.unwrap().len(), 1);
+
+    orders
+        .checkout(shopper.id, checkout(true))
+        .await
+        .expect("confirmed duplicates go through");
+
+    fill_cart(3).await;
+    orders
+        .checkout(shopper.id, checkout(false))
+        .await
+        .expect("a different basket is not a duplicate");
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn product_gallery_upload_reorder_and_delete(pool: PgPool) {
+    let owner = common::insert_user(&pool, "gallery-owner@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "gallery-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-GALLERY", 10.0, 5).await;
+
+    let root = std::env::temp_dir().join(format!("markethub-{}", uuid::Uuid::new_v4()));
+    let images = ProductImageService::new(
+        ProductRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+        Arc::new(LocalStorage::new(&root, "https://cdn.markethub.dev/")),
+    );
+
+    let png = Bytes::from_static(b"\x89PNG\r\n\x1a\nfirst");
+    let jpeg = Bytes::from_static(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00]);
+    let first = images.upload(product.id, png).await.unwrap();
+    let second = images.upload(product.id, jpeg).await.unwrap();
+    assert_eq!((first.position, second.position), (0, 1));
+    assert_eq!(second.content_type, "image/jpeg");
+    assert!(first.url.starts_with("https://cdn.markethub.dev/products/"));
+    assert!(root.join(&first.storage_key).exists());
+
+    let err = images
+        .upload(product.id, Bytes::from_static(b"<svg/>"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+    let listed = products.get_product(product.id).await.unwrap();
+    assert_eq!(listed.thumbnail_url.as_deref(), Some(first.url.as_str()));
+    assert_eq!(listed.images.len(), 2);
+
+    let reordered = images
+        .reorder(
+            product.id,
+            ReorderProductImagesRequest {
+                image_ids: vec![second.id, first.id],
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(reordered[0].id, second.id);
+    let listed = products.list_by_store(store.id, 20, 0).await.unwrap();
+    assert_eq!(listed[0].images[0].id, second.id);
+    assert_eq!(
+        listed[0].thumbnail_url.as_deref(),
+        Some(second.url.as_str())
+    );
+
+    let err = images
+        .reorder(
+            product.id,
+            ReorderProductImagesRequest {
+                image_ids: vec![second.id],
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+
+    images.delete(product.id, second.id).await.unwrap();
+    assert!(!root.join(&second.storage_key).exists());
+    let listed = products.get_product(product.id).await.unwrap();
+    assert_eq!(listed.thumbnail_url.as_deref(), Some(first.url.as_str()));
+    assert_eq!(listed.images.len(), 1);
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn csv_import_upserts_by_sku_and_reports_bad_rows(pool: PgPool) {
+    let owner = common::insert_user(&pool, "import-owner@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "import-store", false).await;
+    let existing = common::create_product(&pool, store.id, "SKU-IMP-1", 10.0, 5).await;
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+
+    let csv = "sku,name,description,price,stock_quantity,category,thumbnail_url\n\
+               SKU-IMP-1,Renamed Mug,,12.50,8,kitchen,\n\
+               SKU-IMP-2,\"Teapot, large\",Cast iron,30,2,,https://example.com/teapot.png\n";
+    let report = products
+        .import_csv(store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    assert!(report.errors.is_empty());
+    assert_eq!((report.created, report.updated), (1, 1));
+
+    let listed = products.list_by_store(store.id, 20, 0).await.unwrap();
+    assert_eq!(listed.len(), 2);
+    let updated = listed.iter().find(|p| p.id == existing.id).unwrap();
+    assert_eq!(updated.name, "Renamed Mug");
+    assert_eq!(updated.price, Decimal::new(1250, 2));
+    assert_eq!(updated.description, None);
+    let teapot = listed.iter().find(|p| p.sku == "SKU-IMP-2").unwrap();
+    assert_eq!(teapot.name, "Teapot, large");
+
+    let csv = "sku,name,description,price,stock_quantity,category,thumbnail_url\n\
+               SKU-IMP-3,Kettle,,15,1,,\n\
+               SKU-IMP-4,Spoon,,-1,1,,\n\
+               SKU-IMP-3,Kettle again,,15,1,,\n\
+               SKU-IMP-5,Fork,,abc,1,,\n";
+    let report = products
+        .import_csv(store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    let lines: Vec<u64> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![3, 4, 5]);
+    assert_eq!((report.created, report.updated), (0, 0));
+    assert_eq!(
+        products.list_by_store(store.id, 20, 0).await.unwrap().len(),
+        2
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn compare_returns_aligned_matrix_for_public_products(pool: PgPool) {
+    let owner = common::insert_user(&pool, "compare-owner@markethub.dev").await;
+    let first = common::create_store(&pool, owner.id, "compare-one", false).await;
+    let second = common::create_store(&pool, owner.id, "compare-two", false).await;
+    let private = common::create_store(&pool, owner.id, "compare-private", true).await;
+    let mug = common::create_product(&pool, first.id, "SKU-CMP-1", 12.0, 50).await;
+    let cup = common::create_product(&pool, second.id, "SKU-CMP-2", 9.5, 0).await;
+    let hidden = common::create_product(&pool, private.id, "SKU-CMP-3", 9.5, 3).await;
+
+    let service = ComparisonService::new(
+        ProductRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+    );
+
+    let table = service
+        .compare(CompareProductsRequest {
+            product_ids: vec![cup.id, mug.id],
+        })
+        .await
+        .unwrap();
+    assert_eq!(table.products[0].product_id, cup.id);
+    assert_eq!(table.products[1].store_name, first.name);
+    let availability = table
+        .attributes
+        .iter()
+        .find(|row| row.attribute == "availability")
+        .unwrap();
+    assert_eq!(availability.values[0], "out_of_stock");
+    assert_eq!(availability.values[1], "in_stock");
+    assert!(availability.differs);
+
+    let err = service
+        .compare(CompareProductsRequest {
+            product_ids: vec![mug.id, hidden.id],
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+
+    let err = service
+        .compare(CompareProductsRequest {
+            product_ids: vec![mug.id, mug.id],
+        })
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn catalog_export_streams_every_product(pool: PgPool) {
+    let owner = common::insert_user(&pool, "export-owner@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "export-store", false).await;
+    for index in 0..3 {
+        common::create_product(&pool, store.id, &format!("SKU-EXP-{}", index), 5.0, 1).await;
+    }
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+
+    let chunks: Vec<Bytes> = products
+        .export_catalog(store.id, ExportFormat::Json)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&chunks.concat()).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), 3);
+
+    let chunks: Vec<Bytes> = products
+        .export_catalog(store.id, ExportFormat::Csv)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let csv = chunks.concat();
+    assert!(csv.starts_with(b"sku,name,description,price"));
+    assert_eq!(csv.iter().filter(|b| **b == b'\n').count(), 4);
+
+    // The CSV export can be fed straight back into the importer.
+    let report = products.import_csv(store.id, &csv, false).await.unwrap();
+    assert!(report.errors.is_empty());
+    assert_eq!((report.created, report.updated), (0, 3));
+
+    let empty = common::create_store(&pool, owner.id, "export-empty", false).await;
+    let chunks: Vec<Bytes> = products
+        .export_catalog(empty.id, ExportFormat::Json)
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), b"[]");
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn recently_viewed_is_bounded_and_hides_unavailable_products(pool: PgPool) {
+    let owner = common::insert_user(&pool, "recent-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "recent-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "recent-store", false).await;
+    let private = common::create_store(&pool, owner.id, "recent-private", true).await;
+
+    let recent = RecentlyViewedService::new(RecentlyViewedRepository::new(pool.clone()));
+    let mut viewed = Vec::new();
+    for index in 0..(RECENTLY_VIEWED_LIMIT + 2) {
+        let product =
+            common::create_product(&pool, store.id, &format!("SKU-RV-{}", index), 3.0, 1).await;
+        recent.record_view(shopper.id, product.id).await.unwrap();
+        viewed.push(product);
+    }
+    // Viewing again moves a product back to the front.
+    recent.record_view(shopper.id, viewed[5].id).await.unwrap();
+
+    let history = recent.list(shopper.id).await.unwrap();
+    assert_eq!(history.len() as i64, RECENTLY_VIEWED_LIMIT);
+    assert_eq!(history[0].product.id, viewed[5].id);
+    assert!(history.iter().all(|entry| entry.product.id != viewed[0].id));
+
+    let hidden = common::create_product(&pool, private.id, "SKU-RV-PRIV", 3.0, 1).await;
+    recent.record_view(shopper.id, hidden.id).await.unwrap();
+    query("UPDATE products SET is_active = false WHERE id = $1")
+        .bind(viewed[5].id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let history = recent.list(shopper.id).await.unwrap();
+    assert!(history.iter().all(|entry| entry.product.id != hidden.id));
+    assert!(history.iter().all(|entry| entry.product.id != viewed[5].id));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn digital_products_skip_shipping_and_download_after_payment(pool: PgPool) {
+    let owner = common::insert_user(&pool, "digital-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "digital-shopper@markethub.dev").await;
+    let other = common::insert_user(&pool, "digital-other@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "digital-store", false).await;
+    let ebook = common::create_product(&pool, store.id, "SKU-EBOOK", 12.0, 100).await;
+    let mug = common::create_product(&pool, store.id, "SKU-MUG", 8.0, 10).await;
+    query("UPDATE products SET product_type = 'Digital' WHERE id = $1")
+        .bind(ebook.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let root = std::env::temp_dir().join(format!("markethub-{}", uuid::Uuid::new_v4()));
+    let storage = Arc::new(LocalStorage::new(&root, ""));
+    let files = ProductFileService::new(
+        ProductRepository::new(pool.clone()),
+        ProductFileRepository::new(pool.clone()),
+        storage.clone(),
+    );
+    let content = Bytes::from_static(b"%PDF-1.7 chapter one");
+    let file = files
+        .upload(ebook.id, "../Guide.pdf", "application/pdf", content.clone())
+        .await
+        .unwrap();
+    assert_eq!(file.file_name, "Guide.pdf");
+    let err = files
+        .upload(mug.id, "mug.pdf", "application/pdf", content.clone())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: ebook.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order = &summary.orders[0];
+    assert_eq!(order.shipping_cost, Decimal::ZERO);
+
+    let downloads = DownloadService::new(
+        OrderRepository::new(pool.clone()),
+        ProductFileRepository::new(pool.clone()),
+        storage,
+        common::test_jwt(),
+    );
+    let err = downloads
+        .links_for_order(shopper.id, order.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    let orders = OrderRepository::new(pool.clone());
+    orders
+        .mark_payment_status(summary.order_group.id, PaymentStatus::Paid)
+        .await
+        .unwrap();
+    let links = downloads
+        .links_for_order(shopper.id, order.id)
+        .await
+        .unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].file_id, file.id);
+    let token = links[0]
+        .url
+        .strip_prefix("/api/v1/downloads/")
+        .unwrap()
+        .to_string();
+
+    let (opened, data) = downloads.open(&token).await.unwrap();
+    assert_eq!(opened.id, file.id);
+    assert_eq!(data, content);
+
+    let err = downloads
+        .links_for_order(other.id, order.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+    let err = downloads.open("not-a-token").await.unwrap_err();
+    assert!(matches!(err, AppError::Authentication(_)));
+
+    orders
+        .mark_payment_status(summary.order_group.id, PaymentStatus::Refunded)
+        .await
+        .unwrap();
+    let err = downloads.open(&token).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkout_fields_are_validated_and_stored_on_orders(pool: PgPool) {
+    let owner = common::insert_user(&pool, "fields-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "fields-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "fields-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-FIELDS", 15.0, 5).await;
+
+    let settings = StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    let field = |key: &str, field_type, required| CreateCheckoutFieldRequest {
+        key: key.into(),
+        label: key.replace('_', " "),
+        field_type,
+        required,
+        max_length: None,
+    };
+    settings
+        .add_checkout_field(
+            store.id,
+            field("delivery_instructions", CheckoutFieldType::Text, false),
+        )
+        .await
+        .unwrap();
+    settings
+        .add_checkout_field(
+            store.id,
+            field("gift_wrap", CheckoutFieldType::Boolean, true),
+        )
+        .await
+        .unwrap();
+    let err = settings
+        .add_checkout_field(store.id, field("gift_wrap", CheckoutFieldType::Text, false))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    let orders = order_service(&pool);
+    let checkout = |values: serde_json::Value| CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: false,
+        custom_fields: [(store.id, values.as_object().cloned().unwrap())].into(),
+        shipping_options: Default::default(),
+    };
+
+    let err = orders
+        .checkout(
+            shopper.id,
+            checkout(serde_json::json!({"delivery_instructions": "Side door"})),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+
+    let summary = orders
+        .checkout(
+            shopper.id,
+            checkout(serde_json::json!({
+                "delivery_instructions": "  Side door  ",
+                "gift_wrap": true,
+            })),
+        )
+        .await
+        .unwrap();
+    let order = &summary.orders[0];
+    assert_eq!(
+        order.custom_fields,
+        serde_json::json!({"delivery_instructions": "Side door", "gift_wrap": true})
+    );
+
+    let detail = orders.store_order_detail(store.id, order.id).await.unwrap();
+    assert_eq!(detail.order.custom_fields, order.custom_fields);
+    assert_eq!(detail.items.len(), 1);
+    assert_eq!(detail.items[0].product_id, product.id);
+    let err = orders
+        .store_order_detail(uuid::Uuid::new_v4(), order.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn bulk_status_reports_partial_success_and_notifies_buyers(pool: PgPool) {
+    let owner = common::insert_user(&pool, "bulk-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "bulk-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "bulk-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-BULK", 5.0, 20).await;
+
+    let carts = cart_service(&pool);
+    let orders = order_service(&pool);
+    let mut order_ids = Vec::new();
+    for quantity in [1, 2] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity,
+                },
+            )
+            .await
+            .unwrap();
+        let summary = orders
+            .checkout(
+                shopper.id,
+                CheckoutRequest {
+                    shipping_address: common::shipping_address(),
+                    confirm_duplicate: false,
+                    custom_fields: Default::default(),
+                    shipping_options: Default::default(),
+                },
+            )
+            .await
+            .unwrap();
+        order_ids.push(summary.orders[0].id);
+    }
+
+    let statuses = OrderStatusService::new(
+        OrderRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        AuditService::new(AuditRepository::new(pool.clone())),
+    );
+    let bulk = |order_ids: Vec<uuid::Uuid>, status| BulkOrderStatusRequest { order_ids, status };
+
+    let confirmed = statuses
+        .bulk_transition(
+            owner.id,
+            store.id,
+            bulk(vec![order_ids[0]], OrderStatus::Confirmed),
+        )
+        .await
+        .unwrap();
+    assert_eq!(confirmed.updated, vec![order_ids[0]]);
+
+    let missing = uuid::Uuid::new_v4();
+    let processing = statuses
+        .bulk_transition(
+            owner.id,
+            store.id,
+            bulk(
+                vec![order_ids[0], order_ids[1], missing, order_ids[0]],
+                OrderStatus::Processing,
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(processing.updated, vec![order_ids[0]]);
+    let failed: Vec<uuid::Uuid> = processing.failed.iter().map(|f| f.order_id).collect();
+    assert_eq!(failed, vec![order_ids[1], missing]);
+
+    let detail = orders
+        .store_order_detail(store.id, order_ids[0])
+        .await
+        .unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Processing);
+    let listed = orders
+        .list_store_orders(
+            store.id,
+            &StoreOrderFilter {
+                status: Some(OrderStatus::Processing),
+                ..Default::default()
+            },
+            20,
+            0,
+        )
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+
+    let notifications = NotificationRepository::new(pool.clone())
+        .list_for_user(shopper.id, 20, 0)
+        .await
+        .unwrap();
+    assert_eq!(notifications.len(), 2);
+    assert!(notifications
+        .iter()
+        .all(|n| n.kind == "order_status_changed" && n.store_id == Some(store.id)));
+
+    let other_store = common::create_store(&pool, owner.id, "bulk-other-store", false).await;
+    let foreign = statuses
+        .bulk_transition(
+            owner.id,
+            other_store.id,
+            bulk(vec![order_ids[1]], OrderStatus::Confirmed),
+        )
+        .await
+        .unwrap();
+    assert!(foreign.updated.is_empty());
+    assert_eq!(foreign.failed.len(), 1);
+
+    let status_events: Vec<(uuid::Uuid, String)> = sqlx::query_as(
+        "SELECT (payload->'data'->>'order_id')::uuid, payload->'data'->>'to' FROM outbox_events \
+         WHERE event_type = 'OrderStatusChanged' ORDER BY created_at",
+    )
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        status_events,
+        vec![
+            (order_ids[0], "Confirmed".to_string()),
+            (order_ids[0], "Processing".to_string()),
+        ]
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn buyer_cancellation_restores_stock_and_group_total(pool: PgPool) {
+    let owner = common::insert_user(&pool, "cancel-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "cancel-shopper@markethub.dev").await;
+    let store_a = common::create_store(&pool, owner.id, "cancel-store-a", false).await;
+    let store_b = common::create_store(&pool, owner.id, "cancel-store-b", false).await;
+    let product_a = common::create_product(&pool, store_a.id, "SKU-CANCEL-A", 10.0, 5).await;
+    let product_b = common::create_product(&pool, store_b.id, "SKU-CANCEL-B", 4.0, 5).await;
+
+    let carts = cart_service(&pool);
+    for (product_id, quantity) in [(product_a.id, 2), (product_b.id, 1)] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let orders = order_service(&pool);
+    let summary = orders
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let cancelled = summary
+        .orders
+        .iter()
+        .find(|order| order.store_id == store_a.id)
+        .unwrap();
+    let kept = summary
+        .orders
+        .iter()
+        .find(|order| order.store_id == store_b.id)
+        .unwrap();
+
+    let err = orders
+        .cancel(owner.id, cancelled.id, CancelOrderRequest::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+
+    let order = orders
+        .cancel(
+            shopper.id,
+            cancelled.id,
+            CancelOrderRequest {
+                reason: Some("Ordered the wrong size".into()),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert_eq!(order.cancelled_by, Some(shopper.id));
+    assert_eq!(
+        order.cancellation_reason.as_deref(),
+        Some("Ordered the wrong size")
+    );
+    assert!(order.cancelled_at.is_some());
+
+    let restocked = ProductRepository::new(pool.clone())
+        .find_by_id(product_a.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(restocked.stock_quantity, 5);
+    let history = InventoryService::new(
+        ProductRepository::new(pool.clone()),
+        InventoryRepository::new(pool.clone()),
+    )
+    .history(product_a.id, 10, 0)
+    .await
+    .unwrap();
+    assert_eq!(history[0].kind, InventoryMovementKind::CancellationRestore);
+    assert_eq!(history[0].order_id, Some(cancelled.id));
+
+    let group = OrderRepository::new(pool.clone())
+        .find_group(summary.order_group.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(group.total_amount, kept.total_amount);
+
+    let notifications = NotificationRepository::new(pool.clone())
+        .list_for_user(owner.id, 20, 0)
+        .await
+        .unwrap();
+    assert!(notifications
+        .iter()
+        .any(|n| n.kind == ORDER_CANCELLED && n.store_id == Some(store_a.id)));
+
+    let err = orders
+        .cancel(shopper.id, cancelled.id, CancelOrderRequest::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn cancellation_gives_back_store_credit_and_coupon_use(pool: PgPool) {
+    let owner = common::insert_user(&pool, "refund-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "refund-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "refund-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-REFUND", 20.0, 5).await;
+
+    let credits = CreditService::new(
+        CreditRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    credits
+        .issue_credit(
+            owner.id,
+            store.id,
+            IssueCreditRequest {
+                user_id: shopper.id,
+                amount: 100.0,
+                kind: CreditEntryKind::Goodwill,
+                order_id: None,
+                note: None,
+            },
+        )
+        .await
+        .unwrap();
+    let coupons = CouponService::new(
+        CouponRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+    );
+    let coupon = coupons
+        .create_coupon(
+            store.id,
+            CreateCouponRequest {
+                code: "ONCE".into(),
+                kind: CouponKind::Fixed,
+                value: 5.0,
+                min_order_value: None,
+                usage_limit: Some(1),
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let carts = cart_service(&pool);
+    let orders = order_service(&pool);
+    let checkout = || async {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+        coupons
+            .apply_coupon(
+                shopper.id,
+                ApplyCouponRequest {
+                    code: "once".into(),
+                    store_id: None,
+                },
+            )
+            .await
+            .unwrap();
+        orders
+            .checkout(
+                shopper.id,
+                CheckoutRequest {
+                    shipping_address: common::shipping_address(),
+                    confirm_duplicate: true,
+                    custom_fields: Default::default(),
+                    shipping_options: Default::default(),
+                },
+            )
+            .await
+            .unwrap()
+    };
+    let summary = checkout().await;
+    let order = &summary.orders[0];
+    assert_eq!(order.discount, Decimal::new(5, 0));
+    assert!(order.credit_applied > Decimal::ZERO);
+    assert_eq!(order.total_amount, Decimal::ZERO);
+
+    orders
+        .cancel(shopper.id, order.id, CancelOrderRequest::default())
+        .await
+        .unwrap();
+
+    let balances = credits.balances_for_user(shopper.id).await.unwrap();
+    assert_eq!(balances[0].balance, Decimal::new(100, 0));
+    let ledger = credits.ledger(store.id, shopper.id, 10, 0).await.unwrap();
+    let restored = ledger
+        .iter()
+        .find(|entry| entry.kind == CreditEntryKind::Refund)
+        .unwrap();
+    assert_eq!(restored.amount, order.credit_applied);
+    assert_eq!(restored.order_id, Some(order.id));
+    let (times_used,): (i32,) = sqlx::query_as("SELECT times_used FROM coupons WHERE id = $1")
+        .bind(coupon.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(times_used, 0);
+
+    // The coupon's single use is free again; once the order is paid for,
+    // the buyer can no longer cancel it without a refund.
+    let summary = checkout().await;
+    let order = &summary.orders[0];
+    assert_eq!(order.discount, Decimal::new(5, 0));
+    sqlx::query(
+        r#"
+        INSERT INTO payments (order_group_id, order_id, store_id, provider, status, amount, currency)
+        VALUES ($1, $2, $3, 'Manual', 'Captured', 1, 'USD')
+        "#,
+    )
+    .bind(summary.order_group.id)
+    .bind(order.id)
+    .bind(store.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let err = orders
+        .cancel(shopper.id, order.id, CancelOrderRequest::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn staff_cancellation_gives_back_store_credit(pool: PgPool) {
+    let owner = common::insert_user(&pool, "staff-refund-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "staff-refund-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "staff-refund-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-STAFF-REFUND", 12.0, 5).await;
+
+    let credits = CreditService::new(
+        CreditRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    credits
+        .issue_credit(
+            owner.id,
+            store.id,
+            IssueCreditRequest {
+                user_id: shopper.id,
+                amount: 50.0,
+                kind: CreditEntryKind::Goodwill,
+                order_id: None,
+                note: None,
+            },
+        )
+        .await
+        .unwrap();
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 2,
+            },
+        )
+        .await
+        .unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order = &summary.orders[0];
+    assert!(order.credit_applied > Decimal::ZERO);
+
+    let cancelled = OrderStatusService::new(
+        OrderRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        AuditService::new(AuditRepository::new(pool.clone())),
+    )
+    .update_status(
+        owner.id,
+        store.id,
+        order.id,
+        UpdateOrderStatusRequest {
+            status: OrderStatus::Cancelled,
+            reason: Some("Out of stock".into()),
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(cancelled.status, OrderStatus::Cancelled);
+
+    let balances = credits.balances_for_user(shopper.id).await.unwrap();
+    assert_eq!(balances[0].balance, Decimal::new(50, 0));
+    let stock = ProductRepository::new(pool.clone())
+        .find_by_id(product.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .stock_quantity;
+    assert_eq!(stock, 5);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn staff_cannot_cancel_a_captured_order_before_refunding(pool: PgPool) {
+    use axum::response::IntoResponse;
+
+    let owner = common::insert_user(&pool, "staff-captured-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "staff-captured-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "staff-captured-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-STAFF-CAPTURED", 12.0, 5).await;
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 2,
+            },
+        )
+        .await
+        .unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order = &summary.orders[0];
+    let payments = payment_service(&pool, PaymentProviders::manual_only());
+    payments
+        .pay(
+            shopper.id,
+            summary.order_group.id,
+            PayOrderGroupRequest {
+                method: Some(PaymentProviderKind::Manual),
+            },
+        )
+        .await
+        .unwrap();
+    payments.capture_order(store.id, order.id).await.unwrap();
+
+    let err = OrderStatusService::new(
+        OrderRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        AuditService::new(AuditRepository::new(pool.clone())),
+    )
+    .update_status(
+        owner.id,
+        store.id,
+        order.id,
+        UpdateOrderStatusRequest {
+            status: OrderStatus::Cancelled,
+            reason: Some("Out of stock".into()),
+        },
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(&err, AppError::Conflict(message) if message.contains("refunded")));
+    assert_eq!(err.into_response().status(), 409);
+
+    let stock = ProductRepository::new(pool.clone())
+        .find_by_id(product.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .stock_quantity;
+    assert_eq!(stock, 3);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn shipments_mark_orders_shipped_and_show_tracking(pool: PgPool) {
+    let owner = common::insert_user(&pool, "ship-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "ship-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "ship-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-SHIP", 9.0, 5).await;
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    let orders = order_service(&pool);
+    let summary = orders
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order_id = summary.orders[0].id;
+
+    let statuses = OrderStatusService::new(
+        OrderRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        AuditService::new(AuditRepository::new(pool.clone())),
+    );
+    let request = || CreateShipmentRequest {
+        carrier: "UPS".into(),
+        tracking_number: "1Z999".into(),
+        tracking_url: Some("https://ups.example.com/track/1Z999".into()),
+        shipped_at: None,
+        items: None,
+    };
+    let err = statuses
+        .ship(owner.id, store.id, order_id, request())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    for status in [OrderStatus::Confirmed, OrderStatus::Processing] {
+        statuses
+            .bulk_transition(
+                owner.id,
+                store.id,
+                BulkOrderStatusRequest {
+                    order_ids: vec![order_id],
+                    status,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    // Without shipments the order can't be marked Shipped or Delivered
+    for status in [OrderStatus::Shipped, OrderStatus::Delivered] {
+        let err = statuses
+            .update_status(
+                owner.id,
+                store.id,
+                order_id,
+                UpdateOrderStatusRequest {
+                    status,
+                    reason: None,
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::Conflict(_)));
+    }
+    let bulk = statuses
+        .bulk_transition(
+            owner.id,
+            store.id,
+            BulkOrderStatusRequest {
+                order_ids: vec![order_id],
+                status: OrderStatus::Shipped,
+            },
+        )
+        .await
+        .unwrap();
+    assert!(bulk.updated.is_empty());
+    assert_eq!(bulk.failed.len(), 1);
+    let detail = orders.order_detail(order_id).await.unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Processing);
+
+    let shipment = statuses
+        .ship(owner.id, store.id, order_id, request())
+        .await
+        .unwrap();
+    assert_eq!(shipment.created_by, Some(owner.id));
+
+    let updated = statuses
+        .update_shipment(
+            store.id,
+            order_id,
+            shipment.id,
+            UpdateShipmentRequest {
+                carrier: None,
+                tracking_number: Some("1Z1000".into()),
+                tracking_url: None,
+                shipped_at: None,
+                status: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.carrier, "UPS");
+    assert_eq!(updated.tracking_number, "1Z1000");
+
+    let other_store = common::create_store(&pool, owner.id, "ship-other-store", false).await;
+    let err = statuses
+        .ship(owner.id, other_store.id, order_id, request())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+
+    let detail = orders.order_detail(order_id).await.unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Shipped);
+    assert_eq!(detail.shipments.len(), 1);
+    assert_eq!(detail.shipments[0].tracking_number, "1Z1000");
+
+    let notifications = NotificationRepository::new(pool.clone())
+        .list_for_user(shopper.id, 20, 0)
+        .await
+        .unwrap();
+    assert!(notifications
+        .iter()
+        .any(|n| n.message.contains("shipped with UPS")));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn orders_ship_in_parts_and_deliver_when_every_parcel_arrives(pool: PgPool) {
+    let owner = common::insert_user(&pool, "split-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "split-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "split-store", false).await;
+    let mug = common::create_product(&pool, store.id, "SKU-SPLIT-MUG", 9.0, 5).await;
+    let poster = common::create_product(&pool, store.id, "SKU-SPLIT-POSTER", 4.0, 5).await;
+
+    let carts = cart_service(&pool);
+    for (product_id, quantity) in [(mug.id, 2), (poster.id, 1)] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let orders = order_service(&pool);
+    let summary = orders
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order_id = summary.orders[0].id;
+
+    let statuses = OrderStatusService::new(
+        OrderRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        AuditService::new(AuditRepository::new(pool.clone())),
+    );
+    for status in [OrderStatus::Confirmed, OrderStatus::Processing] {
+        statuses
+            .bulk_transition(
+                owner.id,
+                store.id,
+                BulkOrderStatusRequest {
+                    order_ids: vec![order_id],
+                    status,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let detail = orders.store_order_detail(store.id, order_id).await.unwrap();
+    assert_eq!(detail.fulfillment, FulfillmentStatus::Unfulfilled);
+    let mug_line = detail
+        .items
+        .iter()
+        .find(|item| item.product_id == mug.id)
+        .unwrap()
+        .id;
+    let parcel = |tracking_number: &str, items| CreateShipmentRequest {
+        carrier: "UPS".into(),
+        tracking_number: tracking_number.into(),
+        tracking_url: None,
+        shipped_at: None,
+        items,
+    };
+    let mugs = |quantity| {
+        Some(vec![ShipmentItemRequest {
+            order_item_id: mug_line,
+            quantity,
+        }])
+    };
+
+    let err = statuses
+        .ship(owner.id, store.id, order_id, parcel("1Z1", mugs(3)))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    let first = statuses
+        .ship(owner.id, store.id, order_id, parcel("1Z1", mugs(1)))
+        .await
+        .unwrap();
+    assert_eq!(first.items.len(), 1);
+    assert_eq!(first.items[0].quantity, 1);
+    let detail = orders.store_order_detail(store.id, order_id).await.unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Processing);
+    assert_eq!(detail.fulfillment, FulfillmentStatus::PartiallyShipped);
+
+    let cancelled = statuses
+        .bulk_transition(
+            owner.id,
+            store.id,
+            BulkOrderStatusRequest {
+                order_ids: vec![order_id],
+                status: OrderStatus::Cancelled,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(cancelled.failed.len(), 1);
+
+    let rest = statuses
+        .ship(owner.id, store.id, order_id, parcel("1Z2", None))
+        .await
+        .unwrap();
+    assert_eq!(rest.items.len(), 2);
+    assert_eq!(rest.items.iter().map(|item| item.quantity).sum::<i32>(), 2);
+    let detail = orders.store_order_detail(store.id, order_id).await.unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Shipped);
+    assert_eq!(detail.fulfillment, FulfillmentStatus::Shipped);
+
+    let err = statuses
+        .ship(owner.id, store.id, order_id, parcel("1Z3", mugs(1)))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    let deliver = || UpdateShipmentRequest {
+        carrier: None,
+        tracking_number: None,
+        tracking_url: None,
+        shipped_at: None,
+        status: Some(ShipmentStatus::Delivered),
+    };
+    let delivered = statuses
+        .update_shipment(store.id, order_id, first.id, deliver())
+        .await
+        .unwrap();
+    assert_eq!(delivered.status, ShipmentStatus::Delivered);
+    assert!(delivered.delivered_at.is_some());
+    let detail = orders.order_detail(order_id).await.unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Shipped);
+
+    statuses
+        .update_shipment(store.id, order_id, rest.id, deliver())
+        .await
+        .unwrap();
+    let detail = orders.order_detail(order_id).await.unwrap();
+    assert_eq!(detail.order.status, OrderStatus::Delivered);
+    assert_eq!(detail.fulfillment, FulfillmentStatus::Delivered);
+    assert_eq!(detail.shipments.len(), 2);
+
+    let notifications = NotificationRepository::new(pool.clone())
+        .list_for_user(shopper.id, 20, 0)
+        .await
+        .unwrap();
+    assert!(notifications
+        .iter()
+        .any(|n| n.message.starts_with("Part of order")));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn archived_products_leave_listings_and_carts_but_keep_history(pool: PgPool) {
+    let owner = common::insert_user(&pool, "archive-owner@markethub.dev").await;
+    let buyer = common::insert_user(&pool, "archive-buyer@markethub.dev").await;
+    let browser = common::insert_user(&pool, "archive-browser@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "archive-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-ARCHIVE", 9.0, 10).await;
+
+    let carts = cart_service(&pool);
+    let add = |quantity| AddCartItemRequest {
+        product_id: product.id,
+        quantity,
+    };
+    carts.add_item(buyer.id, add(1)).await.unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            buyer.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    carts.add_item(browser.id, add(2)).await.unwrap();
+    let reserved = || async {
+        sqlx::query_scalar::<_, i64>(
+            "SELECT COUNT(*) FROM stock_reservations WHERE product_id = $1",
+        )
+        .bind(product.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap()
+    };
+    assert_eq!(reserved().await, 1);
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+    products.archive_product(product.id).await.unwrap();
+
+    assert!(products
+        .list_by_store(store.id, 20, 0)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(carts.list_items(browser.id).await.unwrap().is_empty());
+    assert_eq!(reserved().await, 0);
+    let err = carts.add_item(browser.id, add(1)).await.unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+    let err = products.archive_product(product.id).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    let detail = order_service(&pool)
+        .store_order_detail(store.id, summary.orders[0].id)
+        .await
+        .unwrap();
+    assert_eq!(detail.items[0].product_id, product.id);
+    let archived = products.get_product(product.id).await.unwrap();
+    assert!(archived.is_archived());
+
+    let restored = products.unarchive_product(product.id).await.unwrap();
+    assert!(!restored.is_archived());
+    assert_eq!(
+        products.list_by_store(store.id, 20, 0).await.unwrap().len(),
+        1
+    );
+    carts.add_item(browser.id, add(1)).await.unwrap();
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn suggestions_rank_prefix_matches_and_respect_store_visibility(pool: PgPool) {
+    let owner = common::insert_user(&pool, "suggest-owner@markethub.dev").await;
+    let public = common::create_store(&pool, owner.id, "suggest-public", false).await;
+    let private = common::create_store(&pool, owner.id, "suggest-private", true).await;
+
+    let catalog = [
+        (public.id, "SKU-S1", "Standing Desk Frame", "Desks"),
+        (public.id, "SKU-S2", "Desk Lamp", "Desk Accessories"),
+        (public.id, "SKU-S3", "Oak Desk", "Desks"),
+        (private.id, "SKU-S4", "Desk Mat", "Desk Accessories"),
+    ];
+    for (store_id, sku, name, category) in catalog {
+        let product = common::create_product(&pool, store_id, sku, 20.0, 5).await;
+        query("UPDATE products SET name = $2, category = $3 WHERE id = $1")
+            .bind(product.id)
+            .bind(name)
+            .bind(category)
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+
+    let service = SearchService::new(
+        ProductRepository::new(pool.clone()),
+        Arc::new(SuggestionCache::default()),
+    );
+
+    let anonymous = service.suggest("  DESK", None).await.unwrap();
+    let names: Vec<_> = anonymous.products.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["Desk Lamp", "Oak Desk", "Standing Desk Frame"]);
+    assert_eq!(anonymous.categories, ["Desks", "Desk Accessories"]);
+
+    let member = service.suggest("desk m", Some(owner.id)).await.unwrap();
+    assert_eq!(member.products.len(), 1);
+    assert_eq!(member.products[0].name, "Desk Mat");
+    assert!(service
+        .suggest("desk m", None)
+        .await
+        .unwrap()
+        .products
+        .is_empty());
+
+    assert!(service
+        .suggest("d", None)
+        .await
+        .unwrap()
+        .products
+        .is_empty());
+    assert!(service
+        .suggest("%%", None)
+        .await
+        .unwrap()
+        .products
+        .is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn tax_report_groups_paid_orders_by_jurisdiction_and_rate(pool: PgPool) {
+    let owner = common::insert_user(&pool, "tax-owner@markethub.dev").await;
+    let buyer = common::insert_user(&pool, "tax-buyer@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "tax-report-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-TAXRPT", 50.0, 20).await;
+
+    let settings = StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    let set_rate = |rate| UpdateStoreSettingsRequest {
+        currency_code: None,
+        tax_rate: Some(rate),
+        flat_shipping_fee: None,
+        free_shipping_threshold: None,
+        ships_to: None,
+        tax_provider: None,
+        payment_provider: None,
+    };
+
+    let carts = cart_service(&pool);
+    let orders = OrderRepository::new(pool.clone());
+    let checkout = |address: serde_json::Value| {
+        let carts = carts.clone();
+        let orders = orders.clone();
+        let pool = pool.clone();
+        async move {
+            carts
+                .add_item(
+                    buyer.id,
+                    AddCartItemRequest {
+                        product_id: product.id,
+                        quantity: 1,
+                    },
+                )
+                .await
+                .unwrap();
+            let summary = order_service(&pool)
+                .checkout(
+                    buyer.id,
+                    CheckoutRequest {
+                        shipping_address: address,
+                        confirm_duplicate: true,
+                        custom_fields: Default::default(),
+                        shipping_options: Default::default(),
+                    },
+                )
+                .await
+                .unwrap();
+            orders
+                .mark_payment_status(summary.order_group.id, PaymentStatus::Paid)
+                .await
+                .unwrap();
+            summary
+        }
+    };
+
+    settings
+        .update_settings(store.id, set_rate(0.08))
+        .await
+        .unwrap();
+    let california = serde_json::json!({"line1": "1 Main", "country": "us", "state": "CA"});
+    checkout(california.clone()).await;
+    checkout(california.clone()).await;
+    checkout(serde_json::json!({"line1": "2 Hauptstr", "country": "DE"})).await;
+    let cancelled = checkout(california.clone()).await;
+    query("UPDATE orders SET status = 'Cancelled' WHERE id = $1")
+        .bind(cancelled.orders[0].id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    settings
+        .update_settings(store.id, set_rate(0.1))
+        .await
+        .unwrap();
+    checkout(california).await;
+
+    let analytics = AnalyticsService::new(
+        StoreRepository::new(pool.clone()),
+        AnalyticsRepository::new(pool.clone()),
+    );
+    let rows = analytics
+        .tax_report(store.id, TaxReportPeriod::Year, None, None)
+        .await
+        .unwrap();
+    let summary: Vec<_> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.jurisdiction.as_str(),
+                row.rate,
+                row.order_count,
+                row.tax_collected,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("DE", Decimal::new(8, 2), 1, Decimal::new(400, 2)),
+            ("US-CA", Decimal::new(8, 2), 2, Decimal::new(800, 2)),
+            ("US-CA", Decimal::new(1, 1), 1, Decimal::new(500, 2)),
+        ]
+    );
+
+    let today = chrono::Utc::now().date_naive();
+    let err = analytics
+        .tax_report(
+            store.id,
+            TaxReportPeriod::Month,
+            Some(today),
+            Some(today.pred_opt().unwrap()),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn cart_policy_rejects_incompatible_stores_at_add_time(pool: PgPool) {
+    let owner = common::insert_user(&pool, "policy-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "policy-shopper@markethub.dev").await;
+    let domestic = common::create_store(&pool, owner.id, "policy-domestic", false).await;
+    let euro = common::create_store(&pool, owner.id, "policy-euro", false).await;
+    let german = common::create_store(&pool, owner.id, "policy-german", false).await;
+
+    let settings = StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    let update = |currency: &str, ships_to: &[&str]| UpdateStoreSettingsRequest {
+        currency_code: Some(currency.into()),
+        tax_rate: None,
+        flat_shipping_fee: None,
+        free_shipping_threshold: None,
+        ships_to: Some(ships_to.iter().map(|c| c.to_string()).collect()),
+        tax_provider: None,
+        payment_provider: None,
+    };
+    settings
+        .update_settings(domestic.id, update("USD", &["US"]))
+        .await
+        .unwrap();
+    settings
+        .update_settings(euro.id, update("EUR", &[]))
+        .await
+        .unwrap();
+    settings
+        .update_settings(german.id, update("USD", &["DE"]))
+        .await
+        .unwrap();
+
+    let domestic_product = common::create_product(&pool, domestic.id, "SKU-POL-US", 10.0, 5).await;
+    let euro_product = common::create_product(&pool, euro.id, "SKU-POL-EU", 10.0, 5).await;
+    let german_product = common::create_product(&pool, german.id, "SKU-POL-DE", 10.0, 5).await;
+
+    let carts = cart_service(&pool);
+    let add = |product_id| AddCartItemRequest {
+        product_id,
+        quantity: 1,
+    };
+    carts
+        .add_item(shopper.id, add(domestic_product.id))
+        .await
+        .unwrap();
+
+    let err = carts
+        .add_item(shopper.id, add(euro_product.id))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, AppError::Conflict(message) if message.contains("priced in USD")),
+        "{err}"
+    );
+    let err = carts
+        .add_item(shopper.id, add(german_product.id))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    assert_eq!(carts.list_items(shopper.id).await.unwrap().len(), 1);
+
+    let err = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: serde_json::json!({"line1": "1 Main", "country": "CA"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+
+    order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn inventory_ledger_records_every_stock_change(pool: PgPool) {
+    let owner = common::insert_user(&pool, "ledger-owner@markethub.dev").await;
+    let buyer = common::insert_user(&pool, "ledger-buyer@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "ledger-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-LEDGER", 12.0, 10).await;
+
+    let inventory = InventoryService::new(
+        ProductRepository::new(pool.clone()),
+        InventoryRepository::new(pool.clone()),
+    );
+    let adjust = |kind, delta| AdjustInventoryRequest {
+        kind,
+        delta,
+        reason: "Cycle count".into(),
+    };
+    inventory
+        .adjust(
+            owner.id,
+            product.id,
+            adjust(InventoryMovementKind::Restock, 5),
+        )
+        .await
+        .unwrap();
+    let err = inventory
+        .adjust(
+            owner.id,
+            product.id,
+            adjust(InventoryMovementKind::Adjustment, -100),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    cart_service(&pool)
+        .add_item(
+            buyer.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 3,
+            },
+        )
+        .await
+        .unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            buyer.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let order_id = summary.orders[0].id;
+
+    OrderStatusService::new(
+        OrderRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        AuditService::new(AuditRepository::new(pool.clone())),
+    )
+    .bulk_transition(
+        owner.id,
+        store.id,
+        BulkOrderStatusRequest {
+            order_ids: vec![order_id],
+            status: OrderStatus::Cancelled,
+        },
+    )
+    .await
+    .unwrap();
+
+    let history = inventory.history(product.id, 50, 0).await.unwrap();
+    let entries: Vec<_> = history
+        .iter()
+        .map(|m| (m.kind, m.delta, m.stock_after))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            (InventoryMovementKind::CancellationRestore, 3, 15),
+            (InventoryMovementKind::Sale, -3, 12),
+            (InventoryMovementKind::Restock, 5, 15),
+            (InventoryMovementKind::Adjustment, 10, 10),
+        ]
+    );
+    assert_eq!(history[0].actor_id, Some(owner.id));
+    assert_eq!(history[1].order_id, Some(order_id));
+    assert_eq!(history[1].actor_id, Some(buyer.id));
+
+    let current = ProductRepository::new(pool.clone())
+        .find_by_id(product.id)
+        .await
+        .unwrap()
+        .unwrap();
+    let total: i32 = history.iter().map(|m| m.delta).sum();
+    assert_eq!(total, current.stock_quantity);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn concurrent_checkouts_over_shared_products_never_oversell(pool: PgPool) {
+    const BUYERS: usize = 12;
+    const STOCK: i32 = 8;
+
+    let owner = common::insert_user(&pool, "rush-owner@markethub.dev").await;
+    let kitchen = common::create_store(&pool, owner.id, "rush-kitchen", false).await;
+    let prints = common::create_store(&pool, owner.id, "rush-prints", false).await;
+    let products = [
+        common::create_product(&pool, kitchen.id, "SKU-RUSH-MUG", 9.0, 100).await,
+        common::create_product(&pool, kitchen.id, "SKU-RUSH-BOWL", 12.0, 100).await,
+        common::create_product(&pool, prints.id, "SKU-RUSH-POSTER", 4.0, 100).await,
+    ];
+
+    let carts = cart_service(&pool);
+    let mut buyers = Vec::new();
+    for n in 0..BUYERS {
+        let buyer = common::insert_user(&pool, &format!("rush-{}@markethub.dev", n)).await;
+        // Half the carts list the products the other way round.
+        let mut picks: Vec<_> = products.iter().collect();
+        if n % 2 == 1 {
+            picks.reverse();
+        }
+        for product in picks {
+            carts
+                .add_item(
+                    buyer.id,
+                    AddCartItemRequest {
+                        product_id: product.id,
+                        quantity: 1,
+                    },
+                )
+                .await
+                .unwrap();
+        }
+        buyers.push(buyer.id);
+    }
+    // Let the cart holds lapse and leave less stock than there are buyers,
+    // so the checkouts race for it.
+    sqlx::query("UPDATE stock_reservations SET expires_at = NOW() - INTERVAL '1 second'")
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE products SET stock_quantity = $1 WHERE store_id = ANY($2)")
+        .bind(STOCK)
+        .bind(vec![kitchen.id, prints.id])
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let orders = order_service(&pool);
+    let checkouts: Vec<_> = buyers
+        .into_iter()
+        .map(|buyer_id| {
+            let orders = orders.clone();
+            tokio::spawn(async move {
+                orders
+                    .checkout(
+                        buyer_id,
+                        CheckoutRequest {
+                            shipping_address: common::shipping_address(),
+                            confirm_duplicate: false,
+                            custom_fields: Default::default(),
+                            shipping_options: Default::default(),
+                        },
+                    )
+                    .await
+            })
+        })
+        .collect();
+
+    let mut placed = 0;
+    for checkout in checkouts {
+        match checkout.await.unwrap() {
+            Ok(summary) => {
+                assert_eq!(summary.orders.len(), 2);
+                placed += 1;
+            }
+            Err(AppError::Conflict(_)) => {}
+            Err(err) => panic!("checkout failed with {:?}", err),
+        }
+    }
+    assert_eq!(placed, STOCK);
+
+    for product in &products {
+        let stock: i32 = sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1")
+            .bind(product.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(stock, 0);
+        let sold: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM inventory_movements WHERE product_id = $1 AND kind = 'Sale'",
+        )
+        .bind(product.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(sold, i64::from(STOCK));
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn stock_reservations_hold_stock_until_checkout_or_expiry(pool: PgPool) {
+    let owner = common::insert_user(&pool, "reserve-owner@markethub.dev").await;
+    let first = common::insert_user(&pool, "reserve-first@markethub.dev").await;
+    let second = common::insert_user(&pool, "reserve-second@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "reserve-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-RESERVE", 10.00, 3).await;
+    let carts = cart_service(&pool);
+    let add = |quantity| AddCartItemRequest {
+        product_id: product.id,
+        quantity,
+    };
+
+    carts.add_item(first.id, add(2)).await.unwrap();
+    let err = carts.add_item(second.id, add(2)).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    let items = carts.list_items(second.id).await.unwrap();
+    assert!(items.is_empty(), "a rejected add leaves the cart untouched");
+
+    carts.add_item(second.id, add(1)).await.unwrap();
+    let items = carts.list_items(second.id).await.unwrap();
+    assert!(items[0].reserved_until.is_some());
+
+    // The first buyer's hold lapses and the expiry job purges it.
+    query(
+        "UPDATE stock_reservations SET expires_at = NOW() - INTERVAL '1 second' WHERE user_id = $1",
+    )
+    .bind(first.id)
+    .execute(&pool)
+    .await
+    .unwrap();
+    let released = CartRepository::new(pool.clone())
+        .release_expired(100)
+        .await
+        .unwrap();
+    assert_eq!(released, 1);
+    let items = carts.list_items(first.id).await.unwrap();
+    assert_eq!(items[0].quantity, 2, "the cart line itself survives");
+    assert!(items[0].reserved_until.is_none());
+
+    carts.add_item(second.id, add(1)).await.unwrap();
+
+    // Checkout tries to re-reserve, but only one unit is left unheld.
+    let checkout = || CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: false,
+        custom_fields: Default::default(),
+        shipping_options: Default::default(),
+    };
+    let orders = order_service(&pool);
+    let err = orders.checkout(first.id, checkout()).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+
+    orders.checkout(second.id, checkout()).await.unwrap();
+    let stock: i32 = sqlx::query_scalar("SELECT stock_quantity FROM products WHERE id = $1")
+        .bind(product.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(stock, 1);
+    let held: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE user_id = $1")
+            .bind(second.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(held, 0, "checkout consumes the buyer's reservations");
+
+    // With the second buyer's hold consumed, the remaining unit is free.
+    let err = orders.checkout(first.id, checkout()).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    carts.remove_item(first.id, product.id).await.unwrap();
+    carts.add_item(first.id, add(1)).await.unwrap();
+    orders.checkout(first.id, checkout()).await.unwrap();
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn duplicated_products_start_inactive_with_copied_gallery(pool: PgPool) {
+    let owner = common::insert_user(&pool, "dup-owner@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "dup-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-DUP", 12.5, 8).await;
+
+    let root = std::env::temp_dir().join(format!("markethub-{}", uuid::Uuid::new_v4()));
+    let images = ProductImageService::new(
+        ProductRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+        Arc::new(LocalStorage::new(&root, "https://cdn.markethub.dev/")),
+    );
+    let original = images
+        .upload(product.id, Bytes::from_static(b"\x89PNG\r\n\x1a\ndup"))
+        .await
+        .unwrap();
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+    let copy = products.duplicate_product(product.id).await.unwrap();
+    assert_eq!(copy.sku, "SKU-DUP-COPY");
+    assert_eq!(copy.name, product.name);
+    assert_eq!(copy.price, product.price);
+    assert_eq!(copy.stock_quantity, 0);
+    assert!(!copy.is_active);
+
+    let copied = images.copy_gallery(product.id, copy.id).await.unwrap();
+    assert_eq!(copied.len(), 1);
+    assert_ne!(copied[0].storage_key, original.storage_key);
+    assert!(root.join(&copied[0].storage_key).exists());
+    let copy = products.get_product(copy.id).await.unwrap();
+    assert_eq!(copy.thumbnail_url.as_deref(), Some(copied[0].url.as_str()));
+
+    // Removing the original's image leaves the copy's file in place.
+    images.delete(product.id, original.id).await.unwrap();
+    assert!(root.join(&copied[0].storage_key).exists());
+
+    let second = products.duplicate_product(product.id).await.unwrap();
+    assert_eq!(second.sku, "SKU-DUP-COPY-2");
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn bulk_imports_share_dry_run_and_validation(pool: PgPool) {
+    let owner = common::insert_user(&pool, "bulk-owner@markethub.dev").await;
+    let clerk = common::insert_user(&pool, "bulk-clerk@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "bulk-store", false).await;
+    let mug = common::create_product(&pool, store.id, "SKU-BLK-1", 10.0, 5).await;
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+
+    let csv = "sku,name,description,price,stock_quantity,category,thumbnail_url\n\
+               SKU-BLK-1,Mug,,12.50,5,,\n\
+               SKU-BLK-2,Teapot,,30,2,,\n";
+    let report = products
+        .import_csv(store.id, csv.as_bytes(), true)
+        .await
+        .unwrap();
+    assert!(report.dry_run);
+    assert_eq!((report.created, report.updated), (1, 1));
+    let price_change = report.changes[0]
+        .fields
+        .iter()
+        .find(|change| change.field == "price")
+        .unwrap();
+    assert_eq!(price_change.to, serde_json::json!("12.5"));
+    assert_eq!(
+        products.list_by_store(store.id, 20, 0).await.unwrap().len(),
+        1
+    );
+
+    let report = products
+        .import_prices(store.id, b"sku,price\nSKU-BLK-1,11\nSKU-NOPE,3\n", false)
+        .await
+        .unwrap();
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].key.as_deref(), Some("SKU-NOPE"));
+    assert_eq!(report.errors[0].message, "Unknown SKU");
+
+    let report = products
+        .import_prices(store.id, b"sku,price\nSKU-BLK-1,11\n", false)
+        .await
+        .unwrap();
+    assert_eq!(report.updated, 1);
+    let listed = products.get_product(mug.id).await.unwrap();
+    assert_eq!(listed.price, Decimal::new(11, 0));
+
+    let inventory = InventoryService::new(
+        ProductRepository::new(pool.clone()),
+        InventoryRepository::new(pool.clone()),
+    );
+    let report = inventory
+        .import_stock(
+            owner.id,
+            store.id,
+            b"sku,stock_quantity\nSKU-BLK-1,42\n",
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.updated, 1);
+    let history = inventory.history(mug.id, 10, 0).await.unwrap();
+    assert_eq!(history[0].kind, InventoryMovementKind::Adjustment);
+    assert_eq!((history[0].delta, history[0].stock_after), (37, 42));
+
+    let stores = StoreService::new(
+        StoreRepository::new(pool.clone()),
+        MemberRepository::new(pool.clone()),
+    );
+    let csv = "email,role,permissions\n\
+               Bulk-Clerk@markethub.dev,Staff,VIEW_ORDERS;PROCESS_ORDERS\n\
+               bulk-owner@markethub.dev,Admin,\n\
+               nobody@markethub.dev,Staff,\n";
+    let report = stores
+        .import_members(owner.id, store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    let lines: Vec<u64> = report.errors.iter().map(|e| e.line).collect();
+    assert_eq!(lines, vec![3, 4]);
+
+    let csv = "email,role,permissions\nbulk-clerk@markethub.dev,Staff,VIEW_ORDERS\n";
+    let report = stores
+        .import_members(owner.id, store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    assert_eq!(report.created, 1);
+    let csv = "email,role,permissions\nbulk-clerk@markethub.dev,Manager,\n";
+    let report = stores
+        .import_members(owner.id, store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    assert_eq!(report.updated, 1);
+    let members = stores.list_members(store.id).await.unwrap();
+    let member = members.iter().find(|m| m.user_id == clerk.id).unwrap();
+    assert_eq!(member.role, MemberRole::Manager);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn related_products_rank_co_purchases_then_same_category(pool: PgPool) {
+    let owner = common::insert_user(&pool, "related-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "related-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "related-store", false).await;
+    let other = common::create_store(&pool, owner.id, "related-other", false).await;
+    let private = common::create_store(&pool, owner.id, "related-private", true).await;
+    let mug = common::create_product(&pool, store.id, "SKU-REL-1", 10.0, 10).await;
+    let tea = common::create_product(&pool, other.id, "SKU-REL-2", 6.0, 10).await;
+    let kettle = common::create_product(&pool, store.id, "SKU-REL-3", 30.0, 10).await;
+    let spoon = common::create_product(&pool, other.id, "SKU-REL-4", 2.0, 10).await;
+    let hidden = common::create_product(&pool, private.id, "SKU-REL-5", 2.0, 10).await;
+    sqlx::query("UPDATE products SET category = 'Kitchen' WHERE id = ANY($1)")
+        .bind(vec![mug.id, kettle.id, spoon.id, hidden.id])
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let carts = cart_service(&pool);
+    for product_id in [mug.id, tea.id] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let recommendations = RecommendationService::new(RecommendationRepository::new(pool.clone()));
+    let before = recommendations.related(mug.id, None).await.unwrap();
+    let ids: Vec<_> = before.iter().map(|item| item.product.id).collect();
+    assert_eq!(
+        ids,
+        vec![kettle.id, spoon.id],
+        "same-category fallback only"
+    );
+
+    assert_eq!(recommendations.refresh().await.unwrap(), 2);
+    let related = recommendations.related(mug.id, None).await.unwrap();
+    let ids: Vec<_> = related.iter().map(|item| item.product.id).collect();
+    assert_eq!(ids, vec![tea.id, kettle.id, spoon.id]);
+    assert_eq!(related[0].co_purchases, 1);
+    assert_eq!(related[1].co_purchases, 0);
+
+    let as_owner = recommendations
+        .related(mug.id, Some(owner.id))
+        .await
+        .unwrap();
+    assert!(as_owner.iter().any(|item| item.product.id == hidden.id));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn store_order_export_streams_oldest_first(pool: PgPool) {
+    let owner = common::insert_user(&pool, "order-export-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "order-export-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "order-export-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-OEX-1", 4.0, 10).await;
+
+    let carts = cart_service(&pool);
+    let orders = order_service(&pool);
+    let mut numbers = Vec::new();
+    for quantity in [1, 2] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity,
+                },
+            )
+            .await
+            .unwrap();
+        let summary = orders
+            .checkout(
+                shopper.id,
+                CheckoutRequest {
+                    shipping_address: common::shipping_address(),
+                    confirm_duplicate: true,
+                    custom_fields: Default::default(),
+                    shipping_options: Default::default(),
+                },
+            )
+            .await
+            .unwrap();
+        numbers.push(summary.orders[0].order_number.clone());
+    }
+
+    let today = chrono::Utc::now().date_naive();
+    let chunks: Vec<Bytes> = orders
+        .export_store_orders(store.id, Some(today), Some(today), ExportFormat::Csv)
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let csv = String::from_utf8(chunks.concat()).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("order_number,status,created_at"));
+    assert!(lines[0].contains(",tax,tax_jurisdiction,"));
+    assert!(lines[1].starts_with(&numbers[0]));
+    assert!(lines[2].starts_with(&numbers[1]));
+
+    let chunks: Vec<Bytes> = orders
+        .export_store_orders(store.id, None, None, ExportFormat::Json)
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&chunks.concat()).unwrap();
+    assert_eq!(json[1]["sku"], "SKU-OEX-1");
+    assert_eq!(json[1]["quantity"], 2);
+    assert_eq!(json[1]["line_subtotal"], json[1]["order_subtotal"]);
+    assert!(json[1].get("tax").is_some());
+
+    let tomorrow = today.succ_opt().unwrap();
+    let chunks: Vec<Bytes> = orders
+        .export_store_orders(store.id, Some(tomorrow), None, ExportFormat::Json)
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(chunks.concat(), b"[]");
+    assert!(matches!(
+        orders.export_store_orders(store.id, Some(tomorrow), Some(today), ExportFormat::Csv),
+        Err(AppError::BadRequest(_))
+    ));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn public_order_references_resolve_only_when_signed(pool: PgPool) {
+    let owner = common::insert_user(&pool, "public-ref-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "public-ref-shopper@markethub.dev").await;
+    let first = common::create_store(&pool, owner.id, "public-ref-one", false).await;
+    let second = common::create_store(&pool, owner.id, "public-ref-two", false).await;
+    let mug = common::create_product(&pool, first.id, "SKU-PUB-1", 8.0, 5).await;
+    let tea = common::create_product(&pool, second.id, "SKU-PUB-2", 3.0, 5).await;
+
+    let carts = cart_service(&pool);
+    for product_id in [mug.id, tea.id] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let ids = Arc::new(PublicIds::new("reference-secret"));
+    let public = PublicOrderService::new(OrderRepository::new(pool.clone()), ids.clone());
+    let checkout = public.confirm_checkout(summary, Vec::new());
+    let group_id = checkout.summary.order_group.id.to_string();
+    assert!(!checkout.confirmation_reference.contains(&group_id));
+
+    let confirmation = public
+        .confirmation(&checkout.confirmation_reference)
+        .await
+        .unwrap();
+    assert_eq!(confirmation.orders.len(), 2);
+    assert_eq!(
+        confirmation.group_number,
+        checkout.summary.order_group.group_number
+    );
+    let serialized = serde_json::to_string(&confirmation).unwrap();
+    assert!(!serialized.contains(&group_id));
+    assert!(!serialized.contains("public-ref-shopper"));
+
+    let single = public
+        .confirmation(&confirmation.orders[1].reference)
+        .await
+        .unwrap();
+    assert_eq!(single.orders.len(), 1);
+    assert_eq!(single.reference, checkout.confirmation_reference);
+
+    let foreign = PublicOrderService::new(
+        OrderRepository::new(pool.clone()),
+        Arc::new(PublicIds::new("other-secret")),
+    );
+    let err = foreign
+        .confirmation(&checkout.confirmation_reference)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+    let err = public.confirmation(&group_id).await.unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
+
+struct FailingNotifier;
+
+#[async_trait]
+impl Notifier for FailingNotifier {
+    async fn send(&self, _delivery: &NotificationDelivery) -> markethub::Result<()> {
+        Err(AppError::BadRequest("webhook unreachable".into()))
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn store_notification_rules_route_checkout_events(pool: PgPool) {
+    let owner = common::insert_user(&pool, "route-owner@markethub.dev").await;
+    let clerk = common::insert_user(&pool, "route-clerk@markethub.dev").await;
+    let stranger = common::insert_user(&pool, "route-stranger@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "route-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "route-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-ROUTE", 10.0, 7).await;
+    query("INSERT INTO store_members (store_id, user_id, role) VALUES ($1, $2, 'Staff')")
+        .bind(store.id)
+        .bind(clerk.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let notifications = NotificationService::new(NotificationRepository::new(pool.clone()));
+    let rule = |kind: &str, channel, recipient_user_id, target: Option<&str>| {
+        CreateNotificationRuleRequest {
+            kind: kind.into(),
+            channel,
+            recipient_user_id,
+            target: target.map(Into::into),
+        }
+    };
+
+    let err = notifications
+        .create_rule(
+            owner.id,
+            store.id,
+            rule(
+                NEW_ORDER,
+                NotificationChannel::InApp,
+                Some(stranger.id),
+                None,
+            ),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+    let err = notifications
+        .create_rule(
+            owner.id,
+            store.id,
+            rule(
+                NEW_ORDER,
+                NotificationChannel::Webhook,
+                None,
+                Some("http://hooks.example.com"),
+            ),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+
+    notifications
+        .create_rule(
+            owner.id,
+            store.id,
+            rule(NEW_ORDER, NotificationChannel::InApp, Some(clerk.id), None),
+        )
+        .await
+        .unwrap();
+    let webhook = notifications
+        .create_rule(
+            owner.id,
+            store.id,
+            rule(
+                NEW_ORDER,
+                NotificationChannel::Webhook,
+                None,
+                Some("https://hooks.example.com/orders"),
+            ),
+        )
+        .await
+        .unwrap();
+    assert_eq!(notifications.list_rules(store.id).await.unwrap().len(), 2);
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 3,
+            },
+        )
+        .await
+        .unwrap();
+    order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    // New orders follow the rules; low stock has none and reaches the owner.
+    let kinds = |user_id| {
+        let notifications = notifications.clone();
+        async move {
+            let mut kinds: Vec<String> = notifications
+                .list_for_user(user_id, 20, 0)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|n| n.kind)
+                .collect();
+            kinds.sort();
+            kinds
+        }
+    };
+    assert_eq!(kinds(clerk.id).await, vec![NEW_ORDER.to_string()]);
+    assert_eq!(kinds(owner.id).await, vec![LOW_STOCK.to_string()]);
+
+    let run = notifications
+        .deliver_due(&FailingNotifier, 10)
+        .await
+        .unwrap();
+    assert_eq!(run.retrying, 1);
+    let (status, attempts, target): (DeliveryStatus, i32, String) = sqlx::query_as(
+        "SELECT status, attempts, target FROM notification_deliveries WHERE rule_id = $1",
+    )
+    .bind(webhook.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(status, DeliveryStatus::Pending);
+    assert_eq!(attempts, 1);
+    assert_eq!(target, "https://hooks.example.com/orders");
+
+    // Not due again until the backoff passes.
+    let run = notifications
+        .deliver_due(&FailingNotifier, 10)
+        .await
+        .unwrap();
+    assert_eq!(run, Default::default());
+
+    notifications
+        .delete_rule(store.id, webhook.id)
+        .await
+        .unwrap();
+    let err = notifications
+        .delete_rule(store.id, webhook.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
+
+/// Stands in for a hosted tax API: 8.25% everywhere.
+struct FixedRateTaxProvider;
+
+#[async_trait]
+impl TaxProvider for FixedRateTaxProvider {
+    async fn rate(&self, _lookup: &TaxLookup<'_>) -> markethub::Result<Decimal> {
+        Ok(Decimal::new(825, 4))
+    }
+
+    async fn commit(&self, filing: &TaxFiling) -> markethub::Result<String> {
+        Ok(format!("txn-{}", filing.order_number))
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn external_tax_provider_rates_checkout_and_files_sales(pool: PgPool) {
+    let owner = common::insert_user(&pool, "tax-provider-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "tax-provider-shopper@markethub.dev").await;
+    let external = common::create_store(&pool, owner.id, "external-tax-store", false).await;
+    let builtin = common::create_store(&pool, owner.id, "builtin-tax-store", false).await;
+
+    let settings = StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    for (store_id, tax_provider) in [
+        (external.id, TaxProviderKind::External),
+        (builtin.id, TaxProviderKind::Builtin),
+    ] {
+        settings
+            .update_settings(
+                store_id,
+                UpdateStoreSettingsRequest {
+                    currency_code: None,
+                    tax_rate: Some(0.05),
+                    flat_shipping_fee: None,
+                    free_shipping_threshold: None,
+                    ships_to: None,
+                    tax_provider: Some(tax_provider),
+                    payment_provider: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let carts = cart_service(&pool);
+    for store_id in [external.id, builtin.id] {
+        let product = common::create_product(&pool, store_id, "SKU-TAXED", 40.0, 5).await;
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let provider: Arc<dyn TaxProvider> = Arc::new(FixedRateTaxProvider);
+    let tax_rates = TaxRates::builtin().with_external(provider.clone(), Duration::from_secs(60));
+    let summary = order_service(&pool)
+        .with_tax_rates(Arc::new(tax_rates))
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+
+    let order_for = |store_id| {
+        summary
+            .orders
+            .iter()
+            .find(|order| order.store_id == store_id)
+            .unwrap()
+    };
+    let external_order = order_for(external.id);
+    let builtin_order = order_for(builtin.id);
+    assert_eq!(external_order.tax, Decimal::new(330, 2));
+    assert_eq!(builtin_order.tax, Decimal::new(200, 2));
+
+    let transactions = TaxRepository::new(pool.clone());
+    assert!(transactions
+        .find_for_order(builtin_order.id)
+        .await
+        .unwrap()
+        .is_none());
+    let queued = transactions
+        .find_for_order(external_order.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(queued.status, TaxTransactionStatus::Pending);
+
+    let run = TaxService::new(transactions.clone(), provider)
+        .commit_due(10)
+        .await
+        .unwrap();
+    assert_eq!(run.committed, 1);
+    let committed = transactions
+        .find_for_order(external_order.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(committed.status, TaxTransactionStatus::Committed);
+    assert_eq!(
+        committed.external_id,
+        Some(format!("txn-{}", external_order.order_number))
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn checkout_preview_matches_checkout_without_placing_orders(pool: PgPool) {
+    let owner = common::insert_user(&pool, "preview-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "preview-shopper@markethub.dev").await;
+    let taxed = common::create_store(&pool, owner.id, "preview-taxed", false).await;
+    let credited = common::create_store(&pool, owner.id, "preview-credited", false).await;
+
+    StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    )
+    .update_settings(
+        taxed.id,
+        UpdateStoreSettingsRequest {
+            currency_code: None,
+            tax_rate: Some(0.1),
+            flat_shipping_fee: Some(5.0),
+            free_shipping_threshold: None,
+            ships_to: None,
+            tax_provider: None,
+            payment_provider: None,
+        },
+    )
+    .await
+    .unwrap();
+    CreditService::new(
+        CreditRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    )
+    .issue_credit(
+        owner.id,
+        credited.id,
+        IssueCreditRequest {
+            user_id: shopper.id,
+            amount: 10.0,
+            kind: CreditEntryKind::Goodwill,
+            order_id: None,
+            note: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let carts = cart_service(&pool);
+    for (store_id, price) in [(taxed.id, 20.0), (credited.id, 25.0)] {
+        let product = common::create_product(&pool, store_id, "SKU-PREVIEW", price, 5).await;
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity: 2,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    let request = || CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: false,
+        custom_fields: Default::default(),
+        shipping_options: Default::default(),
+    };
+    let orders = order_service(&pool);
+    let preview = orders.preview(shopper.id, request()).await.unwrap();
+    // Previewing twice changes nothing: no orders, credit or stock consumed.
+    let again = orders.preview(shopper.id, request()).await.unwrap();
+    assert_eq!(again.total_amount, preview.total_amount);
+    assert!(orders
+        .list_orders(shopper.id, 10, 0)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let taxed_preview = preview
+        .stores
+        .iter()
+        .find(|store| store.store_id == taxed.id)
+        .unwrap();
+    assert_eq!(taxed_preview.tax, Decimal::new(400, 2));
+    assert_eq!(taxed_preview.shipping_cost, Decimal::new(500, 2));
+    assert_eq!(taxed_preview.items.len(), 1);
+    assert_eq!(preview.credit_applied, Decimal::new(1000, 2));
+
+    let summary = orders.checkout(shopper.id, request()).await.unwrap();
+    assert_eq!(summary.order_group.total_amount, preview.total_amount);
+    for store in &preview.stores {
+        let order = summary
+            .orders
+            .iter()
+            .find(|order| order.store_id == store.store_id)
+            .unwrap();
+        assert_eq!(order.subtotal, store.subtotal);
+        assert_eq!(order.tax, store.tax);
+        assert_eq!(order.shipping_cost, store.shipping_cost);
+        assert_eq!(order.total_amount, store.total_amount);
+    }
+
+    let err = orders.preview(shopper.id, request()).await.unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn guest_carts_merge_into_the_account_at_sign_in(pool: PgPool) {
+    let owner = common::insert_user(&pool, "guest-cart-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "guest-cart-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "guest-cart-store", false).await;
+    let shared = common::create_product(&pool, store.id, "SKU-GUEST-A", 10.0, 5).await;
+    let guest_only = common::create_product(&pool, store.id, "SKU-GUEST-B", 12.0, 5).await;
+
+    let carts = cart_service(&pool);
+    let guest = CartOwner::Guest(Uuid::new_v4());
+    let add = |owner: CartOwner, product_id, quantity| {
+        carts.add_item(
+            owner,
+            AddCartItemRequest {
+                product_id,
+                quantity,
+            },
+        )
+    };
+
+    let line = add(guest, shared.id, 2).await.unwrap();
+    assert_eq!(line.user_id, None);
+    add(guest, guest_only.id, 1).await.unwrap();
+    // Guests hold no stock, but cannot add more than is available.
+    let err = add(guest, guest_only.id, 5).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    let items = carts.list_items(guest).await.unwrap();
+    assert_eq!(items.len(), 2);
+    assert!(items.iter().all(|item| item.reserved_until.is_none()));
+    assert!(carts.list_items(shopper.id).await.unwrap().is_empty());
+
+    add(shopper.id.into(), shared.id, 1).await.unwrap();
+    let merged = carts
+        .merge_guest(guest.guest_session_id().unwrap(), shopper.id)
+        .await
+        .unwrap();
+    assert_eq!(merged.len(), 2);
+
+    assert!(carts.list_items(guest).await.unwrap().is_empty());
+    let items = carts.list_items(shopper.id).await.unwrap();
+    let quantity_of = |product_id| {
+        items
+            .iter()
+            .find(|item| item.product_id == product_id)
+            .map(|item| (item.quantity, item.reserved_until.is_some()))
+    };
+    assert_eq!(quantity_of(shared.id), Some((3, true)));
+    assert_eq!(quantity_of(guest_only.id), Some((1, true)));
+
+    let stale = CartOwner::Guest(Uuid::new_v4());
+    add(stale, shared.id, 1).await.unwrap();
+    let carts_repo = CartRepository::new(pool.clone());
+    let fresh = carts_repo
+        .purge_stale_guest_items(chrono::Duration::days(30), 100)
+        .await
+        .unwrap();
+    assert_eq!(fresh, 0);
+    let purged = carts_repo
+        .purge_stale_guest_items(chrono::Duration::zero(), 100)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    assert_eq!(carts.list_items(shopper.id).await.unwrap().len(), 2);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn saved_for_later_lines_skip_checkout_and_release_stock(pool: PgPool) {
+    let owner = common::insert_user(&pool, "saved-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "saved-shopper@markethub.dev").await;
+    let rival = common::insert_user(&pool, "saved-rival@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "saved-store", false).await;
+    let saved = common::create_product(&pool, store.id, "SKU-SAVED-A", 30.0, 3).await;
+    let bought = common::create_product(&pool, store.id, "SKU-SAVED-B", 10.0, 5).await;
+
+    let carts = cart_service(&pool);
+    let add = |user_id: Uuid, product_id, quantity| {
+        carts.add_item(
+            user_id,
+            AddCartItemRequest {
+                product_id,
+                quantity,
+            },
+        )
+    };
+    add(shopper.id, saved.id, 2).await.unwrap();
+    add(shopper.id, bought.id, 1).await.unwrap();
+
+    let line = carts.save_for_later(shopper.id, saved.id).await.unwrap();
+    assert!(line.saved_for_later);
+    let in_cart = carts.list_items(shopper.id).await.unwrap();
+    assert_eq!(in_cart.len(), 1);
+    assert_eq!(in_cart[0].product_id, bought.id);
+    let saved_lines = carts.list_saved(shopper.id).await.unwrap();
+    assert_eq!(saved_lines.len(), 1);
+    assert!(saved_lines[0].saved_for_later);
+    assert!(saved_lines[0].reserved_until.is_none());
+
+    // Saving released the hold, so another buyer can take all the stock.
+    add(rival.id, saved.id, 3).await.unwrap();
+
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(summary.orders.len(), 1);
+    assert_eq!(summary.orders[0].subtotal, Decimal::new(10, 0));
+    assert!(carts.list_items(shopper.id).await.unwrap().is_empty());
+    assert_eq!(carts.list_saved(shopper.id).await.unwrap().len(), 1);
+
+    // Restoring needs the stock back; the line stays saved until then.
+    let err = carts.restore_saved(shopper.id, saved.id).await.unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    assert_eq!(carts.list_saved(shopper.id).await.unwrap().len(), 1);
+
+    carts.clear(rival.id).await.unwrap();
+    let line = carts.restore_saved(shopper.id, saved.id).await.unwrap();
+    assert!(!line.saved_for_later);
+    let in_cart = carts.list_items(shopper.id).await.unwrap();
+    assert_eq!(in_cart.len(), 1);
+    assert!(in_cart[0].reserved_until.is_some());
+    assert!(carts.list_saved(shopper.id).await.unwrap().is_empty());
+
+    let err = carts
+        .save_for_later(shopper.id, bought.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn idle_carts_expire_into_abandoned_cart_records(pool: PgPool) {
+    let owner = common::insert_user(&pool, "idle-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "idle-shopper@markethub.dev").await;
+    let first = common::create_store(&pool, owner.id, "idle-first", false).await;
+    let second = common::create_store(&pool, owner.id, "idle-second", false).await;
+    let mug = common::create_product(&pool, first.id, "SKU-IDLE-MUG", 8.0, 10).await;
+    let tea = common::create_product(&pool, first.id, "SKU-IDLE-TEA", 4.5, 10).await;
+    let lamp = common::create_product(&pool, second.id, "SKU-IDLE-LAMP", 40.0, 10).await;
+
+    let carts = cart_service(&pool);
+    let add = |owner: CartOwner, product_id, quantity| {
+        carts.add_item(
+            owner,
+            AddCartItemRequest {
+                product_id,
+                quantity,
+            },
+        )
+    };
+    add(shopper.id.into(), mug.id, 2).await.unwrap();
+    add(shopper.id.into(), tea.id, 1).await.unwrap();
+    add(shopper.id.into(), lamp.id, 1).await.unwrap();
+    carts.save_for_later(shopper.id, lamp.id).await.unwrap();
+    let guest = CartOwner::Guest(Uuid::new_v4());
+    add(guest, mug.id, 1).await.unwrap();
+
+    let repo = CartRepository::new(pool.clone());
+    let recent = repo
+        .expire_idle_carts(chrono::Duration::days(7), 100)
+        .await
+        .unwrap();
+    assert!(recent.is_empty());
+
+    let abandoned = repo
+        .expire_idle_carts(chrono::Duration::zero(), 100)
+        .await
+        .unwrap();
+    assert_eq!(abandoned.len(), 1);
+    assert_eq!(abandoned[0].user_id, shopper.id);
+    assert_eq!(abandoned[0].store_id, first.id);
+    assert_eq!(abandoned[0].item_count, 2);
+    assert_eq!(abandoned[0].cart_value, Decimal::new(205, 1));
+
+    assert!(carts.list_items(shopper.id).await.unwrap().is_empty());
+    assert_eq!(carts.list_saved(shopper.id).await.unwrap().len(), 1);
+    assert_eq!(carts.list_items(guest).await.unwrap().len(), 1);
+    let reservations: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM stock_reservations WHERE user_id = $1")
+            .bind(shopper.id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(reservations, 0);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn cart_listing_warns_about_price_and_availability_changes(pool: PgPool) {
+    let owner = common::insert_user(&pool, "warn-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "warn-shopper@markethub.dev").await;
+    let rival = common::insert_user(&pool, "warn-rival@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "warn-store", false).await;
+    let repriced = common::create_product(&pool, store.id, "SKU-WARN-PRICE", 20.0, 10).await;
+    let withdrawn = common::create_product(&pool, store.id, "SKU-WARN-GONE", 5.0, 10).await;
+    let scarce = common::create_product(&pool, store.id, "SKU-WARN-LOW", 7.0, 4).await;
+
+    let carts = cart_service(&pool);
+    let add = |user_id: Uuid, product_id, quantity| {
+        carts.add_item(
+            user_id,
+            AddCartItemRequest {
+                product_id,
+                quantity,
+            },
+        )
+    };
+    add(shopper.id, repriced.id, 1).await.unwrap();
+    add(shopper.id, withdrawn.id, 1).await.unwrap();
+    add(shopper.id, scarce.id, 3).await.unwrap();
+    let items = carts.list_items(shopper.id).await.unwrap();
+    assert!(items.iter().all(|item| item.warnings.is_empty()));
+
+    sqlx::query("UPDATE products SET price = 24.00 WHERE id = $1")
+        .bind(repriced.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE products SET is_active = false WHERE id = $1")
+        .bind(withdrawn.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    // The shopper's hold lapses and another buyer takes most of the stock.
+    sqlx::query("UPDATE stock_reservations SET expires_at = NOW() WHERE user_id = $1")
+        .bind(shopper.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    add(rival.id, scarce.id, 2).await.unwrap();
+
+    let items = carts.list_items(shopper.id).await.unwrap();
+    let warnings_for = |product_id| {
+        items
+            .iter()
+            .find(|item| item.product_id == product_id)
+            .map(|item| item.warnings.clone())
+            .unwrap()
+    };
+    assert_eq!(
+        warnings_for(repriced.id),
+        vec![CartLineWarning::PriceChanged {
+            added_price: Decimal::new(20, 0),
+            current_price: Decimal::new(24, 0),
+        }]
+    );
+    assert_eq!(
+        warnings_for(withdrawn.id),
+        vec![CartLineWarning::Unavailable]
+    );
+    assert_eq!(
+        warnings_for(scarce.id),
+        vec![CartLineWarning::InsufficientStock {
+            requested: 3,
+            available: 2,
+        }]
+    );
+    let json = serde_json::to_value(&items).unwrap();
+    assert!(json
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|item| item["warnings"][0]["code"] == "price_changed"));
+
+    // Adding again means the shopper has seen the new price.
+    add(shopper.id, repriced.id, 1).await.unwrap();
+    let items = carts.list_items(shopper.id).await.unwrap();
+    let line = items
+        .iter()
+        .find(|item| item.product_id == repriced.id)
+        .unwrap();
+    assert_eq!(line.added_price, Decimal::new(24, 0));
+    assert!(line.warnings.is_empty());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn coupons_discount_checkout_until_used_up(pool: PgPool) {
+    let owner = common::insert_user(&pool, "coupon-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "coupon-shopper@markethub.dev").await;
+    let latecomer = common::insert_user(&pool, "coupon-late@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "coupon-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-COUPON", 40.0, 10).await;
+
+    let coupons = CouponService::new(
+        CouponRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+    );
+    let coupon = coupons
+        .create_coupon(
+            store.id,
+            CreateCouponRequest {
+                code: "Save10".into(),
+                kind: CouponKind::Percent,
+                value: 10.0,
+                min_order_value: Some(50.0),
+                usage_limit: Some(1),
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(coupon.code, "SAVE10");
+    let apply = |user_id| {
+        coupons.apply_coupon(
+            user_id,
+            ApplyCouponRequest {
+                code: "save10".into(),
+                store_id: None,
+            },
+        )
+    };
+
+    let carts = cart_service(&pool);
+    for (user_id, quantity) in [(shopper.id, 1), (latecomer.id, 2)] {
+        carts
+            .add_item(
+                user_id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    // One unit is below the coupon's minimum order value.
+    let err = apply(shopper.id).await.unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+    carts
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    let applied = apply(shopper.id).await.unwrap();
+    assert_eq!(applied.discount, Decimal::new(8, 0));
+    apply(latecomer.id).await.unwrap();
+
+    let request = || CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: false,
+        custom_fields: Default::default(),
+        shipping_options: Default::default(),
+    };
+    let orders = order_service(&pool);
+    let preview = orders.preview(shopper.id, request()).await.unwrap();
+    let store_preview = &preview.stores[0];
+    assert_eq!(store_preview.discount, Decimal::new(8, 0));
+    assert_eq!(store_preview.coupon_code.as_deref(), Some("SAVE10"));
+    assert_eq!(
+        store_preview.tax,
+        (Decimal::new(72, 0) * store_preview.tax_rate).round_dp(2)
+    );
+
+    let summary = orders.checkout(shopper.id, request()).await.unwrap();
+    let order = &summary.orders[0];
+    assert_eq!(order.discount, Decimal::new(8, 0));
+    assert_eq!(
+        order.total_amount,
+        order.subtotal + order.tax + order.shipping_cost - order.discount
+    );
+    let (times_used,): (i32,) = sqlx::query_as("SELECT times_used FROM coupons WHERE id = $1")
+        .bind(coupon.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(times_used, 1);
+
+    // The single use is gone: the other cart's coupon silently stops
+    // applying and cannot be re-applied.
+    let preview = orders.preview(latecomer.id, request()).await.unwrap();
+    assert!(preview.discount.is_zero());
+    assert!(preview.stores[0].coupon_code.is_none());
+    let err = apply(latecomer.id).await.unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn display_currency_converts_prices_and_is_recorded_on_orders(pool: PgPool) {
+    let owner = common::insert_user(&pool, "fx-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "fx-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "fx-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-FX-1", 20.0, 5).await;
+
+    StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    )
+    .update_settings(
+        store.id,
+        UpdateStoreSettingsRequest {
+            currency_code: Some("EUR".into()),
+            tax_rate: Some(0.0),
+            flat_shipping_fee: Some(0.0),
+            free_shipping_threshold: None,
+            ships_to: None,
+            tax_provider: None,
+            payment_provider: None,
+        },
+    )
+    .await
+    .unwrap();
+
+    let rates = Arc::new(ExchangeRates::new(Arc::new(
+        StaticRates::parse("EUR", "USD=1.1").unwrap(),
+    )));
+    let currency = CurrencyService::new(StoreSettingsRepository::new(pool.clone()), rates.clone());
+
+    let mut products = vec![product.clone()];
+    currency
+        .present_products(&mut products, "USD")
+        .await
+        .unwrap();
+    let display = products[0].display_price.as_ref().unwrap();
+    assert_eq!(display.currency, "USD");
+    assert_eq!(display.amount, Decimal::new(2200, 2));
+    let err = currency
+        .present_products(&mut products, "JPY")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 2,
+            },
+        )
+        .await
+        .unwrap();
+    let request = || CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: false,
+        custom_fields: Default::default(),
+        shipping_options: Default::default(),
+    };
+    let orders = order_service(&pool).with_exchange_rates(rates);
+
+    let mut preview = orders.preview(shopper.id, request()).await.unwrap();
+    assert_eq!(preview.currency, "EUR");
+    currency.present_preview(&mut preview, "USD").await.unwrap();
+    let totals = preview.display.unwrap();
+    assert_eq!(totals.subtotal, Decimal::new(4400, 2));
+    assert_eq!(totals.total_amount, Decimal::new(4400, 2));
+
+    let summary = orders
+        .checkout_with_currency(shopper.id, request(), Some("USD"))
+        .await
+        .unwrap();
+    let order = &summary.orders[0];
+    assert_eq!(order.currency, "EUR");
+    assert_eq!(order.total_amount, Decimal::new(4000, 2));
+    assert_eq!(order.presentment_currency.as_deref(), Some("USD"));
+    assert_eq!(order.exchange_rate, Some(Decimal::new(11, 1)));
+}
+
+/// Stands in for a carrier rating API: one ground service at 7.00.
+struct FixedCarrier;
+
+#[async_trait]
+impl CarrierRateProvider for FixedCarrier {
+    async fn quote(&self, parcel: &ShippingParcel<'_>) -> markethub::Result<Vec<CarrierQuote>> {
+        assert_eq!(parcel.weight_grams, 1200);
+        Ok(vec![CarrierQuote {
+            carrier: "ACME".into(),
+            service: "ground".into(),
+            name: "Ground".into(),
+            amount: Decimal::new(700, 2),
+            estimated_days: Some(4),
+        }])
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn shipping_rules_price_checkout_options(pool: PgPool) {
+    let owner = common::insert_user(&pool, "shipping-rules-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "shipping-rules-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "shipping-rules-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-PARCEL", 20.0, 10).await;
+    query("UPDATE products SET weight_grams = 600 WHERE id = $1")
+        .bind(product.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let shipping = ShippingService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    let rule = |name: &str, kind, fee, tiers: Vec<(i64, i64)>| CreateShippingRuleRequest {
+        name: name.into(),
+        kind,
+        fee,
+        tiers: tiers
+            .into_iter()
+            .map(|(min, fee)| ShippingTier {
+                min: Decimal::from(min),
+                fee: Decimal::new(fee, 2),
+            })
+            .collect(),
+        free_shipping_threshold: None,
+    };
+    shipping
+        .add_rule(
+            store.id,
+            rule("Express", ShippingRuleKind::Flat, Some(12.0), vec![]),
+        )
+        .await
+        .unwrap();
+    let economy = shipping
+        .add_rule(
+            store.id,
+            rule(
+                "Economy",
+                ShippingRuleKind::WeightTiers,
+                None,
+                vec![(0, 400), (1000, 900)],
+            ),
+        )
+        .await
+        .unwrap();
+    shipping
+        .add_rule(
+            store.id,
+            rule("ACME", ShippingRuleKind::Carrier, None, vec![]),
+        )
+        .await
+        .unwrap();
+    let err = shipping
+        .add_rule(
+            store.id,
+            rule("Bulky", ShippingRuleKind::PriceTiers, None, vec![(50, 100)]),
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Validation(_)));
+
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 2,
+            },
+        )
+        .await
+        .unwrap();
+
+    let orders = order_service(&pool).with_shipping_rates(Arc::new(
+        ShippingRates::builtin().with_carrier(Arc::new(FixedCarrier)),
+    ));
+    let request = |choice: Option<String>| CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: false,
+        custom_fields: Default::default(),
+        shipping_options: choice.map(|code| (store.id, code)).into_iter().collect(),
+    };
+
+    let preview = orders.preview(shopper.id, request(None)).await.unwrap();
+    let offered: Vec<(&str, Decimal)> = preview.stores[0]
+        .shipping_options
+        .iter()
+        .map(|option| (option.name.as_str(), option.cost))
+        .collect();
+    assert_eq!(
+        offered,
+        [
+            ("ACME (Ground)", Decimal::new(700, 2)),
+            ("Economy", Decimal::new(900, 2)),
+            ("Express", Decimal::new(1200, 2)),
+        ]
+    );
+    assert_eq!(
+        preview.shipping_cost,
+        Decimal::new(700, 2),
+        "cheapest by default"
+    );
+
+    let err = orders
+        .checkout(shopper.id, request(Some("overnight".into())))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+
+    let summary = orders
+        .checkout(shopper.id, request(Some(economy.id.to_string())))
+        .await
+        .unwrap();
+    let order = &summary.orders[0];
+    assert_eq!(order.shipping_cost, Decimal::new(900, 2));
+    assert_eq!(order.shipping_method.as_deref(), Some("Economy"));
+    assert_eq!(
+        order.total_amount,
+        order.subtotal + order.tax + order.shipping_cost
+    );
+}
+
+/// Stands in for a redirect-based provider: buyers approve at a URL and
+/// webhooks carry the reference in the body.
+struct FakeRedirectProvider;
+
+#[async_trait]
+impl PaymentProvider for FakeRedirectProvider {
+    async fn create_intent(
+        &self,
+        request: &PaymentRequest<'_>,
+    ) -> markethub::Result<PaymentIntent> {
+        Ok(PaymentIntent {
+            reference: format!("fake-{}", request.order_number),
+            status: PaymentAttemptStatus::Pending,
+            approval_url: Some(format!("https://pay.example/{}", request.order_number)),
+        })
+    }
+
+    async fn capture(&self, _reference: &str) -> markethub::Result<PaymentAttemptStatus> {
+        Ok(PaymentAttemptStatus::Captured)
+    }
+
+    async fn refund(
+        &self,
+        _reference: &str,
+        _amount: Decimal,
+        _currency: &str,
+    ) -> markethub::Result<()> {
+        Ok(())
+    }
+
+    async fn parse_webhook(
+        &self,
+        _headers: &HeaderMap,
+        body: &[u8],
+    ) -> markethub::Result<Option<PaymentEvent>> {
+        Ok(Some(PaymentEvent::Approved {
+            reference: String::from_utf8(body.to_vec()).unwrap(),
+        }))
+    }
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn payments_follow_each_stores_provider(pool: PgPool) {
+    let owner = common::insert_user(&pool, "payments-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "payments-shopper@markethub.dev").await;
+    let redirect = common::create_store(&pool, owner.id, "payments-redirect", false).await;
+    let manual = common::create_store(&pool, owner.id, "payments-manual", false).await;
+    StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    )
+    .update_settings(
+        redirect.id,
+        UpdateStoreSettingsRequest {
+            currency_code: None,
+            tax_rate: None,
+            flat_shipping_fee: None,
+            free_shipping_threshold: None,
+            ships_to: None,
+            tax_provider: None,
+            payment_provider: Some(PaymentProviderKind::PayPal),
+        },
+    )
+    .await
+    .unwrap();
+
+    let carts = cart_service(&pool);
+    for store_id in [redirect.id, manual.id] {
+        let sku = format!("SKU-PAY-{}", store_id.simple());
+        let product = common::create_product(&pool, store_id, &sku, 15.0, 5).await;
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id: product.id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let group_id = summary.order_group.id;
+    let order_in = |store_id: Uuid| {
+        summary
+            .orders
+            .iter()
+            .find(|order| order.store_id == store_id)
+            .unwrap()
+            .id
+    };
+
+    // Without the provider configured the attempt fails, but the call does not.
+    let payments = payment_service(&pool, PaymentProviders::manual_only())
+        .start(group_id)
+        .await
+        .unwrap();
+    let failed = payments
+        .iter()
+        .find(|payment| payment.store_id == redirect.id)
+        .unwrap();
+    assert_eq!(failed.status, PaymentAttemptStatus::Failed);
+    assert!(failed.failure_reason.is_some());
+
+    let service = payment_service(
+        &pool,
+        PaymentProviders::manual_only()
+            .with_provider(PaymentProviderKind::PayPal, Arc::new(FakeRedirectProvider)),
+    );
+    let payments = service.start(group_id).await.unwrap();
+    assert_eq!(payments.len(), 2);
+    let redirected = payments
+        .iter()
+        .find(|payment| payment.store_id == redirect.id)
+        .unwrap();
+    assert_eq!(redirected.provider, PaymentProviderKind::PayPal);
+    assert_eq!(redirected.status, PaymentAttemptStatus::Pending);
+    assert!(redirected.approval_url.is_some());
+    let offline = payments
+        .iter()
+        .find(|payment| payment.store_id == manual.id)
+        .unwrap();
+    assert_eq!(offline.provider, PaymentProviderKind::Manual);
+    assert!(offline.approval_url.is_none());
+
+    // Starting again reuses the open attempts.
+    let again = service.start(group_id).await.unwrap();
+    assert_eq!(
+        again.iter().map(|payment| payment.id).collect::<Vec<_>>(),
+        payments
+            .iter()
+            .map(|payment| payment.id)
+            .collect::<Vec<_>>()
+    );
+
+    let reference = redirected.reference.clone().unwrap();
+    service
+        .handle_webhook(
+            PaymentProviderKind::PayPal,
+            &HeaderMap::new(),
+            reference.as_bytes(),
+        )
+        .await
+        .unwrap();
+    let orders = OrderRepository::new(pool.clone());
+    let group = orders.find_group(group_id).await.unwrap().unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Pending);
+
+    let err = service
+        .capture_order(redirect.id, order_in(manual.id))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+    let captured = service
+        .capture_order(manual.id, order_in(manual.id))
+        .await
+        .unwrap();
+    assert_eq!(captured.status, PaymentAttemptStatus::Captured);
+    let group = orders.find_group(group_id).await.unwrap().unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Paid);
+
+    for store_id in [redirect.id, manual.id] {
+        let refunded = service
+            .refund_order(store_id, order_in(store_id))
+            .await
+            .unwrap();
+        assert_eq!(refunded.status, PaymentAttemptStatus::Refunded);
+    }
+    let err = service
+        .refund_order(manual.id, order_in(manual.id))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    let group = orders.find_group(group_id).await.unwrap().unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Refunded);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn order_groups_can_be_paid_again_until_paid(pool: PgPool) {
+    let owner = common::insert_user(&pool, "pay-again-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "pay-again-shopper@markethub.dev").await;
+    let other = common::insert_user(&pool, "pay-again-other@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "pay-again-store", false).await;
+    StoreSettingsService::new(
+        StoreSettingsRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    )
+    .update_settings(
+        store.id,
+        UpdateStoreSettingsRequest {
+            currency_code: None,
+            tax_rate: None,
+            flat_shipping_fee: None,
+            free_shipping_threshold: None,
+            ships_to: None,
+            tax_provider: None,
+            payment_provider: Some(PaymentProviderKind::PayPal),
+        },
+    )
+    .await
+    .unwrap();
+    let product = common::create_product(&pool, store.id, "SKU-PAY-AGAIN", 12.0, 5).await;
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    let group_id = summary.order_group.id;
+    let order_id = summary.orders[0].id;
+    let orders = OrderRepository::new(pool.clone());
+
+    // PayPal is not configured, so the store's own provider fails the group.
+    let service = payment_service(&pool, PaymentProviders::manual_only());
+    let payments = service
+        .pay(shopper.id, group_id, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(payments[0].status, PaymentAttemptStatus::Failed);
+    let group = orders.find_group(group_id).await.unwrap().unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Failed);
+
+    let err = service
+        .pay(
+            shopper.id,
+            group_id,
+            PayOrderGroupRequest {
+                method: Some(PaymentProviderKind::PayPal),
+            },
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::BadRequest(_)));
+    let err = service
+        .pay(other.id, group_id, Default::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+
+    let retry = PayOrderGroupRequest {
+        method: Some(PaymentProviderKind::Manual),
+    };
+    let payments = service
+        .pay(shopper.id, group_id, retry.clone())
+        .await
+        .unwrap();
+    assert_eq!(payments[0].provider, PaymentProviderKind::Manual);
+    assert_eq!(payments[0].status, PaymentAttemptStatus::Pending);
+    let group = orders.find_group(group_id).await.unwrap().unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Pending);
+
+    // Switching back to the store's provider replaces the open attempt.
+    let service = payment_service(
+        &pool,
+        PaymentProviders::manual_only()
+            .with_provider(PaymentProviderKind::PayPal, Arc::new(FakeRedirectProvider)),
+    );
+    let switched = service
+        .pay(shopper.id, group_id, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(switched[0].provider, PaymentProviderKind::PayPal);
+    assert_ne!(switched[0].id, payments[0].id);
+    let replaced: (PaymentAttemptStatus,) =
+        sqlx::query_as("SELECT status FROM payments WHERE id = $1")
+            .bind(payments[0].id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(replaced.0, PaymentAttemptStatus::Failed);
+
+    let payments = service.pay(shopper.id, group_id, retry).await.unwrap();
+    service.capture_order(store.id, order_id).await.unwrap();
+    let group = orders.find_group(group_id).await.unwrap().unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Paid);
+    assert_eq!(payments[0].provider, PaymentProviderKind::Manual);
+
+    let err = service
+        .pay(shopper.id, group_id, Default::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn unpaid_order_groups_expire_and_restore_stock(pool: PgPool) {
+    let owner = common::insert_user(&pool, "unpaid-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "unpaid-shopper@markethub.dev").await;
+    let first = common::create_store(&pool, owner.id, "unpaid-first", false).await;
+    let second = common::create_store(&pool, owner.id, "unpaid-second", false).await;
+    let kept = common::create_product(&pool, first.id, "SKU-UNPAID-1", 10.0, 5).await;
+    let restored = common::create_product(&pool, second.id, "SKU-UNPAID-2", 8.0, 5).await;
+
+    let checkout = |product_id: Uuid| {
+        let pool = pool.clone();
+        async move {
+            cart_service(&pool)
+                .add_item(
+                    shopper.id,
+                    AddCartItemRequest {
+                        product_id,
+                        quantity: 2,
+                    },
+                )
+                .await
+                .unwrap();
+            order_service(&pool)
+                .checkout(
+                    shopper.id,
+                    CheckoutRequest {
+                        shipping_address: common::shipping_address(),
+                        confirm_duplicate: false,
+                        custom_fields: Default::default(),
+                        shipping_options: Default::default(),
+                    },
+                )
+                .await
+                .unwrap()
+        }
+    };
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: kept.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    let stale = checkout(restored.id).await;
+    let recent = checkout(restored.id).await;
+    query("UPDATE order_groups SET created_at = NOW() - INTERVAL '4 days' WHERE id = $1")
+        .bind(stale.order_group.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // The first store was paid; only the second store's order is cancelled.
+    let payments = payment_service(&pool, PaymentProviders::manual_only());
+    payments.start(stale.order_group.id).await.unwrap();
+    let paid = stale
+        .orders
+        .iter()
+        .find(|order| order.store_id == first.id)
+        .unwrap();
+    payments.capture_order(first.id, paid.id).await.unwrap();
+
+    let service = UnpaidOrderService::new(
+        OrderRepository::new(pool.clone()),
+        PaymentRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+    );
+    let expired = service
+        .expire_unpaid_groups(chrono::Duration::days(3), 10)
+        .await
+        .unwrap();
+    assert_eq!(expired.len(), 1);
+    assert_eq!(expired[0].order_group_id, stale.order_group.id);
+    assert_eq!(expired[0].orders_cancelled, 1);
+    assert_eq!(expired[0].units_restored, 2);
+
+    let orders = OrderRepository::new(pool.clone());
+    for order in orders.list_for_group(stale.order_group.id).await.unwrap() {
+        let expected = if order.store_id == first.id {
+            OrderStatus::Pending
+        } else {
+            OrderStatus::Cancelled
+        };
+        assert_eq!(order.status, expected);
+    }
+    let group = orders
+        .find_group(stale.order_group.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(group.payment_status, PaymentStatus::Paid);
+    assert_eq!(group.total_amount, paid.total_amount);
+    let recent_group = orders
+        .find_group(recent.order_group.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(recent_group.payment_status, PaymentStatus::Pending);
+
+    let products = ProductRepository::new(pool.clone());
+    let stock = products
+        .find_by_id(restored.id)
+        .await
+        .unwrap()
+        .unwrap()
+        .stock_quantity;
+    assert_eq!(stock, 3);
+    let open: (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM payments WHERE order_group_id = $1 AND status = 'Pending'",
+    )
+    .bind(stale.order_group.id)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(open.0, 0);
+
+    let notifications = NotificationRepository::new(pool.clone())
+        .list_for_user(shopper.id, 10, 0)
+        .await
+        .unwrap();
+    assert!(notifications
+        .iter()
+        .any(|notification| notification.kind == ORDER_CANCELLED));
+
+    let err = payments
+        .pay(shopper.id, stale.order_group.id, Default::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::Conflict(_)));
+    assert!(service
+        .expire_group(stale.order_group.id)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn expired_groups_give_back_store_credit_and_coupon_use(pool: PgPool) {
+    let owner = common::insert_user(&pool, "lapsed-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "lapsed-shopper@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "lapsed-store", false).await;
+    let product = common::create_product(&pool, store.id, "SKU-LAPSED", 30.0, 5).await;
+
+    let credits = CreditService::new(
+        CreditRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+    );
+    credits
+        .issue_credit(
+            owner.id,
+            store.id,
+            IssueCreditRequest {
+                user_id: shopper.id,
+                amount: 10.0,
+                kind: CreditEntryKind::Goodwill,
+                order_id: None,
+                note: None,
+            },
+        )
+        .await
+        .unwrap();
+    let coupons = CouponService::new(
+        CouponRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        CartRepository::new(pool.clone()),
+    );
+    let coupon = coupons
+        .create_coupon(
+            store.id,
+            CreateCouponRequest {
+                code: "LAPSED".into(),
+                kind: CouponKind::Fixed,
+                value: 5.0,
+                min_order_value: None,
+                usage_limit: Some(1),
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+    cart_service(&pool)
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: product.id,
+                quantity: 1,
+            },
+        )
+        .await
+        .unwrap();
+    coupons
+        .apply_coupon(
+            shopper.id,
+            ApplyCouponRequest {
+                code: "lapsed".into(),
+                store_id: None,
+            },
+        )
+        .await
+        .unwrap();
+    let summary = order_service(&pool)
+        .checkout(
+            shopper.id,
+            CheckoutRequest {
+                shipping_address: common::shipping_address(),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(summary.orders[0].credit_applied, Decimal::new(10, 0));
+    assert_eq!(summary.order_group.payment_status, PaymentStatus::Pending);
+
+    let expired = UnpaidOrderService::new(
+        OrderRepository::new(pool.clone()),
+        PaymentRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+    )
+    .expire_group(summary.order_group.id)
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(expired.orders_cancelled, 1);
+
+    let balances = credits.balances_for_user(shopper.id).await.unwrap();
+    assert_eq!(balances[0].balance, Decimal::new(10, 0));
+    let (times_used,): (i32,) = sqlx::query_as("SELECT times_used FROM coupons WHERE id = $1")
+        .bind(coupon.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(times_used, 0);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn order_groups_list_with_nested_store_orders(pool: PgPool) {
+    let owner = common::insert_user(&pool, "groups-owner@markethub.dev").await;
+    let shopper = common::insert_user(&pool, "groups-shopper@markethub.dev").await;
+    let other = common::insert_user(&pool, "groups-other@markethub.dev").await;
+    let first = common::create_store(&pool, owner.id, "groups-first", false).await;
+    let second = common::create_store(&pool, owner.id, "groups-second", false).await;
+    let first_product = common::create_product(&pool, first.id, "SKU-GROUP-1", 10.0, 5).await;
+    let second_product = common::create_product(&pool, second.id, "SKU-GROUP-2", 6.0, 5).await;
+
+    let carts = cart_service(&pool);
+    let orders = order_service(&pool);
+    let request = || CheckoutRequest {
+        shipping_address: common::shipping_address(),
+        confirm_duplicate: true,
+        custom_fields: Default::default(),
+        shipping_options: Default::default(),
+    };
+    for product_id in [first_product.id, second_product.id] {
+        carts
+            .add_item(
+                shopper.id,
+                AddCartItemRequest {
+                    product_id,
+                    quantity: 1,
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let older = orders.checkout(shopper.id, request()).await.unwrap();
+    carts
+        .add_item(
+            shopper.id,
+            AddCartItemRequest {
+                product_id: first_product.id,
+                quantity: 2,
+            },
+        )
+        .await
+        .unwrap();
+    let newer = orders.checkout(shopper.id, request()).await.unwrap();
+
+    let groups = orders.list_order_groups(shopper.id, 10, 0).await.unwrap();
+    assert_eq!(
+        groups
+            .iter()
+            .map(|entry| entry.group.id)
+            .collect::<Vec<_>>(),
+        vec![newer.order_group.id, older.order_group.id]
+    );
+    assert_eq!(groups[0].orders.len(), 1);
+    assert_eq!(groups[1].orders.len(), 2);
+    assert!(groups[1]
+        .orders
+        .iter()
+        .all(|entry| entry.order_group_id == older.order_group.id));
+    assert_eq!(groups[1].group.payment_status, PaymentStatus::Pending);
+    let page = orders.list_order_groups(shopper.id, 1, 1).await.unwrap();
+    assert_eq!(page[0].group.id, older.order_group.id);
+    assert!(orders
+        .list_order_groups(other.id, 10, 0)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let detail = orders
+        .order_group_detail(shopper.id, older.order_group.id)
+        .await
+        .unwrap();
+    assert_eq!(detail.group.group_number, older.order_group.group_number);
+    assert_eq!(detail.orders.len(), 2);
+    assert!(detail
+        .orders
+        .iter()
+        .all(|order| order.items.len() == 1 && order.shipments.is_empty()));
+
+    let err = orders
+        .order_group_detail(other.id, older.order_group.id)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::NotFound(_)));
+}
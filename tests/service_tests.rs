@@ -204,7 +204,11 @@ async fn create_test_setup(pool: &PgPool, email_prefix: &str) -> (Uuid, Uuid, Uu
 
     let cart_repo = CartRepository::new(pool.clone());
     let product_repo = ProductRepository::new(pool.clone());
-    let cart_service = CartService::new(cart_repo, product_repo);
+    let cart_service = CartService::new(
+        cart_repo,
+        product_repo,
+        StoreSettingsRepository::new(pool.clone()),
+    );
 
     (user_id, store.id, product.id, cart_service)
 }
@@ -290,8 +294,9 @@ async fn cart_clear() {
         .await
         .unwrap();
 
-    let result = service.clear(user_id).await;
-    assert!(result.is_ok());
+    let removed = service.clear(user_id).await.unwrap();
+    assert_eq!(removed, 1);
+    assert_eq!(service.clear(user_id).await.unwrap(), 0);
 
     let items = service.list_items(user_id).await.unwrap();
     assert_eq!(items.len(), 0);
@@ -300,7 +305,11 @@ async fn cart_clear() {
 // ========== ORDER SERVICE TESTS ==========
 
 use markethub::{
-    models::order::CheckoutRequest, repositories::OrderRepository,
+    models::order::CheckoutRequest,
+    repositories::{
+        CouponRepository, CreditRepository, FulfillmentRepository, NotificationRepository,
+        OrderRepository, StoreSettingsRepository,
+    },
     services::order_service::OrderService,
 };
 use serde_json::json;
@@ -326,7 +335,16 @@ async fn order_checkout_success() {
     let order_repo = OrderRepository::new(pool.clone());
     let product_repo = ProductRepository::new(pool.clone());
     let cart_repo = CartRepository::new(pool.clone());
-    let order_service = OrderService::new(order_repo, product_repo, cart_repo);
+    let order_service = OrderService::new(
+        order_repo,
+        product_repo,
+        cart_repo,
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    );
 
     // Checkout
     let result = order_service
@@ -334,6 +352,9 @@ async fn order_checkout_success() {
             user_id,
             CheckoutRequest {
                 shipping_address: json!({"street": "123 Main St", "city": "Test City"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await;
@@ -356,13 +377,25 @@ async fn order_checkout_empty_cart() {
     let order_repo = OrderRepository::new(pool.clone());
     let product_repo = ProductRepository::new(pool.clone());
     let cart_repo = CartRepository::new(pool.clone());
-    let order_service = OrderService::new(order_repo, product_repo, cart_repo);
+    let order_service = OrderService::new(
+        order_repo,
+        product_repo,
+        cart_repo,
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    );
 
     let result = order_service
         .checkout(
             user_id,
             CheckoutRequest {
                 shipping_address: json!({"street": "123 Main St"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await;
@@ -418,7 +451,11 @@ async fn order_checkout_multi_store() {
     // Add to cart
     let cart_repo = CartRepository::new(pool.clone());
     let product_repo = ProductRepository::new(pool.clone());
-    let cart_service = CartService::new(cart_repo.clone(), product_repo.clone());
+    let cart_service = CartService::new(
+        cart_repo.clone(),
+        product_repo.clone(),
+        StoreSettingsRepository::new(pool.clone()),
+    );
 
     cart_service
         .add_item(
@@ -443,13 +480,25 @@ async fn order_checkout_multi_store() {
 
     // Checkout
     let order_repo = OrderRepository::new(pool.clone());
-    let order_service = OrderService::new(order_repo, product_repo, cart_repo);
+    let order_service = OrderService::new(
+        order_repo,
+        product_repo,
+        cart_repo,
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    );
 
     let result = order_service
         .checkout(
             user_id,
             CheckoutRequest {
                 shipping_address: json!({"street": "456 Oak Ave"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await;
@@ -489,13 +538,25 @@ async fn order_checkout_decrements_stock() {
     // Checkout
     let order_repo = OrderRepository::new(pool.clone());
     let cart_repo = CartRepository::new(pool.clone());
-    let order_service = OrderService::new(order_repo, product_repo.clone(), cart_repo);
+    let order_service = OrderService::new(
+        order_repo,
+        product_repo.clone(),
+        cart_repo,
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    );
 
     order_service
         .checkout(
             user_id,
             CheckoutRequest {
                 shipping_address: json!({"street": "789 Elm St"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await
@@ -514,7 +575,16 @@ async fn order_list_orders() {
     let order_repo = OrderRepository::new(pool.clone());
     let product_repo = ProductRepository::new(pool.clone());
     let cart_repo = CartRepository::new(pool.clone());
-    let order_service = OrderService::new(order_repo, product_repo, cart_repo);
+    let order_service = OrderService::new(
+        order_repo,
+        product_repo,
+        cart_repo,
+        FulfillmentRepository::new(pool.clone()),
+        StoreSettingsRepository::new(pool.clone()),
+        CreditRepository::new(pool.clone()),
+        NotificationRepository::new(pool.clone()),
+        CouponRepository::new(pool.clone()),
+    );
 
     // Create first order
     cart_service
@@ -532,6 +602,9 @@ async fn order_list_orders() {
             user_id,
             CheckoutRequest {
                 shipping_address: json!({"street": "A St"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await
@@ -553,6 +626,9 @@ async fn order_list_orders() {
             user_id,
             CheckoutRequest {
                 shipping_address: json!({"street": "B St"}),
+                confirm_duplicate: false,
+                custom_fields: Default::default(),
+                shipping_options: Default::default(),
             },
         )
         .await
@@ -639,7 +715,14 @@ async fn permission_admin_has_most_permissions() {
 
     let member_repo = MemberRepository::new(pool.clone());
     member_repo
-        .add_member(store.id, admin_id, MemberRole::Admin, &[], Some(owner_id))
+        .add_member(
+            store.id,
+            admin_id,
+            MemberRole::Admin,
+            &[],
+            None,
+            Some(owner_id),
+        )
         .await
         .unwrap();
 
@@ -854,7 +937,8 @@ async fn store_list_members() {
 // ========== PRODUCT SERVICE TESTS ==========
 
 use markethub::{
-    models::product::{CreateProductRequest, UpdateProductRequest},
+    models::product::{CreateProductRequest, ProductType, UpdateProductRequest},
+    repositories::ProductImageRepository,
     services::product_service::ProductService,
 };
 
@@ -866,7 +950,8 @@ async fn product_create_success() {
 
     let product_repo = ProductRepository::new(pool.clone());
     let store_repo = StoreRepository::new(pool.clone());
-    let service = ProductService::new(product_repo, store_repo);
+    let image_repo = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(product_repo, store_repo, image_repo);
 
     let sku = format!("SKU-{}", Uuid::new_v4());
     let result = service
@@ -878,6 +963,9 @@ async fn product_create_success() {
             price: 99.99,
             stock_quantity: 50,
             category: Some("Electronics".to_string()),
+            thumbnail_url: None,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
         })
         .await;
 
@@ -895,7 +983,8 @@ async fn product_create_invalid_store() {
 
     let product_repo = ProductRepository::new(pool.clone());
     let store_repo = StoreRepository::new(pool.clone());
-    let service = ProductService::new(product_repo, store_repo);
+    let image_repo = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(product_repo, store_repo, image_repo);
 
     let result = service
         .create_product(CreateProductRequest {
@@ -906,6 +995,9 @@ async fn product_create_invalid_store() {
             price: 10.0,
             stock_quantity: 5,
             category: None,
+            thumbnail_url: None,
+            product_type: ProductType::Physical,
+            weight_grams: 0,
         })
         .await;
 
@@ -916,6 +1008,52 @@ async fn product_create_invalid_store() {
     ));
 }
 
+#[tokio::test]
+async fn product_sku_is_unique_per_store() {
+    let pool = setup_test_db().await;
+    let owner_id = create_test_user(&pool, &format!("prod-sku-{}@test.com", Uuid::new_v4())).await;
+    let store = create_store(&pool, owner_id, &format!("store-{}", Uuid::new_v4()), false).await;
+    let other = create_store(&pool, owner_id, &format!("store-{}", Uuid::new_v4()), false).await;
+
+    let sku = format!("SKU-{}", Uuid::new_v4());
+    let existing = create_product(&pool, store.id, &sku, 10.0, 5).await;
+
+    let product_repo = ProductRepository::new(pool.clone());
+    let service = ProductService::new(
+        product_repo.clone(),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+    let request = |store_id| CreateProductRequest {
+        store_id,
+        sku: sku.clone(),
+        name: "Duplicate".to_string(),
+        description: None,
+        price: 10.0,
+        stock_quantity: 5,
+        category: None,
+        thumbnail_url: None,
+        product_type: ProductType::Physical,
+        weight_grams: 0,
+    };
+
+    let err = service.create_product(request(store.id)).await.unwrap_err();
+    assert!(
+        matches!(err, markethub::error::AppError::Conflict(ref msg) if msg == "SKU already exists in this store")
+    );
+    let in_other_store = service.create_product(request(other.id)).await.unwrap();
+
+    let found = product_repo.find_by_sku(store.id, &sku).await.unwrap();
+    assert_eq!(found.map(|p| p.id), Some(existing.id));
+    let found = product_repo.find_by_sku(other.id, &sku).await.unwrap();
+    assert_eq!(found.map(|p| p.id), Some(in_other_store.id));
+    assert!(product_repo
+        .find_by_sku(store.id, "SKU-MISSING")
+        .await
+        .unwrap()
+        .is_none());
+}
+
 #[tokio::test]
 async fn product_list_by_store() {
     let pool = setup_test_db().await;
@@ -935,7 +1073,8 @@ async fn product_list_by_store() {
 
     let product_repo = ProductRepository::new(pool.clone());
     let store_repo = StoreRepository::new(pool.clone());
-    let service = ProductService::new(product_repo, store_repo);
+    let image_repo = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(product_repo, store_repo, image_repo);
 
     let products = service.list_by_store(store.id, 10, 0).await.unwrap();
     assert!(products.len() >= 2);
@@ -957,18 +1096,23 @@ async fn product_update_success() {
 
     let product_repo = ProductRepository::new(pool.clone());
     let store_repo = StoreRepository::new(pool.clone());
-    let service = ProductService::new(product_repo, store_repo);
+    let image_repo = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(product_repo, store_repo, image_repo);
 
     let result = service
         .update_product(
             product.id,
+            owner_id,
             UpdateProductRequest {
                 name: Some("Updated Name".to_string()),
                 description: Some("Updated description".to_string()),
                 price: Some(75.0),
                 stock_quantity: Some(20),
                 category: None,
+                thumbnail_url: None,
                 is_active: Some(true),
+                product_type: None,
+                weight_grams: None,
             },
         )
         .await;
@@ -995,7 +1139,8 @@ async fn product_get_product() {
 
     let product_repo = ProductRepository::new(pool.clone());
     let store_repo = StoreRepository::new(pool.clone());
-    let service = ProductService::new(product_repo, store_repo);
+    let image_repo = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(product_repo, store_repo, image_repo);
 
     let result = service.get_product(product.id).await;
     assert!(result.is_ok());
@@ -1009,7 +1154,8 @@ async fn product_get_nonexistent() {
 
     let product_repo = ProductRepository::new(pool.clone());
     let store_repo = StoreRepository::new(pool.clone());
-    let service = ProductService::new(product_repo, store_repo);
+    let image_repo = ProductImageRepository::new(pool.clone());
+    let service = ProductService::new(product_repo, store_repo, image_repo);
 
     let result = service.get_product(fake_id).await;
     assert!(result.is_err());
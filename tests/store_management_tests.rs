@@ -2,9 +2,19 @@ mod common;
 
 use markethub::{
     error::AppError,
-    models::store::{CreateStoreRequest, MemberRole},
-    repositories::{MemberRepository, StoreRepository},
-    services::store_service::StoreService,
+    models::{
+        order::PaymentStatus,
+        permission::Permission,
+        product::{CreateProductRequest, ProductType},
+        store::{
+            ChangePlanRequest, CreateStoreRequest, MemberRole, StoreAnalyticsParams, StorePlan,
+        },
+    },
+    repositories::{
+        AnalyticsRepository, MemberRepository, ProductImageRepository, ProductRepository,
+        StoreRepository,
+    },
+    services::{store_service::StoreService, AnalyticsService, ProductService},
 };
 use sqlx::PgPool;
 
@@ -84,3 +94,210 @@ async fn list_public_returns_only_public_active_stores(pool: PgPool) {
     assert!(slugs.contains(&"public-store"));
     assert!(!slugs.contains(&"private-store"));
 }
+
+#[sqlx::test(migrations = "./migrations")]
+async fn list_user_stores_includes_owned_and_member_stores(pool: PgPool) {
+    let owner = common::insert_user(&pool, "my-stores-owner@markethub.dev").await;
+    let staff = common::insert_user(&pool, "my-stores-staff@markethub.dev").await;
+    let owned = common::create_store(&pool, owner.id, "alpha-owned", false).await;
+    let joined = common::create_store(&pool, staff.id, "beta-joined", true).await;
+    let departed = common::create_store(&pool, staff.id, "gamma-departed", false).await;
+
+    let members = MemberRepository::new(pool.clone());
+    members
+        .add_member(
+            joined.id,
+            owner.id,
+            MemberRole::Staff,
+            &[Permission::ViewOrders],
+            None,
+            Some(staff.id),
+        )
+        .await
+        .unwrap();
+    members
+        .add_member(
+            departed.id,
+            owner.id,
+            MemberRole::Manager,
+            &[Permission::ViewOrders],
+            None,
+            Some(staff.id),
+        )
+        .await
+        .unwrap();
+    sqlx::query("UPDATE store_members SET is_active = false WHERE store_id = $1 AND user_id = $2")
+        .bind(departed.id)
+        .bind(owner.id)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let stores = store_service(&pool)
+        .list_user_stores(owner.id)
+        .await
+        .unwrap();
+    let summary: Vec<_> = stores
+        .iter()
+        .map(|entry| (entry.store.id, entry.role))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (owned.id, MemberRole::Owner),
+            (joined.id, MemberRole::Staff)
+        ]
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn memberships_list_effective_permissions_per_store(pool: PgPool) {
+    let owner = common::insert_user(&pool, "perm-list-owner@markethub.dev").await;
+    let member = common::insert_user(&pool, "perm-list-member@markethub.dev").await;
+    let owned = common::create_store(&pool, member.id, "perm-list-owned", false).await;
+    let managed = common::create_store(&pool, owner.id, "perm-list-managed", false).await;
+    let custom = common::create_store(&pool, owner.id, "perm-list-custom", true).await;
+
+    let members = MemberRepository::new(pool.clone());
+    members
+        .add_member(
+            managed.id,
+            member.id,
+            MemberRole::Admin,
+            &[],
+            None,
+            Some(owner.id),
+        )
+        .await
+        .unwrap();
+    members
+        .add_member(
+            custom.id,
+            member.id,
+            MemberRole::Custom,
+            &[Permission::ViewOrders, Permission::ProcessOrders],
+            None,
+            Some(owner.id),
+        )
+        .await
+        .unwrap();
+
+    let memberships = store_service(&pool)
+        .list_memberships(member.id)
+        .await
+        .unwrap();
+    let by_store = |store_id| {
+        memberships
+            .iter()
+            .find(|entry| entry.store.store.id == store_id)
+            .unwrap()
+    };
+    assert_eq!(memberships.len(), 3);
+    assert_eq!(by_store(owned.id).store.role, MemberRole::Owner);
+    assert_eq!(
+        by_store(owned.id).permissions.len(),
+        Permission::all().len()
+    );
+    assert_eq!(
+        by_store(managed.id).permissions.len(),
+        Permission::all().len()
+    );
+    assert_eq!(
+        by_store(custom.id)
+            .permissions
+            .iter()
+            .copied()
+            .collect::<Vec<_>>(),
+        vec![Permission::ViewOrders, Permission::ProcessOrders]
+    );
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn store_plan_limits_products_and_analytics_window(pool: PgPool) {
+    let owner = common::insert_user(&pool, "plan-owner@markethub.dev").await;
+    let store = common::create_store(&pool, owner.id, "plan-store", false).await;
+    assert_eq!(store.plan, StorePlan::Free);
+
+    let products = ProductService::new(
+        ProductRepository::new(pool.clone()),
+        StoreRepository::new(pool.clone()),
+        ProductImageRepository::new(pool.clone()),
+    );
+    let limit = StorePlan::Free.limits().max_products;
+    let mut csv =
+        String::from("sku,name,description,price,stock_quantity,category,thumbnail_url\n");
+    for index in 0..limit {
+        csv.push_str(&format!("SKU-PLAN-{},Plan product,,1,1,,\n", index));
+    }
+    let report = products
+        .import_csv(store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    assert_eq!(report.created as i64, limit);
+
+    let extra = CreateProductRequest {
+        store_id: store.id,
+        sku: "SKU-PLAN-EXTRA".into(),
+        name: "One too many".into(),
+        description: None,
+        price: 1.0,
+        stock_quantity: 1,
+        category: None,
+        thumbnail_url: None,
+        product_type: ProductType::Physical,
+        weight_grams: 0,
+    };
+    let err = products.create_product(extra.clone()).await.unwrap_err();
+    assert!(matches!(err, AppError::QuotaExceeded(_)));
+
+    // Re-importing existing SKUs only updates and stays within quota.
+    let report = products
+        .import_csv(store.id, csv.as_bytes(), false)
+        .await
+        .unwrap();
+    assert_eq!(report.updated as i64, limit);
+
+    let analytics = AnalyticsService::new(
+        StoreRepository::new(pool.clone()),
+        AnalyticsRepository::new(pool.clone()),
+    );
+    let err = analytics
+        .store_analytics(
+            store.id,
+            StoreAnalyticsParams {
+                timeframe_days: 120,
+                top_products: 5,
+                payment_status: PaymentStatus::Paid,
+                compare: None,
+            },
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert!(matches!(err, AppError::QuotaExceeded(_)));
+
+    let upgraded = store_service(&pool)
+        .change_plan(
+            store.id,
+            ChangePlanRequest {
+                plan: StorePlan::Pro,
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(upgraded.plan, StorePlan::Pro);
+    products.create_product(extra).await.unwrap();
+    analytics
+        .store_analytics(
+            store.id,
+            StoreAnalyticsParams {
+                timeframe_days: 120,
+                top_products: 5,
+                payment_status: PaymentStatus::Paid,
+                compare: None,
+            },
+            false,
+        )
+        .await
+        .unwrap();
+}
@@ -0,0 +1,435 @@
+mod common;
+
+use std::sync::{
+    atomic::{AtomicU16, Ordering},
+    Arc, Mutex,
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    routing::post,
+    Router,
+};
+use common::shipping_address;
+use markethub::{
+    jobs::{JobRegistry, JobWorker},
+    metrics::Metrics,
+    models::store::StorePlan,
+    outbox::OutboxRelay,
+    repositories::{StoreRepository, WebhookRepository},
+    services::WebhookService,
+    testing::{build_state, data, TestApp},
+    webhooks::{
+        sign, WebhookSender, WebhookSubscriber, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+    },
+};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+const SECRET: &str = "whsec_integration_secret";
+/// A public address, so registering needs no DNS lookup.
+const PUBLIC_URL: &str = "https://93.184.215.14/hooks";
+
+/// An endpoint answering every delivery with `status`.
+#[derive(Clone)]
+struct Receiver {
+    status: Arc<AtomicU16>,
+    received: Arc<Mutex<Vec<(HeaderMap, Bytes)>>>,
+}
+
+impl Receiver {
+    async fn spawn() -> (Self, String) {
+        let receiver = Receiver {
+            status: Arc::new(AtomicU16::new(200)),
+            received: Arc::default(),
+        };
+        let app = Router::new()
+            .route(
+                "/hooks",
+                post(
+                    |State(receiver): State<Receiver>, headers: HeaderMap, body: Bytes| async move {
+                        receiver.received.lock().unwrap().push((headers, body));
+                        StatusCode::from_u16(receiver.status.load(Ordering::SeqCst)).unwrap()
+                    },
+                ),
+            )
+            .with_state(receiver.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hooks", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (receiver, url)
+    }
+}
+
+async fn make_due(pool: &PgPool) {
+    sqlx::query("UPDATE webhook_deliveries SET next_attempt_at = NOW() WHERE status = 'Pending'")
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn stores_manage_webhooks_and_only_owners_see_the_secret(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("hooks-owner@example.com").await;
+    let store = app.create_store(&owner, "hooks-store").await;
+    let outsider = app.register("hooks-outsider@example.com").await;
+    let path = format!("/api/v1/stores/{}/webhooks", store.id);
+
+    let response = app
+        .post(&path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "url": "http://example.com/hooks", "events": ["order.paid"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = app
+        .post(&path)
+        .bearer_auth(&owner.token)
+        .json(&json!({ "url": PUBLIC_URL, "events": ["order.shipped"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    for url in [
+        "https://169.254.169.254/latest/meta-data",
+        "https://127.0.0.1:8443/hooks",
+        "https://[fd00::1]/hooks",
+    ] {
+        let response = app
+            .post(&path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "url": url, "events": ["order.paid"] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400, "{url}");
+    }
+
+    let created: Value = data(
+        app.post(&path)
+            .bearer_auth(&owner.token)
+            .json(&json!({
+                "url": PUBLIC_URL,
+                "events": ["order.paid", "order.created", "order.paid"],
+            }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(created["secret"].as_str().unwrap().starts_with("whsec_"));
+    assert_eq!(created["events"], json!(["order.created", "order.paid"]));
+
+    let listed: Vec<Value> = data(
+        app.get(&path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], created["id"]);
+    assert!(listed[0].get("secret").is_none());
+
+    let response = app
+        .get(&path)
+        .bearer_auth(&outsider.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+
+    let webhook_path = format!("{}/{}", path, created["id"].as_str().unwrap());
+    let response = app
+        .delete(&webhook_path)
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = app
+        .get(&format!("{webhook_path}/deliveries"))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn events_are_delivered_signed_and_retried_until_accepted(pool: PgPool) {
+    let app = TestApp::spawn(pool.clone()).await;
+    let owner = app.register("deliver-owner@example.com").await;
+    let store = app.create_store(&owner, "deliver-store").await;
+    let product = app
+        .create_product(&owner, store.id, "HOOK-1", 10.0, 6)
+        .await;
+    let shopper = app.register("deliver-shopper@example.com").await;
+
+    let webhook: Value = data(
+        app.post(&format!("/api/v1/stores/{}/webhooks", store.id))
+            .bearer_auth(&owner.token)
+            .json(&json!({
+                "url": PUBLIC_URL,
+                "events": ["order.created", "order.paid", "product.low_stock"],
+                "secret": SECRET,
+            }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(webhook["secret"], SECRET);
+    let webhook_id: Uuid = webhook["id"].as_str().unwrap().parse().unwrap();
+    // Registered URLs must be https; point this one at a local endpoint.
+    let (receiver, url) = Receiver::spawn().await;
+    sqlx::query("UPDATE webhooks SET url = $2 WHERE id = $1")
+        .bind(webhook_id)
+        .bind(&url)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // Taking stock from 6 to 4 crosses the low stock threshold
+    app.post("/api/v1/cart/items")
+        .bearer_auth(&shopper.token)
+        .json(&json!({ "product_id": product.id, "quantity": 2 }))
+        .send()
+        .await
+        .unwrap();
+    let _: Value = data(
+        app.post("/api/v1/orders/checkout")
+            .bearer_auth(&shopper.token)
+            .json(&json!({ "shipping_address": shipping_address() }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let order_id: Uuid = sqlx::query_scalar("SELECT id FROM orders WHERE store_id = $1")
+        .bind(store.id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let response = app
+        .post(&format!(
+            "/api/v1/stores/{}/orders/{}/payment/capture",
+            store.id, order_id
+        ))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let webhooks = WebhookRepository::new(pool.clone());
+    let relay = OutboxRelay::new(pool.clone()).subscribe(
+        "webhooks",
+        Arc::new(WebhookSubscriber::new(webhooks.clone())),
+    );
+    let worker = JobWorker::new(
+        pool.clone(),
+        relay.register_jobs(JobRegistry::new()),
+        Arc::new(Metrics::new()),
+    );
+    assert_eq!(relay.relay_pending(100).await.unwrap(), 3);
+    assert_eq!(worker.run_due(100).await.unwrap().succeeded, 3);
+
+    let service = WebhookService::new(webhooks, StoreRepository::new(pool.clone()));
+    let sender = WebhookSender::allowing_private_addresses().unwrap();
+    receiver.status.store(503, Ordering::SeqCst);
+    let run = service.deliver_due(&sender, 10).await.unwrap();
+    assert_eq!((run.sent, run.retrying), (0, 3));
+    // Not due again until the backoff has passed
+    assert_eq!(
+        service.deliver_due(&sender, 10).await.unwrap(),
+        Default::default()
+    );
+
+    let log_path = format!(
+        "/api/v1/stores/{}/webhooks/{}/deliveries",
+        store.id, webhook_id
+    );
+    let log: Vec<Value> = data(
+        app.get(&log_path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(log.len(), 3);
+    assert!(log.iter().all(|delivery| delivery["status"] == "pending"
+        && delivery["attempts"] == 1
+        && delivery["response_status"] == 503));
+
+    receiver.status.store(200, Ordering::SeqCst);
+    make_due(&pool).await;
+    let run = service.deliver_due(&sender, 10).await.unwrap();
+    assert_eq!((run.sent, run.retrying), (3, 0));
+
+    let log: Vec<Value> = data(
+        app.get(&log_path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert!(log.iter().all(|delivery| delivery["status"] == "sent"
+        && delivery["attempts"] == 2
+        && delivery["response_status"] == 200));
+
+    let received = receiver.received.lock().unwrap();
+    assert_eq!(received.len(), 6);
+    let mut types = Vec::new();
+    for (headers, body) in received.iter().skip(3) {
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        let timestamp: i64 = signature
+            .strip_prefix("t=")
+            .and_then(|rest| rest.split(',').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(sign(SECRET, timestamp, body), signature);
+
+        let payload: Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(headers[EVENT_HEADER], payload["type"].as_str().unwrap());
+        assert_eq!(headers[DELIVERY_HEADER], payload["id"].as_str().unwrap());
+        if payload["type"] != "product.low_stock" {
+            assert_eq!(payload["data"]["order_id"], json!(order_id));
+        }
+        types.push(payload["type"].as_str().unwrap().to_string());
+    }
+    types.sort();
+    assert_eq!(types, ["order.created", "order.paid", "product.low_stock"]);
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn free_stores_register_a_limited_number_of_webhooks(pool: PgPool) {
+    let app = TestApp::spawn(pool).await;
+    let owner = app.register("hooks-quota-owner@example.com").await;
+    let store = app.create_store(&owner, "hooks-quota-store").await;
+    let path = format!("/api/v1/stores/{}/webhooks", store.id);
+    let register = || {
+        app.post(&path)
+            .bearer_auth(&owner.token)
+            .json(&json!({ "url": PUBLIC_URL, "events": ["order.paid"] }))
+            .send()
+    };
+
+    for _ in 0..StorePlan::Free.limits().max_webhooks {
+        assert_eq!(register().await.unwrap().status(), 200);
+    }
+    let response = register().await.unwrap();
+    assert_eq!(response.status(), 403);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["error"]["code"], "QUOTA_EXCEEDED");
+}
+
+#[sqlx::test(migrations = "./migrations")]
+async fn test_deliveries_report_how_the_endpoint_answered(pool: PgPool) {
+    let sender = WebhookSender::allowing_private_addresses().unwrap();
+    let app = TestApp::spawn_with(build_state(pool.clone()).with_webhook_sender(sender)).await;
+    let owner = app.register("hooks-ping-owner@example.com").await;
+    let store = app.create_store(&owner, "hooks-ping-store").await;
+    let outsider = app.register("hooks-ping-outsider@example.com").await;
+
+    let created: Value = data(
+        app.post(&format!("/api/v1/stores/{}/webhooks", store.id))
+            .bearer_auth(&owner.token)
+            .json(&json!({ "url": PUBLIC_URL, "events": ["order.paid"], "secret": SECRET }))
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    let webhook_id: Uuid = serde_json::from_value(created["id"].clone()).unwrap();
+    let (receiver, url) = Receiver::spawn().await;
+    sqlx::query("UPDATE webhooks SET url = $2 WHERE id = $1")
+        .bind(webhook_id)
+        .bind(&url)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let path = format!("/api/v1/stores/{}/webhooks/{}/test", store.id, webhook_id);
+
+    let ping: Value = data(
+        app.post(&path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(ping["status"], 200);
+    assert!(ping["latency_ms"].is_u64());
+    assert!(ping["error"].is_null());
+    {
+        let received = receiver.received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let (headers, body) = &received[0];
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+        let timestamp: i64 = signature
+            .strip_prefix("t=")
+            .and_then(|rest| rest.split(',').next())
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(sign(SECRET, timestamp, body), signature);
+        let payload: Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(headers[EVENT_HEADER], "webhook.ping");
+        assert_eq!(payload["type"], "webhook.ping");
+        assert_eq!(payload["data"]["webhook_id"], json!(webhook_id));
+    }
+
+    receiver.status.store(410, Ordering::SeqCst);
+    let ping: Value = data(
+        app.post(&path)
+            .bearer_auth(&owner.token)
+            .send()
+            .await
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(ping["status"], 410);
+
+    // Test deliveries stay out of the delivery log
+    let log: Vec<Value> = data(
+        app.get(&format!(
+            "/api/v1/stores/{}/webhooks/{}/deliveries",
+            store.id, webhook_id
+        ))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap(),
+    )
+    .await;
+    assert!(log.is_empty());
+
+    let response = app
+        .post(&path)
+        .bearer_auth(&outsider.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 403);
+    let response = app
+        .post(&format!(
+            "/api/v1/stores/{}/webhooks/{}/test",
+            store.id,
+            Uuid::new_v4()
+        ))
+        .bearer_auth(&owner.token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}